@@ -0,0 +1,628 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Incremental backups of a live database.
+//!
+//! A [`BackupEngine`] keeps numbered backups in one directory:
+//!
+//! ```text
+//! <backup_dir>/meta/<id>                    the files of backup <id>, with checksums
+//! <backup_dir>/private/<id>/                its MANIFEST, WALs and CURRENT
+//! <backup_dir>/shared_checksum/<file>       SSTables and blob files
+//! ```
+//!
+//! SSTables and blob files never change once written, so a backup stores
+//! each one as `<number>_<crc32c>_<size>.<ext>` and later backups that
+//! contain the same file reuse it.  A backup exists once its `meta` file
+//! does; files no backup references are deleted.
+//!
+//! See `utilities/backup/backup_engine.cc` in RocksDB.
+
+use crate::checkpoint::{copy_file, live_files, remove_dir_all, LiveFiles};
+use crate::coding::{crc32c, crc32c_extend};
+use crate::db::version_set::manifest_filename;
+use crate::env::FileSystem;
+use crate::{parse_db_filename, Db, Error};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const META_DIR: &str = "meta";
+const PRIVATE_DIR: &str = "private";
+const SHARED_DIR: &str = "shared_checksum";
+
+/// Summary of one backup, returned by [`BackupEngine::list_backups`].
+///
+/// See `include/rocksdb/utilities/backup_engine.h: BackupInfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupInfo {
+  pub backup_id: u32,
+  /// When the backup was created, in seconds since the Unix epoch.
+  pub timestamp: u64,
+  /// Sequence number of the last write the backup holds.
+  pub sequence: u64,
+  /// Total size of the backup's files, shared ones included.
+  pub size: u64,
+  pub number_files: usize,
+}
+
+/// A file of a backup, by its path relative to the backup directory.
+struct BackupFile {
+  path: String,
+  size: u64,
+  crc32c: u32,
+}
+
+impl BackupFile {
+  /// Name of the file in a database directory.
+  fn db_name(&self) -> String {
+    let name = self.path.rsplit('/').next().unwrap_or(&self.path);
+    match (name.split_once('_'), name.rsplit_once('.')) {
+      (Some((number, _)), Some((_, ext))) if self.path.starts_with(SHARED_DIR) => {
+        format!("{number}.{ext}")
+      }
+      _ => name.to_owned(),
+    }
+  }
+}
+
+/// Contents of a `meta/<id>` file.
+struct BackupMeta {
+  timestamp: u64,
+  sequence: u64,
+  files: Vec<BackupFile>,
+}
+
+impl BackupMeta {
+  /// ```text
+  /// timestamp <secs>
+  /// sequence <seq>
+  /// files <n>
+  /// <path> <size> <crc32c>     (n lines)
+  /// ```
+  fn encode(&self) -> String {
+    let mut out = format!(
+      "timestamp {}\nsequence {}\nfiles {}\n",
+      self.timestamp,
+      self.sequence,
+      self.files.len()
+    );
+    for file in &self.files {
+      out.push_str(&format!("{} {} {}\n", file.path, file.size, file.crc32c));
+    }
+    out
+  }
+
+  fn decode(content: &str) -> Option<BackupMeta> {
+    let mut lines = content.lines();
+    let mut header = |key: &str| -> Option<u64> {
+      lines
+        .next()?
+        .strip_prefix(key)?
+        .strip_prefix(' ')?
+        .parse()
+        .ok()
+    };
+    let timestamp = header("timestamp")?;
+    let sequence = header("sequence")?;
+    let count = header("files")?;
+    let files = lines
+      .map(|line| {
+        let mut fields = line.split(' ');
+        let file = BackupFile {
+          path: fields.next()?.to_owned(),
+          size: fields.next()?.parse().ok()?,
+          crc32c: fields.next()?.parse().ok()?,
+        };
+        fields.next().is_none().then_some(file)
+      })
+      .collect::<Option<Vec<_>>>()?;
+    (files.len() as u64 == count).then_some(BackupMeta {
+      timestamp,
+      sequence,
+      files,
+    })
+  }
+}
+
+/// Creates, lists, verifies and restores backups kept in one directory.
+///
+/// Only one `BackupEngine` may use a backup directory at a time.
+///
+/// See `include/rocksdb/utilities/backup_engine.h: BackupEngine`.
+pub struct BackupEngine {
+  dir: PathBuf,
+  fs: Arc<dyn FileSystem>,
+  backups: BTreeMap<u32, BackupMeta>,
+}
+
+impl BackupEngine {
+  /// Open the backups in `backup_dir` on `fs`, creating the directory if
+  /// needed.
+  ///
+  /// Deletes what an interrupted backup or deletion left behind.  A backup
+  /// whose `meta` file cannot be parsed is deleted as well.
+  pub fn open<P: AsRef<Path>>(backup_dir: P, fs: Arc<dyn FileSystem>) -> Result<Self, Error> {
+    let dir = backup_dir.as_ref().to_path_buf();
+    for sub in [META_DIR, PRIVATE_DIR, SHARED_DIR] {
+      fs.create_dir_all(&dir.join(sub))?;
+    }
+    let mut backups = BTreeMap::new();
+    for name in fs.children(&dir.join(META_DIR))? {
+      let Ok(id) = name.parse::<u32>() else {
+        continue;
+      };
+      let path = dir.join(META_DIR).join(&name);
+      match BackupMeta::decode(&fs.read_string_from_file(&path)?) {
+        Some(meta) => {
+          backups.insert(id, meta);
+        }
+        None => {
+          log::warn!("backup {id}: unreadable meta file; deleting the backup");
+          fs.remove_file(&path)?;
+        }
+      }
+    }
+    let engine = BackupEngine { dir, fs, backups };
+    engine.garbage_collect();
+    Ok(engine)
+  }
+
+  /// Back up `db` and return the new backup's id.
+  ///
+  /// With `flush_before_backup` the memtables are flushed first; otherwise
+  /// the live WALs are backed up along with the tables.  SSTables and blob
+  /// files already held by an earlier backup are not copied again.  The
+  /// backup's timestamp is read from `db`'s [`Options::clock`](crate::Options::clock).
+  ///
+  /// Returns [`Error::NotSupported`] for in-memory and read-only databases.
+  ///
+  /// See `BackupEngine::CreateNewBackup` in RocksDB.
+  pub fn create_new_backup(&mut self, db: &Db, flush_before_backup: bool) -> Result<u32, Error> {
+    let id = self.backups.keys().next_back().map_or(1, |id| id + 1);
+    let log_size_for_flush = if flush_before_backup { 0 } else { u64::MAX };
+    let (pause, live) = live_files(db, log_size_for_flush)?;
+    let timestamp = db.inner.options.clock.current_time();
+    let result = self.write_backup(id, &live, timestamp);
+    drop(pause);
+    match result {
+      Ok(meta) => {
+        log::info!("created backup {id} ({} files)", meta.files.len());
+        self.backups.insert(id, meta);
+        Ok(id)
+      }
+      Err(e) => {
+        self.garbage_collect();
+        Err(e)
+      }
+    }
+  }
+
+  /// Copy the files of `live` into backup `id`, created at `timestamp`.
+  fn write_backup(&self, id: u32, live: &LiveFiles, timestamp: u64) -> Result<BackupMeta, Error> {
+    let fs = &*self.fs;
+    let mut files = Vec::new();
+
+    for name in &live.immutable {
+      let src = live.dir.join(name);
+      let (size, crc) = checksum(&*live.fs, &src)?;
+      let (stem, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+      let path = format!("{SHARED_DIR}/{stem}_{crc}_{size}.{ext}");
+      let dst = self.dir.join(&path);
+      if fs.file_exists(&dst) && fs.file_size(&dst)? == size {
+        log::debug!("backup {id}: {name} is already backed up");
+      } else {
+        let tmp = self.dir.join(format!("{path}.tmp"));
+        let copied = copy_file(&*live.fs, &src, fs, &tmp, None)?;
+        if copied != (size, crc) {
+          return Err(Error::Corruption(format!(
+            "{name} changed while it was being backed up"
+          )));
+        }
+        fs.rename(&tmp, &dst)?;
+      }
+      files.push(BackupFile {
+        path,
+        size,
+        crc32c: crc,
+      });
+    }
+
+    let private = format!("{PRIVATE_DIR}/{id}");
+    let staging = self.dir.join(format!("{private}.tmp"));
+    fs.create_dir_all(&staging)?;
+    let manifest = manifest_filename(live.manifest_number);
+    let mut private_files = vec![(manifest, Some(live.manifest_size))];
+    private_files.extend(
+      live
+        .wals
+        .iter()
+        .map(|(name, size)| (name.clone(), Some(*size))),
+    );
+    for (name, size) in private_files {
+      let (size, crc) = copy_file(
+        &*live.fs,
+        &live.dir.join(&name),
+        fs,
+        &staging.join(&name),
+        size,
+      )?;
+      files.push(BackupFile {
+        path: format!("{private}/{name}"),
+        size,
+        crc32c: crc,
+      });
+    }
+    let current = format!("{}\n", manifest_filename(live.manifest_number));
+    fs.write_string_to_file(&staging.join("CURRENT"), &current)?;
+    files.push(BackupFile {
+      path: format!("{private}/CURRENT"),
+      size: current.len() as u64,
+      crc32c: crc32c(current.as_bytes()),
+    });
+    fs.sync_dir(&staging)?;
+    fs.rename(&staging, &self.dir.join(&private))?;
+    fs.sync_dir(&self.dir.join(SHARED_DIR))?;
+
+    // The backup exists once its meta file does.
+    let meta = BackupMeta {
+      timestamp,
+      sequence: live.last_sequence,
+      files,
+    };
+    let meta_dir = self.dir.join(META_DIR);
+    let tmp = meta_dir.join(format!("{id}.tmp"));
+    fs.write_string_to_file(&tmp, &meta.encode())?;
+    fs.rename(&tmp, &meta_dir.join(id.to_string()))?;
+    fs.sync_dir(&meta_dir)?;
+    Ok(meta)
+  }
+
+  /// The backups, oldest first.
+  ///
+  /// See `BackupEngine::GetBackupInfo` in RocksDB.
+  pub fn list_backups(&self) -> Vec<BackupInfo> {
+    self
+      .backups
+      .iter()
+      .map(|(&backup_id, meta)| BackupInfo {
+        backup_id,
+        timestamp: meta.timestamp,
+        sequence: meta.sequence,
+        size: meta.files.iter().map(|f| f.size).sum(),
+        number_files: meta.files.len(),
+      })
+      .collect()
+  }
+
+  /// Delete backup `id`, and the shared files no other backup references.
+  ///
+  /// See `BackupEngine::DeleteBackup` in RocksDB.
+  pub fn delete_backup(&mut self, id: u32) -> Result<(), Error> {
+    self.meta(id)?;
+    self
+      .fs
+      .remove_file(&self.dir.join(META_DIR).join(id.to_string()))?;
+    self.backups.remove(&id);
+    log::info!("deleted backup {id}");
+    self.garbage_collect();
+    Ok(())
+  }
+
+  /// Delete all but the `keep` newest backups.
+  ///
+  /// See `BackupEngine::PurgeOldBackups` in RocksDB.
+  pub fn purge_old_backups(&mut self, keep: usize) -> Result<(), Error> {
+    let excess = self.backups.len().saturating_sub(keep);
+    let old: Vec<u32> = self.backups.keys().take(excess).copied().collect();
+    for id in old {
+      self.delete_backup(id)?;
+    }
+    Ok(())
+  }
+
+  /// Check that every file of backup `id` is present with the size and
+  /// CRC32C recorded when it was backed up.
+  ///
+  /// Returns [`Error::Corruption`] naming the first file that is not.
+  ///
+  /// See `BackupEngine::VerifyBackup` in RocksDB.
+  pub fn verify_backup(&self, id: u32) -> Result<(), Error> {
+    for file in &self.meta(id)?.files {
+      let path = self.dir.join(&file.path);
+      if !self.fs.file_exists(&path) {
+        return Err(Error::Corruption(format!(
+          "backup {id}: {} is missing",
+          file.path
+        )));
+      }
+      check_file(id, file, checksum(&*self.fs, &path)?)?;
+    }
+    Ok(())
+  }
+
+  /// Replace the database in `db_dir`, on this engine's [`FileSystem`], with
+  /// backup `id`.  The database must not be open.
+  ///
+  /// Every file is checked against its recorded CRC32C as it is copied.
+  /// `CURRENT` is written last, so an interrupted restore leaves a directory
+  /// that does not open rather than a mix of two databases.
+  ///
+  /// See `BackupEngine::RestoreDBFromBackup` in RocksDB.
+  pub fn restore_db_from_backup<P: AsRef<Path>>(&self, id: u32, db_dir: P) -> Result<(), Error> {
+    let meta = self.meta(id)?;
+    let db_dir = db_dir.as_ref();
+    let fs = &*self.fs;
+    fs.create_dir_all(db_dir)?;
+    for name in fs.children(db_dir)? {
+      if parse_db_filename(&name).is_some() {
+        fs.remove_file(&db_dir.join(name))?;
+      }
+    }
+    let (current, rest): (Vec<_>, Vec<_>) = meta
+      .files
+      .iter()
+      .partition(|file| file.db_name() == "CURRENT");
+    for file in rest.into_iter().chain(current) {
+      let copied = copy_file(
+        fs,
+        &self.dir.join(&file.path),
+        fs,
+        &db_dir.join(file.db_name()),
+        None,
+      )?;
+      check_file(id, file, copied)?;
+    }
+    fs.sync_dir(db_dir)?;
+    log::info!("restored backup {id} to {}", db_dir.display());
+    Ok(())
+  }
+
+  fn meta(&self, id: u32) -> Result<&BackupMeta, Error> {
+    self
+      .backups
+      .get(&id)
+      .ok_or_else(|| Error::InvalidArgument(format!("backup {id} not found")))
+  }
+
+  /// Delete the files no backup references: those of deleted and
+  /// interrupted backups.  Best effort, like `delete_obsolete_files`.
+  ///
+  /// See `BackupEngine::GarbageCollect` in RocksDB.
+  fn garbage_collect(&self) {
+    let fs = &*self.fs;
+    let referenced: HashSet<&str> = self
+      .backups
+      .values()
+      .flat_map(|meta| meta.files.iter().map(|f| f.path.as_str()))
+      .collect();
+    for name in fs.children(&self.dir.join(META_DIR)).unwrap_or_default() {
+      if name.parse::<u32>().is_err() {
+        let _ = fs.remove_file(&self.dir.join(META_DIR).join(name));
+      }
+    }
+    for name in fs.children(&self.dir.join(PRIVATE_DIR)).unwrap_or_default() {
+      if !name.parse().is_ok_and(|id| self.backups.contains_key(&id)) {
+        log::debug!("deleting unreferenced backup files in private/{name}");
+        remove_dir_all(fs, &self.dir.join(PRIVATE_DIR).join(name));
+      }
+    }
+    for name in fs.children(&self.dir.join(SHARED_DIR)).unwrap_or_default() {
+      if !referenced.contains(format!("{SHARED_DIR}/{name}").as_str()) {
+        log::debug!("deleting unreferenced backup file {SHARED_DIR}/{name}");
+        let _ = fs.remove_file(&self.dir.join(SHARED_DIR).join(name));
+      }
+    }
+  }
+}
+
+/// Size and CRC32C of the file at `path`.
+fn checksum(fs: &dyn FileSystem, path: &Path) -> Result<(u64, u32), Error> {
+  let mut file = fs.open_sequential(path)?;
+  let (mut size, mut crc) = (0u64, 0u32);
+  let mut buf = vec![0u8; 64 << 10];
+  loop {
+    let n = file.read(&mut buf)?;
+    if n == 0 {
+      return Ok((size, crc));
+    }
+    crc = crc32c_extend(crc, &buf[..n]);
+    size += n as u64;
+  }
+}
+
+/// [`Error::Corruption`] unless `(size, crc)` is what backup `id` recorded
+/// for `file`.
+fn check_file(id: u32, file: &BackupFile, (size, crc): (u64, u32)) -> Result<(), Error> {
+  if size != file.size {
+    return Err(Error::Corruption(format!(
+      "backup {id}: {} is {size} bytes, expected {}",
+      file.path, file.size
+    )));
+  }
+  if crc != file.crc32c {
+    return Err(Error::Corruption(format!(
+      "backup {id}: {} has checksum {crc}, expected {}",
+      file.path, file.crc32c
+    )));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::env::MemFileSystem;
+  use crate::{FlushOptions, Options};
+
+  fn open_db(fs: &Arc<dyn FileSystem>) -> Db {
+    let opts = Options {
+      create_if_missing: true,
+      file_system: Arc::clone(fs),
+      ..Options::default()
+    };
+    Db::open(Path::new("/db"), opts).unwrap()
+  }
+
+  fn open_restored(fs: &Arc<dyn FileSystem>, dir: &str) -> Db {
+    let opts = Options {
+      file_system: Arc::clone(fs),
+      ..Options::default()
+    };
+    Db::open(Path::new(dir), opts).unwrap()
+  }
+
+  fn shared_files(fs: &dyn FileSystem) -> Vec<String> {
+    let mut names = fs.children(&Path::new("/backup").join(SHARED_DIR)).unwrap();
+    names.sort();
+    names
+  }
+
+  #[test]
+  fn backups_share_tables_and_restore_independently() {
+    let fs: Arc<dyn FileSystem> = Arc::new(MemFileSystem::new());
+    let db = open_db(&fs);
+    let mut engine = BackupEngine::open("/backup", Arc::clone(&fs)).unwrap();
+
+    db.put(b"a", b"1").unwrap();
+    assert_eq!(engine.create_new_backup(&db, true).unwrap(), 1);
+    assert_eq!(shared_files(&*fs).len(), 1);
+
+    db.put(b"b", b"2").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    db.put(b"c", b"3").unwrap();
+    // Not flushed: "c" is backed up through the WAL.
+    assert_eq!(engine.create_new_backup(&db, false).unwrap(), 2);
+    // The first table is shared by both backups.
+    assert_eq!(shared_files(&*fs).len(), 2);
+
+    let infos = engine.list_backups();
+    assert_eq!(
+      infos.iter().map(|i| i.backup_id).collect::<Vec<_>>(),
+      [1, 2]
+    );
+    assert!(infos[0].sequence < infos[1].sequence);
+    assert!(infos[1].number_files > infos[0].number_files);
+    drop(db);
+
+    engine.restore_db_from_backup(1, "/restore").unwrap();
+    let restored = open_restored(&fs, "/restore");
+    assert_eq!(restored.get(b"a").unwrap(), b"1");
+    assert!(matches!(restored.get(b"b"), Err(Error::NotFound)));
+    drop(restored);
+
+    // Restoring over an existing database replaces it.
+    engine.restore_db_from_backup(2, "/restore").unwrap();
+    let restored = open_restored(&fs, "/restore");
+    for (key, value) in [(b"a", b"1"), (b"b", b"2"), (b"c", b"3")] {
+      assert_eq!(restored.get(key).unwrap(), value);
+    }
+
+    // A reopened engine sees the same backups.
+    let engine = BackupEngine::open("/backup", Arc::clone(&fs)).unwrap();
+    assert_eq!(engine.list_backups(), infos);
+  }
+
+  #[test]
+  fn backup_timestamp_comes_from_the_database_clock() {
+    let fs: Arc<dyn FileSystem> = Arc::new(MemFileSystem::new());
+    let opts = Options {
+      create_if_missing: true,
+      file_system: Arc::clone(&fs),
+      clock: Arc::new(crate::env::ManualClock::new(1_234_567)),
+      ..Options::default()
+    };
+    let db = Db::open(Path::new("/db"), opts).unwrap();
+    db.put(b"a", b"1").unwrap();
+    let mut engine = BackupEngine::open("/backup", Arc::clone(&fs)).unwrap();
+    engine.create_new_backup(&db, true).unwrap();
+    assert_eq!(engine.list_backups()[0].timestamp, 1_234_567);
+  }
+
+  #[test]
+  fn purge_old_backups_deletes_unshared_files() {
+    let fs: Arc<dyn FileSystem> = Arc::new(MemFileSystem::new());
+    let db = open_db(&fs);
+    let mut engine = BackupEngine::open("/backup", Arc::clone(&fs)).unwrap();
+    for i in 0..3 {
+      db.put(format!("k{i}").as_bytes(), b"v").unwrap();
+      db.compact_range(None, None).unwrap();
+      engine.create_new_backup(&db, true).unwrap();
+    }
+    // Leftovers of an interrupted backup.
+    fs.write_string_to_file(Path::new("/backup/meta/4.tmp"), "")
+      .unwrap();
+    fs.write_string_to_file(Path::new("/backup/private/4.tmp/CURRENT"), "")
+      .unwrap();
+
+    engine.purge_old_backups(1).unwrap();
+    assert_eq!(
+      engine
+        .list_backups()
+        .iter()
+        .map(|i| i.backup_id)
+        .collect::<Vec<_>>(),
+      [3]
+    );
+    assert_eq!(fs.children(Path::new("/backup/meta")).unwrap(), ["3"]);
+    assert_eq!(fs.children(Path::new("/backup/private")).unwrap(), ["3"]);
+    // Only the tables of the kept backup remain.
+    let kept = shared_files(&*fs);
+    let live: Vec<_> = fs
+      .children(Path::new("/db"))
+      .unwrap()
+      .into_iter()
+      .filter(|n| n.ends_with(".ldb"))
+      .collect();
+    assert_eq!(kept.len(), live.len());
+    engine.verify_backup(3).unwrap();
+    assert!(matches!(
+      engine.verify_backup(1),
+      Err(Error::InvalidArgument(_))
+    ));
+  }
+
+  #[test]
+  fn verify_and_restore_detect_corrupted_files() {
+    let fs: Arc<dyn FileSystem> = Arc::new(MemFileSystem::new());
+    let db = open_db(&fs);
+    let mut engine = BackupEngine::open("/backup", Arc::clone(&fs)).unwrap();
+    db.put(b"k", b"v").unwrap();
+    let id = engine.create_new_backup(&db, true).unwrap();
+    engine.verify_backup(id).unwrap();
+
+    // Flip the table's contents without changing its size.
+    let table = Path::new("/backup")
+      .join(SHARED_DIR)
+      .join(&shared_files(&*fs)[0]);
+    let size = fs.file_size(&table).unwrap() as usize;
+    let mut w = fs.create_writable(&table).unwrap();
+    w.write(&vec![0u8; size]).unwrap();
+    drop(w);
+    assert!(matches!(
+      engine.verify_backup(id),
+      Err(Error::Corruption(_))
+    ));
+    assert!(matches!(
+      engine.restore_db_from_backup(id, "/restore"),
+      Err(Error::Corruption(_))
+    ));
+    // The restore stopped before writing CURRENT.
+    assert!(!fs.file_exists(Path::new("/restore/CURRENT")));
+
+    fs.remove_file(&table).unwrap();
+    assert!(matches!(
+      engine.verify_backup(id),
+      Err(Error::Corruption(_))
+    ));
+  }
+}
@@ -0,0 +1,407 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Openable point-in-time copies of a live database.
+//!
+//! [`Checkpoint::create`] builds, in a new directory, a database holding
+//! exactly what the source held at one moment.  SSTables and blob files are
+//! immutable, so they are hard-linked where the [`FileSystem`] supports it
+//! and copied otherwise; the MANIFEST and the live WAL are copied up to the
+//! size they had at that moment.  The checkpoint opens with [`Db::open`] and
+//! shares nothing mutable with the source.
+//!
+//! See `utilities/checkpoint/checkpoint_impl.cc` in RocksDB.
+
+use crate::coding::crc32c_extend;
+use crate::db::version_set::{manifest_filename, write_current_file};
+use crate::env::FileSystem;
+use crate::{parse_db_filename, Db, Error, FileDeletionPause, FileKind, FlushOptions};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Creates checkpoints of an open [`Db`].
+///
+/// See `include/rocksdb/utilities/checkpoint.h: Checkpoint`.
+pub struct Checkpoint;
+
+/// The files making up a database at one moment, as recorded by
+/// [`live_files`].  Shared with [`BackupEngine`](crate::BackupEngine).
+pub(crate) struct LiveFiles {
+  /// The database directory and the file system it lives on.
+  pub(crate) dir: PathBuf,
+  pub(crate) fs: Arc<dyn FileSystem>,
+  /// SSTables and blob files, which never change once written.
+  pub(crate) immutable: Vec<String>,
+  pub(crate) manifest_number: u64,
+  pub(crate) manifest_size: u64,
+  /// WALs still needed for recovery, with their sizes.
+  pub(crate) wals: Vec<(String, u64)>,
+  /// Sequence number of the last write the files hold.
+  pub(crate) last_sequence: u64,
+}
+
+impl Checkpoint {
+  /// Create a checkpoint of `db` in `checkpoint_dir`, which must not exist.
+  ///
+  /// The memtables are flushed first, so the checkpoint's WAL only holds
+  /// writes that raced with the checkpoint.  Equivalent to
+  /// [`create_with_log_size_for_flush`] with `0`.
+  ///
+  /// [`create_with_log_size_for_flush`]: Checkpoint::create_with_log_size_for_flush
+  pub fn create<P: AsRef<Path>>(db: &Db, checkpoint_dir: P) -> Result<(), Error> {
+    Self::create_with_log_size_for_flush(db, checkpoint_dir, 0)
+  }
+
+  /// Create a checkpoint of `db` in `checkpoint_dir`, which must not exist,
+  /// flushing the memtables first only if the live WALs hold at least
+  /// `log_size_for_flush` bytes.  Otherwise the WALs are copied as they are
+  /// and replayed when the checkpoint is opened.
+  ///
+  /// The checkpoint is built in `<checkpoint_dir>.tmp` and renamed into place
+  /// once complete, so `checkpoint_dir` never holds a partial checkpoint.
+  ///
+  /// Returns [`Error::InvalidArgument`] if `checkpoint_dir` exists, and
+  /// [`Error::NotSupported`] for in-memory and read-only databases.
+  ///
+  /// See `Checkpoint::CreateCheckpoint` in RocksDB.
+  pub fn create_with_log_size_for_flush<P: AsRef<Path>>(
+    db: &Db,
+    checkpoint_dir: P,
+    log_size_for_flush: u64,
+  ) -> Result<(), Error> {
+    let dir = checkpoint_dir.as_ref();
+    let persistence = db.inner.persistence.as_ref();
+    if persistence.is_some_and(|p| p.fs.file_exists(dir)) {
+      return Err(Error::InvalidArgument(format!(
+        "checkpoint directory {} already exists",
+        dir.display()
+      )));
+    }
+    let (_pause, live) = live_files(db, log_size_for_flush)?;
+    let fs = &*live.fs;
+    let mut staging = dir.as_os_str().to_owned();
+    staging.push(".tmp");
+    let staging = PathBuf::from(staging);
+    if fs.file_exists(&staging) {
+      log::info!("checkpoint: removing leftover {}", staging.display());
+      remove_dir_all(fs, &staging);
+    }
+
+    let result = fs
+      .create_dir_all(&staging)
+      .and_then(|()| populate(&staging, &live))
+      .and_then(|()| fs.rename(&staging, dir));
+    if let Err(e) = result {
+      remove_dir_all(fs, &staging);
+      return Err(e);
+    }
+    if let Some(parent) = dir.parent().filter(|d| !d.as_os_str().is_empty()) {
+      fs.sync_dir(parent)?;
+    }
+    log::info!("checkpoint created in {}", dir.display());
+    Ok(())
+  }
+}
+
+/// Record the files of `db`'s current `Version` and their sizes, flushing the
+/// memtables first if the live WALs hold at least `log_size_for_flush`
+/// bytes.  Obsolete-file deletion stays paused, so the files stay on disk,
+/// until the returned guard is dropped.
+///
+/// Writes append to the WAL and MANIFEST under the state lock, so the sizes
+/// read here end on a record boundary.  Waits for an in-progress flush to
+/// install its tables first: until then the data it covers is only in the
+/// WAL it rotated away from.
+///
+/// Returns [`Error::NotSupported`] for in-memory and read-only databases.
+///
+/// See `DBImpl::GetLiveFiles` and `DBImpl::GetSortedWalFiles` in RocksDB.
+pub(crate) fn live_files(
+  db: &Db,
+  log_size_for_flush: u64,
+) -> Result<(FileDeletionPause<'_>, LiveFiles), Error> {
+  let Some(p) = &db.inner.persistence else {
+    return Err(Error::NotSupported(
+      "cannot copy the files of an in-memory database".to_owned(),
+    ));
+  };
+  if db.inner.read_only {
+    return Err(Error::NotSupported(
+      "cannot copy the files of a read-only database".to_owned(),
+    ));
+  }
+  let (path, fs) = (&p.dir, &*p.fs);
+  if live_wal_bytes(path, db, fs)? >= log_size_for_flush {
+    db.flush(&FlushOptions { wait: true })?;
+  }
+
+  let pause = db.pause_file_deletions();
+  let mut g = db.inner.state.lock().unwrap();
+  while g.flush_in_progress() && g.background_error.is_none() {
+    g = db.inner.write_condvar.wait(g).unwrap();
+  }
+  if let Some(e) = &g.background_error {
+    return Err(e.clone());
+  }
+  let vs = g.version_set.as_ref().expect("live_files: no VersionSet");
+  let mut wanted = HashSet::new();
+  vs.add_live_files(&mut wanted);
+  let log_number = vs.log_number();
+  let manifest_number = vs.manifest_number();
+  let manifest_size = fs.file_size(&path.join(manifest_filename(manifest_number)))?;
+
+  let mut live = LiveFiles {
+    dir: path.clone(),
+    fs: Arc::clone(&p.fs),
+    immutable: Vec::new(),
+    manifest_number,
+    manifest_size,
+    wals: Vec::new(),
+    last_sequence: g.last_sequence,
+  };
+  for name in fs.children(path)? {
+    match parse_db_filename(&name) {
+      Some((number, FileKind::Table | FileKind::Blob)) if wanted.remove(&number) => {
+        live.immutable.push(name);
+      }
+      Some((number, FileKind::Log)) if number >= log_number => {
+        let size = fs.file_size(&path.join(&name))?;
+        live.wals.push((name, size));
+      }
+      _ => {}
+    }
+  }
+  if let Some(number) = wanted.iter().min() {
+    return Err(Error::Corruption(format!(
+      "file {number:06}, referenced by the MANIFEST, is missing"
+    )));
+  }
+  drop(g);
+  Ok((pause, live))
+}
+
+/// Total size of the WALs `db` still needs for recovery.
+fn live_wal_bytes(path: &Path, db: &Db, fs: &dyn FileSystem) -> Result<u64, Error> {
+  let g = db.inner.state.lock().unwrap();
+  let log_number = g.version_set.as_ref().map_or(0, |vs| vs.log_number());
+  let mut total = 0;
+  for name in fs.children(path)? {
+    if let Some((number, FileKind::Log)) = parse_db_filename(&name) {
+      if number >= log_number {
+        total += fs.file_size(&path.join(&name))?;
+      }
+    }
+  }
+  Ok(total)
+}
+
+/// Fill `staging` with the files of `live`.
+fn populate(staging: &Path, live: &LiveFiles) -> Result<(), Error> {
+  let (path, fs) = (&live.dir, &*live.fs);
+  let mut link = true;
+  for name in &live.immutable {
+    let (src, dst) = (path.join(name), staging.join(name));
+    if link {
+      match fs.link_file(&src, &dst) {
+        Ok(()) => continue,
+        Err(Error::NotSupported(reason)) => {
+          log::info!("checkpoint: copying files ({reason})");
+          link = false;
+        }
+        Err(e) => return Err(e),
+      }
+    }
+    copy_file(fs, &src, fs, &dst, None)?;
+  }
+  let manifest = manifest_filename(live.manifest_number);
+  copy_file(
+    fs,
+    &path.join(&manifest),
+    fs,
+    &staging.join(&manifest),
+    Some(live.manifest_size),
+  )?;
+  for (name, size) in &live.wals {
+    copy_file(fs, &path.join(name), fs, &staging.join(name), Some(*size))?;
+  }
+  write_current_file(staging, live.manifest_number, fs)
+}
+
+/// Copy `src` on `src_fs` to `dst` on `dst_fs` and sync it, stopping after
+/// `size` bytes if given.  Returns the number of bytes copied and their
+/// CRC32C.
+pub(crate) fn copy_file(
+  src_fs: &dyn FileSystem,
+  src: &Path,
+  dst_fs: &dyn FileSystem,
+  dst: &Path,
+  size: Option<u64>,
+) -> Result<(u64, u32), Error> {
+  let mut reader = src_fs.open_sequential(src)?;
+  let mut writer = dst_fs.create_writable(dst)?;
+  let mut remaining = size.unwrap_or(u64::MAX);
+  let (mut copied, mut crc) = (0u64, 0u32);
+  let mut buf = vec![0u8; 64 << 10];
+  while remaining > 0 {
+    let want = buf
+      .len()
+      .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+    let n = reader.read(&mut buf[..want])?;
+    if n == 0 {
+      if let Some(size) = size {
+        return Err(Error::Corruption(format!(
+          "{} is shorter than the {size} bytes to copy",
+          src.display()
+        )));
+      }
+      break;
+    }
+    writer.write(&buf[..n])?;
+    crc = crc32c_extend(crc, &buf[..n]);
+    copied += n as u64;
+    remaining -= n as u64;
+  }
+  writer.flush()?;
+  writer.sync()?;
+  Ok((copied, crc))
+}
+
+/// Best-effort removal of a staging directory and the files in it.
+pub(crate) fn remove_dir_all(fs: &dyn FileSystem, dir: &Path) {
+  for name in fs.children(dir).unwrap_or_default() {
+    let _ = fs.remove_file(&dir.join(name));
+  }
+  let _ = fs.remove_dir(dir);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Options;
+  use serial_test::serial;
+  use std::sync::Arc;
+
+  fn create_options() -> Options {
+    Options {
+      create_if_missing: true,
+      ..Options::default()
+    }
+  }
+
+  fn names(fs: &dyn FileSystem, dir: &Path) -> Vec<String> {
+    let mut names = fs.children(dir).unwrap();
+    names.sort();
+    names
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn checkpoint_opens_as_independent_db() {
+    let dir = tempfile::tempdir().unwrap();
+    let ckpt = dir.path().join("ckpt");
+    let db = Db::open(dir.path().join("db"), create_options()).unwrap();
+    db.put(b"flushed", b"1").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    db.put(b"in-memtable", b"2").unwrap();
+
+    Checkpoint::create(&db, &ckpt).unwrap();
+    db.put(b"after", b"3").unwrap();
+
+    // The memtable was flushed into the checkpoint; its tables are links.
+    let files = names(&crate::PosixFileSystem, &ckpt);
+    assert!(files.iter().filter(|n| n.ends_with(".ldb")).count() >= 2);
+    assert!(files.contains(&"CURRENT".to_owned()));
+    assert!(!files.iter().any(|n| n.ends_with(".tmp")));
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::MetadataExt;
+      let table = files.iter().find(|n| n.ends_with(".ldb")).unwrap();
+      assert!(std::fs::metadata(ckpt.join(table)).unwrap().nlink() >= 2);
+    }
+
+    let copy = Db::open(&ckpt, Options::default()).unwrap();
+    assert_eq!(copy.get(b"flushed").unwrap(), b"1");
+    assert_eq!(copy.get(b"in-memtable").unwrap(), b"2");
+    assert!(matches!(copy.get(b"after"), Err(Error::NotFound)));
+    copy.put(b"only-in-copy", b"4").unwrap();
+    assert!(matches!(db.get(b"only-in-copy"), Err(Error::NotFound)));
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn checkpoint_copies_wal_instead_of_flushing() {
+    let dir = tempfile::tempdir().unwrap();
+    let ckpt = dir.path().join("ckpt");
+    let db = Db::open(dir.path().join("db"), create_options()).unwrap();
+    for i in 0u32..100 {
+      db.put(format!("k{i:03}").as_bytes(), b"v").unwrap();
+    }
+
+    Checkpoint::create_with_log_size_for_flush(&db, &ckpt, u64::MAX).unwrap();
+    db.put(b"k999", b"v").unwrap();
+
+    let files = names(&crate::PosixFileSystem, &ckpt);
+    assert!(!files.iter().any(|n| n.ends_with(".ldb")));
+    assert_eq!(files.iter().filter(|n| n.ends_with(".log")).count(), 1);
+    let copy = Db::open(&ckpt, Options::default()).unwrap();
+    assert_eq!(copy.get(b"k000").unwrap(), b"v");
+    assert_eq!(copy.get(b"k099").unwrap(), b"v");
+    assert!(matches!(copy.get(b"k999"), Err(Error::NotFound)));
+  }
+
+  #[test]
+  fn checkpoint_copies_files_without_link_support() {
+    // `MemFileSystem` has no `link_file`, so every table is copied.
+    let fs: Arc<dyn FileSystem> = Arc::new(crate::env::MemFileSystem::new());
+    let opts = Options {
+      create_if_missing: true,
+      write_buffer_size: 512,
+      file_system: Arc::clone(&fs),
+      ..Options::default()
+    };
+    let db = Db::open(Path::new("/db"), opts.clone()).unwrap();
+    for i in 0u32..50 {
+      db.put(format!("k{i:04}").as_bytes(), format!("v{i:04}").as_bytes())
+        .unwrap();
+    }
+
+    Checkpoint::create(&db, "/backups/ckpt").unwrap();
+    assert!(!fs.file_exists(Path::new("/backups/ckpt.tmp")));
+    drop(db);
+    fs.remove_file(Path::new("/db/CURRENT")).unwrap();
+
+    let copy = Db::open(Path::new("/backups/ckpt"), opts).unwrap();
+    for i in 0u32..50 {
+      assert_eq!(
+        copy.get(format!("k{i:04}").as_bytes()).unwrap(),
+        format!("v{i:04}").as_bytes()
+      );
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn checkpoint_rejects_existing_dir_and_in_memory_db() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path().join("db"), create_options()).unwrap();
+    assert!(matches!(
+      Checkpoint::create(&db, dir.path()),
+      Err(Error::InvalidArgument(_))
+    ));
+    assert!(matches!(
+      Checkpoint::create(&Db::default(), dir.path().join("ckpt")),
+      Err(Error::NotSupported(_))
+    ));
+    assert!(!dir.path().join("ckpt").exists());
+  }
+}
@@ -0,0 +1,114 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Column families: named keyspaces within one database.
+//!
+//! Every column family has its own memtable, its own levels of SSTables and its
+//! own [`Options`] (comparator, compression, compaction filter, merge operator,
+//! memtable and table sizing).  All families share the database's WAL,
+//! MANIFEST and sequence numbers, so a [`WriteBatch`](crate::WriteBatch)
+//! spanning several families is applied atomically.
+//!
+//! Memtables are flushed together: when any family's memtable fills, every
+//! non-empty memtable is written out and recorded in one atomic MANIFEST
+//! group, so the shared WAL can be retired as a whole (RocksDB's
+//! `atomic_flush`).
+//!
+//! See `include/rocksdb/db.h` (`ColumnFamilyHandle`, `ColumnFamilyDescriptor`)
+//! in RocksDB.
+
+use crate::options::Options;
+
+/// Name of the column family every database has, which the methods without a
+/// `_cf` suffix operate on.
+pub const DEFAULT_COLUMN_FAMILY_NAME: &str = "default";
+
+/// Id of the default column family.
+pub(crate) const DEFAULT_COLUMN_FAMILY_ID: u32 = 0;
+
+/// Identifies a column family of an open [`Db`](crate::Db).
+///
+/// Obtained from [`Db::create_column_family`](crate::Db::create_column_family)
+/// or [`Db::column_family`](crate::Db::column_family).  Once the family is
+/// dropped, operations through the handle return [`Error::InvalidArgument`].
+///
+/// [`Error::InvalidArgument`]: crate::Error::InvalidArgument
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ColumnFamilyHandle {
+  id: u32,
+  name: String,
+}
+
+impl ColumnFamilyHandle {
+  pub(crate) fn new(id: u32, name: &str) -> Self {
+    ColumnFamilyHandle {
+      id,
+      name: name.to_owned(),
+    }
+  }
+
+  /// Id of the column family, unique for the lifetime of the database.
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  /// Name the column family was created with.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+}
+
+/// A column family to open with
+/// [`Db::open_with_column_families`](crate::Db::open_with_column_families).
+///
+/// Only the per-family fields of `options` are used: `comparator`,
+/// the compression settings, `compaction_filter_factory`, `merge_operator`,
+/// `write_buffer_size`, `block_size`, `block_restart_interval`,
+/// `max_file_size`, `filter_policy`, `block_cache` and the blob-file
+/// options.  The rest are taken
+/// from the database's options.
+#[derive(Debug, Clone)]
+pub struct ColumnFamilyDescriptor {
+  pub name: String,
+  pub options: Options,
+}
+
+impl ColumnFamilyDescriptor {
+  pub fn new<N: Into<String>>(name: N, options: Options) -> Self {
+    ColumnFamilyDescriptor {
+      name: name.into(),
+      options,
+    }
+  }
+}
+
+/// `cf_options` with its database-wide fields replaced by those of
+/// `db_options`, so every family shares the database's file system, clock
+/// and integrity settings.
+pub(crate) fn column_family_options(db_options: &Options, cf_options: Options) -> Options {
+  Options {
+    create_if_missing: db_options.create_if_missing,
+    error_if_exists: db_options.error_if_exists,
+    create_missing_column_families: db_options.create_missing_column_families,
+    paranoid_checks: db_options.paranoid_checks,
+    wal_recovery_mode: db_options.wal_recovery_mode,
+    max_open_files: db_options.max_open_files,
+    max_background_jobs: db_options.max_background_jobs,
+    max_subcompactions: db_options.max_subcompactions,
+    reuse_logs: db_options.reuse_logs,
+    wal_ttl: db_options.wal_ttl,
+    wal_size_limit: db_options.wal_size_limit,
+    file_system: std::sync::Arc::clone(&db_options.file_system),
+    clock: std::sync::Arc::clone(&db_options.clock),
+    ..cf_options
+  }
+}
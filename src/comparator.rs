@@ -33,6 +33,49 @@ pub trait Comparator: Send + Sync {
   ///
   /// Used for the last entry in an index block. Simple implementations may leave `key` unchanged.
   fn find_short_successor(&self, key: &mut Vec<u8>);
+
+  /// Size in bytes of the user-defined timestamp that ends every key, or `0`
+  /// (the default) if keys carry no timestamp.
+  ///
+  /// Keys that differ only in their timestamp are versions of the same key:
+  /// [`compare`](Comparator::compare) must order them newest timestamp first.
+  /// See `include/rocksdb/comparator.h: Comparator::timestamp_size`.
+  fn timestamp_size(&self) -> usize {
+    0
+  }
+
+  /// Three-way comparison of two timestamps (older sorts first).
+  ///
+  /// See `Comparator::CompareTimestamp`.
+  fn compare_timestamp(&self, _a: &[u8], _b: &[u8]) -> std::cmp::Ordering {
+    std::cmp::Ordering::Equal
+  }
+
+  /// Compare `a` and `b` ignoring their timestamps.  `a_has_ts` / `b_has_ts`
+  /// say whether each key ends in a timestamp.
+  ///
+  /// The default is correct for comparators without timestamps.
+  /// See `Comparator::CompareWithoutTimestamp`.
+  fn compare_without_timestamp(
+    &self,
+    a: &[u8],
+    _a_has_ts: bool,
+    b: &[u8],
+    _b_has_ts: bool,
+  ) -> std::cmp::Ordering {
+    self.compare(a, b)
+  }
+}
+
+/// `key` without the `ts_size`-byte timestamp it ends with.  A key shorter
+/// than a timestamp is returned empty.
+pub(crate) fn strip_timestamp(key: &[u8], ts_size: usize) -> &[u8] {
+  &key[..key.len().saturating_sub(ts_size)]
+}
+
+/// The timestamp `key` ends with; see [`strip_timestamp`].
+pub(crate) fn timestamp_of(key: &[u8], ts_size: usize) -> &[u8] {
+  &key[key.len().saturating_sub(ts_size)..]
 }
 
 /// Default comparator that orders keys lexicographically (byte-by-byte).
@@ -81,3 +124,100 @@ impl Comparator for BytewiseComparator {
     // key is all 0xff — leave unchanged.
   }
 }
+
+/// Bytewise comparator for keys ending in an 8-byte little-endian `u64`
+/// timestamp.  Versions of a key are ordered newest (largest timestamp)
+/// first.
+///
+/// Index separators are not shortened: doing so could cut into the
+/// timestamp.
+///
+/// See `util/comparator.cc: ComparatorWithU64TsImpl` in RocksDB.
+#[derive(Debug, Clone, Copy)]
+pub struct BytewiseComparatorWithU64Ts;
+
+impl BytewiseComparatorWithU64Ts {
+  /// Encode `ts` as a timestamp for this comparator.
+  pub fn encode_timestamp(ts: u64) -> [u8; 8] {
+    ts.to_le_bytes()
+  }
+
+  fn decode_timestamp(ts: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    let n = ts.len().min(8);
+    buf[..n].copy_from_slice(&ts[..n]);
+    u64::from_le_bytes(buf)
+  }
+}
+
+impl Comparator for BytewiseComparatorWithU64Ts {
+  fn compare(&self, a: &[u8], b: &[u8]) -> std::cmp::Ordering {
+    self
+      .compare_without_timestamp(a, true, b, true)
+      .then_with(|| self.compare_timestamp(timestamp_of(b, 8), timestamp_of(a, 8)))
+  }
+
+  fn name(&self) -> &str {
+    "leveldb.BytewiseComparator.u64ts"
+  }
+
+  fn find_shortest_separator(&self, _start: &mut Vec<u8>, _limit: &[u8]) {}
+
+  fn find_short_successor(&self, _key: &mut Vec<u8>) {}
+
+  fn timestamp_size(&self) -> usize {
+    8
+  }
+
+  fn compare_timestamp(&self, a: &[u8], b: &[u8]) -> std::cmp::Ordering {
+    Self::decode_timestamp(a).cmp(&Self::decode_timestamp(b))
+  }
+
+  fn compare_without_timestamp(
+    &self,
+    a: &[u8],
+    a_has_ts: bool,
+    b: &[u8],
+    b_has_ts: bool,
+  ) -> std::cmp::Ordering {
+    let a = if a_has_ts { strip_timestamp(a, 8) } else { a };
+    let b = if b_has_ts { strip_timestamp(b, 8) } else { b };
+    a.cmp(b)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::cmp::Ordering;
+
+  fn ts_key(key: &[u8], ts: u64) -> Vec<u8> {
+    [key, &BytewiseComparatorWithU64Ts::encode_timestamp(ts)].concat()
+  }
+
+  #[test]
+  fn u64_ts_comparator_orders_versions_newest_first() {
+    let cmp = BytewiseComparatorWithU64Ts;
+    assert_eq!(
+      cmp.compare(&ts_key(b"a", 5), &ts_key(b"a", 3)),
+      Ordering::Less
+    );
+    assert_eq!(
+      cmp.compare(&ts_key(b"a", 3), &ts_key(b"b", 9)),
+      Ordering::Less
+    );
+    // 256 > 1 numerically even though its first byte is smaller.
+    assert_eq!(
+      cmp.compare(&ts_key(b"a", 256), &ts_key(b"a", 1)),
+      Ordering::Less
+    );
+    assert_eq!(
+      cmp.compare_without_timestamp(&ts_key(b"a", 5), true, &ts_key(b"a", 3), true),
+      Ordering::Equal
+    );
+    assert_eq!(
+      cmp.compare_without_timestamp(&ts_key(b"a", 5), true, b"a", false),
+      Ordering::Equal
+    );
+  }
+}
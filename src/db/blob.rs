@@ -0,0 +1,591 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Blob files: append-only files holding values separated from the SSTables.
+//!
+//! With [`Options::enable_blob_files`](crate::Options::enable_blob_files),
+//! flush and compaction write each value of at least `min_blob_size` bytes to
+//! a blob file and store a [`BlobIndex`] (file number, offset and size) in the
+//! SSTable in its place, under the value type [`VALUE_TYPE_BLOB_INDEX`].  Later
+//! compactions move only the small index, so a large value is written once.
+//! Reads resolve an index through the column family's [`TableCache`].
+//!
+//! A blob file is never modified.  The MANIFEST records each file's blob count
+//! and size when it is added, and every compaction records how many of its
+//! blobs it stopped referencing; a file whose blobs are all garbage is dropped
+//! from the `Version` and deleted.  Garbage collection speeds this up by
+//! relocating the live blobs of the oldest files during compaction.
+//!
+//! The file layout follows RocksDB's (`db/blob/blob_log_format.h`):
+//!
+//! ```text
+//! header: magic u32 | version u32 | column family u32 | flags u8 | compression u8 | expiration 2×u64
+//! record: key_len u64 | value_len u64 | expiration u64 | header crc u32 | blob crc u32 | key | value
+//! footer: magic u32 | blob count u64 | expiration 2×u64 | footer crc u32
+//! ```
+//!
+//! All integers are little-endian; checksums are masked CRC32c.  Blobs are
+//! stored uncompressed and without expiration, and the column family id is
+//! not recorded (always `0`).
+//!
+//! See `db/blob/` in RocksDB.
+
+use crate::coding::{crc32c, crc32c_extend, mask_crc, read_varu64, unmask_crc, write_varu64};
+use crate::db::table_cache::TableCache;
+use crate::db::version::Version;
+use crate::db::version_edit::{BlobFileAddition, BlobFileGarbage};
+use crate::env::{FileSystem, RandomAccessFile, WritableFile};
+use crate::error::Error;
+use crate::options::Options;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Value type of an SSTable entry whose value is a [`BlobIndex`] (RocksDB's
+/// `kTypeBlobIndex`).  Only SSTables hold such entries; memtables always hold
+/// the value itself.
+pub(crate) const VALUE_TYPE_BLOB_INDEX: u8 = 0x11;
+
+const BLOB_MAGIC_NUMBER: u32 = 2_395_959;
+const BLOB_FILE_VERSION: u32 = 1;
+const BLOB_HEADER_SIZE: usize = 30;
+/// Size of the header in front of every blob record's key and value.
+pub(crate) const BLOB_RECORD_HEADER_SIZE: usize = 32;
+const BLOB_FOOTER_SIZE: usize = 32;
+
+/// `BlobIndex` type byte of a plain reference (RocksDB's `BlobIndex::Type::kBlob`).
+const BLOB_INDEX_TYPE_BLOB: u8 = 1;
+/// Compression byte for an uncompressed blob.
+const NO_COMPRESSION: u8 = 0;
+
+/// Name of blob file `number` inside the database directory.
+pub(crate) fn blob_file_path(dir: &Path, number: u64) -> PathBuf {
+  dir.join(format!("{number:06}.blob"))
+}
+
+// ── BlobIndex ─────────────────────────────────────────────────────────────────
+
+/// Location of a blob: stored as the value of a blob-index SSTable entry.
+///
+/// `offset` points at the value itself, past the record header and key.
+///
+/// See `db/blob/blob_index.h` in RocksDB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlobIndex {
+  pub(crate) file_number: u64,
+  pub(crate) offset: u64,
+  pub(crate) size: u64,
+}
+
+impl BlobIndex {
+  pub(crate) fn encode(&self) -> Vec<u8> {
+    let mut out = Vec::with_capacity(32);
+    let mut tmp = [0u8; 10];
+    out.push(BLOB_INDEX_TYPE_BLOB);
+    for v in [self.file_number, self.offset, self.size] {
+      let n = write_varu64(&mut tmp, v);
+      out.extend_from_slice(&tmp[..n]);
+    }
+    out.push(NO_COMPRESSION);
+    out
+  }
+
+  pub(crate) fn decode(data: &[u8]) -> Result<Self, Error> {
+    let corrupt = || Error::Corruption("malformed blob index".to_owned());
+    match data.first() {
+      Some(&BLOB_INDEX_TYPE_BLOB) => {}
+      Some(t) => return Err(Error::NotSupported(format!("blob index type {t}"))),
+      None => return Err(corrupt()),
+    }
+    let mut pos = 1;
+    let mut fields = [0u64; 3];
+    for field in &mut fields {
+      let (v, n) = read_varu64(&data[pos..]);
+      if n == 0 {
+        return Err(corrupt());
+      }
+      pos += n;
+      *field = v;
+    }
+    match data.get(pos) {
+      Some(&NO_COMPRESSION) => {}
+      Some(c) => return Err(Error::NotSupported(format!("blob compression type {c}"))),
+      None => return Err(corrupt()),
+    }
+    let [file_number, offset, size] = fields;
+    Ok(BlobIndex {
+      file_number,
+      offset,
+      size,
+    })
+  }
+
+  /// Bytes the referenced blob's record occupies in its file, given the user
+  /// key it is stored under.
+  pub(crate) fn record_size(&self, user_key: &[u8]) -> u64 {
+    (BLOB_RECORD_HEADER_SIZE + user_key.len()) as u64 + self.size
+  }
+}
+
+// ── BlobLogWriter ─────────────────────────────────────────────────────────────
+
+/// Writes one blob file.
+///
+/// See `db/blob/blob_log_writer.cc` in RocksDB.
+pub(crate) struct BlobLogWriter {
+  number: u64,
+  dest: Box<dyn WritableFile>,
+  /// Offset of the next byte to be written (= current file size).
+  offset: u64,
+  blob_count: u64,
+  blob_bytes: u64,
+}
+
+impl BlobLogWriter {
+  /// Create blob file `number` in `dir` and write its header.
+  pub(crate) fn new(fs: &dyn FileSystem, dir: &Path, number: u64) -> Result<Self, Error> {
+    let mut dest = fs.create_writable(&blob_file_path(dir, number))?;
+    let mut header = [0u8; BLOB_HEADER_SIZE];
+    header[0..4].copy_from_slice(&BLOB_MAGIC_NUMBER.to_le_bytes());
+    header[4..8].copy_from_slice(&BLOB_FILE_VERSION.to_le_bytes());
+    // Column family, flags (no TTL), compression and expiration range stay zero.
+    dest.write(&header)?;
+    Ok(BlobLogWriter {
+      number,
+      dest,
+      offset: BLOB_HEADER_SIZE as u64,
+      blob_count: 0,
+      blob_bytes: 0,
+    })
+  }
+
+  /// Append `value` stored under `user_key`, returning its index.
+  pub(crate) fn add(&mut self, user_key: &[u8], value: &[u8]) -> Result<BlobIndex, Error> {
+    let mut header = [0u8; BLOB_RECORD_HEADER_SIZE];
+    header[0..8].copy_from_slice(&(user_key.len() as u64).to_le_bytes());
+    header[8..16].copy_from_slice(&(value.len() as u64).to_le_bytes());
+    // Bytes 16..24: expiration (none).
+    let header_crc = mask_crc(crc32c(&header[..24]));
+    header[24..28].copy_from_slice(&header_crc.to_le_bytes());
+    let blob_crc = mask_crc(crc32c_extend(crc32c(user_key), value));
+    header[28..32].copy_from_slice(&blob_crc.to_le_bytes());
+    self.dest.write(&header)?;
+    self.dest.write(user_key)?;
+    self.dest.write(value)?;
+
+    let record_size = (BLOB_RECORD_HEADER_SIZE + user_key.len() + value.len()) as u64;
+    let index = BlobIndex {
+      file_number: self.number,
+      offset: self.offset + (BLOB_RECORD_HEADER_SIZE + user_key.len()) as u64,
+      size: value.len() as u64,
+    };
+    self.offset += record_size;
+    self.blob_count += 1;
+    self.blob_bytes += record_size;
+    Ok(index)
+  }
+
+  pub(crate) fn file_size(&self) -> u64 {
+    self.offset
+  }
+
+  /// Write the footer and sync the file.  The caller syncs the directory.
+  pub(crate) fn finish(mut self) -> Result<BlobFileAddition, Error> {
+    let mut footer = [0u8; BLOB_FOOTER_SIZE];
+    footer[0..4].copy_from_slice(&BLOB_MAGIC_NUMBER.to_le_bytes());
+    footer[4..12].copy_from_slice(&self.blob_count.to_le_bytes());
+    // Bytes 12..28: expiration range (none).
+    let crc = mask_crc(crc32c(&footer[..28]));
+    footer[28..32].copy_from_slice(&crc.to_le_bytes());
+    self.dest.write(&footer)?;
+    self.dest.sync()?;
+    Ok(BlobFileAddition {
+      blob_file_number: self.number,
+      total_blob_count: self.blob_count,
+      total_blob_bytes: self.blob_bytes,
+    })
+  }
+}
+
+// ── BlobFileBuilder ───────────────────────────────────────────────────────────
+
+/// Separates the large values of a flush or compaction into blob files,
+/// creating each file when its first blob arrives and starting a new one once
+/// it reaches `Options::blob_file_size`.
+///
+/// See `db/blob/blob_file_builder.cc` in RocksDB.
+pub(crate) struct BlobFileBuilder {
+  fs: Arc<dyn FileSystem>,
+  dir: PathBuf,
+  min_blob_size: usize,
+  blob_file_size: u64,
+  current: Option<BlobLogWriter>,
+  additions: Vec<BlobFileAddition>,
+}
+
+impl BlobFileBuilder {
+  /// A builder writing to `dir` with the blob options of `opts`, or `None` if
+  /// `opts` does not enable blob files.
+  pub(crate) fn new(opts: &Options, dir: &Path) -> Option<Self> {
+    opts.enable_blob_files.then(|| BlobFileBuilder {
+      fs: Arc::clone(&opts.file_system),
+      dir: dir.to_owned(),
+      min_blob_size: opts.min_blob_size,
+      blob_file_size: opts.blob_file_size,
+      current: None,
+      additions: Vec::new(),
+    })
+  }
+
+  /// Write `value` to a blob file if it is at least `min_blob_size` bytes,
+  /// returning its index; `None` means the value stays inline.
+  ///
+  /// `next_file_number` allocates the number of each new blob file.
+  pub(crate) fn add(
+    &mut self,
+    user_key: &[u8],
+    value: &[u8],
+    next_file_number: &mut dyn FnMut() -> u64,
+  ) -> Result<Option<BlobIndex>, Error> {
+    if value.len() < self.min_blob_size {
+      return Ok(None);
+    }
+    let writer = match &mut self.current {
+      Some(w) => w,
+      None => self.current.insert(BlobLogWriter::new(
+        &*self.fs,
+        &self.dir,
+        next_file_number(),
+      )?),
+    };
+    let index = writer.add(user_key, value)?;
+    if writer.file_size() >= self.blob_file_size {
+      self.additions.push(self.current.take().unwrap().finish()?);
+    }
+    Ok(Some(index))
+  }
+
+  /// Finish the open blob file, returning every file written.
+  pub(crate) fn finish(mut self) -> Result<Vec<BlobFileAddition>, Error> {
+    if let Some(writer) = self.current.take() {
+      self.additions.push(writer.finish()?);
+    }
+    Ok(self.additions)
+  }
+}
+
+// ── BlobFileReader ────────────────────────────────────────────────────────────
+
+/// Reads blobs from one blob file.  Opened through the [`TableCache`].
+///
+/// See `db/blob/blob_file_reader.cc` in RocksDB.
+pub(crate) struct BlobFileReader {
+  file: Arc<dyn RandomAccessFile>,
+  file_size: u64,
+}
+
+impl BlobFileReader {
+  /// Open blob file `number` in `dir` and validate its header.
+  pub(crate) fn open(fs: &dyn FileSystem, dir: &Path, number: u64) -> Result<Self, Error> {
+    let path = blob_file_path(dir, number);
+    let file_size = fs.file_size(&path)?;
+    let file = fs.open_random_access(&path)?;
+    if file_size < (BLOB_HEADER_SIZE + BLOB_FOOTER_SIZE) as u64 {
+      return Err(Error::Corruption(format!(
+        "blob file {number:06} is too short"
+      )));
+    }
+    let mut header = [0u8; BLOB_HEADER_SIZE];
+    read_exact_at(&*file, &mut header, 0)?;
+    if header[0..4] != BLOB_MAGIC_NUMBER.to_le_bytes() {
+      return Err(Error::Corruption(format!(
+        "blob file {number:06}: bad magic number"
+      )));
+    }
+    if header[4..8] != BLOB_FILE_VERSION.to_le_bytes() {
+      return Err(Error::NotSupported(format!(
+        "blob file {number:06}: unknown version"
+      )));
+    }
+    if header[13] != NO_COMPRESSION {
+      return Err(Error::NotSupported(format!(
+        "blob file {number:06}: compression type {}",
+        header[13]
+      )));
+    }
+    Ok(BlobFileReader { file, file_size })
+  }
+
+  /// Read the footer and return the number of blobs in the file.
+  pub(crate) fn blob_count(&self) -> Result<u64, Error> {
+    let mut footer = [0u8; BLOB_FOOTER_SIZE];
+    read_exact_at(
+      &*self.file,
+      &mut footer,
+      self.file_size - BLOB_FOOTER_SIZE as u64,
+    )?;
+    let stored = unmask_crc(u32::from_le_bytes(footer[28..32].try_into().unwrap()));
+    if footer[0..4] != BLOB_MAGIC_NUMBER.to_le_bytes() || stored != crc32c(&footer[..28]) {
+      return Err(Error::Corruption("bad blob file footer".to_owned()));
+    }
+    Ok(u64::from_le_bytes(footer[4..12].try_into().unwrap()))
+  }
+
+  /// Bytes of all blob records in the file.
+  pub(crate) fn blob_bytes(&self) -> u64 {
+    self.file_size - (BLOB_HEADER_SIZE + BLOB_FOOTER_SIZE) as u64
+  }
+
+  /// Read the blob `index` points at, stored under `user_key`.
+  ///
+  /// With `verify_checksums` the whole record is read and its checksums and
+  /// key are checked; otherwise only the value is read.
+  pub(crate) fn get(
+    &self,
+    user_key: &[u8],
+    index: &BlobIndex,
+    verify_checksums: bool,
+  ) -> Result<Vec<u8>, Error> {
+    let key_offset = (BLOB_RECORD_HEADER_SIZE + user_key.len()) as u64;
+    let end = index.offset.checked_add(index.size);
+    if index.offset < BLOB_HEADER_SIZE as u64 + key_offset
+      || end.is_none_or(|e| e > self.file_size - BLOB_FOOTER_SIZE as u64)
+    {
+      return Err(Error::Corruption(format!(
+        "blob index out of range: offset {} size {}",
+        index.offset, index.size
+      )));
+    }
+    if !verify_checksums {
+      let mut value = vec![0u8; index.size as usize];
+      read_exact_at(&*self.file, &mut value, index.offset)?;
+      return Ok(value);
+    }
+
+    let mut record = vec![0u8; key_offset as usize + index.size as usize];
+    read_exact_at(&*self.file, &mut record, index.offset - key_offset)?;
+    let corrupt = |what: &str| Error::Corruption(format!("blob record: {what}"));
+    let stored_header_crc = unmask_crc(u32::from_le_bytes(record[24..28].try_into().unwrap()));
+    if stored_header_crc != crc32c(&record[..24]) {
+      return Err(corrupt("header checksum mismatch"));
+    }
+    let key_len = u64::from_le_bytes(record[0..8].try_into().unwrap());
+    let value_len = u64::from_le_bytes(record[8..16].try_into().unwrap());
+    if key_len != user_key.len() as u64 || value_len != index.size {
+      return Err(corrupt("length mismatch"));
+    }
+    let (key, value) = record[BLOB_RECORD_HEADER_SIZE..].split_at(user_key.len());
+    if key != user_key {
+      return Err(corrupt("key mismatch"));
+    }
+    let stored_blob_crc = unmask_crc(u32::from_le_bytes(record[28..32].try_into().unwrap()));
+    if stored_blob_crc != crc32c_extend(crc32c(key), value) {
+      return Err(corrupt("blob checksum mismatch"));
+    }
+    Ok(value.to_vec())
+  }
+}
+
+/// Fill `buf` from `file` at `offset`, failing on a short read.
+fn read_exact_at(file: &dyn RandomAccessFile, buf: &mut [u8], offset: u64) -> Result<(), Error> {
+  let n = file.read_at(buf, offset)?;
+  if n < buf.len() {
+    return Err(Error::Corruption("truncated blob file".to_owned()));
+  }
+  Ok(())
+}
+
+// ── BlobFetcher ───────────────────────────────────────────────────────────────
+
+/// Resolves blob indexes found while reading a `Version`.
+///
+/// See `db/blob/blob_fetcher.h` in RocksDB.
+#[derive(Clone)]
+pub(crate) struct BlobFetcher {
+  pub(crate) version: Arc<Version>,
+  pub(crate) table_cache: TableCache,
+  pub(crate) verify_checksums: bool,
+}
+
+impl BlobFetcher {
+  /// The value the blob index `index` stored under `user_key` refers to.
+  pub(crate) fn fetch(&self, user_key: &[u8], index: &[u8]) -> Result<Vec<u8>, Error> {
+    self
+      .version
+      .get_blob(user_key, index, self.verify_checksums, &self.table_cache)
+  }
+}
+
+// ── BlobGarbageMeter ──────────────────────────────────────────────────────────
+
+/// Counts the blob references a compaction reads and writes, per blob file;
+/// references read but not written are the garbage the compaction produced.
+///
+/// See `db/blob/blob_garbage_meter.h` in RocksDB.
+#[derive(Default)]
+pub(crate) struct BlobGarbageMeter {
+  /// File number → `(count, bytes)` in and out.
+  flows: BTreeMap<u64, [(u64, u64); 2]>,
+}
+
+impl BlobGarbageMeter {
+  /// Record a blob-index entry read from an input file.
+  pub(crate) fn process_in(&mut self, user_key: &[u8], index: &BlobIndex) {
+    self.add(0, user_key, index);
+  }
+
+  /// Record a blob-index entry written to an output file.
+  pub(crate) fn process_out(&mut self, user_key: &[u8], index: &BlobIndex) {
+    self.add(1, user_key, index);
+  }
+
+  fn add(&mut self, side: usize, user_key: &[u8], index: &BlobIndex) {
+    let flow = &mut self.flows.entry(index.file_number).or_default()[side];
+    flow.0 += 1;
+    flow.1 += index.record_size(user_key);
+  }
+
+  /// Garbage per blob file that the compaction read references from.
+  pub(crate) fn garbage(&self) -> Vec<BlobFileGarbage> {
+    self
+      .flows
+      .iter()
+      .filter(|(_, [inflow, outflow])| inflow.0 > outflow.0)
+      .map(|(&number, [inflow, outflow])| BlobFileGarbage {
+        blob_file_number: number,
+        garbage_blob_count: inflow.0 - outflow.0,
+        garbage_blob_bytes: inflow.1.saturating_sub(outflow.1),
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::env::PosixFileSystem;
+
+  #[test]
+  fn blob_index_roundtrip() {
+    let index = BlobIndex {
+      file_number: 12,
+      offset: 1 << 40,
+      size: 300,
+    };
+    assert_eq!(BlobIndex::decode(&index.encode()).unwrap(), index);
+    assert!(BlobIndex::decode(b"").is_err());
+    assert!(matches!(
+      BlobIndex::decode(&[2, 1, 1, 1, 0]),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
+  #[test]
+  fn writer_and_reader_roundtrip() {
+    let dir = tempfile::tempdir().unwrap();
+    let fs = PosixFileSystem;
+    let mut builder = BlobLogWriter::new(&fs, dir.path(), 5).unwrap();
+    let a = builder.add(b"a", &[1u8; 100]).unwrap();
+    let b = builder.add(b"bb", b"second").unwrap();
+    let size = builder.file_size();
+    let addition = builder.finish().unwrap();
+    assert_eq!(addition.blob_file_number, 5);
+    assert_eq!(addition.total_blob_count, 2);
+    assert_eq!(
+      addition.total_blob_bytes,
+      a.record_size(b"a") + b.record_size(b"bb")
+    );
+
+    let reader = BlobFileReader::open(&fs, dir.path(), 5).unwrap();
+    assert_eq!(reader.blob_count().unwrap(), 2);
+    assert_eq!(reader.blob_bytes(), addition.total_blob_bytes);
+    assert_eq!(size + BLOB_FOOTER_SIZE as u64, reader.file_size);
+    for verify in [false, true] {
+      assert_eq!(reader.get(b"a", &a, verify).unwrap(), vec![1u8; 100]);
+      assert_eq!(reader.get(b"bb", &b, verify).unwrap(), b"second");
+    }
+    // A key that does not match the record is caught only when verifying.
+    assert!(reader.get(b"xx", &b, true).is_err());
+    let past_end = BlobIndex { offset: size, ..b };
+    assert!(reader.get(b"bb", &past_end, false).is_err());
+  }
+
+  #[test]
+  fn reader_detects_corrupt_blob() {
+    let dir = tempfile::tempdir().unwrap();
+    let fs = PosixFileSystem;
+    let mut builder = BlobLogWriter::new(&fs, dir.path(), 7).unwrap();
+    let index = builder.add(b"k", b"value").unwrap();
+    builder.finish().unwrap();
+
+    let path = blob_file_path(dir.path(), 7);
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[index.offset as usize] ^= 0xff;
+    std::fs::write(&path, bytes).unwrap();
+
+    let reader = BlobFileReader::open(&fs, dir.path(), 7).unwrap();
+    assert!(reader.get(b"k", &index, false).is_ok());
+    assert!(matches!(
+      reader.get(b"k", &index, true),
+      Err(Error::Corruption(_))
+    ));
+  }
+
+  #[test]
+  fn builder_separates_large_values_and_rolls_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let opts = Options {
+      enable_blob_files: true,
+      min_blob_size: 10,
+      blob_file_size: 100,
+      ..Options::default()
+    };
+    assert!(BlobFileBuilder::new(&Options::default(), dir.path()).is_none());
+    let mut builder = BlobFileBuilder::new(&opts, dir.path()).unwrap();
+    let mut next = 20;
+    let mut alloc = || {
+      next += 1;
+      next
+    };
+    assert_eq!(builder.add(b"k", b"small", &mut alloc).unwrap(), None);
+    let a = builder.add(b"k", &[7u8; 80], &mut alloc).unwrap().unwrap();
+    let b = builder.add(b"k", &[8u8; 80], &mut alloc).unwrap().unwrap();
+    assert_eq!((a.file_number, b.file_number), (21, 22));
+    let additions = builder.finish().unwrap();
+    assert_eq!(additions.len(), 2);
+    assert!(additions.iter().all(|a| a.total_blob_count == 1));
+  }
+
+  #[test]
+  fn garbage_meter_reports_unwritten_references() {
+    let index = |file_number, size| BlobIndex {
+      file_number,
+      offset: 100,
+      size,
+    };
+    let mut meter = BlobGarbageMeter::default();
+    meter.process_in(b"a", &index(3, 10));
+    meter.process_in(b"b", &index(3, 20));
+    meter.process_in(b"c", &index(4, 30));
+    meter.process_out(b"a", &index(3, 10));
+    meter.process_out(b"c", &index(4, 30));
+    meter.process_out(b"d", &index(9, 5));
+    assert_eq!(
+      meter.garbage(),
+      vec![BlobFileGarbage {
+        blob_file_number: 3,
+        garbage_blob_count: 1,
+        garbage_blob_bytes: index(3, 20).record_size(b"b"),
+      }]
+    );
+  }
+}
@@ -14,11 +14,17 @@
 //!
 //! The compaction protocol has three phases; this module owns the first two:
 //!
-//! 1. **Planning** (no lock, no I/O): [`pick_compaction`] / [`pick_range_compaction`]
+//! 1. **Planning** (no I/O): [`pick_compaction`] / [`pick_range_compaction`]
 //!    query a [`Version`](crate::db::version::Version) snapshot and produce a
 //!    [`Compaction`] plan — the input
 //!    files at `level` and `level + 1`, the grandparent files used for output
-//!    limiting, and the compact-pointer advance to persist.
+//!    limiting, and the compact-pointer advance to persist.  With universal
+//!    compaction, `pick_compaction` defers to [`pick_universal_compaction`],
+//!    which merges whole sorted runs, and with FIFO compaction to
+//!    [`pick_fifo_compaction`], which drops the oldest files.  `Db` picks
+//!    under its lock and records a
+//!    [`CompactionClaim`] for the plan, so that compactions running at the
+//!    same time never conflict.
 //! 2. **Execution** (I/O, no lock): [`do_compaction`] merges the plan's inputs,
 //!    applies shadow-key pruning, tombstone elision, and the optional
 //!    [`CompactionFilter`](crate::compaction_filter::CompactionFilter), and writes
@@ -33,11 +39,18 @@
 //! (`DoCompactionWork`).
 
 use crate::cache::BlockCache;
-use crate::db::version_edit::{FileMetaData, VersionEdit};
+use crate::comparator::timestamp_of;
+use crate::db::blob::{
+  BlobFetcher, BlobFileBuilder, BlobGarbageMeter, BlobIndex, VALUE_TYPE_BLOB_INDEX,
+};
+use crate::db::fifo_compaction::{needs_fifo_compaction, pick_fifo_compaction};
+use crate::db::range_del::{extend_file_bounds, FragmentedRangeTombstones, RangeTombstone};
+use crate::db::universal_compaction::{needs_universal_compaction, pick_universal_compaction};
+use crate::db::version_edit::{BlobFileGarbage, FileMetaData, VersionEdit};
 use crate::error::Error;
-use crate::options::Options;
+use crate::options::{CompactionStyle, CompressionType, Options};
 use crate::table::builder::TableBuilder;
-use crate::table::format::user_key as ikey_user_key;
+use crate::table::format::{make_internal_key, parse_internal_key, user_key as ikey_user_key};
 use crate::table::reader::Table;
 use std::sync::Arc;
 
@@ -128,7 +141,7 @@ fn max_grandparent_overlap_bytes(max_file_size: u64) -> u64 {
 ///
 /// Rules (port of `Version::PickLevelForMemTableOutput`):
 /// - Start at L0.
-/// - While `level < MAX_MEM_COMPACT_LEVEL` (2):
+/// - While `level < MAX_MEM_COMPACT_LEVEL` (2) and L+1 exists:
 ///   - If the range overlaps L+1, stop.
 ///   - If grandparent (L+2) overlap exceeds `max_grandparent_overlap_bytes`, stop.
 ///   - Otherwise advance to level+1.
@@ -138,17 +151,17 @@ pub(crate) fn pick_level_for_memtable_output(
   largest_uk: &[u8],
   max_file_size: u64,
 ) -> usize {
-  use crate::db::version::NUM_LEVELS;
   const MAX_MEM_COMPACT_LEVEL: usize = 2;
 
   let mut level = 0;
   if !version.overlaps_level(0, smallest_uk, largest_uk) {
     // No L0 overlap — consider pushing deeper.
-    while level < MAX_MEM_COMPACT_LEVEL {
+    let max_level = MAX_MEM_COMPACT_LEVEL.min(version.num_levels() - 1);
+    while level < max_level {
       if version.overlaps_level(level + 1, smallest_uk, largest_uk) {
         break;
       }
-      if level + 2 < NUM_LEVELS {
+      if level + 2 < version.num_levels() {
         // Check grandparent overlap to avoid creating a file that will cause
         // an expensive compaction at the next level.
         let grandparent_bytes =
@@ -218,16 +231,34 @@ fn add_boundary_inputs(
 /// consumed by [`do_compaction`] (execution) and by `install_compaction` /
 /// `install_trivial_move` in `lib.rs` (installation).
 pub(crate) struct Compaction {
-  /// Level being compacted; inputs come from `level` and `level+1`.
+  /// Level being compacted; inputs come from `level` and `output_level`, and
+  /// for universal compactions the levels between.
   pub(crate) level: usize,
-  /// `inputs[0]` = files at `level`; `inputs[1]` = files at `level+1`.
+  /// Level the outputs are written to: [`output_level_for`] `level`, except
+  /// for a forced blob garbage collection of the last level, which rewrites
+  /// it in place, and for FIFO compactions, which stay at `level`.
+  pub(crate) output_level: usize,
+  /// `inputs[0]` = files at `level`; `inputs[1]` = files at `output_level`
+  /// (always empty when it equals `level`).
   pub(crate) inputs: [Vec<Arc<FileMetaData>>; 2],
-  /// Files at `level+2` overlapping the full input range.
+  /// Files at the levels strictly between `level` and `output_level`, by
+  /// level.  Only universal compactions, which merge several sorted runs at
+  /// once, have any.
+  pub(crate) middle_inputs: Vec<(usize, Vec<Arc<FileMetaData>>)>,
+  /// Files at `output_level + 1` overlapping the full input range.
   pub(crate) grandparents: Vec<Arc<FileMetaData>>,
   /// Version snapshot used to build this plan.
   input_version: Arc<crate::db::version::Version>,
-  /// VersionEdit carrying the compact_pointer update (filled by setup_other_inputs).
+  /// VersionEdit carrying the compact_pointer update (filled by
+  /// setup_other_inputs) and the blob files added and garbage produced (filled
+  /// by do_compaction).
   pub(crate) edit: VersionEdit,
+  /// Picked to relocate the blobs of files with too much garbage: never a
+  /// trivial move.
+  pub(crate) forced_blob_gc: bool,
+  /// Picked by FIFO compaction to drop its inputs: installed without reading
+  /// or writing anything.
+  pub(crate) deletion_compaction: bool,
 
   // ── Gap 4: grandparent-overlap output limiting ────────────────────────────
   /// Index into `grandparents` of the next file to scan in `should_stop_before`.
@@ -239,54 +270,224 @@ pub(crate) struct Compaction {
   overlapped_bytes: i64,
   /// Per-level monotone cursors for `is_base_level_for_key`.  Each entry starts
   /// at 0 and only advances forward, amortising repeated scans to O(1)/key.
-  level_ptrs: [usize; crate::db::version::NUM_LEVELS],
+  level_ptrs: Vec<usize>,
 }
 
 impl Compaction {
   pub(crate) fn new(level: usize, input_version: Arc<crate::db::version::Version>) -> Self {
+    let level_ptrs = vec![0; input_version.num_levels()];
     Compaction {
       level,
+      output_level: output_level_for(&input_version, level),
       inputs: [Vec::new(), Vec::new()],
+      middle_inputs: Vec::new(),
       grandparents: Vec::new(),
       input_version,
       edit: VersionEdit::new(),
+      forced_blob_gc: false,
+      deletion_compaction: false,
       grandparent_index: 0,
       seen_key: false,
       overlapped_bytes: 0,
-      level_ptrs: [0; crate::db::version::NUM_LEVELS],
+      level_ptrs,
     }
   }
 
+  /// Every input file, from the newest level to the oldest.
   pub(crate) fn all_input_files(&self) -> impl Iterator<Item = &Arc<FileMetaData>> {
-    self.inputs[0].iter().chain(self.inputs[1].iter())
+    self.inputs[0]
+      .iter()
+      .chain(self.middle_inputs.iter().flat_map(|(_, files)| files))
+      .chain(self.inputs[1].iter())
+  }
+
+  /// Smallest and largest internal keys over every input file.
+  fn input_range(&self, cmp: &dyn crate::comparator::Comparator) -> (Vec<u8>, Vec<u8>) {
+    let files: Vec<_> = self.all_input_files().cloned().collect();
+    get_range(&files, cmp)
+  }
+
+  /// The plan for one key range of this compaction: the same inputs, with
+  /// fresh output-limiting cursors and an empty edit.
+  fn for_subcompaction(&self) -> Compaction {
+    let mut sub = Compaction::new(self.level, Arc::clone(&self.input_version));
+    sub.output_level = self.output_level;
+    sub.inputs = self.inputs.clone();
+    sub.middle_inputs = self.middle_inputs.clone();
+    sub.grandparents = self.grandparents.clone();
+    sub.forced_blob_gc = self.forced_blob_gc;
+    sub
+  }
+}
+
+// ── Concurrent compactions ────────────────────────────────────────────────
+
+/// The input files and key range held by a compaction from the moment it is
+/// picked until its outputs are installed.  Another compaction may only run
+/// alongside it if the two do not [`conflict`](CompactionClaim::conflicts).
+///
+/// See `db/compaction/compaction_picker.cc:
+/// CompactionPicker::FilesRangeOverlapWithCompaction` in RocksDB.
+pub(crate) struct CompactionClaim {
+  pub(crate) id: u64,
+  level: usize,
+  output_level: usize,
+  files: std::collections::HashSet<u64>,
+  /// User-key range of the inputs, which bounds that of the outputs.
+  smallest: Vec<u8>,
+  largest: Vec<u8>,
+}
+
+impl CompactionClaim {
+  pub(crate) fn new(id: u64, spec: &Compaction, cmp: &dyn crate::comparator::Comparator) -> Self {
+    let (smallest, largest) = spec.input_range(cmp);
+    CompactionClaim {
+      id,
+      level: spec.level,
+      output_level: spec.output_level,
+      files: spec.all_input_files().map(|f| f.number).collect(),
+      smallest: ikey_user_key(&smallest).to_vec(),
+      largest: ikey_user_key(&largest).to_vec(),
+    }
+  }
+
+  /// `true` if the user-key range `[smallest_uk, largest_uk]` overlaps the
+  /// claimed range.
+  pub(crate) fn overlaps(
+    &self,
+    smallest_uk: &[u8],
+    largest_uk: &[u8],
+    cmp: &dyn crate::comparator::Comparator,
+  ) -> bool {
+    cmp.compare(smallest_uk, &self.largest).is_le()
+      && cmp.compare(&self.smallest, largest_uk).is_le()
+  }
+
+  /// `true` if `spec` cannot run while this compaction does: it would read a
+  /// claimed file, or write outputs overlapping this compaction's at the same
+  /// level.  Compactions out of L0 also run one at a time, since every L0
+  /// file overlapping one they pick must move down with it.
+  pub(crate) fn conflicts(
+    &self,
+    spec: &Compaction,
+    cmp: &dyn crate::comparator::Comparator,
+  ) -> bool {
+    if spec.level == 0 && self.level == 0 {
+      return true;
+    }
+    if spec
+      .all_input_files()
+      .any(|f| self.files.contains(&f.number))
+    {
+      return true;
+    }
+    if spec.output_level != self.output_level {
+      return false;
+    }
+    let (smallest, largest) = spec.input_range(cmp);
+    self.overlaps(ikey_user_key(&smallest), ikey_user_key(&largest), cmp)
   }
 }
 
+/// `true` if `spec` conflicts with any of the `running` compactions.
+pub(crate) fn conflicts_with_running(
+  spec: &Compaction,
+  running: &[CompactionClaim],
+  cmp: &dyn crate::comparator::Comparator,
+) -> bool {
+  running.iter().any(|c| c.conflicts(spec, cmp))
+}
+
+/// The deepest level at which a new file spanning `[smallest_uk, largest_uk]`
+/// may be placed while the `running` compactions write their outputs: above
+/// the output level of every one whose range it overlaps, or the outputs
+/// (holding older entries) would land beside or above it.  `usize::MAX` if
+/// none overlaps.
+pub(crate) fn placement_limit(
+  running: &[CompactionClaim],
+  smallest_uk: &[u8],
+  largest_uk: &[u8],
+  cmp: &dyn crate::comparator::Comparator,
+) -> usize {
+  running
+    .iter()
+    .filter(|c| c.overlaps(smallest_uk, largest_uk, cmp))
+    .map(|c| c.output_level.saturating_sub(1))
+    .min()
+    .unwrap_or(usize::MAX)
+}
+
+/// The level a compaction of `level` writes to: the base level of `version`
+/// for L0, whose files may skip the empty levels above it, and the next
+/// level otherwise.
+pub(crate) fn output_level_for(version: &crate::db::version::Version, level: usize) -> usize {
+  if level == 0 {
+    version.base_level
+  } else {
+    level + 1
+  }
+}
+
+/// The compression of files written to `level`, in a tree whose L0 compacts
+/// into `base_level`: `bottommost_compression` if set and nothing lies below
+/// the files, else the `compression_per_level` entry for `level`, else
+/// `compression`.
+///
+/// See `db/compaction/compaction_picker.cc: GetCompressionType` in RocksDB.
+pub(crate) fn compression_for_level(
+  opts: &Options,
+  level: usize,
+  base_level: usize,
+  bottommost: bool,
+) -> CompressionType {
+  if let Some(compression) = opts.bottommost_compression.filter(|_| bottommost) {
+    return compression;
+  }
+  let Some(last) = opts.compression_per_level.len().checked_sub(1) else {
+    return opts.compression;
+  };
+  let index = if level == 0 {
+    0
+  } else {
+    (level + 1).saturating_sub(base_level)
+  };
+  opts.compression_per_level[index.min(last)]
+}
+
+/// The compression of the files `spec` writes: bottommost if no level below
+/// its output level holds data.
+fn output_compression(spec: &Compaction, opts: &Options) -> CompressionType {
+  let version = &spec.input_version;
+  let bottommost =
+    ((spec.output_level + 1)..version.num_levels()).all(|lvl| version.files_at(lvl).is_empty());
+  compression_for_level(opts, spec.output_level, version.base_level, bottommost)
+}
+
 // ── setup_other_inputs ────────────────────────────────────────────────────────
 
-/// Populate `spec.inputs[1]` (level+1 files), try to expand inputs[0] without
-/// pulling in more level+1 files, populate grandparents, and record the
-/// compact-pointer update in `spec.edit`.
+/// Populate `spec.inputs[1]` (`spec.output_level` files), try to expand
+/// inputs[0] without pulling in more output-level files, populate
+/// grandparents, and record the compact-pointer update in `spec.edit`.
 ///
 /// Port of LevelDB's `VersionSet::SetupOtherInputs`.
 fn setup_other_inputs(
   spec: &mut Compaction,
   version: &Arc<crate::db::version::Version>,
-  _compact_pointer: &[Vec<u8>; crate::db::version::NUM_LEVELS],
+  _compact_pointer: &[Vec<u8>],
   opts: &Options,
 ) {
-  use crate::db::version::NUM_LEVELS;
   let cmp = &*opts.comparator;
 
   let level = spec.level;
+  let output_level = spec.output_level;
   add_boundary_inputs(version, level, &mut spec.inputs[0], cmp);
 
   let (smallest, largest) = get_range(&spec.inputs[0], cmp);
   let smallest_uk = ikey_user_key(&smallest).to_vec();
   let largest_uk = ikey_user_key(&largest).to_vec();
 
-  spec.inputs[1] = version.overlapping_inputs(level + 1, &smallest_uk, &largest_uk);
-  add_boundary_inputs(version, level + 1, &mut spec.inputs[1], cmp);
+  spec.inputs[1] = version.overlapping_inputs(output_level, &smallest_uk, &largest_uk);
+  add_boundary_inputs(version, output_level, &mut spec.inputs[1], cmp);
 
   let (all_start, all_limit) = get_range2(&spec.inputs[0], &spec.inputs[1], cmp);
   let all_start_uk = ikey_user_key(&all_start).to_vec();
@@ -305,7 +506,7 @@ fn setup_other_inputs(
       let (exp_start, exp_limit) = get_range(&expanded0, cmp);
       let exp_start_uk = ikey_user_key(&exp_start).to_vec();
       let exp_limit_uk = ikey_user_key(&exp_limit).to_vec();
-      let expanded1 = version.overlapping_inputs(level + 1, &exp_start_uk, &exp_limit_uk);
+      let expanded1 = version.overlapping_inputs(output_level, &exp_start_uk, &exp_limit_uk);
       // Only accept the expansion if it doesn't pull in more L+1 files.
       if expanded1.len() == spec.inputs[1].len() {
         spec.inputs[0] = expanded0;
@@ -314,12 +515,14 @@ fn setup_other_inputs(
     }
   }
 
-  // Populate grandparents (level+2 files overlapping the compaction range).
-  if level + 2 < NUM_LEVELS {
+  // Populate grandparents (files below the output level overlapping the
+  // compaction range).
+  if output_level + 1 < version.num_levels() {
     let (final_start, final_limit) = get_range2(&spec.inputs[0], &spec.inputs[1], cmp);
     let final_start_uk = ikey_user_key(&final_start).to_vec();
     let final_limit_uk = ikey_user_key(&final_limit).to_vec();
-    spec.grandparents = version.overlapping_inputs(level + 2, &final_start_uk, &final_limit_uk);
+    spec.grandparents =
+      version.overlapping_inputs(output_level + 1, &final_start_uk, &final_limit_uk);
   }
 
   // Update compact_pointer: advance past largest key of inputs[0].
@@ -359,29 +562,41 @@ fn should_stop_before(spec: &mut Compaction, ikey: &[u8], opts: &Options) -> boo
   false
 }
 
-/// Returns `true` if `user_key` is definitely absent from all levels ≥
-/// `spec.level + 2` (i.e., the output level is the lowest level that holds
+/// Returns `true` if `user_key` is definitely absent from all levels below
+/// `spec.output_level` (i.e., the output level is the lowest level that holds
 /// this key).  Used for tombstone elision: we can only drop a deletion marker
 /// when it cannot hide a live value at a deeper level.
 ///
 /// Uses per-level monotone cursors (`spec.level_ptrs`) so each cursor advances
 /// at most once per key across the entire compaction — amortised O(1)/key.
 ///
+/// Always `false` for a merge into L0, which may leave older L0 files beside
+/// its output.
+///
 /// Port of LevelDB `Compaction::IsBaseLevelForKey`.
 fn is_base_level_for_key(
   spec: &mut Compaction,
   user_key: &[u8],
   cmp: &dyn crate::comparator::Comparator,
 ) -> bool {
-  use crate::db::version::NUM_LEVELS;
-  for lvl in (spec.level + 2)..NUM_LEVELS {
+  if spec.output_level == 0 {
+    return false;
+  }
+  for lvl in (spec.output_level + 1)..spec.input_version.num_levels() {
     let files = spec.input_version.files_at(lvl);
     while spec.level_ptrs[lvl] < files.len() {
       let f = &files[spec.level_ptrs[lvl]];
       let f_largest_uk = ikey_user_key(&f.largest);
-      if cmp.compare(user_key, f_largest_uk).is_le() {
+      // Timestamps are ignored: any version of the key below counts.
+      if cmp
+        .compare_without_timestamp(user_key, true, f_largest_uk, true)
+        .is_le()
+      {
         // user_key is ≤ this file's largest — it may be inside this file.
-        if cmp.compare(user_key, ikey_user_key(&f.smallest)).is_ge() {
+        if cmp
+          .compare_without_timestamp(user_key, true, ikey_user_key(&f.smallest), true)
+          .is_ge()
+        {
           return false; // user_key falls within this file's range
         }
         break; // user_key is before this file; no later file at this level can match
@@ -392,73 +607,123 @@ fn is_base_level_for_key(
   true
 }
 
-/// True if `version` has a level that needs compaction (score ≥ 1.0), or if a
-/// seek-based compaction candidate has been nominated.
+/// Range-tombstone counterpart of [`is_base_level_for_key`]: `true` if no
+/// level below `spec.output_level` holds a file overlapping `[start, end]`.
+/// The check treats `end` inclusively, which is conservative.
+fn is_base_level_for_range(spec: &Compaction, start: &[u8], end: &[u8]) -> bool {
+  spec.output_level > 0
+    && ((spec.output_level + 1)..spec.input_version.num_levels())
+      .all(|lvl| !spec.input_version.overlaps_level(lvl, start, end))
+}
+
+/// True if `version` has a level that needs compaction (score ≥ 1.0), if a
+/// seek-based compaction candidate has been nominated, or if blob garbage
+/// collection must be forced.  With [`CompactionStyle::Universal`] or
+/// [`CompactionStyle::Fifo`], true if [`pick_universal_compaction`] or
+/// [`pick_fifo_compaction`] would pick something.
 pub(crate) fn needs_compaction(
   version: &crate::db::version::Version,
   compaction_needed: bool,
+  opts: &Options,
 ) -> bool {
-  version.compaction_score >= 1.0 || compaction_needed
+  match opts.compaction_style {
+    CompactionStyle::Level => {}
+    CompactionStyle::Universal => return needs_universal_compaction(version, opts),
+    CompactionStyle::Fifo => {
+      return needs_fifo_compaction(version, opts, opts.clock.current_time())
+    }
+  }
+  version.compaction_score >= 1.0
+    || compaction_needed
+    || forced_blob_gc_file(version, opts).is_some()
+}
+
+/// Number of the first blob file too young for garbage collection: references
+/// to older blob files are relocated by compaction.  `0` (nothing is
+/// collected) unless `enable_blob_garbage_collection` is set.
+///
+/// The oldest `blob_garbage_collection_age_cutoff` fraction of the blob files
+/// is collected.
+fn blob_gc_cutoff(version: &crate::db::version::Version, opts: &Options) -> u64 {
+  if !opts.enable_blob_garbage_collection {
+    return 0;
+  }
+  let blob_files = version.blob_files();
+  let collected = (blob_files.len() as f64 * opts.blob_garbage_collection_age_cutoff) as usize;
+  blob_files
+    .keys()
+    .nth(collected)
+    .copied()
+    .unwrap_or(u64::MAX)
+}
+
+/// The SSTable (and its level) to compact to force blob garbage collection:
+/// the first whose oldest referenced blob file is old enough to be collected
+/// and whose garbage is at least `blob_garbage_collection_force_threshold` of
+/// its bytes.
+///
+/// See `db/version_set.cc: VersionStorageInfo::ComputeFilesMarkedForForcedBlobGC`
+/// in RocksDB, which weighs batches of blob files rather than single ones.
+fn forced_blob_gc_file(
+  version: &crate::db::version::Version,
+  opts: &Options,
+) -> Option<(Arc<FileMetaData>, usize)> {
+  let cutoff = blob_gc_cutoff(version, opts);
+  let threshold = opts.blob_garbage_collection_force_threshold;
+  let eligible: std::collections::HashSet<u64> = version
+    .blob_files()
+    .range(..cutoff)
+    .filter(|(_, b)| {
+      b.total_blob_bytes > 0 && b.garbage_blob_bytes as f64 >= threshold * b.total_blob_bytes as f64
+    })
+    .map(|(&number, _)| number)
+    .collect();
+  if eligible.is_empty() {
+    return None;
+  }
+  (0..version.num_levels()).find_map(|level| {
+    version
+      .files_at(level)
+      .iter()
+      .find(|f| eligible.contains(&f.oldest_blob_file_number))
+      .map(|f| (Arc::clone(f), level))
+  })
 }
 
 /// Select the next compaction to run, based on level scores, compact-pointer
-/// round-robin, or seek-based nomination.
+/// round-robin, seek-based nomination, or forced blob garbage collection.
 ///
-/// Returns `None` if no compaction is needed.
+/// Candidates that conflict with one of the `running` compactions are
+/// skipped: levels are tried from the highest score down, and within a level
+/// the files from the compact pointer onwards, wrapping around.
+///
+/// Returns `None` if no compaction is needed or every candidate conflicts.
+///
+/// With [`CompactionStyle::Universal`] or [`CompactionStyle::Fifo`], defers
+/// to [`pick_universal_compaction`] or [`pick_fifo_compaction`].
 pub(crate) fn pick_compaction(
   version: &Arc<crate::db::version::Version>,
-  compact_pointer: &[Vec<u8>; crate::db::version::NUM_LEVELS],
+  compact_pointer: &[Vec<u8>],
   opts: &Options,
   seek_compact: Option<&(Arc<FileMetaData>, usize)>,
+  running: &[CompactionClaim],
 ) -> Option<Compaction> {
+  match opts.compaction_style {
+    CompactionStyle::Level => {}
+    CompactionStyle::Universal => return pick_universal_compaction(version, opts, running),
+    CompactionStyle::Fifo => {
+      return pick_fifo_compaction(version, opts, running, opts.clock.current_time())
+    }
+  }
   let cmp = &*opts.comparator;
   // ── Size-triggered (highest priority) ─────────────────────────────────────
-  if version.compaction_score >= 1.0 {
-    let level = version.compaction_level as usize;
-    let mut spec = Compaction::new(level, Arc::clone(version));
-
-    if level == 0 {
-      // For L0, seed with the first file past compact_pointer[0], then expand
-      // to all overlapping L0 files (L0 files can overlap each other).
-      let seed = version
-        .files_at(0)
-        .iter()
-        .find(|f| {
-          compact_pointer[0].is_empty()
-            || cmp
-              .compare(
-                ikey_user_key(&f.largest),
-                ikey_user_key(&compact_pointer[0]),
-              )
-              .is_gt()
-        })
-        .or_else(|| version.files_at(0).first())
-        .cloned()?;
-
-      let seed_lo = ikey_user_key(&seed.smallest).to_vec();
-      let seed_hi = ikey_user_key(&seed.largest).to_vec();
-      spec.inputs[0] = version.overlapping_inputs(0, &seed_lo, &seed_hi);
-    } else {
-      // L1+: pick the first file whose largest key is past compact_pointer[level].
-      let file = version
-        .files_at(level)
-        .iter()
-        .find(|f| {
-          compact_pointer[level].is_empty()
-            || cmp
-              .compare(
-                ikey_user_key(&f.largest),
-                ikey_user_key(&compact_pointer[level]),
-              )
-              .is_gt()
-        })
-        .or_else(|| version.files_at(level).first())
-        .cloned()?;
-      spec.inputs[0].push(file);
+  for &(level, score) in &version.compaction_scores {
+    if score < 1.0 {
+      break;
+    }
+    if let Some(spec) = pick_size_compaction(version, level, compact_pointer, opts, running) {
+      return Some(spec);
     }
-
-    setup_other_inputs(&mut spec, version, compact_pointer, opts);
-    return Some(spec);
   }
 
   // ── Seek-triggered fallback ────────────────────────────────────────────────
@@ -466,15 +731,93 @@ pub(crate) fn pick_compaction(
     let mut spec = Compaction::new(*level, Arc::clone(version));
     spec.inputs[0].push(Arc::clone(file));
     setup_other_inputs(&mut spec, version, compact_pointer, opts);
-    return Some(spec);
+    if !conflicts_with_running(&spec, running, cmp) {
+      return Some(spec);
+    }
   }
 
+  // ── Forced blob garbage collection ─────────────────────────────────────────
+  if let Some((file, level)) = forced_blob_gc_file(version, opts) {
+    let mut spec = Compaction::new(level, Arc::clone(version));
+    spec.forced_blob_gc = true;
+    if level == 0 {
+      // Older overlapping L0 files must move down with the picked one.
+      let lo = ikey_user_key(&file.smallest);
+      let hi = ikey_user_key(&file.largest);
+      spec.inputs[0] = version.overlapping_inputs(0, lo, hi);
+    } else {
+      spec.inputs[0].push(file);
+    }
+    if level + 1 < version.num_levels() {
+      setup_other_inputs(&mut spec, version, compact_pointer, opts);
+    } else {
+      // The last level is rewritten in place.
+      spec.output_level = level;
+    }
+    if !conflicts_with_running(&spec, running, cmp) {
+      return Some(spec);
+    }
+  }
+
+  None
+}
+
+/// Size-triggered compaction of `level`, seeded with the first file past
+/// `compact_pointer[level]` that yields a compaction not conflicting with the
+/// `running` ones.
+fn pick_size_compaction(
+  version: &Arc<crate::db::version::Version>,
+  level: usize,
+  compact_pointer: &[Vec<u8>],
+  opts: &Options,
+  running: &[CompactionClaim],
+) -> Option<Compaction> {
+  let cmp = &*opts.comparator;
+  if level == 0 && running.iter().any(|c| c.level == 0) {
+    return None;
+  }
+  let files = version.files_at(level);
+  let start = files
+    .iter()
+    .position(|f| {
+      compact_pointer[level].is_empty()
+        || cmp
+          .compare(
+            ikey_user_key(&f.largest),
+            ikey_user_key(&compact_pointer[level]),
+          )
+          .is_gt()
+    })
+    .unwrap_or(0);
+  for i in 0..files.len() {
+    let seed = &files[(start + i) % files.len()];
+    if running.iter().any(|c| c.files.contains(&seed.number)) {
+      continue;
+    }
+    let mut spec = Compaction::new(level, Arc::clone(version));
+    if level == 0 {
+      // L0 files can overlap each other: expand the seed to every L0 file
+      // overlapping it.
+      let seed_lo = ikey_user_key(&seed.smallest);
+      let seed_hi = ikey_user_key(&seed.largest);
+      spec.inputs[0] = version.overlapping_inputs(0, seed_lo, seed_hi);
+    } else {
+      spec.inputs[0].push(Arc::clone(seed));
+    }
+    setup_other_inputs(&mut spec, version, compact_pointer, opts);
+    if !conflicts_with_running(&spec, running, cmp) {
+      return Some(spec);
+    }
+  }
   None
 }
 /// Alias for a plain (level_inputs, next_level_inputs) pair returned by
 /// `pick_range_compaction`.  Used only internally by `compact_level_range`.
 type CompactionInputs = (Vec<Arc<FileMetaData>>, Vec<Arc<FileMetaData>>);
 
+/// An owned internal key and its value.
+type InternalEntry = (Vec<u8>, Vec<u8>);
+
 /// A completed compaction output SSTable.
 pub(crate) struct CompactionOutput {
   pub(crate) file_number: u64,
@@ -482,6 +825,8 @@ pub(crate) struct CompactionOutput {
   pub(crate) smallest: Vec<u8>,
   pub(crate) largest: Vec<u8>,
   pub(crate) table: Arc<Table>,
+  /// Oldest blob file the output references (`0` = none).
+  pub(crate) oldest_blob_file_number: u64,
 }
 
 /// In-progress output SSTable being built during compaction.
@@ -490,6 +835,7 @@ struct CompactionOutputFile {
   path: std::path::PathBuf,
   builder: TableBuilder,
   smallest: Vec<u8>,
+  oldest_blob_file_number: u64,
 }
 
 /// Finalise `cur`, open the file for reading, and push to `outputs`.
@@ -517,37 +863,476 @@ fn finish_compaction_output(
     smallest: cur.smallest,
     largest,
     table,
+    oldest_blob_file_number: cur.oldest_blob_file_number,
   });
   Ok(())
 }
 
+/// Output side of a compaction: appends surviving entries to the current
+/// output SSTable, rotating to a new file on size or grandparent overlap.
+///
+/// Range tombstones are clipped to each output's share of the key space:
+/// from the previous output's upper bound to the first user key of the next
+/// output.  Files therefore never split a user key, and their bounds (which
+/// include the clipped tombstones) stay disjoint.
+///
+/// Values are separated into blob files here too: inline values large enough
+/// for `blobs`, and the blobs of references to files below `blob_gc_cutoff`,
+/// which are read back and rewritten (or inlined if blob files are off).
+struct CompactionWriter<'a> {
+  path: &'a std::path::Path,
+  next_file_number: &'a mut dyn FnMut() -> u64,
+  opts: &'a Options,
+  compression: CompressionType,
+  current: Option<CompactionOutputFile>,
+  current_largest: Vec<u8>,
+  outputs: Vec<CompactionOutput>,
+  /// Range tombstones to carry into the outputs.
+  range_tombstones: Vec<RangeTombstone>,
+  /// User key where the previous output's share of the key space ended.
+  lower_bound: Option<Vec<u8>>,
+  /// Writes separated values, if blob files are enabled.
+  blobs: Option<BlobFileBuilder>,
+  /// Blob references read and written, per blob file.
+  blob_meter: BlobGarbageMeter,
+  /// Reads the blobs relocated by garbage collection (and, in `do_compaction`,
+  /// those a merge or compaction filter needs).
+  blob_fetcher: BlobFetcher,
+  /// References to blob files numbered below this are relocated.
+  blob_gc_cutoff: u64,
+}
+
+impl CompactionWriter<'_> {
+  /// Start a new output file whose first key is `smallest`.
+  fn open_output(&mut self, smallest: Vec<u8>) -> Result<(), Error> {
+    let opts = self.opts;
+    // The allocator briefly takes the DB lock.
+    let file_number = (self.next_file_number)();
+    let sst_path = self.path.join(format!("{file_number:06}.ldb"));
+    let file = opts.file_system.create_writable(&sst_path)?;
+    let builder = TableBuilder::new(
+      file,
+      opts.block_size,
+      opts.block_restart_interval,
+      opts.filter_policy.clone(),
+      self.compression,
+      Arc::clone(&opts.comparator),
+    );
+    self.current = Some(CompactionOutputFile {
+      file_number,
+      path: sst_path,
+      builder,
+      smallest,
+      oldest_blob_file_number: 0,
+    });
+    Ok(())
+  }
+
+  /// Close the current output file, giving it the range tombstones that fall
+  /// in `[lower_bound, upper)` (`None` = unbounded).  Opens a file for them
+  /// if no point entry did.
+  fn finish_current(&mut self, upper: Option<&[u8]>) -> Result<(), Error> {
+    let cmp = &*self.opts.comparator;
+    let lower = self.lower_bound.take();
+    let mut clipped = Vec::new();
+    for t in &self.range_tombstones {
+      let start = match &lower {
+        Some(l) if cmp.compare(&t.start, l).is_lt() => l.clone(),
+        _ => t.start.clone(),
+      };
+      let end = match upper {
+        Some(u) if cmp.compare(&t.end, u).is_gt() => u.to_vec(),
+        _ => t.end.clone(),
+      };
+      if cmp.compare(&start, &end).is_lt() {
+        clipped.push(RangeTombstone {
+          start,
+          end,
+          seq: t.seq,
+        });
+      }
+    }
+    self.lower_bound = upper.map(<[u8]>::to_vec);
+
+    if self.current.is_none() {
+      if clipped.is_empty() {
+        return Ok(());
+      }
+      self.open_output(Vec::new())?;
+    }
+    let mut finished = self.current.take().unwrap();
+    for t in clipped {
+      extend_file_bounds(&mut finished.smallest, &mut self.current_largest, &t, cmp);
+      finished.builder.add_range_tombstone(t);
+    }
+    finish_compaction_output(
+      finished,
+      std::mem::take(&mut self.current_largest),
+      &mut self.outputs,
+      self.opts.filter_policy.clone(),
+      self.opts.block_cache.clone(),
+      Arc::clone(&self.opts.comparator),
+      &*self.opts.file_system,
+    )
+  }
+
+  /// Move the value of `ikey` in or out of a blob file as needed, returning
+  /// the entry to write instead, or `None` to write it unchanged.
+  fn separate_blob(&mut self, ikey: &[u8], value: &[u8]) -> Result<Option<InternalEntry>, Error> {
+    let Some((uk, seq, vtype)) = parse_internal_key(ikey) else {
+      return Ok(None);
+    };
+    let mut relocated = None;
+    if vtype == VALUE_TYPE_BLOB_INDEX {
+      if BlobIndex::decode(value)?.file_number >= self.blob_gc_cutoff {
+        return Ok(None);
+      }
+      relocated = Some(self.blob_fetcher.fetch(uk, value)?);
+    } else if vtype != 1 {
+      return Ok(None);
+    }
+    if let Some(blobs) = &mut self.blobs {
+      let value = relocated.as_deref().unwrap_or(value);
+      if let Some(index) = blobs.add(uk, value, &mut *self.next_file_number)? {
+        let key = make_internal_key(uk, seq, VALUE_TYPE_BLOB_INDEX);
+        return Ok(Some((key, index.encode())));
+      }
+    }
+    Ok(relocated.map(|value| (make_internal_key(uk, seq, 1), value)))
+  }
+
+  /// Append `ikey` → `value`.  Keys must arrive in ascending internal-key order.
+  fn add(&mut self, spec: &mut Compaction, ikey: &[u8], value: &[u8]) -> Result<(), Error> {
+    let opts = self.opts;
+    let separated = self.separate_blob(ikey, value)?;
+    let (ikey, value) = match &separated {
+      Some((k, v)) => (k.as_slice(), v.as_slice()),
+      None => (ikey, value),
+    };
+
+    // Only rotate between user keys, so every version of a key (and every
+    // tombstone covering it) lands in the same output.
+    let new_user_key = self.current.is_some()
+      && opts
+        .comparator
+        .compare(ikey_user_key(ikey), ikey_user_key(&self.current_largest))
+        .is_ne();
+
+    // Close the current output file early if grandparent overlap is too high,
+    // or if it is at the size limit.  The former limits future compaction
+    // amplification (Gap 4).  A merge into L0 writes a single file.
+    if new_user_key
+      && spec.output_level > 0
+      && (should_stop_before(spec, ikey, opts)
+        || self.current.as_ref().unwrap().builder.file_size() >= opts.max_file_size as u64)
+    {
+      self.finish_current(Some(ikey_user_key(ikey)))?;
+    }
+
+    if self.current.is_none() {
+      self.open_output(ikey.to_vec())?;
+    }
+
+    let cur = self.current.as_mut().unwrap();
+    cur.builder.add(ikey, value)?;
+    if let Some((uk, _, VALUE_TYPE_BLOB_INDEX)) = parse_internal_key(ikey) {
+      let index = BlobIndex::decode(value)?;
+      self.blob_meter.process_out(uk, &index);
+      if cur.oldest_blob_file_number == 0 || index.file_number < cur.oldest_blob_file_number {
+        cur.oldest_blob_file_number = index.file_number;
+      }
+    }
+    self.current_largest.clear();
+    self.current_largest.extend_from_slice(ikey);
+    Ok(())
+  }
+
+  /// Finalise the last output file, whose share of the key space ends at
+  /// `upper`, and return every output produced, recording the blob files
+  /// written and the blob garbage left behind in `edit`.
+  fn finish(
+    mut self,
+    upper: Option<&[u8]>,
+    edit: &mut VersionEdit,
+  ) -> Result<Vec<CompactionOutput>, Error> {
+    self.finish_current(upper)?;
+    if let Some(blobs) = self.blobs {
+      edit.blob_file_additions = blobs.finish()?;
+    }
+    edit.blob_file_garbages = self.blob_meter.garbage();
+    Ok(self.outputs)
+  }
+}
+
+/// Outcome of folding a run of merge operands during compaction.
+enum MergeOutcome {
+  /// The operands were applied to a base (or to nothing, at the bottommost
+  /// level for the key): emit a single Value.
+  Value(Vec<u8>),
+  /// All operands were combined into one operand.
+  Operand(Vec<u8>),
+  /// The operands could not be combined; emit them unchanged.
+  Unchanged,
+}
+
+/// Fold `operands` (newest first, each with its internal key) that sit on top
+/// of `base`.
+///
+/// `base` is `Some(Some(v))` when a Value was reached, `Some(None)` when a
+/// Deletion was, and `None` when the operand run ended without a base.  In
+/// the last case a full merge is possible only when `bottommost` (no older
+/// data for the key exists below the output level); otherwise adjacent
+/// operands are combined with `partial_merge`.
+///
+/// See `db/merge_helper.cc: MergeHelper::MergeUntil`.
+fn fold_merge_operands(
+  opts: &Options,
+  user_key: &[u8],
+  operands: &[(Vec<u8>, Vec<u8>)],
+  base: Option<Option<Vec<u8>>>,
+  bottommost: bool,
+) -> Result<MergeOutcome, Error> {
+  let values: Vec<Vec<u8>> = operands.iter().map(|(_, v)| v.clone()).collect();
+  if base.is_some() || bottommost {
+    let existing = base.flatten();
+    return crate::merge_operator::full_merge(
+      opts.merge_operator.as_ref(),
+      user_key,
+      existing.as_deref(),
+      &values,
+    )
+    .map(MergeOutcome::Value);
+  }
+  let Some(operator) = opts.merge_operator.as_ref() else {
+    return Ok(MergeOutcome::Unchanged);
+  };
+  if operands.len() == 1 {
+    return Ok(MergeOutcome::Unchanged);
+  }
+  let mut newer_first = values.into_iter();
+  let mut acc = newer_first.next_back().unwrap();
+  for right in newer_first.rev() {
+    match operator.partial_merge(user_key, &acc, &right) {
+      Some(v) => acc = v,
+      None => return Ok(MergeOutcome::Unchanged),
+    }
+  }
+  Ok(MergeOutcome::Operand(acc))
+}
+
 /// Phase 2 (no lock): merge input files, apply pruning, write output SSTables.
 ///
 /// Shadow-key pruning: for each user key, only the first (newest) version
 /// encountered in the merge order is kept.
 /// Tombstone elision: a deletion marker is dropped when there is provably no
 /// data for that key at levels > `output_level`.
+/// Range deletion: entries covered by a range tombstone that every snapshot
+/// can see are dropped; the tombstones themselves are written to the outputs
+/// until they reach the bottommost level for their range.
+/// Merge folding: a run of merge operands that no snapshot can split is
+/// collapsed into one entry — a Value when its base is among the inputs (or
+/// the key has no older data below the output level), otherwise a single
+/// operand via `MergeOperator::partial_merge`.
+/// User-defined timestamps: the versions of a key older than the version's
+/// `full_history_ts_low` are pruned like versions of one user key, and a
+/// tombstone is only elided below that cutoff.
+/// Blob files: large values are separated, blobs in files older than the
+/// garbage-collection cutoff are relocated, and the blob files added and the
+/// garbage left in the old ones are recorded in `spec.edit`.
 ///
 /// L0 inputs are already newest-first in `spec.inputs[0]` (Version stores them
 /// that way), so `MergingIterator` resolves same-key ties in favour of the
 /// newer version via the lower child index.
 ///
-/// `next_file_number` allocates output file numbers.  Callers that hold shared
-/// state (e.g. `Db`) pass a closure that briefly takes their lock; this module
-/// itself never touches a lock, which is what keeps it executable against a
-/// bare `Version` in tests.
+/// Subcompactions: a compaction larger than `max_file_size` is split at
+/// [`subcompaction_boundaries`] into up to `max_subcompactions` key ranges,
+/// each merged on its own thread into its own output files.  The outputs are
+/// returned in key order, so they install as if written by one pass.
+///
+/// `next_file_number` allocates output file numbers, possibly from several
+/// threads at once.  Callers that hold shared state (e.g. `Db`) pass a
+/// closure that briefly takes their lock; this module itself never touches a
+/// lock, which is what keeps it executable against a bare `Version` in tests.
+///
+/// See `db/compaction/compaction_job.cc: CompactionJob::Run` in RocksDB.
 pub(crate) fn do_compaction(
+  path: &std::path::Path,
+  next_file_number: &(dyn Fn() -> u64 + Sync),
+  spec: &mut Compaction,
+  oldest_snapshot: u64,
+  opts: &Options,
+  tc: &crate::db::table_cache::TableCache,
+) -> Result<Vec<CompactionOutput>, Error> {
+  let boundaries = subcompaction_boundaries(spec, opts);
+  let level_bytes = total_file_size(&spec.inputs[0]);
+  let all_bytes: u64 = spec.all_input_files().map(|f| f.file_size).sum();
+  log::info!(
+    "compaction L{}→L{}: {} + {} files ({} + {} bytes) in {} subcompaction(s)",
+    spec.level,
+    spec.output_level,
+    spec.inputs[0].len(),
+    spec.all_input_files().count() - spec.inputs[0].len(),
+    level_bytes,
+    all_bytes - level_bytes,
+    boundaries.len() + 1,
+  );
+
+  let outputs = if boundaries.is_empty() {
+    let mut alloc = || next_file_number();
+    let mut edit = std::mem::replace(&mut spec.edit, VersionEdit::new());
+    let result = run_subcompaction(
+      path,
+      &mut alloc,
+      spec,
+      (None, None),
+      oldest_snapshot,
+      opts,
+      tc,
+      &mut edit,
+    );
+    spec.edit = edit;
+    result?
+  } else {
+    // Range `i` covers `[boundaries[i - 1], boundaries[i])`, unbounded at
+    // both ends.
+    let starts = std::iter::once(None).chain(boundaries.iter().map(|b| Some(b.as_slice())));
+    let ends = boundaries
+      .iter()
+      .map(|b| Some(b.as_slice()))
+      .chain(std::iter::once(None));
+    let results: Vec<_> = std::thread::scope(|scope| {
+      let workers: Vec<_> = starts
+        .zip(ends)
+        .map(|range| {
+          let mut sub = spec.for_subcompaction();
+          scope.spawn(move || {
+            let mut alloc = || next_file_number();
+            let mut edit = VersionEdit::new();
+            let outputs = run_subcompaction(
+              path,
+              &mut alloc,
+              &mut sub,
+              range,
+              oldest_snapshot,
+              opts,
+              tc,
+              &mut edit,
+            )?;
+            Ok::<_, Error>((outputs, edit))
+          })
+        })
+        .collect();
+      workers
+        .into_iter()
+        .map(|w| w.join().expect("subcompaction thread panicked"))
+        .collect()
+    });
+
+    let mut outputs = Vec::new();
+    let mut garbage = std::collections::BTreeMap::new();
+    for result in results {
+      let (sub_outputs, edit) = result?;
+      outputs.extend(sub_outputs);
+      spec
+        .edit
+        .blob_file_additions
+        .extend(edit.blob_file_additions);
+      for g in edit.blob_file_garbages {
+        let total = garbage.entry(g.blob_file_number).or_insert((0, 0));
+        total.0 += g.garbage_blob_count;
+        total.1 += g.garbage_blob_bytes;
+      }
+    }
+    spec.edit.blob_file_garbages = garbage
+      .into_iter()
+      .map(
+        |(blob_file_number, (garbage_blob_count, garbage_blob_bytes))| BlobFileGarbage {
+          blob_file_number,
+          garbage_blob_count,
+          garbage_blob_bytes,
+        },
+      )
+      .collect();
+    outputs
+  };
+
+  // Each output file was fsync'd by TableBuilder::finish (and each blob file
+  // by its writer); one directory sync persists all their directory entries
+  // before install_compaction records them in the MANIFEST and the inputs
+  // become deletable.
+  if !outputs.is_empty() || !spec.edit.blob_file_additions.is_empty() {
+    opts.file_system.sync_dir(path)?;
+  }
+
+  log::info!(
+    "compaction L{}→L{} complete: {} output files ({} bytes)",
+    spec.level,
+    spec.output_level,
+    outputs.len(),
+    outputs.iter().map(|o| o.file_size).sum::<u64>(),
+  );
+  Ok(outputs)
+}
+
+/// User keys at which to split `spec` into subcompactions: up to
+/// `max_subcompactions - 1` of them, and no more than one per `max_file_size`
+/// of input.  The candidates are the first keys of the input files, chosen so
+/// the input bytes starting in each range are roughly equal.  Empty (no
+/// split) with user-defined timestamps, whose collapsing of old versions
+/// spans user keys, and for a merge into L0, which writes a single file.
+///
+/// See `CompactionJob::GenSubcompactionBoundaries` in RocksDB, which weighs
+/// the ranges with `ApproximateSize` instead.
+fn subcompaction_boundaries(spec: &Compaction, opts: &Options) -> Vec<Vec<u8>> {
+  let cmp = &*opts.comparator;
+  let total: u64 = spec.all_input_files().map(|f| f.file_size).sum();
+  let ranges = (opts.max_subcompactions as u64).min(total / (opts.max_file_size as u64).max(1));
+  if ranges <= 1 || cmp.timestamp_size() > 0 || spec.output_level == 0 {
+    return Vec::new();
+  }
+  let mut anchors: Vec<(&[u8], u64)> = spec
+    .all_input_files()
+    .map(|f| (ikey_user_key(&f.smallest), f.file_size))
+    .collect();
+  anchors.sort_by(|a, b| cmp.compare(a.0, b.0));
+
+  let target = total / ranges;
+  let mut boundaries = Vec::new();
+  let mut bytes = 0;
+  for (i, &(key, size)) in anchors.iter().enumerate() {
+    if i > 0
+      && bytes >= target
+      && (boundaries.len() as u64) < ranges - 1
+      && cmp.compare(key, anchors[i - 1].0).is_gt()
+    {
+      boundaries.push(key.to_vec());
+      bytes = 0;
+    }
+    bytes += size;
+  }
+  boundaries
+}
+
+/// Merge the entries of `spec`'s inputs whose user keys fall in `range`
+/// (`[start, end)`, `None` = unbounded) into new output files, recording the
+/// blob files written and the blob garbage left behind in `edit`.
+#[allow(clippy::too_many_arguments)]
+fn run_subcompaction(
   path: &std::path::Path,
   next_file_number: &mut dyn FnMut() -> u64,
   spec: &mut Compaction,
+  range: (Option<&[u8]>, Option<&[u8]>),
   oldest_snapshot: u64,
   opts: &Options,
   tc: &crate::db::table_cache::TableCache,
+  edit: &mut VersionEdit,
 ) -> Result<Vec<CompactionOutput>, Error> {
   use crate::db::merge_iter::MergingIterator;
   use crate::iter::InternalIterator;
+  use crate::table::format::{MAX_SEQUENCE_NUMBER, VALUE_TYPE_FOR_SEEK, VALUE_TYPE_MERGE};
 
-  let output_level = spec.level + 1;
+  let output_level = spec.output_level;
+  let (start, end) = range;
 
   // Create a compaction filter for this run (if configured).
   let mut compaction_filter: Option<Box<dyn crate::compaction_filter::CompactionFilter>> = opts
@@ -555,29 +1340,66 @@ pub(crate) fn do_compaction(
     .as_ref()
     .map(|f| f.create_compaction_filter());
 
-  log::info!(
-    "compaction L{}→L{}: {} + {} files ({} + {} bytes)",
-    spec.level,
-    spec.level + 1,
-    spec.inputs[0].len(),
-    spec.inputs[1].len(),
-    spec.inputs[0].iter().map(|f| f.file_size).sum::<u64>(),
-    spec.inputs[1].iter().map(|f| f.file_size).sum::<u64>(),
-  );
-
   let mut children: Vec<Box<dyn InternalIterator>> = Vec::new();
+  let mut tombstones = Vec::new();
   for meta in spec.all_input_files() {
     let table = tc.get_or_open(meta.number, meta.file_size)?;
+    tombstones.extend(table.range_tombstones().iter());
     // Compaction is a bulk scan — don't pollute the block cache.
     children.push(Box::new(table.new_iterator(opts.paranoid_checks, false)?));
   }
+  let range_del = FragmentedRangeTombstones::new(tombstones, Arc::clone(&opts.comparator));
+
+  // Tombstones are carried into the outputs (as fragments) unless no
+  // snapshot can see past them and nothing below the output level is in
+  // their range — at the bottommost level they have nothing left to delete.
+  let output_tombstones: Vec<RangeTombstone> = range_del
+    .iter()
+    .filter(|t| !(t.seq <= oldest_snapshot && is_base_level_for_range(spec, &t.start, &t.end)))
+    .collect();
 
   let mut merger = MergingIterator::new(children, Arc::clone(&opts.comparator));
-  merger.seek_to_first();
+  match start {
+    Some(start) => merger.seek(&make_internal_key(
+      start,
+      MAX_SEQUENCE_NUMBER,
+      VALUE_TYPE_FOR_SEEK,
+    )),
+    None => merger.seek_to_first(),
+  }
 
-  let mut outputs: Vec<CompactionOutput> = Vec::new();
-  let mut current: Option<CompactionOutputFile> = None;
-  let mut current_largest: Vec<u8> = Vec::new();
+  let mut writer = CompactionWriter {
+    path,
+    next_file_number,
+    opts,
+    compression: output_compression(spec, opts),
+    current: None,
+    current_largest: Vec::new(),
+    outputs: Vec::new(),
+    range_tombstones: output_tombstones,
+    lower_bound: start.map(<[u8]>::to_vec),
+    blobs: BlobFileBuilder::new(opts, path),
+    blob_meter: BlobGarbageMeter::default(),
+    blob_fetcher: BlobFetcher {
+      version: Arc::clone(&spec.input_version),
+      table_cache: tc.clone(),
+      verify_checksums: opts.paranoid_checks,
+    },
+    blob_gc_cutoff: blob_gc_cutoff(&spec.input_version, opts),
+  };
+
+  // With user-defined timestamps, versions older than the history cutoff
+  // are collapsed into the newest of them, as if they shared one user key.
+  let ts_size = opts.comparator.timestamp_size();
+  let ts_low = spec.input_version.full_history_ts_low.clone();
+  let below_ts_low = |uk: &[u8]| {
+    ts_low.as_ref().is_some_and(|low| {
+      opts
+        .comparator
+        .compare_timestamp(timestamp_of(uk, ts_size), low)
+        .is_lt()
+    })
+  };
 
   // Deduplication / tombstone-elision state.
   let mut current_user_key: Vec<u8> = Vec::new();
@@ -585,6 +1407,17 @@ pub(crate) fn do_compaction(
   let mut last_sequence_for_key: u64 = u64::MAX;
 
   while merger.valid() {
+    // The rest of the inputs belong to the next subcompaction.
+    if end.is_some_and(|end| {
+      opts
+        .comparator
+        .compare(ikey_user_key(merger.key()), end)
+        .is_ge()
+    }) {
+      break;
+    }
+    // Set when folding a merge run has already moved `merger` past it.
+    let mut advanced = false;
     // Zero-copy per entry: `ikey`/`value` borrow from the merging iterator
     // (values point into block memory) and stay valid until `merger.next()`
     // at the bottom of the loop.  The labeled block replaces `continue` so
@@ -594,17 +1427,32 @@ pub(crate) fn do_compaction(
     'entry: {
       let ikey = merger.key();
 
-      let (uk, seq, vtype) = match crate::table::format::parse_internal_key(ikey) {
+      let (uk, seq, vtype) = match parse_internal_key(ikey) {
         Some(parts) => parts,
         None => break 'entry, // corrupt key — skip silently
       };
+      // Every blob reference read is counted, so that those not written back
+      // become garbage of their blob file.
+      if vtype == VALUE_TYPE_BLOB_INDEX {
+        writer
+          .blob_meter
+          .process_in(uk, &BlobIndex::decode(merger.value())?);
+      }
 
       // Track first occurrence of this user key.
-      let first_occurrence = !has_current_user_key
-        || opts
+      let same_key = has_current_user_key
+        && (opts
           .comparator
           .compare(uk, current_user_key.as_slice())
-          .is_ne();
+          .is_eq()
+          || (ts_size > 0
+            && below_ts_low(uk)
+            && below_ts_low(&current_user_key)
+            && opts
+              .comparator
+              .compare_without_timestamp(uk, true, &current_user_key, true)
+              .is_eq()));
+      let first_occurrence = !same_key;
       if first_occurrence {
         current_user_key.clear();
         current_user_key.extend_from_slice(uk);
@@ -619,12 +1467,16 @@ pub(crate) fn do_compaction(
         true
       } else if vtype == 0
         && seq <= oldest_snapshot
+        && (ts_size == 0 || below_ts_low(uk))
         && is_base_level_for_key(spec, uk, &*opts.comparator)
       {
         // Tombstone that no snapshot can see below this level — safe to elide.
+        // With timestamps, an older version may still follow among the
+        // inputs unless the tombstone is below the history cutoff.
         true
       } else {
-        false
+        // Deleted by a range tombstone that every snapshot can see.
+        range_del.covers(uk, seq, oldest_snapshot)
       };
 
       last_sequence_for_key = seq;
@@ -633,111 +1485,117 @@ pub(crate) fn do_compaction(
         break 'entry;
       }
 
+      if vtype == VALUE_TYPE_MERGE && seq <= oldest_snapshot {
+        // Every older version of this key is also below the oldest snapshot,
+        // so no reader can observe the intermediate states: collect the run
+        // of operands down to its base and fold it into one entry.  Older
+        // entries are then dropped by `last_sequence_for_key`.
+        let mut operands = vec![(ikey.to_vec(), merger.value().to_vec())];
+        let mut base: Option<Option<Vec<u8>>> = None;
+        merger.next();
+        advanced = true;
+        while merger.valid() {
+          let Some((next_uk, next_seq, next_vtype)) = parse_internal_key(merger.key()) else {
+            break;
+          };
+          if opts
+            .comparator
+            .compare(next_uk, current_user_key.as_slice())
+            .is_ne()
+          {
+            break;
+          }
+          // A range-deleted entry ends the run like a point deletion.
+          if range_del.covers(next_uk, next_seq, oldest_snapshot) {
+            base = Some(None);
+            break;
+          }
+          match next_vtype {
+            0 => base = Some(None),
+            1 => base = Some(Some(merger.value().to_vec())),
+            VALUE_TYPE_BLOB_INDEX => {
+              base = Some(Some(writer.blob_fetcher.fetch(next_uk, merger.value())?));
+            }
+            VALUE_TYPE_MERGE => {
+              operands.push((merger.key().to_vec(), merger.value().to_vec()));
+              merger.next();
+              continue;
+            }
+            _ => {}
+          }
+          // Leave `merger` on the base entry; the drop rule discards it.
+          break;
+        }
+
+        let bottommost =
+          base.is_none() && is_base_level_for_key(spec, &current_user_key, &*opts.comparator);
+        match fold_merge_operands(opts, &current_user_key, &operands, base, bottommost)? {
+          MergeOutcome::Value(mut value) => {
+            if let Some(ref mut filter) = compaction_filter {
+              if first_occurrence {
+                match filter.filter(output_level, &current_user_key, &value, 1) {
+                  crate::compaction_filter::CompactionDecision::Keep => {}
+                  crate::compaction_filter::CompactionDecision::Remove => break 'entry,
+                  crate::compaction_filter::CompactionDecision::ChangeValue(new_val) => {
+                    value = new_val;
+                  }
+                }
+              }
+            }
+            let merged_key = make_internal_key(&current_user_key, seq, 1);
+            writer.add(spec, &merged_key, &value)?;
+          }
+          MergeOutcome::Operand(operand) => {
+            writer.add(spec, &operands[0].0, &operand)?;
+          }
+          MergeOutcome::Unchanged => {
+            for (k, v) in &operands {
+              writer.add(spec, k, v)?;
+            }
+          }
+        }
+        break 'entry;
+      }
+
       // Borrowed until the filter decides to rewrite it (the uncommon case).
+      let mut key: std::borrow::Cow<[u8]> = std::borrow::Cow::Borrowed(ikey);
       let mut value: std::borrow::Cow<[u8]> = std::borrow::Cow::Borrowed(merger.value());
 
       // Apply the compaction filter to the newest visible version of each key.
       // Entries still visible to a live snapshot are never filtered (seq > oldest_snapshot).
       if let Some(ref mut filter) = compaction_filter {
         if first_occurrence && seq <= oldest_snapshot {
-          match filter.filter(output_level, uk, &value, vtype) {
+          // The filter sees the value a blob index refers to, as a Value.
+          let (blob, filter_vtype) = if vtype == VALUE_TYPE_BLOB_INDEX {
+            (Some(writer.blob_fetcher.fetch(uk, &value)?), 1)
+          } else {
+            (None, vtype)
+          };
+          match filter.filter(
+            output_level,
+            uk,
+            blob.as_deref().unwrap_or(&value),
+            filter_vtype,
+          ) {
             crate::compaction_filter::CompactionDecision::Keep => {}
             crate::compaction_filter::CompactionDecision::Remove => break 'entry,
             crate::compaction_filter::CompactionDecision::ChangeValue(new_val) => {
+              key = std::borrow::Cow::Owned(make_internal_key(uk, seq, filter_vtype));
               value = std::borrow::Cow::Owned(new_val);
             }
           }
         }
       }
 
-      // Close the current output file early if grandparent overlap is too high.
-      // This limits future compaction amplification (Gap 4).
-      if current.is_some() && should_stop_before(spec, ikey, opts) {
-        let finished = current.take().unwrap();
-        finish_compaction_output(
-          finished,
-          std::mem::take(&mut current_largest),
-          &mut outputs,
-          opts.filter_policy.clone(),
-          opts.block_cache.clone(),
-          Arc::clone(&opts.comparator),
-          &*opts.file_system,
-        )?;
-      }
-
-      // Rotate to a new output file if the current one is at the size limit.
-      if let Some(ref cur) = current {
-        if cur.builder.file_size() >= opts.max_file_size as u64 {
-          let finished = current.take().unwrap();
-          finish_compaction_output(
-            finished,
-            std::mem::take(&mut current_largest),
-            &mut outputs,
-            opts.filter_policy.clone(),
-            opts.block_cache.clone(),
-            Arc::clone(&opts.comparator),
-            &*opts.file_system,
-          )?;
-        }
-      }
-
-      // Open a new output file (the allocator briefly takes the DB lock).
-      if current.is_none() {
-        let file_number = next_file_number();
-        let sst_path = path.join(format!("{file_number:06}.ldb"));
-        let file = opts.file_system.create_writable(&sst_path)?;
-        let builder = TableBuilder::new(
-          file,
-          opts.block_size,
-          opts.block_restart_interval,
-          opts.filter_policy.clone(),
-          opts.compression,
-          Arc::clone(&opts.comparator),
-        );
-        current = Some(CompactionOutputFile {
-          file_number,
-          path: sst_path,
-          builder,
-          smallest: ikey.to_vec(),
-        });
-      }
-
-      let cur = current.as_mut().unwrap();
-      cur.builder.add(ikey, &value)?;
-      current_largest.clear();
-      current_largest.extend_from_slice(ikey);
+      writer.add(spec, &key, &value)?;
+    }
+    if !advanced {
+      merger.next();
     }
-    merger.next();
   }
 
   // Finalise the last output file (if any).
-  if let Some(cur) = current {
-    finish_compaction_output(
-      cur,
-      current_largest,
-      &mut outputs,
-      opts.filter_policy.clone(),
-      opts.block_cache.clone(),
-      Arc::clone(&opts.comparator),
-      &*opts.file_system,
-    )?;
-  }
-
-  // Each output file was fsync'd by TableBuilder::finish; one directory sync
-  // persists all their directory entries before install_compaction records
-  // them in the MANIFEST and the inputs become deletable.
-  if !outputs.is_empty() {
-    opts.file_system.sync_dir(path)?;
-  }
-
-  log::info!(
-    "compaction L{}→L{} complete: {} output files ({} bytes)",
-    spec.level,
-    spec.level + 1,
-    outputs.len(),
-    outputs.iter().map(|o| o.file_size).sum::<u64>(),
-  );
-  Ok(outputs)
+  writer.finish(end, edit)
 }
 /// True if a file whose user-key range is `[file_small, file_large]` overlaps
 /// the user-key range `[begin, end]` (either bound may be `None` = open).
@@ -752,11 +1610,12 @@ pub(crate) fn file_overlaps_range(
     && end.is_none_or(|e| cmp.compare(file_small, e).is_le())
 }
 
-/// Select files for a range-based compaction of `level` → `level + 1`.
+/// Select files for a range-based compaction of `level` into
+/// [`output_level_for`] it.
 ///
 /// Returns `(level_inputs, next_level_inputs)` for files at `level` that
-/// overlap `[begin, end]` and the `level+1` files that overlap their union
-/// range.  Returns `None` if there is nothing to compact.
+/// overlap `[begin, end]` and the output-level files that overlap their
+/// union range.  Returns `None` if there is nothing to compact.
 pub(crate) fn pick_range_compaction(
   version: &crate::db::version::Version,
   level: usize,
@@ -764,9 +1623,8 @@ pub(crate) fn pick_range_compaction(
   end: Option<&[u8]>,
   cmp: &dyn crate::comparator::Comparator,
 ) -> Option<CompactionInputs> {
-  use crate::db::version::NUM_LEVELS;
-
-  if level + 1 >= NUM_LEVELS {
+  let output_level = output_level_for(version, level);
+  if output_level >= version.num_levels() {
     return None;
   }
 
@@ -804,7 +1662,7 @@ pub(crate) fn pick_range_compaction(
     .clone();
 
   let next_inputs: Vec<Arc<FileMetaData>> = version
-    .files_at(level + 1)
+    .files_at(output_level)
     .iter()
     .filter(|m| key_ranges_overlap(&m.smallest, &m.largest, &range_small, &range_large, cmp))
     .cloned()
@@ -813,13 +1671,16 @@ pub(crate) fn pick_range_compaction(
   Some((level_inputs, next_inputs))
 }
 /// Returns `true` when the compaction can be executed as a trivial move:
-/// a single file at `spec.level` with no `level+1` overlap and acceptable
+/// a single file at `spec.level` with no output-level overlap and acceptable
 /// grandparent overlap.  No data is read or written — only the MANIFEST is
 /// updated.
 ///
 /// Port of LevelDB `Compaction::IsTrivialMove`.
 pub(crate) fn is_trivial_move(spec: &Compaction, opts: &Options) -> bool {
-  spec.inputs[0].len() == 1
+  !spec.forced_blob_gc
+    && !spec.deletion_compaction
+    && spec.inputs[0].len() == 1
+    && spec.middle_inputs.is_empty()
     && spec.inputs[1].is_empty()
     && total_file_size(&spec.grandparents)
       <= max_grandparent_overlap_bytes(opts.max_file_size as u64)
@@ -846,7 +1707,10 @@ mod tests {
     let file_a = FileMetaData::new(10, 100, ikey_high.clone(), ikey_high.clone());
     let file_b = FileMetaData::new(11, 100, ikey_low.clone(), ikey_low.clone());
 
-    let mut v = Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
+    let mut v = Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
     // L1 sorted by smallest: file_a (seq=5) sorts BEFORE file_b (seq=2) because
     // higher seq means lower internal key value (tag sorts descending).
     v.push_file_for_test(1, Arc::clone(&file_a));
@@ -882,7 +1746,10 @@ mod tests {
       )
     };
 
-    let mut v = Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
+    let mut v = Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
     // One L1 file covering "a".."z"; no L2 files.
     v.push_file_for_test(1, make_meta(10, b"a", b"z"));
     let version = Arc::new(v);
@@ -938,7 +1805,10 @@ mod tests {
       )
     };
 
-    let mut v = Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
+    let mut v = Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
     v.push_file_for_test(1, make_meta(10, b"a", b"z"));
     v.push_file_for_test(2, make_meta(20, b"m", b"p")); // overlapping L2 file
     let version = Arc::new(v);
@@ -977,7 +1847,10 @@ mod tests {
       )
     };
 
-    let mut v = Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
+    let mut v = Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
     // L1 compaction inputs (level 1).
     v.push_file_for_test(1, make_meta(1, b"a", b"z"));
     // L3 = level+2 from L1: two non-overlapping files.
@@ -1020,7 +1893,10 @@ mod tests {
       )
     };
 
-    let v = Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
+    let v = Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
     let version = Arc::new(v);
 
     let opts = Options {
@@ -1053,4 +1929,196 @@ mod tests {
     // After triggering, overlapped_bytes is reset to 0.
     assert_eq!(spec.overlapped_bytes, 0);
   }
+
+  #[test]
+  fn forced_blob_gc_picks_table_of_garbage_heavy_blob_file() {
+    use crate::db::version::Version;
+    use crate::db::version_edit::{BlobFileAddition, BlobFileGarbage, BlobFileMetaData};
+    use std::collections::BTreeMap;
+
+    let blob = |number: u64, garbage_blob_bytes: u64| {
+      let mut meta = BlobFileMetaData::new(&BlobFileAddition {
+        blob_file_number: number,
+        total_blob_count: 10,
+        total_blob_bytes: 1000,
+      });
+      meta.add_garbage(&BlobFileGarbage {
+        blob_file_number: number,
+        garbage_blob_count: garbage_blob_bytes / 100,
+        garbage_blob_bytes,
+      });
+      (number, Arc::new(meta))
+    };
+    let table = |number: u64, oldest_blob: u64| {
+      FileMetaData::with_oldest_blob_file(
+        number,
+        4096,
+        make_internal_key(b"a", 10, VALUE_TYPE_BLOB_INDEX),
+        make_internal_key(b"z", 1, VALUE_TYPE_BLOB_INDEX),
+        oldest_blob,
+      )
+    };
+    let mut files = vec![Vec::new(); 7];
+    files[2].push(table(20, 6));
+    files[6].push(table(21, 5));
+    let version = Arc::new(Version::from_parts(
+      files,
+      BTreeMap::from([blob(5, 600), blob(6, 0)]),
+      Arc::new(crate::comparator::BytewiseComparator),
+    ));
+
+    // Only the older half of the blob files is old enough to be collected.
+    let opts = Options {
+      enable_blob_garbage_collection: true,
+      blob_garbage_collection_age_cutoff: 0.5,
+      blob_garbage_collection_force_threshold: 0.5,
+      ..Options::default()
+    };
+    assert!(needs_compaction(&version, false, &opts));
+    let compact_pointer = vec![Vec::new(); 7];
+    let spec = pick_compaction(&version, &compact_pointer, &opts, None, &[]).unwrap();
+    assert!(spec.forced_blob_gc);
+    assert_eq!(spec.inputs[0][0].number, 21);
+    // The last level is rewritten in place rather than trivially moved.
+    assert_eq!(spec.level, 6);
+    assert_eq!(spec.output_level, 6);
+    assert!(!is_trivial_move(&spec, &opts));
+
+    let lenient = Options {
+      blob_garbage_collection_force_threshold: 0.7,
+      ..opts.clone()
+    };
+    assert!(!needs_compaction(&version, false, &lenient));
+    let disabled = Options {
+      enable_blob_garbage_collection: false,
+      ..opts
+    };
+    assert!(!needs_compaction(&version, false, &disabled));
+  }
+
+  #[test]
+  fn pick_compaction_skips_candidates_conflicting_with_running() {
+    use crate::db::version::{finalize, Version};
+    use crate::table::format::make_internal_key;
+
+    let cmp = crate::comparator::BytewiseComparator;
+    let meta = |number: u64, small: &[u8], large: &[u8]| {
+      FileMetaData::new(
+        number,
+        8 * 1024 * 1024,
+        make_internal_key(small, 10, 1),
+        make_internal_key(large, 1, 1),
+      )
+    };
+    // 16 MiB at L1 (score 1.6), in two files far apart.
+    let mut v = Version::new(7, Arc::new(cmp));
+    v.push_file_for_test(1, meta(10, b"a", b"c"));
+    v.push_file_for_test(1, meta(11, b"m", b"p"));
+    let opts = Options::default();
+    finalize(&mut v, &opts);
+    let version = Arc::new(v);
+    let compact_pointer = vec![Vec::new(); 7];
+
+    let first = pick_compaction(&version, &compact_pointer, &opts, None, &[]).unwrap();
+    assert_eq!(first.inputs[0][0].number, 10);
+    let running = [CompactionClaim::new(1, &first, &cmp)];
+    let second = pick_compaction(&version, &compact_pointer, &opts, None, &running).unwrap();
+    assert_eq!(second.inputs[0][0].number, 11);
+    assert!(!conflicts_with_running(&second, &running, &cmp));
+
+    let running = [
+      CompactionClaim::new(1, &first, &cmp),
+      CompactionClaim::new(2, &second, &cmp),
+    ];
+    assert!(pick_compaction(&version, &compact_pointer, &opts, None, &running).is_none());
+    // New files overlapping a claim must stay above its output level, L2.
+    assert_eq!(placement_limit(&running, b"b", b"b", &cmp), 1);
+    assert_eq!(placement_limit(&running, b"e", b"f", &cmp), usize::MAX);
+  }
+
+  #[test]
+  fn subcompaction_boundaries_balance_input_bytes() {
+    use crate::db::version::Version;
+    use crate::table::format::make_internal_key;
+
+    let version = Arc::new(Version::new(
+      7,
+      Arc::new(crate::comparator::BytewiseComparator),
+    ));
+    let meta = |number: u64, small: &[u8], large: &[u8]| {
+      FileMetaData::new(
+        number,
+        1024,
+        make_internal_key(small, 10, 1),
+        make_internal_key(large, 1, 1),
+      )
+    };
+    let mut spec = Compaction::new(1, version);
+    spec.inputs[0] = vec![meta(1, b"a", b"d"), meta(2, b"e", b"h")];
+    spec.inputs[1] = vec![
+      meta(3, b"a", b"b"),
+      meta(4, b"c", b"f"),
+      meta(5, b"g", b"k"),
+      meta(6, b"m", b"p"),
+    ];
+    let opts = Options {
+      max_file_size: 1024,
+      max_subcompactions: 3,
+      ..Options::default()
+    };
+    // 6 KiB in three ranges of two files each, by first key: a a | c e | g m.
+    assert_eq!(
+      subcompaction_boundaries(&spec, &opts),
+      vec![b"c".to_vec(), b"g".to_vec()]
+    );
+
+    // No split when the inputs fit in one output file, or by default.
+    let small = Options {
+      max_file_size: 8 * 1024,
+      ..opts.clone()
+    };
+    assert!(subcompaction_boundaries(&spec, &small).is_empty());
+    assert!(subcompaction_boundaries(&spec, &Options::default()).is_empty());
+  }
+
+  #[test]
+  fn output_compression_follows_level_and_bottommost_settings() {
+    use crate::db::version::Version;
+    use crate::table::format::make_internal_key;
+    use CompressionType::{NoCompression, Snappy, Zstd};
+
+    let mut opts = Options::default();
+    assert_eq!(compression_for_level(&opts, 3, 1, true), Snappy);
+
+    opts.compression_per_level = vec![NoCompression, NoCompression, Snappy, Zstd(3)];
+    let per_level: Vec<_> = (0..7)
+      .map(|level| compression_for_level(&opts, level, 1, false))
+      .collect();
+    assert_eq!(
+      per_level,
+      vec![
+        NoCompression,
+        NoCompression,
+        Snappy,
+        Zstd(3),
+        Zstd(3),
+        Zstd(3),
+        Zstd(3)
+      ]
+    );
+    // Entry 1 is for the base level, wherever it is.
+    assert_eq!(compression_for_level(&opts, 4, 4, false), NoCompression);
+    assert_eq!(compression_for_level(&opts, 5, 4, false), Snappy);
+
+    // Bottommost: nothing below the output level, L3 and deeper here.
+    opts.bottommost_compression = Some(Zstd(19));
+    let mut v = Version::new(7, Arc::new(crate::comparator::BytewiseComparator));
+    let ikey = make_internal_key(b"k", 1, 1);
+    v.push_file_for_test(3, FileMetaData::new(1, 100, ikey.clone(), ikey));
+    let v = Arc::new(v);
+    let spec = |level| Compaction::new(level, Arc::clone(&v));
+    assert_eq!(output_compression(&spec(0), &opts), NoCompression);
+    assert_eq!(output_compression(&spec(1), &opts), Snappy);
+    assert_eq!(output_compression(&spec(2), &opts), Zstd(19));
+  }
 }
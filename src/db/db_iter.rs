@@ -11,9 +11,14 @@
 //    limitations under the License.
 
 use crate::comparator::Comparator;
+use crate::db::blob::{BlobFetcher, VALUE_TYPE_BLOB_INDEX};
+use crate::db::range_del::FragmentedRangeTombstones;
 use crate::error::Error;
 use crate::iter::InternalIterator;
-use crate::table::format::{make_internal_key, parse_internal_key};
+use crate::merge_operator::MergeOperator;
+use crate::table::format::{
+  make_internal_key, parse_internal_key, VALUE_TYPE_FOR_SEEK, VALUE_TYPE_MERGE,
+};
 use std::sync::Arc;
 
 /// Direction of the last positioning or movement operation.
@@ -32,6 +37,12 @@ enum Direction {
 ///   that key, including keys written earlier within the same sequence range.
 /// - **Version merging**: only the newest visible version of each user key is
 ///   returned; older versions are silently skipped.
+/// - **Merge operands**: a key whose newest visible entry is a merge operand
+///   is presented with the operator applied to its operand chain.
+/// - **Range tombstones**: an entry covered by a visible range tombstone
+///   newer than it is treated exactly like a deletion marker.
+/// - **Blob indexes**: a value stored in a blob file is read through the
+///   [`BlobFetcher`] and presented in place of its index.
 ///
 /// Supports both forward (`seek_to_first`, `seek`, `next`) and backward
 /// (`seek_to_last`, `prev`) iteration.  Switching directions is handled
@@ -39,11 +50,15 @@ enum Direction {
 ///
 /// **Direction semantics** (matching LevelDB's `DBIter`):
 /// - Forward: `iter` is positioned at the current entry; `key()` / `value()`
-///   borrow directly from `iter` without copying.
+///   borrow directly from `iter` without copying.  The exception is a merged
+///   entry (`merged == true`): `iter` has already been advanced past the
+///   operands, and `key()` / `value()` return `saved_key` / `saved_value`.
+///   A value read from a blob file (`blob_value == true`) is likewise held in
+///   `saved_value`, while `key()` still borrows from `iter`.
 /// - Reverse: `iter` is positioned at the entry *just before* the current one
 ///   in key order; `key()` / `value()` return `saved_key` / `saved_value`.
 ///
-/// See `db/db_iter.h/cc` in LevelDB.
+/// See `db/db_iter.h/cc` in LevelDB and RocksDB.
 ///
 /// [`MergingIterator`]: crate::db::merge_iter::MergingIterator
 pub(crate) struct DbIterator {
@@ -51,18 +66,32 @@ pub(crate) struct DbIterator {
   iter: Box<dyn InternalIterator>,
   /// Snapshot sequence number: only entries with `seq <= sequence` are visible.
   sequence: u64,
-  /// Sticky error from a corrupt internal key.
+  /// Sticky error from a corrupt internal key or a failed merge.
   status: Option<Error>,
   /// Whether the iterator is positioned at a valid user entry.
   valid: bool,
   /// Current traversal direction.
   direction: Direction,
-  /// In Reverse direction: user key of the current entry.
+  /// In Reverse direction (or for a merged Forward entry): user key of the
+  /// current entry.
   saved_key: Vec<u8>,
-  /// In Reverse direction: value bytes of the current entry.
+  /// In Reverse direction (or for a merged Forward entry): value bytes of the
+  /// current entry.
   saved_value: Vec<u8>,
+  /// Forward direction only: the current entry was produced by applying the
+  /// merge operator, so it lives in `saved_key` / `saved_value`.
+  merged: bool,
+  /// Forward direction only: the current entry is a blob index whose value
+  /// was read into `saved_value`.
+  blob_value: bool,
   /// Comparator for user-key ordering.
   comparator: Arc<dyn Comparator>,
+  /// Operator used to resolve merge operands; `None` turns them into errors.
+  merge_operator: Option<Arc<dyn MergeOperator>>,
+  /// Range tombstones from every source the internal iterator merges.
+  range_del: FragmentedRangeTombstones,
+  /// Reads the values of blob indexes; `None` turns them into errors.
+  blob_fetcher: Option<BlobFetcher>,
 }
 
 impl DbIterator {
@@ -70,6 +99,9 @@ impl DbIterator {
     iter: Box<dyn InternalIterator>,
     sequence: u64,
     comparator: Arc<dyn Comparator>,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
+    range_del: FragmentedRangeTombstones,
+    blob_fetcher: Option<BlobFetcher>,
   ) -> Self {
     DbIterator {
       iter,
@@ -79,7 +111,39 @@ impl DbIterator {
       direction: Direction::Forward,
       saved_key: Vec::new(),
       saved_value: Vec::new(),
+      merged: false,
+      blob_value: false,
       comparator,
+      merge_operator,
+      range_del,
+      blob_fetcher,
+    }
+  }
+
+  /// Operator used to resolve merge operands, if any.
+  pub(crate) fn merge_operator(&self) -> Option<&Arc<dyn MergeOperator>> {
+    self.merge_operator.as_ref()
+  }
+
+  /// True if the entry `user_key @ seq` is deleted by a range tombstone
+  /// visible at the snapshot.
+  fn is_covered(&self, user_key: &[u8], seq: u64) -> bool {
+    self.range_del.covers(user_key, seq, self.sequence)
+  }
+
+  /// Order of two user keys, ignoring user-defined timestamps: the versions
+  /// of a key with different timestamps are one entry to the iterator.
+  fn key_cmp(&self, a: &[u8], b: &[u8]) -> std::cmp::Ordering {
+    self.comparator.compare_without_timestamp(a, true, b, true)
+  }
+
+  /// The value the blob index `index` stored under `user_key` refers to.
+  fn fetch_blob(&self, user_key: &[u8], index: &[u8]) -> Result<Vec<u8>, Error> {
+    match &self.blob_fetcher {
+      Some(fetcher) => fetcher.fetch(user_key, index),
+      None => Err(Error::Corruption(
+        "blob index without a blob file reader".to_string(),
+      )),
     }
   }
 
@@ -95,6 +159,10 @@ impl DbIterator {
   ///   `user_key ≤ K` to be skipped (`skipping = true`, `skip = K`).
   /// - The first `Value` entry with `user_key > skip` (or any entry when
   ///   `skipping = false`) sets `valid = true` and returns.
+  /// - The first such `Merge` entry is resolved by
+  ///   [`merge_values_new_to_old`](Self::merge_values_new_to_old).
+  /// - The first such blob index is read into `saved_value`
+  ///   (`blob_value = true`).
   ///
   /// On exhaustion `valid` is set to `false`.
   ///
@@ -102,6 +170,8 @@ impl DbIterator {
   /// entry it is used as scratch storage (matching LevelDB's idiom where
   /// `saved_key_` is passed as temporary storage for `FindNextUserEntry`).
   fn find_next_user_entry(&mut self, mut skipping: bool, skip: &mut Vec<u8>) {
+    self.merged = false;
+    self.blob_value = false;
     loop {
       if !self.iter.valid() {
         self.valid = false;
@@ -111,7 +181,8 @@ impl DbIterator {
       let ikey = self.iter.key();
       match parse_internal_key(ikey) {
         Some((user_key, seq, vtype)) if seq <= self.sequence => match vtype {
-          0 => {
+          // A range-deleted entry hides older versions just like a deletion.
+          _ if vtype == 0 || self.is_covered(user_key, seq) => {
             // Deletion: arrange to skip all upcoming entries for this key.
             skip.clear();
             skip.extend_from_slice(user_key);
@@ -119,11 +190,35 @@ impl DbIterator {
           }
           1 => {
             // Value: accept unless it is hidden by a prior tombstone.
-            if !skipping || self.comparator.compare(user_key, skip.as_slice()).is_gt() {
+            if !skipping || self.key_cmp(user_key, skip).is_gt() {
               self.valid = true;
               return;
             }
           }
+          VALUE_TYPE_BLOB_INDEX => {
+            // Blob index: accept like a value, reading it from its blob file.
+            if !skipping || self.key_cmp(user_key, skip).is_gt() {
+              match self.fetch_blob(user_key, self.iter.value()) {
+                Ok(v) => {
+                  self.saved_value = v;
+                  self.blob_value = true;
+                  self.valid = true;
+                }
+                Err(e) => {
+                  self.status = Some(e);
+                  self.valid = false;
+                }
+              }
+              return;
+            }
+          }
+          VALUE_TYPE_MERGE => {
+            // Merge operand: fold the key's operand chain into one value.
+            if !skipping || self.key_cmp(user_key, skip).is_gt() {
+              self.merge_values_new_to_old();
+              return;
+            }
+          }
           _ => {
             // Unknown value type — treat as corruption and record it.
             self.status = Some(Error::Corruption(format!(
@@ -145,6 +240,75 @@ impl DbIterator {
     }
   }
 
+  /// Resolve the merge operand at the current (Forward) position.
+  ///
+  /// Collects operands newest-first until a Value (the base), a Deletion, or a
+  /// different user key, then applies the merge operator.  On return `iter`
+  /// is positioned past the consumed operands and the result is held in
+  /// `saved_key` / `saved_value` with `merged = true`.  A merge failure is
+  /// recorded in `status` and invalidates the iterator.
+  ///
+  /// See `db/db_iter.cc: DBIter::MergeValuesNewToOld` in RocksDB.
+  fn merge_values_new_to_old(&mut self) {
+    let user_key = crate::table::format::user_key(self.iter.key());
+    self.saved_key.clear();
+    self.saved_key.extend_from_slice(user_key);
+    let mut operands = vec![self.iter.value().to_vec()];
+    let mut base: Option<Vec<u8>> = None;
+    self.iter.next();
+    while self.iter.valid() {
+      let Some((user_key, seq, vtype)) = parse_internal_key(self.iter.key()) else {
+        self.status = Some(Error::Corruption(
+          "corrupted internal key in DbIterator".to_string(),
+        ));
+        break;
+      };
+      if self.key_cmp(user_key, &self.saved_key).is_ne() {
+        break;
+      }
+      // Everything from a range-deleted entry down is gone: no base.
+      if self.is_covered(user_key, seq) {
+        break;
+      }
+      match vtype {
+        0 => break,
+        1 => {
+          base = Some(self.iter.value().to_vec());
+          break;
+        }
+        VALUE_TYPE_BLOB_INDEX => {
+          match self.fetch_blob(user_key, self.iter.value()) {
+            Ok(v) => base = Some(v),
+            Err(e) => {
+              self.status = Some(e);
+              self.valid = false;
+              return;
+            }
+          }
+          break;
+        }
+        _ => operands.push(self.iter.value().to_vec()),
+      }
+      self.iter.next();
+    }
+    match crate::merge_operator::full_merge(
+      self.merge_operator.as_ref(),
+      &self.saved_key,
+      base.as_deref(),
+      &operands,
+    ) {
+      Ok(v) => {
+        self.saved_value = v;
+        self.merged = true;
+        self.valid = true;
+      }
+      Err(e) => {
+        self.status = Some(e);
+        self.valid = false;
+      }
+    }
+  }
+
   /// Scan backward through the internal iterator to find the previous
   /// user-visible entry, storing it in `saved_key` / `saved_value`.
   ///
@@ -158,41 +322,79 @@ impl DbIterator {
   /// - If no valid entry exists, `self.valid = false` and direction is reset
   ///   to Forward (matching LevelDB).
   ///
+  /// Versions of one user key are visited oldest-first, so merge operands are
+  /// accumulated on top of the most recent base seen and applied once the
+  /// key is complete.  Likewise a blob index is only read once it is known
+  /// to be the entry presented (or the base of its operands).
+  ///
   /// See `db/db_iter.cc: DBIter::FindPrevUserEntry`.
   fn find_prev_user_entry(&mut self) {
     debug_assert_eq!(self.direction, Direction::Reverse);
-    // `value_type` tracks whether we have saved a Value entry in this call.
+    // `value_type` tracks the type of the newest entry saved in this call.
     // Starts as 0 (Deletion) so the break condition does not fire on the
     // first visible entry.
-    let mut value_type: u8 = 0; // 0 = Deletion, 1 = Value
+    let mut value_type: u8 = 0; // 0 = Deletion, 1 = Value, 2 = Merge
+
+    // Operands (oldest first) written on top of the saved entry, and whether
+    // `saved_value` holds a base value underneath them.
+    let mut operands: Vec<Vec<u8>> = Vec::new();
+    let mut has_base = false;
+    // Whether `saved_value` holds a blob index rather than the value itself.
+    let mut saved_blob = false;
 
     while self.iter.valid() {
       let ikey = self.iter.key();
       if let Some((user_key, seq, vtype)) = parse_internal_key(ikey) {
         if seq <= self.sequence {
+          // An entry covered by a range tombstone reads as a deletion.
+          let vtype = if vtype != 0 && self.is_covered(user_key, seq) {
+            0
+          } else {
+            vtype
+          };
+          let is_blob = vtype == VALUE_TYPE_BLOB_INDEX;
+          let vtype = if is_blob { 1 } else { vtype };
           // Stop when we encounter a value entry for a user key that is
           // strictly smaller than the one we saved (we've found our answer).
-          if value_type != 0
-            && self
-              .comparator
-              .compare(user_key, self.saved_key.as_slice())
-              .is_lt()
-          {
+          if value_type != 0 && self.key_cmp(user_key, &self.saved_key).is_lt() {
             break;
           }
-          value_type = vtype;
-          if vtype == 0 {
-            // Deletion marker: clear the saved entry (this key is deleted).
-            self.saved_key.clear();
-            self.saved_value.clear();
-          } else {
-            // Value: update saved entry with the most recent version seen.
-            let raw_value = self.iter.value();
-            self.saved_value.clear();
-            self.saved_value.extend_from_slice(raw_value);
-            self.saved_key.clear();
-            self.saved_key.extend_from_slice(user_key);
+          match vtype {
+            0 => {
+              // Deletion marker: clear the saved entry (this key is deleted).
+              self.saved_key.clear();
+              self.saved_value.clear();
+              operands.clear();
+              has_base = false;
+              saved_blob = false;
+            }
+            VALUE_TYPE_MERGE => {
+              // Operand: stack it on the entry saved so far for this key.
+              // After a deletion (or at the start) there is no base.
+              if value_type == 0 {
+                self.saved_key.clear();
+                self.saved_key.extend_from_slice(user_key);
+                self.saved_value.clear();
+                has_base = false;
+                saved_blob = false;
+              } else if value_type == 1 {
+                has_base = true;
+              }
+              operands.push(self.iter.value().to_vec());
+            }
+            _ => {
+              // Value: update saved entry with the most recent version seen.
+              let raw_value = self.iter.value();
+              self.saved_value.clear();
+              self.saved_value.extend_from_slice(raw_value);
+              self.saved_key.clear();
+              self.saved_key.extend_from_slice(user_key);
+              operands.clear();
+              has_base = false;
+              saved_blob = is_blob;
+            }
           }
+          value_type = vtype;
         }
         // seq > snapshot: invisible; fall through to iter.prev()
       } else {
@@ -203,6 +405,33 @@ impl DbIterator {
       self.iter.prev();
     }
 
+    if saved_blob && value_type != 0 {
+      match self.fetch_blob(&self.saved_key, &self.saved_value) {
+        Ok(v) => self.saved_value = v,
+        Err(e) => {
+          self.status = Some(e);
+          value_type = 0;
+        }
+      }
+    }
+
+    if value_type == VALUE_TYPE_MERGE {
+      operands.reverse();
+      let base = has_base.then_some(self.saved_value.as_slice());
+      match crate::merge_operator::full_merge(
+        self.merge_operator.as_ref(),
+        &self.saved_key,
+        base,
+        &operands,
+      ) {
+        Ok(v) => self.saved_value = v,
+        Err(e) => {
+          self.status = Some(e);
+          value_type = 0;
+        }
+      }
+    }
+
     if value_type == 0 {
       // Reached the beginning without finding a visible entry.
       self.valid = false;
@@ -238,6 +467,7 @@ impl DbIterator {
   /// `value()` return `saved_key` / `saved_value`.
   pub(crate) fn seek_to_last(&mut self) {
     self.direction = Direction::Reverse;
+    self.merged = false;
     self.saved_value.clear();
     self.iter.seek_to_last();
     self.find_prev_user_entry();
@@ -246,10 +476,10 @@ impl DbIterator {
   /// Position at the first user-visible entry with `user_key >= target`.
   pub(crate) fn seek(&mut self, user_key: &[u8]) {
     self.direction = Direction::Forward;
-    // Seek the internal iterator to `(user_key, snapshot_seq, Value)`, the
-    // largest internal key that sorts at or before any real entry for this
-    // user key at the snapshot.
-    let ikey = make_internal_key(user_key, self.sequence, 1);
+    // Seek the internal iterator to `(user_key, snapshot_seq, VALUE_TYPE_FOR_SEEK)`,
+    // the largest internal key that sorts at or before any real entry for
+    // this user key at the snapshot.
+    let ikey = make_internal_key(user_key, self.sequence, VALUE_TYPE_FOR_SEEK);
     self.iter.seek(&ikey);
     if self.iter.valid() {
       let mut skip = Vec::new();
@@ -281,6 +511,14 @@ impl DbIterator {
       }
       // saved_key already holds the key to skip (the entry we were presenting
       // before the direction switch).
+    } else if self.merged {
+      // Merged entry: `iter` is already past the operands and `saved_key`
+      // holds the key to skip.
+      if !self.iter.valid() {
+        self.valid = false;
+        self.merged = false;
+        return;
+      }
     } else {
       // Forward → Forward: stash the current user key as the skip target,
       // then advance.  Borrowed parse + amortised buffer — no allocation
@@ -297,10 +535,13 @@ impl DbIterator {
     }
 
     // Lend saved_key to find_next_user_entry as the skip buffer, then put it
-    // back so its capacity is reused across calls.
+    // back so its capacity is reused across calls — unless a merge landed its
+    // result in `saved_key` meanwhile.
     let mut skip = std::mem::take(&mut self.saved_key);
     self.find_next_user_entry(true, &mut skip);
-    self.saved_key = skip;
+    if !self.merged {
+      self.saved_key = skip;
+    }
   }
 
   /// Move to the previous user-visible entry.
@@ -312,15 +553,22 @@ impl DbIterator {
 
     if self.direction == Direction::Forward {
       // Switching from Forward to Reverse.
-      // `iter` is positioned AT the current entry.  Save the current user
-      // key, then scan backward until we see a different (smaller) user key.
-      debug_assert!(self.iter.valid());
-      self.saved_key.clear();
-      if let Some((uk, _, _)) = parse_internal_key(self.iter.key()) {
-        self.saved_key.extend_from_slice(uk);
+      // `iter` is positioned AT the current entry — or, for a merged entry,
+      // somewhere past it (possibly exhausted).  Save the current user key,
+      // then scan backward until we see a different (smaller) user key.
+      if self.merged {
+        self.merged = false;
+        if !self.iter.valid() {
+          self.iter.seek_to_last();
+        }
+      } else {
+        debug_assert!(self.iter.valid());
+        self.saved_key.clear();
+        if let Some((uk, _, _)) = parse_internal_key(self.iter.key()) {
+          self.saved_key.extend_from_slice(uk);
+        }
       }
       loop {
-        self.iter.prev();
         if !self.iter.valid() {
           self.valid = false;
           self.saved_key.clear();
@@ -328,14 +576,11 @@ impl DbIterator {
           return;
         }
         if let Some((uk, _, _)) = parse_internal_key(self.iter.key()) {
-          if self
-            .comparator
-            .compare(uk, self.saved_key.as_slice())
-            .is_lt()
-          {
+          if self.key_cmp(uk, &self.saved_key).is_lt() {
             break;
           }
         }
+        self.iter.prev();
       }
       self.direction = Direction::Reverse;
     }
@@ -346,10 +591,10 @@ impl DbIterator {
   /// Current user key.  Only valid when `valid()` is true.
   ///
   /// In Forward direction the slice borrows from the inner iterator (no copy).
-  /// In Reverse direction returns `saved_key`.
+  /// In Reverse direction (or for a merged entry) returns `saved_key`.
   pub(crate) fn key(&self) -> &[u8] {
     debug_assert!(self.valid);
-    if self.direction == Direction::Forward {
+    if self.direction == Direction::Forward && !self.merged {
       let ikey = self.iter.key();
       parse_internal_key(ikey)
         .map(|(uk, _, _)| uk)
@@ -362,7 +607,7 @@ impl DbIterator {
   /// Current value.  Only valid when `valid()` is true.
   pub(crate) fn value(&self) -> &[u8] {
     debug_assert!(self.valid);
-    if self.direction == Direction::Forward {
+    if self.direction == Direction::Forward && !self.merged && !self.blob_value {
       self.iter.value()
     } else {
       &self.saved_value
@@ -381,6 +626,7 @@ impl DbIterator {
 mod tests {
   use super::*;
   use crate::db::merge_iter::MergingIterator;
+  use crate::db::range_del::RangeTombstone;
   use crate::table::builder::TableBuilder;
   use crate::table::format::make_internal_key;
   use crate::table::reader::Table;
@@ -421,13 +667,44 @@ mod tests {
   }
 
   fn make_db_iter(children: Vec<Box<dyn InternalIterator>>, seq: u64) -> DbIterator {
+    make_db_iter_with_tombstones(children, seq, vec![])
+  }
+
+  fn make_db_iter_with_tombstones(
+    children: Vec<Box<dyn InternalIterator>>,
+    seq: u64,
+    tombstones: Vec<RangeTombstone>,
+  ) -> DbIterator {
     DbIterator::new(
       Box::new(MergingIterator::new(children, bytewise_cmp())),
       seq,
       bytewise_cmp(),
+      Some(Arc::new(ConcatOperator)),
+      FragmentedRangeTombstones::new(tombstones, bytewise_cmp()),
+      None,
     )
   }
 
+  /// Appends operands to the base value, separated by commas.
+  struct ConcatOperator;
+
+  impl MergeOperator for ConcatOperator {
+    fn full_merge(
+      &self,
+      _key: &[u8],
+      existing: Option<&[u8]>,
+      operands: &[&[u8]],
+    ) -> Option<Vec<u8>> {
+      let mut parts: Vec<&[u8]> = existing.into_iter().collect();
+      parts.extend_from_slice(operands);
+      Some(parts.join(&b","[..]))
+    }
+
+    fn name(&self) -> &str {
+      "test.ConcatOperator"
+    }
+  }
+
   // ── tests ──────────────────────────────────────────────────────────────────
 
   #[test]
@@ -690,4 +967,198 @@ mod tests {
     assert!(it.valid());
     assert_eq!(it.key(), b"c");
   }
+
+  // ── Merge operand tests ───────────────────────────────────────────────────
+
+  /// Collect every (key, value) pair walking forward from `seek_to_first`.
+  fn collect_forward(it: &mut DbIterator) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut out = Vec::new();
+    it.seek_to_first();
+    while it.valid() {
+      out.push((it.key().to_vec(), it.value().to_vec()));
+      it.next();
+    }
+    out
+  }
+
+  /// Collect every (key, value) pair walking backward from `seek_to_last`.
+  fn collect_backward(it: &mut DbIterator) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut out = Vec::new();
+    it.seek_to_last();
+    while it.valid() {
+      out.push((it.key().to_vec(), it.value().to_vec()));
+      it.prev();
+    }
+    out
+  }
+
+  fn merge_fixture() -> Vec<Box<dyn InternalIterator>> {
+    // a: base + two operands; b: operands over a tombstone; c: plain value;
+    // d: operands only.
+    vec![table_iter(&[
+      (b"a", 6, 2, b"a2"),
+      (b"a", 5, 2, b"a1"),
+      (b"a", 1, 1, b"a0"),
+      (b"b", 7, 2, b"b1"),
+      (b"b", 4, 0, b""),
+      (b"b", 2, 1, b"old"),
+      (b"c", 3, 1, b"c0"),
+      (b"d", 9, 2, b"d2"),
+      (b"d", 8, 2, b"d1"),
+    ])]
+  }
+
+  #[test]
+  fn merge_forward_applies_operands() {
+    let mut it = make_db_iter(merge_fixture(), 100);
+    assert_eq!(
+      collect_forward(&mut it),
+      vec![
+        (b"a".to_vec(), b"a0,a1,a2".to_vec()),
+        (b"b".to_vec(), b"b1".to_vec()),
+        (b"c".to_vec(), b"c0".to_vec()),
+        (b"d".to_vec(), b"d1,d2".to_vec()),
+      ]
+    );
+  }
+
+  #[test]
+  fn merge_backward_applies_operands() {
+    let mut it = make_db_iter(merge_fixture(), 100);
+    assert_eq!(
+      collect_backward(&mut it),
+      vec![
+        (b"d".to_vec(), b"d1,d2".to_vec()),
+        (b"c".to_vec(), b"c0".to_vec()),
+        (b"b".to_vec(), b"b1".to_vec()),
+        (b"a".to_vec(), b"a0,a1,a2".to_vec()),
+      ]
+    );
+  }
+
+  #[test]
+  fn merge_respects_snapshot() {
+    // At seq 5 only the first operand of "a" is visible; "b" still has its
+    // original value and "d" does not exist yet.
+    let mut it = make_db_iter(merge_fixture(), 5);
+    assert_eq!(
+      collect_forward(&mut it),
+      vec![
+        (b"a".to_vec(), b"a0,a1".to_vec()),
+        (b"c".to_vec(), b"c0".to_vec()),
+      ]
+    );
+  }
+
+  #[test]
+  fn merge_direction_switches() {
+    let mut it = make_db_iter(merge_fixture(), 100);
+    it.seek(b"d");
+    assert_eq!(it.value(), b"d1,d2");
+    // Merged entry at the end of the input: iter is exhausted.
+    it.prev();
+    assert_eq!(it.key(), b"c");
+    it.next();
+    assert_eq!(it.key(), b"d");
+    it.seek(b"a");
+    assert_eq!(it.value(), b"a0,a1,a2");
+    it.next();
+    assert_eq!(it.key(), b"b");
+    assert_eq!(it.value(), b"b1");
+    it.prev();
+    assert_eq!(it.key(), b"a");
+    assert_eq!(it.value(), b"a0,a1,a2");
+  }
+
+  #[test]
+  fn merge_without_operator_sets_status() {
+    let mut it = DbIterator::new(
+      Box::new(MergingIterator::new(merge_fixture(), bytewise_cmp())),
+      100,
+      bytewise_cmp(),
+      None,
+      FragmentedRangeTombstones::new(vec![], bytewise_cmp()),
+      None,
+    );
+    it.seek_to_first();
+    assert!(!it.valid());
+    assert!(matches!(it.status(), Some(Error::InvalidArgument(_))));
+  }
+
+  // ── Range tombstone tests ─────────────────────────────────────────────────
+
+  fn range_del_fixture() -> Vec<Box<dyn InternalIterator>> {
+    vec![table_iter(&[
+      (b"a", 1, 1, b"a1"),
+      (b"b", 2, 1, b"b2"),
+      (b"c", 8, 1, b"c8"),
+      (b"c", 3, 1, b"c3"),
+      (b"d", 9, 2, b"d9"),
+      (b"d", 4, 1, b"d4"),
+      (b"e", 5, 1, b"e5"),
+    ])]
+  }
+
+  /// `[b, e) @ 6`: hides b@2, c@3 and d@4 but not the newer c@8 / d@9, nor e.
+  fn range_del_tombstone() -> Vec<RangeTombstone> {
+    vec![RangeTombstone {
+      start: b"b".to_vec(),
+      end: b"e".to_vec(),
+      seq: 6,
+    }]
+  }
+
+  #[test]
+  fn range_tombstone_hides_covered_entries_forward() {
+    let mut it = make_db_iter_with_tombstones(range_del_fixture(), 100, range_del_tombstone());
+    assert_eq!(
+      collect_forward(&mut it),
+      vec![
+        (b"a".to_vec(), b"a1".to_vec()),
+        (b"c".to_vec(), b"c8".to_vec()),
+        // The covered base is gone, so the operand merges onto nothing.
+        (b"d".to_vec(), b"d9".to_vec()),
+        (b"e".to_vec(), b"e5".to_vec()),
+      ]
+    );
+  }
+
+  #[test]
+  fn range_tombstone_hides_covered_entries_backward() {
+    let mut it = make_db_iter_with_tombstones(range_del_fixture(), 100, range_del_tombstone());
+    assert_eq!(
+      collect_backward(&mut it),
+      vec![
+        (b"e".to_vec(), b"e5".to_vec()),
+        (b"d".to_vec(), b"d9".to_vec()),
+        (b"c".to_vec(), b"c8".to_vec()),
+        (b"a".to_vec(), b"a1".to_vec()),
+      ]
+    );
+  }
+
+  #[test]
+  fn range_tombstone_respects_snapshot() {
+    // At seq 5 the tombstone is not yet visible.
+    let mut it = make_db_iter_with_tombstones(range_del_fixture(), 5, range_del_tombstone());
+    assert_eq!(
+      collect_forward(&mut it),
+      vec![
+        (b"a".to_vec(), b"a1".to_vec()),
+        (b"b".to_vec(), b"b2".to_vec()),
+        (b"c".to_vec(), b"c3".to_vec()),
+        (b"d".to_vec(), b"d4".to_vec()),
+        (b"e".to_vec(), b"e5".to_vec()),
+      ]
+    );
+    // At seq 7 it hides everything in the range written before it.
+    let mut it = make_db_iter_with_tombstones(range_del_fixture(), 7, range_del_tombstone());
+    assert_eq!(
+      collect_forward(&mut it),
+      vec![
+        (b"a".to_vec(), b"a1".to_vec()),
+        (b"e".to_vec(), b"e5".to_vec()),
+      ]
+    );
+  }
 }
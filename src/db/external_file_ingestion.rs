@@ -0,0 +1,237 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Preparation of external SSTables for [`Db::ingest_external_file`].
+//!
+//! Ingestion runs in three steps:
+//!   1. [`inspect`] each file (no lock): check it was written by an
+//!      [`SstFileWriter`] and read its key range.
+//!   2. [`stage`] a copy of each file in the database directory (no lock);
+//!      all but the metaindex, index block and footer is copied here.
+//!   3. Under the lock, once the memtables no longer overlap the files, give
+//!      the copies one global sequence number, move them to their final
+//!      names and install them at the level [`pick_level`] chooses.
+//!
+//! See `db/external_sst_file_ingestion_job.cc` in RocksDB.
+//!
+//! [`Db::ingest_external_file`]: crate::Db::ingest_external_file
+//! [`SstFileWriter`]: crate::SstFileWriter
+
+use crate::comparator::Comparator;
+use crate::db::version::Version;
+use crate::error::Error;
+use crate::iter::InternalIterator;
+use crate::options::Options;
+use crate::table::builder::GlobalSeqnoWriter;
+use crate::table::format::{make_internal_key, parse_internal_key, user_key, VALUE_TYPE_MERGE};
+use crate::table::reader::Table;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An external SSTable that passed [`inspect`].
+pub(crate) struct ExternalFile {
+  pub(crate) path: PathBuf,
+  /// First and last internal keys, as stored: at sequence number 0.
+  pub(crate) smallest: Vec<u8>,
+  pub(crate) largest: Vec<u8>,
+}
+
+impl ExternalFile {
+  pub(crate) fn smallest_user_key(&self) -> &[u8] {
+    user_key(&self.smallest)
+  }
+
+  pub(crate) fn largest_user_key(&self) -> &[u8] {
+    user_key(&self.largest)
+  }
+}
+
+/// A copy of an [`ExternalFile`] in the database directory, waiting for its
+/// global sequence number.
+pub(crate) struct StagedFile {
+  pub(crate) file: ExternalFile,
+  pub(crate) path: PathBuf,
+  pub(crate) writer: GlobalSeqnoWriter,
+}
+
+/// Open the table at `path` and check that it can be ingested: every entry
+/// is a put, merge or deletion at sequence number 0, there are no range
+/// tombstones and no global sequence number yet, and the table is not
+/// empty.
+///
+/// See `ExternalSstFileIngestionJob::GetIngestedFileInfo`.
+pub(crate) fn inspect(path: &Path, opts: &Options) -> Result<ExternalFile, Error> {
+  let fs = &opts.file_system;
+  let size = fs.file_size(path)?;
+  let table = Table::open(
+    fs.open_random_access(path)?,
+    size,
+    None,
+    None,
+    Arc::clone(&opts.comparator),
+  )?;
+  let invalid = |reason: &str| {
+    Err(Error::InvalidArgument(format!(
+      "cannot ingest {}: {reason}",
+      path.display()
+    )))
+  };
+  if table.global_seqno().is_some() {
+    return invalid("the file was already ingested");
+  }
+  if !table.range_tombstones().is_empty() {
+    return invalid("range tombstones are not supported");
+  }
+
+  let mut it = table.new_iterator(true, false)?;
+  it.seek_to_first();
+  let mut smallest = None;
+  let mut largest = Vec::new();
+  while it.valid() {
+    match parse_internal_key(it.key()) {
+      Some((_, 0, vtype)) if vtype <= VALUE_TYPE_MERGE => {}
+      _ => return invalid("the file was not written by an SstFileWriter"),
+    }
+    if smallest.is_none() {
+      smallest = Some(it.key().to_vec());
+    }
+    largest.clear();
+    largest.extend_from_slice(it.key());
+    it.next();
+  }
+  if let Some(e) = it.status() {
+    return Err(e.clone());
+  }
+  let Some(smallest) = smallest else {
+    return invalid("the file is empty");
+  };
+  Ok(ExternalFile {
+    path: path.to_owned(),
+    smallest,
+    largest,
+  })
+}
+
+/// Sort `files` by smallest key and reject key ranges that overlap one
+/// another: all the files get the same sequence number, so an overlap would
+/// leave two versions of a key with nothing to order them.
+pub(crate) fn check_disjoint(
+  files: &mut [ExternalFile],
+  cmp: &dyn Comparator,
+) -> Result<(), Error> {
+  files.sort_by(|a, b| cmp.compare(a.smallest_user_key(), b.smallest_user_key()));
+  for pair in files.windows(2) {
+    if cmp
+      .compare(pair[0].largest_user_key(), pair[1].smallest_user_key())
+      .is_ge()
+    {
+      return Err(Error::InvalidArgument(format!(
+        "key ranges of {} and {} overlap",
+        pair[0].path.display(),
+        pair[1].path.display()
+      )));
+    }
+  }
+  Ok(())
+}
+
+/// Copy `file` to `staging_path` on `opts.file_system`, up to the blocks
+/// that depend on the global sequence number.
+pub(crate) fn stage(
+  file: ExternalFile,
+  staging_path: PathBuf,
+  opts: &Options,
+) -> Result<StagedFile, Error> {
+  let fs = &opts.file_system;
+  let src = fs.open_random_access(&file.path)?;
+  let size = fs.file_size(&file.path)?;
+  let writer = GlobalSeqnoWriter::new(&*src, size, fs.create_writable(&staging_path)?)?;
+  Ok(StagedFile {
+    file,
+    path: staging_path,
+    writer,
+  })
+}
+
+/// `ikey` with its sequence number replaced by `seq`.
+pub(crate) fn with_sequence(ikey: &[u8], seq: u64) -> Vec<u8> {
+  let (uk, _, vtype) = parse_internal_key(ikey).expect("checked by inspect");
+  make_internal_key(uk, seq, vtype)
+}
+
+/// The deepest level at which a file spanning `[smallest_uk, largest_uk]`
+/// can be placed: no level above it, nor the level itself, may hold an
+/// overlapping file, since those would hold older entries yet be read first.
+/// A file overlapping L0 goes to L0, where it sorts as the newest file.
+///
+/// See `ExternalSstFileIngestionJob::AssignLevelAndSeqnoForIngestedFile`.
+pub(crate) fn pick_level(version: &Version, smallest_uk: &[u8], largest_uk: &[u8]) -> usize {
+  let mut level = 0;
+  while level + 1 < version.num_levels() && !version.overlaps_level(level, smallest_uk, largest_uk)
+  {
+    if version.overlaps_level(level + 1, smallest_uk, largest_uk) {
+      break;
+    }
+    level += 1;
+  }
+  level
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::comparator::BytewiseComparator;
+  use crate::db::version_edit::FileMetaData;
+  use crate::table::format::VALUE_TYPE_VALUE;
+
+  fn file(number: u64, smallest: &[u8], largest: &[u8]) -> Arc<FileMetaData> {
+    FileMetaData::new(
+      number,
+      1000,
+      make_internal_key(smallest, 1, VALUE_TYPE_VALUE),
+      make_internal_key(largest, 1, VALUE_TYPE_VALUE),
+    )
+  }
+
+  #[test]
+  fn pick_level_stops_above_first_overlap() {
+    let mut v = Version::new(7, Arc::new(BytewiseComparator));
+    // Nothing overlaps: the bottommost level.
+    assert_eq!(pick_level(&v, b"a", b"c"), 6);
+
+    v.push_file_for_test(3, file(1, b"b", b"d"));
+    assert_eq!(pick_level(&v, b"a", b"c"), 2);
+    assert_eq!(pick_level(&v, b"e", b"f"), 6);
+
+    v.push_file_for_test(0, file(2, b"c", b"c"));
+    assert_eq!(pick_level(&v, b"a", b"c"), 0);
+  }
+
+  #[test]
+  fn check_disjoint_sorts_and_rejects_overlaps() {
+    let ext = |s: &[u8], l: &[u8]| ExternalFile {
+      path: PathBuf::from(String::from_utf8_lossy(s).into_owned()),
+      smallest: make_internal_key(s, 0, VALUE_TYPE_VALUE),
+      largest: make_internal_key(l, 0, VALUE_TYPE_VALUE),
+    };
+    let cmp = BytewiseComparator;
+    let mut files = vec![ext(b"m", b"p"), ext(b"a", b"c")];
+    check_disjoint(&mut files, &cmp).unwrap();
+    assert_eq!(files[0].smallest_user_key(), b"a");
+
+    let mut files = vec![ext(b"a", b"m"), ext(b"m", b"p")];
+    assert!(matches!(
+      check_disjoint(&mut files, &cmp),
+      Err(Error::InvalidArgument(_))
+    ));
+  }
+}
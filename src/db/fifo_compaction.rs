@@ -0,0 +1,246 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Compaction picking for [`CompactionStyle::Fifo`].
+//!
+//! Flushes stay in L0 and nothing is merged down.  Instead, the picker drops
+//! the oldest files, in order:
+//!
+//! 1. **Size**: while the SSTables add up to more than
+//!    `max_table_files_size`.
+//! 2. **TTL**: while the oldest file's oldest data is older than `ttl`.  A
+//!    file of unknown age, written before FIFO compaction was enabled, stops
+//!    the search until the size limit drops it.
+//!
+//! Dropping files is a *deletion compaction*: an edit deleting them from the
+//! version, installed under the lock without any I/O.  The files themselves
+//! are removed by `delete_obsolete_files` once no version uses them.
+//!
+//! Files are dropped strictly oldest first, so no entry they hide is ever
+//! uncovered.  Files below L0, left by another compaction style, are older
+//! than every L0 file and go first, deepest level first; one deletion
+//! compaction drops files of a single level.
+//!
+//! With `allow_compaction`, when nothing is to be dropped, the newest small
+//! L0 files are merged into one.  Only the newest are taken: L0 is read
+//! newest file number first, and the output is numbered after its inputs.
+//!
+//! FIFO compactions of a column family run one at a time.
+//!
+//! See `db/compaction/compaction_picker_fifo.cc` in RocksDB.
+//!
+//! [`CompactionStyle::Fifo`]: crate::CompactionStyle::Fifo
+
+use crate::db::compaction::{Compaction, CompactionClaim};
+use crate::db::version::Version;
+use crate::db::version_edit::FileMetaData;
+use crate::options::Options;
+use std::sync::Arc;
+
+/// The oldest files of `version` to drop, all at one level, or `None` if the
+/// files are within the size and age limits.
+fn files_to_drop(
+  version: &Version,
+  opts: &Options,
+  now: u64,
+) -> Option<(usize, Vec<Arc<FileMetaData>>)> {
+  let fopts = &opts.compaction_options_fifo;
+  let total: u64 = (0..version.num_levels())
+    .flat_map(|level| version.files_at(level))
+    .map(|f| f.file_size)
+    .sum();
+  let mut excess = total.saturating_sub(fopts.max_table_files_size);
+  let expired = |f: &FileMetaData| {
+    fopts.ttl.is_some_and(|ttl| {
+      f.oldest_ancestor_time != 0 && now.saturating_sub(f.oldest_ancestor_time) > ttl.as_secs()
+    })
+  };
+
+  let oldest_first = (1..version.num_levels())
+    .rev()
+    .flat_map(|level| version.files_at(level).iter().map(move |f| (level, f)))
+    .chain(version.files_at(0).iter().rev().map(|f| (0, f)));
+  let mut drop_level = None;
+  let mut files = Vec::new();
+  for (level, f) in oldest_first {
+    if (excess == 0 && !expired(f)) || drop_level.is_some_and(|l| l != level) {
+      break;
+    }
+    drop_level = Some(level);
+    excess = excess.saturating_sub(f.file_size);
+    files.push(Arc::clone(f));
+  }
+  let level = drop_level?;
+  log::debug!(
+    "fifo: dropping {} files from L{level}: {total} bytes in all",
+    files.len()
+  );
+  Some((level, files))
+}
+
+/// The newest L0 files to merge: those no larger than 1.1 × the write buffer
+/// size, if there are at least `level0_file_num_compaction_trigger` of them,
+/// and at least two.  Leaving out larger files keeps earlier merges from
+/// being merged again.
+fn files_to_merge(version: &Version, opts: &Options) -> Option<Vec<Arc<FileMetaData>>> {
+  if !opts.compaction_options_fifo.allow_compaction || opts.enable_blob_files {
+    return None;
+  }
+  let limit = opts.write_buffer_size as u64 * 11 / 10;
+  let l0 = version.files_at(0);
+  let small = l0.iter().take_while(|f| f.file_size <= limit).count();
+  let trigger = opts.level0_file_num_compaction_trigger.max(2);
+  (small >= trigger).then(|| l0[..small].to_vec())
+}
+
+/// True if [`pick_fifo_compaction`] would pick a compaction for `version` at
+/// time `now` (seconds since the Unix epoch) with nothing running.
+pub(crate) fn needs_fifo_compaction(version: &Version, opts: &Options, now: u64) -> bool {
+  files_to_drop(version, opts, now).is_some() || files_to_merge(version, opts).is_some()
+}
+
+/// Pick the files of `version` to drop at time `now`, or failing that the
+/// L0 files to merge.  `None` if there is nothing to do or a compaction is
+/// already `running` in the column family.
+pub(crate) fn pick_fifo_compaction(
+  version: &Arc<Version>,
+  opts: &Options,
+  running: &[CompactionClaim],
+  now: u64,
+) -> Option<Compaction> {
+  if !running.is_empty() {
+    return None;
+  }
+  if let Some((level, files)) = files_to_drop(version, opts, now) {
+    let mut spec = Compaction::new(level, Arc::clone(version));
+    spec.output_level = level;
+    spec.inputs[0] = files;
+    spec.deletion_compaction = true;
+    return Some(spec);
+  }
+  let files = files_to_merge(version, opts)?;
+  let mut spec = Compaction::new(0, Arc::clone(version));
+  spec.output_level = 0;
+  spec.inputs[0] = files;
+  Some(spec)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::comparator::BytewiseComparator;
+  use crate::options::CompactionStyle;
+  use crate::table::format::make_internal_key;
+  use std::time::Duration;
+
+  const NOW: u64 = 1_000_000;
+
+  fn file(number: u64, size: u64, time: u64) -> Arc<FileMetaData> {
+    FileMetaData::with_oldest_ancestor_time(
+      number,
+      size,
+      make_internal_key(b"a", number, 1),
+      make_internal_key(b"z", number, 1),
+      0,
+      time,
+    )
+  }
+
+  fn fifo(max_table_files_size: u64) -> Options {
+    let mut opts = Options {
+      compaction_style: CompactionStyle::Fifo,
+      ..Options::default()
+    };
+    opts.compaction_options_fifo.max_table_files_size = max_table_files_size;
+    opts
+  }
+
+  /// A version with L0 files, given newest first as `(size, time)`, numbered
+  /// down from 100.
+  fn version(l0: &[(u64, u64)]) -> Version {
+    let mut v = Version::new(7, Arc::new(BytewiseComparator));
+    for (i, &(size, time)) in l0.iter().enumerate() {
+      v.push_file_for_test(0, file(100 - i as u64, size, time));
+    }
+    v
+  }
+
+  fn numbers(spec: &Compaction) -> Vec<u64> {
+    spec.inputs[0].iter().map(|f| f.number).collect()
+  }
+
+  #[test]
+  fn size_limit_drops_oldest_files() {
+    let v = Arc::new(version(&[(10, 0), (10, 0), (10, 0), (10, 0)]));
+    assert!(!needs_fifo_compaction(&v, &fifo(40), NOW));
+
+    let spec = pick_fifo_compaction(&v, &fifo(25), &[], NOW).unwrap();
+    assert!(spec.deletion_compaction);
+    assert_eq!((spec.level, spec.output_level), (0, 0));
+    assert_eq!(numbers(&spec), vec![97, 98]);
+  }
+
+  #[test]
+  fn files_below_l0_are_dropped_first_one_level_at_a_time() {
+    let mut v = version(&[(10, 0)]);
+    v.push_file_for_test(2, file(5, 10, 0));
+    v.push_file_for_test(6, file(1, 10, 0));
+    let v = Arc::new(v);
+    let spec = pick_fifo_compaction(&v, &fifo(5), &[], NOW).unwrap();
+    assert_eq!((spec.level, numbers(&spec)), (6, vec![1]));
+  }
+
+  #[test]
+  fn ttl_drops_expired_files_up_to_the_first_live_one() {
+    let mut opts = fifo(u64::MAX);
+    opts.compaction_options_fifo.ttl = Some(Duration::from_secs(100));
+    // Oldest first: expired, expired, live, expired.
+    let v = Arc::new(version(&[
+      (10, NOW - 500),
+      (10, NOW - 50),
+      (10, NOW - 200),
+      (10, NOW - 300),
+    ]));
+    let spec = pick_fifo_compaction(&v, &opts, &[], NOW).unwrap();
+    assert_eq!(numbers(&spec), vec![97, 98]);
+
+    // A file of unknown age stops the search.
+    let v = Arc::new(version(&[(10, NOW - 500), (10, 0)]));
+    assert!(!needs_fifo_compaction(&v, &opts, NOW));
+  }
+
+  #[test]
+  fn newest_small_l0_files_are_merged_when_allowed() {
+    let mut opts = fifo(u64::MAX);
+    opts.write_buffer_size = 100;
+    let v = Arc::new(version(&[
+      (10, 0),
+      (10, 0),
+      (10, 0),
+      (10, 0),
+      (500, 0),
+      (10, 0),
+    ]));
+    assert!(!needs_fifo_compaction(&v, &opts, NOW));
+
+    opts.compaction_options_fifo.allow_compaction = true;
+    assert!(needs_fifo_compaction(&v, &opts, NOW));
+    let spec = pick_fifo_compaction(&v, &opts, &[], NOW).unwrap();
+    assert!(!spec.deletion_compaction);
+    assert_eq!((spec.level, spec.output_level), (0, 0));
+    assert_eq!(numbers(&spec), vec![100, 99, 98, 97]);
+
+    // Below the trigger, nothing is merged.
+    let v = Arc::new(version(&[(10, 0), (10, 0), (10, 0), (500, 0)]));
+    assert!(!needs_fifo_compaction(&v, &opts, NOW));
+  }
+}
@@ -10,10 +10,17 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
+pub(crate) mod blob;
 pub(crate) mod compaction;
 pub(crate) mod db_iter;
+pub(crate) mod external_file_ingestion;
+pub(crate) mod fifo_compaction;
 pub(crate) mod merge_iter;
+pub(crate) mod range_del;
 pub(crate) mod table_cache;
+pub(crate) mod timestamp_iter;
+pub(crate) mod universal_compaction;
 pub(crate) mod version;
 pub(crate) mod version_edit;
 pub(crate) mod version_set;
+pub(crate) mod wal_manager;
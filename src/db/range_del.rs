@@ -0,0 +1,266 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Range tombstones written by [`Db::delete_range`](crate::Db::delete_range).
+//!
+//! A range tombstone `[start, end) @ seq` deletes every entry whose user key
+//! lies in the range and whose sequence number is below `seq`.  Tombstones
+//! are kept apart from point entries — in a dedicated skip list in the
+//! memtable and in the `rocksdb.range_del` meta block of an SSTable — and are
+//! consulted by point lookups, iterators and compaction.
+//!
+//! See `db/range_tombstone_fragmenter.h` and `db/range_del_aggregator.h` in
+//! RocksDB.
+
+use crate::comparator::Comparator;
+use crate::table::format::{
+  cmp_internal_keys, make_internal_key, MAX_SEQUENCE_NUMBER, VALUE_TYPE_RANGE_DELETION,
+};
+use std::sync::Arc;
+
+/// A single range tombstone: deletes user keys in `[start, end)` written
+/// before `seq`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RangeTombstone {
+  pub(crate) start: Vec<u8>,
+  pub(crate) end: Vec<u8>,
+  pub(crate) seq: u64,
+}
+
+impl RangeTombstone {
+  /// Internal key of the tombstone's start: `start @ seq` with the
+  /// range-deletion type.  This is the key stored in the range-del block.
+  pub(crate) fn start_key(&self) -> Vec<u8> {
+    make_internal_key(&self.start, self.seq, VALUE_TYPE_RANGE_DELETION)
+  }
+
+  /// Exclusive upper bound as an internal key: `end @ MAX_SEQUENCE_NUMBER`,
+  /// which sorts before every real entry for `end`.  Used as a file's
+  /// `largest` key when a tombstone extends past its last point entry.
+  pub(crate) fn end_sentinel_key(&self) -> Vec<u8> {
+    make_internal_key(&self.end, MAX_SEQUENCE_NUMBER, VALUE_TYPE_RANGE_DELETION)
+  }
+}
+
+/// Widen the internal-key bounds `[smallest, largest]` of an SSTable so they
+/// span `tombstone`.  Empty bounds are treated as unset.
+///
+/// File bounds must include every tombstone the file holds: compaction
+/// picking and `Version::get` only look at files whose range covers a key.
+pub(crate) fn extend_file_bounds(
+  smallest: &mut Vec<u8>,
+  largest: &mut Vec<u8>,
+  tombstone: &RangeTombstone,
+  comparator: &dyn Comparator,
+) {
+  let start = tombstone.start_key();
+  if smallest.is_empty() || cmp_internal_keys(&start, smallest, comparator).is_lt() {
+    *smallest = start;
+  }
+  let end = tombstone.end_sentinel_key();
+  if largest.is_empty() || cmp_internal_keys(&end, largest, comparator).is_gt() {
+    *largest = end;
+  }
+}
+
+/// One non-overlapping piece of the tombstone set: every tombstone that
+/// covers `[start, end)` contributed its sequence number to `seqs`.
+#[derive(Debug)]
+struct Fragment {
+  start: Vec<u8>,
+  end: Vec<u8>,
+  /// Sequence numbers of the covering tombstones, newest first.
+  seqs: Vec<u64>,
+}
+
+/// A set of range tombstones split into sorted, non-overlapping fragments so
+/// that the tombstones covering a key can be found with one binary search.
+///
+/// See `db/range_tombstone_fragmenter.h: FragmentedRangeTombstoneList`.
+pub(crate) struct FragmentedRangeTombstones {
+  fragments: Vec<Fragment>,
+  comparator: Arc<dyn Comparator>,
+}
+
+impl FragmentedRangeTombstones {
+  /// Fragment `tombstones` (in any order).  Empty ranges are discarded.
+  pub(crate) fn new(mut tombstones: Vec<RangeTombstone>, comparator: Arc<dyn Comparator>) -> Self {
+    let cmp = &*comparator;
+    tombstones.retain(|t| cmp.compare(&t.start, &t.end).is_lt());
+    tombstones.sort_by(|a, b| cmp.compare(&a.start, &b.start));
+
+    // Every start and end is a fragment boundary.
+    let mut points: Vec<&[u8]> = tombstones
+      .iter()
+      .flat_map(|t| [t.start.as_slice(), t.end.as_slice()])
+      .collect();
+    points.sort_by(|a, b| cmp.compare(a, b));
+    points.dedup_by(|a, b| cmp.compare(a, b).is_eq());
+
+    // Sweep the boundaries left to right, tracking the tombstones that are
+    // active over each gap.  An active tombstone always ends at or after the
+    // next boundary, since its end is itself a boundary.
+    let mut fragments = Vec::new();
+    let mut active: Vec<&RangeTombstone> = Vec::new();
+    let mut next = 0;
+    for window in points.windows(2) {
+      let (lo, hi) = (window[0], window[1]);
+      while next < tombstones.len() && cmp.compare(&tombstones[next].start, lo).is_le() {
+        active.push(&tombstones[next]);
+        next += 1;
+      }
+      active.retain(|t| cmp.compare(&t.end, lo).is_gt());
+      if active.is_empty() {
+        continue;
+      }
+      let mut seqs: Vec<u64> = active.iter().map(|t| t.seq).collect();
+      seqs.sort_unstable_by(|a, b| b.cmp(a));
+      seqs.dedup();
+      fragments.push(Fragment {
+        start: lo.to_vec(),
+        end: hi.to_vec(),
+        seqs,
+      });
+    }
+
+    FragmentedRangeTombstones {
+      fragments,
+      comparator,
+    }
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.fragments.is_empty()
+  }
+
+  /// Sequence number of the newest tombstone visible at `read_seq` that
+  /// covers `user_key`, or `0` if there is none.  An entry for `user_key`
+  /// with a lower sequence number is deleted.
+  pub(crate) fn max_covering_seq(&self, user_key: &[u8], read_seq: u64) -> u64 {
+    let cmp = &*self.comparator;
+    let idx = self
+      .fragments
+      .partition_point(|f| cmp.compare(&f.start, user_key).is_le());
+    let Some(fragment) = idx.checked_sub(1).map(|i| &self.fragments[i]) else {
+      return 0;
+    };
+    if cmp.compare(user_key, &fragment.end).is_ge() {
+      return 0;
+    }
+    fragment
+      .seqs
+      .iter()
+      .copied()
+      .find(|&s| s <= read_seq)
+      .unwrap_or(0)
+  }
+
+  /// True if the entry `user_key @ seq` is deleted by a tombstone visible at
+  /// `read_seq`.
+  pub(crate) fn covers(&self, user_key: &[u8], seq: u64, read_seq: u64) -> bool {
+    !self.is_empty() && self.max_covering_seq(user_key, read_seq) > seq
+  }
+
+  /// Every fragment as a tombstone (one per fragment and sequence number),
+  /// ordered by start key and then newest first.
+  pub(crate) fn iter(&self) -> impl Iterator<Item = RangeTombstone> + '_ {
+    self.fragments.iter().flat_map(|f| {
+      f.seqs.iter().map(move |&seq| RangeTombstone {
+        start: f.start.clone(),
+        end: f.end.clone(),
+        seq,
+      })
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::comparator::BytewiseComparator;
+
+  fn t(start: &[u8], end: &[u8], seq: u64) -> RangeTombstone {
+    RangeTombstone {
+      start: start.to_vec(),
+      end: end.to_vec(),
+      seq,
+    }
+  }
+
+  fn fragmented(tombstones: Vec<RangeTombstone>) -> FragmentedRangeTombstones {
+    FragmentedRangeTombstones::new(tombstones, Arc::new(BytewiseComparator))
+  }
+
+  #[test]
+  fn empty_set_covers_nothing() {
+    let f = fragmented(vec![]);
+    assert!(f.is_empty());
+    assert_eq!(f.max_covering_seq(b"a", u64::MAX), 0);
+    assert!(!f.covers(b"a", 0, u64::MAX));
+  }
+
+  #[test]
+  fn single_tombstone_is_half_open() {
+    let f = fragmented(vec![t(b"b", b"d", 10)]);
+    assert_eq!(f.max_covering_seq(b"a", 100), 0);
+    assert_eq!(f.max_covering_seq(b"b", 100), 10);
+    assert_eq!(f.max_covering_seq(b"c", 100), 10);
+    assert_eq!(f.max_covering_seq(b"d", 100), 0);
+    assert!(f.covers(b"c", 9, 100));
+    assert!(!f.covers(b"c", 10, 100));
+  }
+
+  #[test]
+  fn snapshot_hides_newer_tombstones() {
+    let f = fragmented(vec![t(b"a", b"z", 10), t(b"c", b"e", 20)]);
+    assert_eq!(f.max_covering_seq(b"d", 100), 20);
+    assert_eq!(f.max_covering_seq(b"d", 15), 10);
+    assert_eq!(f.max_covering_seq(b"d", 5), 0);
+    assert_eq!(f.max_covering_seq(b"x", 100), 10);
+  }
+
+  #[test]
+  fn overlapping_tombstones_are_fragmented() {
+    let f = fragmented(vec![t(b"a", b"e", 5), t(b"c", b"g", 7), t(b"x", b"x", 9)]);
+    let pieces: Vec<_> = f.iter().collect();
+    assert_eq!(
+      pieces,
+      vec![
+        t(b"a", b"c", 5),
+        t(b"c", b"e", 7),
+        t(b"c", b"e", 5),
+        t(b"e", b"g", 7),
+      ]
+    );
+    assert_eq!(f.max_covering_seq(b"b", 100), 5);
+    assert_eq!(f.max_covering_seq(b"f", 100), 7);
+    assert_eq!(f.max_covering_seq(b"h", 100), 0);
+  }
+
+  #[test]
+  fn extend_file_bounds_uses_exclusive_end_sentinel() {
+    let cmp = BytewiseComparator;
+    let mut smallest = make_internal_key(b"c", 3, 1);
+    let mut largest = make_internal_key(b"d", 4, 1);
+    extend_file_bounds(&mut smallest, &mut largest, &t(b"a", b"f", 9), &cmp);
+    assert_eq!(
+      smallest,
+      make_internal_key(b"a", 9, VALUE_TYPE_RANGE_DELETION)
+    );
+    assert_eq!(
+      largest,
+      make_internal_key(b"f", MAX_SEQUENCE_NUMBER, VALUE_TYPE_RANGE_DELETION)
+    );
+    // The sentinel sorts before any real entry for the end key.
+    assert!(cmp_internal_keys(&largest, &make_internal_key(b"f", 100, 1), &cmp).is_lt());
+  }
+}
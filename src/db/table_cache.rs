@@ -12,6 +12,7 @@
 
 use crate::cache::BlockCache;
 use crate::comparator::Comparator;
+use crate::db::blob::BlobFileReader;
 use crate::env::FileSystem;
 use crate::error::Error;
 use crate::filter::FilterPolicy;
@@ -37,6 +38,9 @@ struct Inner {
   order: VecDeque<u64>,
   /// Maps file number → open Table handle.
   map: HashMap<u64, Arc<Table>>,
+  /// Maps file number → open blob file.  Blob files share `order` and
+  /// `capacity` with the tables.
+  blob_files: HashMap<u64, Arc<BlobFileReader>>,
 }
 
 impl Inner {
@@ -52,11 +56,7 @@ impl Inner {
     }
 
     // Cache miss: evict LRU entry if at capacity.
-    if self.map.len() >= self.capacity {
-      if let Some(evict) = self.order.pop_front() {
-        self.map.remove(&evict);
-      }
-    }
+    self.make_room();
 
     // Open the SSTable file and parse the footer + index block.
     let sst_path = self.path.join(format!("{number:06}.ldb"));
@@ -82,10 +82,8 @@ impl Inner {
   /// If `number` is already present, the existing entry is replaced.
   /// Evicts LRU if at capacity before inserting a new entry.
   fn insert(&mut self, number: u64, table: Arc<Table>) {
-    if !self.map.contains_key(&number) && self.map.len() >= self.capacity {
-      if let Some(evict) = self.order.pop_front() {
-        self.map.remove(&evict);
-      }
+    if !self.map.contains_key(&number) {
+      self.make_room();
     }
     // Promote / insert.
     if let Some(pos) = self.order.iter().position(|&n| n == number) {
@@ -95,9 +93,39 @@ impl Inner {
     self.map.insert(number, table);
   }
 
+  /// Return the open blob file `number`, opening (and possibly evicting) as needed.
+  fn get_blob_file(&mut self, number: u64) -> Result<Arc<BlobFileReader>, Error> {
+    if let Some(f) = self.blob_files.get(&number) {
+      let f = Arc::clone(f);
+      if let Some(pos) = self.order.iter().position(|&n| n == number) {
+        self.order.remove(pos);
+      }
+      self.order.push_back(number);
+      return Ok(f);
+    }
+    self.make_room();
+    let reader = Arc::new(
+      BlobFileReader::open(&*self.fs, &self.path, number)
+        .map_err(|e| Error::Corruption(format!("cannot open blob file {number:06}.blob: {e}")))?,
+    );
+    self.order.push_back(number);
+    self.blob_files.insert(number, Arc::clone(&reader));
+    Ok(reader)
+  }
+
+  /// Evict the least-recently-used entry if the cache is full.
+  fn make_room(&mut self) {
+    if self.map.len() + self.blob_files.len() >= self.capacity {
+      if let Some(evict) = self.order.pop_front() {
+        self.map.remove(&evict);
+        self.blob_files.remove(&evict);
+      }
+    }
+  }
+
   /// Remove the entry for `number` (called when the file is deleted by compaction).
   fn evict(&mut self, number: u64) {
-    if self.map.remove(&number).is_some() {
+    if self.map.remove(&number).is_some() || self.blob_files.remove(&number).is_some() {
       if let Some(pos) = self.order.iter().position(|&n| n == number) {
         self.order.remove(pos);
       }
@@ -142,6 +170,7 @@ impl TableCache {
       capacity: capacity.max(1),
       order: VecDeque::new(),
       map: HashMap::new(),
+      blob_files: HashMap::new(),
     })))
   }
 
@@ -162,6 +191,20 @@ impl TableCache {
     self.0.lock().unwrap().insert(number, table);
   }
 
+  /// Return (or lazily open) blob file `number`.
+  ///
+  /// Blob files are cached alongside tables, as RocksDB's `BlobFileCache`
+  /// shares the table cache.
+  pub(crate) fn get_blob_file(&self, number: u64) -> Result<Arc<BlobFileReader>, Error> {
+    self.0.lock().unwrap().get_blob_file(number)
+  }
+
+  /// `true` if table or blob file `number` is open in the cache.
+  pub(crate) fn contains(&self, number: u64) -> bool {
+    let inner = self.0.lock().unwrap();
+    inner.map.contains_key(&number) || inner.blob_files.contains_key(&number)
+  }
+
   /// Evict the entry for `number` (called when the file is garbage-collected).
   pub(crate) fn evict(&self, number: u64) {
     self.0.lock().unwrap().evict(number);
@@ -175,6 +218,7 @@ mod tests {
   use crate::options::CompressionType;
   use crate::table::builder::TableBuilder;
   use crate::table::format::make_internal_key;
+  use crate::table::get_context::GetContext;
 
   fn write_sst(dir: &Path, number: u64, entries: &[(&[u8], &[u8])]) -> u64 {
     let path = dir.join(format!("{number:06}.ldb"));
@@ -208,7 +252,7 @@ mod tests {
     let table = tc.get_or_open(3, size).unwrap();
     use crate::table::reader::LookupResult;
     assert!(
-      matches!(table.get(&make_internal_key(b"hello", u64::MAX, 1), false, true).unwrap(), LookupResult::Value(v) if v == b"world")
+      matches!(table.get(&make_internal_key(b"hello", u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), LookupResult::Value(v) if v == b"world")
     );
   }
 
@@ -0,0 +1,111 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::comparator::{timestamp_of, Comparator};
+use crate::error::Error;
+use crate::iter::InternalIterator;
+use crate::table::format::user_key;
+use std::sync::Arc;
+
+/// Hides the entries of `inner` whose user-defined timestamp is newer than
+/// the read timestamp, so that a [`DbIterator`] over it reads the database
+/// as of that timestamp.
+///
+/// The entries it passes through still carry their timestamps; the
+/// `DbIterator` groups them into one user key by comparing without them.
+///
+/// See the timestamp checks of `db/db_iter.cc: DBIter` in RocksDB.
+///
+/// [`DbIterator`]: crate::db::db_iter::DbIterator
+pub(crate) struct TimestampFilterIterator {
+  inner: Box<dyn InternalIterator>,
+  read_timestamp: Vec<u8>,
+  comparator: Arc<dyn Comparator>,
+}
+
+impl TimestampFilterIterator {
+  pub(crate) fn new(
+    inner: Box<dyn InternalIterator>,
+    read_timestamp: Vec<u8>,
+    comparator: Arc<dyn Comparator>,
+  ) -> Self {
+    TimestampFilterIterator {
+      inner,
+      read_timestamp,
+      comparator,
+    }
+  }
+
+  /// True if the current entry was written after the read timestamp.
+  fn too_new(&self) -> bool {
+    let ts = timestamp_of(user_key(self.inner.key()), self.read_timestamp.len());
+    self
+      .comparator
+      .compare_timestamp(ts, &self.read_timestamp)
+      .is_gt()
+  }
+
+  fn skip_forward(&mut self) {
+    while self.inner.valid() && self.too_new() {
+      self.inner.next();
+    }
+  }
+
+  fn skip_backward(&mut self) {
+    while self.inner.valid() && self.too_new() {
+      self.inner.prev();
+    }
+  }
+}
+
+impl InternalIterator for TimestampFilterIterator {
+  fn valid(&self) -> bool {
+    self.inner.valid()
+  }
+
+  fn seek_to_first(&mut self) {
+    self.inner.seek_to_first();
+    self.skip_forward();
+  }
+
+  fn seek_to_last(&mut self) {
+    self.inner.seek_to_last();
+    self.skip_backward();
+  }
+
+  fn seek(&mut self, target: &[u8]) {
+    self.inner.seek(target);
+    self.skip_forward();
+  }
+
+  fn next(&mut self) {
+    self.inner.next();
+    self.skip_forward();
+  }
+
+  fn prev(&mut self) {
+    self.inner.prev();
+    self.skip_backward();
+  }
+
+  fn key(&self) -> &[u8] {
+    self.inner.key()
+  }
+
+  fn value(&self) -> &[u8] {
+    self.inner.value()
+  }
+
+  fn status(&self) -> Option<&Error> {
+    self.inner.status()
+  }
+}
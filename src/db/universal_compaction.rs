@@ -0,0 +1,289 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Compaction picking for [`CompactionStyle::Universal`].
+//!
+//! The tree is viewed as a list of *sorted runs*, newest first: every L0 file
+//! is a run of its own, and every non-empty level below is one run.  Once
+//! there are `level0_file_num_compaction_trigger` runs, the picker tries, in
+//! order:
+//!
+//! 1. **Space amplification**: if the runs other than the oldest add up to
+//!    more than `max_size_amplification_percent` of it, merge every run.
+//! 2. **Size ratio**: from the newest run onwards, find adjacent runs each no
+//!    more than `size_ratio` percent larger than the runs before it combined,
+//!    and merge at least `min_merge_width` of them.
+//! 3. **Run count**: merge the newest runs until no more than the trigger
+//!    remain.
+//!
+//! A merge writes its output to the level just above the next older run, or
+//! to the last level when the oldest run is merged.  Unlike RocksDB, the
+//! output never goes to L0: L0 files are read newest file number first, and
+//! an output holding older entries than a file flushed meanwhile would be
+//! numbered after it.  So a merge reaching into L0 takes every older L0 file
+//! too, and one whose output would land in L0 takes the next run as well.
+//!
+//! Universal compactions of a column family run one at a time, and flushes
+//! always go to L0.  The merge itself is [`do_compaction`], with the same
+//! snapshot, tombstone and [`CompactionFilter`] handling as leveled
+//! compaction.
+//!
+//! See `db/compaction/compaction_picker_universal.cc` in RocksDB.
+//!
+//! [`CompactionStyle::Universal`]: crate::CompactionStyle::Universal
+//! [`do_compaction`]: crate::db::compaction::do_compaction
+//! [`CompactionFilter`]: crate::CompactionFilter
+
+use crate::db::compaction::{Compaction, CompactionClaim};
+use crate::db::version::Version;
+use crate::db::version_edit::FileMetaData;
+use crate::options::Options;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// One L0 file, or every file of a level below L0.
+struct SortedRun<'a> {
+  level: usize,
+  files: &'a [Arc<FileMetaData>],
+  size: u64,
+}
+
+/// The sorted runs of `version`, newest first.
+fn sorted_runs(version: &Version) -> Vec<SortedRun<'_>> {
+  let l0 = version.files_at(0);
+  let l0_runs = (0..l0.len()).map(|i| SortedRun {
+    level: 0,
+    files: &l0[i..=i],
+    size: l0[i].file_size,
+  });
+  let level_runs = (1..version.num_levels()).filter_map(|level| {
+    let files = version.files_at(level);
+    (!files.is_empty()).then(|| SortedRun {
+      level,
+      files,
+      size: files.iter().map(|f| f.file_size).sum(),
+    })
+  });
+  l0_runs.chain(level_runs).collect()
+}
+
+fn num_sorted_runs(version: &Version) -> usize {
+  version.files_at(0).len()
+    + (1..version.num_levels())
+      .filter(|&level| !version.files_at(level).is_empty())
+      .count()
+}
+
+/// Widen `runs[range]` so that its output lands below L0, and return it with
+/// the output level: just above the next older run, or `last_level`.
+fn with_output_level(
+  runs: &[SortedRun],
+  range: Range<usize>,
+  last_level: usize,
+) -> (Range<usize>, usize) {
+  let mut end = range.end;
+  loop {
+    let Some(next) = runs.get(end) else {
+      return (range.start..end, last_level);
+    };
+    if next.level > 1 {
+      return (range.start..end, next.level - 1);
+    }
+    end += 1;
+  }
+}
+
+/// The runs to merge and their output level, or `None` if the runs are
+/// within every limit.
+fn pick_runs(runs: &[SortedRun], opts: &Options) -> Option<(Range<usize>, usize)> {
+  let trigger = opts.level0_file_num_compaction_trigger;
+  if runs.len() < trigger {
+    return None;
+  }
+  let last_level = opts.num_levels - 1;
+  let uopts = &opts.compaction_options_universal;
+
+  // Space amplification.
+  let (oldest, newer) = runs.split_last()?;
+  let newer_size: u64 = newer.iter().map(|r| r.size).sum();
+  if u128::from(newer_size) * 100
+    > u128::from(uopts.max_size_amplification_percent) * u128::from(oldest.size)
+  {
+    log::debug!(
+      "universal: size amplification {newer_size} / {} bytes: merging all {} runs",
+      oldest.size,
+      runs.len()
+    );
+    return Some((0..runs.len(), last_level));
+  }
+
+  // Size ratio.
+  let min_width = uopts.min_merge_width.max(2);
+  for start in 0..runs.len() {
+    let mut size = runs[start].size;
+    let mut end = start + 1;
+    while end < runs.len() && end - start < uopts.max_merge_width {
+      let limit = u128::from(size) * u128::from(100 + uopts.size_ratio) / 100;
+      if limit < u128::from(runs[end].size) {
+        break;
+      }
+      size += runs[end].size;
+      end += 1;
+    }
+    if end - start >= min_width {
+      log::debug!(
+        "universal: size ratio: merging runs {start}..{end} of {}",
+        runs.len()
+      );
+      return Some(with_output_level(runs, start..end, last_level));
+    }
+  }
+
+  // Run count.
+  if runs.len() > trigger {
+    let width = runs.len() - trigger + 1;
+    log::debug!("universal: {} runs: merging the newest {width}", runs.len());
+    return Some(with_output_level(runs, 0..width, last_level));
+  }
+  None
+}
+
+/// True if [`pick_universal_compaction`] would pick a compaction for
+/// `version` with nothing running.
+pub(crate) fn needs_universal_compaction(version: &Version, opts: &Options) -> bool {
+  num_sorted_runs(version) >= opts.level0_file_num_compaction_trigger
+    && pick_runs(&sorted_runs(version), opts).is_some()
+}
+
+/// Pick the sorted runs of `version` to merge, or `None` if none need
+/// merging or a compaction is already `running` in the column family.
+pub(crate) fn pick_universal_compaction(
+  version: &Arc<Version>,
+  opts: &Options,
+  running: &[CompactionClaim],
+) -> Option<Compaction> {
+  if !running.is_empty() {
+    return None;
+  }
+  let runs = sorted_runs(version);
+  let (range, output_level) = pick_runs(&runs, opts)?;
+  let level = runs[range.start].level;
+  let mut spec = Compaction::new(level, Arc::clone(version));
+  spec.output_level = output_level;
+  for run in &runs[range] {
+    if run.level == level {
+      spec.inputs[0].extend(run.files.iter().cloned());
+    } else if run.level == output_level {
+      spec.inputs[1] = run.files.to_vec();
+    } else {
+      spec.middle_inputs.push((run.level, run.files.to_vec()));
+    }
+  }
+  Some(spec)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::comparator::BytewiseComparator;
+  use crate::options::CompactionStyle;
+  use crate::table::format::make_internal_key;
+
+  fn file(number: u64, size: u64) -> Arc<FileMetaData> {
+    FileMetaData::new(
+      number,
+      size,
+      make_internal_key(b"a", number, 1),
+      make_internal_key(b"z", number, 1),
+    )
+  }
+
+  fn universal() -> Options {
+    Options {
+      compaction_style: CompactionStyle::Universal,
+      ..Options::default()
+    }
+  }
+
+  /// Input file numbers of `spec`, and its output level.
+  fn picked(spec: &Compaction) -> (Vec<u64>, usize) {
+    (
+      spec.all_input_files().map(|f| f.number).collect(),
+      spec.output_level,
+    )
+  }
+
+  /// A version with L0 files (newest first) and whole levels, by size.
+  fn version(l0: &[u64], levels: &[(usize, u64)]) -> Arc<Version> {
+    let mut v = Version::new(7, Arc::new(BytewiseComparator));
+    let mut number = 100;
+    for &size in l0 {
+      v.push_file_for_test(0, file(number, size));
+      number -= 1;
+    }
+    for &(level, size) in levels {
+      v.push_file_for_test(level, file(level as u64, size));
+    }
+    Arc::new(v)
+  }
+
+  #[test]
+  fn nothing_below_the_trigger() {
+    let v = version(&[10, 10, 10], &[]);
+    assert!(!needs_universal_compaction(&v, &universal()));
+    assert!(pick_universal_compaction(&v, &universal(), &[]).is_none());
+  }
+
+  #[test]
+  fn size_amplification_merges_every_run() {
+    // 30 bytes on top of 10: far above 200 %.
+    let v = version(&[10, 10, 10], &[(6, 10)]);
+    let spec = pick_universal_compaction(&v, &universal(), &[]).unwrap();
+    assert_eq!(picked(&spec), (vec![100, 99, 98, 6], 6));
+    assert_eq!(spec.inputs[1].len(), 1);
+  }
+
+  #[test]
+  fn size_ratio_merges_similar_runs_above_the_next_one() {
+    // The four flushes are alike, the last level is much larger.
+    let v = version(&[10, 10, 10, 10], &[(6, 1000)]);
+    let spec = pick_universal_compaction(&v, &universal(), &[]).unwrap();
+    assert_eq!(picked(&spec), (vec![100, 99, 98, 97], 5));
+
+    // A merge ending inside L0 takes the older L0 files too, and one whose
+    // output would land in L0 takes L1 as well.
+    let mut opts = universal();
+    opts.compaction_options_universal.max_merge_width = 2;
+    let v = version(&[10, 10, 10, 10], &[(1, 1000), (6, 100_000)]);
+    let spec = pick_universal_compaction(&v, &opts, &[]).unwrap();
+    assert_eq!(picked(&spec), (vec![100, 99, 98, 97, 1], 5));
+    assert_eq!(spec.middle_inputs.len(), 1);
+  }
+
+  #[test]
+  fn run_count_merges_newest_runs_when_ratios_fail() {
+    // Every run is much larger than the newer ones combined.
+    let v = version(
+      &[1],
+      &[(2, 100), (3, 10_000), (4, 1_000_000), (6, 1_000_000_000)],
+    );
+    let spec = pick_universal_compaction(&v, &universal(), &[]).unwrap();
+    assert_eq!(picked(&spec), (vec![100, 2], 2));
+
+    // At exactly the trigger, nothing is merged.
+    let v = version(
+      &[],
+      &[(2, 100), (3, 10_000), (4, 1_000_000), (6, 1_000_000_000)],
+    );
+    assert!(!needs_universal_compaction(&v, &universal()));
+  }
+}
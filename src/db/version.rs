@@ -12,10 +12,13 @@
 
 use crate::comparator::Comparator;
 use crate::db::table_cache::TableCache;
-use crate::db::version_edit::FileMetaData;
+use crate::db::version_edit::{BlobFileMetaData, FileMetaData};
 use crate::error::Error;
+use crate::options::Options;
 use crate::table::format::{parse_internal_key, user_key};
+use crate::table::get_context::{GetContext, KeyContext};
 use crate::table::reader::LookupResult;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 /// Statistics returned by [`Version::get`] for seek-based compaction tracking.
@@ -33,19 +36,11 @@ pub(crate) struct GetStats {
   pub seek_file_level: usize,
 }
 
-/// Number of levels in the LSM tree.
-pub(crate) const NUM_LEVELS: usize = 7;
-
-/// L0→L1 compaction is triggered when L0 reaches this many files.
-///
-/// Lives here (rather than in `lib.rs`) so `finalize` and the `Version` query
-/// methods can reference it without a back-dependency on the crate root.
-pub(crate) const L0_COMPACTION_TRIGGER: usize = 4;
-
 /// A snapshot of the LSM structure: per-level lists of live SSTable files.
 ///
 /// L0 files are stored newest-first (highest file number first).
-/// L1–L6 files are stored sorted by smallest key (for binary search in Phase 6).
+/// Files at L1 and below are stored sorted by smallest key.  The number of
+/// levels is the column family's `Options::num_levels`.
 ///
 /// `Version` is reference-counted; it lives until all iterators opened on it
 /// are dropped.  Open file handles are managed by the [`TableCache`], not here.
@@ -54,7 +49,9 @@ pub(crate) struct Version {
   /// Per-level live SSTable files.  Private: all overlap/range queries go
   /// through the methods below, and construction goes through `new`/
   /// `from_files`, so callers cannot depend on the raw representation.
-  files: [Vec<Arc<FileMetaData>>; NUM_LEVELS],
+  files: Vec<Vec<Arc<FileMetaData>>>,
+  /// Live blob files by file number (oldest first).
+  blob_files: BTreeMap<u64, Arc<BlobFileMetaData>>,
   /// User-key comparator.  A `Version` is inherently ordered by a comparator,
   /// so it owns one — overlap/range queries need not take it as a parameter.
   comparator: Arc<dyn Comparator>,
@@ -63,35 +60,94 @@ pub(crate) struct Version {
   pub compaction_score: f64,
   /// Level with the highest compaction score.  -1 before `finalize` is called.
   pub compaction_level: i32,
+  /// Every level that can be compacted with its score, highest score first,
+  /// so a level can be picked when a running compaction blocks the best one.
+  pub(crate) compaction_scores: Vec<(usize, f64)>,
+  /// Level that L0 compacts into: L1, or with
+  /// `level_compaction_dynamic_level_bytes` the level `finalize` derives from
+  /// the size of the data below.  The levels between are empty.
+  pub(crate) base_level: usize,
+  /// User-defined timestamp below which compaction may collapse a key's
+  /// versions into the newest one; `None` keeps every version.  Carried
+  /// from version to version and persisted in the MANIFEST.
+  pub(crate) full_history_ts_low: Option<Vec<u8>>,
 }
 
 impl Version {
-  /// Create an empty `Version` (no files at any level).
-  pub(crate) fn new(comparator: Arc<dyn Comparator>) -> Self {
-    Self::from_files(std::array::from_fn(|_| Vec::new()), comparator)
+  /// Create an empty `Version` with `num_levels` levels.
+  pub(crate) fn new(num_levels: usize, comparator: Arc<dyn Comparator>) -> Self {
+    Self::from_files(vec![Vec::new(); num_levels], comparator)
   }
 
   /// Create a `Version` from pre-assembled per-level file lists.  Compaction
   /// scores are left unset (`finalize` computes them).
   pub(crate) fn from_files(
-    files: [Vec<Arc<FileMetaData>>; NUM_LEVELS],
+    files: Vec<Vec<Arc<FileMetaData>>>,
+    comparator: Arc<dyn Comparator>,
+  ) -> Self {
+    Self::from_parts(files, BTreeMap::new(), comparator)
+  }
+
+  /// Like [`from_files`](Self::from_files), with the live blob files.
+  pub(crate) fn from_parts(
+    files: Vec<Vec<Arc<FileMetaData>>>,
+    blob_files: BTreeMap<u64, Arc<BlobFileMetaData>>,
     comparator: Arc<dyn Comparator>,
   ) -> Self {
     Version {
       files,
+      blob_files,
       comparator,
       compaction_score: -1.0,
       compaction_level: -1,
+      compaction_scores: Vec::new(),
+      base_level: 1,
+      full_history_ts_low: None,
     }
   }
 
+  /// Number of levels, L0 included.
+  pub(crate) fn num_levels(&self) -> usize {
+    self.files.len()
+  }
+
   /// Read-only view of the files at `level`.
   ///
-  /// L0 is newest-first; L1–L6 are sorted by smallest user key.
+  /// L0 is newest-first; deeper levels are sorted by smallest user key.
   pub(crate) fn files_at(&self, level: usize) -> &[Arc<FileMetaData>] {
     &self.files[level]
   }
 
+  /// Live blob files, ordered by file number.
+  pub(crate) fn blob_files(&self) -> &BTreeMap<u64, Arc<BlobFileMetaData>> {
+    &self.blob_files
+  }
+
+  /// Read the blob that the blob index `index` (the value of a blob-index
+  /// entry for `user_key`) refers to.
+  ///
+  /// Returns [`Error::Corruption`] if the index names a blob file that is not
+  /// live in this version.
+  ///
+  /// See `db/version_set.cc: Version::GetBlob` in RocksDB.
+  pub(crate) fn get_blob(
+    &self,
+    user_key: &[u8],
+    index: &[u8],
+    verify_checksums: bool,
+    tc: &TableCache,
+  ) -> Result<Vec<u8>, Error> {
+    let index = crate::db::blob::BlobIndex::decode(index)?;
+    if !self.blob_files.contains_key(&index.file_number) {
+      return Err(Error::Corruption(format!(
+        "blob index refers to unknown blob file {}",
+        index.file_number
+      )));
+    }
+    tc.get_blob_file(index.file_number)?
+      .get(user_key, &index, verify_checksums)
+  }
+
   /// Append a file to `level` without re-sorting.  Test-only construction helper.
   #[cfg(test)]
   pub(crate) fn push_file_for_test(&mut self, level: usize, meta: Arc<FileMetaData>) {
@@ -102,7 +158,7 @@ impl Version {
   /// statistics for compaction accounting.
   ///
   /// Only files whose key range can contain `user_key` are probed: L0 is
-  /// scanned newest-first with a containment check per file; L1–L6 use a
+  /// scanned newest-first with a containment check per file; deeper levels use a
   /// binary search for the single candidate file per level (ranges are
   /// disjoint and sorted).  Matching LevelDB's `Version::ForEachOverlapping`.
   ///
@@ -112,6 +168,13 @@ impl Version {
   /// The returned [`GetStats`] blames the first file consulted when the lookup
   /// required probing more than one file (matching LevelDB `Version::Get`).
   /// Files skipped by the range checks are never charged.
+  ///
+  /// Merge operands encountered on the way down are appended to
+  /// `ctx.operands` newest-first; the search continues into older files until
+  /// a base value or tombstone is found (see `Table::get`).  A value stored in
+  /// a blob file is read from it.  Range tombstones
+  /// raise `ctx.max_covering_tombstone_seq` as files are probed, so a newer
+  /// file's tombstone deletes entries found in older ones.
   pub(crate) fn get(
     &self,
     user_key: &[u8],
@@ -119,6 +182,7 @@ impl Version {
     verify_checksums: bool,
     fill_cache: bool,
     tc: &TableCache,
+    ctx: &mut GetContext,
   ) -> Result<(LookupResult, GetStats), Error> {
     let mut stats = GetStats {
       seek_file: None,
@@ -128,7 +192,11 @@ impl Version {
     let mut last_file_read_level: usize = 0;
 
     // Build the lookup internal key once; every table probe reuses it.
-    let lookup_key = crate::table::format::make_internal_key(user_key, sequence, 1);
+    let lookup_key = crate::table::format::make_internal_key(
+      user_key,
+      sequence,
+      crate::table::format::VALUE_TYPE_FOR_SEEK,
+    );
 
     // Helper: record blame on the previous file before moving to a new one.
     macro_rules! charge_prev {
@@ -145,50 +213,192 @@ impl Version {
     // L0: newest-first scan of the files whose key range contains `user_key`.
     // Ranges may overlap, so every containing file is a candidate.
     for meta in &self.files[0] {
-      let lo = crate::table::format::user_key(&meta.smallest);
-      let hi = crate::table::format::user_key(&meta.largest);
-      if self.comparator.compare(user_key, lo).is_lt()
-        || self.comparator.compare(user_key, hi).is_gt()
-      {
+      if !self.may_contain(meta, user_key) {
         continue; // file cannot contain the key — don't open or charge it
       }
       charge_prev!(meta, 0);
       let table = tc.get_or_open(meta.number, meta.file_size)?;
-      match table.get(&lookup_key, verify_checksums, fill_cache)? {
-        LookupResult::Value(v) => return Ok((LookupResult::Value(v), stats)),
+      match table.get(&lookup_key, verify_checksums, fill_cache, ctx)? {
+        LookupResult::Value(v) => {
+          let v = self.resolve_value(user_key, v, verify_checksums, tc, ctx)?;
+          return Ok((LookupResult::Value(v), stats));
+        }
         LookupResult::Deleted => return Ok((LookupResult::Deleted, stats)),
         LookupResult::NotInTable => {}
       }
     }
 
-    // L1–L6: file ranges are disjoint and sorted by smallest key, so at most
-    // one file per level can contain the key — binary-search for it.
+    // L1 and below: file ranges are disjoint and sorted by smallest key, so the
+    // lookup starts at the one file whose range holds it — binary-search for
+    // it.  Older versions of the key (merge bases, or versions with an older
+    // user-defined timestamp) may continue into the files after it.
     // See LevelDB's `Version::ForEachOverlapping` / `FindFile`.
-    for level in 1..NUM_LEVELS {
+    for level in 1..self.files.len() {
       let files = &self.files[level];
-      let index = find_file(files, &lookup_key, &*self.comparator);
-      let Some(meta) = files.get(index) else {
-        continue; // every file's range ends before the key
-      };
-      if self
-        .comparator
-        .compare(user_key, crate::table::format::user_key(&meta.smallest))
-        .is_lt()
+      let mut index = find_file(files, &lookup_key, &*self.comparator);
+      // Stops when every file's range ends before the key, or the next file
+      // starts after it.
+      while let Some(meta) = files
+        .get(index)
+        .filter(|m| self.starts_at_or_before(m, user_key))
       {
-        continue; // the candidate file starts after the key
-      }
-      charge_prev!(meta, level);
-      let table = tc.get_or_open(meta.number, meta.file_size)?;
-      match table.get(&lookup_key, verify_checksums, fill_cache)? {
-        LookupResult::Value(v) => return Ok((LookupResult::Value(v), stats)),
-        LookupResult::Deleted => return Ok((LookupResult::Deleted, stats)),
-        LookupResult::NotInTable => {}
+        charge_prev!(meta, level);
+        let table = tc.get_or_open(meta.number, meta.file_size)?;
+        match table.get(&lookup_key, verify_checksums, fill_cache, ctx)? {
+          LookupResult::Value(v) => {
+            let v = self.resolve_value(user_key, v, verify_checksums, tc, ctx)?;
+            return Ok((LookupResult::Value(v), stats));
+          }
+          LookupResult::Deleted => return Ok((LookupResult::Deleted, stats)),
+          LookupResult::NotInTable => {}
+        }
+        index += 1;
       }
     }
 
     Ok((LookupResult::NotInTable, stats))
   }
 
+  /// Look up a batch of keys, sorted by user key, as [`get`](Self::get) does
+  /// for each one.  Keys that already have a `result` (resolved by a
+  /// memtable) are skipped; the others get theirs from the first table that
+  /// holds them and are left unresolved if none does.
+  ///
+  /// Keys are grouped per table — every L0 file containing them, then the
+  /// one candidate file per level — so each table is fetched from `tc` once
+  /// and reads each of its data blocks once per batch.  Unlike `get`, no
+  /// seek statistics are collected.
+  ///
+  /// See `db/version_set.cc: Version::MultiGet` in RocksDB.
+  pub(crate) fn multi_get(
+    &self,
+    keys: &mut [KeyContext],
+    verify_checksums: bool,
+    fill_cache: bool,
+    tc: &TableCache,
+  ) {
+    let cmp = &*self.comparator;
+
+    // L0: every file whose range contains a pending key, newest first.
+    for meta in &self.files[0] {
+      let mut batch: Vec<&mut KeyContext> = keys
+        .iter_mut()
+        .filter(|k| k.result.is_none() && self.may_contain(meta, k.user_key()))
+        .collect();
+      self.multi_get_from(meta, &mut batch, verify_checksums, fill_cache, tc);
+    }
+
+    // L1 and below: sorted keys map to a non-decreasing run of candidate files, so
+    // consecutive keys with the same candidate form one batch.  Keys left
+    // unresolved by their candidate move on to the next file, as in `get`.
+    for level in 1..self.files.len() {
+      let files = &self.files[level];
+      let mut pending: Vec<(usize, &mut KeyContext)> = keys
+        .iter_mut()
+        .filter(|k| k.result.is_none())
+        .map(|k| (find_file(files, &k.lookup_key, cmp), k))
+        .collect();
+      while !pending.is_empty() {
+        let mut batches: Vec<(usize, Vec<&mut KeyContext>)> = Vec::new();
+        for (index, key) in pending {
+          let Some(meta) = files.get(index) else {
+            continue;
+          };
+          if !self.starts_at_or_before(meta, key.user_key()) {
+            continue;
+          }
+          match batches.last_mut() {
+            Some((last, batch)) if *last == index => batch.push(key),
+            _ => batches.push((index, vec![key])),
+          }
+        }
+        pending = Vec::new();
+        for (index, mut batch) in batches {
+          self.multi_get_from(&files[index], &mut batch, verify_checksums, fill_cache, tc);
+          pending.extend(
+            batch
+              .into_iter()
+              .filter(|k| k.result.is_none())
+              .map(|k| (index + 1, k)),
+          );
+        }
+      }
+    }
+  }
+
+  /// True if `key` (ignoring its timestamp) lies within the range of `meta`.
+  fn may_contain(&self, meta: &FileMetaData, key: &[u8]) -> bool {
+    self.starts_at_or_before(meta, key)
+      && self
+        .comparator
+        .compare_without_timestamp(key, true, user_key(&meta.largest), true)
+        .is_le()
+  }
+
+  /// True if `meta` starts at or before `key`, ignoring timestamps.
+  fn starts_at_or_before(&self, meta: &FileMetaData, key: &[u8]) -> bool {
+    self
+      .comparator
+      .compare_without_timestamp(user_key(&meta.smallest), true, key, true)
+      .is_le()
+  }
+
+  /// Probe the table `meta` for `batch`, reading blobs for the values that
+  /// are blob indexes.
+  fn multi_get_from(
+    &self,
+    meta: &FileMetaData,
+    batch: &mut [&mut KeyContext],
+    verify_checksums: bool,
+    fill_cache: bool,
+    tc: &TableCache,
+  ) {
+    if batch.is_empty() {
+      return;
+    }
+    let table = match tc.get_or_open(meta.number, meta.file_size) {
+      Ok(table) => table,
+      Err(e) => {
+        for key in batch.iter_mut() {
+          key.result = Some(Err(e.clone()));
+        }
+        return;
+      }
+    };
+    table.multi_get(
+      batch.iter_mut().map(|k| &mut **k).collect(),
+      verify_checksums,
+      fill_cache,
+    );
+    for key in batch.iter_mut() {
+      if let Some(Ok(LookupResult::Value(v))) = &mut key.result {
+        let value = std::mem::take(v);
+        let user_key = user_key(&key.lookup_key);
+        key.result = Some(
+          self
+            .resolve_value(user_key, value, verify_checksums, tc, &mut key.ctx)
+            .map(LookupResult::Value),
+        );
+      }
+    }
+  }
+
+  /// `value` as found by `Table::get`, with a blob index replaced by the blob.
+  fn resolve_value(
+    &self,
+    user_key: &[u8],
+    value: Vec<u8>,
+    verify_checksums: bool,
+    tc: &TableCache,
+    ctx: &mut GetContext,
+  ) -> Result<Vec<u8>, Error> {
+    if std::mem::take(&mut ctx.is_blob_index) {
+      self.get_blob(user_key, &value, verify_checksums, tc)
+    } else {
+      Ok(value)
+    }
+  }
+
   /// Estimate the cumulative on-disk byte offset of `ikey` across all levels.
   ///
   /// For each level, files whose `largest` key is entirely before `ikey` have
@@ -202,8 +412,8 @@ impl Version {
     use crate::table::format::cmp_internal_keys;
     let cmp = &*tc.comparator();
     let mut result = 0u64;
-    for level in 0..NUM_LEVELS {
-      for meta in &self.files[level] {
+    for (level, files) in self.files.iter().enumerate() {
+      for meta in files {
         if cmp_internal_keys(&meta.largest, ikey, cmp) <= std::cmp::Ordering::Equal {
           // Entire file is before ikey.
           result += meta.file_size;
@@ -313,9 +523,9 @@ impl Version {
   /// suffix is stripped so only the user key is shown.
   pub(crate) fn debug_string(&self) -> String {
     let mut out = String::new();
-    for level in 0..NUM_LEVELS {
+    for (level, files) in self.files.iter().enumerate() {
       out.push_str(&format!("--- level {level} ---\n"));
-      for f in &self.files[level] {
+      for f in files {
         let smallest = format_user_key(&f.smallest);
         let largest = format_user_key(&f.largest);
         out.push_str(&format!(
@@ -355,39 +565,108 @@ fn find_file(files: &[Arc<FileMetaData>], ikey: &[u8], comparator: &dyn Comparat
   left
 }
 
-pub(crate) fn max_bytes_for_level(level: usize) -> f64 {
-  let mut result = 10.0 * 1_048_576.0_f64; // 10 MiB
-  let mut l = level;
-  while l > 1 {
-    result *= 10.0;
-    l -= 1;
+/// Target size of `level` (L1 and below): `max_bytes_for_level_base` at L1,
+/// growing by `max_bytes_for_level_multiplier` per level.
+///
+/// See `db/version_set.cc: VersionStorageInfo::CalculateBaseBytes` in RocksDB.
+pub(crate) fn max_bytes_for_level(level: usize, opts: &Options) -> f64 {
+  let mut result = opts.max_bytes_for_level_base as f64;
+  for _ in 1..level {
+    result *= opts.max_bytes_for_level_multiplier;
   }
   result
 }
 
-/// Compute and store the highest compaction score across all levels in `version`.
+/// The base level of `version` and the target size of each of its levels
+/// (the entry for L0 is unused).
 ///
-/// Must be called on an owned `Version` before it is wrapped in `Arc`.  Uses
-/// [`L0_COMPACTION_TRIGGER`] as the L0 file-count threshold.
-pub(crate) fn finalize(version: &mut Version) {
-  let mut best_level = -1i32;
-  let mut best_score = -1.0f64;
-
-  for level in 0..(NUM_LEVELS - 1) {
-    let score = if level == 0 {
-      version.files[0].len() as f64 / L0_COMPACTION_TRIGGER as f64
-    } else {
-      let bytes: u64 = version.files[level].iter().map(|f| f.file_size).sum();
-      bytes as f64 / max_bytes_for_level(level)
-    };
-    if score > best_score {
-      best_score = score;
-      best_level = level as i32;
+/// Without `level_compaction_dynamic_level_bytes`, the base level is L1 and
+/// the targets are [`max_bytes_for_level`].  With it, the targets are derived
+/// backwards from the largest level below L0, normally the last: each level
+/// above is `max_bytes_for_level_multiplier` times smaller, up to the first
+/// level whose target is no more than `max_bytes_for_level_base`.  That level
+/// is the base level, and the levels above it stay empty.  No level below the
+/// base is targeted at less than `max_bytes_for_level_base`.  An empty tree
+/// has the last level as its base.
+///
+/// See `db/version_set.cc: VersionStorageInfo::CalculateBaseBytes` in RocksDB.
+pub(crate) fn level_targets(version: &Version, opts: &Options) -> (usize, Vec<f64>) {
+  let num_levels = version.files.len();
+  if !opts.level_compaction_dynamic_level_bytes {
+    let targets = (0..num_levels)
+      .map(|level| max_bytes_for_level(level, opts))
+      .collect();
+    return (1, targets);
+  }
+
+  let base_bytes_max = opts.max_bytes_for_level_base as f64;
+  let multiplier = opts.max_bytes_for_level_multiplier;
+  let mut targets = vec![f64::MAX; num_levels];
+  let level_bytes = |level: usize| version.level_bytes(level) as f64;
+  let Some(first_non_empty) = (1..num_levels).find(|&level| level_bytes(level) > 0.0) else {
+    // L0 compacts straight into the last level.
+    return (num_levels - 1, targets);
+  };
+  let max_level_bytes = (1..num_levels).map(level_bytes).fold(0.0, f64::max);
+
+  // The target of the first non-empty level if the largest level were
+  // exactly full.
+  let mut size = max_level_bytes;
+  for _ in first_non_empty..num_levels - 1 {
+    size /= multiplier;
+  }
+  let (base_level, base_size) = if size <= base_bytes_max / multiplier {
+    // More levels hold data than the data needs.  The size ratios between
+    // them cannot all be kept; start from the smallest useful target.
+    (first_non_empty, base_bytes_max / multiplier)
+  } else {
+    let mut base_level = first_non_empty;
+    while base_level > 1 && size > base_bytes_max {
+      base_level -= 1;
+      size /= multiplier;
     }
+    (base_level, size.min(base_bytes_max))
+  };
+
+  let mut size = base_size;
+  for (level, target) in targets.iter_mut().enumerate().skip(base_level) {
+    if level > base_level {
+      size *= multiplier;
+    }
+    *target = size.max(base_bytes_max);
   }
+  (base_level, targets)
+}
 
-  version.compaction_score = best_score;
-  version.compaction_level = best_level;
+/// Compute and store the compaction score of every level in `version`, the
+/// highest of them and the base level.
+///
+/// Must be called on an owned `Version` before it is wrapped in `Arc`.  L0 is
+/// scored by file count against `opts.level0_file_num_compaction_trigger`,
+/// deeper levels by size against their [`level_targets`].  The last level is
+/// not scored: it has nowhere to compact to.
+pub(crate) fn finalize(version: &mut Version, opts: &Options) {
+  let trigger = opts.level0_file_num_compaction_trigger.max(1);
+  let (base_level, targets) = level_targets(version, opts);
+  let mut scores: Vec<(usize, f64)> = (0..version.files.len().saturating_sub(1))
+    .map(|level| {
+      let score = if level == 0 {
+        version.files[0].len() as f64 / trigger as f64
+      } else {
+        version.level_bytes(level) as f64 / targets[level]
+      };
+      (level, score)
+    })
+    .collect();
+  // Stable: among equal scores the shallower level comes first.
+  scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+  (version.compaction_level, version.compaction_score) = match scores.first() {
+    Some(&(level, score)) => (level as i32, score),
+    None => (-1, 0.0),
+  };
+  version.compaction_scores = scores;
+  version.base_level = base_level;
 }
 
 /// Render an internal key as an escaped user-key string.
@@ -425,7 +704,7 @@ mod tests {
   }
 
   fn version_l1(ranges: &[(u64, &[u8], &[u8])]) -> Version {
-    let mut v = Version::new(Arc::new(BytewiseComparator));
+    let mut v = Version::new(7, Arc::new(BytewiseComparator));
     for &(n, lo, hi) in ranges {
       v.push_file_for_test(1, meta(n, lo, hi));
     }
@@ -505,7 +784,7 @@ mod tests {
   fn overlapping_inputs_l0_expands_transitively() {
     // L0 files can overlap each other, so a query that touches one pulls in
     // its transitively-overlapping neighbours.
-    let mut v = Version::new(Arc::new(BytewiseComparator));
+    let mut v = Version::new(7, Arc::new(BytewiseComparator));
     v.push_file_for_test(0, meta(1, b"a", b"e"));
     v.push_file_for_test(0, meta(2, b"d", b"h")); // overlaps file 1 (d..e)
     v.push_file_for_test(0, meta(3, b"t", b"z")); // disjoint
@@ -535,7 +814,7 @@ mod tests {
     }
     // With the reverse comparator, a file "covers" [hi, lo] in reverse order.
     // File smallest=z largest=a means the descending span from z down to a.
-    let mut v = Version::new(Arc::new(Reverse));
+    let mut v = Version::new(7, Arc::new(Reverse));
     v.push_file_for_test(1, meta(1, b"z", b"a"));
     // Query span from m down to b overlaps the file under reverse ordering.
     assert!(v.overlaps_level(1, b"m", b"b"));
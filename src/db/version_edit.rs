@@ -25,6 +25,38 @@ const TAG_DELETED_FILE: u64 = 6;
 const TAG_NEW_FILE: u64 = 7;
 const TAG_PREV_LOG_NUMBER: u64 = 9;
 
+// RocksDB column-family and atomic-group tags.  Edits that touch only the
+// default column family outside an atomic group use none of them, so they
+// stay readable by LevelDB.
+const TAG_COLUMN_FAMILY: u64 = 200;
+const TAG_COLUMN_FAMILY_ADD: u64 = 201;
+const TAG_COLUMN_FAMILY_DROP: u64 = 202;
+const TAG_MAX_COLUMN_FAMILY: u64 = 203;
+const TAG_IN_ATOMIC_GROUP: u64 = 300;
+
+// RocksDB tags for files that reference blob files.  `TAG_NEW_FILE4` is used
+// instead of `TAG_NEW_FILE` only for SSTables that hold blob references or
+// record their age, and the blob-file tags only by databases with blob files
+// enabled.
+const TAG_NEW_FILE4: u64 = 103;
+const TAG_BLOB_FILE_ADDITION: u64 = 400;
+const TAG_BLOB_FILE_GARBAGE: u64 = 401;
+
+// RocksDB tag for the user-defined timestamp below which a column family's
+// history may be collapsed; written only by families with timestamps.  It
+// lies in RocksDB's safe-to-ignore range (`1 << 13` and up).
+const TAG_FULL_HISTORY_TS_LOW: u64 = 8198;
+
+// Custom fields of a `TAG_NEW_FILE4` record.  A field tag with the
+// `NON_SAFE_IGNORE` bit set must be understood by the reader.
+const NEW_FILE_CUSTOM_TAG_TERMINATE: u64 = 1;
+const NEW_FILE_CUSTOM_TAG_OLDEST_ANCESTOR_TIME: u64 = 5;
+const NEW_FILE_CUSTOM_TAG_OLDEST_BLOB_FILE_NUMBER: u64 = 9;
+const NEW_FILE_CUSTOM_TAG_NON_SAFE_IGNORE: u64 = 1 << 6;
+
+/// Terminates the (empty) list of custom fields of a blob-file record.
+const BLOB_FILE_END_MARKER: u64 = 1;
+
 /// Metadata for one SSTable file.
 ///
 /// Open `Table` handles are managed by the [`TableCache`]; this struct carries
@@ -47,10 +79,50 @@ pub(crate) struct FileMetaData {
   /// without finding the target key; when it reaches ≤ 0 the file is
   /// flagged for seek-based compaction.
   pub allowed_seeks: AtomicI32,
+  /// Smallest blob file number referenced by this file, `0` if it holds no
+  /// blob references.  Used to pick the files to compact when blob garbage
+  /// collection is forced.
+  pub oldest_blob_file_number: u64,
+  /// When the oldest data in this file was written, in seconds since the
+  /// Unix epoch; `0` if unknown.  Recorded only by column families using
+  /// FIFO compaction, whose TTL reads it.
+  pub oldest_ancestor_time: u64,
 }
 
 impl FileMetaData {
   pub(crate) fn new(number: u64, file_size: u64, smallest: Vec<u8>, largest: Vec<u8>) -> Arc<Self> {
+    Self::with_oldest_blob_file(number, file_size, smallest, largest, 0)
+  }
+
+  /// Like [`new`](Self::new), for a file holding references to blob files
+  /// numbered `oldest_blob_file_number` and up.
+  pub(crate) fn with_oldest_blob_file(
+    number: u64,
+    file_size: u64,
+    smallest: Vec<u8>,
+    largest: Vec<u8>,
+    oldest_blob_file_number: u64,
+  ) -> Arc<Self> {
+    Self::with_oldest_ancestor_time(
+      number,
+      file_size,
+      smallest,
+      largest,
+      oldest_blob_file_number,
+      0,
+    )
+  }
+
+  /// Like [`with_oldest_blob_file`](Self::with_oldest_blob_file), for a file
+  /// whose oldest data was written at `oldest_ancestor_time`.
+  pub(crate) fn with_oldest_ancestor_time(
+    number: u64,
+    file_size: u64,
+    smallest: Vec<u8>,
+    largest: Vec<u8>,
+    oldest_blob_file_number: u64,
+    oldest_ancestor_time: u64,
+  ) -> Arc<Self> {
     let allowed_seeks = (file_size / 16384).max(100) as i32;
     Arc::new(Self {
       number,
@@ -58,10 +130,69 @@ impl FileMetaData {
       smallest,
       largest,
       allowed_seeks: AtomicI32::new(allowed_seeks),
+      oldest_blob_file_number,
+      oldest_ancestor_time,
     })
   }
 }
 
+/// A blob file written by a flush or compaction.
+///
+/// See `db/blob/blob_file_addition.h` in RocksDB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlobFileAddition {
+  pub blob_file_number: u64,
+  pub total_blob_count: u64,
+  /// Bytes of all blob records (record header, key and value).
+  pub total_blob_bytes: u64,
+}
+
+/// Blobs of one blob file that a compaction found to be no longer referenced.
+///
+/// See `db/blob/blob_file_garbage.h` in RocksDB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlobFileGarbage {
+  pub blob_file_number: u64,
+  pub garbage_blob_count: u64,
+  pub garbage_blob_bytes: u64,
+}
+
+/// A live blob file: its totals, fixed when it was written, and the garbage
+/// accumulated by compactions since.
+///
+/// See `db/blob/blob_file_meta.h` in RocksDB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlobFileMetaData {
+  pub number: u64,
+  pub total_blob_count: u64,
+  pub total_blob_bytes: u64,
+  pub garbage_blob_count: u64,
+  pub garbage_blob_bytes: u64,
+}
+
+impl BlobFileMetaData {
+  pub(crate) fn new(addition: &BlobFileAddition) -> Self {
+    BlobFileMetaData {
+      number: addition.blob_file_number,
+      total_blob_count: addition.total_blob_count,
+      total_blob_bytes: addition.total_blob_bytes,
+      garbage_blob_count: 0,
+      garbage_blob_bytes: 0,
+    }
+  }
+
+  /// Add `garbage` to this file's garbage counts.
+  pub(crate) fn add_garbage(&mut self, garbage: &BlobFileGarbage) {
+    self.garbage_blob_count += garbage.garbage_blob_count;
+    self.garbage_blob_bytes += garbage.garbage_blob_bytes;
+  }
+
+  /// `true` once no SSTable references any blob in the file.
+  pub(crate) fn is_obsolete(&self) -> bool {
+    self.garbage_blob_count >= self.total_blob_count
+  }
+}
+
 /// An atomic delta to the set of live SSTable files.
 ///
 /// Written to the MANIFEST log for crash-safe recovery. LevelDB-compatible
@@ -78,6 +209,24 @@ pub(crate) struct VersionEdit {
   pub deleted_files: Vec<(i32, u64)>,
   /// Compact pointers: `(level, largest_internal_key)` — round-robin compaction cursor.
   pub compact_pointers: Vec<(i32, Vec<u8>)>,
+  /// Blob files added.
+  pub blob_file_additions: Vec<BlobFileAddition>,
+  /// Blob garbage produced by a compaction.
+  pub blob_file_garbages: Vec<BlobFileGarbage>,
+  /// Column family the file and compact-pointer changes apply to (`0` = default).
+  pub column_family: u32,
+  /// Set when the edit creates `column_family` with this name.
+  pub column_family_add: Option<String>,
+  /// Set when the edit drops `column_family`.
+  pub column_family_drop: bool,
+  /// Largest column-family id ever allocated, so ids are never reused.
+  pub max_column_family: Option<u32>,
+  /// New cutoff timestamp for `column_family`: versions older than it may be
+  /// garbage-collected by compaction.
+  pub full_history_ts_low: Option<Vec<u8>>,
+  /// Number of edits that follow this one in the same atomic group.  A group
+  /// is applied on recovery only once its last edit (`Some(0)`) is read.
+  pub remaining_entries: Option<u32>,
 }
 
 impl VersionEdit {
@@ -91,6 +240,14 @@ impl VersionEdit {
       new_files: Vec::new(),
       deleted_files: Vec::new(),
       compact_pointers: Vec::new(),
+      blob_file_additions: Vec::new(),
+      blob_file_garbages: Vec::new(),
+      column_family: 0,
+      column_family_add: None,
+      column_family_drop: false,
+      max_column_family: None,
+      full_history_ts_low: None,
+      remaining_entries: None,
     }
   }
 
@@ -140,12 +297,96 @@ impl VersionEdit {
       push_varint!(number);
     }
     for (level, meta) in &self.new_files {
-      push_varint!(TAG_NEW_FILE);
+      let custom_fields: Vec<(u64, u64)> = [
+        (
+          NEW_FILE_CUSTOM_TAG_OLDEST_ANCESTOR_TIME,
+          meta.oldest_ancestor_time,
+        ),
+        (
+          NEW_FILE_CUSTOM_TAG_OLDEST_BLOB_FILE_NUMBER,
+          meta.oldest_blob_file_number,
+        ),
+      ]
+      .into_iter()
+      .filter(|&(_, v)| v != 0)
+      .collect();
+      if custom_fields.is_empty() {
+        push_varint!(TAG_NEW_FILE);
+      } else {
+        push_varint!(TAG_NEW_FILE4);
+      }
       push_varint!(*level as u64);
       push_varint!(meta.number);
       push_varint!(meta.file_size);
       encode_bytes(&mut buf, &meta.smallest);
       encode_bytes(&mut buf, &meta.largest);
+      if !custom_fields.is_empty() {
+        // Smallest and largest sequence numbers, which are not tracked.
+        push_varint!(0);
+        push_varint!(0);
+        for (field_tag, v) in custom_fields {
+          push_varint!(field_tag);
+          let len = write_varu64(&mut tmp, v);
+          let field = tmp[..len].to_vec();
+          encode_bytes(&mut buf, &field);
+        }
+        push_varint!(NEW_FILE_CUSTOM_TAG_TERMINATE);
+      }
+    }
+    for blob in &self.blob_file_additions {
+      push_varint!(TAG_BLOB_FILE_ADDITION);
+      let mut record = Vec::new();
+      for v in [
+        blob.blob_file_number,
+        blob.total_blob_count,
+        blob.total_blob_bytes,
+      ] {
+        let len = write_varu64(&mut tmp, v);
+        record.extend_from_slice(&tmp[..len]);
+      }
+      // Empty checksum method and value.
+      encode_bytes(&mut record, b"");
+      encode_bytes(&mut record, b"");
+      let len = write_varu64(&mut tmp, BLOB_FILE_END_MARKER);
+      record.extend_from_slice(&tmp[..len]);
+      encode_bytes(&mut buf, &record);
+    }
+    for garbage in &self.blob_file_garbages {
+      push_varint!(TAG_BLOB_FILE_GARBAGE);
+      let mut record = Vec::new();
+      for v in [
+        garbage.blob_file_number,
+        garbage.garbage_blob_count,
+        garbage.garbage_blob_bytes,
+        BLOB_FILE_END_MARKER,
+      ] {
+        let len = write_varu64(&mut tmp, v);
+        record.extend_from_slice(&tmp[..len]);
+      }
+      encode_bytes(&mut buf, &record);
+    }
+    if self.column_family != 0 {
+      push_varint!(TAG_COLUMN_FAMILY);
+      push_varint!(self.column_family as u64);
+    }
+    if let Some(ref name) = self.column_family_add {
+      push_varint!(TAG_COLUMN_FAMILY_ADD);
+      encode_bytes(&mut buf, name.as_bytes());
+    }
+    if self.column_family_drop {
+      push_varint!(TAG_COLUMN_FAMILY_DROP);
+    }
+    if let Some(v) = self.max_column_family {
+      push_varint!(TAG_MAX_COLUMN_FAMILY);
+      push_varint!(v as u64);
+    }
+    if let Some(ref ts) = self.full_history_ts_low {
+      push_varint!(TAG_FULL_HISTORY_TS_LOW);
+      encode_bytes(&mut buf, ts);
+    }
+    if let Some(v) = self.remaining_entries {
+      push_varint!(TAG_IN_ATOMIC_GROUP);
+      push_varint!(v as u64);
     }
     buf
   }
@@ -238,6 +479,113 @@ impl VersionEdit {
             FileMetaData::new(number, file_size, smallest, largest),
           ));
         }
+        TAG_NEW_FILE4 => {
+          let mut fields = [0u64; 3];
+          for (field, name) in fields.iter_mut().zip(["level", "number", "size"]) {
+            let (v, n) = read_varu64(&data[pos..]);
+            if n == 0 {
+              return Err(trunc(&format!("new_file4 {name}")));
+            }
+            pos += n;
+            *field = v;
+          }
+          let [level, number, file_size] = fields;
+          let (smallest, n) = decode_bytes(&data[pos..])?;
+          pos += n;
+          let (largest, n) = decode_bytes(&data[pos..])?;
+          pos += n;
+          // Smallest and largest sequence numbers.
+          for _ in 0..2 {
+            let (_, n) = read_varu64(&data[pos..]);
+            if n == 0 {
+              return Err(trunc("new_file4 seqno"));
+            }
+            pos += n;
+          }
+          let mut oldest_blob_file_number = 0;
+          let mut oldest_ancestor_time = 0;
+          loop {
+            let (field_tag, n) = read_varu64(&data[pos..]);
+            if n == 0 {
+              return Err(trunc("new_file4 custom tag"));
+            }
+            pos += n;
+            if field_tag == NEW_FILE_CUSTOM_TAG_TERMINATE {
+              break;
+            }
+            let (field, n) = decode_bytes(&data[pos..])?;
+            pos += n;
+            if field_tag == NEW_FILE_CUSTOM_TAG_OLDEST_BLOB_FILE_NUMBER {
+              let (v, n) = read_varu64(&field);
+              if n == 0 {
+                return Err(trunc("oldest_blob_file_number"));
+              }
+              oldest_blob_file_number = v;
+            } else if field_tag == NEW_FILE_CUSTOM_TAG_OLDEST_ANCESTOR_TIME {
+              let (v, n) = read_varu64(&field);
+              if n == 0 {
+                return Err(trunc("oldest_ancestor_time"));
+              }
+              oldest_ancestor_time = v;
+            } else if field_tag & NEW_FILE_CUSTOM_TAG_NON_SAFE_IGNORE != 0 {
+              return Err(Error::Corruption(format!(
+                "VersionEdit: unsupported new_file4 field {field_tag}"
+              )));
+            }
+          }
+          edit.new_files.push((
+            level as i32,
+            FileMetaData::with_oldest_ancestor_time(
+              number,
+              file_size,
+              smallest,
+              largest,
+              oldest_blob_file_number,
+              oldest_ancestor_time,
+            ),
+          ));
+        }
+        TAG_BLOB_FILE_ADDITION => {
+          let (record, n) = decode_bytes(&data[pos..])?;
+          pos += n;
+          let mut rpos = 0;
+          let mut fields = [0u64; 3];
+          for field in &mut fields {
+            let (v, n) = read_varu64(&record[rpos..]);
+            if n == 0 {
+              return Err(trunc("blob_file_addition"));
+            }
+            rpos += n;
+            *field = v;
+          }
+          // The checksum method and value are not used.
+          let [blob_file_number, total_blob_count, total_blob_bytes] = fields;
+          edit.blob_file_additions.push(BlobFileAddition {
+            blob_file_number,
+            total_blob_count,
+            total_blob_bytes,
+          });
+        }
+        TAG_BLOB_FILE_GARBAGE => {
+          let (record, n) = decode_bytes(&data[pos..])?;
+          pos += n;
+          let mut rpos = 0;
+          let mut fields = [0u64; 3];
+          for field in &mut fields {
+            let (v, n) = read_varu64(&record[rpos..]);
+            if n == 0 {
+              return Err(trunc("blob_file_garbage"));
+            }
+            rpos += n;
+            *field = v;
+          }
+          let [blob_file_number, garbage_blob_count, garbage_blob_bytes] = fields;
+          edit.blob_file_garbages.push(BlobFileGarbage {
+            blob_file_number,
+            garbage_blob_count,
+            garbage_blob_bytes,
+          });
+        }
         TAG_COMPACT_POINTER => {
           let (level, n) = read_varu64(&data[pos..]);
           if n == 0 {
@@ -248,6 +596,45 @@ impl VersionEdit {
           pos += n;
           edit.compact_pointers.push((level as i32, key));
         }
+        TAG_COLUMN_FAMILY => {
+          let (v, n) = read_varu64(&data[pos..]);
+          if n == 0 {
+            return Err(trunc("column_family"));
+          }
+          pos += n;
+          edit.column_family = v as u32;
+        }
+        TAG_COLUMN_FAMILY_ADD => {
+          let (name_bytes, n) = decode_bytes(&data[pos..])?;
+          pos += n;
+          edit.column_family_add = Some(String::from_utf8(name_bytes).map_err(|e| {
+            Error::Corruption(format!("VersionEdit: invalid column family name: {e}"))
+          })?);
+        }
+        TAG_COLUMN_FAMILY_DROP => {
+          edit.column_family_drop = true;
+        }
+        TAG_MAX_COLUMN_FAMILY => {
+          let (v, n) = read_varu64(&data[pos..]);
+          if n == 0 {
+            return Err(trunc("max_column_family"));
+          }
+          pos += n;
+          edit.max_column_family = Some(v as u32);
+        }
+        TAG_FULL_HISTORY_TS_LOW => {
+          let (ts, n) = decode_bytes(&data[pos..])?;
+          pos += n;
+          edit.full_history_ts_low = Some(ts);
+        }
+        TAG_IN_ATOMIC_GROUP => {
+          let (v, n) = read_varu64(&data[pos..]);
+          if n == 0 {
+            return Err(trunc("remaining_entries"));
+          }
+          pos += n;
+          edit.remaining_entries = Some(v as u32);
+        }
         TAG_COMPARATOR => {
           let (name_bytes, n) = decode_bytes(&data[pos..])?;
           pos += n;
@@ -338,6 +725,108 @@ mod tests {
     assert_eq!(meta.largest, b"zzz\x01\x00\x00\x00\x00\x00\x00\x00\x00");
   }
 
+  #[test]
+  fn encode_decode_column_family_fields() {
+    let mut edit = VersionEdit::new();
+    edit.comparator_name = Some("leveldb.BytewiseComparator".to_owned());
+    edit.column_family = 3;
+    edit.column_family_add = Some("cf".to_owned());
+    edit.max_column_family = Some(3);
+    edit.remaining_entries = Some(1);
+
+    let decoded = VersionEdit::decode(&edit.encode()).unwrap();
+    assert_eq!(decoded.column_family, 3);
+    assert_eq!(decoded.column_family_add.as_deref(), Some("cf"));
+    assert!(!decoded.column_family_drop);
+    assert_eq!(decoded.max_column_family, Some(3));
+    assert_eq!(decoded.remaining_entries, Some(1));
+
+    let mut drop = VersionEdit::new();
+    drop.column_family = 3;
+    drop.column_family_drop = true;
+    let decoded = VersionEdit::decode(&drop.encode()).unwrap();
+    assert_eq!(decoded.column_family, 3);
+    assert!(decoded.column_family_drop);
+    assert!(decoded.column_family_add.is_none());
+  }
+
+  #[test]
+  fn encode_decode_full_history_ts_low() {
+    let mut edit = VersionEdit::new();
+    edit.column_family = 2;
+    edit.full_history_ts_low = Some(42u64.to_le_bytes().to_vec());
+    let decoded = VersionEdit::decode(&edit.encode()).unwrap();
+    assert_eq!(decoded.column_family, 2);
+    assert_eq!(decoded.full_history_ts_low, edit.full_history_ts_low);
+  }
+
+  #[test]
+  fn encode_decode_blob_file_fields() {
+    let mut edit = VersionEdit::new();
+    edit.new_files.push((
+      2,
+      FileMetaData::with_oldest_blob_file(9, 512, b"a".repeat(9), b"b".repeat(9), 4),
+    ));
+    edit.new_files.push((
+      2,
+      FileMetaData::new(10, 256, b"c".repeat(9), b"d".repeat(9)),
+    ));
+    edit.blob_file_additions.push(BlobFileAddition {
+      blob_file_number: 4,
+      total_blob_count: 3,
+      total_blob_bytes: 3000,
+    });
+    edit.blob_file_garbages.push(BlobFileGarbage {
+      blob_file_number: 4,
+      garbage_blob_count: 1,
+      garbage_blob_bytes: 1000,
+    });
+    edit.last_sequence = Some(7);
+
+    let decoded = VersionEdit::decode(&edit.encode()).unwrap();
+    assert_eq!(decoded.new_files.len(), 2);
+    assert_eq!(decoded.new_files[0].1.number, 9);
+    assert_eq!(decoded.new_files[0].1.largest, b"b".repeat(9));
+    assert_eq!(decoded.new_files[0].1.oldest_blob_file_number, 4);
+    assert_eq!(decoded.new_files[1].1.oldest_blob_file_number, 0);
+    assert_eq!(decoded.blob_file_additions, edit.blob_file_additions);
+    assert_eq!(decoded.blob_file_garbages, edit.blob_file_garbages);
+    assert_eq!(decoded.last_sequence, Some(7));
+  }
+
+  #[test]
+  fn encode_decode_oldest_ancestor_time() {
+    let mut edit = VersionEdit::new();
+    edit.new_files.push((
+      0,
+      FileMetaData::with_oldest_ancestor_time(
+        9,
+        512,
+        b"a".repeat(9),
+        b"b".repeat(9),
+        0,
+        1_700_000_000,
+      ),
+    ));
+    edit.new_files.push((
+      0,
+      FileMetaData::with_oldest_ancestor_time(
+        10,
+        256,
+        b"c".repeat(9),
+        b"d".repeat(9),
+        4,
+        1_600_000_000,
+      ),
+    ));
+
+    let decoded = VersionEdit::decode(&edit.encode()).unwrap();
+    assert_eq!(decoded.new_files[0].1.oldest_ancestor_time, 1_700_000_000);
+    assert_eq!(decoded.new_files[0].1.oldest_blob_file_number, 0);
+    assert_eq!(decoded.new_files[1].1.oldest_ancestor_time, 1_600_000_000);
+    assert_eq!(decoded.new_files[1].1.oldest_blob_file_number, 4);
+  }
+
   #[test]
   fn encode_decode_empty() {
     let edit = VersionEdit::new();
@@ -10,13 +10,16 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
+use crate::column_family::{DEFAULT_COLUMN_FAMILY_ID, DEFAULT_COLUMN_FAMILY_NAME};
 use crate::db::table_cache::TableCache;
 use crate::db::version::Version;
-use crate::db::version_edit::{FileMetaData, VersionEdit};
+use crate::db::version_edit::{BlobFileMetaData, FileMetaData, VersionEdit};
 use crate::error::Error;
 use crate::logfile::reader::Reader as LogReader;
 use crate::logfile::writer::Writer as LogWriter;
-use std::collections::{HashMap, HashSet};
+use crate::options::Options;
+use crate::table::format::parse_internal_key;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -55,28 +58,63 @@ fn read_current_file(path: &Path, fs: &dyn crate::env::FileSystem) -> Result<Str
 
 // ── VersionSet ────────────────────────────────────────────────────────────────
 
-/// Manages the set of live SSTable files across all levels.
+/// The per-column-family part of a `VersionSet`: the family's live files and
+/// its round-robin compaction cursor.
 ///
-/// Writes every change (flush, compaction) to the MANIFEST log so the database
-/// can be recovered without replaying the entire WAL.
-pub(crate) struct VersionSet {
+/// See `db/column_family.h: ColumnFamilyData` in RocksDB.
+struct ColumnFamilyVersions {
+  name: String,
   current: Arc<Version>,
+  /// Round-robin compaction cursor per level: the largest internal key last compacted.
+  /// Empty vec = not yet set.  Persisted to MANIFEST via TAG_COMPACT_POINTER.
+  compact_pointer: Vec<Vec<u8>>,
+  /// The family's options.  Every `Version` built for it has
+  /// `options.num_levels` levels, is ordered by `options.comparator`, and is
+  /// scored against the level targets.
+  options: Arc<Options>,
+}
+
+impl ColumnFamilyVersions {
+  fn new(name: &str, options: Arc<Options>) -> Self {
+    ColumnFamilyVersions {
+      name: name.to_owned(),
+      current: Arc::new(Version::new(
+        options.num_levels,
+        Arc::clone(&options.comparator),
+      )),
+      compact_pointer: vec![Vec::new(); options.num_levels],
+      options,
+    }
+  }
+}
+
+/// Manages the set of live SSTable files across all levels of every column
+/// family.
+///
+/// Writes every change (flush, compaction, column-family creation and drop)
+/// to the MANIFEST log so the database can be recovered without replaying the
+/// entire WAL.  File numbers, sequence numbers and the WAL are shared by all
+/// column families.
+pub(crate) struct VersionSet {
+  /// Live column families by id.  The default family (id 0) always exists.
+  column_families: BTreeMap<u32, ColumnFamilyVersions>,
+  /// Largest column-family id ever allocated; ids of dropped families are
+  /// never reused.
+  max_column_family: u32,
   /// Next file number to allocate.  Incremented by `next_file_number()`.
   next_file_number: u64,
   /// Sequence number of the last write reflected in the current `Version`.
   last_sequence: u64,
   /// File number of the current WAL.
   log_number: u64,
-  /// Open writer for the MANIFEST log.
-  manifest_log: LogWriter,
+  /// Open writer for the MANIFEST log; `None` for a read-only `VersionSet`.
+  manifest_log: Option<LogWriter>,
+  /// Where `catch_up` resumes reading the MANIFEST: one past the start of
+  /// the last record of the last complete edit group applied.
+  manifest_resume_offset: u64,
   /// File number of the MANIFEST file (used to update CURRENT on rotation in Phase 6).
   #[allow(dead_code)]
   pub(crate) manifest_number: u64,
-  /// Round-robin compaction cursor per level: the largest internal key last compacted.
-  /// Empty vec = not yet set.  Persisted to MANIFEST via TAG_COMPACT_POINTER.
-  compact_pointer: [Vec<u8>; crate::db::version::NUM_LEVELS],
-  /// User-key comparator, stamped onto every `Version` this set builds.
-  comparator: Arc<dyn crate::comparator::Comparator>,
 }
 
 impl VersionSet {
@@ -85,10 +123,12 @@ impl VersionSet {
   /// - Creates `MANIFEST-000002` and writes the initial `VersionEdit`.
   /// - Writes `CURRENT` pointing to that file.
   ///
+  /// `options` configures the default column family.
+  ///
   /// File numbering: 1 = WAL, 2 = MANIFEST, 3+ = SSTables.
   pub(crate) fn create(
     path: &Path,
-    comparator: Arc<dyn crate::comparator::Comparator>,
+    options: Arc<Options>,
     fs: &dyn crate::env::FileSystem,
   ) -> Result<Self, Error> {
     let manifest_number = 2u64;
@@ -99,7 +139,7 @@ impl VersionSet {
 
     // Write the initial VersionEdit so recovery always finds a valid sequence.
     let mut edit = VersionEdit::new();
-    edit.comparator_name = Some(comparator.name().to_owned());
+    edit.comparator_name = Some(options.comparator.name().to_owned());
     edit.next_file_number = Some(3); // SSTables start at 3
     edit.last_sequence = Some(0);
     edit.log_number = Some(1);
@@ -110,28 +150,48 @@ impl VersionSet {
     write_current_file(path, manifest_number, fs)?;
 
     Ok(VersionSet {
-      current: Arc::new(Version::new(Arc::clone(&comparator))),
+      column_families: BTreeMap::from([(
+        DEFAULT_COLUMN_FAMILY_ID,
+        ColumnFamilyVersions::new(DEFAULT_COLUMN_FAMILY_NAME, options),
+      )]),
+      max_column_family: DEFAULT_COLUMN_FAMILY_ID,
       next_file_number: 3,
       last_sequence: 0,
       log_number: 1,
-      manifest_log,
+      manifest_log: Some(manifest_log),
+      manifest_resume_offset: 0,
       manifest_number,
-      compact_pointer: std::array::from_fn(|_| Vec::new()),
-      comparator,
     })
   }
 
   /// Recover a `VersionSet` from an existing MANIFEST.
   ///
   /// Reads `CURRENT`, replays each `VersionEdit` in the MANIFEST log, and
-  /// assembles the initial `Version` (metadata only — tables are opened lazily
-  /// by the `TableCache` on first access).  Returns a `VersionSet` ready for
-  /// `log_and_apply`.
+  /// assembles the initial `Version` of every column family (metadata only —
+  /// tables are opened lazily by the `TableCache` on first access).  Returns a
+  /// `VersionSet` ready for `log_and_apply`.
+  ///
+  /// `options` configures the default column family; `column_families` names
+  /// the options of every other family the caller is opening.  Returns
+  /// [`Error::InvalidArgument`] if the MANIFEST holds a live family missing
+  /// from `column_families`, if any comparator name differs from the one
+  /// recorded when the family was created, or if a family has files at a
+  /// level its `num_levels` does not reach.  Families in `column_families`
+  /// that the MANIFEST does not know are ignored.
+  ///
+  /// With `read_only`, the MANIFEST is not reopened for appending (every
+  /// method that writes an edit returns [`Error::NotSupported`]) and live
+  /// families missing from `column_families` are left out rather than
+  /// rejected.
+  ///
+  /// See `db/version_set.cc: VersionSet::Recover` in RocksDB.
   pub(crate) fn recover(
     path: &Path,
     paranoid_checks: bool,
-    comparator: Arc<dyn crate::comparator::Comparator>,
+    options: Arc<Options>,
+    column_families: &[(&str, Arc<Options>)],
     fs: &dyn crate::env::FileSystem,
+    read_only: bool,
   ) -> Result<Self, Error> {
     let manifest_name = read_current_file(path, fs)?;
     let manifest_path = path.join(&manifest_name);
@@ -143,15 +203,19 @@ impl VersionSet {
     let manifest_file_for_read = fs.open_sequential(&manifest_path)?;
     let mut reader = LogReader::new(manifest_file_for_read, None, paranoid_checks, 0);
 
-    let mut builder = Builder::new();
+    let mut replay = Replay::new();
+    let mut manifest_resume_offset = 0;
     let mut first_edit = true;
+    // Edits of an atomic group are held back until the group's last edit is
+    // read.  A group cut short by a crash is dropped as a whole.
+    let mut group: Vec<VersionEdit> = Vec::new();
     while let Some(record) = reader.read_record() {
       let edit = VersionEdit::decode(&record)?;
       // The first edit in the MANIFEST records the comparator name.
       // Verify it matches the comparator the caller is using.
       if first_edit {
         if let Some(ref stored_name) = edit.comparator_name {
-          let expected = comparator.name();
+          let expected = options.comparator.name();
           if stored_name != expected {
             return Err(Error::InvalidArgument(format!(
               "comparator mismatch: DB uses {stored_name}, caller uses {expected}"
@@ -160,61 +224,301 @@ impl VersionSet {
         }
         first_edit = false;
       }
-      builder.apply(&edit);
+      let in_group = edit.remaining_entries.is_some_and(|n| n > 0);
+      group.push(edit);
+      if !in_group {
+        for edit in group.drain(..) {
+          replay.apply(&edit)?;
+        }
+        manifest_resume_offset = reader.last_record_offset() + 1;
+      }
     }
 
-    // Extract scalar fields before `build` consumes the builder.
-    let next_file_number = builder.next_file_number;
-    let last_sequence = builder.last_sequence;
-    let log_number = builder.log_number;
-    // Clone compact_pointer before builder is consumed by build().
-    let compact_pointer: [Vec<u8>; crate::db::version::NUM_LEVELS] =
-      std::array::from_fn(|i| builder.compact_pointer[i].clone());
-
-    let mut files = builder.build(Arc::clone(&comparator));
-    // Compute compaction scores on the recovered version.
-    crate::db::version::finalize(&mut files);
+    // Resolve each surviving family's options and build its Version.
+    let mut recovered = BTreeMap::new();
+    for (id, (name, comparator_name, builder)) in replay.column_families {
+      let cf_options = if id == DEFAULT_COLUMN_FAMILY_ID {
+        Arc::clone(&options)
+      } else {
+        let listed = column_families
+          .iter()
+          .find(|(n, _)| *n == name)
+          .map(|(_, o)| Arc::clone(o));
+        let cf_options = match listed {
+          Some(cf_options) => cf_options,
+          None if read_only => continue,
+          None => {
+            return Err(Error::InvalidArgument(format!(
+              "column family not opened: {name}"
+            )))
+          }
+        };
+        if let Some(stored_name) = comparator_name {
+          let expected = cf_options.comparator.name();
+          if stored_name != expected {
+            return Err(Error::InvalidArgument(format!(
+              "comparator mismatch for column family {name}: DB uses {stored_name}, caller uses {expected}"
+            )));
+          }
+        }
+        cf_options
+      };
+      check_levels(
+        &name,
+        builder.added.keys().map(|&(level, _)| level),
+        &cf_options,
+      )?;
+      let mut compact_pointer = builder.compact_pointer.clone();
+      compact_pointer.resize(cf_options.num_levels, Vec::new());
+      let mut files = builder.build(&cf_options);
+      // Compute compaction scores on the recovered version.
+      crate::db::version::finalize(&mut files, &cf_options);
+      recovered.insert(
+        id,
+        ColumnFamilyVersions {
+          name,
+          current: Arc::new(files),
+          compact_pointer,
+          options: cf_options,
+        },
+      );
+    }
 
     // Re-open the MANIFEST for appending (continue after the last record).
-    let manifest_len = fs.file_size(&manifest_path)?;
-    let manifest_file_for_write = fs.open_appendable(&manifest_path)?;
-    let manifest_log = LogWriter::new(manifest_file_for_write, manifest_len);
+    let manifest_log = if read_only {
+      None
+    } else {
+      let manifest_len = fs.file_size(&manifest_path)?;
+      let manifest_file_for_write = fs.open_appendable(&manifest_path)?;
+      Some(LogWriter::new(manifest_file_for_write, manifest_len))
+    };
 
     Ok(VersionSet {
-      current: Arc::new(files),
-      next_file_number,
-      last_sequence,
-      log_number,
+      column_families: recovered,
+      max_column_family: replay.max_column_family,
+      next_file_number: replay.next_file_number,
+      last_sequence: replay.last_sequence,
+      log_number: replay.log_number,
       manifest_log,
+      manifest_resume_offset,
       manifest_number,
-      compact_pointer,
-      comparator,
     })
   }
 
-  /// Apply `edit` to the current `Version` and append it to the MANIFEST.
+  /// Apply the edits appended to the MANIFEST at `path` since it was last
+  /// read; used by a secondary instance to follow its primary.
+  ///
+  /// Edits of an atomic group are applied only once the whole group is on
+  /// disk, and a record the primary is still writing is left for the next
+  /// call.  Edits for column families this `VersionSet` does not hold are
+  /// skipped (a family the primary creates is not opened); a dropped family
+  /// is removed.  Deleted files are evicted from the family's cache in
+  /// `table_caches`.  Returns `true` if any edit was read.
+  ///
+  /// Returns [`Error::NotSupported`] if `CURRENT` names another MANIFEST, as
+  /// after [`Db::repair`]; the secondary must then be reopened.  Returns
+  /// [`Error::InvalidArgument`] if the primary adds a file at a level beyond
+  /// the family's `num_levels`.
+  ///
+  /// See `db/version_set.cc: ReactiveVersionSet::ReadAndApply` in RocksDB.
+  ///
+  /// [`Db::repair`]: crate::Db::repair
+  pub(crate) fn catch_up(
+    &mut self,
+    path: &Path,
+    paranoid_checks: bool,
+    table_caches: &BTreeMap<u32, TableCache>,
+    fs: &dyn crate::env::FileSystem,
+  ) -> Result<bool, Error> {
+    let manifest_name = read_current_file(path, fs)?;
+    if manifest_name != manifest_filename(self.manifest_number) {
+      return Err(Error::NotSupported(format!(
+        "the primary switched to {manifest_name}; reopen the secondary"
+      )));
+    }
+    let file = fs.open_sequential(&path.join(&manifest_name))?;
+    let mut reader = LogReader::new(file, None, paranoid_checks, self.manifest_resume_offset);
+    let start = self.manifest_resume_offset;
+    let mut group = Vec::new();
+    while let Some(record) = reader.read_record() {
+      let edit = VersionEdit::decode(&record)?;
+      let in_group = edit.remaining_entries.is_some_and(|n| n > 0);
+      group.push(edit);
+      if in_group {
+        continue;
+      }
+      for edit in group.drain(..) {
+        if let Some(v) = edit.log_number {
+          self.log_number = v;
+        }
+        if let Some(v) = edit.next_file_number {
+          self.next_file_number = v;
+        }
+        if let Some(v) = edit.last_sequence {
+          self.last_sequence = v;
+        }
+        if let Some(v) = edit.max_column_family {
+          self.max_column_family = self.max_column_family.max(v);
+        }
+        let id = edit.column_family;
+        if edit.column_family_add.is_some() {
+          self.max_column_family = self.max_column_family.max(id);
+        } else if edit.column_family_drop {
+          self.column_families.remove(&id);
+        } else if let Some(cf) = self.column_families.get(&id) {
+          let levels = edit.new_files.iter().map(|&(level, _)| level);
+          check_levels(&cf.name, levels, &cf.options)?;
+          if let Some(tc) = table_caches.get(&id) {
+            self.install(&edit, tc);
+          }
+        }
+      }
+      self.manifest_resume_offset = reader.last_record_offset() + 1;
+    }
+    Ok(self.manifest_resume_offset != start)
+  }
+
+  /// Apply `edit` to the current `Version` of `edit.column_family` and append
+  /// it to the MANIFEST.
   ///
   /// Fills in `next_file_number`, `last_sequence`, and `log_number` from the
   /// current `VersionSet` state before encoding.  Deleted files are evicted
   /// from `tc` so their file handles are released.
+  ///
+  /// Returns [`Error::InvalidArgument`] if the column family has been dropped.
   pub(crate) fn log_and_apply(
     &mut self,
     edit: &mut VersionEdit,
     tc: &TableCache,
   ) -> Result<(), Error> {
-    edit.next_file_number = Some(self.next_file_number);
-    edit.last_sequence = Some(self.last_sequence);
-    edit.log_number = Some(self.log_number);
+    self.check_column_family(edit.column_family)?;
+    self.stamp(edit);
 
-    self.manifest_log.add_record(&edit.encode())?;
+    let manifest_log = self.manifest_writer()?;
+    manifest_log.add_record(&edit.encode())?;
     // Sync before installing the new version in memory: once this returns,
     // callers may delete files (old WALs, compacted SSTables) that are only
     // recoverable while the pre-edit MANIFEST state is intact on disk.
-    self.manifest_log.sync()?;
+    manifest_log.sync()?;
 
-    // Build new Version by cloning current and applying additions/deletions.
-    let mut new_files: [Vec<Arc<FileMetaData>>; 7] =
-      std::array::from_fn(|i| self.current.files_at(i).to_vec());
+    self.install(edit, tc);
+    Ok(())
+  }
+
+  /// Write `edits` to the MANIFEST as one atomic group and apply each to its
+  /// column family, evicting deleted files from the paired table cache.
+  ///
+  /// Recovery applies either every edit of the group or none of them, so a
+  /// change spanning several column families (a flush of all memtables into
+  /// one new WAL) cannot be half-recorded by a crash.  A single edit is
+  /// written as a plain record.
+  ///
+  /// See `db/version_set.cc: VersionSet::LogAndApply` (atomic groups) in RocksDB.
+  pub(crate) fn log_and_apply_group(
+    &mut self,
+    mut edits: Vec<(VersionEdit, &TableCache)>,
+  ) -> Result<(), Error> {
+    for (edit, _) in &edits {
+      self.check_column_family(edit.column_family)?;
+    }
+    self.manifest_writer()?;
+    let count = edits.len() as u32;
+    for (i, (edit, _)) in edits.iter_mut().enumerate() {
+      self.stamp(edit);
+      if count > 1 {
+        edit.remaining_entries = Some(count - 1 - i as u32);
+      }
+      self.manifest_writer()?.add_record(&edit.encode())?;
+    }
+    self.manifest_writer()?.sync()?;
+
+    for (edit, tc) in &edits {
+      self.install(edit, tc);
+    }
+    Ok(())
+  }
+
+  /// Create column family `name` with the (previously unused) id `id`,
+  /// recording it in the MANIFEST.
+  ///
+  /// See `db/version_set.cc: VersionSet::CreateColumnFamily` in RocksDB.
+  pub(crate) fn create_column_family(
+    &mut self,
+    id: u32,
+    name: &str,
+    options: Arc<Options>,
+  ) -> Result<(), Error> {
+    debug_assert!(id > self.max_column_family);
+    let mut edit = VersionEdit::new();
+    edit.comparator_name = Some(options.comparator.name().to_owned());
+    edit.column_family = id;
+    edit.column_family_add = Some(name.to_owned());
+    edit.max_column_family = Some(id);
+    self.stamp(&mut edit);
+    let manifest_log = self.manifest_writer()?;
+    manifest_log.add_record(&edit.encode())?;
+    manifest_log.sync()?;
+
+    self
+      .column_families
+      .insert(id, ColumnFamilyVersions::new(name, options));
+    self.max_column_family = id;
+    Ok(())
+  }
+
+  /// Drop column family `id`, recording the drop in the MANIFEST.  Its files
+  /// stop being live and are removed by the next obsolete-file sweep.
+  pub(crate) fn drop_column_family(&mut self, id: u32) -> Result<(), Error> {
+    self.check_column_family(id)?;
+    let mut edit = VersionEdit::new();
+    edit.column_family = id;
+    edit.column_family_drop = true;
+    self.stamp(&mut edit);
+    let manifest_log = self.manifest_writer()?;
+    manifest_log.add_record(&edit.encode())?;
+    manifest_log.sync()?;
+
+    self.column_families.remove(&id);
+    Ok(())
+  }
+
+  /// The MANIFEST writer, or [`Error::NotSupported`] for a read-only
+  /// `VersionSet`.
+  fn manifest_writer(&mut self) -> Result<&mut LogWriter, Error> {
+    self
+      .manifest_log
+      .as_mut()
+      .ok_or_else(|| Error::NotSupported("the MANIFEST was opened read-only".to_owned()))
+  }
+
+  fn check_column_family(&self, id: u32) -> Result<(), Error> {
+    if self.column_families.contains_key(&id) {
+      Ok(())
+    } else {
+      Err(Error::InvalidArgument(format!(
+        "column family {id} does not exist"
+      )))
+    }
+  }
+
+  /// Fill in the fields every MANIFEST record carries.
+  fn stamp(&self, edit: &mut VersionEdit) {
+    edit.next_file_number = Some(self.next_file_number);
+    edit.last_sequence = Some(self.last_sequence);
+    edit.log_number = Some(self.log_number);
+  }
+
+  /// Build a new `Version` for `edit.column_family` by cloning its current one
+  /// and applying the edit's additions and deletions.
+  fn install(&mut self, edit: &VersionEdit, tc: &TableCache) {
+    let cf = self
+      .column_families
+      .get_mut(&edit.column_family)
+      .expect("install: column family checked before logging");
+
+    let mut new_files: Vec<Vec<Arc<FileMetaData>>> = (0..cf.current.num_levels())
+      .map(|level| cf.current.files_at(level).to_vec())
+      .collect();
 
     for &(level, number) in &edit.deleted_files {
       let level = level as usize;
@@ -222,19 +526,15 @@ impl VersionSet {
       tc.evict(number);
     }
     for (level, meta) in &edit.new_files {
-      let level = *level as usize;
-      if level == 0 {
-        // L0: newest-first — prepend.
-        new_files[0].insert(0, Arc::clone(meta));
-      } else {
-        // L1+: append; sorted below.
-        new_files[level].push(Arc::clone(meta));
-      }
+      new_files[*level as usize].push(Arc::clone(meta));
     }
 
-    // Keep L1–L6 sorted by smallest user key so binary search and overlap
-    // checks work correctly.  L0 files are intentionally newest-first.
-    let cmp = &*self.comparator;
+    // L0: newest-first by file number, as `build` orders it on recovery.  A
+    // FIFO merge into L0 is installed after flushes numbered after it.
+    new_files[0].sort_by_key(|f| std::cmp::Reverse(f.number));
+    // Keep L1 and below sorted by smallest user key so binary search and
+    // overlap checks work correctly.
+    let cmp = &*cf.options.comparator;
     for level_files in new_files.iter_mut().skip(1) {
       level_files.sort_by(|a, b| {
         let ak = crate::table::format::user_key(&a.smallest);
@@ -245,13 +545,21 @@ impl VersionSet {
 
     // Apply compact-pointer updates from this edit.
     for (level, key) in &edit.compact_pointers {
-      self.compact_pointer[*level as usize] = key.clone();
+      cf.compact_pointer[*level as usize] = key.clone();
     }
 
-    let mut v = Version::from_files(new_files, Arc::clone(&self.comparator));
-    crate::db::version::finalize(&mut v);
-    self.current = Arc::new(v);
-    Ok(())
+    let mut blob_files = cf.current.blob_files().clone();
+    for number in apply_blob_file_edits(&mut blob_files, edit) {
+      tc.evict(number);
+    }
+
+    let mut v = Version::from_parts(new_files, blob_files, Arc::clone(&cf.options.comparator));
+    v.full_history_ts_low = edit
+      .full_history_ts_low
+      .clone()
+      .or_else(|| cf.current.full_history_ts_low.clone());
+    crate::db::version::finalize(&mut v, &cf.options);
+    cf.current = Arc::new(v);
   }
 
   // ── Accessors ───────────────────────────────────────────────────────────────
@@ -263,6 +571,12 @@ impl VersionSet {
     n
   }
 
+  /// The number the next call to [`next_file_number`](Self::next_file_number)
+  /// will return.
+  pub(crate) fn current_next_file_number(&self) -> u64 {
+    self.next_file_number
+  }
+
   pub(crate) fn last_sequence(&self) -> u64 {
     self.last_sequence
   }
@@ -279,63 +593,164 @@ impl VersionSet {
     self.log_number = n;
   }
 
-  pub(crate) fn current(&self) -> Arc<Version> {
-    Arc::clone(&self.current)
+  /// Current `Version` of column family `id`, or `None` if it has been dropped.
+  pub(crate) fn current(&self, id: u32) -> Option<Arc<Version>> {
+    self
+      .column_families
+      .get(&id)
+      .map(|cf| Arc::clone(&cf.current))
+  }
+
+  /// Read-only access to the compact-pointer array (one entry per level) of
+  /// column family `id`.
+  pub(crate) fn compact_pointer(&self, id: u32) -> Option<&[Vec<u8>]> {
+    self
+      .column_families
+      .get(&id)
+      .map(|cf| cf.compact_pointer.as_slice())
   }
 
-  /// Read-only access to the compact-pointer array (one entry per level).
-  pub(crate) fn compact_pointer(&self) -> &[Vec<u8>; crate::db::version::NUM_LEVELS] {
-    &self.compact_pointer
+  /// `(id, name)` of every live column family, in id order.
+  pub(crate) fn column_families(&self) -> impl Iterator<Item = (u32, &str)> {
+    self
+      .column_families
+      .iter()
+      .map(|(&id, cf)| (id, cf.name.as_str()))
+  }
+
+  pub(crate) fn max_column_family(&self) -> u32 {
+    self.max_column_family
   }
 
   pub(crate) fn manifest_number(&self) -> u64 {
     self.manifest_number
   }
 
-  /// Add the file numbers of every live SSTable across all levels to `live`.
+  /// Add the file numbers of every live SSTable and blob file of every column
+  /// family to `live`.
   ///
   /// Called by `delete_obsolete_files` to compute the set of files that must
   /// not be deleted.  Matches LevelDB's `VersionSet::AddLiveFiles`.
   pub(crate) fn add_live_files(&self, live: &mut HashSet<u64>) {
-    for level in 0..crate::db::version::NUM_LEVELS {
-      for meta in self.current.files_at(level) {
-        live.insert(meta.number);
+    for cf in self.column_families.values() {
+      for level in 0..cf.current.num_levels() {
+        for meta in cf.current.files_at(level) {
+          live.insert(meta.number);
+        }
       }
+      live.extend(cf.current.blob_files().keys());
     }
   }
+
+  /// The largest sequence number in the boundary keys of any live SSTable.
+  ///
+  /// Ingested files get a sequence number the MANIFEST's last sequence does
+  /// not cover, so recovery raises the last sequence to at least this.
+  pub(crate) fn max_file_sequence(&self) -> u64 {
+    let mut max = 0;
+    for cf in self.column_families.values() {
+      for level in 0..cf.current.num_levels() {
+        for meta in cf.current.files_at(level) {
+          for key in [&meta.smallest, &meta.largest] {
+            if let Some((_, seq, _)) = parse_internal_key(key) {
+              max = max.max(seq);
+            }
+          }
+        }
+      }
+    }
+    max
+  }
 }
 
-// ── Builder ───────────────────────────────────────────────────────────────────
+/// Names of the column families recorded in the MANIFEST at `path`, default
+/// first and the rest in creation order.
+///
+/// See `db/version_set.cc: VersionSet::ListColumnFamilies` in RocksDB.
+pub(crate) fn list_column_families(
+  path: &Path,
+  fs: &dyn crate::env::FileSystem,
+) -> Result<Vec<String>, Error> {
+  let manifest_name = read_current_file(path, fs)?;
+  let file = fs.open_sequential(&path.join(&manifest_name))?;
+  let mut reader = LogReader::new(file, None, true, 0);
+  let mut names = BTreeMap::from([(
+    DEFAULT_COLUMN_FAMILY_ID,
+    DEFAULT_COLUMN_FAMILY_NAME.to_owned(),
+  )]);
+  while let Some(record) = reader.read_record() {
+    let edit = VersionEdit::decode(&record)?;
+    if let Some(name) = edit.column_family_add {
+      names.insert(edit.column_family, name);
+    } else if edit.column_family_drop {
+      names.remove(&edit.column_family);
+    }
+  }
+  Ok(names.into_values().collect())
+}
 
-/// Accumulates `VersionEdit` deltas during MANIFEST replay.
+/// Apply the blob-file additions and garbage of `edit` to `blob_files`,
+/// removing the files that no longer hold live blobs.  Returns the numbers of
+/// the removed files.
 ///
-/// After all records have been applied, `build` opens the live SSTable files
-/// and constructs the initial `Version`.
-struct Builder {
-  /// Live files keyed by `(level, file_number)`.
-  added: HashMap<(i32, u64), Arc<FileMetaData>>,
-  /// Files that have been explicitly deleted (supersedes any prior addition).
-  deleted: HashSet<(i32, u64)>,
+/// See `db/version_builder.cc: VersionBuilder::Rep::ApplyBlobFileAddition`
+/// and `ApplyBlobFileGarbage` in RocksDB.
+fn apply_blob_file_edits(
+  blob_files: &mut BTreeMap<u64, Arc<BlobFileMetaData>>,
+  edit: &VersionEdit,
+) -> Vec<u64> {
+  for addition in &edit.blob_file_additions {
+    blob_files.insert(
+      addition.blob_file_number,
+      Arc::new(BlobFileMetaData::new(addition)),
+    );
+  }
+  let mut dropped = Vec::new();
+  for garbage in &edit.blob_file_garbages {
+    let Some(meta) = blob_files.get_mut(&garbage.blob_file_number) else {
+      log::warn!(
+        "garbage recorded for unknown blob file {}",
+        garbage.blob_file_number
+      );
+      continue;
+    };
+    Arc::make_mut(meta).add_garbage(garbage);
+    if meta.is_obsolete() {
+      blob_files.remove(&garbage.blob_file_number);
+      dropped.push(garbage.blob_file_number);
+    }
+  }
+  dropped
+}
+
+// ── Replay ────────────────────────────────────────────────────────────────────
+
+/// State accumulated while replaying the MANIFEST: the database-wide
+/// counters plus a `Builder` per live column family.
+struct Replay {
+  /// `(name, comparator name recorded at creation, builder)` by family id.
+  column_families: BTreeMap<u32, (String, Option<String>, Builder)>,
+  max_column_family: u32,
   next_file_number: u64,
   last_sequence: u64,
   log_number: u64,
-  /// Round-robin compaction cursor accumulated from compact-pointer edits.
-  compact_pointer: [Vec<u8>; crate::db::version::NUM_LEVELS],
 }
 
-impl Builder {
+impl Replay {
   fn new() -> Self {
-    Builder {
-      added: HashMap::new(),
-      deleted: HashSet::new(),
+    Replay {
+      column_families: BTreeMap::from([(
+        DEFAULT_COLUMN_FAMILY_ID,
+        (DEFAULT_COLUMN_FAMILY_NAME.to_owned(), None, Builder::new()),
+      )]),
+      max_column_family: DEFAULT_COLUMN_FAMILY_ID,
       next_file_number: 3,
       last_sequence: 0,
       log_number: 1,
-      compact_pointer: std::array::from_fn(|_| Vec::new()),
     }
   }
 
-  fn apply(&mut self, edit: &VersionEdit) {
+  fn apply(&mut self, edit: &VersionEdit) -> Result<(), Error> {
     if let Some(v) = edit.log_number {
       self.log_number = v;
     }
@@ -345,6 +760,60 @@ impl Builder {
     if let Some(v) = edit.last_sequence {
       self.last_sequence = v;
     }
+    if let Some(v) = edit.max_column_family {
+      self.max_column_family = self.max_column_family.max(v);
+    }
+    let id = edit.column_family;
+    if let Some(ref name) = edit.column_family_add {
+      self.column_families.insert(
+        id,
+        (name.clone(), edit.comparator_name.clone(), Builder::new()),
+      );
+      self.max_column_family = self.max_column_family.max(id);
+    } else if edit.column_family_drop {
+      self.column_families.remove(&id);
+    } else {
+      let (_, _, builder) = self.column_families.get_mut(&id).ok_or_else(|| {
+        Error::Corruption(format!("MANIFEST edit for unknown column family {id}"))
+      })?;
+      builder.apply(edit);
+    }
+    Ok(())
+  }
+}
+
+// ── Builder ───────────────────────────────────────────────────────────────────
+
+/// Accumulates one column family's `VersionEdit` deltas during MANIFEST replay.
+///
+/// After all records have been applied, `build` opens the live SSTable files
+/// and constructs the initial `Version`.
+struct Builder {
+  /// Live files keyed by `(level, file_number)`.
+  added: HashMap<(i32, u64), Arc<FileMetaData>>,
+  /// Files that have been explicitly deleted (supersedes any prior addition).
+  deleted: HashSet<(i32, u64)>,
+  /// Round-robin compaction cursor accumulated from compact-pointer edits,
+  /// up to the deepest level any edit named.
+  compact_pointer: Vec<Vec<u8>>,
+  /// Live blob files.
+  blob_files: BTreeMap<u64, Arc<BlobFileMetaData>>,
+  /// Latest history cutoff recorded for the family.
+  full_history_ts_low: Option<Vec<u8>>,
+}
+
+impl Builder {
+  fn new() -> Self {
+    Builder {
+      added: HashMap::new(),
+      deleted: HashSet::new(),
+      compact_pointer: Vec::new(),
+      blob_files: BTreeMap::new(),
+      full_history_ts_low: None,
+    }
+  }
+
+  fn apply(&mut self, edit: &VersionEdit) {
     for &(level, number) in &edit.deleted_files {
       self.deleted.insert((level, number));
       self.added.remove(&(level, number));
@@ -355,7 +824,15 @@ impl Builder {
       self.added.insert(key, Arc::clone(meta));
     }
     for (level, key) in &edit.compact_pointers {
-      self.compact_pointer[*level as usize] = key.clone();
+      let level = *level as usize;
+      if self.compact_pointer.len() <= level {
+        self.compact_pointer.resize(level + 1, Vec::new());
+      }
+      self.compact_pointer[level] = key.clone();
+    }
+    apply_blob_file_edits(&mut self.blob_files, edit);
+    if let Some(ref ts) = edit.full_history_ts_low {
+      self.full_history_ts_low = Some(ts.clone());
     }
   }
 
@@ -363,12 +840,14 @@ impl Builder {
   ///
   /// Tables are opened lazily by the `TableCache` on first access — no I/O
   /// here, matching LevelDB's separation between `VersionSet` and
-  /// `TableCache`.
-  fn build(self, comparator: Arc<dyn crate::comparator::Comparator>) -> Version {
-    let mut files: [Vec<Arc<FileMetaData>>; 7] = std::array::from_fn(|_| Vec::new());
+  /// `TableCache`.  Every file must be at a level below `options.num_levels`
+  /// (see [`check_levels`]).
+  fn build(self, options: &Options) -> Version {
+    let comparator = Arc::clone(&options.comparator);
+    let mut files: Vec<Vec<Arc<FileMetaData>>> = vec![Vec::new(); options.num_levels];
 
     // Collect live files per level (stable insertion order for L0).
-    let mut by_level: Vec<Vec<(u64, Arc<FileMetaData>)>> = vec![Vec::new(); 7];
+    let mut by_level: Vec<Vec<(u64, Arc<FileMetaData>)>> = vec![Vec::new(); options.num_levels];
     for ((level, number), meta) in self.added {
       by_level[level as usize].push((number, meta));
     }
@@ -392,7 +871,28 @@ impl Builder {
       }
     }
 
-    Version::from_files(files, comparator)
+    let mut version = Version::from_parts(files, self.blob_files, comparator);
+    version.full_history_ts_low = self.full_history_ts_low;
+    version
+  }
+}
+
+/// Fail with [`Error::InvalidArgument`] if any of `levels`, the levels of
+/// column family `name`'s files, is beyond its `options.num_levels`: the
+/// database was written with more levels than it is being opened with.
+/// RocksDB refuses such a database the same way ("db has more levels than
+/// options.num_levels").
+fn check_levels(
+  name: &str,
+  levels: impl Iterator<Item = i32>,
+  options: &Options,
+) -> Result<(), Error> {
+  match levels.max() {
+    Some(level) if level as usize >= options.num_levels => Err(Error::InvalidArgument(format!(
+      "column family {name} has files at level {level}, but num_levels is {}",
+      options.num_levels
+    ))),
+    _ => Ok(()),
   }
 }
 
@@ -411,7 +911,6 @@ fn parse_manifest_number(name: &str) -> Result<u64, Error> {
 mod tests {
   use super::*;
   use crate::db::table_cache::{TableCache, NUM_NON_TABLE_CACHE_FILES};
-  use crate::options::Options;
   use crate::table::builder::TableBuilder;
   use crate::table::format::make_internal_key;
 
@@ -454,12 +953,7 @@ mod tests {
   #[test]
   fn create_writes_current_file() {
     let dir = tempfile::tempdir().unwrap();
-    VersionSet::create(
-      dir.path(),
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
-      &crate::env::PosixFileSystem,
-    )
-    .unwrap();
+    VersionSet::create(dir.path(), defaults(), &crate::env::PosixFileSystem).unwrap();
 
     let content = std::fs::read_to_string(dir.path().join("CURRENT")).unwrap();
     assert_eq!(content, "MANIFEST-000002\n");
@@ -469,12 +963,7 @@ mod tests {
   #[test]
   fn log_and_apply_persists() {
     let dir = tempfile::tempdir().unwrap();
-    let mut vs = VersionSet::create(
-      dir.path(),
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
-      &crate::env::PosixFileSystem,
-    )
-    .unwrap();
+    let mut vs = VersionSet::create(dir.path(), defaults(), &crate::env::PosixFileSystem).unwrap();
     let tc = make_tc(dir.path());
 
     // Simulate flushing file 3.
@@ -485,7 +974,7 @@ mod tests {
     vs.log_and_apply(&mut edit, &tc).unwrap();
 
     // Current version must have file 3 in L0.
-    let cur = vs.current();
+    let cur = vs.current(0).unwrap();
     assert_eq!(cur.files_at(0).len(), 1);
     assert_eq!(cur.files_at(0)[0].number, 3);
 
@@ -494,11 +983,13 @@ mod tests {
     let vs2 = VersionSet::recover(
       dir.path(),
       false,
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+      defaults(),
+      &[],
       &crate::env::PosixFileSystem,
+      false,
     )
     .unwrap();
-    let cur2 = vs2.current();
+    let cur2 = vs2.current(0).unwrap();
     assert_eq!(cur2.files_at(0).len(), 1);
     assert_eq!(cur2.files_at(0)[0].number, 3);
   }
@@ -506,12 +997,7 @@ mod tests {
   #[test]
   fn recover_reopens_tables() {
     let dir = tempfile::tempdir().unwrap();
-    let mut vs = VersionSet::create(
-      dir.path(),
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
-      &crate::env::PosixFileSystem,
-    )
-    .unwrap();
+    let mut vs = VersionSet::create(dir.path(), defaults(), &crate::env::PosixFileSystem).unwrap();
     let tc = make_tc(dir.path());
 
     let (fnum, fsize) = write_sst(dir.path(), 3, &[(b"key", 1, 1, b"val")]);
@@ -524,15 +1010,17 @@ mod tests {
     let vs2 = VersionSet::recover(
       dir.path(),
       false,
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+      defaults(),
+      &[],
       &crate::env::PosixFileSystem,
+      false,
     )
     .unwrap();
     let tc2 = make_tc(dir.path());
-    let cur = vs2.current();
+    let cur = vs2.current(0).unwrap();
     use crate::table::reader::LookupResult;
     assert!(matches!(
-      cur.get(b"key", u64::MAX, false, true, &tc2).unwrap().0,
+      cur.get(b"key", u64::MAX, false, true, &tc2, &mut crate::table::get_context::GetContext::default()).unwrap().0,
       LookupResult::Value(v) if v == b"val"
     ));
   }
@@ -540,12 +1028,7 @@ mod tests {
   #[test]
   fn sequence_survives_reopen() {
     let dir = tempfile::tempdir().unwrap();
-    let mut vs = VersionSet::create(
-      dir.path(),
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
-      &crate::env::PosixFileSystem,
-    )
-    .unwrap();
+    let mut vs = VersionSet::create(dir.path(), defaults(), &crate::env::PosixFileSystem).unwrap();
     let tc = make_tc(dir.path());
 
     vs.set_last_sequence(42);
@@ -557,10 +1040,115 @@ mod tests {
     let vs2 = VersionSet::recover(
       dir.path(),
       false,
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+      defaults(),
+      &[],
       &crate::env::PosixFileSystem,
+      false,
     )
     .unwrap();
     assert_eq!(vs2.last_sequence(), 42);
   }
+
+  fn defaults() -> Arc<Options> {
+    Arc::new(Options::default())
+  }
+
+  #[test]
+  fn column_families_survive_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut vs = VersionSet::create(dir.path(), defaults(), &crate::env::PosixFileSystem).unwrap();
+    let tc = make_tc(dir.path());
+    vs.create_column_family(1, "one", defaults()).unwrap();
+    vs.create_column_family(2, "two", defaults()).unwrap();
+    vs.drop_column_family(1).unwrap();
+
+    let (fnum, fsize) = write_sst(dir.path(), 3, &[(b"k", 1, 1, b"v")]);
+    let mut edit = VersionEdit::new();
+    edit.column_family = 2;
+    edit.new_files.push((
+      0,
+      FileMetaData::new(fnum, fsize, b"k".to_vec(), b"k".to_vec()),
+    ));
+    vs.log_and_apply(&mut edit, &tc).unwrap();
+    assert!(vs.current(0).unwrap().files_at(0).is_empty());
+    assert!(vs.current(1).is_none());
+    drop(vs);
+
+    assert_eq!(
+      list_column_families(dir.path(), &crate::env::PosixFileSystem).unwrap(),
+      vec!["default".to_owned(), "two".to_owned()]
+    );
+    // Opening without naming "two" is refused.
+    assert!(matches!(
+      VersionSet::recover(
+        dir.path(),
+        false,
+        defaults(),
+        &[],
+        &crate::env::PosixFileSystem,
+        false,
+      ),
+      Err(Error::InvalidArgument(_))
+    ));
+    let vs = VersionSet::recover(
+      dir.path(),
+      false,
+      defaults(),
+      &[("two", defaults())],
+      &crate::env::PosixFileSystem,
+      false,
+    )
+    .unwrap();
+    assert_eq!(
+      vs.column_families().collect::<Vec<_>>(),
+      vec![(0, "default"), (2, "two")]
+    );
+    assert_eq!(vs.max_column_family(), 2);
+    assert_eq!(vs.current(2).unwrap().files_at(0)[0].number, 3);
+    assert!(vs.current(0).unwrap().files_at(0).is_empty());
+  }
+
+  #[test]
+  fn incomplete_atomic_group_is_discarded() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut vs = VersionSet::create(dir.path(), defaults(), &crate::env::PosixFileSystem).unwrap();
+    let tc = make_tc(dir.path());
+    let meta = |n: u64| FileMetaData::new(n, 100, b"a".to_vec(), b"b".to_vec());
+
+    let mut first = VersionEdit::new();
+    first.new_files.push((0, meta(3)));
+    let mut second = VersionEdit::new();
+    second.new_files.push((0, meta(4)));
+    vs.log_and_apply_group(vec![(first, &tc), (second, &tc)])
+      .unwrap();
+    assert_eq!(vs.current(0).unwrap().files_at(0).len(), 2);
+
+    // Simulate a crash part-way through a second group: only its first
+    // record reaches the MANIFEST.
+    let mut partial = VersionEdit::new();
+    partial.new_files.push((0, meta(5)));
+    partial.remaining_entries = Some(1);
+    let manifest_log = vs.manifest_writer().unwrap();
+    manifest_log.add_record(&partial.encode()).unwrap();
+    manifest_log.sync().unwrap();
+    drop(vs);
+
+    let vs = VersionSet::recover(
+      dir.path(),
+      false,
+      defaults(),
+      &[],
+      &crate::env::PosixFileSystem,
+      false,
+    )
+    .unwrap();
+    let numbers: Vec<u64> = vs
+      .current(0)
+      .unwrap()
+      .files_at(0)
+      .iter()
+      .map(|f| f.number)
+      .collect();
+    assert_eq!(numbers, vec![4, 3]);
+  }
 }
@@ -0,0 +1,384 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! WAL archiving and change-data-capture reads over archived and live WALs.
+//!
+//! When [`Options::wal_ttl`] or [`Options::wal_size_limit`] is set, a WAL
+//! that is no longer needed for recovery is moved into `archive/` rather than
+//! deleted, and the archive is trimmed to those limits.
+//! [`TransactionLogIterator`] replays the write batches of the archived and
+//! live WALs in sequence order, and the ingestions between them.
+//!
+//! See `db/wal_manager.cc` and `db/transaction_log_impl.cc` in RocksDB.
+
+use crate::env::FileSystem;
+use crate::error::Error;
+use crate::logfile::reader::{Reader as LogReader, Reporter};
+use crate::options::Options;
+use crate::write_batch::WriteBatch;
+use crate::FileKind;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Subdirectory of the database directory holding archived WALs.
+pub(crate) const ARCHIVE_DIR: &str = "archive";
+
+fn log_file_name(number: u64) -> String {
+  format!("{number:06}.log")
+}
+
+/// `true` if obsolete WALs are archived rather than deleted.
+pub(crate) fn archiving_enabled(opts: &Options) -> bool {
+  opts.wal_ttl.is_some() || opts.wal_size_limit.is_some()
+}
+
+/// Dispose of the obsolete WAL `number` in `dir`: move it into `archive/`
+/// and trim the archive if archiving is enabled, delete it otherwise.
+///
+/// Best effort, like the deletions it replaces: failures are logged.
+pub(crate) fn archive_or_delete_wal(dir: &Path, number: u64, opts: &Options) {
+  let fs = &*opts.file_system;
+  let path = dir.join(log_file_name(number));
+  if !archiving_enabled(opts) {
+    let _ = fs.remove_file(&path);
+    return;
+  }
+  let archive = dir.join(ARCHIVE_DIR);
+  let moved = fs
+    .create_dir_all(&archive)
+    .and_then(|()| fs.rename(&path, &archive.join(log_file_name(number))));
+  match moved {
+    Ok(()) => log::debug!("archived WAL {}", log_file_name(number)),
+    Err(e) => log::warn!("failed to archive WAL {}: {e}", log_file_name(number)),
+  }
+  purge_archived_wals(dir, opts);
+}
+
+/// Archived WALs in `dir`, oldest first.
+fn archived_wals(dir: &Path, fs: &dyn FileSystem) -> Vec<u64> {
+  let mut numbers: Vec<u64> = fs
+    .children(&dir.join(ARCHIVE_DIR))
+    .unwrap_or_default()
+    .iter()
+    .filter_map(|name| match crate::parse_db_filename(name) {
+      Some((number, FileKind::Log)) => Some(number),
+      _ => None,
+    })
+    .collect();
+  numbers.sort_unstable();
+  numbers
+}
+
+/// Delete archived WALs older than [`Options::wal_ttl`], then the oldest of
+/// the rest while they total more than [`Options::wal_size_limit`] bytes.
+///
+/// See `WalManager::PurgeObsoleteWALFiles`.
+pub(crate) fn purge_archived_wals(dir: &Path, opts: &Options) {
+  let fs = &*opts.file_system;
+  let archive = dir.join(ARCHIVE_DIR);
+  let now = opts.clock.current_time();
+  let mut kept = Vec::new();
+  for number in archived_wals(dir, fs) {
+    let path = archive.join(log_file_name(number));
+    if let Some(ttl) = opts.wal_ttl {
+      match fs.modified_time(&path) {
+        Ok(mtime) if now.saturating_sub(mtime) >= ttl.as_secs() => {
+          log::debug!("purging archived WAL {} (ttl)", log_file_name(number));
+          let _ = fs.remove_file(&path);
+          continue;
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("cannot read age of {}: {e}", path.display()),
+      }
+    }
+    let size = fs.file_size(&path).unwrap_or(0);
+    kept.push((path, size));
+  }
+  if let Some(limit) = opts.wal_size_limit {
+    let mut total: u64 = kept.iter().map(|(_, size)| size).sum();
+    for (path, size) in kept {
+      if total <= limit {
+        break;
+      }
+      log::debug!("purging archived WAL {} (size limit)", path.display());
+      let _ = fs.remove_file(&path);
+      total -= size;
+    }
+  }
+}
+
+/// Remove `archive/` and the WALs in it; used by [`Db::destroy`].
+///
+/// [`Db::destroy`]: crate::Db::destroy
+pub(crate) fn destroy_archive(dir: &Path, fs: &dyn FileSystem) {
+  let archive = dir.join(ARCHIVE_DIR);
+  for number in archived_wals(dir, fs) {
+    let _ = fs.remove_file(&archive.join(log_file_name(number)));
+  }
+  let _ = fs.remove_dir(&archive);
+}
+
+/// Remembers the first corruption the WAL reader reports.
+struct FirstCorruption {
+  log_number: u64,
+  error: Arc<Mutex<Option<Error>>>,
+}
+
+impl Reporter for FirstCorruption {
+  fn corruption(&mut self, offset: u64, bytes: u64, reason: &str) {
+    self.error.lock().unwrap().get_or_insert_with(|| {
+      Error::Corruption(format!(
+        "WAL {:06}.log: {bytes} bytes at offset {offset}: {reason}",
+        self.log_number
+      ))
+    });
+  }
+}
+
+/// A change yielded by [`TransactionLogIterator`].
+#[derive(Clone)]
+pub enum WalUpdate {
+  /// A committed batch and the sequence number of its first record.
+  Batch(u64, WriteBatch),
+  /// [`Db::ingest_external_file`] installed files whose entries all have
+  /// this sequence number.  The entries are not in the WAL: a replica
+  /// applies the ingestion by ingesting the same files, which gives them its
+  /// next sequence number.
+  ///
+  /// [`Db::ingest_external_file`]: crate::Db::ingest_external_file
+  Ingestion(u64),
+}
+
+impl WalUpdate {
+  /// Classify a batch read from the WAL.
+  fn new(batch: WriteBatch) -> Self {
+    match batch.ingested_sequence() {
+      Some(sequence) => WalUpdate::Ingestion(sequence),
+      None => WalUpdate::Batch(batch.sequence(), batch),
+    }
+  }
+
+  /// The first sequence number the update covers.
+  fn first_sequence(&self) -> u64 {
+    match self {
+      WalUpdate::Batch(sequence, _) | WalUpdate::Ingestion(sequence) => *sequence,
+    }
+  }
+
+  /// One past the last sequence number the update covers.
+  fn end_sequence(&self) -> u64 {
+    match self {
+      WalUpdate::Batch(sequence, batch) => sequence + u64::from(batch.count()),
+      WalUpdate::Ingestion(sequence) => sequence + 1,
+    }
+  }
+}
+
+/// Iterator over the committed write batches from a given sequence number
+/// onwards, returned by [`Db::get_updates_since`].
+///
+/// Yields a [`WalUpdate`] per batch in commit order, and one per ingestion,
+/// so the sequence numbers they cover follow each other without gaps.  The
+/// first batch yielded is the one containing the requested sequence number,
+/// so it may start before it.
+///
+/// The WALs to read are listed when the iterator is created: batches written
+/// to the live WAL are picked up while it is being read, but WALs created
+/// after that are not.  To follow the database, call
+/// [`Db::get_updates_since`] again with the sequence after the last batch
+/// seen.
+///
+/// See `include/rocksdb/transaction_log.h: TransactionLogIterator`.
+///
+/// [`Db::get_updates_since`]: crate::Db::get_updates_since
+pub struct TransactionLogIterator {
+  dir: PathBuf,
+  fs: Arc<dyn FileSystem>,
+  /// WALs not opened yet, oldest first.
+  logs: VecDeque<u64>,
+  /// The WAL being read and the first corruption its reader reported.
+  reader: Option<(LogReader, Arc<Mutex<Option<Error>>>)>,
+  /// An update read ahead while locating the requested sequence.
+  peeked: Option<WalUpdate>,
+  done: bool,
+}
+
+impl TransactionLogIterator {
+  /// Open an iterator over the WALs of the database in `dir` that yields
+  /// batches from `sequence` onwards.  `last_sequence` is the newest sequence
+  /// number the database has assigned.
+  ///
+  /// Returns [`Error::InvalidArgument`] if batches at or after `sequence`
+  /// have already been purged from the archive.
+  pub(crate) fn new(
+    dir: &Path,
+    fs: Arc<dyn FileSystem>,
+    sequence: u64,
+    last_sequence: u64,
+  ) -> Result<Self, Error> {
+    let mut logs: Vec<u64> = fs
+      .children(dir)?
+      .iter()
+      .filter_map(|name| match crate::parse_db_filename(name) {
+        Some((number, FileKind::Log)) => Some(number),
+        _ => None,
+      })
+      .chain(archived_wals(dir, &*fs))
+      .collect();
+    logs.sort_unstable();
+    logs.dedup();
+    let mut iter = TransactionLogIterator {
+      dir: dir.to_path_buf(),
+      fs,
+      logs: logs.into(),
+      reader: None,
+      peeked: None,
+      done: false,
+    };
+
+    // Read up to the batch containing `sequence`, checking that the oldest
+    // batch still on disk is not newer than it.
+    let wanted = sequence.max(1);
+    let mut oldest = None;
+    while let Some(batch) = iter.read_batch()? {
+      let update = WalUpdate::new(batch);
+      oldest.get_or_insert(update.first_sequence());
+      if update.end_sequence() > wanted {
+        iter.peeked = Some(update);
+        break;
+      }
+    }
+    let available = match oldest {
+      Some(oldest) => oldest <= wanted,
+      // Nothing on disk: fine only if nothing at `sequence` was written yet.
+      None => wanted > last_sequence,
+    };
+    if !available {
+      return Err(Error::InvalidArgument(format!(
+        "get_updates_since: sequence {sequence} is no longer available in the WAL archive \
+         (oldest available is {})",
+        oldest.map_or_else(|| "none".to_string(), |s| s.to_string())
+      )));
+    }
+    Ok(iter)
+  }
+
+  /// Next batch across the remaining WALs, or `None` when all are read.
+  fn read_batch(&mut self) -> Result<Option<WriteBatch>, Error> {
+    loop {
+      if let Some((reader, corruption)) = &mut self.reader {
+        let record = reader.read_record();
+        if let Some(e) = corruption.lock().unwrap().take() {
+          return Err(e);
+        }
+        match record {
+          Some(record) => return WriteBatch::from_contents(record).map(Some),
+          None => self.reader = None,
+        }
+      }
+      let Some(number) = self.logs.pop_front() else {
+        return Ok(None);
+      };
+      // A live WAL may have been archived since it was listed.
+      let live = self.dir.join(log_file_name(number));
+      let archived = self.dir.join(ARCHIVE_DIR).join(log_file_name(number));
+      let file = match self.fs.open_sequential(&live) {
+        Ok(file) => file,
+        Err(_) => self.fs.open_sequential(&archived)?,
+      };
+      let error = Arc::new(Mutex::new(None));
+      let reporter = FirstCorruption {
+        log_number: number,
+        error: Arc::clone(&error),
+      };
+      self.reader = Some((
+        LogReader::new(file, Some(Box::new(reporter)), true, 0),
+        error,
+      ));
+    }
+  }
+}
+
+impl Iterator for TransactionLogIterator {
+  type Item = Result<WalUpdate, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let update = match self.peeked.take() {
+      Some(update) => Ok(Some(update)),
+      None => self.read_batch().map(|batch| batch.map(WalUpdate::new)),
+    };
+    match update {
+      Ok(Some(update)) => Some(Ok(update)),
+      Ok(None) => {
+        self.done = true;
+        None
+      }
+      Err(e) => {
+        // Like `DbIter::forward`, report an error once and then stop.
+        self.done = true;
+        Some(Err(e))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn archive_with(sizes: &[(u64, usize)]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join(ARCHIVE_DIR);
+    std::fs::create_dir(&archive).unwrap();
+    for &(number, size) in sizes {
+      std::fs::write(archive.join(log_file_name(number)), vec![0u8; size]).unwrap();
+    }
+    dir
+  }
+
+  #[test]
+  fn purge_trims_oldest_archived_wals_to_size_limit() {
+    let dir = archive_with(&[(3, 100), (7, 100), (12, 100)]);
+    let opts = Options {
+      wal_size_limit: Some(250),
+      ..Options::default()
+    };
+    purge_archived_wals(dir.path(), &opts);
+    assert_eq!(archived_wals(dir.path(), &*opts.file_system), [7, 12]);
+  }
+
+  #[test]
+  fn purge_removes_archived_wals_past_ttl() {
+    use crate::env::{Clock, SystemClock};
+
+    let dir = archive_with(&[(3, 10), (7, 10)]);
+    let now = SystemClock.current_time();
+    let at = |time| Options {
+      wal_ttl: Some(std::time::Duration::from_secs(3600)),
+      clock: Arc::new(crate::env::ManualClock::new(time)),
+      ..Options::default()
+    };
+    purge_archived_wals(dir.path(), &at(now));
+    assert_eq!(
+      archived_wals(dir.path(), &crate::env::PosixFileSystem),
+      [3, 7]
+    );
+
+    // An hour later, both have expired.
+    purge_archived_wals(dir.path(), &at(now + 3601));
+    assert!(archived_wals(dir.path(), &crate::env::PosixFileSystem).is_empty());
+  }
+}
@@ -18,6 +18,9 @@
 //! Custom implementations enable in-memory filesystems (for testing), encrypted
 //! storage, cloud backends, or async I/O — without touching core database logic.
 //!
+//! Wall-clock time is read through the [`Clock`] trait, which defaults to
+//! [`SystemClock`], so time-dependent features can be tested deterministically.
+//!
 //! Port of LevelDB's `include/leveldb/env.h` and RocksDB's
 //! `include/rocksdb/file_system.h`.
 
@@ -96,9 +99,27 @@ pub trait FileSystem: Send + Sync {
   /// Returns `true` if the path exists.
   fn file_exists(&self, path: &Path) -> bool;
 
+  /// Last modification time of the file, in seconds since the Unix epoch.
+  ///
+  /// See `include/rocksdb/file_system.h: FileSystem::GetFileModificationTime`.
+  fn modified_time(&self, path: &Path) -> Result<u64, Error>;
+
   /// Atomically rename `src` to `dst`.
   fn rename(&self, src: &Path, dst: &Path) -> Result<(), Error>;
 
+  /// Create `dst` as a hard link to the existing file `src`.
+  ///
+  /// Returns [`Error::NotSupported`] if the backend cannot link files, or
+  /// cannot link these two paths (e.g. they are on different devices);
+  /// callers then fall back to copying.  The default implementation always
+  /// does.
+  ///
+  /// See `include/rocksdb/file_system.h: FileSystem::LinkFile`.
+  fn link_file(&self, src: &Path, dst: &Path) -> Result<(), Error> {
+    let _ = (src, dst);
+    Err(Error::NotSupported("link_file".to_string()))
+  }
+
   /// Delete a file.
   fn remove_file(&self, path: &Path) -> Result<(), Error>;
 
@@ -266,10 +287,30 @@ impl FileSystem for PosixFileSystem {
     path.exists()
   }
 
+  fn modified_time(&self, path: &Path) -> Result<u64, Error> {
+    let modified = std::fs::metadata(path)
+      .and_then(|m| m.modified())
+      .map_err(Error::IoError)?;
+    Ok(
+      modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs()),
+    )
+  }
+
   fn rename(&self, src: &Path, dst: &Path) -> Result<(), Error> {
     std::fs::rename(src, dst).map_err(Error::IoError)
   }
 
+  fn link_file(&self, src: &Path, dst: &Path) -> Result<(), Error> {
+    std::fs::hard_link(src, dst).map_err(|e| match e.kind() {
+      std::io::ErrorKind::CrossesDevices | std::io::ErrorKind::Unsupported => {
+        Error::NotSupported(format!("link_file: {e}"))
+      }
+      _ => Error::IoError(e),
+    })
+  }
+
   fn remove_file(&self, path: &Path) -> Result<(), Error> {
     std::fs::remove_file(path).map_err(Error::IoError)
   }
@@ -323,6 +364,52 @@ impl FileSystem for PosixFileSystem {
   }
 }
 
+// ── Clock ────────────────────────────────────────────────────────────────────
+
+/// Source of wall-clock time.
+///
+/// See `include/rocksdb/system_clock.h: SystemClock`.
+pub trait Clock: Send + Sync {
+  /// Seconds since the Unix epoch.
+  ///
+  /// See `SystemClock::GetCurrentTime`.
+  fn current_time(&self) -> u64;
+}
+
+/// [`Clock`] backed by [`std::time::SystemTime`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn current_time(&self) -> u64 {
+    std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map_or(0, |d| d.as_secs())
+  }
+}
+
+/// [`Clock`] that only moves when a test advances it.
+#[cfg(test)]
+pub(crate) struct ManualClock(std::sync::atomic::AtomicU64);
+
+#[cfg(test)]
+impl ManualClock {
+  pub(crate) fn new(secs: u64) -> Self {
+    ManualClock(std::sync::atomic::AtomicU64::new(secs))
+  }
+
+  pub(crate) fn advance(&self, secs: u64) {
+    self.0.fetch_add(secs, std::sync::atomic::Ordering::SeqCst);
+  }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+  fn current_time(&self) -> u64 {
+    self.0.load(std::sync::atomic::Ordering::SeqCst)
+  }
+}
+
 // ── Convenience wrappers for tests and interop ──────────────────────────────
 
 /// Create a [`WritableFile`] from an already-open [`std::fs::File`].
@@ -382,6 +469,8 @@ impl WritableFile for Vec<u8> {
 ///   the process.
 /// - `lock_file` enforces exclusivity within a single `MemFileSystem` instance;
 ///   the lock is released when the returned handle is dropped.
+/// - `rename` of a directory moves everything below it.
+/// - `link_file` is not supported, so callers take their copy fallback.
 ///
 /// Not thread-optimised — a single `Mutex` guards all state — but correct for
 /// concurrent access, which is all the database requires.
@@ -569,8 +658,42 @@ mod memfs {
       st.files.contains_key(path) || st.dirs.contains(path)
     }
 
+    fn modified_time(&self, path: &Path) -> Result<u64, Error> {
+      // Modification times are not tracked; every file reads as just written.
+      if !self.state.lock().unwrap().files.contains_key(path) {
+        return Err(not_found(path));
+      }
+      Ok(SystemClock.current_time())
+    }
+
     fn rename(&self, src: &Path, dst: &Path) -> Result<(), Error> {
       let mut st = self.state.lock().unwrap();
+      if st.dirs.contains(src) {
+        // Move the directory and everything below it.
+        let moved = |p: &PathBuf| dst.join(p.strip_prefix(src).unwrap());
+        let files: Vec<PathBuf> = st
+          .files
+          .keys()
+          .filter(|p| p.starts_with(src))
+          .cloned()
+          .collect();
+        for path in files {
+          let inode = st.files.remove(&path).unwrap();
+          st.files.insert(moved(&path), inode);
+        }
+        let dirs: Vec<PathBuf> = st
+          .dirs
+          .iter()
+          .filter(|p| p.starts_with(src))
+          .cloned()
+          .collect();
+        for path in dirs {
+          st.dirs.remove(&path);
+          st.dirs.insert(moved(&path));
+        }
+        ensure_ancestor_dirs(&mut st.dirs, dst);
+        return Ok(());
+      }
       let inode = st.files.remove(src).ok_or_else(|| not_found(src))?;
       ensure_ancestor_dirs(&mut st.dirs, dst);
       st.files.insert(dst.to_path_buf(), inode);
@@ -778,6 +901,22 @@ mod tests {
     assert!(!fs.file_exists(&dst));
   }
 
+  #[test]
+  fn posix_link_file_shares_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let fs = PosixFileSystem;
+    let src = dir.path().join("src.txt");
+    let dst = dir.path().join("dst.txt");
+
+    fs.write_string_to_file(&src, "linked").unwrap();
+    fs.link_file(&src, &dst).unwrap();
+    fs.remove_file(&src).unwrap();
+    assert_eq!(fs.read_string_from_file(&dst).unwrap(), "linked");
+    // Linking onto an existing name fails.
+    fs.write_string_to_file(&src, "again").unwrap();
+    assert!(matches!(fs.link_file(&src, &dst), Err(Error::IoError(_))));
+  }
+
   #[test]
   fn posix_sync_dir() {
     let dir = tempfile::tempdir().unwrap();
@@ -922,6 +1061,27 @@ mod tests {
     assert!(matches!(fs.remove_file(dst), Err(Error::IoError(_))));
   }
 
+  #[test]
+  fn mem_rename_moves_directory() {
+    let fs = MemFileSystem::new();
+    fs.write_string_to_file(Path::new("/a.tmp/f"), "x").unwrap();
+    fs.rename(Path::new("/a.tmp"), Path::new("/b/a")).unwrap();
+    assert!(!fs.file_exists(Path::new("/a.tmp")));
+    assert_eq!(fs.children(Path::new("/b/a")).unwrap(), vec!["f"]);
+    assert_eq!(fs.read_string_from_file(Path::new("/b/a/f")).unwrap(), "x");
+  }
+
+  #[test]
+  fn mem_link_file_is_not_supported() {
+    let fs = MemFileSystem::new();
+    let src = Path::new("/db/src.txt");
+    fs.create_writable(src).unwrap();
+    assert!(matches!(
+      fs.link_file(src, Path::new("/db/dst.txt")),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
   #[test]
   fn mem_sync_dir_is_noop() {
     let fs = MemFileSystem::new();
@@ -20,12 +20,21 @@ pub enum Error {
   InvalidArgument(String),
   NotSupported(String),
   IoError(std::io::Error),
+  /// A transaction could not commit because a key it depends on was written
+  /// concurrently.  Retrying the transaction may succeed.
+  Busy(String),
+  /// An operation gave up waiting, e.g. for a transaction lock.
+  TimedOut(String),
 }
 
 impl Error {
   pub fn is_not_found(&self) -> bool {
     matches!(self, Error::NotFound)
   }
+
+  pub fn is_busy(&self) -> bool {
+    matches!(self, Error::Busy(_))
+  }
 }
 
 impl fmt::Display for Error {
@@ -36,6 +45,8 @@ impl fmt::Display for Error {
       Error::InvalidArgument(msg) => write!(f, "InvalidArgument: {msg}"),
       Error::NotSupported(msg) => write!(f, "NotSupported: {msg}"),
       Error::IoError(e) => write!(f, "IO error: {e}"),
+      Error::Busy(msg) => write!(f, "Resource busy: {msg}"),
+      Error::TimedOut(msg) => write!(f, "Operation timed out: {msg}"),
     }
   }
 }
@@ -64,6 +75,8 @@ impl Clone for Error {
       Error::NotSupported(s) => Error::NotSupported(s.clone()),
       // `std::io::Error` isn't Clone; preserve kind + message.
       Error::IoError(e) => Error::IoError(std::io::Error::new(e.kind(), e.to_string())),
+      Error::Busy(s) => Error::Busy(s.clone()),
+      Error::TimedOut(s) => Error::TimedOut(s.clone()),
     }
   }
 }
@@ -109,40 +109,67 @@
 //! # Ok::<(), roughdb::Error>(())
 //! ```
 
+use crate::column_family::{column_family_options, DEFAULT_COLUMN_FAMILY_ID};
+use crate::db::blob::{BlobFileBuilder, VALUE_TYPE_BLOB_INDEX};
 use crate::db::table_cache::TableCache;
-use crate::db::version_edit::{FileMetaData, VersionEdit};
+use crate::db::version_edit::{BlobFileAddition, FileMetaData, VersionEdit};
 use crate::db::version_set::VersionSet;
 use crate::logfile::reader::Reader as LogReader;
 use crate::logfile::writer::Writer as LogWriter;
 use crate::memtable::{ArcMemTableIter, Memtable, MemtableResult};
 use crate::table::builder::TableBuilder;
+use crate::table::format::{encode_internal_key_into, parse_internal_key};
+use crate::table::get_context::GetContext;
 use crate::table::reader::{LookupResult, Table};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
+pub mod backup;
+pub use backup::{BackupEngine, BackupInfo};
 pub mod cache;
 pub use cache::BlockCache;
+pub mod checkpoint;
+pub use checkpoint::Checkpoint;
+pub mod column_family;
+pub use column_family::{ColumnFamilyDescriptor, ColumnFamilyHandle, DEFAULT_COLUMN_FAMILY_NAME};
 pub mod compaction_filter;
 pub use compaction_filter::{CompactionDecision, CompactionFilter, CompactionFilterFactory};
 pub mod comparator;
-pub use comparator::{BytewiseComparator, Comparator};
+pub use comparator::{BytewiseComparator, BytewiseComparatorWithU64Ts, Comparator};
 pub mod env;
-pub use env::{FileSystem, PosixFileSystem};
+pub use env::{Clock, FileSystem, PosixFileSystem, SystemClock};
 pub mod error;
 pub use error::Error;
 pub mod filter;
 pub use filter::BloomFilterPolicy;
+pub mod merge_operator;
+pub use merge_operator::MergeOperator;
 pub mod options;
-pub use options::{CompressionType, FlushOptions, Options, WriteOptions};
+pub use options::{
+  CompactionOptionsFIFO, CompactionOptionsUniversal, CompactionStyle, CompressionType,
+  FlushOptions, IngestExternalFileOptions, Options, WalRecoveryMode, WriteOptions,
+};
 pub(crate) mod coding;
 pub(crate) mod db;
+pub use db::wal_manager::{TransactionLogIterator, WalUpdate};
 pub(crate) mod iter;
 pub(crate) mod logfile;
 pub(crate) mod memtable;
+pub mod sst_file_writer;
 pub(crate) mod table;
+pub use sst_file_writer::{ExternalSstFileInfo, SstFileWriter};
+pub mod transaction;
+pub use transaction::{
+  OptimisticTransaction, Transaction, TransactionDb, TransactionDbOptions, TransactionOptions,
+};
+pub mod ttl;
+pub use ttl::{DbWithTtl, TtlCompactionFilterFactory, TtlIter, TtlOptions};
 pub mod write_batch;
 pub use write_batch::{Handler, WriteBatch};
+pub mod write_batch_with_index;
+pub use write_batch_with_index::{BaseDeltaIterator, WriteBatchWithIndex};
 
 /// An immutable snapshot of the database state at a particular sequence number.
 ///
@@ -203,6 +230,17 @@ pub struct ReadOptions<'snap> {
   /// Read as of this snapshot's sequence number.
   /// `None` means "use an implicit snapshot of the current state".
   pub snapshot: Option<&'snap Snapshot<'snap>>,
+
+  /// Read as of this user-defined timestamp: versions written with a newer
+  /// timestamp are invisible.
+  ///
+  /// Required, and must be [`Comparator::timestamp_size`] bytes long, when
+  /// the column family's comparator has timestamps; must be `None` otherwise.
+  ///
+  /// See `include/rocksdb/options.h: ReadOptions::timestamp`.
+  ///
+  /// Default: `None`.
+  pub timestamp: Option<&'snap [u8]>,
 }
 
 impl Default for ReadOptions<'_> {
@@ -211,28 +249,201 @@ impl Default for ReadOptions<'_> {
       verify_checksums: false,
       fill_cache: true,
       snapshot: None,
+      timestamp: None,
+    }
+  }
+}
+
+/// Result of a point lookup: the newest visible base value (`None` if the key
+/// is absent or deleted) and the merge operands written on top of it, newest
+/// first.
+type LookupOutcome = (Option<Vec<u8>>, Vec<Vec<u8>>);
+
+/// The value a point lookup of `key` through `view` reads: `base` with the
+/// merge operands written on top of it applied.
+fn merge_lookup(
+  view: &ReadView,
+  key: &[u8],
+  base: Option<Vec<u8>>,
+  operands: Vec<Vec<u8>>,
+) -> Result<Vec<u8>, Error> {
+  if operands.is_empty() {
+    return base.ok_or(Error::NotFound);
+  }
+  merge_operator::full_merge(
+    view.options.merge_operator.as_ref(),
+    key,
+    base.as_deref(),
+    &operands,
+  )
+}
+
+/// The key a lookup of `key` through `view` seeks: `key` itself, or `key`
+/// followed by the read timestamp when the column family has user-defined
+/// timestamps.
+///
+/// Returns [`Error::InvalidArgument`] if `opts.timestamp` does not match the
+/// comparator's timestamp size, or is older than the column family's
+/// `full_history_ts_low` (history below it may already be collapsed).
+fn lookup_key<'k>(
+  opts: &ReadOptions,
+  view: &ReadView,
+  key: &'k [u8],
+) -> Result<std::borrow::Cow<'k, [u8]>, Error> {
+  match check_read_timestamp(opts, view)? {
+    Some(ts) => Ok(std::borrow::Cow::Owned([key, ts].concat())),
+    None => Ok(std::borrow::Cow::Borrowed(key)),
+  }
+}
+
+/// Validate `opts.timestamp` against the comparator of `view`; see
+/// [`lookup_key`].
+fn check_read_timestamp<'a>(
+  opts: &ReadOptions<'a>,
+  view: &ReadView,
+) -> Result<Option<&'a [u8]>, Error> {
+  let cmp = &*view.options.comparator;
+  let ts_size = cmp.timestamp_size();
+  match opts.timestamp {
+    None if ts_size == 0 => Ok(None),
+    Some(ts) if ts_size > 0 && ts.len() == ts_size => {
+      let ts_low = view
+        .version
+        .as_ref()
+        .and_then(|v| v.full_history_ts_low.as_deref());
+      if ts_low.is_some_and(|low| cmp.compare_timestamp(ts, low).is_lt()) {
+        return Err(Error::InvalidArgument(
+          "read timestamp is older than full_history_ts_low".to_string(),
+        ));
+      }
+      Ok(Some(ts))
     }
+    _ => Err(Error::InvalidArgument(format!(
+      "ReadOptions::timestamp must be {ts_size} bytes for this column family"
+    ))),
+  }
+}
+
+/// What a read of one column family needs, cloned under the lock so the read
+/// itself proceeds without it.
+struct ReadView {
+  /// Entries newer than this are invisible.
+  sequence: u64,
+  options: Arc<Options>,
+  mem: Arc<Memtable>,
+  imm: Option<Arc<Memtable>>,
+  /// `None` for in-memory databases.
+  version: Option<Arc<crate::db::version::Version>>,
+  table_cache: Option<TableCache>,
+}
+
+// ── WAL recovery reporting ───────────────────────────────────────────────────
+
+/// Outcome of `Db::recover_wal`.
+struct WalReplay {
+  /// Highest sequence number replayed, or the `min_sequence` passed in if
+  /// nothing was.
+  max_sequence: u64,
+  /// `true` if part of the log was dropped as corrupt.
+  dropped: bool,
+  /// Where to resume reading the log: one past the start of the last record
+  /// read, or the `initial_offset` passed in if none was.
+  resume_offset: u64,
+}
+
+/// A range of a WAL that recovery did not replay.
+struct WalDrop {
+  /// File offset where the dropped range starts.
+  offset: u64,
+  bytes: u64,
+  reason: String,
+}
+
+impl WalDrop {
+  fn log(&self, log_number: u64) {
+    log::warn!(
+      "WAL {log_number:06}.log: dropping {} bytes at offset {}: {}",
+      self.bytes,
+      self.offset,
+      self.reason
+    );
+  }
+
+  fn into_error(self, log_number: u64) -> Error {
+    Error::Corruption(format!(
+      "WAL {log_number:06}.log: {} bytes at offset {}: {}",
+      self.bytes, self.offset, self.reason
+    ))
+  }
+}
+
+/// Logs each corruption the WAL reader reports during [`Db::open`] and hands
+/// it to `recover_wal`, which applies the [`WalRecoveryMode`].
+///
+/// See `LogReporter` in `db/db_impl/db_impl_open.cc` (RocksDB).
+struct WalRecoveryReporter {
+  log_number: u64,
+  drops: Arc<Mutex<Vec<WalDrop>>>,
+}
+
+impl crate::logfile::reader::Reporter for WalRecoveryReporter {
+  fn corruption(&mut self, offset: u64, bytes: u64, reason: &str) {
+    let drop = WalDrop {
+      offset,
+      bytes,
+      reason: reason.to_string(),
+    };
+    drop.log(self.log_number);
+    self.drops.lock().unwrap().push(drop);
   }
 }
 
 // ── Shared inserter used by both Db::write and Db::recover_wal ───────────────
 
+/// Inserts each record into the memtable of the column family it targets.
+///
+/// Records for a column family that does not exist (dropped after the batch
+/// was validated, or before a WAL record was replayed) still consume their
+/// sequence number but are otherwise skipped, as in RocksDB's
+/// `MemTableInserter`.
 struct Inserter<'a> {
-  mem: &'a Memtable,
+  column_families: &'a BTreeMap<u32, ColumnFamilyData>,
   seq: u64,
 }
 
-impl Handler for Inserter<'_> {
-  fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
-    self.mem.add(self.seq, key, value);
+impl Inserter<'_> {
+  fn insert(&mut self, cf: u32, f: impl FnOnce(&Memtable, u64)) -> Result<(), Error> {
+    if let Some(cfd) = self.column_families.get(&cf) {
+      f(&cfd.mem, self.seq);
+    }
     self.seq += 1;
     Ok(())
   }
+}
+
+impl Handler for Inserter<'_> {
+  fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+    self.put_cf(DEFAULT_COLUMN_FAMILY_ID, key, value)
+  }
 
   fn delete(&mut self, key: &[u8]) -> Result<(), Error> {
-    self.mem.delete(self.seq, key);
-    self.seq += 1;
-    Ok(())
+    self.delete_cf(DEFAULT_COLUMN_FAMILY_ID, key)
+  }
+
+  fn put_cf(&mut self, cf: u32, key: &[u8], value: &[u8]) -> Result<(), Error> {
+    self.insert(cf, |mem, seq| mem.add(seq, key, value))
+  }
+
+  fn delete_cf(&mut self, cf: u32, key: &[u8]) -> Result<(), Error> {
+    self.insert(cf, |mem, seq| mem.delete(seq, key))
+  }
+
+  fn merge_cf(&mut self, cf: u32, key: &[u8], value: &[u8]) -> Result<(), Error> {
+    self.insert(cf, |mem, seq| mem.merge(seq, key, value))
+  }
+
+  fn delete_range_cf(&mut self, cf: u32, begin: &[u8], end: &[u8]) -> Result<(), Error> {
+    self.insert(cf, |mem, seq| mem.delete_range(seq, begin, end))
   }
 }
 
@@ -256,6 +467,20 @@ struct WriterSlot {
   id: u64,
   batch: WriteBatch,
   sync: bool,
+  /// Set for an optimistic-transaction commit: validated by the leader just
+  /// before the batch is written.  Such a writer is always a group of one.
+  conflict_check: Option<ConflictCheck>,
+  /// Set for a replicated batch: the sequence number its first record must
+  /// get.  Such a writer is always a group of one.
+  sequence: Option<u64>,
+}
+
+/// Keys an optimistic transaction depends on, and the snapshot they were
+/// read at.  The commit fails with [`Error::Busy`] if any of them has been
+/// written since.
+struct ConflictCheck {
+  snapshot_seq: u64,
+  keys: Vec<Vec<u8>>,
 }
 
 struct DbState {
@@ -263,13 +488,10 @@ struct DbState {
   last_sequence: u64,
   /// `None` for in-memory / test databases (no WAL).
   log: Option<LogWriter>,
-  /// Active memtable.  `Arc` allows cloning a handle for iterators without
-  /// holding the DB lock.
-  mem: Arc<Memtable>,
-  /// Sealed memtable currently being flushed to disk.  Reads must check this
-  /// after `mem` and before the current `Version`.  `None` when no flush is
-  /// in progress.
-  imm: Option<Arc<Memtable>>,
+  /// Live column families by id.  The default family (id 0) always exists.
+  column_families: BTreeMap<u32, ColumnFamilyData>,
+  /// Largest column-family id allocated so far; ids are never reused.
+  max_column_family: u32,
   /// Tracks the set of live SSTable files and the MANIFEST.
   /// `None` for in-memory databases (`Db::default()`).
   version_set: Option<VersionSet>,
@@ -289,6 +511,191 @@ struct DbState {
   /// A follower checks this map on wake-up and removes its own entry.
   /// `Ok(())` is the common case; `Err` propagates a WAL/memtable failure.
   completed: std::collections::HashMap<u64, Result<(), Error>>,
+  // ── Background thread coordination ─────────────────────────────────────────
+  /// Compaction jobs requested from the worker threads but not yet taken up
+  /// by one.  Prevents redundant notifications.
+  bg_jobs_queued: usize,
+  /// Compaction jobs a worker thread is running; at most
+  /// `max_compaction_jobs`.
+  bg_jobs_running: usize,
+  /// Set when a worker found a compaction needed but every candidate
+  /// conflicted with a running one, or a FIFO merge into L0 had to wait for
+  /// a flush.  No further job is requested for compaction until a running
+  /// one finishes or a flush installs new files.
+  compactions_blocked: bool,
+  /// Sticky error set by a background thread on flush/compaction failure.
+  /// Returned to writers on their next call; clears only on reopen.
+  background_error: Option<Error>,
+  /// `FlushPrep` produced by `begin_flush` under the write lock; consumed by
+  /// the flush thread via `write_flush` + `finish_flush`.
+  pending_flush: Option<FlushPrep>,
+  /// Secondary instances only: how far each of the primary's WALs has been
+  /// read, by log number.
+  wal_offsets: Option<BTreeMap<u64, u64>>,
+  /// Number of live [`FileDeletionPause`] guards.  While non-zero, obsolete
+  /// files are left on disk.
+  file_deletions_paused: usize,
+  /// Id of the next [`CompactionClaim`].
+  next_compaction_id: u64,
+  /// First file numbers of the running flushes and compactions, with a count
+  /// of each.  Files numbered at or above the smallest may be outputs not yet
+  /// installed, so `delete_obsolete_files` keeps them.
+  pending_outputs: BTreeMap<u64, usize>,
+}
+
+impl DbState {
+  fn new(
+    last_sequence: u64,
+    log: Option<LogWriter>,
+    version_set: Option<VersionSet>,
+    column_families: BTreeMap<u32, ColumnFamilyData>,
+  ) -> Self {
+    let max_column_family = version_set.as_ref().map_or_else(
+      || column_families.keys().copied().max().unwrap_or(0),
+      |vs| vs.max_column_family(),
+    );
+    DbState {
+      last_sequence,
+      log,
+      column_families,
+      max_column_family,
+      version_set,
+      snapshots: std::collections::BTreeMap::new(),
+      writers: std::collections::VecDeque::new(),
+      next_writer_id: 0,
+      completed: std::collections::HashMap::new(),
+      bg_jobs_queued: 0,
+      bg_jobs_running: 0,
+      compactions_blocked: false,
+      background_error: None,
+      pending_flush: None,
+      wal_offsets: None,
+      file_deletions_paused: 0,
+      next_compaction_id: 0,
+      pending_outputs: BTreeMap::new(),
+    }
+  }
+
+  /// The live column family `id`, or [`Error::InvalidArgument`] if it has
+  /// been dropped.
+  fn column_family(&self, id: u32) -> Result<&ColumnFamilyData, Error> {
+    self
+      .column_families
+      .get(&id)
+      .ok_or_else(|| Error::InvalidArgument(format!("column family {id} does not exist")))
+  }
+
+  fn default_column_family(&self) -> &ColumnFamilyData {
+    &self.column_families[&DEFAULT_COLUMN_FAMILY_ID]
+  }
+
+  /// Current `Version` of column family `id`; `None` for in-memory databases
+  /// and dropped families.
+  fn current(&self, id: u32) -> Option<Arc<crate::db::version::Version>> {
+    self.version_set.as_ref().and_then(|vs| vs.current(id))
+  }
+
+  /// Keep the files numbered from now on from being deleted as obsolete
+  /// until [`release_pending_output`](Self::release_pending_output) is called
+  /// with the returned number: they may be outputs of a flush or compaction
+  /// that has yet to install them.
+  ///
+  /// See `DBImpl::CaptureCurrentFileNumberInPendingOutputs` in RocksDB.
+  fn capture_pending_output(&mut self) -> u64 {
+    let number = self
+      .version_set
+      .as_ref()
+      .map_or(0, |vs| vs.current_next_file_number());
+    *self.pending_outputs.entry(number).or_default() += 1;
+    number
+  }
+
+  fn release_pending_output(&mut self, number: u64) {
+    if let Some(count) = self.pending_outputs.get_mut(&number) {
+      *count -= 1;
+      if *count == 0 {
+        self.pending_outputs.remove(&number);
+      }
+    }
+  }
+
+  /// `true` from `begin_flush` until `finish_flush` has installed the flushed
+  /// tables.
+  fn flush_in_progress(&self) -> bool {
+    self.pending_flush.is_some() || self.column_families.values().any(|cf| cf.imm.is_some())
+  }
+
+  /// `true` if any column family's memtable has reached its `write_buffer_size`.
+  fn memtable_full(&self) -> bool {
+    self
+      .column_families
+      .values()
+      .any(|cf| cf.mem.approximate_memory_usage() >= cf.options.write_buffer_size)
+  }
+
+  /// `true` if no column family's memtable holds any data.
+  fn memtables_empty(&self) -> bool {
+    self
+      .column_families
+      .values()
+      .all(|cf| cf.mem.approximate_memory_usage() == 0)
+  }
+
+  /// `(slowdown, stop)`: whether any column family's L0 file count has
+  /// reached its `level0_slowdown_writes_trigger`, and whether any has
+  /// reached its `level0_stop_writes_trigger`; drives write backpressure.
+  /// Families using FIFO compaction, which keeps every file in L0, are left
+  /// out.
+  fn l0_write_stall(&self) -> (bool, bool) {
+    let mut stall = (false, false);
+    for (&id, cf) in &self.column_families {
+      if cf.options.compaction_style == CompactionStyle::Fifo {
+        continue;
+      }
+      let Some(v) = self.current(id) else {
+        continue;
+      };
+      let o = &cf.options;
+      let slowdown = o
+        .level0_slowdown_writes_trigger
+        .max(o.level0_file_num_compaction_trigger);
+      let stop = o.level0_stop_writes_trigger.max(slowdown);
+      stall.0 |= v.num_files(0) >= slowdown;
+      stall.1 |= v.num_files(0) >= stop;
+    }
+    stall
+  }
+
+  /// `true` if any column family needs a size- or seek-triggered compaction.
+  fn compaction_pending(&self) -> bool {
+    self.column_families.iter().any(|(&id, cf)| {
+      self
+        .current(id)
+        .is_some_and(|v| needs_compaction(&v, cf.compaction_needed, &cf.options))
+    })
+  }
+}
+
+// ── ColumnFamilyData ──────────────────────────────────────────────────────────
+
+/// State of one column family inside `DbState`.  The family's current
+/// `Version` lives in the `VersionSet` under the same id.
+///
+/// See `db/column_family.h: ColumnFamilyData` in RocksDB.
+struct ColumnFamilyData {
+  name: String,
+  /// The family's options; database-wide fields match the `Db`'s.
+  options: Arc<Options>,
+  /// Active memtable.  `Arc` allows cloning a handle for iterators without
+  /// holding the DB lock.
+  mem: Arc<Memtable>,
+  /// Sealed memtable currently being flushed to disk.  Reads must check this
+  /// after `mem` and before the current `Version`.  `None` when no flush is
+  /// in progress.
+  imm: Option<Arc<Memtable>>,
+  /// Opens this family's SSTables with its comparator and filter policy.
+  /// `None` for in-memory databases.
+  table_cache: Option<TableCache>,
   // ── Seek-based compaction (Gap 5) ──────────────────────────────────────────
   /// File + level nominated for seek-based compaction (its `allowed_seeks` hit
   /// zero).  `None` when no file is pending.  Cleared by `install_compaction`
@@ -297,16 +704,113 @@ struct DbState {
   /// Set to `true` when `seek_compact_file` is first populated, so the next
   /// `Db::write` compaction loop runs even without a preceding flush.
   compaction_needed: bool,
-  // ── Background thread coordination ─────────────────────────────────────────
-  /// `true` while the background thread has been notified and hasn't yet
-  /// finished one cycle.  Prevents redundant notifications.
-  background_scheduled: bool,
-  /// Sticky error set by the background thread on flush/compaction failure.
-  /// Returned to writers on their next call; clears only on reopen.
-  background_error: Option<Error>,
-  /// `FlushPrep` produced by `begin_flush` under the write lock; consumed by
-  /// the background thread via `write_flush` + `finish_flush`.
-  pending_flush: Option<FlushPrep>,
+  /// Compactions between picking their inputs and installing their outputs;
+  /// see [`RunningCompaction`].
+  running_compactions: Vec<CompactionClaim>,
+  /// Sequence number of the latest ingestion into this family, `0` if none
+  /// since open.  Ingested keys skip the memtables, so transaction
+  /// validation cannot see which keys they touched.
+  last_ingested_sequence: u64,
+}
+
+impl ColumnFamilyData {
+  fn new(name: &str, options: Arc<Options>, table_cache: Option<TableCache>) -> Self {
+    ColumnFamilyData {
+      name: name.to_owned(),
+      mem: Arc::new(Memtable::new(Arc::clone(&options.comparator))),
+      imm: None,
+      table_cache,
+      seek_compact_file: None,
+      compaction_needed: false,
+      running_compactions: Vec::new(),
+      last_ingested_sequence: 0,
+      options,
+    }
+  }
+
+  fn handle(&self, id: u32) -> ColumnFamilyHandle {
+    ColumnFamilyHandle::new(id, &self.name)
+  }
+}
+
+/// Open every table and blob file of `version` that `tc` does not hold yet.
+/// Returns the name of the first that cannot be opened.
+fn open_version_files(version: &crate::db::version::Version, tc: &TableCache) -> Option<String> {
+  for level in 0..version.num_levels() {
+    for meta in version.files_at(level) {
+      if !tc.contains(meta.number) && tc.get_or_open(meta.number, meta.file_size).is_err() {
+        return Some(format!("{:06}.ldb", meta.number));
+      }
+    }
+  }
+  for &number in version.blob_files().keys() {
+    if !tc.contains(number) && tc.get_blob_file(number).is_err() {
+      return Some(format!("{number:06}.blob"));
+    }
+  }
+  None
+}
+
+/// Reject a descriptor for the default column family (configured by the
+/// database options) and names listed twice.
+fn check_column_family_descriptors(
+  column_families: &[ColumnFamilyDescriptor],
+) -> Result<(), Error> {
+  for (i, cf) in column_families.iter().enumerate() {
+    if cf.name == DEFAULT_COLUMN_FAMILY_NAME {
+      return Err(Error::InvalidArgument(
+        "the default column family is configured by the database options".to_owned(),
+      ));
+    }
+    if column_families[..i].iter().any(|c| c.name == cf.name) {
+      return Err(Error::InvalidArgument(format!(
+        "column family listed twice: {}",
+        cf.name
+      )));
+    }
+  }
+  Ok(())
+}
+
+/// Reject level settings no column family can be run with: `name`'s
+/// `options` must have at least one level (two under
+/// [`CompactionStyle::Level`], which compacts L0 into the next level), a
+/// positive L0 compaction trigger and a positive level-size multiplier.
+///
+/// See `db/column_family.cc: ColumnFamilyData::ValidateOptions` in RocksDB.
+fn check_level_options(name: &str, options: &Options) -> Result<(), Error> {
+  let min_levels = match options.compaction_style {
+    CompactionStyle::Level => 2,
+    _ => 1,
+  };
+  let problem = if options.num_levels < min_levels {
+    format!("num_levels must be at least {min_levels}")
+  } else if options.level0_file_num_compaction_trigger == 0 {
+    "level0_file_num_compaction_trigger must be positive".to_owned()
+  } else if options.max_bytes_for_level_multiplier <= 0.0 {
+    "max_bytes_for_level_multiplier must be positive".to_owned()
+  } else {
+    return Ok(());
+  };
+  Err(Error::InvalidArgument(format!(
+    "column family {name}: {problem}"
+  )))
+}
+
+/// Create the table cache for a column family of the database at `path`.
+fn new_table_cache(path: &std::path::Path, options: &Options) -> TableCache {
+  let cache_capacity = options
+    .max_open_files
+    .saturating_sub(crate::db::table_cache::NUM_NON_TABLE_CACHE_FILES)
+    .max(1);
+  TableCache::new(
+    path,
+    cache_capacity,
+    options.filter_policy.clone(),
+    options.block_cache.clone(),
+    Arc::clone(&options.comparator),
+    Arc::clone(&options.file_system),
+  )
 }
 
 // ── FlushPrep / FlushResult ───────────────────────────────────────────────────
@@ -314,9 +818,8 @@ struct DbState {
 /// Produced by `begin_flush` (under the write lock): everything needed to run
 /// `write_flush` and `finish_flush` without holding the lock.
 struct FlushPrep {
-  sst_number: u64,
-  sst_path: std::path::PathBuf,
-  old_mem: Arc<Memtable>,
+  /// One sealed memtable per column family that had data.
+  memtables: Vec<MemtableFlush>,
   /// File number of the new WAL that was activated in `begin_flush`.
   /// `finish_flush` records this in the MANIFEST via `vs.set_log_number`.
   new_log_number: u64,
@@ -326,11 +829,42 @@ struct FlushPrep {
   last_sequence_at_rotation: u64,
   /// Path of the old WAL to delete after `log_and_apply` succeeds.
   old_log_path: std::path::PathBuf,
+  old_log_number: u64,
+  /// From `DbState::capture_pending_output`; released once the flush ends.
+  pending_output: u64,
+}
+
+/// A sealed memtable of one column family and the SSTable it is flushed to.
+struct MemtableFlush {
+  column_family: u32,
+  options: Arc<Options>,
+  sst_number: u64,
+  sst_path: std::path::PathBuf,
+  old_mem: Arc<Memtable>,
+  /// The level the table is meant for, from [`plan_flush_output`].
+  output_level: usize,
+  /// The compression of files written to `output_level`.
+  compression: CompressionType,
 }
 
 /// Return value of `write_flush`: everything `finish_flush` needs to install
-/// the new SSTable into the `VersionSet` and complete WAL rotation.
+/// the new SSTables into the `VersionSet` and complete WAL rotation.
 struct FlushResult {
+  tables: Vec<FlushedTable>,
+  /// New WAL file number (already active; just needs to be committed to MANIFEST).
+  new_log_number: u64,
+  /// Sequence number captured at rotation time; used as `last_sequence` in the
+  /// MANIFEST so WAL replay correctly replays entries written after the rotation.
+  last_sequence_at_rotation: u64,
+  /// Path of the old WAL, deleted (or archived) by `finish_flush` after
+  /// `log_and_apply`.
+  old_log_path: std::path::PathBuf,
+  old_log_number: u64,
+}
+
+/// One SSTable written from a column family's memtable.
+struct FlushedTable {
+  column_family: u32,
   file_number: u64,
   file_size: u64,
   /// Smallest internal key written to the SSTable.
@@ -341,14 +875,11 @@ struct FlushResult {
   smallest_user_key: Vec<u8>,
   /// User-key extracted from `largest` (for `pick_level_for_memtable_output`).
   largest_user_key: Vec<u8>,
+  /// The level the table was compressed for; it is installed no deeper.
+  output_level: usize,
   table: Arc<Table>,
-  /// New WAL file number (already active; just needs to be committed to MANIFEST).
-  new_log_number: u64,
-  /// Sequence number captured at rotation time; used as `last_sequence` in the
-  /// MANIFEST so WAL replay correctly replays entries written after the rotation.
-  last_sequence_at_rotation: u64,
-  /// Path of the old WAL, deleted by `finish_flush` after `log_and_apply`.
-  old_log_path: std::path::PathBuf,
+  /// Blob files holding the values separated from the SSTable.
+  blob_files: Vec<BlobFileAddition>,
 }
 
 // ── DbIter ────────────────────────────────────────────────────────────────────
@@ -370,6 +901,8 @@ struct FlushResult {
 /// [`prev`]: DbIter::prev
 pub struct DbIter {
   inner: db::db_iter::DbIterator,
+  /// [`ReadOptions::timestamp`]; empty without user-defined timestamps.
+  read_timestamp: Vec<u8>,
 }
 
 impl DbIter {
@@ -389,8 +922,14 @@ impl DbIter {
   }
 
   /// Position at the first user-visible entry with `key >= target`.
+  ///
+  /// With user-defined timestamps, `key` excludes the timestamp.
   pub fn seek(&mut self, key: &[u8]) {
-    self.inner.seek(key);
+    if self.read_timestamp.is_empty() {
+      self.inner.seek(key);
+    } else {
+      self.inner.seek(&[key, &self.read_timestamp].concat());
+    }
   }
 
   /// Advance to the next user-visible entry.
@@ -409,9 +948,18 @@ impl DbIter {
     self.inner.prev();
   }
 
-  /// Current user key.  Only valid when `valid()` is true.
+  /// Current user key, without its timestamp.  Only valid when `valid()` is
+  /// true.
   pub fn key(&self) -> &[u8] {
-    self.inner.key()
+    comparator::strip_timestamp(self.inner.key(), self.read_timestamp.len())
+  }
+
+  /// User-defined timestamp of the current entry; empty if the column family
+  /// has none.  Only valid when `valid()` is true.
+  ///
+  /// See `include/rocksdb/iterator.h: Iterator::timestamp`.
+  pub fn timestamp(&self) -> &[u8] {
+    comparator::timestamp_of(self.inner.key(), self.read_timestamp.len())
   }
 
   /// Current value.  Only valid when `valid()` is true.
@@ -491,31 +1039,38 @@ impl Iterator for ForwardIter<'_> {
 
 struct Persistence {
   dir: PathBuf,
-  table_cache: TableCache,
   fs: Arc<dyn crate::env::FileSystem>,
-  _lock: Box<dyn crate::env::FileLock>,
+  /// `None` for a read-only database, which does not take the `LOCK` file.
+  _lock: Option<Box<dyn crate::env::FileLock>>,
 }
 
 // ── DbInner / Db ─────────────────────────────────────────────────────────────
 
 /// Shared state owned by both the foreground `Db` handle and the background
-/// compaction/flush thread.  Always accessed through an `Arc`.
+/// compaction/flush threads.  Always accessed through an `Arc`.
 pub(crate) struct DbInner {
   pub(crate) state: Mutex<DbState>,
   /// Wakes follower writers when the current group leader finishes and when
   /// there may be a new leader at the front of the write queue.
   write_condvar: std::sync::Condvar,
-  /// Wakes the background thread when work is available (flush / compact).
+  /// Wakes a compaction worker when a compaction job is requested.
   bg_condvar: std::sync::Condvar,
+  /// Wakes the flush thread when `pending_flush` is set.
+  flush_condvar: std::sync::Condvar,
   pub(crate) options: Options,
   pub(crate) persistence: Option<Persistence>,
-  /// Set to `true` on `Db::drop` to tell the background thread to exit.
+  /// Set to `true` on `Db::drop` to tell the background threads to exit.
   shutting_down: AtomicBool,
+  /// Set by [`Db::open_read_only`]: writes are rejected and no background
+  /// work is ever scheduled.
+  read_only: bool,
 }
 
 pub struct Db {
   pub(crate) inner: Arc<DbInner>,
-  bg_thread: Option<std::thread::JoinHandle<()>>,
+  /// The thread running [`flush_worker`] and the threads running
+  /// [`bg_worker`].
+  bg_threads: Vec<std::thread::JoinHandle<()>>,
 }
 
 impl Default for Db {
@@ -524,29 +1079,28 @@ impl Default for Db {
   fn default() -> Self {
     Self {
       inner: Arc::new(DbInner {
-        state: Mutex::new(DbState {
-          last_sequence: 0,
-          log: None,
-          mem: Arc::new(Memtable::default()),
-          imm: None,
-          version_set: None,
-          snapshots: std::collections::BTreeMap::new(),
-          writers: std::collections::VecDeque::new(),
-          next_writer_id: 0,
-          completed: std::collections::HashMap::new(),
-          seek_compact_file: None,
-          compaction_needed: false,
-          background_scheduled: false,
-          background_error: None,
-          pending_flush: None,
-        }),
+        state: Mutex::new(DbState::new(
+          0,
+          None,
+          None,
+          BTreeMap::from([(
+            DEFAULT_COLUMN_FAMILY_ID,
+            ColumnFamilyData::new(
+              DEFAULT_COLUMN_FAMILY_NAME,
+              Arc::new(Options::default()),
+              None,
+            ),
+          )]),
+        )),
         write_condvar: std::sync::Condvar::new(),
         bg_condvar: std::sync::Condvar::new(),
+        flush_condvar: std::sync::Condvar::new(),
         options: Options::default(),
         persistence: None,
         shutting_down: AtomicBool::new(false),
+        read_only: false,
       }),
-      bg_thread: None,
+      bg_threads: Vec::new(),
     }
   }
 }
@@ -559,11 +1113,12 @@ impl Drop for Db {
       // Notify while holding the state mutex: bg_worker checks shutting_down
       // under this mutex before parking on bg_condvar, so an unlocked notify
       // can land between its check and its park and be lost — leaving the
-      // join below waiting forever.
+      // joins below waiting forever.
       let g = self.inner.state.lock().unwrap();
-      self.inner.bg_condvar.notify_one();
+      self.inner.bg_condvar.notify_all();
+      self.inner.flush_condvar.notify_all();
       drop(g);
-      if let Some(t) = self.bg_thread.take() {
+      for t in self.bg_threads.drain(..) {
         t.join().ok();
       }
     }
@@ -589,8 +1144,31 @@ impl Db {
   /// If another process already holds the lock, an [`Error::IoError`] is returned
   /// immediately rather than blocking.  The lock is released when the `Db` is dropped.
   ///
+  /// Only the default column family is opened; a database with other column
+  /// families must be opened with [`Db::open_with_column_families`].
+  ///
   /// `Db::default()` is retained for in-memory / test use (no WAL, no flush).
   pub fn open<P: AsRef<std::path::Path>>(path: P, options: Options) -> Result<Self, Error> {
+    Self::open_with_column_families(path, options, Vec::new())
+  }
+
+  /// Open (or create) a persistent database at `path` together with the
+  /// column families in `column_families`.  `options` configures the default
+  /// column family and the database as a whole.
+  ///
+  /// Every column family recorded in the MANIFEST must be listed (see
+  /// [`Db::list_column_families`]), otherwise [`Error::InvalidArgument`] is
+  /// returned.  A listed family that does not exist yet is created if
+  /// `options.create_missing_column_families` is set, and is an
+  /// [`Error::InvalidArgument`] otherwise.  Handles to the opened families are
+  /// available through [`Db::column_family`].
+  ///
+  /// See `db/db_impl/db_impl_open.cc: DB::Open` in RocksDB.
+  pub fn open_with_column_families<P: AsRef<std::path::Path>>(
+    path: P,
+    options: Options,
+    column_families: Vec<ColumnFamilyDescriptor>,
+  ) -> Result<Self, Error> {
     let path = path.as_ref();
     let fs = Arc::clone(&options.file_system);
     let current_path = path.join("CURRENT");
@@ -608,6 +1186,7 @@ impl Db {
         path.display()
       )));
     }
+    check_column_family_descriptors(&column_families)?;
 
     // Create the directory only when we are going to create a new database.
     if !db_exists {
@@ -618,91 +1197,141 @@ impl Db {
     // This prevents two processes from corrupting the same database concurrently.
     let lock_file = fs.lock_file(&path.join("LOCK"))?;
 
-    // Create the table cache for this persistent database.
-    let cache_capacity = options
-      .max_open_files
-      .saturating_sub(crate::db::table_cache::NUM_NON_TABLE_CACHE_FILES)
-      .max(1);
-    let table_cache = crate::db::table_cache::TableCache::new(
-      path,
-      cache_capacity,
-      options.filter_policy.clone(),
-      options.block_cache.clone(),
-      Arc::clone(&options.comparator),
-      Arc::clone(&options.file_system),
-    );
+    let cf_options: Vec<(String, Arc<Options>)> = column_families
+      .into_iter()
+      .map(|cf| {
+        (
+          cf.name,
+          Arc::new(column_family_options(&options, cf.options)),
+        )
+      })
+      .collect();
+    check_level_options(DEFAULT_COLUMN_FAMILY_NAME, &options)?;
+    for (name, cf_opts) in &cf_options {
+      check_level_options(name, cf_opts)?;
+    }
+    let default_options = Arc::new(options.clone());
 
-    let (version_set, mem, last_sequence) = if db_exists {
+    let (mut vs, manifest_last_seq) = if db_exists {
       // ── Existing database: MANIFEST-driven recovery ──────────────────────
       log::info!("opening existing database at {}", path.display());
-      let mut vs = VersionSet::recover(
+      let listed: Vec<(&str, Arc<Options>)> = cf_options
+        .iter()
+        .map(|(name, o)| (name.as_str(), Arc::clone(o)))
+        .collect();
+      let vs = VersionSet::recover(
         path,
         options.paranoid_checks,
-        Arc::clone(&options.comparator),
+        Arc::clone(&default_options),
+        &listed,
         &*options.file_system,
+        false,
       )?;
-      let manifest_last_seq = vs.last_sequence();
-      let mem = Arc::new(Memtable::new(Arc::clone(&options.comparator)));
-
-      // Replay WAL records newer than what is already in the SSTables.
-      // Use the log number recorded in the MANIFEST (set by WAL rotation).
-      let log_path = path.join(format!("{:06}.log", vs.log_number()));
-      let actual_last_seq = if fs.file_exists(&log_path) {
-        let file_len = fs.file_size(&log_path)?;
-        if file_len > 0 {
-          log::info!(
-            "replaying WAL {:06}.log (manifest_last_seq={})",
-            vs.log_number(),
-            manifest_last_seq
-          );
-          let file = options.file_system.open_sequential(&log_path)?;
-          let seq = Self::recover_wal(file, &mem, manifest_last_seq, options.paranoid_checks)?;
-          log::info!("WAL replay complete: max_sequence={seq}");
-          seq
-        } else {
-          manifest_last_seq
-        }
-      } else {
-        manifest_last_seq
-      };
-
-      if actual_last_seq > manifest_last_seq {
-        vs.set_last_sequence(actual_last_seq);
-      }
       let last_seq = vs.last_sequence();
-
-      // When reuse_logs is false and the WAL had data, flush the replayed
-      // memtable to an SSTable so the next open doesn't need to replay it.
-      // When reuse_logs is true (or the WAL was empty), keep the replayed
-      // memtable as the active one and append to the existing WAL.
-      if !options.reuse_logs
-        && actual_last_seq > manifest_last_seq
-        && mem.approximate_memory_usage() > 0
-      {
-        log::info!("flushing replayed WAL data to SSTable (reuse_logs=false)");
-        let flush_result = write_flush_from_mem(&mem, &mut vs, &options, &*fs, path)?;
-        finish_flush_at_open(&mut vs, flush_result, &options, &table_cache)?;
-        let fresh_mem = Arc::new(Memtable::new(Arc::clone(&options.comparator)));
-        (Some(vs), fresh_mem, last_seq)
-      } else {
-        (Some(vs), mem, last_seq)
-      }
+      (vs, last_seq)
     } else {
       // ── New database: create MANIFEST and WAL ────────────────────────────
       log::info!("creating new database at {}", path.display());
-      let vs = VersionSet::create(path, Arc::clone(&options.comparator), &*options.file_system)?;
+      let vs = VersionSet::create(path, Arc::clone(&default_options), &*options.file_system)?;
       // Initial WAL is always 000001.log (log_number = 1 from VersionSet::create).
       fs.create_writable(&path.join("000001.log"))?;
-      (
-        Some(vs),
-        Arc::new(Memtable::new(Arc::clone(&options.comparator))),
-        0,
-      )
+      (vs, 0)
     };
 
+    // Create the families the MANIFEST does not know yet, then set up the
+    // in-memory state of every family.
+    let mut cf_data = BTreeMap::from([(
+      DEFAULT_COLUMN_FAMILY_ID,
+      ColumnFamilyData::new(
+        DEFAULT_COLUMN_FAMILY_NAME,
+        Arc::clone(&default_options),
+        Some(new_table_cache(path, &default_options)),
+      ),
+    )]);
+    for (name, cf_opts) in cf_options {
+      let existing = vs
+        .column_families()
+        .find(|&(_, n)| n == name)
+        .map(|(id, _)| id);
+      let id = match existing {
+        Some(id) => id,
+        None if options.create_missing_column_families => {
+          let id = vs.max_column_family() + 1;
+          vs.create_column_family(id, &name, Arc::clone(&cf_opts))?;
+          log::info!("created column family '{name}' (id {id})");
+          id
+        }
+        None => {
+          return Err(Error::InvalidArgument(format!(
+            "column family does not exist: {name}"
+          )))
+        }
+      };
+      let table_cache = new_table_cache(path, &cf_opts);
+      cf_data.insert(id, ColumnFamilyData::new(&name, cf_opts, Some(table_cache)));
+    }
+
+    // Replay WAL records newer than what is already in the SSTables.
+    // Use the log number recorded in the MANIFEST (set by WAL rotation).
+    let log_path = path.join(format!("{:06}.log", vs.log_number()));
+    let (actual_last_seq, wal_dropped) =
+      if db_exists && fs.file_exists(&log_path) && fs.file_size(&log_path)? > 0 {
+        log::info!(
+          "replaying WAL {:06}.log (manifest_last_seq={})",
+          vs.log_number(),
+          manifest_last_seq
+        );
+        let file = options.file_system.open_sequential(&log_path)?;
+        let replay = Self::recover_wal(
+          file,
+          vs.log_number(),
+          0,
+          &cf_data,
+          manifest_last_seq,
+          options.wal_recovery_mode,
+        )?;
+        log::info!("WAL replay complete: max_sequence={}", replay.max_sequence);
+        (replay.max_sequence, replay.dropped)
+      } else {
+        (manifest_last_seq, false)
+      };
+
+    // An ingestion's WAL marker is written after its files are installed,
+    // so a crash in between leaves only the files to show their sequence.
+    let actual_last_seq = actual_last_seq.max(vs.max_file_sequence());
+    if actual_last_seq > manifest_last_seq {
+      vs.set_last_sequence(actual_last_seq);
+    }
+    let last_sequence = vs.last_sequence();
+
+    // When reuse_logs is false and the WAL had data, flush the replayed
+    // memtables to SSTables so the next open doesn't need to replay them.
+    // When reuse_logs is true (or the WAL was empty), keep the replayed
+    // memtables as the active ones and append to the existing WAL.
+    //
+    // If replay dropped part of the WAL, appending to it would put new writes
+    // behind the damage, where the next recovery may drop them too.  Flush
+    // what was replayed and switch to a fresh WAL instead.
+    if wal_dropped {
+      log::warn!(
+        "WAL {:06}.log was not fully replayed; starting a new WAL",
+        vs.log_number()
+      );
+      let new_log_number = vs.next_file_number();
+      vs.set_log_number(new_log_number);
+      flush_memtables_at_open(&mut vs, &mut cf_data, &*fs, path)?;
+    } else if !options.reuse_logs
+      && actual_last_seq > manifest_last_seq
+      && cf_data
+        .values()
+        .any(|cf| cf.mem.approximate_memory_usage() > 0)
+    {
+      log::info!("flushing replayed WAL data to SSTables (reuse_logs=false)");
+      flush_memtables_at_open(&mut vs, &mut cf_data, &*fs, path)?;
+    }
+
     // Open (or reopen) the current WAL for subsequent writes.
-    let log_number = version_set.as_ref().map_or(1, |vs| vs.log_number());
-    let log_path = path.join(format!("{log_number:06}.log"));
+    let log_path = path.join(format!("{:06}.log", vs.log_number()));
     let file_len = if fs.file_exists(&log_path) {
       fs.file_size(&log_path)?
     } else {
@@ -710,80 +1339,472 @@ impl Db {
     };
     let log_file = fs.open_appendable(&log_path)?;
     let log_writer = LogWriter::new(log_file, file_len);
+    if crate::db::wal_manager::archiving_enabled(&options) {
+      crate::db::wal_manager::purge_archived_wals(path, &options);
+    }
 
     let inner = Arc::new(DbInner {
-      state: Mutex::new(DbState {
+      state: Mutex::new(DbState::new(
         last_sequence,
-        log: Some(log_writer),
-        mem,
-        imm: None,
-        version_set,
-        snapshots: std::collections::BTreeMap::new(),
-        writers: std::collections::VecDeque::new(),
-        next_writer_id: 0,
-        completed: std::collections::HashMap::new(),
-        seek_compact_file: None,
-        compaction_needed: false,
-        background_scheduled: false,
-        background_error: None,
-        pending_flush: None,
-      }),
+        Some(log_writer),
+        Some(vs),
+        cf_data,
+      )),
       write_condvar: std::sync::Condvar::new(),
       bg_condvar: std::sync::Condvar::new(),
+      flush_condvar: std::sync::Condvar::new(),
       persistence: Some(Persistence {
         dir: path.to_path_buf(),
-        table_cache,
         fs: Arc::clone(&fs),
-        _lock: lock_file,
+        _lock: Some(lock_file),
       }),
       options,
       shutting_down: AtomicBool::new(false),
+      read_only: false,
     });
-    let bg_inner = Arc::clone(&inner);
-    let bg_thread = std::thread::spawn(move || bg_worker(bg_inner));
-    Ok(Self {
-      inner,
-      bg_thread: Some(bg_thread),
-    })
+    let flush_inner = Arc::clone(&inner);
+    let bg_threads = std::iter::once(std::thread::spawn(move || flush_worker(flush_inner)))
+      .chain((0..max_compaction_jobs(&inner.options)).map(|_| {
+        let bg_inner = Arc::clone(&inner);
+        std::thread::spawn(move || bg_worker(bg_inner))
+      }))
+      .collect();
+    Ok(Self { inner, bg_threads })
   }
 
-  /// Replay WAL records from `file` into `mem`, skipping any whose sequence
-  /// number is entirely covered by `min_sequence` (already in an SSTable).
+  /// Open the existing database at `path` for reading only.
   ///
-  /// Returns the highest sequence number replayed (or `min_sequence` if
-  /// nothing was replayed).
-  fn recover_wal(
-    file: Box<dyn crate::env::SequentialFile>,
-    mem: &Memtable,
-    min_sequence: u64,
-    paranoid_checks: bool,
-  ) -> Result<u64, Error> {
-    let mut reader = LogReader::new(file, None, paranoid_checks, 0);
-    let mut max_sequence: u64 = min_sequence;
+  /// The `VersionSet` is recovered from `CURRENT` and the MANIFEST, and the
+  /// WALs not yet flushed are replayed into memtables that are never
+  /// flushed.  Nothing in the directory is created, written or deleted: the
+  /// `LOCK` file is not taken (another process may have the database open
+  /// for writing), no background thread is started, and writes, flushes,
+  /// compactions and column family changes return [`Error::NotSupported`].
+  /// The handle sees the database as it was when opened.
+  ///
+  /// Returns [`Error::InvalidArgument`] if there is no database at `path`.
+  ///
+  /// See `db/db_impl/db_impl_readonly.cc: DB::OpenForReadOnly` in RocksDB.
+  pub fn open_read_only<P: AsRef<std::path::Path>>(
+    path: P,
+    options: Options,
+  ) -> Result<Self, Error> {
+    Self::open_read_only_with_column_families(path, options, Vec::new())
+  }
 
-    while let Some(record) = reader.read_record() {
-      let batch = WriteBatch::from_contents(record)?;
-      let start_seq = batch.sequence();
-      // Skip batches fully covered by data already in SSTables.
-      if batch.count() > 0 {
-        let end_seq = start_seq + batch.count() as u64 - 1;
-        if end_seq <= min_sequence {
-          continue;
-        }
-      }
-      batch.iterate(&mut Inserter {
-        mem,
-        seq: start_seq,
-      })?;
-      if batch.count() > 0 {
-        let end_seq = start_seq + batch.count() as u64 - 1;
-        if end_seq > max_sequence {
-          max_sequence = end_seq;
+  /// Open the existing database at `path` for reading only, together with
+  /// the column families in `column_families`; see [`Db::open_read_only`].
+  ///
+  /// Unlike [`Db::open_with_column_families`], families recorded in the
+  /// MANIFEST may be left out; they are not opened.  A listed family that
+  /// does not exist is an [`Error::InvalidArgument`].
+  pub fn open_read_only_with_column_families<P: AsRef<std::path::Path>>(
+    path: P,
+    options: Options,
+    column_families: Vec<ColumnFamilyDescriptor>,
+  ) -> Result<Self, Error> {
+    Self::open_read_only_internal(path.as_ref(), options, column_families, false)
+  }
+
+  /// Open the database at `path` as a secondary instance of the primary that
+  /// has it open for writing.
+  ///
+  /// A secondary is a read-only handle (see [`Db::open_read_only`]) that can
+  /// be brought up to date with [`Db::try_catch_up_with_primary`] instead of
+  /// being reopened.  It keeps every table it references open, whatever
+  /// [`Options::max_open_files`] says, so files the primary deletes after
+  /// compacting them stay readable until the secondary catches up.
+  ///
+  /// See `db/db_impl/db_impl_secondary.cc: DB::OpenAsSecondary` in RocksDB.
+  pub fn open_as_secondary<P: AsRef<std::path::Path>>(
+    path: P,
+    options: Options,
+  ) -> Result<Self, Error> {
+    Self::open_as_secondary_with_column_families(path, options, Vec::new())
+  }
+
+  /// Open the database at `path` as a secondary instance together with the
+  /// column families in `column_families`; see [`Db::open_as_secondary`] and
+  /// [`Db::open_read_only_with_column_families`].
+  pub fn open_as_secondary_with_column_families<P: AsRef<std::path::Path>>(
+    path: P,
+    options: Options,
+    column_families: Vec<ColumnFamilyDescriptor>,
+  ) -> Result<Self, Error> {
+    let db = Self::open_read_only_internal(path.as_ref(), options, column_families, true)?;
+    // Opens the tables of the recovered versions.
+    db.try_catch_up_with_primary()?;
+    Ok(db)
+  }
+
+  fn open_read_only_internal(
+    path: &std::path::Path,
+    options: Options,
+    column_families: Vec<ColumnFamilyDescriptor>,
+    secondary: bool,
+  ) -> Result<Self, Error> {
+    let fs = Arc::clone(&options.file_system);
+    if !fs.file_exists(&path.join("CURRENT")) {
+      return Err(Error::InvalidArgument(format!(
+        "database does not exist at {}",
+        path.display()
+      )));
+    }
+    check_column_family_descriptors(&column_families)?;
+    let options = if secondary {
+      log::info!("opening database at {} as a secondary", path.display());
+      Options {
+        max_open_files: usize::MAX,
+        ..options
+      }
+    } else {
+      log::info!("opening database at {} read-only", path.display());
+      options
+    };
+
+    let cf_options: Vec<(String, Arc<Options>)> = column_families
+      .into_iter()
+      .map(|cf| {
+        (
+          cf.name,
+          Arc::new(column_family_options(&options, cf.options)),
+        )
+      })
+      .collect();
+    check_level_options(DEFAULT_COLUMN_FAMILY_NAME, &options)?;
+    for (name, cf_opts) in &cf_options {
+      check_level_options(name, cf_opts)?;
+    }
+    let default_options = Arc::new(options.clone());
+    let listed: Vec<(&str, Arc<Options>)> = cf_options
+      .iter()
+      .map(|(name, o)| (name.as_str(), Arc::clone(o)))
+      .collect();
+    let vs = VersionSet::recover(
+      path,
+      options.paranoid_checks,
+      Arc::clone(&default_options),
+      &listed,
+      &*fs,
+      true,
+    )?;
+
+    let mut cf_data = BTreeMap::from([(
+      DEFAULT_COLUMN_FAMILY_ID,
+      ColumnFamilyData::new(
+        DEFAULT_COLUMN_FAMILY_NAME,
+        Arc::clone(&default_options),
+        Some(new_table_cache(path, &default_options)),
+      ),
+    )]);
+    for (name, cf_opts) in cf_options {
+      let id = vs
+        .column_families()
+        .find(|&(_, n)| n == name)
+        .map(|(id, _)| id)
+        .ok_or_else(|| Error::InvalidArgument(format!("column family does not exist: {name}")))?;
+      let table_cache = new_table_cache(path, &cf_opts);
+      cf_data.insert(id, ColumnFamilyData::new(&name, cf_opts, Some(table_cache)));
+    }
+
+    let mut wal_offsets = BTreeMap::new();
+    let (last_sequence, _) = Self::replay_wal_tails(
+      path,
+      &*fs,
+      vs.log_number(),
+      vs.last_sequence(),
+      &cf_data,
+      options.wal_recovery_mode,
+      &mut wal_offsets,
+    )?;
+    let last_sequence = last_sequence.max(vs.max_file_sequence());
+    log::info!("read-only open complete: last_sequence={last_sequence}");
+
+    let mut state = DbState::new(last_sequence, None, Some(vs), cf_data);
+    if secondary {
+      state.wal_offsets = Some(wal_offsets);
+    }
+    Ok(Self {
+      inner: Arc::new(DbInner {
+        state: Mutex::new(state),
+        write_condvar: std::sync::Condvar::new(),
+        bg_condvar: std::sync::Condvar::new(),
+        flush_condvar: std::sync::Condvar::new(),
+        persistence: Some(Persistence {
+          dir: path.to_path_buf(),
+          fs,
+          _lock: None,
+        }),
+        options,
+        shutting_down: AtomicBool::new(false),
+        read_only: true,
+      }),
+      bg_threads: Vec::new(),
+    })
+  }
+
+  /// Replay the WALs in `path`, from `log_number` onwards, into the
+  /// memtables of `column_families`, skipping records up to `min_sequence`.
+  /// Each WAL is read from its offset in `wal_offsets` (or its start), and
+  /// the offset is advanced past the records read; offsets of older WALs are
+  /// forgotten.
+  ///
+  /// Every WAL from `log_number` on is read because a writer between
+  /// `begin_flush` and `finish_flush` already appends to the next one.
+  ///
+  /// Returns the last sequence number replayed (at least `min_sequence`) and
+  /// the number of a WAL that was deleted before it could be opened, if any.
+  fn replay_wal_tails(
+    path: &std::path::Path,
+    fs: &dyn crate::env::FileSystem,
+    log_number: u64,
+    min_sequence: u64,
+    column_families: &BTreeMap<u32, ColumnFamilyData>,
+    mode: WalRecoveryMode,
+    wal_offsets: &mut BTreeMap<u64, u64>,
+  ) -> Result<(u64, Option<u64>), Error> {
+    let mut logs: Vec<u64> = fs
+      .children(path)?
+      .iter()
+      .filter_map(|name| match parse_db_filename(name) {
+        Some((number, FileKind::Log)) if number >= log_number => Some(number),
+        _ => None,
+      })
+      .collect();
+    logs.sort_unstable();
+    wal_offsets.retain(|&number, _| number >= log_number);
+
+    let mut last_sequence = min_sequence;
+    for number in logs {
+      let log_path = path.join(format!("{number:06}.log"));
+      let file = match fs.open_sequential(&log_path) {
+        Ok(file) => file,
+        Err(_) if !fs.file_exists(&log_path) => return Ok((last_sequence, Some(number))),
+        Err(e) => return Err(e),
+      };
+      let offset = wal_offsets.entry(number).or_insert(0);
+      let replay = Self::recover_wal(file, number, *offset, column_families, last_sequence, mode)?;
+      if replay.dropped {
+        log::warn!("WAL {number:06}.log was not fully replayed");
+      }
+      *offset = replay.resume_offset;
+      last_sequence = replay.max_sequence;
+    }
+    Ok((last_sequence, None))
+  }
+
+  /// Bring a secondary instance up to date with its primary: apply the
+  /// `VersionEdit`s appended to the MANIFEST and read the WAL records written
+  /// since the open or the previous call.  Reads then see every write the
+  /// primary had written to its WAL before this call.
+  ///
+  /// When the MANIFEST shows that the primary flushed, the memtables are
+  /// rebuilt from the newer WALs.  The tables of the new versions are opened
+  /// at once; if the primary has already deleted one (it was compacted away
+  /// in an edit written after the MANIFEST was read) or a WAL, the MANIFEST
+  /// is read again.  Column families the primary creates are not opened and
+  /// the ones it drops become unavailable.
+  ///
+  /// Iterators keep reading the state they were created on, but one created
+  /// before the call may fail with an error status if it reaches a table
+  /// that the catch-up closed after the primary deleted it.
+  ///
+  /// Returns [`Error::NotSupported`] unless the database was opened with
+  /// [`Db::open_as_secondary`], and [`Error::Corruption`] if a file the
+  /// MANIFEST references is missing.
+  ///
+  /// See `db/db_impl/db_impl_secondary.cc: DBImplSecondary::TryCatchUpWithPrimary`
+  /// in RocksDB.
+  pub fn try_catch_up_with_primary(&self) -> Result<(), Error> {
+    let not_secondary = || Error::NotSupported("not a secondary instance".to_owned());
+    let p = self.inner.persistence.as_ref().ok_or_else(not_secondary)?;
+    let mut guard = self.inner.state.lock().unwrap();
+    let g = &mut *guard;
+    let wal_offsets = g.wal_offsets.as_mut().ok_or_else(not_secondary)?;
+    let vs = g
+      .version_set
+      .as_mut()
+      .expect("try_catch_up_with_primary: no VersionSet");
+    loop {
+      let table_caches: BTreeMap<u32, TableCache> = g
+        .column_families
+        .iter()
+        .filter_map(|(&id, cf)| Some((id, cf.table_cache.clone()?)))
+        .collect();
+      let old_log_number = vs.log_number();
+      let manifest_read = vs.catch_up(
+        &p.dir,
+        self.inner.options.paranoid_checks,
+        &table_caches,
+        &*p.fs,
+      )?;
+      g.column_families.retain(|&id, _| vs.current(id).is_some());
+
+      if vs.log_number() != old_log_number {
+        // The primary flushed: what the memtables hold from the older WALs
+        // is now in its tables.
+        log::debug!(
+          "secondary: WAL {old_log_number:06}.log -> {:06}.log",
+          vs.log_number()
+        );
+        for cf in g.column_families.values_mut() {
+          cf.mem = Arc::new(Memtable::new(Arc::clone(&cf.options.comparator)));
+        }
+        wal_offsets.clear();
+        g.last_sequence = vs.last_sequence();
+      }
+      let mut missing = None;
+      for (&id, tc) in &table_caches {
+        if let Some(version) = vs.current(id) {
+          missing = missing.or(open_version_files(&version, tc));
+        }
+      }
+      let (last_sequence, missing_wal) = Self::replay_wal_tails(
+        &p.dir,
+        &*p.fs,
+        vs.log_number(),
+        g.last_sequence.max(vs.last_sequence()),
+        &g.column_families,
+        self.inner.options.wal_recovery_mode,
+        wal_offsets,
+      )?;
+      let last_sequence = last_sequence.max(vs.max_file_sequence());
+      g.last_sequence = last_sequence;
+      let Some(missing) = missing.or(missing_wal.map(|n| format!("{n:06}.log"))) else {
+        log::debug!("secondary caught up to sequence {last_sequence}");
+        return Ok(());
+      };
+      if !manifest_read {
+        return Err(Error::Corruption(format!(
+          "{missing}, referenced by the MANIFEST, is missing"
+        )));
+      }
+      log::debug!("secondary: {missing} was deleted by the primary; re-reading the MANIFEST");
+    }
+  }
+
+  /// Stop deleting obsolete files until the returned guard is dropped, so
+  /// that every file of the current `Version` and every WAL still needed for
+  /// recovery stays on disk meanwhile.  Pauses nest; deletion resumes, and
+  /// catches up, when the last guard is dropped.
+  ///
+  /// See `DBImpl::DisableFileDeletions` in RocksDB.
+  pub(crate) fn pause_file_deletions(&self) -> FileDeletionPause<'_> {
+    self.inner.state.lock().unwrap().file_deletions_paused += 1;
+    FileDeletionPause { db: self }
+  }
+
+  /// [`Error::NotSupported`] if the database was opened with
+  /// [`Db::open_read_only`].
+  fn check_writable(&self) -> Result<(), Error> {
+    if self.inner.read_only {
+      return Err(Error::NotSupported(
+        "the database was opened read-only".to_owned(),
+      ));
+    }
+    Ok(())
+  }
+
+  /// Names of the column families of the database at `path`, as recorded in
+  /// its MANIFEST.  The default column family is always listed first.
+  ///
+  /// See `include/rocksdb/db.h: DB::ListColumnFamilies` in RocksDB.
+  pub fn list_column_families<P: AsRef<std::path::Path>>(
+    path: P,
+    options: &Options,
+  ) -> Result<Vec<String>, Error> {
+    crate::db::version_set::list_column_families(path.as_ref(), &*options.file_system)
+  }
+
+  /// Replay WAL records from `file` (log number `log_number`) into the
+  /// memtables of `column_families`, skipping any whose sequence number is
+  /// entirely covered by `min_sequence` (already in an SSTable).  Records for
+  /// dropped column families are skipped.  Only records starting at or after
+  /// `initial_offset` are read.
+  ///
+  /// Corruption is handled as `mode` says; see [`WalRecoveryMode`].
+  fn recover_wal(
+    file: Box<dyn crate::env::SequentialFile>,
+    log_number: u64,
+    initial_offset: u64,
+    column_families: &BTreeMap<u32, ColumnFamilyData>,
+    min_sequence: u64,
+    mode: WalRecoveryMode,
+  ) -> Result<WalReplay, Error> {
+    let drops = Arc::new(Mutex::new(Vec::new()));
+    let reporter = WalRecoveryReporter {
+      log_number,
+      drops: Arc::clone(&drops),
+    };
+    let mut reader = LogReader::new(file, Some(Box::new(reporter)), true, initial_offset);
+    reader.set_report_eof_truncation(mode == WalRecoveryMode::AbsoluteConsistency);
+    let mut max_sequence: u64 = min_sequence;
+    let mut dropped = false;
+    let mut resume_offset = initial_offset;
+
+    loop {
+      let record = reader.read_record();
+      // Everything the reader dropped lies before `record`.
+      if let Some(drop) = drops.lock().unwrap().drain(..).next() {
+        dropped = true;
+        match mode {
+          WalRecoveryMode::TolerateCorruptedTailRecords | WalRecoveryMode::AbsoluteConsistency => {
+            return Err(drop.into_error(log_number))
+          }
+          WalRecoveryMode::PointInTimeRecovery => break,
+          WalRecoveryMode::SkipAnyCorruptedRecords => {}
+        }
+      }
+      let Some(record) = record else { break };
+      let offset = reader.last_record_offset();
+      let len = record.len() as u64;
+      let batch = match WriteBatch::from_contents(record) {
+        Ok(batch) => batch,
+        Err(e) => {
+          let drop = WalDrop {
+            offset,
+            bytes: len,
+            reason: e.to_string(),
+          };
+          drop.log(log_number);
+          dropped = true;
+          match mode {
+            WalRecoveryMode::TolerateCorruptedTailRecords
+            | WalRecoveryMode::AbsoluteConsistency => return Err(drop.into_error(log_number)),
+            WalRecoveryMode::PointInTimeRecovery => break,
+            WalRecoveryMode::SkipAnyCorruptedRecords => {
+              resume_offset = offset + 1;
+              continue;
+            }
+          }
         }
+      };
+      resume_offset = offset + 1;
+      let start_seq = batch.sequence();
+      if let Some(ingested) = batch.ingested_sequence() {
+        // The ingested files are in the MANIFEST; only the sequence number
+        // they took needs restoring.
+        max_sequence = max_sequence.max(ingested);
+        continue;
+      }
+      // The last sequence number the batch used.
+      let end_seq = start_seq + batch.count() as u64 - 1;
+      // Skip batches fully covered by data already in SSTables.
+      if end_seq <= min_sequence {
+        continue;
       }
+      batch.iterate(&mut Inserter {
+        column_families,
+        seq: start_seq,
+      })?;
+      max_sequence = max_sequence.max(end_seq);
     }
 
-    Ok(max_sequence)
+    Ok(WalReplay {
+      max_sequence,
+      dropped,
+      resume_offset,
+    })
   }
 
   /// Look up `key` in the database, returning its value.
@@ -820,62 +1841,233 @@ impl Db {
   where
     K: AsRef<[u8]>,
   {
-    let key = key.as_ref();
+    self.get_internal(opts, DEFAULT_COLUMN_FAMILY_ID, key.as_ref())
+  }
 
-    // Mirror LevelDB's DBImpl::Get: take the lock only long enough to snapshot
-    // the current memtable refs and sequence number, then release before doing
-    // any I/O (memtable reads are lock-free; SSTable reads go to disk).
-    let (sequence, mem, imm, version) = {
-      let state = self.inner.state.lock().unwrap();
-      (
-        opts.snapshot.map(|s| s.seq).unwrap_or(state.last_sequence),
-        Arc::clone(&state.mem),
-        state.imm.as_ref().map(Arc::clone),
-        state.version_set.as_ref().map(|vs| vs.current()),
-      )
+  /// Look up `key` in column family `cf`; see [`Db::get`].
+  pub fn get_cf<K>(&self, cf: &ColumnFamilyHandle, key: K) -> Result<Vec<u8>, Error>
+  where
+    K: AsRef<[u8]>,
+  {
+    self.get_cf_with_options(&ReadOptions::default(), cf, key)
+  }
+
+  /// Look up `key` in column family `cf` with explicit read options; see
+  /// [`Db::get_with_options`].
+  ///
+  /// Returns [`Error::InvalidArgument`] if the column family has been dropped.
+  pub fn get_cf_with_options<K>(
+    &self,
+    opts: &ReadOptions,
+    cf: &ColumnFamilyHandle,
+    key: K,
+  ) -> Result<Vec<u8>, Error>
+  where
+    K: AsRef<[u8]>,
+  {
+    self.get_internal(opts, cf.id(), key.as_ref())
+  }
+
+  fn get_internal(&self, opts: &ReadOptions, cf: u32, key: &[u8]) -> Result<Vec<u8>, Error> {
+    let view = self.read_view(opts, cf)?;
+    let lookup_key = lookup_key(opts, &view, key)?;
+    let (base, operands) = self.lookup(opts, cf, &view, &lookup_key)?;
+    merge_lookup(&view, key, base, operands)
+  }
+
+  /// Look up several keys at once, returning one result per key in the order given.
+  ///
+  /// Equivalent to calling [`Db::get_with_options`] for each key, but cheaper for large
+  /// batches: every key is read at the same sequence number (taken under the lock once), the
+  /// keys are sorted and grouped per SSTable, and each data block a table needs is read only
+  /// once for the whole batch.  A key's result is [`Error::NotFound`] if it does not exist.
+  ///
+  /// See `include/rocksdb/db.h: DB::MultiGet`.
+  pub fn multi_get<K>(&self, opts: &ReadOptions, keys: &[K]) -> Vec<Result<Vec<u8>, Error>>
+  where
+    K: AsRef<[u8]>,
+  {
+    self.multi_get_internal(opts, DEFAULT_COLUMN_FAMILY_ID, keys)
+  }
+
+  /// Look up several keys of column family `cf` at once; see [`Db::multi_get`].
+  pub fn multi_get_cf<K>(
+    &self,
+    opts: &ReadOptions,
+    cf: &ColumnFamilyHandle,
+    keys: &[K],
+  ) -> Vec<Result<Vec<u8>, Error>>
+  where
+    K: AsRef<[u8]>,
+  {
+    self.multi_get_internal(opts, cf.id(), keys)
+  }
+
+  fn multi_get_internal<K>(
+    &self,
+    opts: &ReadOptions,
+    cf: u32,
+    keys: &[K],
+  ) -> Vec<Result<Vec<u8>, Error>>
+  where
+    K: AsRef<[u8]>,
+  {
+    use crate::table::get_context::KeyContext;
+
+    let view = match self.read_view(opts, cf) {
+      Ok(view) => view,
+      Err(e) => return keys.iter().map(|_| Err(e.clone())).collect(),
+    };
+    let lookup_keys = match keys
+      .iter()
+      .map(|k| lookup_key(opts, &view, k.as_ref()))
+      .collect::<Result<Vec<_>, Error>>()
+    {
+      Ok(lookup_keys) => lookup_keys,
+      Err(e) => return keys.iter().map(|_| Err(e.clone())).collect(),
     };
+    let cmp = &*view.options.comparator;
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by(|&a, &b| cmp.compare(&lookup_keys[a], &lookup_keys[b]));
+    let mut lookups: Vec<KeyContext> = order
+      .iter()
+      .map(|&i| KeyContext::new(&lookup_keys[i], view.sequence))
+      .collect();
+
+    for key in &mut lookups {
+      let user_key = crate::table::format::user_key(&key.lookup_key);
+      for mem in std::iter::once(&view.mem).chain(&view.imm) {
+        match mem.get(user_key, view.sequence, &mut key.ctx) {
+          MemtableResult::Hit(v) => key.result = Some(Ok(LookupResult::Value(v))),
+          MemtableResult::Deleted => key.result = Some(Ok(LookupResult::Deleted)),
+          MemtableResult::Miss => continue,
+        }
+        break;
+      }
+    }
+
+    if let (Some(version), Some(table_cache)) = (&view.version, &view.table_cache) {
+      let verify_checksums = opts.verify_checksums || self.inner.options.paranoid_checks;
+      version.multi_get(&mut lookups, verify_checksums, opts.fill_cache, table_cache);
+    }
+
+    let mut results: Vec<Option<Result<Vec<u8>, Error>>> = keys.iter().map(|_| None).collect();
+    for (i, key) in order.into_iter().zip(lookups) {
+      let base = match key.result {
+        Some(Ok(LookupResult::Value(v))) => Some(v),
+        Some(Err(e)) => {
+          results[i] = Some(Err(e));
+          continue;
+        }
+        _ => None,
+      };
+      results[i] = Some(merge_lookup(
+        &view,
+        keys[i].as_ref(),
+        base,
+        key.ctx.operands,
+      ));
+    }
+    results.into_iter().map(Option::unwrap).collect()
+  }
+
+  /// Return the merge operands recorded for `key`, without applying the merge operator.
+  ///
+  /// The result lists the key's base value first (if it has one that has not been folded away
+  /// by compaction), followed by the operands written on top of it, oldest first.  A key
+  /// written only with [`Db::put`] yields a single element.
+  ///
+  /// Returns [`Error::NotFound`] if `key` does not exist or was deleted with no operands after
+  /// the deletion.
+  ///
+  /// See `include/rocksdb/db.h: DB::GetMergeOperands`.
+  pub fn get_merge_operands<K>(&self, opts: &ReadOptions, key: K) -> Result<Vec<Vec<u8>>, Error>
+  where
+    K: AsRef<[u8]>,
+  {
+    let view = self.read_view(opts, DEFAULT_COLUMN_FAMILY_ID)?;
+    let lookup_key = lookup_key(opts, &view, key.as_ref())?;
+    let (base, mut operands) = self.lookup(opts, DEFAULT_COLUMN_FAMILY_ID, &view, &lookup_key)?;
+    if base.is_none() && operands.is_empty() {
+      return Err(Error::NotFound);
+    }
+    operands.extend(base);
+    operands.reverse();
+    Ok(operands)
+  }
+
+  /// Snapshot the memtable refs, current `Version` and read sequence of
+  /// column family `cf`.
+  ///
+  /// Mirrors LevelDB's DBImpl::Get: the lock is held only long enough to
+  /// clone the refs; memtable reads are lock-free and SSTable reads go to disk
+  /// without it.
+  fn read_view(&self, opts: &ReadOptions, cf: u32) -> Result<ReadView, Error> {
+    let state = self.inner.state.lock().unwrap();
+    let cfd = state.column_family(cf)?;
+    Ok(ReadView {
+      sequence: opts.snapshot.map(|s| s.seq).unwrap_or(state.last_sequence),
+      options: Arc::clone(&cfd.options),
+      mem: Arc::clone(&cfd.mem),
+      imm: cfd.imm.as_ref().map(Arc::clone),
+      version: state.current(cf),
+      table_cache: cfd.table_cache.clone(),
+    })
+  }
 
+  /// Point lookup in column family `cf` shared by [`Db::get_with_options`]
+  /// and [`Db::get_merge_operands`].
+  fn lookup(
+    &self,
+    opts: &ReadOptions,
+    cf: u32,
+    view: &ReadView,
+    key: &[u8],
+  ) -> Result<LookupOutcome, Error> {
+    let sequence = view.sequence;
     let verify_checksums = opts.verify_checksums || self.inner.options.paranoid_checks;
     let fill_cache = opts.fill_cache;
+    let mut ctx = GetContext::default();
 
-    match mem.get(key, sequence) {
-      MemtableResult::Hit(v) => return Ok(v),
-      MemtableResult::Deleted => return Err(Error::NotFound),
+    match view.mem.get(key, sequence, &mut ctx) {
+      MemtableResult::Hit(v) => return Ok((Some(v), ctx.operands)),
+      MemtableResult::Deleted => return Ok((None, ctx.operands)),
       MemtableResult::Miss => {}
     }
 
-    if let Some(imm) = imm {
-      match imm.get(key, sequence) {
-        MemtableResult::Hit(v) => return Ok(v),
-        MemtableResult::Deleted => return Err(Error::NotFound),
+    if let Some(imm) = &view.imm {
+      match imm.get(key, sequence, &mut ctx) {
+        MemtableResult::Hit(v) => return Ok((Some(v), ctx.operands)),
+        MemtableResult::Deleted => return Ok((None, ctx.operands)),
         MemtableResult::Miss => {}
       }
     }
 
-    if let Some(version) = version {
-      if let Some(persistence) = &self.inner.persistence {
-        let (result, stats) = version.get(
-          key,
-          sequence,
-          verify_checksums,
-          fill_cache,
-          &persistence.table_cache,
-        )?;
-        // Update seek stats under the lock (re-acquire briefly).
-        if stats.seek_file.is_some() {
-          let mut g = self.inner.state.lock().unwrap();
-          update_stats(&mut g, &stats);
-          maybe_schedule_compaction(&self.inner, &mut g);
-        }
-        match result {
-          LookupResult::Value(v) => return Ok(v),
-          LookupResult::Deleted => return Err(Error::NotFound),
-          LookupResult::NotInTable => {}
+    if let (Some(version), Some(table_cache)) = (&view.version, &view.table_cache) {
+      let (result, stats) = version.get(
+        key,
+        sequence,
+        verify_checksums,
+        fill_cache,
+        table_cache,
+        &mut ctx,
+      )?;
+      // Update seek stats under the lock (re-acquire briefly).
+      if stats.seek_file.is_some() {
+        let mut g = self.inner.state.lock().unwrap();
+        if let Some(cfd) = g.column_families.get_mut(&cf) {
+          update_stats(cfd, &stats);
         }
+        maybe_schedule_compaction(&self.inner, &mut g);
+      }
+      match result {
+        LookupResult::Value(v) => return Ok((Some(v), ctx.operands)),
+        LookupResult::Deleted => return Ok((None, ctx.operands)),
+        LookupResult::NotInTable => {}
       }
     }
 
-    Err(Error::NotFound)
+    Ok((None, ctx.operands))
   }
 
   /// Return an immutable snapshot of the current database state.
@@ -895,8 +2087,39 @@ impl Db {
     Snapshot { db: self, seq }
   }
 
-  /// Return a property value for a named `property`, or `None` if the property
-  /// is unknown.
+  /// Begin an [`OptimisticTransaction`] that reads from a snapshot taken now
+  /// and commits with `opts`.
+  ///
+  /// See `include/rocksdb/utilities/optimistic_transaction_db.h:
+  /// OptimisticTransactionDB::BeginTransaction`.
+  pub fn begin_optimistic_transaction(&self, opts: &WriteOptions) -> OptimisticTransaction<'_> {
+    OptimisticTransaction::new(self, opts)
+  }
+
+  /// Iterate over every committed [`WriteBatch`] and ingestion from sequence
+  /// number `seq` onwards, in commit order; see [`TransactionLogIterator`].
+  ///
+  /// The batches are read from the live WAL and from the WALs kept in the
+  /// `archive/` directory (see [`Options::wal_ttl`] and
+  /// [`Options::wal_size_limit`]); without archiving, only the batches not
+  /// yet flushed are available.
+  ///
+  /// Returns [`Error::InvalidArgument`] if the WAL holding `seq` has already
+  /// been purged, and [`Error::NotSupported`] for in-memory databases.
+  ///
+  /// See `include/rocksdb/db.h: DB::GetUpdatesSince`.
+  pub fn get_updates_since(&self, seq: u64) -> Result<TransactionLogIterator, Error> {
+    let Some(p) = &self.inner.persistence else {
+      return Err(Error::NotSupported(
+        "get_updates_since requires a persistent database".to_string(),
+      ));
+    };
+    let last_sequence = self.inner.state.lock().unwrap().last_sequence;
+    TransactionLogIterator::new(&p.dir, Arc::clone(&p.fs), seq, last_sequence)
+  }
+
+  /// Return a property value for a named `property` of the default column
+  /// family, or `None` if the property is unknown.
   ///
   /// Supported property names:
   ///
@@ -914,18 +2137,16 @@ impl Db {
     // Snapshot what we need under the lock, then release before formatting.
     let (version, mem_usage, imm_usage) = {
       let state = self.inner.state.lock().unwrap();
-      let version = state.version_set.as_ref().map(|vs| vs.current());
-      let mem_usage = state.mem.approximate_memory_usage();
-      let imm_usage = state
-        .imm
-        .as_ref()
-        .map_or(0, |i| i.approximate_memory_usage());
+      let version = state.current(DEFAULT_COLUMN_FAMILY_ID);
+      let cfd = state.default_column_family();
+      let mem_usage = cfd.mem.approximate_memory_usage();
+      let imm_usage = cfd.imm.as_ref().map_or(0, |i| i.approximate_memory_usage());
       (version, mem_usage, imm_usage)
     };
 
     if let Some(rest) = prop.strip_prefix("num-files-at-level") {
       let level: usize = rest.parse().ok()?;
-      if level >= crate::db::version::NUM_LEVELS {
+      if level >= self.inner.options.num_levels {
         return None;
       }
       let count = version.as_ref().map_or(0, |v| v.num_files(level));
@@ -939,7 +2160,7 @@ impl Db {
            Level  Files Size(MB) Time(sec) Read(MB) Write(MB)\n\
            --------------------------------------------------\n",
         );
-        for level in 0..crate::db::version::NUM_LEVELS {
+        for level in 0..self.inner.options.num_levels {
           let files = version.as_ref().map_or(0, |v| v.num_files(level));
           let bytes = version.as_ref().map_or(0, |v| v.level_bytes(level));
           if files > 0 {
@@ -961,7 +2182,8 @@ impl Db {
     }
   }
 
-  /// Return approximate on-disk byte sizes for each `(start, limit)` key range.
+  /// Return approximate on-disk byte sizes for each `(start, limit)` key range
+  /// of the default column family.
   ///
   /// Each element of the returned `Vec` corresponds to the same-indexed range
   /// in `ranges`.  The sizes are estimates only and do not account for
@@ -978,15 +2200,18 @@ impl Db {
     use crate::table::format::make_internal_key;
 
     // Snapshot the current version under the lock, then release.
-    let version = {
+    let (version, table_cache) = {
       let state = self.inner.state.lock().unwrap();
-      state.version_set.as_ref().map(|vs| vs.current())
+      (
+        state.current(DEFAULT_COLUMN_FAMILY_ID),
+        state.default_column_family().table_cache.clone(),
+      )
     };
 
     ranges
       .iter()
       .map(|&(start, limit)| {
-        let (Some(ref v), Some(ref tc)) = (&version, &self.inner.persistence) else {
+        let (Some(v), Some(tc)) = (&version, &table_cache) else {
           return 0;
         };
         // Convert user keys to lookup internal keys (seq=u64::MAX>>8, vtype=1)
@@ -994,8 +2219,8 @@ impl Db {
         // sentinel used in Table::get.
         let istart = make_internal_key(start, u64::MAX >> 8, 1);
         let ilimit = make_internal_key(limit, u64::MAX >> 8, 1);
-        let start_off = v.approximate_offset_of(&istart, &tc.table_cache);
-        let limit_off = v.approximate_offset_of(&ilimit, &tc.table_cache);
+        let start_off = v.approximate_offset_of(&istart, tc);
+        let limit_off = v.approximate_offset_of(&ilimit, tc);
         limit_off.saturating_sub(start_off)
       })
       .collect()
@@ -1011,6 +2236,17 @@ impl Db {
     self.write(&WriteOptions::default(), batch)
   }
 
+  /// Set `key` to `value` in column family `cf`.
+  pub fn put_cf<K, V>(&self, cf: &ColumnFamilyHandle, key: K, value: V) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let mut batch = WriteBatch::new();
+    batch.put_cf(cf, key.as_ref(), value.as_ref());
+    self.write(&WriteOptions::default(), batch)
+  }
+
   pub fn delete<K>(&self, key: K) -> Result<(), Error>
   where
     K: AsRef<[u8]>,
@@ -1020,72 +2256,451 @@ impl Db {
     self.write(&WriteOptions::default(), batch)
   }
 
-  /// Create a forward iterator over a consistent snapshot of the database.
+  /// Delete `key` from column family `cf`.
+  pub fn delete_cf<K>(&self, cf: &ColumnFamilyHandle, key: K) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+  {
+    let mut batch = WriteBatch::new();
+    batch.delete_cf(cf, key.as_ref());
+    self.write(&WriteOptions::default(), batch)
+  }
+
+  /// Set `key` to `value` as of the user-defined timestamp `ts`.
   ///
-  /// If `opts.snapshot` is set, the iterator is pinned to that sequence number;
-  /// otherwise it reads as of the current `last_sequence`.
+  /// The column family's comparator must have timestamps
+  /// ([`Comparator::timestamp_size`] > 0) and `ts` must be exactly that many
+  /// bytes; otherwise this returns [`Error::InvalidArgument`].  Timestamps
+  /// written to one key must increase along with the sequence numbers: a
+  /// write with an older timestamp than an existing version of the key is
+  /// not supported.
   ///
-  /// The memtable(s) and all SSTable file handles are pinned for the lifetime
-  /// of the returned `DbIter`; reads and writes proceed concurrently.
-  pub fn new_iterator(&self, opts: &ReadOptions<'_>) -> Result<DbIter, Error> {
-    use crate::db::db_iter::DbIterator;
-    use crate::db::merge_iter::MergingIterator;
-
-    // Snapshot Arc refs under the lock, then release before constructing the
-    // iterator (matching LevelDB's NewInternalIterator pattern).
-    let (sequence, mem, imm, version) = {
-      let state = self.inner.state.lock().unwrap();
-      (
-        opts.snapshot.map(|s| s.seq).unwrap_or(state.last_sequence),
-        Arc::clone(&state.mem),
-        state.imm.as_ref().map(Arc::clone),
-        state.version_set.as_ref().map(|vs| vs.current()),
-      )
-    };
-
-    let verify_checksums = opts.verify_checksums || self.inner.options.paranoid_checks;
-    let fill_cache = opts.fill_cache;
-
-    let mut children: Vec<Box<dyn crate::iter::InternalIterator>> = Vec::new();
+  /// [`Db::put`] and [`WriteBatch`] take the timestamp as a suffix of the key
+  /// instead.
+  ///
+  /// See `include/rocksdb/db.h: DB::Put` (the overload taking `ts`).
+  pub fn put_with_ts<K, T, V>(&self, key: K, ts: T, value: V) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    T: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    self.put_cf_with_ts(&self.default_column_family(), key, ts, value)
+  }
+
+  /// Set `key` to `value` in column family `cf` as of timestamp `ts`; see
+  /// [`Db::put_with_ts`].
+  pub fn put_cf_with_ts<K, T, V>(
+    &self,
+    cf: &ColumnFamilyHandle,
+    key: K,
+    ts: T,
+    value: V,
+  ) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    T: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let key = self.key_with_timestamp(cf.id(), key.as_ref(), ts.as_ref())?;
+    self.put_cf(cf, key, value)
+  }
 
-    // Active memtable (newest writes, scanned first).
-    children.push(Box::new(ArcMemTableIter::new(mem)));
+  /// Delete `key` as of the user-defined timestamp `ts`; reads at `ts` or
+  /// later see it as absent, while reads at older timestamps still see the
+  /// versions before it.  See [`Db::put_with_ts`] for the requirements on
+  /// `ts`.
+  ///
+  /// See `include/rocksdb/db.h: DB::Delete` (the overload taking `ts`).
+  pub fn delete_with_ts<K, T>(&self, key: K, ts: T) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    T: AsRef<[u8]>,
+  {
+    self.delete_cf_with_ts(&self.default_column_family(), key, ts)
+  }
 
-    // Sealed memtable being flushed to disk (if any).
-    if let Some(imm) = imm {
-      children.push(Box::new(ArcMemTableIter::new(imm)));
-    }
+  /// Delete `key` from column family `cf` as of timestamp `ts`; see
+  /// [`Db::delete_with_ts`].
+  pub fn delete_cf_with_ts<K, T>(&self, cf: &ColumnFamilyHandle, key: K, ts: T) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    T: AsRef<[u8]>,
+  {
+    let key = self.key_with_timestamp(cf.id(), key.as_ref(), ts.as_ref())?;
+    self.delete_cf(cf, key)
+  }
 
-    // SSTable files from the current Version, level by level (L0 first).
-    if let (Some(version), Some(persistence)) = (version, &self.inner.persistence) {
-      for level in 0..crate::db::version::NUM_LEVELS {
-        for meta in version.files_at(level) {
-          let table = persistence
-            .table_cache
-            .get_or_open(meta.number, meta.file_size)?;
-          children.push(Box::new(table.new_iterator(verify_checksums, fill_cache)?));
-        }
-      }
+  /// `key` followed by `ts`, after checking `ts` against the timestamp size
+  /// of column family `cf`.
+  fn key_with_timestamp(&self, cf: u32, key: &[u8], ts: &[u8]) -> Result<Vec<u8>, Error> {
+    let ts_size = self.column_family_options(cf)?.comparator.timestamp_size();
+    if ts_size == 0 || ts.len() != ts_size {
+      return Err(Error::InvalidArgument(format!(
+        "timestamp must be {ts_size} bytes for this column family"
+      )));
     }
-
-    let cmp = Arc::clone(&self.inner.options.comparator);
-    let inner = DbIterator::new(
-      Box::new(MergingIterator::new(children, Arc::clone(&cmp))),
-      sequence,
-      cmp,
-    );
-    Ok(DbIter { inner })
+    Ok([key, ts].concat())
   }
 
-  /// All recognised database files (`CURRENT`, `MANIFEST-*`, `*.log`, `*.ldb`, `LOCK`) are
-  /// removed.  Unrecognised files are left in place; the directory is removed only if it is
-  /// empty afterwards.
+  /// Raise the timestamp below which compaction may garbage-collect the
+  /// history of each key: of the versions with older timestamps, only the
+  /// newest survives (and a deletion among them may be dropped entirely once
+  /// nothing lies beneath it).  Reads with a [`ReadOptions::timestamp`] older
+  /// than `ts` are rejected from then on.
   ///
-  /// Returns `Ok(())` if `path` does not exist.
+  /// The cutoff is recorded in the MANIFEST and survives a reopen.  It only
+  /// takes effect as files are compacted; see [`Db::compact_range`].  It has
+  /// no effect on in-memory databases, which never compact.
   ///
-  /// See `db/db_impl.cc: DestroyDB`.
-  pub fn destroy<P: AsRef<std::path::Path>>(path: P) -> Result<(), Error> {
-    let path = path.as_ref();
+  /// Returns [`Error::InvalidArgument`] if `ts` is not a timestamp of the
+  /// default column family's comparator or is older than the current cutoff.
+  ///
+  /// See `include/rocksdb/db.h: DB::IncreaseFullHistoryTsLow`.
+  pub fn increase_full_history_ts_low<T: AsRef<[u8]>>(&self, ts: T) -> Result<(), Error> {
+    self.increase_full_history_ts_low_cf(&self.default_column_family(), ts)
+  }
+
+  /// Raise the history cutoff of column family `cf`; see
+  /// [`Db::increase_full_history_ts_low`].
+  pub fn increase_full_history_ts_low_cf<T: AsRef<[u8]>>(
+    &self,
+    cf: &ColumnFamilyHandle,
+    ts: T,
+  ) -> Result<(), Error> {
+    let ts = ts.as_ref();
+    self.check_writable()?;
+    let mut g = self.inner.state.lock().unwrap();
+    let cfd = g.column_family(cf.id())?;
+    let cmp = Arc::clone(&cfd.options.comparator);
+    let ts_size = cmp.timestamp_size();
+    if ts_size == 0 || ts.len() != ts_size {
+      return Err(Error::InvalidArgument(format!(
+        "timestamp must be {ts_size} bytes for this column family"
+      )));
+    }
+    let Some(table_cache) = cfd.table_cache.clone() else {
+      return Ok(());
+    };
+    let current = g.current(cf.id());
+    let current_low = current
+      .as_ref()
+      .and_then(|v| v.full_history_ts_low.as_deref());
+    if current_low.is_some_and(|low| cmp.compare_timestamp(ts, low).is_lt()) {
+      return Err(Error::InvalidArgument(
+        "full_history_ts_low cannot be decreased".to_string(),
+      ));
+    }
+    let vs = g
+      .version_set
+      .as_mut()
+      .expect("increase_full_history_ts_low: no VersionSet");
+    let mut edit = VersionEdit::new();
+    edit.column_family = cf.id();
+    edit.full_history_ts_low = Some(ts.to_vec());
+    vs.log_and_apply(&mut edit, &table_cache)
+  }
+
+  /// Delete every key in `[begin, end)` with a single range tombstone.
+  ///
+  /// The tombstone occupies one sequence number regardless of how many keys it covers; reads,
+  /// iterators and compaction treat keys in the range written before it as deleted.  Keys
+  /// written afterwards are unaffected.
+  ///
+  /// Returns [`Error::InvalidArgument`] if `begin` sorts after `end` under the configured
+  /// comparator.  An empty range (`begin == end`) is a no-op.
+  ///
+  /// See `include/rocksdb/db.h: DB::DeleteRange`.
+  pub fn delete_range<K, E>(&self, begin: K, end: E) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    E: AsRef<[u8]>,
+  {
+    self.delete_range_internal(
+      &ColumnFamilyHandle::new(DEFAULT_COLUMN_FAMILY_ID, DEFAULT_COLUMN_FAMILY_NAME),
+      begin.as_ref(),
+      end.as_ref(),
+    )
+  }
+
+  /// Delete every key in `[begin, end)` of column family `cf`, ordered by
+  /// that family's comparator; see [`Db::delete_range`].
+  pub fn delete_range_cf<K, E>(
+    &self,
+    cf: &ColumnFamilyHandle,
+    begin: K,
+    end: E,
+  ) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    E: AsRef<[u8]>,
+  {
+    self.delete_range_internal(cf, begin.as_ref(), end.as_ref())
+  }
+
+  fn delete_range_internal(
+    &self,
+    cf: &ColumnFamilyHandle,
+    begin: &[u8],
+    end: &[u8],
+  ) -> Result<(), Error> {
+    let options = self.column_family_options(cf.id())?;
+    if options.comparator.timestamp_size() > 0 {
+      return Err(Error::NotSupported(
+        "delete_range is not supported with user-defined timestamps".to_string(),
+      ));
+    }
+    match options.comparator.compare(begin, end) {
+      std::cmp::Ordering::Greater => Err(Error::InvalidArgument(
+        "delete_range: begin key sorts after end key".to_string(),
+      )),
+      std::cmp::Ordering::Equal => Ok(()),
+      std::cmp::Ordering::Less => {
+        let mut batch = WriteBatch::new();
+        batch.delete_range_cf(cf, begin, end);
+        self.write(&WriteOptions::default(), batch)
+      }
+    }
+  }
+
+  /// Record a merge operand for `key`; see [`MergeOperator`].
+  ///
+  /// Returns [`Error::NotSupported`] if [`Options::merge_operator`] is not set.
+  ///
+  /// See `include/rocksdb/db.h: DB::Merge`.
+  pub fn merge<K, V>(&self, key: K, value: V) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    self.merge_cf(
+      &ColumnFamilyHandle::new(DEFAULT_COLUMN_FAMILY_ID, DEFAULT_COLUMN_FAMILY_NAME),
+      key,
+      value,
+    )
+  }
+
+  /// Record a merge operand for `key` in column family `cf`, which must have
+  /// a merge operator configured; see [`Db::merge`].
+  pub fn merge_cf<K, V>(&self, cf: &ColumnFamilyHandle, key: K, value: V) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let options = self.column_family_options(cf.id())?;
+    if options.merge_operator.is_none() {
+      return Err(Error::NotSupported(
+        "merge requires Options::merge_operator".to_string(),
+      ));
+    }
+    if options.comparator.timestamp_size() > 0 {
+      return Err(Error::NotSupported(
+        "merge is not supported with user-defined timestamps".to_string(),
+      ));
+    }
+    let mut batch = WriteBatch::new();
+    batch.merge_cf(cf, key.as_ref(), value.as_ref());
+    self.write(&WriteOptions::default(), batch)
+  }
+
+  /// Create a forward iterator over a consistent snapshot of the database.
+  ///
+  /// If `opts.snapshot` is set, the iterator is pinned to that sequence number;
+  /// otherwise it reads as of the current `last_sequence`.
+  ///
+  /// The memtable(s) and all SSTable file handles are pinned for the lifetime
+  /// of the returned `DbIter`; reads and writes proceed concurrently.
+  pub fn new_iterator(&self, opts: &ReadOptions<'_>) -> Result<DbIter, Error> {
+    self.new_iterator_internal(opts, DEFAULT_COLUMN_FAMILY_ID)
+  }
+
+  /// Create an iterator over column family `cf`, ordered by its comparator;
+  /// see [`Db::new_iterator`].
+  pub fn new_iterator_cf(
+    &self,
+    opts: &ReadOptions<'_>,
+    cf: &ColumnFamilyHandle,
+  ) -> Result<DbIter, Error> {
+    self.new_iterator_internal(opts, cf.id())
+  }
+
+  fn new_iterator_internal(&self, opts: &ReadOptions<'_>, cf: u32) -> Result<DbIter, Error> {
+    use crate::db::db_iter::DbIterator;
+    use crate::db::merge_iter::MergingIterator;
+    use crate::db::timestamp_iter::TimestampFilterIterator;
+
+    // Snapshot Arc refs under the lock, then release before constructing the
+    // iterator (matching LevelDB's NewInternalIterator pattern).
+    let view = self.read_view(opts, cf)?;
+    let read_timestamp = check_read_timestamp(opts, &view)?.map(<[u8]>::to_vec);
+    let ReadView {
+      sequence,
+      options,
+      mem,
+      imm,
+      version,
+      table_cache,
+    } = view;
+
+    let verify_checksums = opts.verify_checksums || self.inner.options.paranoid_checks;
+    let fill_cache = opts.fill_cache;
+
+    let mut children: Vec<Box<dyn crate::iter::InternalIterator>> = Vec::new();
+    // Range tombstones from every source, gathered alongside the children.
+    // Those newer than the read sequence can never apply and are skipped.
+    let mut tombstones = Vec::new();
+
+    // Active memtable (newest writes, scanned first).
+    tombstones.extend(mem.range_tombstones());
+    children.push(Box::new(ArcMemTableIter::new(mem)));
+
+    // Sealed memtable being flushed to disk (if any).
+    if let Some(imm) = imm {
+      tombstones.extend(imm.range_tombstones());
+      children.push(Box::new(ArcMemTableIter::new(imm)));
+    }
+
+    // SSTable files from the current Version, level by level (L0 first).
+    // Blob indexes are read through the same version and table cache.
+    let mut blob_fetcher = None;
+    if let (Some(version), Some(table_cache)) = (version, table_cache) {
+      for level in 0..version.num_levels() {
+        for meta in version.files_at(level) {
+          let table = table_cache.get_or_open(meta.number, meta.file_size)?;
+          tombstones.extend(table.range_tombstones().iter());
+          children.push(Box::new(table.new_iterator(verify_checksums, fill_cache)?));
+        }
+      }
+      blob_fetcher = Some(crate::db::blob::BlobFetcher {
+        version,
+        table_cache,
+        verify_checksums,
+      });
+    }
+    tombstones.retain(|t| t.seq <= sequence);
+
+    let cmp = Arc::clone(&options.comparator);
+    let mut merged: Box<dyn crate::iter::InternalIterator> =
+      Box::new(MergingIterator::new(children, Arc::clone(&cmp)));
+    if let Some(ts) = &read_timestamp {
+      merged = Box::new(TimestampFilterIterator::new(
+        merged,
+        ts.clone(),
+        Arc::clone(&cmp),
+      ));
+    }
+    let inner = DbIterator::new(
+      merged,
+      sequence,
+      Arc::clone(&cmp),
+      options.merge_operator.clone(),
+      crate::db::range_del::FragmentedRangeTombstones::new(tombstones, cmp),
+      blob_fetcher,
+    );
+    Ok(DbIter {
+      inner,
+      read_timestamp: read_timestamp.unwrap_or_default(),
+    })
+  }
+
+  // ── Column families ─────────────────────────────────────────────────────────
+
+  /// Create a column family named `name` configured by `options`; see
+  /// [`ColumnFamilyDescriptor`] for which fields apply.
+  ///
+  /// Returns [`Error::InvalidArgument`] if a column family with that name
+  /// already exists.
+  ///
+  /// See `include/rocksdb/db.h: DB::CreateColumnFamily` in RocksDB.
+  pub fn create_column_family(
+    &self,
+    name: &str,
+    options: Options,
+  ) -> Result<ColumnFamilyHandle, Error> {
+    self.check_writable()?;
+    let options = Arc::new(column_family_options(&self.inner.options, options));
+    check_level_options(name, &options)?;
+    let mut g = self.inner.state.lock().unwrap();
+    if g.column_families.values().any(|cf| cf.name == name) {
+      return Err(Error::InvalidArgument(format!(
+        "column family already exists: {name}"
+      )));
+    }
+    let id = g.max_column_family + 1;
+    if let Some(vs) = g.version_set.as_mut() {
+      vs.create_column_family(id, name, Arc::clone(&options))?;
+    }
+    g.max_column_family = id;
+    let table_cache = self
+      .inner
+      .persistence
+      .as_ref()
+      .map(|p| new_table_cache(&p.dir, &options));
+    g.column_families
+      .insert(id, ColumnFamilyData::new(name, options, table_cache));
+    log::info!("created column family '{name}' (id {id})");
+    Ok(ColumnFamilyHandle::new(id, name))
+  }
+
+  /// Drop column family `cf`.  Its data becomes unreachable at once and its
+  /// files are deleted; handles to it stop working.
+  ///
+  /// Returns [`Error::InvalidArgument`] for the default column family or one
+  /// that was already dropped.
+  ///
+  /// See `include/rocksdb/db.h: DB::DropColumnFamily` in RocksDB.
+  pub fn drop_column_family(&self, cf: &ColumnFamilyHandle) -> Result<(), Error> {
+    self.check_writable()?;
+    if cf.id() == DEFAULT_COLUMN_FAMILY_ID {
+      return Err(Error::InvalidArgument(
+        "the default column family cannot be dropped".to_owned(),
+      ));
+    }
+    let mut g = self.inner.state.lock().unwrap();
+    g.column_family(cf.id())?;
+    if let Some(vs) = g.version_set.as_mut() {
+      vs.drop_column_family(cf.id())?;
+    }
+    g.column_families.remove(&cf.id());
+    drop(g);
+    log::info!("dropped column family '{}' (id {})", cf.name(), cf.id());
+    if let Some(p) = &self.inner.persistence {
+      delete_obsolete_files(&p.dir, &self.inner.state, &self.inner.options);
+    }
+    Ok(())
+  }
+
+  /// Handle to the open column family named `name`, or `None` if there is
+  /// none.
+  pub fn column_family(&self, name: &str) -> Option<ColumnFamilyHandle> {
+    let g = self.inner.state.lock().unwrap();
+    g.column_families
+      .iter()
+      .find(|(_, cf)| cf.name == name)
+      .map(|(&id, cf)| cf.handle(id))
+  }
+
+  /// Handle to the default column family.
+  pub fn default_column_family(&self) -> ColumnFamilyHandle {
+    ColumnFamilyHandle::new(DEFAULT_COLUMN_FAMILY_ID, DEFAULT_COLUMN_FAMILY_NAME)
+  }
+
+  /// Options of column family `cf`, or [`Error::InvalidArgument`] if it has
+  /// been dropped.
+  fn column_family_options(&self, cf: u32) -> Result<Arc<Options>, Error> {
+    let g = self.inner.state.lock().unwrap();
+    Ok(Arc::clone(&g.column_family(cf)?.options))
+  }
+
+  /// All recognised database files (`CURRENT`, `MANIFEST-*`, `*.log`, `*.ldb`, `LOCK`) are
+  /// removed, along with the WAL archive.  Unrecognised files are left in place; the directory is removed only if it is
+  /// empty afterwards.
+  ///
+  /// Returns `Ok(())` if `path` does not exist.
+  ///
+  /// See `db/db_impl.cc: DestroyDB`.
+  pub fn destroy<P: AsRef<std::path::Path>>(path: P) -> Result<(), Error> {
+    let path = path.as_ref();
     let fs: Arc<dyn crate::env::FileSystem> = Arc::new(crate::env::PosixFileSystem);
 
     if !fs.file_exists(path) {
@@ -1106,6 +2721,8 @@ impl Db {
       }
     }
 
+    crate::db::wal_manager::destroy_archive(path, &*fs);
+
     // Release the lock by dropping, then remove the LOCK file itself.
     drop(lock);
     let _ = fs.remove_file(&lock_path);
@@ -1133,26 +2750,43 @@ impl Db {
   /// has the database open.  Returns [`Error::IoError`] immediately (without blocking)
   /// if the lock cannot be acquired.
   pub fn compact_range(&self, begin: Option<&[u8]>, end: Option<&[u8]>) -> Result<(), Error> {
-    use crate::db::version::NUM_LEVELS;
+    self.compact_range_internal(DEFAULT_COLUMN_FAMILY_ID, begin, end)
+  }
 
-    let persistence = match &self.inner.persistence {
-      Some(p) => p,
-      None => return Ok(()),
-    };
+  /// Compact the SSTable files of column family `cf` that overlap
+  /// `[begin, end]`; see [`Db::compact_range`].
+  pub fn compact_range_cf(
+    &self,
+    cf: &ColumnFamilyHandle,
+    begin: Option<&[u8]>,
+    end: Option<&[u8]>,
+  ) -> Result<(), Error> {
+    self.compact_range_internal(cf.id(), begin, end)
+  }
 
-    let path = persistence.dir.as_path();
+  fn compact_range_internal(
+    &self,
+    cf: u32,
+    begin: Option<&[u8]>,
+    end: Option<&[u8]>,
+  ) -> Result<(), Error> {
+    self.check_writable()?;
+
+    if self.inner.persistence.is_none() {
+      return Ok(());
+    }
 
     // Find the deepest level that currently has files overlapping [begin, end].
-    let max_level = {
+    let (max_level, num_levels, options, table_cache) = {
       let g = self.inner.state.lock().unwrap();
-      let vs = match &g.version_set {
-        Some(vs) => vs,
+      let cfd = g.column_family(cf)?;
+      let version = match g.current(cf) {
+        Some(v) => v,
         None => return Ok(()),
       };
-      let version = vs.current();
-      let cmp = &*self.inner.options.comparator;
+      let cmp = &*cfd.options.comparator;
       let mut max = 0usize;
-      for level in 0..NUM_LEVELS {
+      for level in 0..version.num_levels() {
         let has = version.files_at(level).iter().any(|m| {
           file_overlaps_range(
             ikey_user_key(&m.smallest),
@@ -1166,33 +2800,33 @@ impl Db {
           max = level;
         }
       }
-      max
+      (
+        max,
+        version.num_levels(),
+        Arc::clone(&cfd.options),
+        cfd
+          .table_cache
+          .clone()
+          .expect("compact_range: no TableCache"),
+      )
     };
 
     // Compact every level from 0 up to and including max_level, pushing data
-    // toward deeper levels.  Cap at NUM_LEVELS-2 since level N compacts into
-    // level N+1, and there is no level above NUM_LEVELS-1.
-    let stop = max_level.min(NUM_LEVELS - 2);
-    for level in 0..=stop {
-      compact_level_range(
-        path,
-        &self.inner.state,
-        &self.inner.options,
-        &persistence.table_cache,
-        level,
-        begin,
-        end,
-      )?;
+    // toward deeper levels.  Stop above the last level, since level N
+    // compacts into level N+1.
+    for level in 0..=max_level.min(num_levels.saturating_sub(2)) {
+      compact_level_range(&self.inner, cf, &options, &table_cache, level, begin, end)?;
     }
 
     Ok(())
   }
 
-  /// Flush all in-memory writes to an SSTable and clear the corresponding WAL segment.
+  /// Flush all in-memory writes, of every column family, to SSTables and clear the
+  /// corresponding WAL segment.
   ///
-  /// If the active memtable is empty, this is a no-op (returns `Ok(())` immediately).
-  /// If a flush is already in progress for a previous memtable, waits for it to finish
-  /// before rotating the current one.
+  /// If the active memtables are empty, this is a no-op (returns `Ok(())` immediately).
+  /// If a flush is already in progress for previous memtables, waits for it to finish
+  /// before rotating the current ones.
   ///
   /// After this call returns (with `opts.wait = true`), all data written before the call
   /// is guaranteed to reside in an SSTable on disk and the WAL segment that covered it has
@@ -1202,6 +2836,7 @@ impl Db {
   ///
   /// See `include/rocksdb/db.h: DB::Flush`.
   pub fn flush(&self, opts: &FlushOptions) -> Result<(), Error> {
+    self.check_writable()?;
     let mut g = self.inner.state.lock().unwrap();
 
     if let Some(ref e) = g.background_error {
@@ -1212,11 +2847,11 @@ impl Db {
       return Ok(());
     }
 
-    // If the memtable is empty, there is nothing to flush.  We may still need to wait for an
+    // If the memtables are empty, there is nothing to flush.  We may still need to wait for an
     // in-progress flush that was triggered by a prior writer.
-    if g.mem.approximate_memory_usage() == 0 {
+    if g.memtables_empty() {
       if opts.wait {
-        while (g.imm.is_some() || g.pending_flush.is_some()) && g.background_error.is_none() {
+        while g.flush_in_progress() && g.background_error.is_none() {
           g = self.inner.write_condvar.wait(g).unwrap();
         }
         return g
@@ -1228,7 +2863,7 @@ impl Db {
     }
 
     // Wait for any in-progress flush to drain before rotating mem → imm.
-    while (g.imm.is_some() || g.pending_flush.is_some()) && g.background_error.is_none() {
+    while g.flush_in_progress() && g.background_error.is_none() {
       g = self.inner.write_condvar.wait(g).unwrap();
     }
     if let Some(ref e) = g.background_error {
@@ -1238,13 +2873,12 @@ impl Db {
     // Rotate mem → imm and schedule the background flush.
     let p = self.inner.persistence.as_ref().unwrap();
     let path = p.dir.as_path();
-    let prep = begin_flush(path, &mut g, &self.inner.options.comparator, &*p.fs)?;
-    g.imm = Some(Arc::clone(&prep.old_mem));
+    let prep = begin_flush(path, &mut g, &*p.fs)?;
     g.pending_flush = Some(prep);
     maybe_schedule_compaction(&self.inner, &mut g);
 
     if opts.wait {
-      while (g.imm.is_some() || g.pending_flush.is_some()) && g.background_error.is_none() {
+      while g.flush_in_progress() && g.background_error.is_none() {
         g = self.inner.write_condvar.wait(g).unwrap();
       }
       if let Some(ref e) = g.background_error {
@@ -1255,6 +2889,203 @@ impl Db {
     Ok(())
   }
 
+  /// Add the SSTables at `paths`, written by [`SstFileWriter`], to the
+  /// default column family without going through the WAL or the memtable.
+  ///
+  /// The files are copied into the database; the originals are left in
+  /// place.  All of their entries get one new sequence number, so they shadow
+  /// every earlier write of the same keys and are hidden from snapshots taken
+  /// before the call.  Each file goes to the deepest level at which nothing
+  /// at or above it overlaps its key range.
+  ///
+  /// If the memtable overlaps the files it is flushed first, unless
+  /// `opts.allow_blocking_flush` is `false`, in which case the call fails with
+  /// [`Error::InvalidArgument`].  So does a file that was not written by an
+  /// [`SstFileWriter`], or key ranges overlapping one another.  Waits for
+  /// running compactions to finish before installing the files.
+  ///
+  /// Returns [`Error::NotSupported`] for in-memory databases.
+  ///
+  /// See `include/rocksdb/db.h: DB::IngestExternalFile`.
+  pub fn ingest_external_file<P: AsRef<std::path::Path>>(
+    &self,
+    paths: &[P],
+    opts: &IngestExternalFileOptions,
+  ) -> Result<(), Error> {
+    self.ingest_external_file_internal(DEFAULT_COLUMN_FAMILY_ID, paths, opts)
+  }
+
+  /// Add the SSTables at `paths` to column family `cf`; see
+  /// [`Db::ingest_external_file`].
+  pub fn ingest_external_file_cf<P: AsRef<std::path::Path>>(
+    &self,
+    cf: &ColumnFamilyHandle,
+    paths: &[P],
+    opts: &IngestExternalFileOptions,
+  ) -> Result<(), Error> {
+    self.ingest_external_file_internal(cf.id(), paths, opts)
+  }
+
+  fn ingest_external_file_internal<P: AsRef<std::path::Path>>(
+    &self,
+    cf: u32,
+    paths: &[P],
+    opts: &IngestExternalFileOptions,
+  ) -> Result<(), Error> {
+    use crate::db::external_file_ingestion::{
+      check_disjoint, inspect, pick_level, stage, with_sequence,
+    };
+
+    self.check_writable()?;
+    let Some(p) = &self.inner.persistence else {
+      return Err(Error::NotSupported(
+        "ingest_external_file on an in-memory database".to_owned(),
+      ));
+    };
+    if paths.is_empty() {
+      return Err(Error::InvalidArgument("no files to ingest".to_owned()));
+    }
+    let options = self.column_family_options(cf)?;
+    let mut files = paths
+      .iter()
+      .map(|path| inspect(path.as_ref(), &options))
+      .collect::<Result<Vec<_>, _>>()?;
+    check_disjoint(&mut files, &*options.comparator)?;
+
+    // Staged copies and installed files that end up unreferenced are removed
+    // once deletions resume, if the ingestion fails.
+    let _pause = self.pause_file_deletions();
+    let mut staged = Vec::with_capacity(files.len());
+    for file in files {
+      let number = {
+        let mut g = self.inner.state.lock().unwrap();
+        g.version_set.as_mut().unwrap().next_file_number()
+      };
+      staged.push(stage(
+        file,
+        p.dir.join(format!("{number:06}.dbtmp")),
+        &options,
+      )?);
+    }
+
+    let mut g = self.inner.state.lock().unwrap();
+    loop {
+      if let Some(ref e) = g.background_error {
+        return Err(e.clone());
+      }
+      // Until a flush is installed, recovery reads only the WAL it retires,
+      // not the one the sequence number below would be recorded in.
+      if g.pending_flush.is_some() || !g.column_family(cf)?.running_compactions.is_empty() {
+        g = self.inner.write_condvar.wait(g).unwrap();
+        continue;
+      }
+      // The files get a sequence number newer than anything in the
+      // memtables, so they must not hide an overlapping memtable entry from
+      // reads that consult the memtables first.
+      let cfd = g.column_family(cf)?;
+      let overlaps = staged.iter().any(|f| {
+        let (smallest, largest) = (f.file.smallest_user_key(), f.file.largest_user_key());
+        cfd.mem.overlaps(smallest, largest)
+          || cfd
+            .imm
+            .as_ref()
+            .is_some_and(|m| m.overlaps(smallest, largest))
+      });
+      if !overlaps {
+        break;
+      }
+      if !opts.allow_blocking_flush {
+        return Err(Error::InvalidArgument(
+          "the files overlap the memtable and allow_blocking_flush is false".to_owned(),
+        ));
+      }
+      drop(g);
+      self.flush(&FlushOptions::default())?;
+      g = self.inner.state.lock().unwrap();
+    }
+
+    // Phase 3 (under lock): assign the sequence number and install.  File
+    // numbers are allocated here so that an ingested L0 file sorts after
+    // every L0 file holding older entries.
+    //
+    // The MANIFEST's last sequence number tells recovery which WAL records
+    // are already in tables, so it must not cover the memtables' unflushed
+    // writes.  The global sequence number is recorded in the WAL instead,
+    // behind them, as an empty batch, which `get_updates_since` reports as a
+    // `WalUpdate::Ingestion`.  The marker follows the install, so a failed
+    // ingestion leaves none; recovery takes the sequence number from the
+    // files should the process die in between.
+    let global_seqno = g.last_sequence + 1;
+    let cfd = g.column_family(cf)?;
+    let tc = cfd
+      .table_cache
+      .clone()
+      .expect("ingest_external_file: no TableCache");
+    // FIFO compaction keeps every file in L0 and ages it from ingestion.
+    let fifo = cfd.options.compaction_style == CompactionStyle::Fifo;
+    let oldest_ancestor_time = if fifo {
+      cfd.options.clock.current_time()
+    } else {
+      0
+    };
+    let version = g.current(cf).expect("ingest_external_file: no Version");
+    let mut edit = VersionEdit::new();
+    edit.column_family = cf;
+    let vs = g.version_set.as_mut().unwrap();
+    for f in staged {
+      let file_size = f.writer.finish(global_seqno)?;
+      let number = vs.next_file_number();
+      p.fs
+        .rename(&f.path, &p.dir.join(format!("{number:06}.ldb")))?;
+      let level = if fifo {
+        0
+      } else {
+        pick_level(
+          &version,
+          f.file.smallest_user_key(),
+          f.file.largest_user_key(),
+        )
+      };
+      log::info!(
+        "ingest: {} as file {number} ({file_size} bytes) at L{level}",
+        f.file.path.display()
+      );
+      edit.new_files.push((
+        level as i32,
+        FileMetaData::with_oldest_ancestor_time(
+          number,
+          file_size,
+          with_sequence(&f.file.smallest, global_seqno),
+          with_sequence(&f.file.largest, global_seqno),
+          0,
+          oldest_ancestor_time,
+        ),
+      ));
+    }
+    vs.log_and_apply(&mut edit, &tc)?;
+    g.last_sequence = global_seqno;
+    if let Some(cfd) = g.column_families.get_mut(&cf) {
+      cfd.last_ingested_sequence = global_seqno;
+    }
+    let marked = match g.log.as_mut() {
+      Some(log) => {
+        let marker = WriteBatch::ingestion_marker(global_seqno);
+        log.add_record(marker.data()).and_then(|()| log.sync())
+      }
+      None => Ok(()),
+    };
+    if let Err(e) = marked {
+      // The files are installed; without the marker the WAL no longer
+      // accounts for every sequence number, so stop taking writes.
+      log::error!("ingest: writing the WAL marker failed: {e}, stopping writes");
+      g.background_error = Some(e.clone());
+      self.inner.write_condvar.notify_all();
+      return Err(e);
+    }
+    maybe_schedule_compaction(&self.inner, &mut g);
+    Ok(())
+  }
+
   /// Apply `batch` atomically to the database.
   ///
   /// All operations in `batch` are written together as a single WAL record and inserted into the
@@ -1271,9 +3102,10 @@ impl Db {
   /// ## Backpressure
   ///
   /// The write leader calls `make_room_for_write` before writing:
-  /// - **L0 slowdown** (≥ 8 files): sleeps 1 ms (at most once per `write` call).
-  /// - **Flush in progress** (`imm` present): waits for the background thread to complete it.
-  /// - **L0 hard stop** (≥ 12 files): blocks until the background thread drains L0.
+  /// - **L0 slowdown** (`level0_slowdown_writes_trigger` files): sleeps 1 ms (at most once per
+  ///   `write` call).
+  /// - **Flush in progress** (`imm` present): waits for the flush thread to complete it.
+  /// - **L0 hard stop** (`level0_stop_writes_trigger` files): blocks until compaction drains L0.
   /// - **Memtable full**: rotates `mem → imm`, schedules the background flush, and continues.
   ///
   /// Followers bypass all backpressure — only the leader gates on it.
@@ -1285,19 +3117,73 @@ impl Db {
   ///
   /// See `db/db_impl.cc: DBImpl::Write`.
   pub fn write(&self, opts: &WriteOptions, batch: WriteBatch) -> Result<(), Error> {
+    self.write_internal(opts, batch, None, None)
+  }
+
+  /// Apply `batch` on a replica so that its first record gets sequence
+  /// number `sequence`, keeping the replica's sequence numbers identical to
+  /// the primary's.
+  ///
+  /// `sequence` must be one past [`Db::latest_sequence_number`]: applying the
+  /// updates of the primary's [`Db::get_updates_since`] in order satisfies
+  /// this, a [`WalUpdate::Ingestion`] by ingesting the same files.  A `sequence` at or below the latest one is a duplicate and one
+  /// further ahead means a batch was missed; both return
+  /// [`Error::InvalidArgument`] without writing anything.  A replica should
+  /// take no other writes, which would consume the sequence numbers the
+  /// primary's next batches need.
+  pub fn write_at_sequence(
+    &self,
+    opts: &WriteOptions,
+    sequence: u64,
+    batch: WriteBatch,
+  ) -> Result<(), Error> {
+    self.write_internal(opts, batch, None, Some(sequence))
+  }
+
+  /// The sequence number of the most recent write.
+  ///
+  /// See `include/rocksdb/db.h: DB::GetLatestSequenceNumber`.
+  pub fn latest_sequence_number(&self) -> u64 {
+    self.inner.state.lock().unwrap().last_sequence
+  }
+
+  /// [`Db::write`], optionally validating `conflict_check` or the required
+  /// starting `sequence` in the group leader right before the batch is
+  /// applied.  The check and the write happen under the same lock hold, so
+  /// no other write can slip in between.
+  ///
+  /// See `db/db_impl/db_impl_write.cc: DBImpl::WriteImpl` (`callback`).
+  fn write_internal(
+    &self,
+    opts: &WriteOptions,
+    batch: WriteBatch,
+    conflict_check: Option<ConflictCheck>,
+    sequence: Option<u64>,
+  ) -> Result<(), Error> {
+    self.check_writable()?;
     // ── Phase 1: Enqueue this write request ──────────────────────────────────
     //
     // Every caller pushes a `WriterSlot` and waits until it is either at the
     // front of the queue (becoming the group leader) or has been processed by a
     // previous leader and its result placed in `state.completed`.
+    //
+    // A batch naming a column family that does not exist is rejected here, on
+    // its own, rather than failing the whole group it would join.
+    // Batches built through the API know their families without decoding.
+    let column_families = batch.column_family_ids()?.into_owned();
     let my_id = {
       let mut state = self.inner.state.lock().unwrap();
+      for &cf in &column_families {
+        state.column_family(cf)?;
+      }
       let id = state.next_writer_id;
       state.next_writer_id += 1;
       state.writers.push_back(WriterSlot {
         id,
         batch,
         sync: opts.sync,
+        conflict_check,
+        sequence,
       });
       id
     };
@@ -1338,14 +3224,23 @@ impl Db {
     };
     let first_sync = state.writers.front().unwrap().sync;
 
+    // A transaction commit or a replicated batch is validated against the
+    // state just before its own batch, so it neither leads nor joins a larger
+    // group.
+    let is_checked = |w: &WriterSlot| w.conflict_check.is_some() || w.sequence.is_some();
+    let first_checked = is_checked(state.writers.front().unwrap());
+
     let mut group_size = first_size;
     let mut group_len = 1;
-    while group_len < state.writers.len() {
+    while !first_checked && group_len < state.writers.len() {
       let next = &state.writers[group_len];
       // Stop before a sync writer if the group is non-sync.
       if next.sync && !first_sync {
         break;
       }
+      if is_checked(next) {
+        break;
+      }
       let next_size = next.batch.approximate_size();
       if group_size + next_size > max_size {
         break;
@@ -1355,6 +3250,12 @@ impl Db {
     }
 
     // ── Phase 4: Write WAL, insert into memtable ──────────────────────────────
+    let leader = state.writers.front().unwrap();
+    let conflict = match (&leader.conflict_check, leader.sequence) {
+      (Some(check), _) => check_for_conflicts(&state, check),
+      (None, Some(sequence)) => check_replicated_sequence(sequence, state.last_sequence),
+      (None, None) => Ok(()),
+    };
     let need_sync = state.writers.iter().take(group_len).any(|w| w.sync);
     let start_seq = state.last_sequence + 1;
 
@@ -1380,14 +3281,21 @@ impl Db {
     };
 
     let status: Result<(), Error> = (|| {
+      conflict?;
       if let Some(log) = st.log.as_mut() {
-        log.add_record(batch.contents())?;
+        // Recovery and `get_updates_since` read every empty batch in the
+        // WAL as an ingestion marker (see `WriteBatch::ingestion_marker`),
+        // so a write with no records, including a group of them or a
+        // replicated one, only syncs what is already logged.
+        if batch.count() > 0 {
+          log.add_record(batch.data())?;
+        }
         if need_sync {
           log.sync()?;
         }
       }
       batch.iterate(&mut Inserter {
-        mem: &st.mem,
+        column_families: &st.column_families,
         seq: start_seq,
       })?;
       st.last_sequence += batch.count() as u64;
@@ -1417,24 +3325,15 @@ impl Db {
 
     // ── Phase 6: Schedule background work if needed ───────────────────────────
     //
-    // If the write filled the memtable and no flush is already pending, rotate
-    // mem → imm now so the background thread can flush it.  Then wait for the
+    // If the write filled a memtable and no flush is already pending, rotate
+    // mem → imm now so the flush thread can flush it.  Then wait for the
     // flush to complete before returning, preserving the invariant that all
     // data written before a `put` returns is durable and readable.
     if let Some(ref persistence) = self.inner.persistence {
-      let triggered_flush = if state.imm.is_none()
-        && state.pending_flush.is_none()
-        && state.mem.approximate_memory_usage() >= self.inner.options.write_buffer_size
-      {
+      let triggered_flush = if !state.flush_in_progress() && state.memtable_full() {
         let path = persistence.dir.as_path();
-        match begin_flush(
-          path,
-          &mut state,
-          &self.inner.options.comparator,
-          &*persistence.fs,
-        ) {
+        match begin_flush(path, &mut state, &*persistence.fs) {
           Ok(prep) => {
-            state.imm = Some(Arc::clone(&prep.old_mem));
             state.pending_flush = Some(prep);
             true
           }
@@ -1447,12 +3346,10 @@ impl Db {
         false
       };
       maybe_schedule_compaction(&self.inner, &mut state);
-      // If we triggered a flush, wait for the background thread to complete it.
+      // If we triggered a flush, wait for the flush thread to complete it.
       // This preserves the original synchronous-flush behaviour seen by callers.
       if triggered_flush {
-        while (state.imm.is_some() || state.pending_flush.is_some())
-          && state.background_error.is_none()
-        {
+        while state.flush_in_progress() && state.background_error.is_none() {
           state = self.inner.write_condvar.wait(state).unwrap();
         }
         // A background error means the flush failed.  The write itself already
@@ -1477,6 +3374,10 @@ impl Db {
   /// Does **not** acquire the database lock; the caller must ensure no other process has the
   /// database open.  Call [`Db::open`] after repair to use the recovered database.
   ///
+  /// Only the default column family is recovered: WAL records of other column families are
+  /// dropped, and every surviving SSTable is assigned to the default column family.  Readable
+  /// blob files are registered again, without the garbage recorded against them.
+  ///
   /// See `db/repair.cc: RepairDB`.
   pub fn repair<P: AsRef<std::path::Path>>(path: P, options: Options) -> Result<(), Error> {
     use crate::iter::InternalIterator;
@@ -1495,6 +3396,7 @@ impl Db {
 
     let mut logs: Vec<u64> = Vec::new();
     let mut table_numbers: Vec<u64> = Vec::new();
+    let mut blob_numbers: Vec<u64> = Vec::new();
     let mut manifests: Vec<String> = Vec::new();
     let mut max_number: u64 = 0;
 
@@ -1506,6 +3408,7 @@ impl Db {
         match kind {
           FileKind::Log => logs.push(number),
           FileKind::Table => table_numbers.push(number),
+          FileKind::Blob => blob_numbers.push(number),
           FileKind::Manifest => manifests.push(name.to_string()),
           _ => {}
         }
@@ -1524,7 +3427,11 @@ impl Db {
         }
       };
       let mut reader = LogReader::new(file, None, false, 0);
-      let mem = Memtable::new(Arc::clone(&options.comparator));
+      let column_families = BTreeMap::from([(
+        DEFAULT_COLUMN_FAMILY_ID,
+        ColumnFamilyData::new(DEFAULT_COLUMN_FAMILY_NAME, Arc::new(options.clone()), None),
+      )]);
+      let mem = &column_families[&DEFAULT_COLUMN_FAMILY_ID].mem;
 
       while let Some(record) = reader.read_record() {
         let batch = match WriteBatch::from_contents(record) {
@@ -1538,7 +3445,7 @@ impl Db {
           }
         };
         let mut inserter = Inserter {
-          mem: &mem,
+          column_families: &column_families,
           seq: batch.sequence(),
         };
         if let Err(e) = batch.iterate(&mut inserter) {
@@ -1628,6 +3535,7 @@ impl Db {
       let mut smallest: Vec<u8> = Vec::new();
       let mut largest: Vec<u8> = Vec::new();
       let mut max_seq: u64 = 0;
+      let mut oldest_blob: u64 = 0;
 
       iter.seek_to_first();
       while iter.valid() {
@@ -1636,10 +3544,17 @@ impl Db {
           smallest = ikey.to_vec();
         }
         largest = ikey.to_vec();
-        if let Some((_user_key, seq, _vtype)) = parse_internal_key(ikey) {
+        if let Some((_user_key, seq, vtype)) = parse_internal_key(ikey) {
           if seq > max_seq {
             max_seq = seq;
           }
+          if vtype == VALUE_TYPE_BLOB_INDEX {
+            if let Ok(index) = crate::db::blob::BlobIndex::decode(iter.value()) {
+              if oldest_blob == 0 || index.file_number < oldest_blob {
+                oldest_blob = index.file_number;
+              }
+            }
+          }
         }
         iter.next();
       }
@@ -1652,14 +3567,40 @@ impl Db {
         continue;
       }
 
+      // The file's range must also span its range tombstones.
+      for tombstone in table.range_tombstones().iter() {
+        crate::db::range_del::extend_file_bounds(
+          &mut smallest,
+          &mut largest,
+          &tombstone,
+          &*options.comparator,
+        );
+        max_seq = max_seq.max(tombstone.seq);
+      }
+
       if !smallest.is_empty() {
         tables.push(TableInfo {
-          meta: FileMetaData::new(num, file_size, smallest, largest),
+          meta: FileMetaData::with_oldest_blob_file(num, file_size, smallest, largest, oldest_blob),
           max_sequence: max_seq,
         });
       }
     }
 
+    // Blob files carry their own totals in their footers.
+    let mut blob_files = Vec::new();
+    for &num in &blob_numbers {
+      let counted = crate::db::blob::BlobFileReader::open(fs, path, num)
+        .and_then(|reader| Ok((reader.blob_count()?, reader.blob_bytes())));
+      match counted {
+        Ok((total_blob_count, total_blob_bytes)) => blob_files.push(BlobFileAddition {
+          blob_file_number: num,
+          total_blob_count,
+          total_blob_bytes,
+        }),
+        Err(e) => log::warn!("repair: cannot read blob file {num:06}.blob: {e}"),
+      }
+    }
+
     // ── Phase 4: write_descriptor ──────────────────────────────────────────────
     let max_sequence = tables.iter().map(|t| t.max_sequence).max().unwrap_or(0);
 
@@ -1671,6 +3612,7 @@ impl Db {
     for t in &tables {
       edit.new_files.push((0, Arc::clone(&t.meta)));
     }
+    edit.blob_file_additions = blob_files;
 
     let manifest_path = path.join(crate::db::version_set::manifest_filename(1));
     let manifest_file = fs.create_writable(&manifest_path)?;
@@ -1696,35 +3638,111 @@ impl Db {
   }
 }
 
+// ── Replicated writes ───────────────────────────────────────────────────────
+
+/// Fail with [`Error::InvalidArgument`] unless a replicated batch starting at
+/// `sequence` directly follows `last_sequence`; see [`Db::write_at_sequence`].
+fn check_replicated_sequence(sequence: u64, last_sequence: u64) -> Result<(), Error> {
+  let expected = last_sequence + 1;
+  match sequence.cmp(&expected) {
+    std::cmp::Ordering::Equal => Ok(()),
+    std::cmp::Ordering::Less => Err(Error::InvalidArgument(format!(
+      "replicated batch at sequence {sequence} is a duplicate: sequences up to \
+       {last_sequence} are already applied"
+    ))),
+    std::cmp::Ordering::Greater => Err(Error::InvalidArgument(format!(
+      "replicated batch at sequence {sequence} is out of order: expected {expected}"
+    ))),
+  }
+}
+
+// ── Optimistic transaction validation ───────────────────────────────────────
+
+/// Fail with [`Error::Busy`] if any key in `check` was written after
+/// `check.snapshot_seq`.  Call while holding the `DbState` lock.
+///
+/// Only the memtables are consulted.  If writes newer than the snapshot may
+/// already have been flushed, or files were ingested since, the conflict
+/// cannot be ruled out and the check fails conservatively.
+///
+/// See `utilities/transactions/transaction_util.cc:
+/// TransactionUtil::CheckKeysForConflicts` (`cache_only = true`).
+fn check_for_conflicts(state: &DbState, check: &ConflictCheck) -> Result<(), Error> {
+  // Every write at or after `earliest` is still in `imm` or `mem`.
+  let cfd = state.default_column_family();
+  let earliest = cfd
+    .imm
+    .as_ref()
+    .and_then(|imm| imm.first_sequence())
+    .or_else(|| cfd.mem.first_sequence())
+    .unwrap_or(state.last_sequence + 1);
+  if earliest > check.snapshot_seq + 1 {
+    return Err(Error::Busy(format!(
+      "could not check for conflicts at sequence {}: memtables only hold writes from {earliest}",
+      check.snapshot_seq
+    )));
+  }
+  if cfd.last_ingested_sequence > check.snapshot_seq {
+    return Err(Error::Busy(format!(
+      "could not check for conflicts at sequence {}: files were ingested at {}",
+      check.snapshot_seq, cfd.last_ingested_sequence
+    )));
+  }
+  for key in &check.keys {
+    let latest = [Some(&cfd.mem), cfd.imm.as_ref()]
+      .into_iter()
+      .flatten()
+      .filter_map(|mem| mem.latest_sequence_for_key(key))
+      .max();
+    if latest.is_some_and(|seq| seq > check.snapshot_seq) {
+      return Err(Error::Busy("write conflict".to_owned()));
+    }
+  }
+  Ok(())
+}
+
 // ── Background scheduling helpers ────────────────────────────────────────────
 
-/// Notify the background thread if there is work to do and no notification is
-/// already outstanding.  Call while holding the `DbState` lock.
+/// Number of [`bg_worker`] threads: `max_background_jobs` less the flush
+/// thread, and at least one.
+fn max_compaction_jobs(opts: &Options) -> usize {
+  opts.max_background_jobs.saturating_sub(1).max(1)
+}
+
+/// Wake the flush thread if a flush is pending, and request a compaction job
+/// if one is needed, no request is already outstanding, and fewer than
+/// [`max_compaction_jobs`] are running.  Call while holding the `DbState`
+/// lock.
+///
+/// A worker that has picked its compaction calls this again, so that
+/// compactions that do not conflict run at once.
+///
+/// See `DBImpl::MaybeScheduleFlushOrCompaction` in RocksDB.
 fn maybe_schedule_compaction(inner: &Arc<DbInner>, g: &mut DbState) {
+  if inner.read_only {
+    return;
+  }
   if inner.shutting_down.load(Ordering::Relaxed) {
     return;
   }
   if g.background_error.is_some() {
     return;
   }
-  if g.background_scheduled {
+  if g.pending_flush.is_some() {
+    inner.flush_condvar.notify_one();
+  }
+  if g.bg_jobs_queued > 0 || g.bg_jobs_running >= max_compaction_jobs(&inner.options) {
     return;
   }
-  let has_imm = g.imm.is_some() || g.pending_flush.is_some();
-  let needs = has_imm || {
-    let v = g.version_set.as_ref().map(|vs| vs.current());
-    v.as_ref()
-      .is_some_and(|v| needs_compaction(v, g.compaction_needed))
-  };
-  if !needs {
+  if g.compactions_blocked || !g.compaction_pending() {
     return;
   }
-  g.background_scheduled = true;
+  g.bg_jobs_queued += 1;
   inner.bg_condvar.notify_one();
 }
 
 /// Called by the write leader (while holding the lock) to ensure there is room
-/// in the memtable.  May sleep or wait on `write_condvar` if the system is
+/// in the memtables.  May sleep or wait on `write_condvar` if the system is
 /// under write pressure.  Returns the (possibly re-acquired) lock guard.
 fn make_room_for_write<'a>(
   inner: &'a Arc<DbInner>,
@@ -1739,40 +3757,32 @@ fn make_room_for_write<'a>(
       // In-memory: no flush needed.
       break;
     }
-    let l0 = g
-      .version_set
-      .as_ref()
-      .map_or(0, |vs| vs.current().num_files(0));
-    if allow_delay && l0 >= L0_SLOWDOWN_WRITES_TRIGGER {
+    let (slowdown, stop) = g.l0_write_stall();
+    if allow_delay && slowdown {
       // Slow down at most once per write call.
-      log::debug!("L0 file count ({l0}) ≥ {L0_SLOWDOWN_WRITES_TRIGGER}: delaying writes 1ms");
+      log::debug!("L0 file count ≥ level0_slowdown_writes_trigger: delaying writes 1ms");
       allow_delay = false;
       drop(g);
       std::thread::sleep(std::time::Duration::from_millis(1));
       g = inner.state.lock().unwrap();
-    } else if g.mem.approximate_memory_usage() < inner.options.write_buffer_size {
-      break; // There is room in the current memtable.
-    } else if g.imm.is_some() || g.pending_flush.is_some() {
-      // A flush is already in progress; wait for the background thread.
+    } else if !g.memtable_full() {
+      break; // There is room in the current memtables.
+    } else if g.flush_in_progress() {
+      // A flush is already in progress; wait for the flush thread.
       log::debug!("waiting for in-progress flush to complete");
       g = inner.write_condvar.wait(g).unwrap();
-    } else if l0 >= L0_STOP_WRITES_TRIGGER {
-      // Too many L0 files; wait for the background thread to drain them.
+    } else if stop {
+      // Too many L0 files; wait for compaction to drain them.
       log::warn!(
-        "L0 file count ({l0}) ≥ {L0_STOP_WRITES_TRIGGER}: stopping writes until compaction drains L0"
+        "L0 file count ≥ level0_stop_writes_trigger: stopping writes until compaction drains L0"
       );
       g = inner.write_condvar.wait(g).unwrap();
     } else {
       // Rotate mem → imm, schedule background flush.
-      log::info!(
-        "memtable full ({} bytes ≥ {}): rotating to immutable",
-        g.mem.approximate_memory_usage(),
-        inner.options.write_buffer_size,
-      );
+      log::info!("memtable full: rotating to immutable");
       let p = inner.persistence.as_ref().unwrap();
       let path = p.dir.as_path();
-      let prep = begin_flush(path, &mut g, &inner.options.comparator, &*p.fs)?;
-      g.imm = Some(Arc::clone(&prep.old_mem));
+      let prep = begin_flush(path, &mut g, &*p.fs)?;
       g.pending_flush = Some(prep);
       maybe_schedule_compaction(inner, &mut g);
     }
@@ -1780,96 +3790,111 @@ fn make_room_for_write<'a>(
   Ok(g)
 }
 
-/// Background worker: sleeps on `bg_condvar`, wakes to perform flush or
-/// compaction, then re-checks.
-fn bg_worker(inner: Arc<DbInner>) {
+/// The flush thread: sleeps on `flush_condvar`, wakes to flush the pending
+/// memtables, then requests a compaction if the new files call for one.  It
+/// never runs compactions, so a long compaction cannot hold up a flush and
+/// stall writers in `make_room_for_write`.
+///
+/// On shutdown, a pending flush is still run.
+///
+/// See the `Env::Priority::HIGH` pool in RocksDB.
+fn flush_worker(inner: Arc<DbInner>) {
   let mut g = inner.state.lock().unwrap();
   loop {
-    // Sleep until scheduled or shutting down.
-    while !g.background_scheduled && !inner.shutting_down.load(Ordering::Relaxed) {
-      g = inner.bg_condvar.wait(g).unwrap();
+    while (g.pending_flush.is_none() || g.background_error.is_some())
+      && !inner.shutting_down.load(Ordering::Relaxed)
+    {
+      g = inner.flush_condvar.wait(g).unwrap();
     }
-    g.background_scheduled = false;
-
-    // On shutdown: flush any pending memtable, then exit.
-    let shutting_down = inner.shutting_down.load(Ordering::Relaxed);
-
-    if g.background_error.is_some() {
+    if g.pending_flush.is_none() || g.background_error.is_some() {
       inner.write_condvar.notify_all();
-      if shutting_down {
-        return;
-      }
-      continue;
+      return;
     }
+    g = background_flush(&inner, g);
+    maybe_schedule_compaction(&inner, &mut g);
+    inner.write_condvar.notify_all();
+  }
+}
 
-    let Some(ref p) = inner.persistence else {
+/// Compaction worker, [`max_compaction_jobs`] of them: sleeps on
+/// `bg_condvar`, wakes to run one compaction job, then requests another if
+/// work remains.
+///
+/// On shutdown, no further compaction is started.
+fn bg_worker(inner: Arc<DbInner>) {
+  let mut g = inner.state.lock().unwrap();
+  loop {
+    // Sleep until a job is requested or shutting down.
+    while g.bg_jobs_queued == 0 && !inner.shutting_down.load(Ordering::Relaxed) {
+      g = inner.bg_condvar.wait(g).unwrap();
+    }
+    if inner.shutting_down.load(Ordering::Relaxed) {
       inner.write_condvar.notify_all();
-      if shutting_down {
-        return;
-      }
-      continue;
-    };
-    let path = p.dir.clone();
-    let tc = p.table_cache.clone();
-    let fs = Arc::clone(&p.fs);
-
-    // ── Flush if pending ──────────────────────────────────────────────────────
-    if let Some(prep) = g.pending_flush.take() {
-      log::info!("bg: flushing memtable to SSTable {}", prep.sst_number);
-      drop(g);
-      let result = write_flush(prep, &inner.options);
-      g = inner.state.lock().unwrap();
-      match result {
-        Ok(res) => {
-          if let Err(e) = finish_flush(&mut g, res, &inner.options, &tc) {
-            log::error!("bg: finish_flush failed: {e}, stopping writes");
-            g.background_error = Some(e);
-          }
-        }
-        Err(e) => {
-          log::error!("bg: write_flush failed: {e}, stopping writes");
-          g.background_error = Some(e);
-        }
-      }
-      drop(g);
-      // Run GC before waking waiters: a `flush(wait: true)` caller must observe
-      // obsolete files already removed, not just imm/pending_flush cleared.
-      delete_obsolete_files(&path, &inner.state, &*fs);
-      inner.write_condvar.notify_all();
-      g = inner.state.lock().unwrap();
-    } else if !shutting_down {
-      // ── Compaction (skipped on shutdown path) ────────────────────────────
+      return;
+    }
+    g.bg_jobs_queued -= 1;
+    if g.background_error.is_none() {
+      g.bg_jobs_running += 1;
       drop(g);
-      maybe_compact(&path, &inner.state, &inner.options, &tc);
-      delete_obsolete_files(&path, &inner.state, &*fs);
-      inner.write_condvar.notify_all();
+      maybe_compact(&inner);
+      let p = inner
+        .persistence
+        .as_ref()
+        .expect("bg_worker: no persistence");
+      delete_obsolete_files(&p.dir, &inner.state, &inner.options);
       g = inner.state.lock().unwrap();
+      g.bg_jobs_running -= 1;
     }
+    // Request the next job if more work remains.
+    maybe_schedule_compaction(&inner, &mut g);
+    inner.write_condvar.notify_all();
+  }
+}
 
-    // After processing, check whether to exit (shutdown) or reschedule.
-    let shutting_down = inner.shutting_down.load(Ordering::Relaxed);
-    let has_pending = g.imm.is_some() || g.pending_flush.is_some();
-    if shutting_down && !has_pending {
-      inner.write_condvar.notify_all();
-      return;
-    }
-
-    // Reschedule immediately if more work remains.
-    if !shutting_down {
-      let needs_c = {
-        let v = g.version_set.as_ref().map(|vs| vs.current());
-        v.as_ref()
-          .is_some_and(|v| needs_compaction(v, g.compaction_needed))
-      };
-      if has_pending || needs_c {
-        g.background_scheduled = true;
-        // Loop back immediately without waiting.
+/// Flush the pending memtables.  Takes and returns the lock, releasing it
+/// during I/O.
+fn background_flush<'a>(
+  inner: &'a Arc<DbInner>,
+  mut g: std::sync::MutexGuard<'a, DbState>,
+) -> std::sync::MutexGuard<'a, DbState> {
+  let p = inner
+    .persistence
+    .as_ref()
+    .expect("background_flush: no persistence");
+  let path = p.dir.as_path();
+  let prep = g.pending_flush.take().expect("background_flush: no flush");
+  log::info!(
+    "bg: flushing {} memtable(s) to SSTables",
+    prep.memtables.len()
+  );
+  drop(g);
+  let pending_output = prep.pending_output;
+  let mut alloc = || {
+    let mut g = inner.state.lock().unwrap();
+    g.version_set.as_mut().unwrap().next_file_number()
+  };
+  let result = write_flush(prep, &inner.options, &mut alloc);
+  g = inner.state.lock().unwrap();
+  g.release_pending_output(pending_output);
+  match result {
+    Ok(res) => {
+      if let Err(e) = finish_flush(&mut g, res, &inner.options) {
+        log::error!("bg: finish_flush failed: {e}, stopping writes");
+        g.background_error = Some(e);
       }
-    } else if has_pending {
-      // Still have pending work during shutdown — keep looping.
-      g.background_scheduled = true;
+    }
+    Err(e) => {
+      log::error!("bg: write_flush failed: {e}, stopping writes");
+      g.background_error = Some(e);
     }
   }
+  // The new files may make a compaction possible.
+  g.compactions_blocked = false;
+  drop(g);
+  // Run GC before waking waiters: a `flush(wait: true)` caller must observe
+  // obsolete files already removed, not just imm/pending_flush cleared.
+  delete_obsolete_files(path, &inner.state, &inner.options);
+  inner.state.lock().unwrap()
 }
 
 // ── flush helpers ─────────────────────────────────────────────────────────────
@@ -1880,8 +3905,12 @@ fn bg_worker(inner: Arc<DbInner>) {
 //   write_flush  — no lock: write SSTable to disk, open it for reading
 //   finish_flush — under lock: install new Version, swap WAL, clear imm
 
-/// Phase 1 (under write lock): seal `mem` as `imm`, install a fresh memtable,
-/// allocate the next SSTable file number, and create and activate the new WAL.
+/// Phase 1 (under write lock): seal every non-empty memtable as its column
+/// family's `imm`, install fresh memtables, allocate an SSTable file number
+/// per sealed memtable, and create and activate the new WAL.
+///
+/// All column families are flushed together because they share one WAL: the
+/// old WAL can only be deleted once no memtable holds data logged in it.
 ///
 /// The new WAL is activated immediately (swapped into `state.log`) so that
 /// any writes between now and when the background thread runs `finish_flush`
@@ -1889,96 +3918,205 @@ fn bg_worker(inner: Arc<DbInner>) {
 /// only in `finish_flush`, those intermediate writes would land in the old WAL
 /// which `finish_flush` then deletes — causing data loss on reopen.
 ///
-/// `finish_flush` still calls `vs.set_log_number` and `log_and_apply` to
-/// commit the new log number and SSTable to the MANIFEST, so that on reopen
+/// `finish_flush` still calls `vs.set_log_number` and `log_and_apply_group` to
+/// commit the new log number and SSTables to the MANIFEST, so that on reopen
 /// the correct WAL is replayed.
 fn begin_flush(
   path: &std::path::Path,
   state: &mut DbState,
-  comparator: &Arc<dyn crate::comparator::Comparator>,
   fs: &dyn crate::env::FileSystem,
 ) -> Result<FlushPrep, Error> {
+  let pending_output = state.capture_pending_output();
   let vs = state
     .version_set
     .as_mut()
     .expect("begin_flush: no VersionSet");
   let old_log_number = vs.log_number();
-  let sst_number = vs.next_file_number();
+  let mut memtables = Vec::new();
+  for (&id, cfd) in &state.column_families {
+    if cfd.mem.approximate_memory_usage() == 0 {
+      continue;
+    }
+    let sst_number = vs.next_file_number();
+    let version = vs.current(id).expect("begin_flush: no Version");
+    let (output_level, compression) = plan_flush_output(cfd, &version);
+    memtables.push(MemtableFlush {
+      column_family: id,
+      options: Arc::clone(&cfd.options),
+      sst_number,
+      sst_path: path.join(format!("{sst_number:06}.ldb")),
+      old_mem: Arc::clone(&cfd.mem),
+      output_level,
+      compression,
+    });
+  }
   let new_log_number = vs.next_file_number();
-  log::debug!("begin_flush: sst={sst_number}, new_log={new_log_number}, old_log={old_log_number}");
+  log::debug!(
+    "begin_flush: memtables={}, new_log={new_log_number}, old_log={old_log_number}",
+    memtables.len()
+  );
   let new_log_path = path.join(format!("{new_log_number:06}.log"));
-  let new_log_file = fs.create_writable(&new_log_path)?;
+  let new_log_file = match fs.create_writable(&new_log_path) {
+    Ok(f) => f,
+    Err(e) => {
+      state.release_pending_output(pending_output);
+      return Err(e);
+    }
+  };
   let new_log = LogWriter::new(new_log_file, 0);
   // Activate the new WAL immediately; preserve the old WAL so finish_flush
   // can delete it after log_and_apply commits the rotation to the MANIFEST.
   let _old_log = state.log.replace(new_log);
-  let new_mem = Arc::new(Memtable::new(Arc::clone(comparator)));
-  let old_mem = std::mem::replace(&mut state.mem, new_mem);
-  state.imm = None; // should already be None; be explicit
-                    // Capture last_sequence now so finish_flush stores the correct value in the
-                    // MANIFEST.  Writes that happen to the new WAL after begin_flush will have
-                    // sequences > last_sequence_at_rotation and will be replayed on next open.
+  for m in &memtables {
+    let cfd = state
+      .column_families
+      .get_mut(&m.column_family)
+      .expect("begin_flush: column family");
+    cfd.mem = Arc::new(Memtable::new(Arc::clone(&cfd.options.comparator)));
+    cfd.imm = Some(Arc::clone(&m.old_mem));
+  }
+  // Capture last_sequence now so finish_flush stores the correct value in the
+  // MANIFEST.  Writes that happen to the new WAL after begin_flush will have
+  // sequences > last_sequence_at_rotation and will be replayed on next open.
   let last_sequence_at_rotation = state.last_sequence;
   Ok(FlushPrep {
-    sst_number,
-    sst_path: path.join(format!("{sst_number:06}.ldb")),
-    old_mem,
+    memtables,
     new_log_number,
     last_sequence_at_rotation,
     old_log_path: path.join(format!("{old_log_number:06}.log")),
+    old_log_number,
+    pending_output,
   })
 }
 
-// ── Open-time flush (reuse_logs=false) ─────────────────────────────────────
-//
-// When reuse_logs is false, replayed WAL data is flushed to an SSTable during
-// Db::open so the next open doesn't need to replay it.  These are simpler than
-// the normal flush path: no WAL rotation, no imm, no background thread.
-
-/// Flush `mem` to a new SSTable during `Db::open` (used when `reuse_logs` is false).
+/// Add every entry and range tombstone of `mem` to `builder`, returning the
+/// smallest and largest internal keys of the table (both empty if `mem` is).
+///
+/// With `blobs`, values large enough are written to blob files and the table
+/// gets a blob index in their place; `next_file_number` numbers the files.
 ///
-/// Allocates a file number from `vs`, writes the memtable contents to an SSTable,
-/// and returns a `FlushResult` for `finish_flush_at_open` to install.  Unlike the
-/// normal `write_flush`, this does not involve WAL rotation or the background thread.
-fn write_flush_from_mem(
+/// The bounds are widened to span the range tombstones, so a table holding
+/// only tombstones still gets a key range.
+fn add_memtable_to_table(
   mem: &Memtable,
-  vs: &mut VersionSet,
-  opts: &Options,
-  fs: &dyn crate::env::FileSystem,
-  path: &std::path::Path,
-) -> Result<FlushResult, Error> {
-  let sst_number = vs.next_file_number();
-  let sst_path = path.join(format!("{sst_number:06}.ldb"));
-  let file = fs.create_writable(&sst_path)?;
+  builder: &mut TableBuilder,
+  mut blobs: Option<&mut BlobFileBuilder>,
+  next_file_number: &mut dyn FnMut() -> u64,
+  comparator: &dyn Comparator,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+  let mut smallest = Vec::new();
+  let mut largest = Vec::new();
+  let mut blob_key = Vec::new();
+  let mut it = mem.iter();
+  it.seek_to_first();
+  while it.valid() {
+    // `it.key()` is the SSTable internal key (user_key || tag), borrowed
+    // from the iterator's scratch buffer — only the range bounds are copied
+    // (internal keys are never empty, so empty `smallest` means "first").
+    let (mut key, mut value) = (it.key(), std::borrow::Cow::Borrowed(it.value()));
+    if let (Some(blobs), Some((uk, seq, 1))) = (blobs.as_deref_mut(), parse_internal_key(key)) {
+      if let Some(index) = blobs.add(uk, &value, next_file_number)? {
+        encode_internal_key_into(&mut blob_key, uk, seq, VALUE_TYPE_BLOB_INDEX);
+        key = &blob_key;
+        value = std::borrow::Cow::Owned(index.encode());
+      }
+    }
+    if smallest.is_empty() {
+      smallest.extend_from_slice(key);
+    }
+    builder.add(key, &value)?;
+    largest.clear();
+    largest.extend_from_slice(key);
+    it.advance();
+  }
+  for tombstone in mem.range_tombstones() {
+    crate::db::range_del::extend_file_bounds(&mut smallest, &mut largest, &tombstone, comparator);
+    builder.add_range_tombstone(tombstone);
+  }
+  Ok((smallest, largest))
+}
+
+/// The level a flush of `cfd`'s memtable spanning `[smallest_uk, largest_uk]`
+/// goes to in `version`: past L0 when no level above overlaps it and the
+/// grandparent bytes are within bounds, and above the outputs of any
+/// overlapping running compaction.  Universal compaction keeps every flush
+/// in L0 as a sorted run of its own, and FIFO compaction keeps every file
+/// there.  With dynamic level sizes, a flush pushed down would make its
+/// level the base level; L0 compacts straight into the base instead.
+fn flush_output_level(
+  cfd: &ColumnFamilyData,
+  version: &crate::db::version::Version,
+  smallest_uk: &[u8],
+  largest_uk: &[u8],
+) -> usize {
+  if matches!(
+    cfd.options.compaction_style,
+    CompactionStyle::Fifo | CompactionStyle::Universal
+  ) || cfd.options.level_compaction_dynamic_level_bytes
+  {
+    return 0;
+  }
+  pick_level_for_memtable_output(
+    version,
+    smallest_uk,
+    largest_uk,
+    cfd.options.max_file_size as u64,
+  )
+  .min(placement_limit(
+    &cfd.running_compactions,
+    smallest_uk,
+    largest_uk,
+    &*cfd.options.comparator,
+  ))
+}
+
+/// The level `cfd`'s memtable would be flushed to in `version`, and the
+/// compression of files written there.  Picked before the table is built,
+/// since the compression depends on the level.
+fn plan_flush_output(
+  cfd: &ColumnFamilyData,
+  version: &crate::db::version::Version,
+) -> (usize, CompressionType) {
+  let level = cfd.mem.user_key_range().map_or(0, |(smallest, largest)| {
+    flush_output_level(cfd, version, &smallest, &largest)
+  });
+  let bottommost = ((level + 1)..version.num_levels()).all(|l| version.files_at(l).is_empty());
+  let compression = compression_for_level(&cfd.options, level, version.base_level, bottommost);
+  (level, compression)
+}
+
+/// Write the memtable of `m` to its SSTable with the column family's options
+/// and open it for reading, separating large values into blob files numbered
+/// by `next_file_number` if the options enable them.  The caller syncs the
+/// directory entries.
+fn write_memtable_table(
+  m: &MemtableFlush,
+  next_file_number: &mut dyn FnMut() -> u64,
+) -> Result<FlushedTable, Error> {
+  let (mem, sst_path, opts) = (&*m.old_mem, m.sst_path.as_path(), &*m.options);
+  let fs = &*opts.file_system;
+  let mut blobs = sst_path
+    .parent()
+    .and_then(|dir| BlobFileBuilder::new(opts, dir));
+  let file = fs.create_writable(sst_path)?;
   let mut builder = TableBuilder::new(
     file,
     opts.block_size,
     opts.block_restart_interval,
     opts.filter_policy.clone(),
-    opts.compression,
+    m.compression,
     Arc::clone(&opts.comparator),
   );
-  let mut smallest = Vec::new();
-  let mut largest = Vec::new();
-  {
-    let mut it = mem.iter();
-    it.seek_to_first();
-    while it.valid() {
-      // Borrowed key/value; only the range bounds are copied (internal keys
-      // are never empty — the 8-byte tag — so empty `smallest` means "first").
-      if smallest.is_empty() {
-        smallest.extend_from_slice(it.key());
-      }
-      builder.add(it.key(), it.value())?;
-      largest.clear();
-      largest.extend_from_slice(it.key());
-      it.advance();
-    }
-  }
+  let (smallest, largest) = add_memtable_to_table(
+    mem,
+    &mut builder,
+    blobs.as_mut(),
+    next_file_number,
+    &*opts.comparator,
+  )?;
+  let blob_files = blobs.map_or(Ok(Vec::new()), BlobFileBuilder::finish)?;
   let file_size = builder.finish()?;
-  // Persist the new SSTable's directory entry before it enters the MANIFEST.
-  fs.sync_dir(path)?;
-  let read_file = fs.open_random_access(&sst_path)?;
+  let read_file = fs.open_random_access(sst_path)?;
   let table = Arc::new(Table::open(
     read_file,
     file_size,
@@ -1988,170 +4126,195 @@ fn write_flush_from_mem(
   )?);
   let smallest_user_key = ikey_user_key(&smallest).to_vec();
   let largest_user_key = ikey_user_key(&largest).to_vec();
-  Ok(FlushResult {
-    file_number: sst_number,
+  Ok(FlushedTable {
+    column_family: m.column_family,
+    file_number: m.sst_number,
     file_size,
     smallest,
     largest,
     smallest_user_key,
     largest_user_key,
+    output_level: m.output_level,
     table,
-    // These fields are unused by finish_flush_at_open but required by the struct.
-    new_log_number: vs.log_number(),
-    last_sequence_at_rotation: vs.last_sequence(),
-    old_log_path: std::path::PathBuf::new(),
+    blob_files,
   })
 }
 
-/// Install the flushed SSTable into the VersionSet during open.
-fn finish_flush_at_open(
+/// Record flushed `tables` in the MANIFEST as one atomic group and install
+/// them into their column families' `Version`s.
+///
+/// Tables of column families dropped while the flush ran are skipped (their
+/// files are never live and get removed as obsolete).  If nothing is left, an
+/// empty edit is still logged so the WAL rotation is recorded.
+fn install_flushed_tables(
   vs: &mut VersionSet,
-  result: FlushResult,
-  opts: &Options,
-  tc: &crate::db::table_cache::TableCache,
+  column_families: &BTreeMap<u32, ColumnFamilyData>,
+  tables: Vec<FlushedTable>,
 ) -> Result<(), Error> {
-  tc.insert(result.file_number, result.table);
-  let meta = FileMetaData::new(
-    result.file_number,
-    result.file_size,
-    result.smallest,
-    result.largest,
-  );
-  let output_level = {
-    let version = vs.current();
-    if !result.smallest_user_key.is_empty() {
-      pick_level_for_memtable_output(
+  let mut group = Vec::with_capacity(tables.len().max(1));
+  for t in tables {
+    let (Some(cfd), Some(version)) = (
+      column_families.get(&t.column_family),
+      vs.current(t.column_family),
+    ) else {
+      continue;
+    };
+    let tc = cfd
+      .table_cache
+      .as_ref()
+      .expect("install_flushed_tables: no TableCache");
+    // Register the new table in the cache before installing the version.
+    tc.insert(t.file_number, t.table);
+    // The level planned when the flush began, unless files installed since
+    // take it: then the deepest level still free, keeping the planned
+    // compression until the table is next compacted.
+    let fifo = cfd.options.compaction_style == CompactionStyle::Fifo;
+    let output_level = if t.smallest_user_key.is_empty() {
+      0
+    } else {
+      t.output_level.min(flush_output_level(
+        cfd,
         &version,
-        &result.smallest_user_key,
-        &result.largest_user_key,
-        opts.max_file_size as u64,
-      )
+        &t.smallest_user_key,
+        &t.largest_user_key,
+      ))
+    };
+    log::info!(
+      "flush: column family '{}' file {} ({} bytes) at L{output_level}",
+      cfd.name,
+      t.file_number,
+      t.file_size,
+    );
+    let mut edit = VersionEdit::new();
+    edit.column_family = t.column_family;
+    let oldest_blob_file_number = t
+      .blob_files
+      .iter()
+      .map(|b| b.blob_file_number)
+      .min()
+      .unwrap_or(0);
+    // FIFO compaction's TTL counts from the flush.
+    let oldest_ancestor_time = if fifo {
+      cfd.options.clock.current_time()
     } else {
       0
+    };
+    edit.new_files.push((
+      output_level as i32,
+      FileMetaData::with_oldest_ancestor_time(
+        t.file_number,
+        t.file_size,
+        t.smallest,
+        t.largest,
+        oldest_blob_file_number,
+        oldest_ancestor_time,
+      ),
+    ));
+    edit.blob_file_additions = t.blob_files;
+    group.push((edit, tc));
+  }
+  if group.is_empty() {
+    let tc = column_families[&DEFAULT_COLUMN_FAMILY_ID]
+      .table_cache
+      .as_ref()
+      .expect("install_flushed_tables: no TableCache");
+    group.push((VersionEdit::new(), tc));
+  }
+  vs.log_and_apply_group(group)
+}
+
+// ── Open-time flush (reuse_logs=false) ─────────────────────────────────────
+//
+// When reuse_logs is false, replayed WAL data is flushed to SSTables during
+// Db::open so the next open doesn't need to replay it.  This is simpler than
+// the normal flush path: no WAL rotation, no imm, no background thread.
+
+/// Flush every non-empty memtable in `column_families` to a new SSTable
+/// during `Db::open` (used when `reuse_logs` is false), install the tables,
+/// and replace the flushed memtables with empty ones.
+fn flush_memtables_at_open(
+  vs: &mut VersionSet,
+  column_families: &mut BTreeMap<u32, ColumnFamilyData>,
+  fs: &dyn crate::env::FileSystem,
+  path: &std::path::Path,
+) -> Result<(), Error> {
+  let mut tables = Vec::new();
+  for (&id, cfd) in column_families.iter() {
+    if cfd.mem.approximate_memory_usage() == 0 {
+      continue;
     }
-  };
-  let mut edit = VersionEdit::new();
-  edit.new_files.push((output_level as i32, meta));
-  vs.log_and_apply(&mut edit, tc)?;
-  log::info!(
-    "open-time flush: file {} ({} bytes) at L{output_level}",
-    result.file_number,
-    result.file_size,
-  );
+    let sst_number = vs.next_file_number();
+    let version = vs.current(id).expect("flush_memtables_at_open: no Version");
+    let (output_level, compression) = plan_flush_output(cfd, &version);
+    let flush = MemtableFlush {
+      column_family: id,
+      options: Arc::clone(&cfd.options),
+      sst_number,
+      sst_path: path.join(format!("{sst_number:06}.ldb")),
+      old_mem: Arc::clone(&cfd.mem),
+      output_level,
+      compression,
+    };
+    tables.push(write_memtable_table(&flush, &mut || vs.next_file_number())?);
+  }
+  // Persist the new SSTables' directory entries before they enter the MANIFEST.
+  fs.sync_dir(path)?;
+  install_flushed_tables(vs, column_families, tables)?;
+  for cfd in column_families.values_mut() {
+    cfd.mem = Arc::new(Memtable::new(Arc::clone(&cfd.options.comparator)));
+  }
   Ok(())
 }
 
-/// Phase 2 (no lock held): iterate `old_mem`, write an SSTable, and return a
-/// `FlushResult` for `finish_flush` to install.
+/// Phase 2 (no lock held): write each sealed memtable to its SSTable and
+/// return a `FlushResult` for `finish_flush` to install.
 ///
-/// On error `old_mem` is dropped; data remains safe in the old WAL (not yet
-/// rotated away — `begin_flush` already activated the new WAL).
-fn write_flush(prep: FlushPrep, opts: &Options) -> Result<FlushResult, Error> {
+/// `next_file_number` allocates blob file numbers; like compaction's
+/// allocator it briefly takes the DB lock.
+///
+/// On error the memtables are dropped; data remains safe in the old WAL (not
+/// yet rotated away — `begin_flush` already activated the new WAL).
+fn write_flush(
+  prep: FlushPrep,
+  opts: &Options,
+  next_file_number: &mut dyn FnMut() -> u64,
+) -> Result<FlushResult, Error> {
   let FlushPrep {
-    sst_number,
-    sst_path,
-    old_mem,
+    memtables,
     new_log_number,
     last_sequence_at_rotation,
     old_log_path,
+    old_log_number,
+    pending_output: _,
   } = prep;
-  let fs = &*opts.file_system;
-  let file = fs.create_writable(&sst_path)?;
-  let mut builder = TableBuilder::new(
-    file,
-    opts.block_size,
-    opts.block_restart_interval,
-    opts.filter_policy.clone(),
-    opts.compression,
-    Arc::clone(&opts.comparator),
-  );
-  let mut smallest = Vec::new();
-  let mut largest = Vec::new();
-  {
-    let mut it = old_mem.iter();
-    it.seek_to_first();
-    while it.valid() {
-      // `it.key()` is the SSTable internal key (user_key || tag), borrowed
-      // from the iterator's scratch buffer — only the range bounds are copied.
-      if smallest.is_empty() {
-        smallest.extend_from_slice(it.key());
-      }
-      builder.add(it.key(), it.value())?;
-      largest.clear();
-      largest.extend_from_slice(it.key());
-      it.advance();
-    }
+  let mut tables = Vec::with_capacity(memtables.len());
+  for m in memtables {
+    tables.push(write_memtable_table(&m, next_file_number)?);
   }
-  let file_size = builder.finish()?;
-  // Persist the directory entries for the new SSTable and the new WAL
+  // Persist the directory entries for the new SSTables and the new WAL
   // (created in begin_flush) before finish_flush records them in the MANIFEST
   // and deletes the old WAL.
-  if let Some(dir) = sst_path.parent() {
-    fs.sync_dir(dir)?;
+  if let Some(dir) = old_log_path.parent() {
+    opts.file_system.sync_dir(dir)?;
   }
-  drop(old_mem);
-  let read_file = fs.open_random_access(&sst_path)?;
-  let table = Arc::new(Table::open(
-    read_file,
-    file_size,
-    opts.filter_policy.clone(),
-    opts.block_cache.clone(),
-    Arc::clone(&opts.comparator),
-  )?);
-  let smallest_user_key = ikey_user_key(&smallest).to_vec();
-  let largest_user_key = ikey_user_key(&largest).to_vec();
   Ok(FlushResult {
-    file_number: sst_number,
-    file_size,
-    smallest,
-    largest,
-    smallest_user_key,
-    largest_user_key,
-    table,
+    tables,
     new_log_number,
     last_sequence_at_rotation,
     old_log_path,
+    old_log_number,
   })
 }
 
-/// Phase 3 (under write lock): atomically record the new SST *and* new log
-/// number in the MANIFEST (`log_and_apply`), clear `imm`, and delete the old WAL.
+/// Phase 3 (under write lock): atomically record the new SSTs *and* new log
+/// number in the MANIFEST (`log_and_apply_group`), clear every `imm`, and
+/// delete the old WAL.
 ///
 /// `begin_flush` already activated the new WAL (swapping `state.log`), so
 /// `finish_flush` does not touch `state.log`.  `set_log_number` is called
-/// before `log_and_apply` so the MANIFEST record carries the correct log
-/// number — matching LevelDB's `MakeRoomForWrite`.
-fn finish_flush(
-  state: &mut DbState,
-  result: FlushResult,
-  opts: &Options,
-  tc: &crate::db::table_cache::TableCache,
-) -> Result<(), Error> {
-  // Register the new table in the cache before installing the version.
-  tc.insert(result.file_number, result.table);
-  let meta = FileMetaData::new(
-    result.file_number,
-    result.file_size,
-    result.smallest,
-    result.largest,
-  );
-  // Choose output level: try to push past L0 when there is no overlap and
-  // grandparent bytes are within bounds.  Falls back to L0 when the memtable
-  // range overlaps existing L0 files.
-  let output_level = {
-    let version = state.version_set.as_ref().map(|vs| vs.current());
-    match version {
-      Some(v) if !result.smallest_user_key.is_empty() => pick_level_for_memtable_output(
-        &v,
-        &result.smallest_user_key,
-        &result.largest_user_key,
-        opts.max_file_size as u64,
-      ),
-      _ => 0,
-    }
-  };
-  let mut edit = VersionEdit::new();
-  edit.new_files.push((output_level as i32, meta));
+/// before logging so the MANIFEST records carry the correct log number —
+/// matching LevelDB's `MakeRoomForWrite`.
+fn finish_flush(state: &mut DbState, result: FlushResult, opts: &Options) -> Result<(), Error> {
   let vs = state
     .version_set
     .as_mut()
@@ -2162,16 +4325,20 @@ fn finish_flush(
   // recording the current (higher) last_sequence would cause them to be skipped.
   vs.set_last_sequence(result.last_sequence_at_rotation);
   vs.set_log_number(result.new_log_number);
-  vs.log_and_apply(&mut edit, tc)?;
+  install_flushed_tables(vs, &state.column_families, result.tables)?;
   // state.log was already swapped to the new WAL in begin_flush — no swap needed here.
-  state.imm = None;
-  log::info!(
-    "flush complete: file {} ({} bytes) at L{output_level}",
-    result.file_number,
-    result.file_size,
-  );
-  // Best-effort delete — ignore errors (e.g. the path never existed on new DB).
-  let _ = opts.file_system.remove_file(&result.old_log_path);
+  for cfd in state.column_families.values_mut() {
+    cfd.imm = None;
+  }
+  log::info!("flush complete: log {:06}", result.new_log_number);
+  // Best-effort — ignore errors (e.g. the path never existed on new DB).
+  // While deletions are paused the old WAL stays; `delete_obsolete_files`
+  // disposes of it once they resume.
+  if state.file_deletions_paused == 0 {
+    if let Some(dir) = result.old_log_path.parent() {
+      crate::db::wal_manager::archive_or_delete_wal(dir, result.old_log_number, opts);
+    }
+  }
   Ok(())
 }
 
@@ -2182,13 +4349,10 @@ fn finish_flush(
 // the three-phase lock protocol (snapshot → I/O → install), seek-stat updates,
 // and MANIFEST installation via `VersionSet::log_and_apply`.
 
-const L0_SLOWDOWN_WRITES_TRIGGER: usize = 8;
-const L0_STOP_WRITES_TRIGGER: usize = 12;
-
 use crate::db::compaction::{
-  do_compaction, file_overlaps_range, get_range2, is_trivial_move, needs_compaction,
-  pick_compaction, pick_level_for_memtable_output, pick_range_compaction, Compaction,
-  CompactionOutput,
+  compression_for_level, conflicts_with_running, do_compaction, file_overlaps_range, get_range2,
+  is_trivial_move, needs_compaction, pick_compaction, pick_level_for_memtable_output,
+  pick_range_compaction, placement_limit, Compaction, CompactionClaim, CompactionOutput,
 };
 /// Extract the user-key prefix from an SSTable internal key.
 /// Canonical implementation lives in `table::format`; aliased here for the
@@ -2197,28 +4361,30 @@ use crate::table::format::user_key as ikey_user_key;
 // ── Seek-based compaction helpers ─────────────────────────────────────────────
 
 /// Decrement the seek budget of the blamed file and, if it hits zero, nominate
-/// it for seek-based compaction.
+/// it for seek-based compaction of its column family.
 ///
 /// Called under the DB lock after every SSTable `get` that had to probe more
 /// than one file.  Port of LevelDB `Version::UpdateStats`.
-fn update_stats(state: &mut DbState, stats: &crate::db::version::GetStats) {
+fn update_stats(cfd: &mut ColumnFamilyData, stats: &crate::db::version::GetStats) {
   use std::sync::atomic::Ordering;
   if let Some(ref file) = stats.seek_file {
     let prev = file.allowed_seeks.fetch_sub(1, Ordering::Relaxed);
-    if prev <= 1 && state.seek_compact_file.is_none() {
-      state.seek_compact_file = Some((Arc::clone(file), stats.seek_file_level));
-      state.compaction_needed = true;
+    if prev <= 1 && cfd.seek_compact_file.is_none() {
+      cfd.seek_compact_file = Some((Arc::clone(file), stats.seek_file_level));
+      cfd.compaction_needed = true;
     }
   }
 }
 
-/// Phase 3 (under lock): record deleted input files and new output files in a
-/// single `VersionEdit`, then call `log_and_apply`.
+/// Phase 3 (under lock): record deleted input files and new output files of
+/// column family `cf` in a single `VersionEdit`, then call `log_and_apply`.
 ///
-/// Also persists the compact-pointer update from `spec.edit` so the MANIFEST
-/// records the round-robin cursor advance.
+/// Also persists the compact-pointer update and the blob-file additions and
+/// garbage from `spec.edit`, so the MANIFEST records the round-robin cursor
+/// advance and the state of the blob files.
 fn install_compaction(
   state: &mut DbState,
+  cf: u32,
   spec: &Compaction,
   outputs: Vec<CompactionOutput>,
   tc: &crate::db::table_cache::TableCache,
@@ -2227,104 +4393,146 @@ fn install_compaction(
     .version_set
     .as_mut()
     .expect("install_compaction: no VersionSet");
-  let output_level = (spec.level + 1) as i32;
+  let output_level = spec.output_level as i32;
   let mut edit = VersionEdit::new();
+  edit.column_family = cf;
   for f in &spec.inputs[0] {
     edit.deleted_files.push((spec.level as i32, f.number));
   }
-  for f in &spec.inputs[1] {
-    edit.deleted_files.push((spec.level as i32 + 1, f.number));
+  for (level, files) in &spec.middle_inputs {
+    for f in files {
+      edit.deleted_files.push((*level as i32, f.number));
+    }
   }
+  for f in &spec.inputs[1] {
+    edit.deleted_files.push((output_level, f.number));
+  }
+  // The outputs hold data as old as the oldest input of known age.
+  let oldest_ancestor_time = spec
+    .all_input_files()
+    .map(|f| f.oldest_ancestor_time)
+    .filter(|&t| t != 0)
+    .min()
+    .unwrap_or(0);
   for out in outputs {
     // Register the output table in the cache before installing the version.
     tc.insert(out.file_number, out.table);
-    let meta = FileMetaData::new(out.file_number, out.file_size, out.smallest, out.largest);
+    let meta = FileMetaData::with_oldest_ancestor_time(
+      out.file_number,
+      out.file_size,
+      out.smallest,
+      out.largest,
+      out.oldest_blob_file_number,
+      oldest_ancestor_time,
+    );
     edit.new_files.push((output_level, meta));
   }
   // Persist the compact-pointer update so it survives a reopen.
   for (lvl, key) in &spec.edit.compact_pointers {
     edit.compact_pointers.push((*lvl, key.clone()));
   }
-  vs.set_last_sequence(state.last_sequence);
+  edit.blob_file_additions = spec.edit.blob_file_additions.clone();
+  edit.blob_file_garbages = spec.edit.blob_file_garbages.clone();
+  // The MANIFEST's last sequence is left alone: a compaction adds no entries,
+  // and raising it past writes still only in the WAL would make recovery skip
+  // them (see `finish_flush`).
   vs.log_and_apply(&mut edit, tc)?;
 
   // Clear seek_compact_file if the nominated file was removed by this compaction.
   let deleted_numbers: std::collections::HashSet<u64> =
     spec.all_input_files().map(|f| f.number).collect();
-  if let Some((ref f, _)) = state.seek_compact_file {
-    if deleted_numbers.contains(&f.number) {
-      state.seek_compact_file = None;
+  if let Some(cfd) = state.column_families.get_mut(&cf) {
+    if let Some((ref f, _)) = cfd.seek_compact_file {
+      if deleted_numbers.contains(&f.number) {
+        cfd.seek_compact_file = None;
+      }
     }
   }
 
   Ok(())
 }
 
-/// Run a single-level range compaction (`level` → `level + 1`) synchronously.
+/// Run a single-level range compaction of `level` into the next level (the
+/// base level, for L0) of column family `cf` synchronously.
 ///
-/// Uses the same three-phase lock protocol as `maybe_compact`.
+/// Uses the same three-phase lock protocol as `maybe_compact`, waiting first
+/// for the running compactions it conflicts with to finish.
 /// Returns `Ok(true)` if a compaction ran, `Ok(false)` if nothing to compact.
 fn compact_level_range(
-  path: &std::path::Path,
-  state: &Mutex<DbState>,
+  inner: &Arc<DbInner>,
+  cf: u32,
   opts: &Options,
   tc: &crate::db::table_cache::TableCache,
   level: usize,
   begin: Option<&[u8]>,
   end: Option<&[u8]>,
 ) -> Result<bool, Error> {
-  // Phase 1: snapshot.
-  let (version, oldest_snapshot) = {
-    let g = state.lock().unwrap();
-    let vs = match &g.version_set {
-      Some(vs) => vs,
-      None => return Ok(false),
-    };
-    let oldest = g
-      .snapshots
-      .keys()
-      .next()
-      .copied()
-      .unwrap_or(g.last_sequence);
-    (vs.current(), oldest)
-  };
-
+  let path = inner.persistence.as_ref().unwrap().dir.as_path();
   let cmp = &*opts.comparator;
-  let (level_inputs, next_inputs) = match pick_range_compaction(&version, level, begin, end, cmp) {
-    Some(p) => p,
-    None => return Ok(false),
-  };
+  // Phase 1: pick and claim.
+  let (mut spec, oldest_snapshot, running) = {
+    let mut g = inner.state.lock().unwrap();
+    loop {
+      let version = match g.current(cf) {
+        Some(v) => v,
+        None => return Ok(false),
+      };
+      let (level_inputs, next_inputs) =
+        match pick_range_compaction(&version, level, begin, end, cmp) {
+          Some(p) => p,
+          None => return Ok(false),
+        };
 
-  // Build a Compaction from the range-compaction inputs.
-  let mut spec = Compaction::new(level, Arc::clone(&version));
-  spec.inputs[0] = level_inputs;
-  spec.inputs[1] = next_inputs;
-  // No compact-pointer update for manual range compactions.
-  // Populate grandparents if level+2 < NUM_LEVELS.
-  if level + 2 < crate::db::version::NUM_LEVELS {
-    let (s, l) = get_range2(&spec.inputs[0], &spec.inputs[1], cmp);
-    spec.grandparents = version.overlapping_inputs(level + 2, ikey_user_key(&s), ikey_user_key(&l));
-  }
+      // Build a Compaction from the range-compaction inputs.
+      let mut spec = Compaction::new(level, Arc::clone(&version));
+      spec.inputs[0] = level_inputs;
+      spec.inputs[1] = next_inputs;
+      // No compact-pointer update for manual range compactions.
+      // Populate grandparents if there is a level below the output level.
+      if spec.output_level + 1 < version.num_levels() {
+        let (s, l) = get_range2(&spec.inputs[0], &spec.inputs[1], cmp);
+        spec.grandparents =
+          version.overlapping_inputs(spec.output_level + 1, ikey_user_key(&s), ikey_user_key(&l));
+      }
+
+      let cfd = g.column_family(cf)?;
+      if conflicts_with_running(&spec, &cfd.running_compactions, cmp) {
+        // Re-pick once the conflicting compaction has installed its outputs.
+        g = inner.write_condvar.wait(g).unwrap();
+        continue;
+      }
+      let oldest = g
+        .snapshots
+        .keys()
+        .next()
+        .copied()
+        .unwrap_or(g.last_sequence);
+      let running = RunningCompaction::claim(inner, &mut g, cf, &spec);
+      break (spec, oldest, running);
+    }
+  };
 
   // Phase 2: I/O (no lock; the allocator takes it briefly per output file).
-  let mut alloc = || {
-    let mut g = state.lock().unwrap();
+  let alloc = || {
+    let mut g = inner.state.lock().unwrap();
     g.version_set.as_mut().unwrap().next_file_number()
   };
-  let outputs = do_compaction(path, &mut alloc, &mut spec, oldest_snapshot, opts, tc)?;
+  let outputs = do_compaction(path, &alloc, &mut spec, oldest_snapshot, opts, tc)?;
 
   // Phase 3: install.
   {
-    let mut g = state.lock().unwrap();
-    install_compaction(&mut g, &spec, outputs, tc)?;
+    let mut g = inner.state.lock().unwrap();
+    install_compaction(&mut g, cf, &spec, outputs, tc)?;
   }
+  drop(running);
 
-  delete_obsolete_files(path, state, &*opts.file_system);
+  delete_obsolete_files(path, &inner.state, opts);
   Ok(true)
 }
 
-/// Execute a trivial-move compaction: move one file from `spec.level` to
-/// `spec.level + 1` via a MANIFEST update only — no I/O.
+/// Execute a trivial-move compaction: move one file of column family `cf`
+/// from `spec.level` to `spec.output_level` via a MANIFEST update only — no
+/// I/O.
 ///
 /// The `TableCache` entry is evicted (so the file will be re-opened under the
 /// new level number mapping on next access) and then re-inserted with the same
@@ -2333,6 +4541,7 @@ fn compact_level_range(
 /// Port of the trivial-move branch in LevelDB `DBImpl::BackgroundCompaction`.
 fn install_trivial_move(
   state: &mut DbState,
+  cf: u32,
   spec: &Compaction,
   tc: &crate::db::table_cache::TableCache,
 ) -> Result<(), Error> {
@@ -2343,10 +4552,11 @@ fn install_trivial_move(
 
   let file = Arc::clone(&spec.inputs[0][0]);
   let mut edit = VersionEdit::new();
+  edit.column_family = cf;
   edit.deleted_files.push((spec.level as i32, file.number));
   edit
     .new_files
-    .push((spec.level as i32 + 1, Arc::clone(&file)));
+    .push((spec.output_level as i32, Arc::clone(&file)));
   // Persist the compact-pointer update.
   for (lvl, key) in &spec.edit.compact_pointers {
     edit.compact_pointers.push((*lvl, key.clone()));
@@ -2357,7 +4567,7 @@ fn install_trivial_move(
   // access finds it in the cache instead of re-opening from disk.
   let table_arc = tc.get_or_open(file.number, file.file_size).ok();
 
-  vs.set_last_sequence(state.last_sequence);
+  // As in `install_compaction`, the MANIFEST's last sequence is left alone.
   vs.log_and_apply(&mut edit, tc)?;
 
   // Re-insert so the file stays warm even though log_and_apply evicted it.
@@ -2366,89 +4576,160 @@ fn install_trivial_move(
   }
 
   // Clear seek_compact_file if this file was the nominee.
-  if let Some((ref scf, _)) = state.seek_compact_file {
-    if scf.number == file.number {
-      state.seek_compact_file = None;
+  if let Some(cfd) = state.column_families.get_mut(&cf) {
+    if let Some((ref scf, _)) = cfd.seek_compact_file {
+      if scf.number == file.number {
+        cfd.seek_compact_file = None;
+      }
     }
   }
 
   Ok(())
 }
 
-/// Run synchronous compaction for any level that needs it (score ≥ 1.0).
+/// Run one compaction for the first column family with a level that needs it
+/// (score ≥ 1.0), a file nominated by seek statistics, or blob files to
+/// collect, and that does not conflict with the compactions already running.
 ///
 /// Uses the same three-phase lock protocol as flush:
-///   1. Snapshot current version under lock.
+///   1. Pick the compaction and claim its inputs under the lock.
 ///   2. Run I/O (MergingIterator + SSTable writes) without the lock.
 ///   3. Install the result (VersionEdit + log_and_apply) under the lock.
 ///
 /// Errors are silently ignored — a failed compaction does not affect
 /// correctness; the triggering level will be retried on the next call.
-fn maybe_compact(
-  path: &std::path::Path,
-  state: &Mutex<DbState>,
-  opts: &Options,
-  tc: &crate::db::table_cache::TableCache,
-) {
-  // Phase 1: snapshot.
-  let (version, oldest_snapshot, compact_pointer, seek_compact_file) = {
+fn maybe_compact(inner: &Arc<DbInner>) {
+  let path = inner.persistence.as_ref().unwrap().dir.as_path();
+  let state = &inner.state;
+  // Phase 1: pick and claim.
+  let (cf, opts, tc, spec, oldest_snapshot, running, reserved) = {
     let mut g = state.lock().unwrap();
-    let (current, compact_ptr) = match &g.version_set {
-      Some(vs) => (vs.current(), vs.compact_pointer().clone()),
-      None => return,
+    let Some(vs) = &g.version_set else {
+      return;
+    };
+    let picked = g.column_families.iter().find_map(|(&id, cfd)| {
+      let current = vs.current(id)?;
+      if !needs_compaction(&current, cfd.seek_compact_file.is_some(), &cfd.options) {
+        return None;
+      }
+      let spec = pick_compaction(
+        &current,
+        vs.compact_pointer(id)?,
+        &cfd.options,
+        cfd.seek_compact_file.as_ref(),
+        &cfd.running_compactions,
+      )?;
+      Some((id, spec))
+    });
+    let Some((cf, spec)) = picked else {
+      if g
+        .column_families
+        .values()
+        .any(|cfd| !cfd.running_compactions.is_empty())
+      {
+        // Everything needing compaction is held by running compactions;
+        // wait for one of them to finish.
+        g.compactions_blocked = true;
+      } else {
+        for cfd in g.column_families.values_mut() {
+          cfd.compaction_needed = false;
+        }
+      }
+      return;
     };
+    // A FIFO merge into L0 must be numbered before the files of flushes, which
+    // hold newer entries: L0 is read newest file number first.  Wait for the
+    // flush in progress; later ones are numbered after the output number
+    // reserved below.
+    let l0_merge = spec.level == 0 && spec.output_level == 0 && !spec.deletion_compaction;
+    if l0_merge && g.pending_flush.is_some() {
+      g.compactions_blocked = true;
+      return;
+    }
     let oldest = g
       .snapshots
       .keys()
       .next()
       .copied()
       .unwrap_or(g.last_sequence);
+    let cfd = g
+      .column_families
+      .get_mut(&cf)
+      .expect("maybe_compact: column family");
     // Clear the compaction_needed flag; it will be re-set if another seek miss
     // occurs before we finish.
-    g.compaction_needed = false;
-    let seek = g.seek_compact_file.clone();
-    (current, oldest, compact_ptr, seek)
-  };
+    cfd.compaction_needed = false;
+    let opts = Arc::clone(&cfd.options);
+    let tc = cfd
+      .table_cache
+      .clone()
+      .expect("maybe_compact: no TableCache");
+
+    // Trivial move: single file, no L+1 overlap, acceptable grandparent overlap.
+    // No I/O needed — just a MANIFEST update, made while still holding the
+    // lock so no other compaction can pick the file meanwhile.
+    if is_trivial_move(&spec, &opts) {
+      let file = &spec.inputs[0][0];
+      log::info!(
+        "trivial move: file {} ({} bytes) L{}→L{}",
+        file.number,
+        file.file_size,
+        spec.level,
+        spec.output_level,
+      );
+      if let Err(e) = install_trivial_move(&mut g, cf, &spec, &tc) {
+        log::warn!("trivial move failed: {e}");
+      }
+      return;
+    }
 
-  if !needs_compaction(&version, seek_compact_file.is_some()) {
-    return;
-  }
+    // FIFO deletion: likewise only a MANIFEST update.  The files are removed
+    // by `delete_obsolete_files` once no version uses them.
+    if spec.deletion_compaction {
+      log::info!(
+        "fifo: dropping {} files ({} bytes) from L{}",
+        spec.inputs[0].len(),
+        spec.inputs[0].iter().map(|f| f.file_size).sum::<u64>(),
+        spec.level,
+      );
+      if let Err(e) = install_compaction(&mut g, cf, &spec, Vec::new(), &tc) {
+        log::warn!("fifo deletion failed: {e}");
+      }
+      return;
+    }
 
-  let mut spec = match pick_compaction(&version, &compact_pointer, opts, seek_compact_file.as_ref())
-  {
-    Some(s) => s,
-    None => return,
+    let running = RunningCompaction::claim(inner, &mut g, cf, &spec);
+    // Allocated after the claim, so its pending output protects the file.
+    let reserved = if l0_merge {
+      g.version_set.as_mut().unwrap().next_file_number()
+    } else {
+      0
+    };
+    // Let another worker look for a compaction that can run alongside.
+    maybe_schedule_compaction(inner, &mut g);
+    (cf, opts, tc, spec, oldest, running, reserved)
   };
-
-  // Trivial move: single file, no L+1 overlap, acceptable grandparent overlap.
-  // No I/O needed — just a MANIFEST update.
-  if is_trivial_move(&spec, opts) {
-    let file = &spec.inputs[0][0];
-    log::info!(
-      "trivial move: file {} ({} bytes) L{}→L{}",
-      file.number,
-      file.file_size,
-      spec.level,
-      spec.level + 1,
-    );
-    let mut g = state.lock().unwrap();
-    if let Err(e) = install_trivial_move(&mut g, &spec, tc) {
-      log::warn!("trivial move failed: {e}");
-    }
-    drop(g);
-    delete_obsolete_files(path, state, &*opts.file_system);
-    return;
-  }
+  let opts = &*opts;
+  let tc = &tc;
+  let mut spec = spec;
 
   // Phase 2: I/O (no lock; the allocator takes it briefly per output file).
-  let mut alloc = || {
-    let mut g = state.lock().unwrap();
-    g.version_set.as_mut().unwrap().next_file_number()
+  let reserved = AtomicU64::new(reserved);
+  let alloc = || match reserved.swap(0, Ordering::Relaxed) {
+    0 => {
+      let mut g = state.lock().unwrap();
+      g.version_set.as_mut().unwrap().next_file_number()
+    }
+    number => number,
   };
-  let outputs = match do_compaction(path, &mut alloc, &mut spec, oldest_snapshot, opts, tc) {
+  let outputs = match do_compaction(path, &alloc, &mut spec, oldest_snapshot, opts, tc) {
     Ok(o) => o,
     Err(e) => {
-      log::warn!("compaction L{}→L{} failed: {e}", spec.level, spec.level + 1);
+      log::warn!(
+        "compaction L{}→L{} failed: {e}",
+        spec.level,
+        spec.output_level
+      );
       return;
     }
   };
@@ -2456,31 +4737,82 @@ fn maybe_compact(
   // Phase 3: install.
   {
     let mut g = state.lock().unwrap();
-    if let Err(e) = install_compaction(&mut g, &spec, outputs, tc) {
+    if let Err(e) = install_compaction(&mut g, cf, &spec, outputs, tc) {
       log::warn!(
         "install_compaction L{}→L{} failed: {e}",
         spec.level,
-        spec.level + 1
+        spec.output_level
       );
-      return;
     }
   }
-
-  delete_obsolete_files(path, state, &*opts.file_system);
+  drop(running);
 }
 
-// ── DeleteObsoleteFiles ───────────────────────────────────────────────────────
+/// Holds a [`CompactionClaim`] in its column family's `running_compactions`
+/// from the end of a compaction's first phase until dropped.  Must be dropped
+/// without the lock held.
+///
+/// Besides keeping other compactions off its inputs and output range, the
+/// claim stops ingestion (which waits for the family's claims to be
+/// released) and flushes from placing files where its outputs will go.
+struct RunningCompaction<'a> {
+  inner: &'a DbInner,
+  cf: u32,
+  id: u64,
+  /// From `DbState::capture_pending_output`, protecting the outputs.
+  pending_output: u64,
+}
 
-/// Recognised kinds of database files, parsed from their filenames.
+impl<'a> RunningCompaction<'a> {
+  /// Claim the inputs and output range of `spec`, in column family `cf`, and
+  /// protect the files it is about to write.
+  fn claim(inner: &'a DbInner, g: &mut DbState, cf: u32, spec: &Compaction) -> Self {
+    let id = g.next_compaction_id;
+    g.next_compaction_id += 1;
+    let cfd = g
+      .column_families
+      .get_mut(&cf)
+      .expect("RunningCompaction: column family");
+    let claim = CompactionClaim::new(id, spec, &*cfd.options.comparator);
+    cfd.running_compactions.push(claim);
+    RunningCompaction {
+      inner,
+      cf,
+      id,
+      pending_output: g.capture_pending_output(),
+    }
+  }
+}
+
+impl Drop for RunningCompaction<'_> {
+  fn drop(&mut self) {
+    let mut g = self.inner.state.lock().unwrap();
+    // The column family may have been dropped meanwhile.
+    if let Some(cfd) = g.column_families.get_mut(&self.cf) {
+      cfd.running_compactions.retain(|c| c.id != self.id);
+    }
+    g.release_pending_output(self.pending_output);
+    g.compactions_blocked = false;
+    drop(g);
+    // Wake ingestions and manual compactions waiting for the claim.
+    self.inner.write_condvar.notify_all();
+  }
+}
+
+// ── DeleteObsoleteFiles ───────────────────────────────────────────────────────
+
+/// Recognised kinds of database files, parsed from their filenames.
 enum FileKind {
   Log,
   Manifest,
   Table,
+  Blob,
   Current,
   Lock,
   /// Temporary file from an interrupted atomic-rename (e.g. `CURRENT` update).
   /// Always safe to delete: a live temp file only exists inside
-  /// `write_current_file`, which never runs concurrently with GC.
+  /// `write_current_file`, which never runs concurrently with GC, or while
+  /// `Db::ingest_external_file`, which pauses file deletions, stages copies.
   Temp,
 }
 
@@ -2509,6 +4841,10 @@ fn parse_db_filename(name: &str) -> Option<(u64, FileKind)> {
     let n = stem.parse().ok()?;
     return Some((n, FileKind::Table));
   }
+  if let Some(stem) = name.strip_suffix(".blob") {
+    let n = stem.parse().ok()?;
+    return Some((n, FileKind::Blob));
+  }
   if let Some(stem) = name.strip_suffix(".dbtmp") {
     let n = stem.parse().ok()?;
     return Some((n, FileKind::Temp));
@@ -2516,6 +4852,25 @@ fn parse_db_filename(name: &str) -> Option<(u64, FileKind)> {
   None
 }
 
+/// Guard returned by [`Db::pause_file_deletions`].
+pub(crate) struct FileDeletionPause<'a> {
+  db: &'a Db,
+}
+
+impl Drop for FileDeletionPause<'_> {
+  fn drop(&mut self) {
+    let inner = &self.db.inner;
+    let resumed = {
+      let mut g = inner.state.lock().unwrap();
+      g.file_deletions_paused -= 1;
+      g.file_deletions_paused == 0
+    };
+    if let (true, Some(p)) = (resumed, &inner.persistence) {
+      delete_obsolete_files(&p.dir, &inner.state, &inner.options);
+    }
+  }
+}
+
 /// Move a file from the database directory into a `lost/` subdirectory.
 ///
 /// Used by [`Db::repair`] to archive processed WAL files and corrupt SSTables
@@ -2529,29 +4884,36 @@ fn archive_file(db_path: &std::path::Path, filename: &str, fs: &dyn crate::env::
 /// Delete database files that are no longer referenced by any live `Version`.
 ///
 /// Matches LevelDB's `DBImpl::RemoveObsoleteFiles`:
-///   1. Take the lock briefly to collect the live file set and watermarks.
+///   1. Take the lock briefly to collect the live file set and watermarks,
+///      including the smallest pending output of a running flush or compaction.
 ///   2. Release the lock.
 ///   3. Enumerate the directory and delete any obsolete files.
 ///
 /// Errors (missing dir, unlink failures) are silently ignored — GC is
 /// best-effort and failure does not affect correctness.
-fn delete_obsolete_files(
-  path: &std::path::Path,
-  state: &Mutex<DbState>,
-  fs: &dyn crate::env::FileSystem,
-) {
+fn delete_obsolete_files(path: &std::path::Path, state: &Mutex<DbState>, opts: &Options) {
   use std::collections::HashSet;
+  let fs = &*opts.file_system;
 
   // Step 1: snapshot live-file info under the lock, then release it.
-  let (live_tables, log_number, manifest_number) = {
+  let (live_tables, log_number, manifest_number, min_pending_output) = {
     let state = state.lock().unwrap();
     let vs = match &state.version_set {
       Some(vs) => vs,
       None => return, // in-memory DB — nothing to clean up
     };
+    if state.file_deletions_paused > 0 {
+      return; // resumed by the last `FileDeletionPause` to drop
+    }
     let mut live = HashSet::new();
     vs.add_live_files(&mut live);
-    (live, vs.log_number(), vs.manifest_number())
+    let min_pending_output = state.pending_outputs.keys().next().copied();
+    (
+      live,
+      vs.log_number(),
+      vs.manifest_number(),
+      min_pending_output.unwrap_or(u64::MAX),
+    )
   };
 
   // Step 2: enumerate the directory and delete obsolete files.
@@ -2564,11 +4926,18 @@ fn delete_obsolete_files(
       let keep = match kind {
         FileKind::Log => number >= log_number,
         FileKind::Manifest => number >= manifest_number,
-        FileKind::Table => live_tables.contains(&number),
+        FileKind::Table | FileKind::Blob => {
+          live_tables.contains(&number) || number >= min_pending_output
+        }
         FileKind::Current | FileKind::Lock => true,
         FileKind::Temp => false,
       };
-      if !keep {
+      if keep {
+        continue;
+      }
+      if matches!(kind, FileKind::Log) {
+        crate::db::wal_manager::archive_or_delete_wal(path, number, opts);
+      } else {
         log::debug!("deleting obsolete file: {name}");
         let _ = fs.remove_file(&path.join(name));
       }
@@ -2580,7 +4949,10 @@ fn delete_obsolete_files(
 
 #[cfg(test)]
 mod tests {
-  use crate::{Db, Error, Options, ReadOptions, WriteBatch, WriteOptions};
+  use crate::{
+    ColumnFamilyDescriptor, Db, DbIter, Error, FlushOptions, Options, ReadOptions, WalRecoveryMode,
+    WalUpdate, WriteBatch, WriteOptions,
+  };
   use serial_test::serial;
 
   // ── parse_db_filename tests (port of LevelDB filename_test.cc: Parse) ─────────
@@ -2593,6 +4965,7 @@ mod tests {
       ("100.log", 100, FileKind::Log),
       ("0.log", 0, FileKind::Log),
       ("0.ldb", 0, FileKind::Table),
+      ("000012.blob", 12, FileKind::Blob),
       ("CURRENT", 0, FileKind::Current),
       ("LOCK", 0, FileKind::Lock),
       ("MANIFEST-2", 2, FileKind::Manifest),
@@ -3361,6 +5734,298 @@ mod tests {
     }
   }
 
+  #[test]
+  fn paused_file_deletions_keep_compacted_inputs_until_resumed() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    for round in 0..2 {
+      db.put(b"k", format!("v{round}").as_bytes()).unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+    }
+    let tables = || -> Vec<String> {
+      let mut names: Vec<String> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|n| n.ends_with(".ldb"))
+        .collect();
+      names.sort();
+      names
+    };
+    let before = tables();
+
+    let pause = db.pause_file_deletions();
+    db.compact_range(None, None).unwrap();
+    let paused = tables();
+    assert!(before.iter().all(|name| paused.contains(name)));
+
+    drop(pause);
+    let resumed = tables();
+    assert!(before.iter().any(|name| !resumed.contains(name)));
+    assert_eq!(db.get(b"k").unwrap(), b"v1");
+  }
+
+  /// Write `entries` to an SSTable at `path` with an `SstFileWriter`.
+  fn write_external_file(path: &std::path::Path, entries: &[(&[u8], &[u8])]) {
+    let mut w = crate::SstFileWriter::create(path, &Options::default()).unwrap();
+    for &(k, v) in entries {
+      w.put(k, v).unwrap();
+    }
+    w.finish().unwrap();
+  }
+
+  #[test]
+  fn ingest_external_file_into_empty_db_goes_to_bottom_level() {
+    let ext = tempfile::tempdir().unwrap();
+    let sst = ext.path().join("bulk.sst");
+    write_external_file(&sst, &[(b"a", b"1"), (b"b", b"2"), (b"c", b"3")]);
+
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      db.ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+        .unwrap();
+      assert_eq!(
+        db.get_property("leveldb.num-files-at-level6").as_deref(),
+        Some("1")
+      );
+      assert_eq!(db.latest_sequence_number(), 1);
+      assert_eq!(db.get(b"b").unwrap(), b"2");
+      db.put(b"b", b"newer").unwrap();
+    }
+    // The original is left in place; the copy and the sequence number
+    // survive a reopen.
+    assert!(sst.exists());
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"1");
+    assert_eq!(db.get(b"b").unwrap(), b"newer");
+    assert_eq!(db.latest_sequence_number(), 2);
+    let mut it = db.new_iterator(&ReadOptions::default()).unwrap();
+    it.seek_to_first();
+    let keys: Vec<Vec<u8>> = it.forward().map(|r| r.unwrap().0).collect();
+    assert_eq!(keys, [b"a", b"b", b"c"]);
+  }
+
+  #[test]
+  fn ingest_external_file_overlapping_memtable_flushes_first() {
+    let ext = tempfile::tempdir().unwrap();
+    let sst = ext.path().join("bulk.sst");
+    write_external_file(&sst, &[(b"k1", b"ingested"), (b"k3", b"ingested")]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    db.put(b"k1", b"old").unwrap();
+    db.put(b"k2", b"old").unwrap();
+    let snap = db.get_snapshot();
+
+    let no_flush = crate::IngestExternalFileOptions {
+      allow_blocking_flush: false,
+    };
+    assert!(matches!(
+      db.ingest_external_file(&[&sst], &no_flush),
+      Err(Error::InvalidArgument(_))
+    ));
+    db.ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+      .unwrap();
+
+    assert_eq!(db.get(b"k1").unwrap(), b"ingested");
+    assert_eq!(db.get(b"k2").unwrap(), b"old");
+    assert_eq!(db.get(b"k3").unwrap(), b"ingested");
+    let at_snap = ReadOptions {
+      snapshot: Some(&snap),
+      ..ReadOptions::default()
+    };
+    assert_eq!(db.get_with_options(&at_snap, b"k1").unwrap(), b"old");
+    assert!(matches!(
+      db.get_with_options(&at_snap, b"k3"),
+      Err(Error::NotFound)
+    ));
+    drop(snap);
+
+    // Compaction merges the ingested file with the flushed one.
+    db.compact_range(None, None).unwrap();
+    assert_eq!(db.get(b"k1").unwrap(), b"ingested");
+    assert_eq!(db.get(b"k2").unwrap(), b"old");
+  }
+
+  #[test]
+  fn ingest_external_file_keeps_earlier_wal_writes_recoverable() {
+    let ext = tempfile::tempdir().unwrap();
+    let sst = ext.path().join("bulk.sst");
+    write_external_file(&sst, &[(b"m", b"ingested")]);
+
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      // Does not overlap the file, so stays in the memtable and the WAL.
+      db.put(b"a", b"unflushed").unwrap();
+      db.ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+        .unwrap();
+      assert_eq!(db.latest_sequence_number(), 2);
+    }
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"unflushed");
+    assert_eq!(db.get(b"m").unwrap(), b"ingested");
+    assert_eq!(db.latest_sequence_number(), 2);
+    db.put(b"m", b"newer").unwrap();
+    assert_eq!(db.get(b"m").unwrap(), b"newer");
+  }
+
+  #[test]
+  fn failed_ingestion_leaves_no_wal_marker() {
+    use crate::env::WritableFile;
+    use crate::env::{FileLock, FileSystem, PosixFileSystem, RandomAccessFile, SequentialFile};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// Fails renames into table files while `fail` is set.
+    struct FailingInstall {
+      fail: AtomicBool,
+    }
+    impl FileSystem for FailingInstall {
+      fn open_sequential(&self, path: &Path) -> Result<Box<dyn SequentialFile>, Error> {
+        PosixFileSystem.open_sequential(path)
+      }
+      fn open_random_access(&self, path: &Path) -> Result<Arc<dyn RandomAccessFile>, Error> {
+        PosixFileSystem.open_random_access(path)
+      }
+      fn open_appendable(&self, path: &Path) -> Result<Box<dyn WritableFile>, Error> {
+        PosixFileSystem.open_appendable(path)
+      }
+      fn create_writable(&self, path: &Path) -> Result<Box<dyn WritableFile>, Error> {
+        PosixFileSystem.create_writable(path)
+      }
+      fn file_size(&self, path: &Path) -> Result<u64, Error> {
+        PosixFileSystem.file_size(path)
+      }
+      fn file_exists(&self, path: &Path) -> bool {
+        PosixFileSystem.file_exists(path)
+      }
+      fn modified_time(&self, path: &Path) -> Result<u64, Error> {
+        PosixFileSystem.modified_time(path)
+      }
+      fn rename(&self, src: &Path, dst: &Path) -> Result<(), Error> {
+        if self.fail.load(Ordering::SeqCst) && dst.extension().is_some_and(|e| e == "ldb") {
+          return Err(Error::IoError(std::io::Error::other(
+            "injected rename failure",
+          )));
+        }
+        PosixFileSystem.rename(src, dst)
+      }
+      fn remove_file(&self, path: &Path) -> Result<(), Error> {
+        PosixFileSystem.remove_file(path)
+      }
+      fn create_dir_all(&self, path: &Path) -> Result<(), Error> {
+        PosixFileSystem.create_dir_all(path)
+      }
+      fn remove_dir(&self, path: &Path) -> Result<(), Error> {
+        PosixFileSystem.remove_dir(path)
+      }
+      fn sync_dir(&self, path: &Path) -> Result<(), Error> {
+        PosixFileSystem.sync_dir(path)
+      }
+      fn children(&self, path: &Path) -> Result<Vec<String>, Error> {
+        PosixFileSystem.children(path)
+      }
+      fn lock_file(&self, path: &Path) -> Result<Box<dyn FileLock>, Error> {
+        PosixFileSystem.lock_file(path)
+      }
+    }
+
+    let ext = tempfile::tempdir().unwrap();
+    let sst = ext.path().join("bulk.sst");
+    write_external_file(&sst, &[(b"m", b"ingested")]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let fs = Arc::new(FailingInstall {
+      fail: AtomicBool::new(true),
+    });
+    let mut opts = create_options();
+    opts.file_system = Arc::clone(&fs) as Arc<dyn FileSystem>;
+    {
+      let db = Db::open(dir.path(), opts.clone()).unwrap();
+      db.put(b"a", b"1").unwrap();
+      assert!(db
+        .ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+        .is_err());
+      assert_eq!(db.latest_sequence_number(), 1);
+      // The sequence number the ingestion would have used goes to the next
+      // write, and the WAL holds no ingestion for it.
+      db.put(b"b", b"2").unwrap();
+      assert_eq!(db.latest_sequence_number(), 2);
+      assert_eq!(updates_since(&db, 1), vec![(1, 1), (2, 1)]);
+      fs.fail.store(false, Ordering::SeqCst);
+      db.ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+        .unwrap();
+      assert_eq!(db.latest_sequence_number(), 3);
+    }
+    let db = Db::open(dir.path(), opts).unwrap();
+    assert_eq!(db.latest_sequence_number(), 3);
+    assert_eq!(db.get(b"b").unwrap(), b"2");
+    assert_eq!(db.get(b"m").unwrap(), b"ingested");
+  }
+
+  #[test]
+  fn ingestion_sequence_survives_a_lost_wal_marker() {
+    let ext = tempfile::tempdir().unwrap();
+    let sst = ext.path().join("bulk.sst");
+    write_external_file(&sst, &[(b"m", b"ingested")]);
+
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      db.ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+        .unwrap();
+    }
+    // A crash after the install but before the marker reached the WAL.
+    for name in std::fs::read_dir(dir.path()).unwrap() {
+      let path = name.unwrap().path();
+      if path.extension().is_some_and(|e| e == "log") {
+        std::fs::File::create(&path).unwrap();
+      }
+    }
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert_eq!(db.latest_sequence_number(), 1);
+    db.put(b"m", b"newer").unwrap();
+    assert_eq!(db.get(b"m").unwrap(), b"newer");
+  }
+
+  #[test]
+  fn ingest_external_file_rejects_invalid_files() {
+    let ext = tempfile::tempdir().unwrap();
+    let (a, b) = (ext.path().join("a.sst"), ext.path().join("b.sst"));
+    write_external_file(&a, &[(b"a", b"1"), (b"m", b"1")]);
+    write_external_file(&b, &[(b"k", b"2"), (b"z", b"2")]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    let opts = crate::IngestExternalFileOptions::default();
+    assert!(matches!(
+      db.ingest_external_file(&[&a, &b], &opts),
+      Err(Error::InvalidArgument(_))
+    ));
+
+    // A table of another database has non-zero sequence numbers.
+    db.put(b"x", b"1").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    let table = std::fs::read_dir(dir.path())
+      .unwrap()
+      .map(|e| e.unwrap().path())
+      .find(|p| p.extension().is_some_and(|e| e == "ldb"))
+      .unwrap();
+    let copy = ext.path().join("db.sst");
+    std::fs::copy(table, &copy).unwrap();
+    assert!(matches!(
+      db.ingest_external_file(&[&copy], &opts),
+      Err(Error::InvalidArgument(_))
+    ));
+
+    assert!(matches!(
+      Db::default().ingest_external_file(&[&a], &opts),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
   #[test]
   fn obsolete_ldb_file_deleted_after_reopen_with_no_reference() {
     // Manually place an .ldb file with a number that is NOT in any Version,
@@ -3459,7 +6124,7 @@ mod tests {
   #[test]
   #[serial(fd)]
   fn compaction_reduces_l0_file_count() {
-    // Write enough data to produce ≥ L0_COMPACTION_TRIGGER flushes, which
+    // Write enough data to produce ≥ level0_file_num_compaction_trigger flushes, which
     // should trigger a compaction that moves files from L0 to L1.
     let dir = tempfile::tempdir().unwrap();
     let db = Db::open(dir.path(), tiny_options()).unwrap();
@@ -3476,10 +6141,10 @@ mod tests {
     // be below the compaction trigger.
     let l0_count = {
       let g = db.inner.state.lock().unwrap();
-      g.version_set.as_ref().unwrap().current().files_at(0).len()
+      g.current(0).unwrap().files_at(0).len()
     };
     assert!(
-      l0_count < crate::db::version::L0_COMPACTION_TRIGGER,
+      l0_count < Options::default().level0_file_num_compaction_trigger,
       "L0 should have been compacted; found {l0_count} files"
     );
   }
@@ -3570,10 +6235,8 @@ mod tests {
     }
     let deep_count: usize = {
       let g = db.inner.state.lock().unwrap();
-      let cur = g.version_set.as_ref().unwrap().current();
-      (1..crate::db::version::NUM_LEVELS)
-        .map(|l| cur.files_at(l).len())
-        .sum()
+      let cur = g.current(0).unwrap();
+      (1..cur.num_levels()).map(|l| cur.files_at(l).len()).sum()
     };
     assert!(
       deep_count >= 1,
@@ -3635,7 +6298,7 @@ mod tests {
     db.compact_range(None, None).unwrap();
     let l0_count = {
       let g = db.inner.state.lock().unwrap();
-      g.version_set.as_ref().unwrap().current().files_at(0).len()
+      g.current(0).unwrap().files_at(0).len()
     };
     assert_eq!(l0_count, 0, "L0 should be empty after full compact_range");
   }
@@ -4351,23 +7014,23 @@ mod tests {
     use crate::db::version_edit::FileMetaData;
     use crate::table::format::make_internal_key;
 
-    let mut v =
-      crate::db::version::Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
-    let mut file_number = 10u64;
-    for &(level, size) in level_sizes {
+    let mut v = crate::db::version::Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
+    for (file_number, &(level, size)) in (10u64..).zip(level_sizes) {
       let ikey = make_internal_key(format!("a{file_number}").as_bytes(), file_number, 1);
       let meta = FileMetaData::new(file_number, size, ikey.clone(), ikey);
       v.push_file_for_test(level, meta);
-      file_number += 1;
     }
     v
   }
 
   #[test]
   fn finalize_l0_score_from_file_count() {
-    // 4 L0 files / L0_COMPACTION_TRIGGER (4) = score 1.0.
+    // 4 L0 files / level0_file_num_compaction_trigger (4) = score 1.0.
     let mut v = version_with_files(&[(0, 0), (0, 0), (0, 0), (0, 0)]);
-    crate::db::version::finalize(&mut v);
+    crate::db::version::finalize(&mut v, &Options::default());
     assert!(
       (v.compaction_score - 1.0).abs() < 1e-9,
       "expected score 1.0, got {}",
@@ -4381,7 +7044,7 @@ mod tests {
     // 10 MiB at L1 = score exactly 1.0.
     let ten_mib = 10 * 1_048_576u64;
     let mut v = version_with_files(&[(1, ten_mib)]);
-    crate::db::version::finalize(&mut v);
+    crate::db::version::finalize(&mut v, &Options::default());
     assert!(
       (v.compaction_score - 1.0).abs() < 1e-9,
       "expected score 1.0, got {}",
@@ -4405,11 +7068,141 @@ mod tests {
       (0, 0), // 8 L0 files → score 2.0
       (1, five_mib),
     ]);
-    crate::db::version::finalize(&mut v);
+    crate::db::version::finalize(&mut v, &Options::default());
     assert!(v.compaction_score >= 2.0 - 1e-9);
     assert_eq!(v.compaction_level, 0);
   }
 
+  #[test]
+  fn finalize_scores_against_level_options() {
+    let opts = Options {
+      level0_file_num_compaction_trigger: 2,
+      max_bytes_for_level_base: 1_048_576,
+      max_bytes_for_level_multiplier: 4.0,
+      ..Options::default()
+    };
+    // 1 L0 file / 2 = 0.5; 3 MiB at L2 / (1 MiB × 4) = 0.75.
+    let mut v = version_with_files(&[(0, 0), (2, 3 * 1_048_576)]);
+    crate::db::version::finalize(&mut v, &opts);
+    assert_eq!(v.compaction_level, 2);
+    assert!((v.compaction_score - 0.75).abs() < 1e-9);
+
+    // The last level is never scored.
+    let mut v = crate::db::version::Version::new(
+      2,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
+    crate::db::version::finalize(&mut v, &opts);
+    assert_eq!(v.compaction_scores, vec![(0, 0.0)]);
+  }
+
+  #[test]
+  fn dynamic_level_targets_derive_from_the_last_level() {
+    use crate::db::version::level_targets;
+    const MIB: u64 = 1_048_576;
+    let mut opts = Options {
+      max_bytes_for_level_base: MIB,
+      max_bytes_for_level_multiplier: 4.0,
+      ..Options::default()
+    };
+    let v = version_with_files(&[(4, 6 * MIB), (6, 64 * MIB)]);
+    assert_eq!(level_targets(&v, &opts).0, 1);
+
+    // 64 MiB at L6: 16 MiB at L5, 4 MiB at L4 and 1 MiB at the base, L3.
+    opts.level_compaction_dynamic_level_bytes = true;
+    let (base_level, targets) = level_targets(&v, &opts);
+    assert_eq!(base_level, 3);
+    let mib = |t: f64| t / MIB as f64;
+    assert_eq!(targets[1..3], [f64::MAX, f64::MAX]);
+    assert_eq!(
+      targets[3..].iter().map(|&t| mib(t)).collect::<Vec<_>>(),
+      vec![1.0, 4.0, 16.0, 64.0]
+    );
+    let mut v = v;
+    crate::db::version::finalize(&mut v, &opts);
+    assert_eq!((v.base_level, v.compaction_level), (3, 4));
+    assert!((v.compaction_score - 1.5).abs() < 1e-9);
+
+    // Little data: the last level is the base, targeted at the level base.
+    let v = version_with_files(&[(6, MIB / 10)]);
+    let (base_level, targets) = level_targets(&v, &opts);
+    assert_eq!((base_level, mib(targets[6])), (6, 1.0));
+
+    // An empty tree compacts L0 straight into the last level.
+    let v = version_with_files(&[(0, MIB)]);
+    assert_eq!(level_targets(&v, &opts).0, 6);
+  }
+
+  #[test]
+  fn dynamic_level_bytes_flushes_to_l0_and_compacts_into_the_last_level() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(
+      dir.path(),
+      Options {
+        create_if_missing: true,
+        level_compaction_dynamic_level_bytes: true,
+        ..Options::default()
+      },
+    )
+    .unwrap();
+    let levels = |db: &Db| {
+      let g = db.inner.state.lock().unwrap();
+      let v = g.current(super::DEFAULT_COLUMN_FAMILY_ID).unwrap();
+      (0..v.num_levels())
+        .map(|level| v.files_at(level).len())
+        .collect::<Vec<_>>()
+    };
+    db.put(b"k", b"v").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    assert_eq!(levels(&db), vec![1, 0, 0, 0, 0, 0, 0]);
+
+    db.compact_range(None, None).unwrap();
+    assert_eq!(levels(&db), vec![0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+  }
+
+  #[test]
+  fn reopen_validates_num_levels_against_the_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let opts = |num_levels| Options {
+      create_if_missing: true,
+      num_levels,
+      ..Options::default()
+    };
+    assert!(matches!(
+      Db::open(dir.path(), opts(1)),
+      Err(Error::InvalidArgument(_))
+    ));
+    let deepest = {
+      let db = Db::open(dir.path(), opts(7)).unwrap();
+      db.put(b"k", b"v").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+      db.compact_range(None, None).unwrap();
+      let g = db.inner.state.lock().unwrap();
+      let v = g.current(super::DEFAULT_COLUMN_FAMILY_ID).unwrap();
+      (0..v.num_levels()).rfind(|&l| v.num_files(l) > 0).unwrap()
+    };
+    assert!(
+      deepest > 1,
+      "the file should sit below L1, found L{deepest}"
+    );
+
+    // Too few levels to hold the file.
+    assert!(matches!(
+      Db::open(dir.path(), opts(deepest)),
+      Err(Error::InvalidArgument(_))
+    ));
+    // Fewer levels than before, but enough.
+    let db = Db::open(dir.path(), opts(deepest + 1)).unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+    db.put(b"k2", b"v2").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    db.compact_range(None, None).unwrap();
+    drop(db);
+    let db = Db::open(dir.path(), opts(deepest + 1)).unwrap();
+    assert_eq!(db.get(b"k2").unwrap(), b"v2");
+  }
+
   #[test]
   #[serial(fd)]
   fn compact_pointer_persists_across_reopen() {
@@ -4418,7 +7211,7 @@ mod tests {
     //
     // Strategy: write the same small key range many times so each flush overlaps
     // with the previous L0 file, forcing all flushes to land at L0.  Once L0
-    // reaches L0_COMPACTION_TRIGGER (4) a real L0→L1 compaction fires and the
+    // reaches level0_file_num_compaction_trigger (4) a real L0→L1 compaction fires and the
     // compact_pointer is recorded in the MANIFEST.
     let dir = tempfile::tempdir().unwrap();
     {
@@ -4441,12 +7234,14 @@ mod tests {
     let vs = crate::db::version_set::VersionSet::recover(
       dir.path(),
       false,
-      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+      std::sync::Arc::new(Options::default()),
+      &[],
       &crate::env::PosixFileSystem,
+      false,
     )
     .unwrap();
     // At least one level should have a non-empty compact_pointer (L0 or L1).
-    let has_pointer = vs.compact_pointer().iter().any(|p| !p.is_empty());
+    let has_pointer = vs.compact_pointer(0).unwrap().iter().any(|p| !p.is_empty());
     assert!(
       has_pointer,
       "at least one compact_pointer should be set after compaction"
@@ -4485,7 +7280,7 @@ mod tests {
     // With a completely empty version the output lands at L2 (MAX_MEM_COMPACT_LEVEL).
     let (l0, l2) = {
       let g = db.inner.state.lock().unwrap();
-      let cur = g.version_set.as_ref().unwrap().current();
+      let cur = g.current(0).unwrap();
       (cur.files_at(0).len(), cur.files_at(2).len())
     };
     assert_eq!(
@@ -4569,7 +7364,7 @@ mod tests {
 
     let (l1_bytes, l2_files) = {
       let g = db.inner.state.lock().unwrap();
-      let cur = g.version_set.as_ref().unwrap().current();
+      let cur = g.current(0).unwrap();
       let l1b: u64 = cur.files_at(1).iter().map(|f| f.file_size).sum();
       (l1b, cur.files_at(2).len())
     };
@@ -4680,65 +7475,378 @@ mod tests {
     }
   }
 
-  // ── Gap 5: seek-based compaction tests ────────────────────────────────────
-
   #[test]
-  fn seek_stats_decrements_allowed_seeks() {
-    // Build a two-file L0 scenario and verify that the first probed file has
-    // its allowed_seeks decremented when more than one file is consulted.
-    use crate::db::version::Version;
-    use crate::db::version_edit::FileMetaData;
-    use crate::table::format::make_internal_key;
-    use std::sync::atomic::Ordering;
-    use std::sync::Arc;
+  #[serial(fd)]
+  fn concurrent_compactions_and_subcompactions_keep_levels_disjoint() {
+    use crate::table::format::user_key;
 
-    // Two L0 files with overlapping ranges so any key lookup in the overlap
-    // must consult file 0 first (newest-first), then file 1.
-    let mk = |num: u64, small: &[u8], large: &[u8]| -> Arc<FileMetaData> {
-      FileMetaData::new(
-        num,
-        4096,
-        make_internal_key(small, 10, 1u8),
-        make_internal_key(large, 1, 1u8),
-      )
+    let dir = tempfile::tempdir().unwrap();
+    let opts = Options {
+      create_if_missing: true,
+      write_buffer_size: 8 * 1024,
+      max_file_size: 4 * 1024,
+      compression: crate::CompressionType::NoCompression,
+      max_background_jobs: 4,
+      max_subcompactions: 4,
+      ..Options::default()
     };
+    let value = |round: u32, k: u32| format!("{round}-{k}-{}", "v".repeat(100)).into_bytes();
+    let db = Db::open(dir.path(), opts.clone()).unwrap();
+    for round in 0u32..3 {
+      for k in 0u32..600 {
+        db.put(format!("key{k:05}").as_bytes(), value(round, k))
+          .unwrap();
+      }
+    }
+    db.compact_range(None, None).unwrap();
 
-    let mut v = Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
-    // Newest file (file 1) covers "a".."m"; older file (file 2) covers "g".."z".
-    // L0 is newest-first so file 1 is index 0.
-    v.push_file_for_test(0, mk(1, b"a", b"m"));
-    v.push_file_for_test(0, mk(2, b"g", b"z"));
-    let version = Arc::new(v);
+    let version = db
+      .inner
+      .state
+      .lock()
+      .unwrap()
+      .current(super::DEFAULT_COLUMN_FAMILY_ID)
+      .unwrap();
+    assert_eq!(version.num_files(0), 0);
+    for level in 1..version.num_levels() {
+      for pair in version.files_at(level).windows(2) {
+        assert!(
+          user_key(&pair[0].largest) < user_key(&pair[1].smallest),
+          "overlapping files at L{level}"
+        );
+      }
+    }
+    drop(db);
 
-    // We can't do a real table lookup without actual SSTable files, so instead
-    // directly call the get stats logic by checking GetStats construction.
-    // Simulate: we probed file 1 first (found nothing) then file 2.
-    // Blame = file 1 (first consulted, not where we found the answer).
-    let before = version.files_at(0)[0].allowed_seeks.load(Ordering::Relaxed);
-    assert!(before >= 100, "initial allowed_seeks should be ≥ 100");
+    let db = Db::open(dir.path(), opts).unwrap();
+    for k in 0u32..600 {
+      assert_eq!(
+        db.get(format!("key{k:05}").as_bytes()).unwrap(),
+        value(2, k)
+      );
+    }
+  }
 
-    // Simulate the update_stats call that Db::get would make.
-    use crate::db::version::GetStats;
-    let stats = GetStats {
+  /// `(entered, released)`: set when a compaction reaches the filter, and
+  /// to let it continue.
+  type Gate = std::sync::Arc<(std::sync::Mutex<(bool, bool)>, std::sync::Condvar)>;
+
+  /// Parks every compaction in its first entry until the gate is released.
+  struct GateFilterFactory(Gate);
+
+  struct GateFilter(Gate);
+
+  impl crate::compaction_filter::CompactionFilter for GateFilter {
+    fn filter(
+      &mut self,
+      _level: usize,
+      _key: &[u8],
+      _value: &[u8],
+      _vtype: u8,
+    ) -> crate::CompactionDecision {
+      let (lock, cvar) = &*self.0;
+      let mut g = lock.lock().unwrap();
+      g.0 = true;
+      cvar.notify_all();
+      while !g.1 {
+        g = cvar.wait(g).unwrap();
+      }
+      crate::CompactionDecision::Keep
+    }
+    fn name(&self) -> &str {
+      "test.GateFilter"
+    }
+  }
+
+  impl crate::compaction_filter::CompactionFilterFactory for GateFilterFactory {
+    fn create_compaction_filter(&self) -> Box<dyn crate::compaction_filter::CompactionFilter> {
+      Box::new(GateFilter(std::sync::Arc::clone(&self.0)))
+    }
+    fn name(&self) -> &str {
+      "test.GateFilterFactory"
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn flush_completes_while_compaction_is_running() {
+    let gate: Gate = Default::default();
+    let dir = tempfile::tempdir().unwrap();
+    let opts = Options {
+      create_if_missing: true,
+      max_background_jobs: 1,
+      compaction_filter_factory: Some(std::sync::Arc::new(GateFilterFactory(gate.clone()))),
+      ..Options::default()
+    };
+    let db = Db::open(dir.path(), opts).unwrap();
+    // Overlapping flushes pile up in L0 until a compaction starts and parks.
+    let mut round = 0;
+    while !gate.0.lock().unwrap().0 {
+      assert!(round < 10, "no compaction started");
+      db.put(b"a", format!("{round}").as_bytes()).unwrap();
+      db.put(b"z", b"z").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+      round += 1;
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    // The flush thread is not held up by the parked compaction.
+    db.put(b"a", b"during").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"during");
+    assert!(db.inner.state.lock().unwrap().pending_flush.is_none());
+
+    let (lock, cvar) = &*gate;
+    lock.lock().unwrap().1 = true;
+    cvar.notify_all();
+    drop(db);
+  }
+
+  /// Wait until no compaction is running or needed.
+  fn wait_for_compactions(db: &Db) {
+    loop {
+      {
+        let g = db.inner.state.lock().unwrap();
+        if g.bg_jobs_running == 0 && g.bg_jobs_queued == 0 && !g.compaction_pending() {
+          return;
+        }
+      }
+      std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn universal_compaction_merges_sorted_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    let opts = || Options {
+      create_if_missing: true,
+      compaction_style: crate::CompactionStyle::Universal,
+      ..Options::default()
+    };
+    let put_round = |db: &Db, round: u32| {
+      for i in 0..100u32 {
+        db.put(format!("k{i:03}").as_bytes(), format!("{round}").as_bytes())
+          .unwrap();
+      }
+      db.flush(&FlushOptions::default()).unwrap();
+    };
+    {
+      let db = Db::open(dir.path(), opts()).unwrap();
+      put_round(&db, 0);
+      let snap = db.get_snapshot();
+      for round in 1..12 {
+        put_round(&db, round);
+      }
+      wait_for_compactions(&db);
+
+      let g = db.inner.state.lock().unwrap();
+      let v = g.current(super::DEFAULT_COLUMN_FAMILY_ID).unwrap();
+      let runs = v.files_at(0).len()
+        + (1..v.num_levels())
+          .filter(|&l| !v.files_at(l).is_empty())
+          .count();
+      assert!(
+        runs <= Options::default().level0_file_num_compaction_trigger,
+        "{runs} sorted runs"
+      );
+      assert!((1..v.num_levels()).any(|l| !v.files_at(l).is_empty()));
+      drop(g);
+
+      let at_snap = ReadOptions {
+        snapshot: Some(&snap),
+        ..ReadOptions::default()
+      };
+      for i in 0..100u32 {
+        let key = format!("k{i:03}");
+        assert_eq!(db.get(key.as_bytes()).unwrap(), b"11");
+        assert_eq!(db.get_with_options(&at_snap, key.as_bytes()).unwrap(), b"0");
+      }
+    }
+    let db = Db::open(dir.path(), opts()).unwrap();
+    assert_eq!(db.get(b"k042").unwrap(), b"11");
+  }
+
+  /// Numbers of the `.ldb` files in `dir`.
+  fn table_files_on_disk(dir: &std::path::Path) -> std::collections::BTreeSet<u64> {
+    std::fs::read_dir(dir)
+      .unwrap()
+      .filter_map(|e| {
+        let name = e.unwrap().file_name().into_string().unwrap();
+        name.strip_suffix(".ldb")?.parse().ok()
+      })
+      .collect()
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn fifo_compaction_drops_oldest_files_over_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let opts = || {
+      let mut opts = Options {
+        create_if_missing: true,
+        compaction_style: crate::CompactionStyle::Fifo,
+        compression: crate::CompressionType::NoCompression,
+        ..Options::default()
+      };
+      opts.compaction_options_fifo.max_table_files_size = 65_000;
+      opts
+    };
+    {
+      let db = Db::open(dir.path(), opts()).unwrap();
+      // Ten files of about 20 KB each: only the newest three fit.
+      for round in 0..10u32 {
+        for i in 0..20u32 {
+          db.put(format!("r{round:02}-{i:02}").as_bytes(), [b'v'; 1000])
+            .unwrap();
+        }
+        db.flush(&FlushOptions::default()).unwrap();
+        wait_for_compactions(&db);
+      }
+
+      let g = db.inner.state.lock().unwrap();
+      let v = g.current(super::DEFAULT_COLUMN_FAMILY_ID).unwrap();
+      assert!((1..v.num_levels()).all(|l| v.files_at(l).is_empty()));
+      let live: std::collections::BTreeSet<u64> = v.files_at(0).iter().map(|f| f.number).collect();
+      let total: u64 = v.files_at(0).iter().map(|f| f.file_size).sum();
+      assert!(total <= 65_000, "{total} bytes left");
+      assert!(v.files_at(0).iter().all(|f| f.oldest_ancestor_time > 0));
+      drop(g);
+      assert_eq!(table_files_on_disk(dir.path()), live);
+
+      assert!(matches!(db.get(b"r00-00"), Err(Error::NotFound)));
+      assert!(matches!(db.get(b"r06-19"), Err(Error::NotFound)));
+      assert_eq!(db.get(b"r09-00").unwrap(), vec![b'v'; 1000]);
+    }
+    let db = Db::open(dir.path(), opts()).unwrap();
+    assert!(matches!(db.get(b"r05-00"), Err(Error::NotFound)));
+    assert_eq!(db.get(b"r07-00").unwrap(), vec![b'v'; 1000]);
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn fifo_compaction_drops_files_past_ttl_by_the_options_clock() {
+    let dir = tempfile::tempdir().unwrap();
+    let clock = std::sync::Arc::new(crate::env::ManualClock::new(1_000));
+    let mut opts = Options {
+      create_if_missing: true,
+      compaction_style: crate::CompactionStyle::Fifo,
+      clock: std::sync::Arc::clone(&clock) as std::sync::Arc<dyn crate::Clock>,
+      ..Options::default()
+    };
+    opts.compaction_options_fifo.ttl = Some(std::time::Duration::from_secs(100));
+    let db = Db::open(dir.path(), opts).unwrap();
+    for key in [b"a", b"b"] {
+      db.put(key, b"v").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+    }
+    wait_for_compactions(&db);
+    assert_eq!(db.get(b"a").unwrap(), b"v");
+
+    // Past the TTL of both files; the next flush schedules their removal.
+    clock.advance(101);
+    db.put(b"c", b"v").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    wait_for_compactions(&db);
+    assert!(matches!(db.get(b"a"), Err(Error::NotFound)));
+    assert!(matches!(db.get(b"b"), Err(Error::NotFound)));
+    assert_eq!(db.get(b"c").unwrap(), b"v");
+    let g = db.inner.state.lock().unwrap();
+    let v = g.current(super::DEFAULT_COLUMN_FAMILY_ID).unwrap();
+    assert_eq!(v.files_at(0).len(), 1);
+    assert_eq!(v.files_at(0)[0].oldest_ancestor_time, 1_101);
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn fifo_compaction_merges_small_l0_files_keeping_tombstones() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut opts = Options {
+      create_if_missing: true,
+      compaction_style: crate::CompactionStyle::Fifo,
+      compression: crate::CompressionType::NoCompression,
+      write_buffer_size: 64 * 1024,
+      ..Options::default()
+    };
+    opts.compaction_options_fifo.allow_compaction = true;
+    let db = Db::open(dir.path(), opts).unwrap();
+    // An older file too large to be merged.
+    db.put(b"gone", b"old").unwrap();
+    db.put(b"big", [b'x'; 100 * 1024]).unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    // Small files, the oldest deleting a key of the large one.
+    db.delete(b"gone").unwrap();
+    for round in 0..6u32 {
+      db.put(format!("k{round}").as_bytes(), b"v").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+    }
+    wait_for_compactions(&db);
+
+    let g = db.inner.state.lock().unwrap();
+    let v = g.current(super::DEFAULT_COLUMN_FAMILY_ID).unwrap();
+    let l0 = v.files_at(0);
+    assert!(
+      l0.len() <= Options::default().level0_file_num_compaction_trigger,
+      "{} files",
+      l0.len()
+    );
+    // The merged files sort before the large one.
+    assert!(l0.last().unwrap().file_size > 100 * 1024);
+    drop(g);
+
+    assert!(matches!(db.get(b"gone"), Err(Error::NotFound)));
+    for round in 0..6u32 {
+      assert_eq!(db.get(format!("k{round}").as_bytes()).unwrap(), b"v");
+    }
+  }
+
+  // ── Gap 5: seek-based compaction tests ────────────────────────────────────
+
+  #[test]
+  fn seek_stats_decrements_allowed_seeks() {
+    // Build a two-file L0 scenario and verify that the first probed file has
+    // its allowed_seeks decremented when more than one file is consulted.
+    use crate::db::version::Version;
+    use crate::db::version_edit::FileMetaData;
+    use crate::table::format::make_internal_key;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    // Two L0 files with overlapping ranges so any key lookup in the overlap
+    // must consult file 0 first (newest-first), then file 1.
+    let mk = |num: u64, small: &[u8], large: &[u8]| -> Arc<FileMetaData> {
+      FileMetaData::new(
+        num,
+        4096,
+        make_internal_key(small, 10, 1u8),
+        make_internal_key(large, 1, 1u8),
+      )
+    };
+
+    let mut v = Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
+    // Newest file (file 1) covers "a".."m"; older file (file 2) covers "g".."z".
+    // L0 is newest-first so file 1 is index 0.
+    v.push_file_for_test(0, mk(1, b"a", b"m"));
+    v.push_file_for_test(0, mk(2, b"g", b"z"));
+    let version = Arc::new(v);
+
+    // We can't do a real table lookup without actual SSTable files, so instead
+    // directly call the get stats logic by checking GetStats construction.
+    // Simulate: we probed file 1 first (found nothing) then file 2.
+    // Blame = file 1 (first consulted, not where we found the answer).
+    let before = version.files_at(0)[0].allowed_seeks.load(Ordering::Relaxed);
+    assert!(before >= 100, "initial allowed_seeks should be ≥ 100");
+
+    // Simulate the update_stats call that Db::get would make.
+    use crate::db::version::GetStats;
+    let stats = GetStats {
       seek_file: Some(Arc::clone(&version.files_at(0)[0])),
       seek_file_level: 0,
     };
-    let mut ds = super::DbState {
-      last_sequence: 0,
-      log: None,
-      mem: Arc::new(crate::memtable::Memtable::default()),
-      imm: None,
-      version_set: None,
-      snapshots: Default::default(),
-      writers: Default::default(),
-      next_writer_id: 0,
-      completed: Default::default(),
-      seek_compact_file: None,
-      compaction_needed: false,
-      background_scheduled: false,
-      background_error: None,
-      pending_flush: None,
-    };
+    let mut ds = super::ColumnFamilyData::new("default", Arc::new(Options::default()), None);
     super::update_stats(&mut ds, &stats);
     let after = version.files_at(0)[0].allowed_seeks.load(Ordering::Relaxed);
     assert_eq!(
@@ -4772,7 +7880,10 @@ mod tests {
       )
     };
 
-    let mut v = Version::new(std::sync::Arc::new(crate::comparator::BytewiseComparator));
+    let mut v = Version::new(
+      7,
+      std::sync::Arc::new(crate::comparator::BytewiseComparator),
+    );
     v.push_file_for_test(0, mk(1));
     let version = Arc::new(v);
 
@@ -4787,22 +7898,7 @@ mod tests {
       seek_file: Some(Arc::clone(&file)),
       seek_file_level: 0,
     };
-    let mut ds = super::DbState {
-      last_sequence: 0,
-      log: None,
-      mem: Arc::new(crate::memtable::Memtable::default()),
-      imm: None,
-      version_set: None,
-      snapshots: Default::default(),
-      writers: Default::default(),
-      next_writer_id: 0,
-      completed: Default::default(),
-      seek_compact_file: None,
-      compaction_needed: false,
-      background_scheduled: false,
-      background_error: None,
-      pending_flush: None,
-    };
+    let mut ds = super::ColumnFamilyData::new("default", Arc::new(Options::default()), None);
     super::update_stats(&mut ds, &stats);
 
     assert!(
@@ -4851,7 +7947,7 @@ mod tests {
       use std::sync::atomic::Ordering;
       let g = db.inner.state.lock().unwrap();
       if let Some(vs) = &g.version_set {
-        let cur = vs.current();
+        let cur = vs.current(0).unwrap();
         if let Some(f) = cur.files_at(0).first() {
           f.allowed_seeks.store(1, Ordering::Relaxed);
         }
@@ -4891,6 +7987,51 @@ mod tests {
     db.flush(&crate::FlushOptions::default()).unwrap();
   }
 
+  #[serial(fd)]
+  #[test]
+  fn flush_below_l0_is_compressed_for_its_level() {
+    use crate::table::reader::Table;
+    use crate::CompressionType::{NoCompression, Snappy};
+    use std::sync::Arc;
+    let dir = tempfile::tempdir().unwrap();
+    let opts = Options {
+      compression_per_level: vec![NoCompression, NoCompression, Snappy],
+      ..create_options()
+    };
+    let db = Db::open(dir.path(), opts).unwrap();
+    for i in 0..100u32 {
+      db.put(format!("key{i:03}"), [b'x'; 100]).unwrap();
+    }
+    db.flush(&crate::FlushOptions::default()).unwrap();
+    // Nothing overlaps the memtable, so the flush skips L0 and L1.
+    assert_eq!(
+      db.get_property("leveldb.num-files-at-level2").as_deref(),
+      Some("1")
+    );
+    let meta = {
+      let g = db.inner.state.lock().unwrap();
+      let version = g.current(0).unwrap();
+      Arc::clone(&version.files_at(2)[0])
+    };
+    let path = dir.path().join(format!("{:06}.ldb", meta.number));
+    let table = Table::open(
+      db.inner
+        .options
+        .file_system
+        .open_random_access(&path)
+        .unwrap(),
+      meta.file_size,
+      None,
+      None,
+      Arc::clone(&db.inner.options.comparator),
+    )
+    .unwrap();
+    let types = table.data_block_compression_types().unwrap();
+    assert!(!types.is_empty());
+    // 0x01 is Snappy; an L0 table would be uncompressed.
+    assert!(types.iter().all(|&t| t == 0x01), "{types:?}");
+  }
+
   #[serial(fd)]
   #[test]
   fn flush_moves_data_to_sstable() {
@@ -5114,31 +8255,24 @@ mod tests {
     };
     let db = Db {
       inner: std::sync::Arc::new(crate::DbInner {
-        state: std::sync::Mutex::new(crate::DbState {
-          last_sequence: 0,
-          log: None,
-          mem: std::sync::Arc::new(crate::memtable::Memtable::new(std::sync::Arc::clone(
-            &opts.comparator,
-          ))),
-          imm: None,
-          version_set: None,
-          snapshots: std::collections::BTreeMap::new(),
-          writers: std::collections::VecDeque::new(),
-          next_writer_id: 0,
-          completed: std::collections::HashMap::new(),
-          seek_compact_file: None,
-          compaction_needed: false,
-          background_scheduled: false,
-          background_error: None,
-          pending_flush: None,
-        }),
+        state: std::sync::Mutex::new(crate::DbState::new(
+          0,
+          None,
+          None,
+          std::collections::BTreeMap::from([(
+            0,
+            crate::ColumnFamilyData::new("default", std::sync::Arc::new(opts.clone()), None),
+          )]),
+        )),
         write_condvar: std::sync::Condvar::new(),
         bg_condvar: std::sync::Condvar::new(),
+        flush_condvar: std::sync::Condvar::new(),
         options: opts,
         persistence: None,
         shutting_down: std::sync::atomic::AtomicBool::new(false),
+        read_only: false,
       }),
-      bg_thread: None,
+      bg_threads: Vec::new(),
     };
     db.put(b"a", b"1").unwrap();
     db.put(b"b", b"2").unwrap();
@@ -5266,70 +8400,621 @@ mod tests {
     }
   }
 
-  // ── CompactionFilter tests ──────────────────────────────────────────
-
-  /// A filter that removes keys starting with "drop_".
-  struct PrefixDropFilter;
+  // ── WalRecoveryMode tests ─────────────────────────────────────────────
 
-  impl crate::compaction_filter::CompactionFilter for PrefixDropFilter {
-    fn filter(
-      &mut self,
-      _level: usize,
-      key: &[u8],
-      _value: &[u8],
-      _vtype: u8,
-    ) -> crate::CompactionDecision {
-      if key.starts_with(b"drop_") {
-        crate::CompactionDecision::Remove
-      } else {
-        crate::CompactionDecision::Keep
-      }
-    }
-    fn name(&self) -> &str {
-      "test.PrefixDropFilter"
+  /// Write `a`, a 40 KiB `b` spanning two WAL blocks and `c` to a new
+  /// database in `dir`, and return the path of its WAL.
+  fn write_three_wal_records(dir: &std::path::Path) -> std::path::PathBuf {
+    {
+      let db = Db::open(dir, create_options()).unwrap();
+      db.put(b"a", b"1").unwrap();
+      db.put(b"b", vec![b'2'; 40 * 1024]).unwrap();
+      db.put(b"c", b"3").unwrap();
     }
+    std::fs::read_dir(dir)
+      .unwrap()
+      .map(|e| e.unwrap().path())
+      .find(|p| p.extension().is_some_and(|e| e == "log"))
+      .unwrap()
   }
 
-  struct PrefixDropFilterFactory;
-
-  impl crate::compaction_filter::CompactionFilterFactory for PrefixDropFilterFactory {
-    fn create_compaction_filter(&self) -> Box<dyn crate::compaction_filter::CompactionFilter> {
-      Box::new(PrefixDropFilter)
-    }
-    fn name(&self) -> &str {
-      "test.PrefixDropFilterFactory"
-    }
+  /// Flip a byte inside the payload of the WAL record holding `b`.
+  fn corrupt_middle_wal_record(log: &std::path::Path) {
+    let mut bytes = std::fs::read(log).unwrap();
+    // `a`'s record is 7 header + 17 batch bytes; skip into `b`'s value.
+    bytes[24 + 7 + 100] ^= 0xff;
+    std::fs::write(log, bytes).unwrap();
   }
 
-  /// A filter that uppercases all values.
-  struct UppercaseFilter;
+  fn open_with_recovery_mode(dir: &std::path::Path, mode: WalRecoveryMode) -> Result<Db, Error> {
+    Db::open(
+      dir,
+      Options {
+        wal_recovery_mode: mode,
+        ..Options::default()
+      },
+    )
+  }
 
-  impl crate::compaction_filter::CompactionFilter for UppercaseFilter {
-    fn filter(
-      &mut self,
-      _level: usize,
-      _key: &[u8],
-      value: &[u8],
-      _vtype: u8,
-    ) -> crate::CompactionDecision {
-      let upper: Vec<u8> = value.iter().map(|b| b.to_ascii_uppercase()).collect();
-      if upper != value {
-        crate::CompactionDecision::ChangeValue(upper)
-      } else {
-        crate::CompactionDecision::Keep
-      }
-    }
-    fn name(&self) -> &str {
-      "test.UppercaseFilter"
+  #[serial(fd)]
+  #[test]
+  fn wal_recovery_modes_on_corrupted_middle_record() {
+    for mode in [
+      WalRecoveryMode::TolerateCorruptedTailRecords,
+      WalRecoveryMode::AbsoluteConsistency,
+    ] {
+      let dir = tempfile::tempdir().unwrap();
+      corrupt_middle_wal_record(&write_three_wal_records(dir.path()));
+      let err = open_with_recovery_mode(dir.path(), mode).err().unwrap();
+      assert!(
+        matches!(&err, Error::Corruption(m) if m.contains("000001.log") && m.contains("offset 24")),
+        "{mode:?}: {err:?}"
+      );
     }
-  }
 
-  struct UppercaseFilterFactory;
+    let dir = tempfile::tempdir().unwrap();
+    corrupt_middle_wal_record(&write_three_wal_records(dir.path()));
+    let db = open_with_recovery_mode(dir.path(), WalRecoveryMode::PointInTimeRecovery).unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"1");
+    assert!(matches!(db.get(b"b"), Err(Error::NotFound)));
+    assert!(matches!(db.get(b"c"), Err(Error::NotFound)));
 
-  impl crate::compaction_filter::CompactionFilterFactory for UppercaseFilterFactory {
-    fn create_compaction_filter(&self) -> Box<dyn crate::compaction_filter::CompactionFilter> {
-      Box::new(UppercaseFilter)
-    }
+    let dir = tempfile::tempdir().unwrap();
+    corrupt_middle_wal_record(&write_three_wal_records(dir.path()));
+    let db = open_with_recovery_mode(dir.path(), WalRecoveryMode::SkipAnyCorruptedRecords).unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"1");
+    assert!(matches!(db.get(b"b"), Err(Error::NotFound)));
+    assert_eq!(db.get(b"c").unwrap(), b"3");
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn wal_recovery_modes_on_torn_tail() {
+    let torn = |dir: &std::path::Path| {
+      let log = write_three_wal_records(dir);
+      let len = std::fs::metadata(&log).unwrap().len();
+      let f = std::fs::OpenOptions::new().write(true).open(&log).unwrap();
+      f.set_len(len - 1).unwrap();
+    };
+    for mode in [
+      WalRecoveryMode::TolerateCorruptedTailRecords,
+      WalRecoveryMode::PointInTimeRecovery,
+      WalRecoveryMode::SkipAnyCorruptedRecords,
+    ] {
+      let dir = tempfile::tempdir().unwrap();
+      torn(dir.path());
+      let db = open_with_recovery_mode(dir.path(), mode).unwrap();
+      assert_eq!(db.get(b"b").unwrap().len(), 40 * 1024, "{mode:?}");
+      assert!(matches!(db.get(b"c"), Err(Error::NotFound)), "{mode:?}");
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    torn(dir.path());
+    let err = open_with_recovery_mode(dir.path(), WalRecoveryMode::AbsoluteConsistency)
+      .err()
+      .unwrap();
+    assert!(matches!(err, Error::Corruption(_)), "{err:?}");
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn writes_after_point_in_time_recovery_survive_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    corrupt_middle_wal_record(&write_three_wal_records(dir.path()));
+    {
+      let db = open_with_recovery_mode(dir.path(), WalRecoveryMode::PointInTimeRecovery).unwrap();
+      db.put(b"d", b"4").unwrap();
+    }
+    let db = open_with_recovery_mode(dir.path(), WalRecoveryMode::PointInTimeRecovery).unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"1");
+    assert_eq!(db.get(b"d").unwrap(), b"4");
+  }
+
+  // ── WAL archive / get_updates_since tests ─────────────────────────────
+
+  /// The batches of `db.get_updates_since(seq)`, unwrapped.
+  fn updates_since_batches(db: &Db, seq: u64) -> Vec<(u64, WriteBatch)> {
+    db.get_updates_since(seq)
+      .unwrap()
+      .map(|r| match r.unwrap() {
+        WalUpdate::Batch(seq, batch) => (seq, batch),
+        WalUpdate::Ingestion(seq) => panic!("unexpected ingestion at {seq}"),
+      })
+      .collect()
+  }
+
+  fn updates_since(db: &Db, seq: u64) -> Vec<(u64, u32)> {
+    updates_since_batches(db, seq)
+      .into_iter()
+      .map(|(seq, batch)| (seq, batch.count()))
+      .collect()
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn get_updates_since_reads_archived_and_live_wals() {
+    let dir = tempfile::tempdir().unwrap();
+    let opts = Options {
+      wal_ttl: Some(std::time::Duration::from_secs(3600)),
+      ..create_options()
+    };
+    {
+      let db = Db::open(dir.path(), opts.clone()).unwrap();
+      db.put(b"a", b"1").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+      let mut batch = WriteBatch::new();
+      batch.put(b"b", b"2");
+      batch.put(b"c", b"3");
+      db.write(&WriteOptions::default(), batch).unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+      db.put(b"d", b"4").unwrap();
+
+      assert!(dir.path().join("archive").join("000001.log").exists());
+      assert_eq!(updates_since(&db, 0), [(1, 1), (2, 2), (4, 1)]);
+      // The batch holding sequence 3 starts at 2.
+      assert_eq!(updates_since(&db, 3), [(2, 2), (4, 1)]);
+      assert_eq!(updates_since(&db, 5), []);
+    }
+    // The archive outlives a reopen.
+    let db = Db::open(dir.path(), opts).unwrap();
+    let mut it = db.get_updates_since(1).unwrap();
+    assert!(matches!(it.next(), Some(Ok(WalUpdate::Batch(1, b))) if b.count() == 1));
+    drop(db);
+    Db::destroy(dir.path()).unwrap();
+    assert!(!dir.path().exists());
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn get_updates_since_reports_purged_sequence() {
+    for opts in [
+      create_options(),
+      Options {
+        wal_size_limit: Some(0),
+        ..create_options()
+      },
+    ] {
+      let dir = tempfile::tempdir().unwrap();
+      let db = Db::open(dir.path(), opts).unwrap();
+      db.put(b"a", b"1").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+      db.put(b"b", b"2").unwrap();
+      let err = db.get_updates_since(1).err().unwrap();
+      assert!(
+        matches!(&err, Error::InvalidArgument(m) if m.contains("sequence 1")),
+        "{err:?}"
+      );
+      assert_eq!(updates_since(&db, 2), [(2, 1)]);
+    }
+    assert!(matches!(
+      Db::default().get_updates_since(0),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn empty_writes_are_not_logged_as_ingestions() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      db.write(&WriteOptions::default(), WriteBatch::new())
+        .unwrap();
+      db.put(b"a", b"1").unwrap();
+      let sync = WriteOptions { sync: true };
+      db.write(&sync, WriteBatch::new()).unwrap();
+      db.write_at_sequence(&WriteOptions::default(), 2, WriteBatch::new())
+        .unwrap();
+      assert_eq!(db.latest_sequence_number(), 1);
+      assert_eq!(updates_since(&db, 1), [(1, 1)]);
+    }
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert_eq!(db.latest_sequence_number(), 1);
+    assert_eq!(updates_since(&db, 1), [(1, 1)]);
+  }
+
+  // ── Replication tests ─────────────────────────────────────────────────
+
+  #[serial(fd)]
+  #[test]
+  fn replica_applies_primary_batches_at_same_sequences() {
+    let primary_dir = tempfile::tempdir().unwrap();
+    let replica_dir = tempfile::tempdir().unwrap();
+    let primary = Db::open(primary_dir.path(), create_options()).unwrap();
+    primary.put(b"a", b"1").unwrap();
+    let mut batch = WriteBatch::new();
+    batch.put(b"b", b"2");
+    batch.delete(b"a");
+    primary.write(&WriteOptions::default(), batch).unwrap();
+    primary.put(b"c", b"3").unwrap();
+
+    let shipped: Vec<(u64, Vec<u8>)> = updates_since_batches(&primary, 1)
+      .into_iter()
+      .map(|(seq, batch)| (seq, batch.data().to_vec()))
+      .collect();
+    {
+      let replica = Db::open(replica_dir.path(), create_options()).unwrap();
+      for (seq, data) in &shipped {
+        let batch = WriteBatch::from_bytes(data).unwrap();
+        replica
+          .write_at_sequence(&WriteOptions::default(), *seq, batch)
+          .unwrap();
+      }
+      assert_eq!(
+        replica.latest_sequence_number(),
+        primary.latest_sequence_number()
+      );
+
+      // Re-applying a batch, or skipping one, is rejected.
+      let (seq, data) = &shipped[1];
+      let err = replica
+        .write_at_sequence(
+          &WriteOptions::default(),
+          *seq,
+          WriteBatch::from_bytes(data).unwrap(),
+        )
+        .unwrap_err();
+      assert!(matches!(&err, Error::InvalidArgument(m) if m.contains("duplicate")));
+      let mut gap = WriteBatch::new();
+      gap.put(b"x", b"y");
+      let next = replica.latest_sequence_number() + 2;
+      let err = replica
+        .write_at_sequence(&WriteOptions::default(), next, gap)
+        .unwrap_err();
+      assert!(matches!(&err, Error::InvalidArgument(m) if m.contains("out of order")));
+      assert!(matches!(replica.get(b"x"), Err(Error::NotFound)));
+    }
+    // The replica's own WAL carries the primary's sequence numbers.
+    let replica = Db::open(replica_dir.path(), create_options()).unwrap();
+    assert_eq!(replica.latest_sequence_number(), 4);
+    assert!(matches!(replica.get(b"a"), Err(Error::NotFound)));
+    assert_eq!(replica.get(b"b").unwrap(), b"2");
+    assert_eq!(replica.get(b"c").unwrap(), b"3");
+    let seqs: Vec<u64> = updates_since_batches(&replica, 1)
+      .into_iter()
+      .map(|(seq, _)| seq)
+      .collect();
+    assert_eq!(seqs, [1, 2, 4]);
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn replica_applies_primary_ingestion_from_updates() {
+    let ext = tempfile::tempdir().unwrap();
+    let sst = ext.path().join("bulk.sst");
+    write_external_file(&sst, &[(b"k", b"ingested")]);
+    let primary_dir = tempfile::tempdir().unwrap();
+    let replica_dir = tempfile::tempdir().unwrap();
+    let primary = Db::open(primary_dir.path(), create_options()).unwrap();
+    primary.put(b"a", b"1").unwrap();
+    primary
+      .ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+      .unwrap();
+    primary.put(b"b", b"2").unwrap();
+    // An empty write logs nothing, so it is not mistaken for an ingestion.
+    primary
+      .write(&WriteOptions::default(), WriteBatch::new())
+      .unwrap();
+
+    let replica = Db::open(replica_dir.path(), create_options()).unwrap();
+    let mut applied = Vec::new();
+    for update in primary.get_updates_since(1).unwrap() {
+      match update.unwrap() {
+        WalUpdate::Batch(seq, batch) => {
+          replica
+            .write_at_sequence(&WriteOptions::default(), seq, batch)
+            .unwrap();
+          applied.push(("batch", seq));
+        }
+        WalUpdate::Ingestion(seq) => {
+          replica
+            .ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+            .unwrap();
+          assert_eq!(replica.latest_sequence_number(), seq);
+          applied.push(("ingestion", seq));
+        }
+      }
+    }
+    assert_eq!(applied, [("batch", 1), ("ingestion", 2), ("batch", 3)]);
+    assert_eq!(replica.latest_sequence_number(), 3);
+    assert_eq!(replica.get(b"k").unwrap(), b"ingested");
+    assert_eq!(replica.get(b"b").unwrap(), b"2");
+
+    // Starting at the ingestion yields it first, even across a reopen.
+    drop(primary);
+    let primary = Db::open(primary_dir.path(), create_options()).unwrap();
+    let mut it = primary.get_updates_since(2).unwrap();
+    assert!(matches!(it.next(), Some(Ok(WalUpdate::Ingestion(2)))));
+    assert!(matches!(it.next(), Some(Ok(WalUpdate::Batch(3, _)))));
+    assert!(it.next().is_none());
+  }
+
+  // ── Read-only open tests ──────────────────────────────────────────────
+
+  fn dir_listing(path: &std::path::Path) -> Vec<(String, u64)> {
+    let mut files: Vec<(String, u64)> = std::fs::read_dir(path)
+      .unwrap()
+      .map(|e| {
+        let e = e.unwrap();
+        (
+          e.file_name().into_string().unwrap(),
+          e.metadata().unwrap().len(),
+        )
+      })
+      .collect();
+    files.sort();
+    files
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn read_only_open_alongside_writer_sees_flushed_and_wal_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    db.put(b"flushed", b"1").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    db.put(b"in-wal", b"2").unwrap();
+
+    // The writer still holds LOCK.
+    let ro = Db::open_read_only(dir.path(), Options::default()).unwrap();
+    assert_eq!(ro.get(b"flushed").unwrap(), b"1");
+    assert_eq!(ro.get(b"in-wal").unwrap(), b"2");
+    assert_eq!(ro.latest_sequence_number(), 2);
+
+    // Later writes are not seen.
+    db.put(b"later", b"3").unwrap();
+    assert!(matches!(ro.get(b"later"), Err(Error::NotFound)));
+    let mut it = ro.new_iterator(&ReadOptions::default()).unwrap();
+    it.seek_to_first();
+    let keys: Vec<Vec<u8>> = it.forward().map(|r| r.unwrap().0).collect();
+    assert_eq!(keys, [b"flushed".to_vec(), b"in-wal".to_vec()]);
+
+    drop(db);
+    let after_writer = dir_listing(dir.path());
+    drop(ro);
+    assert_eq!(dir_listing(dir.path()), after_writer);
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn read_only_open_rejects_writes_and_creates_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(matches!(
+      Db::open_read_only(dir.path(), create_options()),
+      Err(Error::InvalidArgument(_))
+    ));
+    assert!(dir_listing(dir.path()).is_empty());
+
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    db.put(b"k", b"v").unwrap();
+    drop(db);
+    let before = dir_listing(dir.path());
+
+    let ro = Db::open_read_only(dir.path(), Options::default()).unwrap();
+    let not_supported = |r: Result<(), Error>| matches!(r, Err(Error::NotSupported(_)));
+    assert!(not_supported(ro.put(b"k", b"w")));
+    assert!(not_supported(ro.delete(b"k")));
+    assert!(not_supported(
+      ro.write(&WriteOptions::default(), WriteBatch::new())
+    ));
+    assert!(not_supported(ro.flush(&FlushOptions::default())));
+    assert!(not_supported(ro.compact_range(None, None)));
+    assert!(not_supported(
+      ro.create_column_family("cf", Options::default())
+        .map(|_| ())
+    ));
+    assert_eq!(ro.get(b"k").unwrap(), b"v");
+    drop(ro);
+    assert_eq!(dir_listing(dir.path()), before);
+
+    // The read-only handle took no lock and left the database intact.
+    let db = Db::open(dir.path(), Options::default()).unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn read_only_open_may_leave_out_column_families() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      let cf = db
+        .create_column_family("extra", Options::default())
+        .unwrap();
+      db.put(b"a", b"default").unwrap();
+      db.put_cf(&cf, b"a", b"extra").unwrap();
+    }
+    let ro = Db::open_read_only(dir.path(), Options::default()).unwrap();
+    assert_eq!(ro.get(b"a").unwrap(), b"default");
+    assert!(ro.column_family("extra").is_none());
+    drop(ro);
+
+    let ro = Db::open_read_only_with_column_families(
+      dir.path(),
+      Options::default(),
+      vec![ColumnFamilyDescriptor::new("extra", Options::default())],
+    )
+    .unwrap();
+    let cf = ro.column_family("extra").unwrap();
+    assert_eq!(ro.get_cf(&cf, b"a").unwrap(), b"extra");
+    drop(ro);
+
+    assert!(matches!(
+      Db::open_read_only_with_column_families(
+        dir.path(),
+        Options::default(),
+        vec![ColumnFamilyDescriptor::new("missing", Options::default())],
+      ),
+      Err(Error::InvalidArgument(_))
+    ));
+  }
+
+  // ── Secondary instance tests ──────────────────────────────────────────
+
+  #[serial(fd)]
+  #[test]
+  fn secondary_catches_up_with_wal_flush_and_compaction() {
+    let dir = tempfile::tempdir().unwrap();
+    let primary = Db::open(dir.path(), create_options()).unwrap();
+    primary.put(b"a", b"1").unwrap();
+    let secondary = Db::open_as_secondary(dir.path(), Options::default()).unwrap();
+    assert_eq!(secondary.get(b"a").unwrap(), b"1");
+
+    // WAL tail.
+    primary.put(b"b", b"2").unwrap();
+    assert!(matches!(secondary.get(b"b"), Err(Error::NotFound)));
+    secondary.try_catch_up_with_primary().unwrap();
+    assert_eq!(secondary.get(b"b").unwrap(), b"2");
+
+    // A flush moves the data into a table and the primary onto a new WAL.
+    let flush = FlushOptions::default();
+    primary.flush(&flush).unwrap();
+    primary.put(b"c", b"3").unwrap();
+    primary
+      .create_column_family("other", Options::default())
+      .unwrap();
+    secondary.try_catch_up_with_primary().unwrap();
+    for (k, v) in [(b"a", b"1"), (b"b", b"2"), (b"c", b"3")] {
+      assert_eq!(secondary.get(k).unwrap(), v);
+    }
+    assert_eq!(
+      secondary.latest_sequence_number(),
+      primary.latest_sequence_number()
+    );
+
+    // Compaction deletes the tables the secondary reads; it keeps them open.
+    primary.delete(b"a").unwrap();
+    primary.flush(&flush).unwrap();
+    secondary.try_catch_up_with_primary().unwrap();
+    let tables = |dir: &std::path::Path| -> Vec<String> {
+      dir_listing(dir)
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| name.ends_with(".ldb"))
+        .collect()
+    };
+    let before = tables(dir.path());
+    primary.compact_range(None, None).unwrap();
+    let after = tables(dir.path());
+    assert!(before.iter().any(|t| !after.contains(t)));
+    assert!(matches!(secondary.get(b"a"), Err(Error::NotFound)));
+    assert_eq!(secondary.get(b"b").unwrap(), b"2");
+    secondary.try_catch_up_with_primary().unwrap();
+    assert_eq!(secondary.get(b"c").unwrap(), b"3");
+    let mut it = secondary.new_iterator(&ReadOptions::default()).unwrap();
+    it.seek_to_first();
+    let keys: Vec<Vec<u8>> = it.forward().map(|r| r.unwrap().0).collect();
+    assert_eq!(keys, [b"b".to_vec(), b"c".to_vec()]);
+    assert!(matches!(
+      secondary.put(b"x", b"y"),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn secondary_reports_table_missing_from_primary() {
+    let dir = tempfile::tempdir().unwrap();
+    let primary = Db::open(dir.path(), create_options()).unwrap();
+    let secondary = Db::open_as_secondary(dir.path(), Options::default()).unwrap();
+    primary.put(b"k", b"v").unwrap();
+    primary.flush(&FlushOptions::default()).unwrap();
+    for (name, _) in dir_listing(dir.path()) {
+      if name.ends_with(".ldb") {
+        std::fs::remove_file(dir.path().join(name)).unwrap();
+      }
+    }
+    let err = secondary.try_catch_up_with_primary().unwrap_err();
+    assert!(
+      matches!(&err, Error::Corruption(m) if m.contains(".ldb")),
+      "{err:?}"
+    );
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn catch_up_requires_secondary() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert!(matches!(
+      db.try_catch_up_with_primary(),
+      Err(Error::NotSupported(_))
+    ));
+    let ro = Db::open_read_only(dir.path(), Options::default()).unwrap();
+    assert!(matches!(
+      ro.try_catch_up_with_primary(),
+      Err(Error::NotSupported(_))
+    ));
+    assert!(matches!(
+      Db::default().try_catch_up_with_primary(),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
+  // ── CompactionFilter tests ──────────────────────────────────────────
+
+  /// A filter that removes keys starting with "drop_".
+  struct PrefixDropFilter;
+
+  impl crate::compaction_filter::CompactionFilter for PrefixDropFilter {
+    fn filter(
+      &mut self,
+      _level: usize,
+      key: &[u8],
+      _value: &[u8],
+      _vtype: u8,
+    ) -> crate::CompactionDecision {
+      if key.starts_with(b"drop_") {
+        crate::CompactionDecision::Remove
+      } else {
+        crate::CompactionDecision::Keep
+      }
+    }
+    fn name(&self) -> &str {
+      "test.PrefixDropFilter"
+    }
+  }
+
+  struct PrefixDropFilterFactory;
+
+  impl crate::compaction_filter::CompactionFilterFactory for PrefixDropFilterFactory {
+    fn create_compaction_filter(&self) -> Box<dyn crate::compaction_filter::CompactionFilter> {
+      Box::new(PrefixDropFilter)
+    }
+    fn name(&self) -> &str {
+      "test.PrefixDropFilterFactory"
+    }
+  }
+
+  /// A filter that uppercases all values.
+  struct UppercaseFilter;
+
+  impl crate::compaction_filter::CompactionFilter for UppercaseFilter {
+    fn filter(
+      &mut self,
+      _level: usize,
+      _key: &[u8],
+      value: &[u8],
+      _vtype: u8,
+    ) -> crate::CompactionDecision {
+      let upper: Vec<u8> = value.iter().map(|b| b.to_ascii_uppercase()).collect();
+      if upper != value {
+        crate::CompactionDecision::ChangeValue(upper)
+      } else {
+        crate::CompactionDecision::Keep
+      }
+    }
+    fn name(&self) -> &str {
+      "test.UppercaseFilter"
+    }
+  }
+
+  struct UppercaseFilterFactory;
+
+  impl crate::compaction_filter::CompactionFilterFactory for UppercaseFilterFactory {
+    fn create_compaction_filter(&self) -> Box<dyn crate::compaction_filter::CompactionFilter> {
+      Box::new(UppercaseFilter)
+    }
     fn name(&self) -> &str {
       "test.UppercaseFilterFactory"
     }
@@ -5411,4 +9096,1096 @@ mod tests {
       );
     }
   }
+
+  // ── Merge operator tests ────────────────────────────────────────────────
+
+  /// Adds little-endian u64 operands to a u64 counter.
+  struct CounterOperator;
+
+  fn decode_counter(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap_or([0; 8]))
+  }
+
+  impl crate::merge_operator::MergeOperator for CounterOperator {
+    fn full_merge(
+      &self,
+      _key: &[u8],
+      existing: Option<&[u8]>,
+      operands: &[&[u8]],
+    ) -> Option<Vec<u8>> {
+      let base = existing.map_or(0, decode_counter);
+      let sum = operands
+        .iter()
+        .fold(base, |acc, op| acc + decode_counter(op));
+      Some(sum.to_le_bytes().to_vec())
+    }
+
+    fn partial_merge(&self, _key: &[u8], left: &[u8], right: &[u8]) -> Option<Vec<u8>> {
+      Some(
+        (decode_counter(left) + decode_counter(right))
+          .to_le_bytes()
+          .to_vec(),
+      )
+    }
+
+    fn name(&self) -> &str {
+      "test.CounterOperator"
+    }
+  }
+
+  fn counter_options() -> Options {
+    Options {
+      create_if_missing: true,
+      merge_operator: Some(std::sync::Arc::new(CounterOperator)),
+      ..Options::default()
+    }
+  }
+
+  #[test]
+  fn merge_without_operator_not_supported() {
+    let db = Db::default();
+    assert!(matches!(
+      db.merge(b"k", 1u64.to_le_bytes()),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
+  #[test]
+  fn merge_applies_operands_on_get() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), counter_options()).unwrap();
+    db.merge(b"fresh", 5u64.to_le_bytes()).unwrap();
+    db.put(b"c", 10u64.to_le_bytes()).unwrap();
+    db.merge(b"c", 1u64.to_le_bytes()).unwrap();
+    db.merge(b"c", 2u64.to_le_bytes()).unwrap();
+    assert_eq!(decode_counter(&db.get(b"fresh").unwrap()), 5);
+    assert_eq!(decode_counter(&db.get(b"c").unwrap()), 13);
+
+    // A deletion resets the counter for subsequent operands.
+    db.delete(b"c").unwrap();
+    db.merge(b"c", 4u64.to_le_bytes()).unwrap();
+    assert_eq!(decode_counter(&db.get(b"c").unwrap()), 4);
+  }
+
+  #[test]
+  fn merge_batch_and_wal_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), counter_options()).unwrap();
+      let mut batch = WriteBatch::new();
+      batch.put(b"c", 1u64.to_le_bytes());
+      batch.merge(b"c", 2u64.to_le_bytes());
+      batch.merge(b"c", 3u64.to_le_bytes());
+      db.write(&WriteOptions::default(), batch).unwrap();
+    }
+    let db = Db::open(dir.path(), counter_options()).unwrap();
+    assert_eq!(decode_counter(&db.get(b"c").unwrap()), 6);
+  }
+
+  #[test]
+  fn merge_operands_span_memtable_and_tables() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), counter_options()).unwrap();
+    db.put(b"c", 100u64.to_le_bytes()).unwrap();
+    for i in 1..=3u64 {
+      db.merge(b"c", i.to_le_bytes()).unwrap();
+      db.flush(&crate::FlushOptions::default()).unwrap();
+    }
+    db.merge(b"c", 4u64.to_le_bytes()).unwrap();
+    assert_eq!(decode_counter(&db.get(b"c").unwrap()), 110);
+
+    let operands: Vec<u64> = db
+      .get_merge_operands(&ReadOptions::default(), b"c")
+      .unwrap()
+      .iter()
+      .map(|v| decode_counter(v))
+      .collect();
+    assert_eq!(operands, vec![100, 1, 2, 3, 4]);
+
+    let mut it = db.new_iterator(&ReadOptions::default()).unwrap();
+    it.seek_to_first();
+    assert_eq!(it.key(), b"c");
+    assert_eq!(decode_counter(it.value()), 110);
+    it.seek_to_last();
+    assert_eq!(decode_counter(it.value()), 110);
+  }
+
+  #[test]
+  fn get_merge_operands_not_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), counter_options()).unwrap();
+    assert!(db
+      .get_merge_operands(&ReadOptions::default(), b"missing")
+      .unwrap_err()
+      .is_not_found());
+    db.put(b"plain", b"v").unwrap();
+    assert_eq!(
+      db.get_merge_operands(&ReadOptions::default(), b"plain")
+        .unwrap(),
+      vec![b"v".to_vec()]
+    );
+  }
+
+  #[test]
+  fn merge_compaction_folds_operands() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), counter_options()).unwrap();
+    db.put(b"base", 10u64.to_le_bytes()).unwrap();
+    for i in 1..=5u64 {
+      db.merge(b"base", i.to_le_bytes()).unwrap();
+      db.merge(b"nobase", i.to_le_bytes()).unwrap();
+      db.flush(&crate::FlushOptions::default()).unwrap();
+    }
+    db.compact_range(None, None).unwrap();
+
+    assert_eq!(decode_counter(&db.get(b"base").unwrap()), 25);
+    assert_eq!(decode_counter(&db.get(b"nobase").unwrap()), 15);
+    // Compaction collapsed each operand chain into a single value.
+    assert_eq!(
+      db.get_merge_operands(&ReadOptions::default(), b"base")
+        .unwrap()
+        .len(),
+      1
+    );
+    assert_eq!(
+      db.get_merge_operands(&ReadOptions::default(), b"nobase")
+        .unwrap()
+        .len(),
+      1
+    );
+  }
+
+  #[test]
+  fn merge_compaction_preserves_snapshot_view() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), counter_options()).unwrap();
+    db.put(b"c", 1u64.to_le_bytes()).unwrap();
+    db.merge(b"c", 1u64.to_le_bytes()).unwrap();
+    let snap = db.get_snapshot();
+    db.merge(b"c", 10u64.to_le_bytes()).unwrap();
+    db.flush(&crate::FlushOptions::default()).unwrap();
+    db.compact_range(None, None).unwrap();
+
+    let at_snap = ReadOptions {
+      snapshot: Some(&snap),
+      ..ReadOptions::default()
+    };
+    assert_eq!(
+      decode_counter(&db.get_with_options(&at_snap, b"c").unwrap()),
+      2
+    );
+    assert_eq!(decode_counter(&db.get(b"c").unwrap()), 12);
+  }
+
+  // ── Range deletion ────────────────────────────────────────────────────────
+
+  /// Keys currently visible through a fresh iterator, in order.
+  fn visible_keys(db: &Db) -> Vec<Vec<u8>> {
+    let mut it = db.new_iterator(&ReadOptions::default()).unwrap();
+    it.seek_to_first();
+    let mut keys = Vec::new();
+    while it.valid() {
+      keys.push(it.key().to_vec());
+      it.next();
+    }
+    keys
+  }
+
+  #[test]
+  fn delete_range_hides_keys_in_memtable() {
+    let db = Db::default();
+    for k in [b"a", b"b", b"c", b"d"] {
+      db.put(k, b"v").unwrap();
+    }
+    db.delete_range(b"b", b"d").unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"v");
+    assert!(db.get(b"b").unwrap_err().is_not_found());
+    assert!(db.get(b"c").unwrap_err().is_not_found());
+    // The end key is exclusive.
+    assert_eq!(db.get(b"d").unwrap(), b"v");
+    assert_eq!(visible_keys(&db), vec![b"a".to_vec(), b"d".to_vec()]);
+
+    // Writes after the tombstone are visible again.
+    db.put(b"c", b"new").unwrap();
+    assert_eq!(db.get(b"c").unwrap(), b"new");
+    assert_eq!(
+      visible_keys(&db),
+      vec![b"a".to_vec(), b"c".to_vec(), b"d".to_vec()]
+    );
+  }
+
+  #[test]
+  fn delete_range_rejects_inverted_range() {
+    let db = Db::default();
+    db.put(b"k", b"v").unwrap();
+    assert!(matches!(
+      db.delete_range(b"z", b"a"),
+      Err(Error::InvalidArgument(_))
+    ));
+    // An empty range is accepted and deletes nothing.
+    db.delete_range(b"k", b"k").unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+  }
+
+  #[test]
+  fn delete_range_respects_snapshots() {
+    let db = Db::default();
+    db.put(b"k1", b"v1").unwrap();
+    db.put(b"k2", b"v2").unwrap();
+    let snap = db.get_snapshot();
+    db.delete_range(b"k", b"l").unwrap();
+    let at_snap = ReadOptions {
+      snapshot: Some(&snap),
+      ..ReadOptions::default()
+    };
+    assert_eq!(db.get_with_options(&at_snap, b"k1").unwrap(), b"v1");
+    assert!(db.get(b"k1").unwrap_err().is_not_found());
+    let mut it = db.new_iterator(&at_snap).unwrap();
+    it.seek_to_first();
+    assert_eq!(it.key(), b"k1");
+    it.next();
+    assert_eq!(it.key(), b"k2");
+  }
+
+  #[test]
+  fn delete_range_wal_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      let mut batch = WriteBatch::new();
+      batch.put(b"a", b"1");
+      batch.put(b"b", b"2");
+      batch.delete_range(b"a", b"b");
+      db.write(&WriteOptions::default(), batch).unwrap();
+    }
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert!(db.get(b"a").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"b").unwrap(), b"2");
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn delete_range_applies_across_tables_and_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      for i in 0u32..20 {
+        db.put(format!("key{i:02}"), b"v").unwrap();
+      }
+      db.flush(&crate::FlushOptions::default()).unwrap();
+      // The tombstone lands in its own table, newer than the data.
+      db.delete_range(b"key05", b"key15").unwrap();
+      db.flush(&crate::FlushOptions::default()).unwrap();
+      db.put(b"key10", b"again").unwrap();
+    }
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert_eq!(db.get(b"key04").unwrap(), b"v");
+    assert!(db.get(b"key05").unwrap_err().is_not_found());
+    assert!(db.get(b"key14").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"key10").unwrap(), b"again");
+    assert_eq!(db.get(b"key15").unwrap(), b"v");
+    let keys = visible_keys(&db);
+    assert_eq!(keys.len(), 11);
+    assert!(keys.contains(&b"key10".to_vec()));
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn delete_range_compaction_drops_covered_keys_and_tombstone() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), tiny_options()).unwrap();
+    for i in 0u32..200 {
+      db.put(format!("key{i:05}"), b"value").unwrap();
+    }
+    db.delete_range(b"key00000", b"key00200").unwrap();
+    db.compact_range(None, None).unwrap();
+    assert!(visible_keys(&db).is_empty());
+    assert!(db.get(b"key00100").unwrap_err().is_not_found());
+    // Nothing lies below the bottommost level, so the covered keys and the
+    // tombstone itself are gone.
+    assert_eq!(count_files(dir.path(), ".ldb"), 0);
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn delete_range_compaction_keeps_keys_visible_to_snapshot() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), tiny_options()).unwrap();
+    for i in 0u32..100 {
+      db.put(format!("key{i:05}"), b"value").unwrap();
+    }
+    let snap = db.get_snapshot();
+    db.delete_range(b"key00000", b"key00050").unwrap();
+    db.compact_range(None, None).unwrap();
+
+    let at_snap = ReadOptions {
+      snapshot: Some(&snap),
+      ..ReadOptions::default()
+    };
+    assert_eq!(
+      db.get_with_options(&at_snap, b"key00010").unwrap(),
+      b"value"
+    );
+    assert!(db.get(b"key00010").unwrap_err().is_not_found());
+    assert_eq!(visible_keys(&db).len(), 50);
+  }
+
+  // ── Optimistic transactions ───────────────────────────────────────────────
+
+  #[test]
+  fn optimistic_transaction_commit_survives_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      db.put(b"a", b"1").unwrap();
+      let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+      txn.get(b"a").unwrap();
+      txn.delete(b"a");
+      txn.put(b"b", b"2");
+      txn.commit().unwrap();
+    }
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert!(db.get(b"a").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"b").unwrap(), b"2");
+  }
+
+  #[test]
+  fn optimistic_transaction_busy_when_history_was_flushed() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    txn.get(b"k").unwrap_err();
+    // A write newer than the snapshot leaves the memtables: the conflict can
+    // no longer be ruled out.
+    db.put(b"unrelated", b"v").unwrap();
+    db.flush(&crate::FlushOptions::default()).unwrap();
+    txn.put(b"k", b"v");
+    assert!(txn.commit().unwrap_err().is_busy());
+
+    // A transaction started after the flush commits normally.
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    txn.put(b"k", b"v");
+    txn.commit().unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+  }
+
+  #[test]
+  fn optimistic_transaction_busy_after_ingestion() {
+    let ext = tempfile::tempdir().unwrap();
+    let sst = ext.path().join("bulk.sst");
+    write_external_file(&sst, &[(b"k", b"ingested")]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    db.put(b"a", b"1").unwrap();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    txn.get(b"k").unwrap_err();
+    // The memtable still holds every write since the snapshot, but not the
+    // ingested key.
+    db.ingest_external_file(&[&sst], &crate::IngestExternalFileOptions::default())
+      .unwrap();
+    txn.put(b"k", b"txn");
+    assert!(txn.commit().unwrap_err().is_busy());
+    assert_eq!(db.get(b"k").unwrap(), b"ingested");
+
+    // A transaction started after the ingestion commits normally.
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    txn.put(b"k", b"txn");
+    txn.commit().unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"txn");
+  }
+
+  #[test]
+  fn optimistic_transactions_retry_to_a_consistent_total() {
+    let db = std::sync::Arc::new(Db::default());
+    db.put(b"counter", 0u64.to_le_bytes()).unwrap();
+    let threads: Vec<_> = (0..4)
+      .map(|_| {
+        let db = std::sync::Arc::clone(&db);
+        std::thread::spawn(move || {
+          for _ in 0..50 {
+            loop {
+              let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+              let v = txn.get(b"counter").unwrap();
+              let n = u64::from_le_bytes(v.try_into().unwrap());
+              txn.put(b"counter", (n + 1).to_le_bytes());
+              match txn.commit() {
+                Ok(()) => break,
+                Err(e) if e.is_busy() => continue,
+                Err(e) => panic!("{e}"),
+              }
+            }
+          }
+        })
+      })
+      .collect();
+    for t in threads {
+      t.join().unwrap();
+    }
+    let v = db.get(b"counter").unwrap();
+    assert_eq!(u64::from_le_bytes(v.try_into().unwrap()), 200);
+  }
+
+  // ── Pessimistic transactions ──────────────────────────────────────────────
+
+  #[test]
+  fn transaction_db_commit_is_recovered_from_wal() {
+    use crate::{TransactionDb, TransactionDbOptions, TransactionOptions};
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = TransactionDb::open(
+        dir.path(),
+        create_options(),
+        TransactionDbOptions::default(),
+      )
+      .unwrap();
+      let mut txn = db.begin_transaction(&WriteOptions::default(), &TransactionOptions::default());
+      txn.put(b"a", b"1").unwrap();
+      txn.put(b"b", b"2").unwrap();
+      txn.commit().unwrap();
+      // Dropped without committing: never reaches the WAL.
+      let mut txn = db.begin_transaction(&WriteOptions::default(), &TransactionOptions::default());
+      txn.put(b"c", b"3").unwrap();
+    }
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert_eq!(db.get(b"a").unwrap(), b"1");
+    assert_eq!(db.get(b"b").unwrap(), b"2");
+    assert!(db.get(b"c").unwrap_err().is_not_found());
+  }
+
+  #[test]
+  fn transaction_db_serialises_read_modify_write() {
+    use crate::{TransactionDb, TransactionDbOptions, TransactionOptions};
+    let db = std::sync::Arc::new(TransactionDb::new(
+      Db::default(),
+      TransactionDbOptions::default(),
+    ));
+    db.put(b"counter", 0u64.to_le_bytes()).unwrap();
+    let threads: Vec<_> = (0..4)
+      .map(|_| {
+        let db = std::sync::Arc::clone(&db);
+        std::thread::spawn(move || {
+          let opts = TransactionOptions {
+            lock_timeout: Some(std::time::Duration::from_secs(30)),
+            ..TransactionOptions::default()
+          };
+          for _ in 0..50 {
+            let mut txn = db.begin_transaction(&WriteOptions::default(), &opts);
+            let v = txn.get_for_update(b"counter").unwrap();
+            let n = u64::from_le_bytes(v.try_into().unwrap());
+            txn.put(b"counter", (n + 1).to_le_bytes()).unwrap();
+            txn.commit().unwrap();
+          }
+        })
+      })
+      .collect();
+    for t in threads {
+      t.join().unwrap();
+    }
+    let v = db.get(b"counter").unwrap();
+    assert_eq!(u64::from_le_bytes(v.try_into().unwrap()), 200);
+  }
+
+  // ── Column families ───────────────────────────────────────────────────────
+
+  #[test]
+  fn column_families_are_separate_keyspaces() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    let cf = db
+      .create_column_family("users", Options::default())
+      .unwrap();
+    assert_eq!(db.column_family("users"), Some(cf.clone()));
+
+    db.put(b"k", b"default").unwrap();
+    db.put_cf(&cf, b"k", b"users").unwrap();
+    db.put_cf(&cf, b"only-users", b"1").unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"default");
+    assert_eq!(db.get_cf(&cf, b"k").unwrap(), b"users");
+    assert!(db.get(b"only-users").unwrap_err().is_not_found());
+
+    db.delete_cf(&cf, b"k").unwrap();
+    assert!(db.get_cf(&cf, b"k").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"k").unwrap(), b"default");
+    assert!(matches!(
+      db.create_column_family("users", Options::default()),
+      Err(Error::InvalidArgument(_))
+    ));
+  }
+
+  #[test]
+  fn column_family_batch_is_atomic_across_families_and_recovered() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      let cf = db
+        .create_column_family("index", Options::default())
+        .unwrap();
+      let mut batch = WriteBatch::new();
+      batch.put(b"doc1", b"body");
+      batch.put_cf(&cf, b"word", b"doc1");
+      db.write(&WriteOptions::default(), batch).unwrap();
+    }
+    assert!(matches!(
+      Db::open(dir.path(), create_options()),
+      Err(Error::InvalidArgument(_))
+    ));
+    assert_eq!(
+      Db::list_column_families(dir.path(), &create_options()).unwrap(),
+      vec!["default".to_owned(), "index".to_owned()]
+    );
+    let db = Db::open_with_column_families(
+      dir.path(),
+      create_options(),
+      vec![ColumnFamilyDescriptor::new("index", Options::default())],
+    )
+    .unwrap();
+    let cf = db.column_family("index").unwrap();
+    assert_eq!(db.get(b"doc1").unwrap(), b"body");
+    assert_eq!(db.get_cf(&cf, b"word").unwrap(), b"doc1");
+  }
+
+  #[test]
+  fn column_family_write_to_unknown_family_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    let cf = db.create_column_family("gone", Options::default()).unwrap();
+    db.drop_column_family(&cf).unwrap();
+
+    let mut batch = WriteBatch::new();
+    batch.put(b"a", b"1");
+    batch.put_cf(&cf, b"b", b"2");
+    assert!(matches!(
+      db.write(&WriteOptions::default(), batch),
+      Err(Error::InvalidArgument(_))
+    ));
+    // Nothing of the rejected batch was applied.
+    assert!(db.get(b"a").unwrap_err().is_not_found());
+    assert!(matches!(
+      db.get_cf(&cf, b"b"),
+      Err(Error::InvalidArgument(_))
+    ));
+    assert!(matches!(
+      db.drop_column_family(&db.default_column_family()),
+      Err(Error::InvalidArgument(_))
+    ));
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn column_family_flush_and_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let n = 200u32;
+    {
+      let db = Db::open(dir.path(), small_options()).unwrap();
+      let cf = db.create_column_family("logs", small_options()).unwrap();
+      for i in 0..n {
+        let key = format!("key{i:05}");
+        db.put(key.as_bytes(), b"d").unwrap();
+        db.put_cf(&cf, key.as_bytes(), b"l").unwrap();
+      }
+      db.flush(&FlushOptions::default()).unwrap();
+      let g = db.inner.state.lock().unwrap();
+      let files = |id| {
+        let v = g.current(id).unwrap();
+        (0..v.num_levels()).map(|l| v.num_files(l)).sum::<usize>()
+      };
+      assert!(files(0) > 0, "default family should have SSTables");
+      assert!(files(cf.id()) > 0, "logs family should have SSTables");
+    }
+    let db = Db::open_with_column_families(
+      dir.path(),
+      small_options(),
+      vec![ColumnFamilyDescriptor::new("logs", small_options())],
+    )
+    .unwrap();
+    let cf = db.column_family("logs").unwrap();
+    for i in 0..n {
+      let key = format!("key{i:05}");
+      assert_eq!(db.get(key.as_bytes()).unwrap(), b"d");
+      assert_eq!(db.get_cf(&cf, key.as_bytes()).unwrap(), b"l");
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn column_family_drop_removes_its_files() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      let cf = db.create_column_family("tmp", create_options()).unwrap();
+      db.put(b"keep", b"1").unwrap();
+      db.put_cf(&cf, b"drop", b"2").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+      assert_eq!(count_files(dir.path(), "ldb"), 2);
+
+      db.drop_column_family(&cf).unwrap();
+      assert_eq!(count_files(dir.path(), "ldb"), 1);
+      assert!(db.column_family("tmp").is_none());
+    }
+    assert_eq!(
+      Db::list_column_families(dir.path(), &create_options()).unwrap(),
+      vec!["default".to_owned()]
+    );
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    assert_eq!(db.get(b"keep").unwrap(), b"1");
+  }
+
+  #[test]
+  fn column_family_open_creates_missing_only_when_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    let descriptors = || vec![ColumnFamilyDescriptor::new("new", Options::default())];
+    assert!(matches!(
+      Db::open_with_column_families(dir.path(), create_options(), descriptors()),
+      Err(Error::InvalidArgument(_))
+    ));
+    let opts = Options {
+      create_missing_column_families: true,
+      ..create_options()
+    };
+    let db = Db::open_with_column_families(dir.path(), opts, descriptors()).unwrap();
+    assert!(db.column_family("new").is_some());
+  }
+
+  #[test]
+  fn column_family_uses_its_own_comparator_and_merge_operator() {
+    let dir = tempfile::tempdir().unwrap();
+    let reversed = Options {
+      comparator: std::sync::Arc::new(ReverseBytewiseComparator),
+      ..counter_options()
+    };
+    {
+      let db = Db::open(dir.path(), create_options()).unwrap();
+      let cf = db.create_column_family("rev", reversed.clone()).unwrap();
+      for k in [b"a", b"b", b"c"] {
+        db.put(k, b"").unwrap();
+        db.put_cf(&cf, k, b"").unwrap();
+      }
+      db.merge_cf(&cf, b"n", 2u64.to_le_bytes()).unwrap();
+      db.merge_cf(&cf, b"n", 3u64.to_le_bytes()).unwrap();
+      assert!(matches!(
+        db.merge(b"n", 1u64.to_le_bytes()),
+        Err(Error::NotSupported(_))
+      ));
+    }
+
+    // Reopening with a different comparator for the family is rejected.
+    assert!(matches!(
+      Db::open_with_column_families(
+        dir.path(),
+        create_options(),
+        vec![ColumnFamilyDescriptor::new("rev", Options::default())],
+      ),
+      Err(Error::InvalidArgument(_))
+    ));
+
+    let db = Db::open_with_column_families(
+      dir.path(),
+      create_options(),
+      vec![ColumnFamilyDescriptor::new("rev", reversed)],
+    )
+    .unwrap();
+    let cf = db.column_family("rev").unwrap();
+    let keys = |mut it: DbIter| {
+      it.seek_to_first();
+      let mut keys = Vec::new();
+      while it.valid() {
+        keys.push(it.key().to_vec());
+        it.next();
+      }
+      keys
+    };
+    assert_eq!(
+      keys(db.new_iterator(&ReadOptions::default()).unwrap()),
+      vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+    );
+    assert_eq!(
+      keys(db.new_iterator_cf(&ReadOptions::default(), &cf).unwrap()),
+      vec![b"n".to_vec(), b"c".to_vec(), b"b".to_vec(), b"a".to_vec()]
+    );
+    assert_eq!(db.get_cf(&cf, b"n").unwrap(), 5u64.to_le_bytes());
+  }
+
+  // ── MultiGet ──────────────────────────────────────────────────────────────
+
+  #[test]
+  #[serial(fd)]
+  fn multi_get_matches_get_across_memtable_and_tables() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), counter_options()).unwrap();
+    for i in 0u32..300 {
+      db.put(format!("key{i:05}"), format!("old{i}")).unwrap();
+    }
+    db.flush(&FlushOptions::default()).unwrap();
+    db.delete(b"key00010").unwrap();
+    db.put(b"key00020", b"new").unwrap();
+    db.merge(b"count", 2u64.to_le_bytes()).unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    let snap = db.get_snapshot();
+    db.merge(b"count", 3u64.to_le_bytes()).unwrap();
+    db.put(b"key00030", b"newest").unwrap();
+
+    // Unsorted, with a duplicate and keys that do not exist.
+    let keys: [&[u8]; 8] = [
+      b"key00030",
+      b"missing",
+      b"key00020",
+      b"count",
+      b"key00010",
+      b"key00299",
+      b"key00020",
+      b"a",
+    ];
+    let results = db.multi_get(&ReadOptions::default(), &keys);
+    assert_eq!(results.len(), keys.len());
+    for (key, result) in keys.iter().zip(&results) {
+      match db.get(key) {
+        Ok(v) => assert_eq!(result.as_ref().unwrap(), &v),
+        Err(e) => {
+          assert!(e.is_not_found());
+          assert!(result.as_ref().unwrap_err().is_not_found());
+        }
+      }
+    }
+    assert_eq!(results[3].as_ref().unwrap(), &5u64.to_le_bytes());
+
+    let at_snap = ReadOptions {
+      snapshot: Some(&snap),
+      ..ReadOptions::default()
+    };
+    let results = db.multi_get(&at_snap, &["key00030", "count"]);
+    assert_eq!(results[0].as_ref().unwrap(), b"old30");
+    assert_eq!(results[1].as_ref().unwrap(), &2u64.to_le_bytes());
+  }
+
+  #[test]
+  fn multi_get_cf_reads_its_family() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), create_options()).unwrap();
+    let cf = db.create_column_family("cf", create_options()).unwrap();
+    db.put(b"k", b"default").unwrap();
+    db.put_cf(&cf, b"k", b"cf").unwrap();
+    let results = db.multi_get_cf(&ReadOptions::default(), &cf, &["k", "x"]);
+    assert_eq!(results[0].as_ref().unwrap(), b"cf");
+    assert!(results[1].as_ref().unwrap_err().is_not_found());
+
+    db.drop_column_family(&cf).unwrap();
+    let results = db.multi_get_cf(&ReadOptions::default(), &cf, &[b"k"]);
+    assert!(matches!(results[0], Err(Error::InvalidArgument(_))));
+  }
+
+  // ── Blob files ────────────────────────────────────────────────────────────
+
+  fn blob_options() -> Options {
+    Options {
+      create_if_missing: true,
+      enable_blob_files: true,
+      min_blob_size: 64,
+      ..Options::default()
+    }
+  }
+
+  fn blob_value(i: u32, generation: u32) -> Vec<u8> {
+    format!("{generation}:{i:05}:").repeat(10).into_bytes()
+  }
+
+  /// Numbers of the blob files in `dir`, ascending.
+  fn blob_file_numbers(dir: &std::path::Path) -> Vec<u64> {
+    let mut numbers: Vec<u64> = std::fs::read_dir(dir)
+      .unwrap()
+      .filter_map(|e| {
+        let name = e.unwrap().file_name().into_string().unwrap();
+        name.strip_suffix(".blob")?.parse().ok()
+      })
+      .collect();
+    numbers.sort_unstable();
+    numbers
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn blob_files_hold_large_values_across_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), blob_options()).unwrap();
+      for i in 0u32..50 {
+        db.put(format!("key{i:05}"), blob_value(i, 0)).unwrap();
+      }
+      db.put(b"small", b"inline").unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+      assert_eq!(blob_file_numbers(dir.path()).len(), 1);
+    }
+    let db = Db::open(dir.path(), blob_options()).unwrap();
+    assert_eq!(db.get(b"key00007").unwrap(), blob_value(7, 0));
+    assert_eq!(db.get(b"small").unwrap(), b"inline");
+
+    let mut it = db.new_iterator(&ReadOptions::default()).unwrap();
+    it.seek(b"key00010");
+    assert_eq!(it.value(), blob_value(10, 0));
+    it.next();
+    assert_eq!(it.value(), blob_value(11, 0));
+    it.prev();
+    it.prev();
+    assert_eq!(it.key(), b"key00009");
+    assert_eq!(it.value(), blob_value(9, 0));
+    it.seek_to_last();
+    assert_eq!(it.value(), b"inline");
+    it.prev();
+    assert_eq!(it.value(), blob_value(49, 0));
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn blob_files_wholly_overwritten_are_deleted_by_compaction() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), blob_options()).unwrap();
+    for generation in 0..2 {
+      for i in 0u32..50 {
+        db.put(format!("key{i:05}"), blob_value(i, generation))
+          .unwrap();
+      }
+      db.flush(&FlushOptions::default()).unwrap();
+    }
+    let before = blob_file_numbers(dir.path());
+    assert_eq!(before.len(), 2);
+
+    // Every blob of the first file is now garbage: the file goes away while
+    // the second one is still referenced.
+    db.compact_range(None, None).unwrap();
+    assert_eq!(blob_file_numbers(dir.path()), &before[1..]);
+    for i in 0u32..50 {
+      assert_eq!(db.get(format!("key{i:05}")).unwrap(), blob_value(i, 1));
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn blob_garbage_collection_relocates_live_blobs() {
+    let dir = tempfile::tempdir().unwrap();
+    let opts = Options {
+      enable_blob_garbage_collection: true,
+      blob_garbage_collection_age_cutoff: 1.0,
+      ..blob_options()
+    };
+    {
+      let db = Db::open(dir.path(), opts.clone()).unwrap();
+      for i in 0u32..50 {
+        db.put(format!("key{i:05}"), blob_value(i, 0)).unwrap();
+      }
+      db.flush(&FlushOptions::default()).unwrap();
+      // Overwrite half the keys: without collection the first blob file
+      // would stay alive for the other half.
+      for i in (0u32..50).step_by(2) {
+        db.put(format!("key{i:05}"), blob_value(i, 1)).unwrap();
+      }
+      db.flush(&FlushOptions::default()).unwrap();
+      let before = blob_file_numbers(dir.path());
+      assert_eq!(before.len(), 2);
+
+      db.compact_range(None, None).unwrap();
+      let after = blob_file_numbers(dir.path());
+      assert!(after.iter().all(|n| !before.contains(n)), "{after:?}");
+    }
+    let db = Db::open(dir.path(), opts).unwrap();
+    for i in 0u32..50 {
+      let generation = if i % 2 == 0 { 1 } else { 0 };
+      assert_eq!(
+        db.get(format!("key{i:05}")).unwrap(),
+        blob_value(i, generation)
+      );
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn blob_values_are_inlined_when_blob_files_are_disabled() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), blob_options()).unwrap();
+      for i in 0u32..20 {
+        db.put(format!("key{i:05}"), blob_value(i, 0)).unwrap();
+      }
+      db.flush(&FlushOptions::default()).unwrap();
+    }
+    // Existing blobs stay readable; garbage collection moves them back inline.
+    let opts = Options {
+      enable_blob_files: false,
+      enable_blob_garbage_collection: true,
+      blob_garbage_collection_age_cutoff: 1.0,
+      ..blob_options()
+    };
+    let db = Db::open(dir.path(), opts).unwrap();
+    assert_eq!(db.get(b"key00003").unwrap(), blob_value(3, 0));
+    db.compact_range(None, None).unwrap();
+    assert!(blob_file_numbers(dir.path()).is_empty());
+    assert_eq!(db.get(b"key00003").unwrap(), blob_value(3, 0));
+  }
+
+  // ── User-defined timestamps ────────────────────────────────────────────────
+
+  fn ts_options() -> Options {
+    Options {
+      create_if_missing: true,
+      comparator: std::sync::Arc::new(crate::BytewiseComparatorWithU64Ts),
+      filter_policy: Some(std::sync::Arc::new(crate::BloomFilterPolicy::new(10))),
+      ..Options::default()
+    }
+  }
+
+  /// User keys of every entry in the tables of the default column family.
+  fn table_user_keys(db: &Db) -> Vec<Vec<u8>> {
+    use crate::iter::InternalIterator;
+    let g = db.inner.state.lock().unwrap();
+    let version = g.current(crate::DEFAULT_COLUMN_FAMILY_ID).unwrap();
+    let tc = g
+      .column_family(crate::DEFAULT_COLUMN_FAMILY_ID)
+      .unwrap()
+      .table_cache
+      .clone()
+      .unwrap();
+    let mut keys = Vec::new();
+    for level in 0..version.num_levels() {
+      for meta in version.files_at(level) {
+        let table = tc.get_or_open(meta.number, meta.file_size).unwrap();
+        let mut it = table.new_iterator(false, false).unwrap();
+        it.seek_to_first();
+        while it.valid() {
+          keys.push(crate::table::format::user_key(it.key()).to_vec());
+          it.next();
+        }
+      }
+    }
+    keys
+  }
+
+  fn as_of(ts: &[u8; 8]) -> ReadOptions<'_> {
+    ReadOptions {
+      timestamp: Some(ts),
+      ..ReadOptions::default()
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn timestamp_reads_see_versions_as_of_read_timestamp() {
+    let ts = crate::BytewiseComparatorWithU64Ts::encode_timestamp;
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), ts_options()).unwrap();
+    db.put_with_ts(b"a", ts(10), b"a10").unwrap();
+    db.put_with_ts(b"b", ts(10), b"b10").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    db.put_with_ts(b"a", ts(20), b"a20").unwrap();
+    db.delete_with_ts(b"b", ts(30)).unwrap();
+
+    for _ in 0..2 {
+      assert!(matches!(
+        db.get_with_options(&as_of(&ts(5)), b"a"),
+        Err(Error::NotFound)
+      ));
+      assert_eq!(db.get_with_options(&as_of(&ts(15)), b"a").unwrap(), b"a10");
+      assert_eq!(db.get_with_options(&as_of(&ts(25)), b"a").unwrap(), b"a20");
+      assert_eq!(db.get_with_options(&as_of(&ts(25)), b"b").unwrap(), b"b10");
+      assert!(matches!(
+        db.get_with_options(&as_of(&ts(30)), b"b"),
+        Err(Error::NotFound)
+      ));
+      let got: Vec<_> = db
+        .multi_get(&as_of(&ts(15)), &[&b"b"[..], b"a"])
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+      assert_eq!(got, [b"b10".to_vec(), b"a10".to_vec()]);
+
+      let read_ts = ts(25);
+      let mut it = db.new_iterator(&as_of(&read_ts)).unwrap();
+      it.seek_to_first();
+      let mut seen = Vec::new();
+      while it.valid() {
+        seen.push((
+          it.key().to_vec(),
+          it.timestamp().to_vec(),
+          it.value().to_vec(),
+        ));
+        it.next();
+      }
+      assert_eq!(
+        seen,
+        [
+          (b"a".to_vec(), ts(20).to_vec(), b"a20".to_vec()),
+          (b"b".to_vec(), ts(10).to_vec(), b"b10".to_vec()),
+        ]
+      );
+      it.seek_to_last();
+      assert_eq!(it.key(), b"b");
+      it.prev();
+      assert_eq!(it.value(), b"a20");
+      it.seek(b"b");
+      assert_eq!(it.value(), b"b10");
+
+      db.compact_range(None, None).unwrap();
+    }
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn timestamp_size_is_validated() {
+    let ts = crate::BytewiseComparatorWithU64Ts::encode_timestamp;
+    let dir = tempfile::tempdir().unwrap();
+    let db = Db::open(dir.path(), ts_options()).unwrap();
+    assert!(matches!(
+      db.put_with_ts(b"k", [1u8; 4], b"v"),
+      Err(Error::InvalidArgument(_))
+    ));
+    assert!(matches!(db.get(b"k"), Err(Error::InvalidArgument(_))));
+    assert!(matches!(
+      db.delete_range(b"a", b"z"),
+      Err(Error::NotSupported(_))
+    ));
+    let read_ts = ts(1);
+    assert!(matches!(
+      Db::default().get_with_options(&as_of(&read_ts), b"k"),
+      Err(Error::InvalidArgument(_))
+    ));
+  }
+
+  #[test]
+  #[serial(fd)]
+  fn full_history_ts_low_collapses_old_versions() {
+    let ts = crate::BytewiseComparatorWithU64Ts::encode_timestamp;
+    let dir = tempfile::tempdir().unwrap();
+    {
+      let db = Db::open(dir.path(), ts_options()).unwrap();
+      db.put_with_ts(b"k", ts(10), b"v10").unwrap();
+      db.put_with_ts(b"k", ts(20), b"v20").unwrap();
+      db.put_with_ts(b"k", ts(30), b"v30").unwrap();
+      db.put_with_ts(b"gone", ts(10), b"x").unwrap();
+      db.delete_with_ts(b"gone", ts(20)).unwrap();
+      db.flush(&FlushOptions::default()).unwrap();
+
+      db.increase_full_history_ts_low(ts(25)).unwrap();
+      assert!(matches!(
+        db.increase_full_history_ts_low(ts(15)),
+        Err(Error::InvalidArgument(_))
+      ));
+      db.compact_range(None, None).unwrap();
+      // k@10 and both versions of "gone" are dropped; k@20 is the newest
+      // version below the cutoff and stays.
+      assert_eq!(
+        table_user_keys(&db),
+        [[b"k", &ts(30)[..]].concat(), [b"k", &ts(20)[..]].concat()]
+      );
+      assert_eq!(db.get_with_options(&as_of(&ts(25)), b"k").unwrap(), b"v20");
+      assert_eq!(db.get_with_options(&as_of(&ts(40)), b"k").unwrap(), b"v30");
+    }
+    let db = Db::open(dir.path(), ts_options()).unwrap();
+    assert!(matches!(
+      db.get_with_options(&as_of(&ts(20)), b"k"),
+      Err(Error::InvalidArgument(_))
+    ));
+    assert_eq!(db.get_with_options(&as_of(&ts(25)), b"k").unwrap(), b"v20");
+    assert!(matches!(
+      db.get_with_options(&as_of(&ts(25)), b"gone"),
+      Err(Error::NotFound)
+    ));
+  }
 }
@@ -17,8 +17,9 @@ use crate::logfile::format::{RecordType, BLOCK_SIZE, HEADER_SIZE};
 /// Receives corruption notifications from the [`Reader`].
 ///
 /// See `db/log_reader.h: Reader::Reporter`.
-pub(crate) trait Reporter {
-  fn corruption(&mut self, bytes: u64, reason: &str);
+pub(crate) trait Reporter: Send {
+  /// `bytes` bytes starting at file offset `offset` were dropped.
+  fn corruption(&mut self, offset: u64, bytes: u64, reason: &str);
 }
 
 /// WAL log reader. Reads sequentially from a [`SequentialFile`], reassembling
@@ -41,6 +42,9 @@ pub(crate) struct Reader {
   initial_offset: u64,
   /// True while skipping Middle/Last fragments after an initial-offset seek.
   resyncing: bool,
+  /// Report a record truncated by the end of the file instead of silently
+  /// treating it as EOF.
+  report_eof_truncation: bool,
 }
 
 /// Internal result of reading one physical record from the block buffer.
@@ -81,12 +85,20 @@ impl Reader {
       last_record_offset: 0,
       initial_offset,
       resyncing: initial_offset > 0,
+      report_eof_truncation: false,
     }
   }
 
+  /// Report a record cut short by the end of the file (normally a write torn
+  /// by a crash) as corruption rather than silently treating it as EOF.
+  ///
+  /// See the `kAbsoluteConsistency` checks of `db/log_reader.cc` in RocksDB.
+  pub(crate) fn set_report_eof_truncation(&mut self, report: bool) {
+    self.report_eof_truncation = report;
+  }
+
   /// File offset of the last record returned by `read_record`.
   /// Undefined before the first successful call.
-  #[cfg(test)]
   pub(crate) fn last_record_offset(&self) -> u64 {
     self.last_record_offset
   }
@@ -109,10 +121,13 @@ impl Reader {
 
       // Physical offset of this record's header: end of buffer minus
       // remaining bytes minus the header and payload we just consumed.
-      let physical_record_offset = self.end_of_buffer_offset
-        - (self.buf_end - self.buf_start) as u64
-        - HEADER_SIZE as u64
-        - pr.data_len as u64;
+      // Meaningless (and allowed to wrap) for `Eof`, which can come before
+      // any header was read.
+      let physical_record_offset = self
+        .end_of_buffer_offset
+        .wrapping_sub((self.buf_end - self.buf_start) as u64)
+        .wrapping_sub(HEADER_SIZE as u64)
+        .wrapping_sub(pr.data_len as u64);
 
       // While resyncing after an initial-offset seek, skip Middle/Last
       // fragments that belong to a record that started before the seek point.
@@ -170,6 +185,9 @@ impl Reader {
 
         PhysKind::Eof => {
           // An incomplete trailing record is a torn write, not corruption.
+          if in_fragmented && self.report_eof_truncation {
+            self.report_corruption(scratch.len() as u64, "partial record without end(3)");
+          }
           scratch.clear();
           return None;
         }
@@ -267,6 +285,9 @@ impl Reader {
         self.buf_start = self.buf_end;
         if self.eof {
           // Payload truncated at EOF: torn write.
+          if self.report_eof_truncation {
+            self.report_corruption(drop, "truncated record body");
+          }
           return eof;
         }
         self.report_corruption(drop, "bad record length");
@@ -335,7 +356,7 @@ impl Reader {
       .saturating_sub(bytes);
     if drop_start >= self.initial_offset {
       if let Some(r) = self.reporter.as_mut() {
-        r.corruption(bytes, reason);
+        r.corruption(drop_start, bytes, reason);
       }
     }
   }
@@ -347,11 +368,11 @@ mod tests {
   use crate::logfile::writer::Writer;
   use std::io::{Seek, SeekFrom, Write};
 
-  struct CorruptionLog(Vec<(u64, String)>);
+  struct CorruptionLog(Vec<(u64, u64, String)>);
 
   impl Reporter for CorruptionLog {
-    fn corruption(&mut self, bytes: u64, reason: &str) {
-      self.0.push((bytes, reason.to_owned()));
+    fn corruption(&mut self, offset: u64, bytes: u64, reason: &str) {
+      self.0.push((offset, bytes, reason.to_owned()));
     }
   }
 
@@ -456,7 +477,9 @@ mod tests {
     // SAFETY: log_ptr is valid for the duration of this test.
     let log = unsafe { &*log_ptr };
     assert!(!log.0.is_empty(), "corruption should have been reported");
-    assert!(log.0[0].1.contains("checksum mismatch"));
+    assert!(log.0[0].2.contains("checksum mismatch"));
+    // The drop starts at the corrupted record's header.
+    assert_eq!(log.0[0].0, (HEADER_SIZE + 4) as u64);
   }
 
   #[test]
@@ -500,6 +523,32 @@ mod tests {
     assert!(r.read_record().is_none());
   }
 
+  #[test]
+  fn torn_write_at_eof_reported_when_requested() {
+    let file = tempfile::tempfile().unwrap();
+    {
+      let mut w = Writer::new(crate::env::writable_from_file(file.try_clone().unwrap()), 0);
+      w.add_record(b"complete").unwrap();
+      w.add_record(b"torn").unwrap();
+    }
+    // Cut the second record's payload short.
+    let len = file.metadata().unwrap().len();
+    file.set_len(len - 2).unwrap();
+
+    let mut f = file.try_clone().unwrap();
+    f.seek(SeekFrom::Start(0)).unwrap();
+    let (mut r, log_ptr) = make_reader_with_reporter(f, true, 0);
+    r.set_report_eof_truncation(true);
+    assert_eq!(r.read_record().unwrap(), b"complete");
+    assert!(r.read_record().is_none());
+
+    // SAFETY: log_ptr is valid for the duration of this test.
+    let log = unsafe { &*log_ptr };
+    assert_eq!(log.0.len(), 1);
+    assert_eq!(log.0[0].0, (HEADER_SIZE + 8) as u64);
+    assert!(log.0[0].2.contains("truncated record body"));
+  }
+
   #[test]
   fn last_record_offset_tracks_correctly() {
     let f = write_records(&[b"a", b"bb", b"ccc"]);
@@ -514,4 +563,23 @@ mod tests {
     assert!(off0 < off1, "{off0} < {off1}");
     assert!(off1 < off2, "{off1} < {off2}");
   }
+
+  #[test]
+  fn resume_after_last_record_reads_only_newer_records() {
+    let f = write_records(&[b"a", b"bb", b"ccc"]);
+    let mut r = make_reader(f.try_clone().unwrap(), true, 0);
+    r.read_record();
+    r.read_record();
+    let resume = r.last_record_offset() + 1;
+
+    let mut f = f;
+    f.seek(SeekFrom::Start(0)).unwrap();
+    let mut r = make_reader(f, true, resume);
+    assert_eq!(r.read_record().unwrap(), b"ccc");
+    assert!(r.read_record().is_none());
+    // Resuming past the end of a log yields nothing.
+    assert!(make_reader(write_records(&[]), true, 1)
+      .read_record()
+      .is_none());
+  }
 }
@@ -14,10 +14,12 @@ use crate::coding::{read_varu64, write_varu64};
 use std::fmt::Debug;
 use std::fmt::Formatter;
 
-#[derive(Debug, PartialEq)]
-enum ValueType {
-  Deletion,
-  Value,
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ValueType {
+  Deletion = 0,
+  Value = 1,
+  Merge = 2,
+  RangeDeletion = 0x0F,
 }
 
 impl TryFrom<u8> for ValueType {
@@ -27,6 +29,8 @@ impl TryFrom<u8> for ValueType {
     match value {
       0 => Ok(ValueType::Deletion),
       1 => Ok(ValueType::Value),
+      2 => Ok(ValueType::Merge),
+      0x0F => Ok(ValueType::RangeDeletion),
       _ => Err(()),
     }
   }
@@ -57,6 +61,22 @@ impl<'a> Entry<'a> {
   /// Encode a value entry into `buf` (which must be exactly
   /// [`encoded_value_size`] bytes long).
   pub(crate) fn write_value_to(buf: &mut [u8], seq: u64, key: &[u8], value: &[u8]) {
+    Self::write_typed_to(buf, seq, ValueType::Value, key, value);
+  }
+
+  /// Encode a merge operand into `buf` (which must be exactly
+  /// [`encoded_value_size`] bytes long — operands share the value layout).
+  pub(crate) fn write_merge_to(buf: &mut [u8], seq: u64, key: &[u8], value: &[u8]) {
+    Self::write_typed_to(buf, seq, ValueType::Merge, key, value);
+  }
+
+  /// Encode a range tombstone `[key, end)` into `buf` (which must be exactly
+  /// [`encoded_value_size`] bytes long — the end key is stored as the value).
+  pub(crate) fn write_range_deletion_to(buf: &mut [u8], seq: u64, key: &[u8], end: &[u8]) {
+    Self::write_typed_to(buf, seq, ValueType::RangeDeletion, key, end);
+  }
+
+  fn write_typed_to(buf: &mut [u8], seq: u64, vtype: ValueType, key: &[u8], value: &[u8]) {
     let mut kd = [0u8; 10];
     let ks = write_varu64(&mut kd, key.len() as u64);
     let mut sd = [0u8; 10];
@@ -71,7 +91,7 @@ impl<'a> Entry<'a> {
     pos += key.len();
     buf[pos..pos + ss].copy_from_slice(&sd[..ss]);
     pos += ss;
-    buf[pos] = ValueType::Value as u8;
+    buf[pos] = vtype as u8;
     pos += 1;
     buf[pos..pos + vs].copy_from_slice(&vd[..vs]);
     pos += vs;
@@ -170,6 +190,19 @@ impl<'a> Entry<'a> {
     &self.data[pos..pos + len as usize]
   }
 
+  /// The entry's value type: a Put, a Deletion tombstone or a Merge operand.
+  pub(crate) fn value_type(&self) -> ValueType {
+    let (klen, ksize) = read_varu64(self.data);
+    let pos = ksize + klen as usize;
+    let (_seq, seq_size) = read_varu64(&self.data[pos..]);
+    match <u8 as TryInto<ValueType>>::try_into(self.data[pos + seq_size]) {
+      Ok(value_type) => value_type,
+      Err(_) => panic!("Corruption! This needs handling... eventually!"),
+    }
+  }
+
+  /// Value bytes for a Put or Merge entry (the end key for a range
+  /// tombstone); `None` for a point tombstone.
   pub fn value(&self) -> Option<&'a [u8]> {
     let (klen, ksize) = read_varu64(self.data);
     let mut pos = ksize + klen as usize;
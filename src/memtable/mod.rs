@@ -15,12 +15,15 @@ mod entry;
 mod skiplist;
 
 use arena::Arena;
-use entry::Entry;
+use entry::{Entry, ValueType};
 use skiplist::SkipList;
 use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use crate::comparator::{BytewiseComparator, Comparator};
+use crate::db::range_del::RangeTombstone;
+use crate::table::get_context::GetContext;
 
 #[derive(Debug, PartialEq)]
 pub enum MemtableResult<T> {
@@ -46,6 +49,16 @@ impl<T> MemtableResult<T> {
 
 pub struct Memtable {
   table: UnsafeCell<SkipList>,
+  /// Range tombstones, keyed by start key (`value` = exclusive end key).
+  /// Kept apart from point entries like RocksDB's `range_del_table_`.
+  range_del_table: UnsafeCell<SkipList>,
+  /// Arena usage of `range_del_table` while empty (its head node), left out
+  /// of `approximate_memory_usage` so a memtable without range tombstones
+  /// reports the same usage as one without the table.
+  range_del_base_usage: usize,
+  /// Sequence number of the first entry inserted, or `0` while empty.
+  /// Every write from this sequence on lives in this memtable or a newer one.
+  first_seq: AtomicU64,
   comparator: Arc<dyn Comparator>,
 }
 
@@ -55,13 +68,18 @@ unsafe impl Sync for Memtable {}
 
 impl Memtable {
   pub fn new(comparator: Arc<dyn Comparator>) -> Self {
+    let range_del_table = SkipList::new(Arena::default(), Arc::clone(&comparator));
     Self {
       table: UnsafeCell::new(SkipList::new(Arena::default(), Arc::clone(&comparator))),
+      range_del_base_usage: range_del_table.arena_memory_usage(),
+      range_del_table: UnsafeCell::new(range_del_table),
+      first_seq: AtomicU64::new(0),
       comparator,
     }
   }
 
   pub fn add(&self, seq: u64, key: &[u8], value: &[u8]) {
+    self.note_sequence(seq);
     let size = Entry::encoded_value_size(seq, key, value);
     // SAFETY: caller holds the DB write mutex, serialising all mutations.
     let table = unsafe { &mut *self.table.get() };
@@ -72,10 +90,25 @@ impl Memtable {
   ///
   /// Returns `Hit(value)` if the newest version with `seq <= sequence` is a
   /// Put, `Deleted` if it is a tombstone, or `Miss` if no visible version
-  /// exists.  Pass `u64::MAX` (or use [`Memtable::get_latest`]) to read the
-  /// absolute latest version.
-  pub fn get<K: AsRef<[u8]>>(&self, key: K, sequence: u64) -> MemtableResult<Vec<u8>> {
+  /// exists.  Pass `u64::MAX` to read the absolute latest version.
+  ///
+  /// Merge operands newer than the returned base are appended to
+  /// `ctx.operands` newest-first; a `Miss` with non-empty operands means the
+  /// base value (if any) lives in an older layer.
+  ///
+  /// `ctx.max_covering_tombstone_seq` is first raised by this memtable's range
+  /// tombstones; an entry older than it reads as `Deleted`.  See
+  /// `db/memtable.cc: MemTable::Get`.
+  pub(crate) fn get<K: AsRef<[u8]>>(
+    &self,
+    key: K,
+    sequence: u64,
+    ctx: &mut GetContext,
+  ) -> MemtableResult<Vec<u8>> {
     let key = key.as_ref();
+    ctx.max_covering_tombstone_seq = ctx
+      .max_covering_tombstone_seq
+      .max(self.max_covering_tombstone_seq(key, sequence));
     // Seek to (key, sequence): in skip-list order (user_key ASC, seq DESC),
     // this positions at the first entry with the same user key and seq ≤ sequence.
     let ssize = Entry::seek_key_size(key, sequence);
@@ -93,29 +126,209 @@ impl Memtable {
     Entry::write_seek_key_to(sbuf, key, sequence);
     // SAFETY: SkipList reads are lock-free via acquire/release atomics.
     let table = unsafe { &*self.table.get() };
-    match table.find_first_at_or_after(sbuf) {
-      Some(payload) => {
-        let e = Entry::from_slice(payload);
-        if self.comparator.compare(e.key(), key) == std::cmp::Ordering::Equal {
-          match e.value().map(Vec::from) {
-            None => MemtableResult::Deleted,
-            Some(val) => MemtableResult::Hit(val),
-          }
-        } else {
-          MemtableResult::Miss
-        }
+    let mut it = table.iter();
+    it.seek(sbuf);
+    // Older versions of the same key follow in sequence-descending order;
+    // keep walking only while the entries are merge operands.  With
+    // user-defined timestamps, versions with an older timestamp follow
+    // whatever their sequence number, so newer ones are skipped.
+    while it.valid() {
+      let e = Entry::from_slice(it.payload());
+      if self
+        .comparator
+        .compare_without_timestamp(e.key(), true, key, true)
+        .is_ne()
+      {
+        break;
+      }
+      if e.sequence_id() > sequence {
+        it.advance();
+        continue;
+      }
+      if e.sequence_id() < ctx.max_covering_tombstone_seq {
+        return MemtableResult::Deleted;
       }
-      None => MemtableResult::Miss,
+      match e.value_type() {
+        ValueType::Value => return MemtableResult::Hit(e.value().unwrap_or(&[]).to_vec()),
+        ValueType::Deletion => return MemtableResult::Deleted,
+        ValueType::Merge => ctx.operands.push(e.value().unwrap_or(&[]).to_vec()),
+        ValueType::RangeDeletion => unreachable!("range tombstones live in range_del_table"),
+      }
+      it.advance();
     }
+    MemtableResult::Miss
   }
 
   pub fn delete(&self, seq: u64, key: &[u8]) {
+    self.note_sequence(seq);
     let size = Entry::encoded_deletion_size(seq, key);
     // SAFETY: caller holds the DB write mutex, serialising all mutations.
     let table = unsafe { &mut *self.table.get() };
     table.alloc_and_insert(size, |buf| Entry::write_deletion_to(buf, seq, key));
   }
 
+  /// Insert a merge operand for `key` at `seq`.
+  pub fn merge(&self, seq: u64, key: &[u8], value: &[u8]) {
+    self.note_sequence(seq);
+    let size = Entry::encoded_value_size(seq, key, value);
+    // SAFETY: caller holds the DB write mutex, serialising all mutations.
+    let table = unsafe { &mut *self.table.get() };
+    table.alloc_and_insert(size, |buf| Entry::write_merge_to(buf, seq, key, value));
+  }
+
+  /// Insert a range tombstone deleting `[begin, end)` at `seq`.
+  pub fn delete_range(&self, seq: u64, begin: &[u8], end: &[u8]) {
+    self.note_sequence(seq);
+    let size = Entry::encoded_value_size(seq, begin, end);
+    // SAFETY: caller holds the DB write mutex, serialising all mutations.
+    let table = unsafe { &mut *self.range_del_table.get() };
+    table.alloc_and_insert(size, |buf| {
+      Entry::write_range_deletion_to(buf, seq, begin, end)
+    });
+  }
+
+  /// Record `seq` as the first sequence number if nothing was inserted yet.
+  fn note_sequence(&self, seq: u64) {
+    if self.first_seq.load(Ordering::Relaxed) == 0 {
+      self.first_seq.store(seq, Ordering::Relaxed);
+    }
+  }
+
+  /// Sequence number of the first entry inserted, or `None` if the memtable
+  /// is empty.  See `db/memtable.h: MemTable::GetFirstSequenceNumber`.
+  pub(crate) fn first_sequence(&self) -> Option<u64> {
+    match self.first_seq.load(Ordering::Relaxed) {
+      0 => None,
+      seq => Some(seq),
+    }
+  }
+
+  /// Sequence number of the newest write to `key` in this memtable — a point
+  /// entry or a range tombstone covering it — or `None` if there is none.
+  ///
+  /// See `db/memtable.cc: MemTable::GetLatestSequenceForKey`.
+  pub(crate) fn latest_sequence_for_key(&self, key: &[u8]) -> Option<u64> {
+    let mut seek_key = vec![0u8; Entry::seek_key_size(key, u64::MAX)];
+    Entry::write_seek_key_to(&mut seek_key, key, u64::MAX);
+    // SAFETY: SkipList reads are lock-free via acquire/release atomics.
+    let table = unsafe { &*self.table.get() };
+    let mut it = table.iter();
+    it.seek(&seek_key);
+    let point = it
+      .valid()
+      .then(|| Entry::from_slice(it.payload()))
+      .filter(|e| self.comparator.compare(e.key(), key).is_eq())
+      .map_or(0, |e| e.sequence_id());
+    match point.max(self.max_covering_tombstone_seq(key, u64::MAX)) {
+      0 => None,
+      seq => Some(seq),
+    }
+  }
+
+  /// `true` if a point entry or range tombstone in this memtable touches the
+  /// user-key range `[smallest, largest]`.
+  ///
+  /// See `db/column_family.cc: ColumnFamilyData::RangesOverlapWithMemtables`
+  /// in RocksDB.
+  pub(crate) fn overlaps(&self, smallest: &[u8], largest: &[u8]) -> bool {
+    let mut seek_key = vec![0u8; Entry::seek_key_size(smallest, u64::MAX)];
+    Entry::write_seek_key_to(&mut seek_key, smallest, u64::MAX);
+    // SAFETY: SkipList reads are lock-free via acquire/release atomics.
+    let table = unsafe { &*self.table.get() };
+    let mut it = table.iter();
+    it.seek(&seek_key);
+    if it.valid()
+      && self
+        .comparator
+        .compare(Entry::from_slice(it.payload()).key(), largest)
+        .is_le()
+    {
+      return true;
+    }
+    self.range_tombstones().iter().any(|t| {
+      self.comparator.compare(&t.start, largest).is_le()
+        && self.comparator.compare(&t.end, smallest).is_gt()
+    })
+  }
+
+  /// The smallest and largest user keys the memtable's point entries and
+  /// range tombstones touch (a tombstone's exclusive end counts), or `None`
+  /// if it is empty.
+  pub(crate) fn user_key_range(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+    // SAFETY: SkipList reads are lock-free via acquire/release atomics.
+    let table = unsafe { &*self.table.get() };
+    let mut it = table.iter();
+    it.seek_to_first();
+    let mut range = it.valid().then(|| {
+      let smallest = Entry::from_slice(it.payload()).key().to_vec();
+      it.seek_to_last();
+      (smallest, Entry::from_slice(it.payload()).key().to_vec())
+    });
+    for t in self.range_tombstones() {
+      match &mut range {
+        Some((smallest, largest)) => {
+          if self.comparator.compare(&t.start, smallest).is_lt() {
+            *smallest = t.start;
+          }
+          if self.comparator.compare(&t.end, largest).is_gt() {
+            *largest = t.end;
+          }
+        }
+        None => range = Some((t.start, t.end)),
+      }
+    }
+    range
+  }
+
+  /// All range tombstones in this memtable, ordered by start key and then
+  /// newest first.
+  pub(crate) fn range_tombstones(&self) -> Vec<RangeTombstone> {
+    // SAFETY: SkipList reads are lock-free via acquire/release atomics.
+    let table = unsafe { &*self.range_del_table.get() };
+    let mut it = table.iter();
+    it.seek_to_first();
+    let mut out = Vec::new();
+    while it.valid() {
+      let e = Entry::from_slice(it.payload());
+      out.push(RangeTombstone {
+        start: e.key().to_vec(),
+        end: e.value().unwrap_or(&[]).to_vec(),
+        seq: e.sequence_id(),
+      });
+      it.advance();
+    }
+    out
+  }
+
+  /// Sequence number of the newest range tombstone visible at `sequence`
+  /// that covers `key`, or `0` if there is none.
+  fn max_covering_tombstone_seq(&self, key: &[u8], sequence: u64) -> u64 {
+    // SAFETY: SkipList reads are lock-free via acquire/release atomics.
+    let table = unsafe { &*self.range_del_table.get() };
+    let mut it = table.iter();
+    it.seek_to_first();
+    let mut max_seq = 0;
+    // Entries are sorted by start key, so stop at the first one past `key`.
+    while it.valid() {
+      let e = Entry::from_slice(it.payload());
+      if self.comparator.compare(e.key(), key).is_gt() {
+        break;
+      }
+      let seq = e.sequence_id();
+      if seq <= sequence
+        && seq > max_seq
+        && self
+          .comparator
+          .compare(key, e.value().unwrap_or(&[]))
+          .is_lt()
+      {
+        max_seq = seq;
+      }
+      it.advance();
+    }
+    max_seq
+  }
+
   /// Return a forward iterator over all entries in internal-key order.
   ///
   /// The returned iterator starts in an invalid (unpositioned) state; the
@@ -135,7 +348,8 @@ impl Memtable {
   pub(crate) fn approximate_memory_usage(&self) -> usize {
     // SAFETY: accessing Arena (which is Send+Sync) from a shared ref is fine.
     let table = unsafe { &*self.table.get() };
-    table.arena_memory_usage()
+    let range_del_table = unsafe { &*self.range_del_table.get() };
+    table.arena_memory_usage() + range_del_table.arena_memory_usage() - self.range_del_base_usage
   }
 }
 
@@ -164,7 +378,7 @@ impl<'a> MemTableIterator<'a> {
   fn update_cached_key(&mut self) {
     if self.inner.valid() {
       let e = Entry::from_slice(self.inner.payload());
-      let vtype: u8 = if e.value().is_some() { 1 } else { 0 };
+      let vtype = e.value_type() as u8;
       // Encode in place so `cached_key`'s allocation is reused across steps
       // instead of allocating a fresh `Vec` per entry.
       crate::table::format::encode_internal_key_into(
@@ -369,7 +583,10 @@ mod tests {
     table.add(0, b"foo", b"bar");
     assert_eq!(
       b"bar",
-      table.get(b"foo", u64::MAX).unwrap_value().as_slice()
+      table
+        .get(b"foo", u64::MAX, &mut GetContext::default())
+        .unwrap_value()
+        .as_slice()
     );
   }
 
@@ -379,12 +596,18 @@ mod tests {
     table.add(0, b"foo", b"foo");
     assert_eq!(
       b"foo",
-      table.get(b"foo", u64::MAX).unwrap_value().as_slice()
+      table
+        .get(b"foo", u64::MAX, &mut GetContext::default())
+        .unwrap_value()
+        .as_slice()
     );
     table.add(1, b"foo", b"bar");
     assert_eq!(
       b"bar",
-      table.get(b"foo", u64::MAX).unwrap_value().as_slice()
+      table
+        .get(b"foo", u64::MAX, &mut GetContext::default())
+        .unwrap_value()
+        .as_slice()
     );
   }
 
@@ -392,13 +615,19 @@ mod tests {
   fn miss_get() {
     let table = Memtable::default();
     table.add(0, b"foo", b"bar");
-    assert_eq!(table.get(b"bar", u64::MAX), MemtableResult::Miss);
+    assert_eq!(
+      table.get(b"bar", u64::MAX, &mut GetContext::default()),
+      MemtableResult::Miss
+    );
   }
 
   #[test]
   fn miss_empty() {
     let table = Memtable::default();
-    assert_eq!(table.get(b"foo", u64::MAX), MemtableResult::Miss);
+    assert_eq!(
+      table.get(b"foo", u64::MAX, &mut GetContext::default()),
+      MemtableResult::Miss
+    );
   }
 
   #[test]
@@ -406,7 +635,10 @@ mod tests {
     let table = Memtable::default();
     table.add(0, b"foo", b"bar");
     table.delete(1, b"foo");
-    assert_eq!(table.get(b"foo", u64::MAX), MemtableResult::Deleted);
+    assert_eq!(
+      table.get(b"foo", u64::MAX, &mut GetContext::default()),
+      MemtableResult::Deleted
+    );
   }
 
   #[test]
@@ -415,19 +647,97 @@ mod tests {
     {
       let foo = String::from("foo");
       table.add(0, foo.as_bytes(), foo.as_bytes());
-      let value = table.get(b"foo", u64::MAX).unwrap_value();
+      let value = table
+        .get(b"foo", u64::MAX, &mut GetContext::default())
+        .unwrap_value();
       assert_eq!("foo", from_utf8(value.as_ref()).unwrap());
     }
     {
       let sparkle_heart = String::from("💖");
       table.add(1, b"foo", sparkle_heart.as_bytes());
     }
-    let value = table.get(b"foo", u64::MAX).unwrap_value();
+    let value = table
+      .get(b"foo", u64::MAX, &mut GetContext::default())
+      .unwrap_value();
     assert_eq!("💖", from_utf8(value.as_ref()).unwrap());
     table.delete(2, b"foo");
     assert_eq!(3, unsafe { &*table.table.get() }.len());
   }
 
+  #[test]
+  fn merge_operands_collected_until_base() {
+    let table = Memtable::default();
+    table.add(1, b"foo", b"base");
+    table.merge(2, b"foo", b"m2");
+    table.merge(3, b"foo", b"m3");
+    let mut ctx = GetContext::default();
+    assert_eq!(
+      table.get(b"foo", u64::MAX, &mut ctx),
+      MemtableResult::Hit(b"base".to_vec())
+    );
+    assert_eq!(ctx.operands, vec![b"m3".to_vec(), b"m2".to_vec()]);
+
+    // Reading at seq 2 sees only the older operand.
+    let mut ctx = GetContext::default();
+    table.get(b"foo", 2, &mut ctx);
+    assert_eq!(ctx.operands, vec![b"m2".to_vec()]);
+  }
+
+  #[test]
+  fn merge_operands_without_base_miss() {
+    let table = Memtable::default();
+    table.merge(1, b"foo", b"m1");
+    table.add(2, b"other", b"x");
+    let mut ctx = GetContext::default();
+    assert_eq!(table.get(b"foo", u64::MAX, &mut ctx), MemtableResult::Miss);
+    assert_eq!(ctx.operands, vec![b"m1".to_vec()]);
+  }
+
+  #[test]
+  fn range_tombstone_hides_older_entries() {
+    let table = Memtable::default();
+    table.add(1, b"a", b"1");
+    table.add(2, b"b", b"2");
+    table.add(3, b"c", b"3");
+    table.delete_range(4, b"a", b"c");
+    table.add(5, b"b", b"5");
+
+    // `b@5` is newer than the tombstone; `a@1` is covered; `c` is past the
+    // exclusive end.
+    assert_eq!(
+      table.get(b"b", u64::MAX, &mut GetContext::default()),
+      MemtableResult::Hit(b"5".to_vec())
+    );
+    let mut ctx = GetContext::default();
+    assert_eq!(table.get(b"a", u64::MAX, &mut ctx), MemtableResult::Deleted);
+    assert_eq!(ctx.max_covering_tombstone_seq, 4);
+    assert_eq!(
+      table.get(b"c", u64::MAX, &mut GetContext::default()),
+      MemtableResult::Hit(b"3".to_vec())
+    );
+
+    // Reading below the tombstone's sequence number ignores it.
+    assert_eq!(
+      table.get(b"b", 3, &mut GetContext::default()),
+      MemtableResult::Hit(b"2".to_vec())
+    );
+
+    // A key the memtable never saw still picks up the covering sequence, so
+    // older layers can apply it.
+    let mut ctx = GetContext::default();
+    assert_eq!(table.get(b"ab", u64::MAX, &mut ctx), MemtableResult::Miss);
+    assert_eq!(ctx.max_covering_tombstone_seq, 4);
+
+    assert_eq!(
+      table.range_tombstones(),
+      vec![RangeTombstone {
+        start: b"a".to_vec(),
+        end: b"c".to_vec(),
+        seq: 4,
+      }]
+    );
+  }
+
   // ── MemTableIterator tests ────────────────────────────────────────────────
 
   use crate::table::format::parse_internal_key;
@@ -473,6 +783,18 @@ mod tests {
     assert!(!it.valid());
   }
 
+  #[test]
+  fn iter_merge_operand_type() {
+    let mem = Memtable::default();
+    mem.merge(4, b"k", b"op");
+    let mut it = mem.iter();
+    it.seek_to_first();
+    let (_, seq, vtype) = parse_internal_key(it.key()).unwrap();
+    assert_eq!(seq, 4);
+    assert_eq!(vtype, 2); // Merge
+    assert_eq!(it.value(), b"op");
+  }
+
   #[test]
   fn iter_ordering_user_key_asc_seq_desc() {
     let mem = Memtable::default();
@@ -533,6 +855,21 @@ mod tests {
     assert_eq!(keys, vec![b"c".to_vec(), b"b".to_vec(), b"a".to_vec()]);
   }
 
+  #[test]
+  fn first_and_latest_sequence() {
+    let mem = Memtable::default();
+    assert_eq!(mem.first_sequence(), None);
+    assert_eq!(mem.latest_sequence_for_key(b"k"), None);
+    mem.add(5, b"k", b"v");
+    mem.delete(7, b"k");
+    mem.add(8, b"l", b"v");
+    assert_eq!(mem.first_sequence(), Some(5));
+    assert_eq!(mem.latest_sequence_for_key(b"k"), Some(7));
+    mem.delete_range(9, b"a", b"z");
+    assert_eq!(mem.latest_sequence_for_key(b"k"), Some(9));
+    assert_eq!(mem.latest_sequence_for_key(b"zz"), None);
+  }
+
   #[test]
   fn approximate_memory_usage_grows() {
     let mem = Memtable::default();
@@ -541,4 +878,19 @@ mod tests {
     let after = mem.approximate_memory_usage();
     assert!(after > before);
   }
+
+  #[test]
+  fn overlaps_checks_points_and_range_tombstones() {
+    let mem = Memtable::default();
+    assert!(!mem.overlaps(b"a", b"z"));
+    mem.add(1, b"m", b"v");
+    assert!(mem.overlaps(b"m", b"m"));
+    assert!(mem.overlaps(b"a", b"z"));
+    assert!(!mem.overlaps(b"a", b"l"));
+    assert!(!mem.overlaps(b"n", b"z"));
+    mem.delete_range(2, b"p", b"r");
+    assert!(mem.overlaps(b"q", b"z"));
+    // The end of a range tombstone is exclusive.
+    assert!(!mem.overlaps(b"r", b"z"));
+  }
 }
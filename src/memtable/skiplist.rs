@@ -398,6 +398,7 @@ impl SkipList {
   ///
   /// Returns `None` if no such node exists.  The returned slice is borrowed
   /// from the `Arena` and valid for the lifetime of `&self`.
+  #[cfg(test)]
   pub fn find_first_at_or_after<'s>(&'s self, key_data: &[u8]) -> Option<&'s [u8]> {
     let mut x = self.head;
     let mut level = self.max_height() - 1;
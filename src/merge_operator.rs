@@ -0,0 +1,80 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! User-supplied merge operators for read-modify-write updates.
+//!
+//! [`Db::merge`](crate::Db::merge) records an *operand* instead of a full value.
+//! Operands accumulate on top of the key's base value (if any) and are folded
+//! together lazily: on reads, and during compaction once no snapshot can observe
+//! the intermediate states.  Counters, append-only lists and set unions can be
+//! updated this way without a `get` + `put` round trip.
+//!
+//! The operator is configured via [`Options::merge_operator`](crate::Options).
+//!
+//! See `include/rocksdb/merge_operator.h`.
+
+use crate::Error;
+
+/// Combines merge operands with a key's existing value.
+///
+/// # Thread safety
+///
+/// A single operator instance is shared (via `Arc` in `Options`) by readers,
+/// writers and background compactions, so it must be `Send + Sync`.
+///
+/// See `include/rocksdb/merge_operator.h: MergeOperator`.
+pub trait MergeOperator: Send + Sync {
+  /// Apply `operands` to `existing` and return the resulting value.
+  ///
+  /// - `key`: the user key.
+  /// - `existing`: the base value, or `None` if the key had no value (never
+  ///   written, or deleted) before the first operand.
+  /// - `operands`: operands in the order they were written (oldest first).
+  ///
+  /// Returning `None` signals that the operands could not be applied; reads
+  /// of the key then fail with [`Error::Corruption`](crate::Error::Corruption).
+  fn full_merge(&self, key: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Option<Vec<u8>>;
+
+  /// Combine two adjacent operands into one, without knowing the base value.
+  ///
+  /// `left` was written before `right`.  Used by compaction to shrink operand
+  /// chains whose base value lives in a deeper level.  Return `None` (the
+  /// default) when the operands cannot be combined; both are then kept as-is.
+  fn partial_merge(&self, _key: &[u8], _left: &[u8], _right: &[u8]) -> Option<Vec<u8>> {
+    None
+  }
+
+  /// Human-readable name for logging.
+  fn name(&self) -> &str;
+}
+
+/// Fold `operands` (newest first, as collected by a point lookup or an
+/// iterator) onto `existing` with the configured operator.
+///
+/// Returns [`Error::InvalidArgument`] if no operator is configured and
+/// [`Error::Corruption`] if the operator rejects the operands.
+pub(crate) fn full_merge(
+  operator: Option<&std::sync::Arc<dyn MergeOperator>>,
+  key: &[u8],
+  existing: Option<&[u8]>,
+  operands: &[Vec<u8>],
+) -> Result<Vec<u8>, Error> {
+  let Some(operator) = operator else {
+    return Err(Error::InvalidArgument(
+      "merge operand found but no merge operator configured".to_string(),
+    ));
+  };
+  let ordered: Vec<&[u8]> = operands.iter().rev().map(Vec::as_slice).collect();
+  operator
+    .full_merge(key, existing, &ordered)
+    .ok_or_else(|| Error::Corruption(format!("merge operator {} failed", operator.name())))
+}
@@ -23,6 +23,141 @@ pub enum CompressionType {
   Zstd(i32),
 }
 
+/// How [`Db::open`](crate::Db::open) treats corruption found while replaying
+/// the write-ahead log.
+///
+/// Whatever the mode, every dropped range is logged with the WAL's file
+/// number and the byte offset where it starts.
+///
+/// See `include/rocksdb/options.h: WALRecoveryMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalRecoveryMode {
+  /// Ignore an incomplete record at the end of the log (a write torn by a
+  /// crash) but fail the open on corruption anywhere else.
+  TolerateCorruptedTailRecords,
+  /// Fail the open on any corruption, including a torn final record.
+  AbsoluteConsistency,
+  /// Replay up to the first corruption and discard the rest of the log, so
+  /// the database opens at a consistent point in time.
+  #[default]
+  PointInTimeRecovery,
+  /// Skip every corrupted record and replay everything else.
+  SkipAnyCorruptedRecords,
+}
+
+/// How a column family's SSTables are organised and merged.
+///
+/// The style can be changed on reopen: each picker works on any layout the
+/// other leaves behind.
+///
+/// See `include/rocksdb/advanced_options.h: CompactionStyle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompactionStyle {
+  /// Leveled compaction: each level below L0 is kept within a byte budget
+  /// ten times that of the level above, and files are merged one level down
+  /// at a time.  Low space and read amplification.
+  #[default]
+  Level,
+  /// Universal (tiered) compaction: whole sorted runs of similar size are
+  /// merged together, tuned by [`CompactionOptionsUniversal`].  Each byte is
+  /// rewritten far fewer times than with `Level`, at the cost of more sorted
+  /// runs to read and up to `max_size_amplification_percent` extra space.
+  Universal,
+  /// FIFO compaction for data that only matters while it is recent, such as
+  /// logs and metrics: every flush stays in L0 and the oldest files are
+  /// dropped once the budget or age limit of [`CompactionOptionsFIFO`] is
+  /// exceeded.  Nothing is merged down, so updates and deletions never
+  /// reclaim space; the data goes away with its file.  Blob files are not
+  /// reclaimed when the tables referencing them are dropped, so this style
+  /// is not meant for use with [`Options::enable_blob_files`].
+  Fifo,
+}
+
+/// Tuning for [`CompactionStyle::Universal`].
+///
+/// A *sorted run* is an L0 file or a whole level below L0.  Compaction starts
+/// once there are `level0_file_num_compaction_trigger` (4) sorted runs.
+///
+/// See `include/rocksdb/universal_compaction.h`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionOptionsUniversal {
+  /// Percentage by which a sorted run may be larger than the runs newer than
+  /// it combined and still be merged with them.
+  ///
+  /// Default: 1.
+  pub size_ratio: u32,
+
+  /// Fewest sorted runs merged by a size-ratio compaction.  Values below `2`
+  /// are treated as `2`.
+  ///
+  /// Default: 2.
+  pub min_merge_width: usize,
+
+  /// Most sorted runs merged by a size-ratio compaction.
+  ///
+  /// Default: `usize::MAX`.
+  pub max_merge_width: usize,
+
+  /// When the sorted runs other than the oldest add up to more than this
+  /// percentage of the oldest, every run is merged into one.
+  ///
+  /// Default: 200.
+  pub max_size_amplification_percent: u32,
+}
+
+impl Default for CompactionOptionsUniversal {
+  fn default() -> Self {
+    CompactionOptionsUniversal {
+      size_ratio: 1,
+      min_merge_width: 2,
+      max_merge_width: usize::MAX,
+      max_size_amplification_percent: 200,
+    }
+  }
+}
+
+/// Tuning for [`CompactionStyle::Fifo`].
+///
+/// See `include/rocksdb/advanced_options.h: CompactionOptionsFIFO`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionOptionsFIFO {
+  /// Once the SSTables of a column family add up to more than this many
+  /// bytes, the oldest are deleted until they fit again.
+  ///
+  /// Default: 1 GiB.
+  pub max_table_files_size: u64,
+
+  /// Delete SSTables whose oldest data was written longer ago than this.
+  /// Expired files are dropped the next time compactions are scheduled, as
+  /// after a flush; the age is kept in the MANIFEST, so it survives
+  /// reopening.
+  ///
+  /// See `ttl` in `include/rocksdb/advanced_options.h`, a column family
+  /// option there.
+  ///
+  /// Default: `None`.
+  pub ttl: Option<std::time::Duration>,
+
+  /// Merge the newest L0 files into one once there are
+  /// `level0_file_num_compaction_trigger` (4) of them no larger than 1.1 ×
+  /// `write_buffer_size`, to keep the file count down.  Merged files are
+  /// deleted as a whole, when their oldest data expires.  Not done with
+  /// [`Options::enable_blob_files`].
+  ///
+  /// Default: false.
+  pub allow_compaction: bool,
+}
+
+impl Default for CompactionOptionsFIFO {
+  fn default() -> Self {
+    CompactionOptionsFIFO {
+      max_table_files_size: 1024 * 1024 * 1024,
+      ttl: None,
+      allow_compaction: false,
+    }
+  }
+}
+
 /// Options that control the overall behaviour of a database.
 ///
 /// See `include/leveldb/options.h`.
@@ -39,12 +174,19 @@ pub struct Options {
   /// Default: false.
   pub error_if_exists: bool,
 
+  /// When opening with [`Db::open_with_column_families`](crate::Db::open_with_column_families),
+  /// create any listed column family the database does not have yet instead of failing.
+  ///
+  /// Default: false.
+  pub create_missing_column_families: bool,
+
   /// Aggressively check data integrity; stop early on any detected error.
   ///
   /// When set, acts as a database-wide `verify_checksums = true`: every SSTable block read
   /// (both point lookups via [`Db::get`] and iterator block reads) verifies its CRC32c checksum,
-  /// and WAL and MANIFEST records are checksum-verified during recovery.  Any mismatch returns
-  /// [`Error::Corruption`].
+  /// and MANIFEST records are checksum-verified during recovery.  Any mismatch returns
+  /// [`Error::Corruption`].  WAL records are always checksum-verified; what happens on a
+  /// mismatch is set by [`Options::wal_recovery_mode`].
   ///
   /// This flag is OR-ed with [`ReadOptions::verify_checksums`] on each individual read, so
   /// per-read verification can also be enabled independently.
@@ -52,6 +194,12 @@ pub struct Options {
   /// Default: false.
   pub paranoid_checks: bool,
 
+  /// How corruption in the write-ahead log is handled when the database is
+  /// opened; see [`WalRecoveryMode`].
+  ///
+  /// Default: [`WalRecoveryMode::PointInTimeRecovery`].
+  pub wal_recovery_mode: WalRecoveryMode,
+
   // ── Performance ─────────────────────────────────────────────────────────
   /// Bytes of key-value data to accumulate in the memtable before flushing to an L0 SSTable.
   /// Larger values improve bulk-load throughput at the cost of higher memory use and longer
@@ -83,6 +231,110 @@ pub struct Options {
   /// Default: 2 MiB.
   pub max_file_size: usize,
 
+  /// Maximum number of flushes and compactions that run at the same time, each
+  /// on its own background thread.  One thread only flushes, so a flush never
+  /// waits behind a compaction; the others compact.  Compactions only run
+  /// together when they share no input files and their outputs do not
+  /// overlap.  Values below `2` are treated as `2`.
+  ///
+  /// See `include/rocksdb/options.h: DBOptions::max_background_jobs`.
+  ///
+  /// Default: 2.
+  pub max_background_jobs: usize,
+
+  /// Maximum number of threads a single compaction is split across.  A
+  /// compaction whose inputs exceed `max_file_size` is divided into disjoint
+  /// key ranges that are merged in parallel; the outputs of all ranges are
+  /// installed together.  `0` is treated as `1`.
+  ///
+  /// See `include/rocksdb/options.h: DBOptions::max_subcompactions`.
+  ///
+  /// Default: 1.
+  pub max_subcompactions: usize,
+
+  /// How SSTables are organised and merged; see [`CompactionStyle`].
+  ///
+  /// See `include/rocksdb/advanced_options.h: compaction_style`.
+  ///
+  /// Default: `Level`.
+  pub compaction_style: CompactionStyle,
+
+  /// Tuning for [`CompactionStyle::Universal`]; ignored by other styles.
+  ///
+  /// See `include/rocksdb/advanced_options.h: compaction_options_universal`.
+  pub compaction_options_universal: CompactionOptionsUniversal,
+
+  /// Tuning for [`CompactionStyle::Fifo`]; ignored by other styles.
+  ///
+  /// See `include/rocksdb/advanced_options.h: compaction_options_fifo`.
+  pub compaction_options_fifo: CompactionOptionsFIFO,
+
+  // ── LSM shape ───────────────────────────────────────────────────────────
+  /// Number of levels, L0 included.  A database cannot be reopened with
+  /// fewer levels than hold its files.  Must be at least 2 for
+  /// [`CompactionStyle::Level`] and at least 1 otherwise.
+  ///
+  /// See `include/rocksdb/advanced_options.h: num_levels`.
+  ///
+  /// Default: 7.
+  pub num_levels: usize,
+
+  /// Number of L0 files that triggers a compaction of L0.  Must be at least
+  /// 1.
+  ///
+  /// See `include/rocksdb/options.h: level0_file_num_compaction_trigger`.
+  ///
+  /// Default: 4.
+  pub level0_file_num_compaction_trigger: usize,
+
+  /// Number of L0 files at which writes are delayed by 1 ms each.  Values
+  /// below `level0_file_num_compaction_trigger` are treated as it.
+  ///
+  /// See `include/rocksdb/advanced_options.h: level0_slowdown_writes_trigger`.
+  ///
+  /// Default: 8.
+  pub level0_slowdown_writes_trigger: usize,
+
+  /// Number of L0 files at which writes stop until a compaction brings the
+  /// count down.  Values below `level0_slowdown_writes_trigger` are treated
+  /// as it.
+  ///
+  /// See `include/rocksdb/advanced_options.h: level0_stop_writes_trigger`.
+  ///
+  /// Default: 12.
+  pub level0_stop_writes_trigger: usize,
+
+  /// Target size of L1 under [`CompactionStyle::Level`]; L1 is compacted once
+  /// it holds more.
+  ///
+  /// See `include/rocksdb/options.h: max_bytes_for_level_base`.
+  ///
+  /// Default: 10 MiB.
+  pub max_bytes_for_level_base: u64,
+
+  /// Growth of the target size from one level to the next below L1.  Must be
+  /// positive.
+  ///
+  /// See `include/rocksdb/advanced_options.h: max_bytes_for_level_multiplier`.
+  ///
+  /// Default: 10.
+  pub max_bytes_for_level_multiplier: f64,
+
+  /// Under [`CompactionStyle::Level`], derive the level target sizes from
+  /// the size of the last level instead of growing them from
+  /// `max_bytes_for_level_base` at L1.  Each level above the last is
+  /// `max_bytes_for_level_multiplier` times smaller, and L0 compacts straight
+  /// into the first level whose target is no more than
+  /// `max_bytes_for_level_base`; the levels above it stay empty.  A small
+  /// database then keeps its data in few levels, and about 90 % of it in the
+  /// last one, which bounds space amplification.  Flushes always go to L0.
+  ///
+  /// See `include/rocksdb/advanced_options.h:
+  /// level_compaction_dynamic_level_bytes`.
+  ///
+  /// Default: `false`.
+  pub level_compaction_dynamic_level_bytes: bool,
+
   /// Compression algorithm applied to SSTable data blocks.
   ///
   /// Use `CompressionType::Zstd(level)` to enable Zstd at a specific level (`[-5, 22]`).
@@ -90,6 +342,26 @@ pub struct Options {
   /// Default: `Snappy`.
   pub compression: CompressionType,
 
+  /// Compression per level, overriding `compression`.  Entry 0 applies to
+  /// L0 and flushes, entry 1 to the level L0 compacts into (L1, or the base
+  /// level with `level_compaction_dynamic_level_bytes`), and so on; the last
+  /// entry covers every deeper level.  Cheap compression suits the upper
+  /// levels, whose data is soon rewritten.
+  ///
+  /// See `include/rocksdb/advanced_options.h: compression_per_level`.
+  ///
+  /// Default: empty (use `compression` everywhere).
+  pub compression_per_level: Vec<CompressionType>,
+
+  /// Compression for compaction outputs with no data below them, where most
+  /// of the bytes end up, overriding `compression` and
+  /// `compression_per_level`.
+  ///
+  /// See `include/rocksdb/advanced_options.h: bottommost_compression`.
+  ///
+  /// Default: `None`.
+  pub bottommost_compression: Option<CompressionType>,
+
   /// Reuse existing MANIFEST and log files on open rather than creating new ones (experimental).
   ///
   /// **Not yet implemented.** Accepted but ignored.
@@ -97,6 +369,28 @@ pub struct Options {
   /// Default: false.
   pub reuse_logs: bool,
 
+  /// Move obsolete WAL files into an `archive/` subdirectory instead of
+  /// deleting them, and delete archived files once they are older than this.
+  /// Archived WALs feed [`Db::get_updates_since`](crate::Db::get_updates_since).
+  ///
+  /// WALs are archived when either this or [`Options::wal_size_limit`] is
+  /// set; when both are, a file is deleted once either limit is exceeded.
+  /// Age is measured from the file's last modification time, by
+  /// [`Options::clock`].
+  ///
+  /// See `WAL_ttl_seconds` in `include/rocksdb/options.h`.
+  ///
+  /// Default: `None`.
+  pub wal_ttl: Option<std::time::Duration>,
+
+  /// Archive obsolete WAL files (see [`Options::wal_ttl`]) and delete the
+  /// oldest archived files while their total size exceeds this many bytes.
+  ///
+  /// See `WAL_size_limit_MB` in `include/rocksdb/options.h`.
+  ///
+  /// Default: `None`.
+  pub wal_size_limit: Option<u64>,
+
   // ── Filters ─────────────────────────────────────────────────────────────
   /// Filter policy applied to SSTable data blocks.
   ///
@@ -148,6 +442,13 @@ pub struct Options {
   /// Default: [`PosixFileSystem`](crate::PosixFileSystem) (local filesystem via `std::fs`).
   pub file_system: std::sync::Arc<dyn crate::env::FileSystem>,
 
+  /// Source of the current time, against which archived WALs are aged for
+  /// [`Options::wal_ttl`] and FIFO compaction ages files for
+  /// [`CompactionOptionsFIFO::ttl`], and with which backups are timestamped.
+  ///
+  /// Default: [`SystemClock`](crate::SystemClock).
+  pub clock: std::sync::Arc<dyn crate::env::Clock>,
+
   // ── Compaction filter ─────────────────────────────────────────────────
   /// Factory that creates a [`CompactionFilter`](crate::CompactionFilter) for each compaction run.
   ///
@@ -159,6 +460,63 @@ pub struct Options {
   /// See `include/rocksdb/compaction_filter.h`.
   pub compaction_filter_factory:
     Option<std::sync::Arc<dyn crate::compaction_filter::CompactionFilterFactory>>,
+
+  // ── Merge operator ────────────────────────────────────────────────────
+  /// Operator that folds [`Db::merge`](crate::Db::merge) operands into a key's value.
+  ///
+  /// Required for `merge`: writing or reading a merge operand without an operator configured
+  /// returns an error.  Reopening a database that contains operands must use an operator with
+  /// the same semantics.
+  ///
+  /// Default: `None`.
+  ///
+  /// See `include/rocksdb/merge_operator.h`.
+  pub merge_operator: Option<std::sync::Arc<dyn crate::merge_operator::MergeOperator>>,
+
+  // ── Blob files ────────────────────────────────────────────────────────
+  /// Store values of at least `min_blob_size` bytes in separate blob files,
+  /// leaving only a small blob reference in the SSTables.
+  ///
+  /// Large values are then written once (at flush) instead of being rewritten
+  /// by every compaction, at the cost of one extra read per lookup of a
+  /// separated value.  Existing blob references stay readable when this is
+  /// turned off again.
+  ///
+  /// Default: false.
+  ///
+  /// See `include/rocksdb/advanced_options.h: enable_blob_files`.
+  pub enable_blob_files: bool,
+
+  /// Values at least this large are separated into blob files when
+  /// `enable_blob_files` is set.
+  ///
+  /// Default: 0 (every value).
+  pub min_blob_size: usize,
+
+  /// Size at which compaction closes a blob file and starts a new one.  A
+  /// flush writes at most one blob file per column family.
+  ///
+  /// Default: 256 MiB.
+  pub blob_file_size: u64,
+
+  /// Relocate the live blobs of the oldest blob files during compaction, so
+  /// that those files become pure garbage and are deleted.
+  ///
+  /// Default: false.
+  pub enable_blob_garbage_collection: bool,
+
+  /// Fraction of blob files, oldest first, whose blobs compaction relocates
+  /// when `enable_blob_garbage_collection` is set.  Must be in `[0.0, 1.0]`.
+  ///
+  /// Default: 0.25.
+  pub blob_garbage_collection_age_cutoff: f64,
+
+  /// When the blob files within the age cutoff are at least this fraction
+  /// garbage, the SSTables referencing them are compacted even if no level
+  /// needs compaction.  `1.0` disables forced collection.
+  ///
+  /// Default: 1.0.
+  pub blob_garbage_collection_force_threshold: f64,
 }
 
 impl Default for Options {
@@ -166,21 +524,47 @@ impl Default for Options {
     Options {
       create_if_missing: false,
       error_if_exists: false,
+      create_missing_column_families: false,
       paranoid_checks: false,
+      wal_recovery_mode: WalRecoveryMode::PointInTimeRecovery,
       write_buffer_size: 4 * 1024 * 1024,
       max_open_files: 1_000,
       block_size: 4 * 1024,
       block_restart_interval: 16,
       max_file_size: 2 * 1024 * 1024,
+      max_background_jobs: 2,
+      max_subcompactions: 1,
+      compaction_style: CompactionStyle::Level,
+      compaction_options_universal: CompactionOptionsUniversal::default(),
+      compaction_options_fifo: CompactionOptionsFIFO::default(),
+      num_levels: 7,
+      level0_file_num_compaction_trigger: 4,
+      level0_slowdown_writes_trigger: 8,
+      level0_stop_writes_trigger: 12,
+      max_bytes_for_level_base: 10 * 1024 * 1024,
+      max_bytes_for_level_multiplier: 10.0,
+      level_compaction_dynamic_level_bytes: false,
       compression: CompressionType::Snappy,
+      compression_per_level: Vec::new(),
+      bottommost_compression: None,
       reuse_logs: false,
+      wal_ttl: None,
+      wal_size_limit: None,
       filter_policy: None,
       block_cache: Some(std::sync::Arc::new(crate::cache::BlockCache::new(
         crate::cache::DEFAULT_BLOCK_CACHE_CAPACITY,
       ))),
       comparator: std::sync::Arc::new(crate::comparator::BytewiseComparator),
       compaction_filter_factory: None,
+      merge_operator: None,
+      enable_blob_files: false,
+      min_blob_size: 0,
+      blob_file_size: 256 * 1024 * 1024,
+      enable_blob_garbage_collection: false,
+      blob_garbage_collection_age_cutoff: 0.25,
+      blob_garbage_collection_force_threshold: 1.0,
       file_system: std::sync::Arc::new(crate::env::PosixFileSystem),
+      clock: std::sync::Arc::new(crate::env::SystemClock),
     }
   }
 }
@@ -190,14 +574,53 @@ impl std::fmt::Debug for Options {
     f.debug_struct("Options")
       .field("create_if_missing", &self.create_if_missing)
       .field("error_if_exists", &self.error_if_exists)
+      .field(
+        "create_missing_column_families",
+        &self.create_missing_column_families,
+      )
       .field("paranoid_checks", &self.paranoid_checks)
+      .field("wal_recovery_mode", &self.wal_recovery_mode)
       .field("write_buffer_size", &self.write_buffer_size)
       .field("max_open_files", &self.max_open_files)
       .field("block_size", &self.block_size)
       .field("block_restart_interval", &self.block_restart_interval)
       .field("max_file_size", &self.max_file_size)
+      .field("max_background_jobs", &self.max_background_jobs)
+      .field("max_subcompactions", &self.max_subcompactions)
+      .field("compaction_style", &self.compaction_style)
+      .field(
+        "compaction_options_universal",
+        &self.compaction_options_universal,
+      )
+      .field("compaction_options_fifo", &self.compaction_options_fifo)
+      .field("num_levels", &self.num_levels)
+      .field(
+        "level0_file_num_compaction_trigger",
+        &self.level0_file_num_compaction_trigger,
+      )
+      .field(
+        "level0_slowdown_writes_trigger",
+        &self.level0_slowdown_writes_trigger,
+      )
+      .field(
+        "level0_stop_writes_trigger",
+        &self.level0_stop_writes_trigger,
+      )
+      .field("max_bytes_for_level_base", &self.max_bytes_for_level_base)
+      .field(
+        "max_bytes_for_level_multiplier",
+        &self.max_bytes_for_level_multiplier,
+      )
+      .field(
+        "level_compaction_dynamic_level_bytes",
+        &self.level_compaction_dynamic_level_bytes,
+      )
       .field("compression", &self.compression)
+      .field("compression_per_level", &self.compression_per_level)
+      .field("bottommost_compression", &self.bottommost_compression)
       .field("reuse_logs", &self.reuse_logs)
+      .field("wal_ttl", &self.wal_ttl)
+      .field("wal_size_limit", &self.wal_size_limit)
       .field(
         "filter_policy",
         &self.filter_policy.as_ref().map(|p| p.name()),
@@ -208,10 +631,30 @@ impl std::fmt::Debug for Options {
       )
       .field("comparator", &self.comparator.name())
       .field("file_system", &"<FileSystem>")
+      .field("clock", &"<Clock>")
       .field(
         "compaction_filter_factory",
         &self.compaction_filter_factory.as_ref().map(|f| f.name()),
       )
+      .field(
+        "merge_operator",
+        &self.merge_operator.as_ref().map(|m| m.name()),
+      )
+      .field("enable_blob_files", &self.enable_blob_files)
+      .field("min_blob_size", &self.min_blob_size)
+      .field("blob_file_size", &self.blob_file_size)
+      .field(
+        "enable_blob_garbage_collection",
+        &self.enable_blob_garbage_collection,
+      )
+      .field(
+        "blob_garbage_collection_age_cutoff",
+        &self.blob_garbage_collection_age_cutoff,
+      )
+      .field(
+        "blob_garbage_collection_force_threshold",
+        &self.blob_garbage_collection_force_threshold,
+      )
       .finish()
   }
 }
@@ -251,3 +694,24 @@ impl Default for FlushOptions {
     FlushOptions { wait: true }
   }
 }
+
+/// Options that control [`Db::ingest_external_file`](crate::Db::ingest_external_file).
+///
+/// See `include/rocksdb/options.h: IngestExternalFileOptions`.
+#[derive(Debug, Clone)]
+pub struct IngestExternalFileOptions {
+  /// If the ingested key ranges overlap the memtable, flush it first so the
+  /// ingested entries, which take a newer sequence number, land above it.
+  /// If `false`, such an ingestion fails with `InvalidArgument` instead.
+  ///
+  /// Default: `true`.
+  pub allow_blocking_flush: bool,
+}
+
+impl Default for IngestExternalFileOptions {
+  fn default() -> Self {
+    IngestExternalFileOptions {
+      allow_blocking_flush: true,
+    }
+  }
+}
@@ -0,0 +1,197 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Offline construction of SSTables for bulk loading.
+//!
+//! An [`SstFileWriter`] writes sorted entries straight into a table file,
+//! bypassing the WAL, the memtable and compaction.  The finished file is
+//! added to a database with [`Db::ingest_external_file`], which gives all of
+//! its entries a single sequence number.
+//!
+//! See `include/rocksdb/sst_file_writer.h` and `table/sst_file_writer.cc` in
+//! RocksDB.
+//!
+//! [`Db::ingest_external_file`]: crate::Db::ingest_external_file
+
+use crate::comparator::Comparator;
+use crate::error::Error;
+use crate::options::Options;
+use crate::table::builder::TableBuilder;
+use crate::table::format::{
+  make_internal_key, VALUE_TYPE_DELETION, VALUE_TYPE_MERGE, VALUE_TYPE_VALUE,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Writes a table file from entries supplied in strictly increasing key
+/// order.
+///
+/// The block size, restart interval, filter policy, compression and
+/// comparator are taken from the [`Options`] passed to
+/// [`create`](Self::create); the comparator must be the one of the database
+/// the file will be ingested into.
+///
+/// ```no_run
+/// use roughdb::{Options, SstFileWriter};
+///
+/// let opts = Options::default();
+/// let mut writer = SstFileWriter::create("/tmp/bulk.sst", &opts).unwrap();
+/// writer.put(b"a", b"1").unwrap();
+/// writer.put(b"b", b"2").unwrap();
+/// let info = writer.finish().unwrap();
+/// assert_eq!(info.num_entries, 2);
+/// ```
+pub struct SstFileWriter {
+  path: PathBuf,
+  builder: Option<TableBuilder>,
+  comparator: Arc<dyn Comparator>,
+  smallest_key: Vec<u8>,
+  largest_key: Vec<u8>,
+}
+
+/// Describes a file written by [`SstFileWriter`].
+///
+/// See `include/rocksdb/sst_file_writer.h: ExternalSstFileInfo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalSstFileInfo {
+  pub file_path: PathBuf,
+  /// The first and last user keys in the file.
+  pub smallest_key: Vec<u8>,
+  pub largest_key: Vec<u8>,
+  pub num_entries: u64,
+  pub file_size: u64,
+}
+
+impl SstFileWriter {
+  /// Create the file at `path` on `options.file_system`, replacing any
+  /// existing file.
+  pub fn create(path: impl AsRef<Path>, options: &Options) -> Result<Self, Error> {
+    let path = path.as_ref().to_path_buf();
+    let file = options.file_system.create_writable(&path)?;
+    let builder = TableBuilder::new(
+      file,
+      options.block_size,
+      options.block_restart_interval,
+      options.filter_policy.clone(),
+      options.compression,
+      Arc::clone(&options.comparator),
+    );
+    Ok(SstFileWriter {
+      path,
+      builder: Some(builder),
+      comparator: Arc::clone(&options.comparator),
+      smallest_key: Vec::new(),
+      largest_key: Vec::new(),
+    })
+  }
+
+  /// Add `key` → `value`.
+  pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+    self.add(key, VALUE_TYPE_VALUE, value)
+  }
+
+  /// Add a merge operand for `key`.  It is combined with whatever the
+  /// database already holds for `key` when the file is read.
+  pub fn merge(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+    self.add(key, VALUE_TYPE_MERGE, value)
+  }
+
+  /// Add a deletion of `key`, hiding older values of it once ingested.
+  pub fn delete(&mut self, key: &[u8]) -> Result<(), Error> {
+    self.add(key, VALUE_TYPE_DELETION, b"")
+  }
+
+  /// Write the remaining blocks and the footer, and sync the file.
+  ///
+  /// Returns `InvalidArgument` if no entries were added; the partial file is
+  /// left in place.
+  pub fn finish(mut self) -> Result<ExternalSstFileInfo, Error> {
+    let builder = self.builder.take().expect("finish called once");
+    let num_entries = builder.num_entries();
+    if num_entries == 0 {
+      builder.abandon();
+      return Err(Error::InvalidArgument(
+        "cannot create an SST file with no entries".to_owned(),
+      ));
+    }
+    let file_size = builder.finish()?;
+    Ok(ExternalSstFileInfo {
+      file_path: self.path,
+      smallest_key: self.smallest_key,
+      largest_key: self.largest_key,
+      num_entries,
+      file_size,
+    })
+  }
+
+  fn add(&mut self, key: &[u8], vtype: u8, value: &[u8]) -> Result<(), Error> {
+    let builder = self.builder.as_mut().expect("not finished");
+    if builder.num_entries() > 0
+      && self.comparator.compare(key, &self.largest_key) != std::cmp::Ordering::Greater
+    {
+      return Err(Error::InvalidArgument(
+        "keys must be added in strictly increasing order".to_owned(),
+      ));
+    }
+    // Ingestion replaces sequence number 0 with one assigned at ingest time.
+    builder.add(&make_internal_key(key, 0, vtype), value)?;
+    if builder.num_entries() == 1 {
+      self.smallest_key = key.to_vec();
+    }
+    self.largest_key = key.to_vec();
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::env::MemFileSystem;
+
+  fn mem_options() -> Options {
+    Options {
+      file_system: Arc::new(MemFileSystem::new()),
+      ..Options::default()
+    }
+  }
+
+  #[test]
+  fn finish_reports_key_range_and_entry_count() {
+    let opts = mem_options();
+    let mut w = SstFileWriter::create("/x.sst", &opts).unwrap();
+    w.put(b"a", b"1").unwrap();
+    w.delete(b"b").unwrap();
+    w.merge(b"c", b"3").unwrap();
+    let info = w.finish().unwrap();
+    assert_eq!(info.smallest_key, b"a");
+    assert_eq!(info.largest_key, b"c");
+    assert_eq!(info.num_entries, 3);
+    assert_eq!(
+      info.file_size,
+      opts.file_system.file_size(Path::new("/x.sst")).unwrap()
+    );
+  }
+
+  #[test]
+  fn out_of_order_and_empty_are_rejected() {
+    let opts = mem_options();
+    let mut w = SstFileWriter::create("/x.sst", &opts).unwrap();
+    w.put(b"b", b"1").unwrap();
+    assert!(matches!(w.put(b"b", b"2"), Err(Error::InvalidArgument(_))));
+    assert!(matches!(w.put(b"a", b"2"), Err(Error::InvalidArgument(_))));
+    w.put(b"c", b"3").unwrap();
+    assert_eq!(w.finish().unwrap().num_entries, 2);
+
+    let w = SstFileWriter::create("/y.sst", &opts).unwrap();
+    assert!(matches!(w.finish(), Err(Error::InvalidArgument(_))));
+  }
+}
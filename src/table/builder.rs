@@ -10,15 +10,32 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use crate::env::WritableFile;
+use crate::comparator::BytewiseComparator;
+use crate::db::range_del::RangeTombstone;
+use crate::env::{RandomAccessFile, WritableFile};
 use crate::error::Error;
 use crate::filter::FilterPolicy;
+use crate::iter::InternalIterator;
 use crate::options::CompressionType;
+use crate::table::block::Block;
 use crate::table::block_builder::BlockBuilder;
 use crate::table::filter_block::FilterBlockWriter;
-use crate::table::format::{write_raw_block, BlockHandle, Footer, FOOTER_ENCODED_LENGTH};
+use crate::table::format::{
+  cmp_internal_keys, read_block, read_exact_at, write_raw_block, BlockHandle, Footer,
+  BLOCK_TRAILER_SIZE, FOOTER_ENCODED_LENGTH,
+};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+/// Metaindex key of the range-del block.  Matches RocksDB's
+/// `kRangeDelBlockName` so the layout stays recognisable.
+pub(crate) const RANGE_DEL_BLOCK_NAME: &str = "rocksdb.range_del";
+
+/// Metaindex key of an ingested table's global sequence number, stored as a
+/// fixed64 value rather than a block handle.  RocksDB keeps it, under this
+/// name, in the properties block.
+pub(crate) const GLOBAL_SEQNO_NAME: &str = "rocksdb.external_sst_file.global_seqno";
+
 /// Builds an SSTable file from sorted key-value pairs.
 ///
 /// Typical usage:
@@ -46,6 +63,8 @@ pub(crate) struct TableBuilder {
   filter_policy_name: Option<String>,
   compression: CompressionType,
   comparator: Arc<dyn crate::comparator::Comparator>,
+  /// Range tombstones for the range-del block, in any order.
+  range_tombstones: Vec<RangeTombstone>,
 }
 
 impl TableBuilder {
@@ -88,6 +107,7 @@ impl TableBuilder {
       filter_policy_name,
       compression,
       comparator,
+      range_tombstones: Vec::new(),
     }
   }
 
@@ -110,8 +130,13 @@ impl TableBuilder {
     // LevelDB wraps the user's FilterPolicy with InternalFilterPolicy, which
     // strips the 8-byte sequence+type suffix before hashing.  We replicate
     // that behaviour here: the filter always stores and queries user keys.
+    // A user-defined timestamp is stripped too, so that a lookup as of any
+    // timestamp probes the same filter key.
     if let Some(fw) = self.filter_writer.as_mut() {
-      fw.add_key(crate::table::format::user_key(key));
+      fw.add_key(crate::comparator::strip_timestamp(
+        crate::table::format::user_key(key),
+        self.comparator.timestamp_size(),
+      ));
     }
 
     self.data_block.add(key, value);
@@ -127,8 +152,19 @@ impl TableBuilder {
     Ok(())
   }
 
+  /// Record a range tombstone for the table's range-del block.  Unlike `add`,
+  /// tombstones may arrive in any order; they are sorted by `finish`.
+  ///
+  /// The caller is responsible for widening the file's key range to cover
+  /// the tombstone (see [`extend_file_bounds`](crate::db::range_del::extend_file_bounds)).
+  pub(crate) fn add_range_tombstone(&mut self, tombstone: RangeTombstone) {
+    debug_assert!(!self.closed);
+    self.range_tombstones.push(tombstone);
+  }
+
   /// Complete the SSTable: flush any remaining data, write the filter block
-  /// (if configured), index block, metaindex block, and footer.
+  /// (if configured), range-del block (if any), index block, metaindex
+  /// block, and footer.
   /// Returns the total file size.
   pub(crate) fn finish(mut self) -> Result<u64, Error> {
     self.closed = true;
@@ -147,9 +183,13 @@ impl TableBuilder {
       self.index_block.add(&self.last_key, &handle_enc[..n]);
     }
 
-    // Write the filter block (if a policy was configured) and build the
-    // metaindex block that points to it.  Otherwise write an empty metaindex.
-    let metaindex_handle = if let (Some(fw), Some(filter_key)) =
+    // Write the filter block (if a policy was configured) and the range-del
+    // block (if any range tombstones were added), then the metaindex block
+    // that points to them.  Metaindex keys must be added in sorted order:
+    // "filter.<name>" < "rocksdb.range_del".
+    let mut meta = BlockBuilder::new(1);
+    let mut handle_enc = [0u8; 20];
+    if let (Some(fw), Some(filter_key)) =
       (self.filter_writer.take(), self.filter_policy_name.take())
     {
       // Finalise and write the filter block (always uncompressed — LevelDB does not compress it).
@@ -161,34 +201,40 @@ impl TableBuilder {
         CompressionType::NoCompression,
       )?;
       self.offset += filter_handle.size + 5;
-
-      // Write the metaindex block with one entry: "filter.<name>" → BlockHandle.
-      let mut meta = BlockBuilder::new(1);
-      let mut handle_enc = [0u8; 20];
       let n = filter_handle.encode_to(&mut handle_enc);
       meta.add(filter_key.as_bytes(), &handle_enc[..n]);
-      let meta_data = meta.finish().to_vec();
-      let mh = write_raw_block(
-        &mut *self.dest,
-        &meta_data,
-        self.offset,
-        CompressionType::NoCompression,
-      )?;
-      self.offset += mh.size + 5;
-      mh
-    } else {
-      // No filter — write an empty metaindex block.
-      let mut empty_meta = BlockBuilder::new(1);
-      let meta_data = empty_meta.finish().to_vec();
-      let mh = write_raw_block(
+    }
+    if !self.range_tombstones.is_empty() {
+      // One entry per tombstone: `start @ seq` (internal key) → end key, in
+      // internal-key order.  Uncompressed, like RocksDB's range-del block.
+      let cmp = &*self.comparator;
+      let mut entries: Vec<(Vec<u8>, Vec<u8>)> = std::mem::take(&mut self.range_tombstones)
+        .into_iter()
+        .map(|t| (t.start_key(), t.end))
+        .collect();
+      entries.sort_by(|a, b| cmp_internal_keys(&a.0, &b.0, cmp));
+      let mut block = BlockBuilder::new(1);
+      for (start, end) in &entries {
+        block.add(start, end);
+      }
+      let range_del_handle = write_raw_block(
         &mut *self.dest,
-        &meta_data,
+        block.finish(),
         self.offset,
         CompressionType::NoCompression,
       )?;
-      self.offset += mh.size + 5;
-      mh
-    };
+      self.offset += range_del_handle.size + 5;
+      let n = range_del_handle.encode_to(&mut handle_enc);
+      meta.add(RANGE_DEL_BLOCK_NAME.as_bytes(), &handle_enc[..n]);
+    }
+    let meta_data = meta.finish().to_vec();
+    let metaindex_handle = write_raw_block(
+      &mut *self.dest,
+      &meta_data,
+      self.offset,
+      CompressionType::NoCompression,
+    )?;
+    self.offset += metaindex_handle.size + 5;
 
     // Write index block (uncompressed — LevelDB does not compress index/meta blocks).
     let index_data = self.index_block.finish().to_vec();
@@ -258,10 +304,115 @@ impl TableBuilder {
   }
 }
 
+/// Copies a finished SSTable, adding a [`GLOBAL_SEQNO_NAME`] entry to its
+/// metaindex.
+///
+/// The sequence number is only known once the copy is about to be installed,
+/// so the copy is split in two: [`new`](Self::new) copies the data, filter
+/// and range-del blocks, which make up almost all of the file;
+/// [`finish`](Self::finish) writes the metaindex, the index block and the
+/// footer.
+///
+/// See `db/external_sst_file_ingestion_job.cc: AssignGlobalSeqnoForIngestedFile`.
+pub(crate) struct GlobalSeqnoWriter {
+  dest: Box<dyn WritableFile>,
+  offset: u64,
+  /// The source's metaindex entries.
+  meta: BTreeMap<Vec<u8>, Vec<u8>>,
+  /// The source's index block, trailer included.
+  index_block: Vec<u8>,
+}
+
+impl GlobalSeqnoWriter {
+  /// Copy the table in `src`, `src_size` bytes long, up to its metaindex
+  /// block into `dest`.
+  pub(crate) fn new(
+    src: &dyn RandomAccessFile,
+    src_size: u64,
+    mut dest: Box<dyn WritableFile>,
+  ) -> Result<Self, Error> {
+    let Some(footer_offset) = src_size.checked_sub(FOOTER_ENCODED_LENGTH as u64) else {
+      return Err(Error::Corruption("SSTable file too small".to_owned()));
+    };
+    let mut footer = [0u8; FOOTER_ENCODED_LENGTH];
+    read_exact_at(src, &mut footer, footer_offset)?;
+    let footer = Footer::decode(&footer)?;
+
+    let contents = read_block(src, &footer.metaindex_handle, true)?;
+    let block = Block::new(contents.data, Arc::new(BytewiseComparator))?;
+    let mut meta = BTreeMap::new();
+    let mut it = block.iter();
+    it.seek_to_first();
+    while it.valid() {
+      meta.insert(it.key().to_vec(), it.value().to_vec());
+      it.next();
+    }
+    let index = footer.index_handle;
+    let mut index_block = vec![0u8; index.size as usize + BLOCK_TRAILER_SIZE];
+    read_exact_at(src, &mut index_block, index.offset)?;
+
+    let end = footer.metaindex_handle.offset;
+    let mut buf = vec![0u8; 64 << 10];
+    let mut offset = 0;
+    while offset < end {
+      let n = buf.len().min((end - offset) as usize);
+      read_exact_at(src, &mut buf[..n], offset)?;
+      dest.write(&buf[..n])?;
+      offset += n as u64;
+    }
+    Ok(GlobalSeqnoWriter {
+      dest,
+      offset,
+      meta,
+      index_block,
+    })
+  }
+
+  /// Write the metaindex with `global_seqno` added, the index block and the
+  /// footer, and sync the copy.  Returns its size.
+  pub(crate) fn finish(mut self, global_seqno: u64) -> Result<u64, Error> {
+    self.meta.insert(
+      GLOBAL_SEQNO_NAME.as_bytes().to_vec(),
+      global_seqno.to_le_bytes().to_vec(),
+    );
+    let mut meta = BlockBuilder::new(1);
+    for (key, value) in &self.meta {
+      meta.add(key, value);
+    }
+    let metaindex_handle = write_raw_block(
+      &mut *self.dest,
+      meta.finish(),
+      self.offset,
+      CompressionType::NoCompression,
+    )?;
+    self.offset += metaindex_handle.size + BLOCK_TRAILER_SIZE as u64;
+
+    // The index block's handles point at the data blocks, which kept their
+    // offsets, so it is copied as is.
+    let index_handle = BlockHandle {
+      offset: self.offset,
+      size: (self.index_block.len() - BLOCK_TRAILER_SIZE) as u64,
+    };
+    self.dest.write(&self.index_block)?;
+    self.offset += self.index_block.len() as u64;
+
+    let footer = Footer {
+      metaindex_handle,
+      index_handle,
+    };
+    self.dest.write(&footer.encode())?;
+    self.offset += FOOTER_ENCODED_LENGTH as u64;
+    self.dest.flush()?;
+    self.dest.sync()?;
+    Ok(self.offset)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use crate::table::format::make_internal_key;
+  use crate::table::get_context::GetContext;
   use crate::table::reader::{LookupResult as L, Table};
 
   /// Build a table from (user_key, value) pairs using internal keys with ascending sequence numbers.
@@ -313,11 +464,16 @@ mod tests {
     )
     .unwrap();
     assert!(
-      matches!(table.get(&make_internal_key(b"hello", u64::MAX, 1), false, true).unwrap(), L::Value(v) if v == b"world")
+      matches!(table.get(&make_internal_key(b"hello", u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), L::Value(v) if v == b"world")
     );
     assert!(matches!(
       table
-        .get(&make_internal_key(b"missing", u64::MAX, 1), false, true)
+        .get(
+          &make_internal_key(b"missing", u64::MAX, 1),
+          false,
+          true,
+          &mut GetContext::default()
+        )
         .unwrap(),
       L::NotInTable
     ));
@@ -337,7 +493,7 @@ mod tests {
     .unwrap();
     for (k, v) in &pairs {
       assert!(
-        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true).unwrap(), L::Value(ref val) if val == v)
+        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), L::Value(ref val) if val == v)
       );
     }
   }
@@ -378,11 +534,63 @@ mod tests {
     .unwrap();
     for (k, v) in &pairs {
       assert!(
-        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true).unwrap(), L::Value(ref val) if val == v)
+        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), L::Value(ref val) if val == v)
       );
     }
   }
 
+  #[test]
+  fn global_seqno_writer_rewrites_sequence_numbers() {
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let mut builder = TableBuilder::new(
+      crate::env::writable_from_file(tmp.reopen().unwrap()),
+      64,
+      4,
+      None,
+      CompressionType::NoCompression,
+      Arc::new(crate::comparator::BytewiseComparator),
+    );
+    let keys: Vec<Vec<u8>> = (0u32..50)
+      .map(|i| format!("key{i:03}").into_bytes())
+      .collect();
+    for k in &keys {
+      builder.add(&make_internal_key(k, 0, 1), k).unwrap();
+    }
+    let size = builder.finish().unwrap();
+
+    let copy = tempfile::NamedTempFile::new().unwrap();
+    let writer = GlobalSeqnoWriter::new(
+      &*crate::env::random_access_from_file(tmp.reopen().unwrap()),
+      size,
+      crate::env::writable_from_file(copy.reopen().unwrap()),
+    )
+    .unwrap();
+    let copy_size = writer.finish(7).unwrap();
+    let table = Table::open(
+      crate::env::random_access_from_file(copy.reopen().unwrap()),
+      copy_size,
+      None,
+      None,
+      Arc::new(crate::comparator::BytewiseComparator),
+    )
+    .unwrap();
+    assert_eq!(table.global_seqno(), Some(7));
+
+    let mut it = table.new_iterator(true, false).unwrap();
+    it.seek_to_first();
+    for k in &keys {
+      assert_eq!(it.key(), make_internal_key(k, 7, 1));
+      it.next();
+    }
+    assert!(!it.valid());
+    // A seek at the global sequence number lands on the entry itself.
+    it.seek(&make_internal_key(b"key020", 7, 1));
+    assert_eq!(it.key(), make_internal_key(b"key020", 7, 1));
+    assert!(
+      matches!(table.get(&make_internal_key(b"key031", u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), L::Value(v) if v == b"key031")
+    );
+  }
+
   fn build_table_with_compression(
     pairs: &[(&[u8], &[u8])],
     compression: CompressionType,
@@ -419,7 +627,7 @@ mod tests {
     .unwrap();
     for (k, v) in &pairs {
       assert!(
-        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true).unwrap(), L::Value(ref val) if val == v)
+        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), L::Value(ref val) if val == v)
       );
     }
   }
@@ -438,7 +646,7 @@ mod tests {
     .unwrap();
     for (k, v) in &pairs {
       assert!(
-        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true).unwrap(), L::Value(ref val) if val == v)
+        matches!(table.get(&make_internal_key(k, u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), L::Value(ref val) if val == v)
       );
     }
   }
@@ -81,6 +81,31 @@ pub(crate) fn make_internal_key(user_key: &[u8], seq: u64, vtype: u8) -> Vec<u8>
   out
 }
 
+/// Value type of a point deletion (LevelDB's `kTypeDeletion`).
+pub(crate) const VALUE_TYPE_DELETION: u8 = 0;
+
+/// Value type of a put (LevelDB's `kTypeValue`).
+pub(crate) const VALUE_TYPE_VALUE: u8 = 1;
+
+/// Value type of a merge operand (RocksDB's `kTypeMerge`).
+pub(crate) const VALUE_TYPE_MERGE: u8 = 2;
+
+/// Value type used when building a lookup/seek key.  Tags sort descending, so
+/// this must be the highest-numbered value type for a seek at `seq` to land
+/// before every entry with that sequence: the blob-index type.  See
+/// `db/dbformat.h: kValueTypeForSeek`.
+pub(crate) const VALUE_TYPE_FOR_SEEK: u8 = crate::db::blob::VALUE_TYPE_BLOB_INDEX;
+
+/// Value type of a range tombstone (RocksDB's `kTypeRangeDeletion`).  Range
+/// tombstones live in the memtable's range-del list and the table's
+/// `rocksdb.range_del` block, never among point entries, so seeks need not
+/// account for them in `VALUE_TYPE_FOR_SEEK`.
+pub(crate) const VALUE_TYPE_RANGE_DELETION: u8 = 0x0F;
+
+/// Largest sequence number that fits in the 56-bit tag.  See
+/// `db/dbformat.h: kMaxSequenceNumber`.
+pub(crate) const MAX_SEQUENCE_NUMBER: u64 = (1 << 56) - 1;
+
 /// Extract the user-key prefix from an SSTable internal key by stripping the
 /// 8-byte trailing tag.  Returns the whole slice unchanged if it is shorter
 /// than a tag (defensive — well-formed internal keys are always ≥ 8 bytes).
@@ -0,0 +1,59 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::error::Error;
+use crate::table::format::{make_internal_key, user_key, VALUE_TYPE_FOR_SEEK};
+use crate::table::reader::LookupResult;
+
+/// State carried across the layers probed by one point lookup (memtable,
+/// sealed memtable, then SSTables level by level).
+///
+/// See `table/get_context.h` in RocksDB.
+#[derive(Debug, Default)]
+pub(crate) struct GetContext {
+  /// Merge operands found above the base entry, newest first.
+  pub(crate) operands: Vec<Vec<u8>>,
+  /// Sequence number of the newest range tombstone seen so far that covers
+  /// the key at the read snapshot (`0` = none).  Each layer raises it from
+  /// its own tombstones before probing its point entries; an entry older
+  /// than it is treated as deleted.
+  pub(crate) max_covering_tombstone_seq: u64,
+  /// Set by `Table::get` when the value it returns is a blob index rather
+  /// than the value itself.
+  pub(crate) is_blob_index: bool,
+}
+
+/// One key of a batched lookup (`Db::multi_get`): its lookup state and,
+/// once a layer has resolved it, the result.
+///
+/// See `table/multiget_context.h: KeyContext` in RocksDB.
+pub(crate) struct KeyContext {
+  /// Internal key to seek: `user_key` at the read sequence.
+  pub(crate) lookup_key: Vec<u8>,
+  pub(crate) ctx: GetContext,
+  /// `None` while the key is still to be looked up in older layers.
+  pub(crate) result: Option<Result<LookupResult, Error>>,
+}
+
+impl KeyContext {
+  pub(crate) fn new(user_key: &[u8], sequence: u64) -> Self {
+    KeyContext {
+      lookup_key: make_internal_key(user_key, sequence, VALUE_TYPE_FOR_SEEK),
+      ctx: GetContext::default(),
+      result: None,
+    }
+  }
+
+  pub(crate) fn user_key(&self) -> &[u8] {
+    user_key(&self.lookup_key)
+  }
+}
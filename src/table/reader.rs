@@ -12,14 +12,21 @@
 
 use crate::cache::BlockCache;
 use crate::comparator::Comparator;
+use crate::db::range_del::{FragmentedRangeTombstones, RangeTombstone};
 use crate::env::RandomAccessFile;
 use crate::error::Error;
 use crate::filter::FilterPolicy;
 use crate::iter::InternalIterator;
 use crate::table::block::Block;
+use crate::table::builder::{GLOBAL_SEQNO_NAME, RANGE_DEL_BLOCK_NAME};
 use crate::table::filter_block::FilterBlockReader;
-use crate::table::format::{read_block, read_exact_at, BlockHandle, Footer, FOOTER_ENCODED_LENGTH};
+use crate::table::format::{
+  cmp_internal_keys, encode_internal_key_into, parse_internal_key, read_block, read_exact_at,
+  BlockHandle, Footer, FOOTER_ENCODED_LENGTH,
+};
+use crate::table::get_context::{GetContext, KeyContext};
 use crate::table::two_level_iterator::TwoLevelIterator;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Three-way result of a `Table::get` lookup.
@@ -39,8 +46,8 @@ pub(crate) enum LookupResult {
 
 /// A random-access SSTable reader.
 ///
-/// `Table::open` reads the footer, index block, and (optionally) the filter
-/// block once; subsequent `get` calls use the in-memory index and filter to
+/// `Table::open` reads the footer, index block, range tombstones and
+/// (optionally) the filter block once; subsequent `get` calls use the in-memory index and filter to
 /// locate data blocks, reading them from disk via `pread` (no seeking —
 /// takes `&self`).  See `table/table.h/cc`.
 pub(crate) struct Table {
@@ -53,20 +60,25 @@ pub(crate) struct Table {
   /// Parsed filter block, present when the SSTable was written with a filter
   /// policy whose name matches the one in the metaindex.
   filter: Option<FilterBlockReader>,
+  /// Range tombstones from the `rocksdb.range_del` block (empty if absent).
+  range_tombstones: FragmentedRangeTombstones,
   /// Unique ID assigned by the block cache; used as the high half of the cache key.
   cache_id: u64,
   /// Shared block cache, if configured via `Options::block_cache`.
   block_cache: Option<Arc<BlockCache>>,
   /// Comparator for user-key ordering.
   comparator: Arc<dyn Comparator>,
+  /// Sequence number every entry reads as, for a table ingested with
+  /// `Db::ingest_external_file`; its keys are stored with sequence 0.
+  global_seqno: Option<u64>,
 }
 
 impl Table {
   /// Open an SSTable file of `file_size` bytes.
   ///
-  /// Reads and validates the footer, then reads the index block and (if
-  /// `filter_policy` is `Some` and the metaindex contains a matching filter
-  /// block) the filter block into memory.
+  /// Reads and validates the footer, then reads the index block, the
+  /// range-del block (if present) and (if `filter_policy` is `Some` and the
+  /// metaindex contains a matching filter block) the filter block into memory.
   pub(crate) fn open(
     file: Arc<dyn RandomAccessFile>,
     file_size: u64,
@@ -95,6 +107,15 @@ impl Table {
         .flatten()
     });
 
+    // Range tombstones are not optional: losing them would resurrect deleted
+    // keys, so a failure here fails the open.
+    let range_tombstones = FragmentedRangeTombstones::new(
+      read_range_del_block(file.as_ref(), &footer.metaindex_handle)?,
+      Arc::clone(&comparator),
+    );
+
+    let global_seqno = read_global_seqno(file.as_ref(), &footer.metaindex_handle)?;
+
     // Claim a unique cache ID from the block cache (0 = no cache).
     let cache_id = block_cache.as_ref().map(|c| c.new_id()).unwrap_or(0);
 
@@ -103,12 +124,19 @@ impl Table {
       index_block,
       metaindex_offset: footer.metaindex_handle.offset,
       filter,
+      range_tombstones,
       cache_id,
       block_cache,
       comparator,
+      global_seqno,
     })
   }
 
+  /// The sequence number every entry of an ingested table reads as.
+  pub(crate) fn global_seqno(&self) -> Option<u64> {
+    self.global_seqno
+  }
+
   /// Read (or retrieve from cache) the data block at `handle`.
   ///
   /// - Cache hit: returns a clone of the cached `Block` without any I/O.
@@ -141,10 +169,41 @@ impl Table {
     Ok(block)
   }
 
-  /// Look up `lookup_key` (an internal key: `user_key || tag(sequence, vtype=1)`,
+  /// [`read_data_block`](Self::read_data_block), reusing a block already in
+  /// `blocks` (keyed by offset) and adding the ones it reads.
+  fn read_data_block_once(
+    &self,
+    handle: &BlockHandle,
+    verify: bool,
+    fill_cache: bool,
+    blocks: &mut HashMap<u64, Block>,
+  ) -> Result<Block, Error> {
+    if let Some(block) = blocks.get(&handle.offset) {
+      return Ok(block.clone());
+    }
+    let block = self.read_data_block(handle, verify, fill_cache)?;
+    blocks.insert(handle.offset, block.clone());
+    Ok(block)
+  }
+
+  /// Range tombstones stored in this table.
+  pub(crate) fn range_tombstones(&self) -> &FragmentedRangeTombstones {
+    &self.range_tombstones
+  }
+
+  /// Look up `lookup_key` (an internal key: `user_key || tag(sequence, VALUE_TYPE_FOR_SEEK)`,
   /// built via [`make_internal_key`](crate::table::format::make_internal_key)),
   /// returning a [`LookupResult`].
   ///
+  /// Merge operands found before the newest Value/Deletion are appended to
+  /// `ctx.operands` newest-first; the scan continues past them (across data
+  /// blocks if necessary) until it reaches a base entry or a different key.
+  ///
+  /// A blob index is returned as the value, with `ctx.is_blob_index` set.
+  ///
+  /// `ctx.max_covering_tombstone_seq` is first raised by this table's range
+  /// tombstones; an entry older than it reads as `Deleted`.
+  ///
   /// Callers probing several tables for one lookup (see `Version::get`) build
   /// the key once and pass it to each probe — not once per table.
   ///
@@ -155,13 +214,63 @@ impl Table {
     lookup_key: &[u8],
     verify_checksums: bool,
     fill_cache: bool,
+    ctx: &mut GetContext,
   ) -> Result<LookupResult, Error> {
-    use crate::table::format::parse_internal_key;
+    let mut blocks = HashMap::new();
+    self.get_in(lookup_key, verify_checksums, fill_cache, ctx, &mut blocks)
+  }
+
+  /// Look up each of `keys` (sorted by user key) as [`get`](Self::get) does,
+  /// reading every data block they need only once.  A key found in this
+  /// table gets its `result`; the others are left for older tables.
+  ///
+  /// See `table/block_based/block_based_table_reader.cc:
+  /// BlockBasedTable::MultiGet` in RocksDB.
+  pub(crate) fn multi_get(
+    &self,
+    keys: Vec<&mut KeyContext>,
+    verify_checksums: bool,
+    fill_cache: bool,
+  ) {
+    let mut blocks = HashMap::new();
+    for key in keys {
+      match self.get_in(
+        &key.lookup_key,
+        verify_checksums,
+        fill_cache,
+        &mut key.ctx,
+        &mut blocks,
+      ) {
+        Ok(LookupResult::NotInTable) => {}
+        result => key.result = Some(result),
+      }
+    }
+  }
+
+  /// Body of [`get`](Self::get), taking data blocks from `blocks` when
+  /// present.
+  fn get_in(
+    &self,
+    lookup_key: &[u8],
+    verify_checksums: bool,
+    fill_cache: bool,
+    ctx: &mut GetContext,
+    blocks: &mut HashMap<u64, Block>,
+  ) -> Result<LookupResult, Error> {
+    use crate::db::blob::VALUE_TYPE_BLOB_INDEX;
+    use crate::table::format::VALUE_TYPE_MERGE;
 
     // In internal-key order (seq DESC), the lookup key sorts before all entries
     // for `user_key` with seq <= `sequence`, so `seek(lookup_key)` lands at the
     // newest visible version.
-    let user_key = crate::table::format::user_key(lookup_key);
+    let Some((user_key, sequence, _)) = parse_internal_key(lookup_key) else {
+      return Err(Error::InvalidArgument("malformed lookup key".to_owned()));
+    };
+    if !self.range_tombstones.is_empty() {
+      ctx.max_covering_tombstone_seq = ctx
+        .max_covering_tombstone_seq
+        .max(self.range_tombstones.max_covering_seq(user_key, sequence));
+    }
 
     // Search the index block for the first data block whose largest key >= lookup_key.
     let mut idx = self.index_block.iter();
@@ -175,38 +284,75 @@ impl Table {
 
     // Consult the filter (if present) before doing any data-block I/O.
     // A definite-negative skips the read entirely; false positives proceed normally.
+    let ts_size = self.comparator.timestamp_size();
     if let Some(filter) = &self.filter {
-      if !filter.key_may_match(handle.offset, user_key) {
+      let filter_key = crate::comparator::strip_timestamp(user_key, ts_size);
+      if !filter.key_may_match(handle.offset, filter_key) {
         return Ok(LookupResult::NotInTable);
       }
     }
 
     // Read (or fetch from cache) the data block.
-    let data_block = self.read_data_block(&handle, verify_checksums, fill_cache)?;
+    let data_block = self.read_data_block_once(&handle, verify_checksums, fill_cache, blocks)?;
     let mut it = data_block.iter();
     it.seek(lookup_key);
-
-    if it.valid() {
-      let ikey = it.key();
-      match parse_internal_key(ikey) {
-        None => {
-          return Err(Error::Corruption(
-            "invalid internal key in data block".to_owned(),
-          ))
+    // Set once an entry for the key has been passed over, so the versions
+    // may continue into the next data block.
+    let mut skipped = false;
+
+    loop {
+      if !it.valid() {
+        // A run of merge operands (or of versions newer than the snapshot)
+        // may continue into the next data block.
+        if ctx.operands.is_empty() && !skipped {
+          break;
+        }
+        idx.next();
+        if !idx.valid() {
+          break;
         }
-        Some((found_user_key, _seq, vtype)) => {
-          if self.comparator.compare(found_user_key, user_key) != std::cmp::Ordering::Equal {
-            return Ok(LookupResult::NotInTable);
-          }
-          // we did find the key, we can ignore `_seq`, as it will be <= `sequence` by definition
-          // no need to recheck for that
-          match vtype {
-            1 => return Ok(LookupResult::Value(it.value().to_vec())),
-            0 => return Ok(LookupResult::Deleted),
-            _ => return Err(Error::Corruption(format!("unknown vtype {vtype}"))),
-          }
+        let (handle, _) = BlockHandle::decode_from(idx.value())?;
+        it = self
+          .read_data_block_once(&handle, verify_checksums, fill_cache, blocks)?
+          .iter();
+        it.seek_to_first();
+        continue;
+      }
+      let ikey = it.key();
+      let Some((found_user_key, seq, vtype)) = parse_internal_key(ikey) else {
+        return Err(Error::Corruption(
+          "invalid internal key in data block".to_owned(),
+        ));
+      };
+      let seq = self.global_seqno.unwrap_or(seq);
+      if self
+        .comparator
+        .compare_without_timestamp(found_user_key, true, user_key, true)
+        .is_ne()
+      {
+        break;
+      }
+      // The seek skips newer versions of `user_key` itself, but versions
+      // with an older timestamp follow regardless of their sequence number.
+      if seq > sequence {
+        skipped = true;
+        it.next();
+        continue;
+      }
+      if seq < ctx.max_covering_tombstone_seq {
+        return Ok(LookupResult::Deleted);
+      }
+      match vtype {
+        1 => return Ok(LookupResult::Value(it.value().to_vec())),
+        VALUE_TYPE_BLOB_INDEX => {
+          ctx.is_blob_index = true;
+          return Ok(LookupResult::Value(it.value().to_vec()));
         }
+        0 => return Ok(LookupResult::Deleted),
+        VALUE_TYPE_MERGE => ctx.operands.push(it.value().to_vec()),
+        _ => return Err(Error::Corruption(format!("unknown vtype {vtype}"))),
       }
+      it.next();
     }
     Ok(LookupResult::NotInTable)
   }
@@ -250,14 +396,25 @@ impl Table {
     let block_cache = self.block_cache.clone();
     let cache_id = self.cache_id;
     let comparator = Arc::clone(&self.comparator);
+    let global_seqno = self.global_seqno;
     let index_iter: Box<dyn InternalIterator> = Box::new(self.index_block.iter());
     let block_fn: BlockFn = Box::new(move |handle_value: &[u8]| {
       let (handle, _) = BlockHandle::decode_from(handle_value)?;
+      let iter = |block: Block| -> Box<dyn InternalIterator> {
+        match global_seqno {
+          Some(seqno) => Box::new(GlobalSeqnoIter::new(
+            block.iter(),
+            seqno,
+            Arc::clone(&comparator),
+          )),
+          None => Box::new(block.iter()),
+        }
+      };
 
       // Check block cache first.
       if let Some(cache) = &block_cache {
         if let Some(block) = cache.get(cache_id, handle.offset) {
-          return Ok(Box::new(block.iter()) as Box<dyn InternalIterator>);
+          return Ok(iter(block));
         }
       }
 
@@ -271,7 +428,7 @@ impl Table {
         }
       }
 
-      Ok(Box::new(block.iter()) as Box<dyn InternalIterator>)
+      Ok(iter(block))
     });
     Ok(TwoLevelIterator::new(index_iter, block_fn))
   }
@@ -301,10 +458,140 @@ impl Table {
     }
     Ok(())
   }
+
+  /// The compression type byte in the trailer of each data block.
+  #[cfg(test)]
+  pub(crate) fn data_block_compression_types(&self) -> Result<Vec<u8>, Error> {
+    let mut idx = self.index_block.iter();
+    idx.seek_to_first();
+    let mut types = Vec::new();
+    while idx.valid() {
+      let (handle, _) = BlockHandle::decode_from(idx.value())?;
+      let mut trailer_type = [0u8];
+      read_exact_at(
+        self.file.as_ref(),
+        &mut trailer_type,
+        handle.offset + handle.size,
+      )?;
+      types.push(trailer_type[0]);
+      idx.next();
+    }
+    Ok(types)
+  }
+}
+
+/// Iterator over a data block of an ingested table that presents every entry
+/// at the table's global sequence number.
+///
+/// The stored keys carry sequence 0, which sorts after any seek target for
+/// the same user key; `seek` steps past the entries that, at the global
+/// sequence number, sort before the target.
+struct GlobalSeqnoIter {
+  inner: crate::table::block::BlockIter,
+  global_seqno: u64,
+  comparator: Arc<dyn Comparator>,
+  /// The current key, rewritten to `global_seqno`.
+  key: Vec<u8>,
+}
+
+impl GlobalSeqnoIter {
+  fn new(
+    inner: crate::table::block::BlockIter,
+    global_seqno: u64,
+    comparator: Arc<dyn Comparator>,
+  ) -> Self {
+    GlobalSeqnoIter {
+      inner,
+      global_seqno,
+      comparator,
+      key: Vec::new(),
+    }
+  }
+
+  fn update_key(&mut self) {
+    self.key.clear();
+    if self.inner.valid() {
+      if let Some((user_key, _, vtype)) = parse_internal_key(self.inner.key()) {
+        encode_internal_key_into(&mut self.key, user_key, self.global_seqno, vtype);
+      }
+    }
+  }
+}
+
+impl InternalIterator for GlobalSeqnoIter {
+  fn valid(&self) -> bool {
+    self.inner.valid()
+  }
+
+  fn seek_to_first(&mut self) {
+    self.inner.seek_to_first();
+    self.update_key();
+  }
+
+  fn seek_to_last(&mut self) {
+    self.inner.seek_to_last();
+    self.update_key();
+  }
+
+  fn seek(&mut self, target: &[u8]) {
+    self.inner.seek(target);
+    self.update_key();
+    while self.inner.valid() && cmp_internal_keys(&self.key, target, &*self.comparator).is_lt() {
+      self.inner.next();
+      self.update_key();
+    }
+  }
+
+  fn next(&mut self) {
+    self.inner.next();
+    self.update_key();
+  }
+
+  fn prev(&mut self) {
+    self.inner.prev();
+    self.update_key();
+  }
+
+  fn key(&self) -> &[u8] {
+    &self.key
+  }
+
+  fn value(&self) -> &[u8] {
+    self.inner.value()
+  }
+
+  fn status(&self) -> Option<&Error> {
+    self.inner.status()
+  }
 }
 
 // ── Private helpers ───────────────────────────────────────────────────────────
 
+/// Read the global sequence number of an ingested table from its metaindex,
+/// or `None` for a table written by the database itself.
+fn read_global_seqno(
+  file: &dyn RandomAccessFile,
+  metaindex_handle: &BlockHandle,
+) -> Result<Option<u64>, Error> {
+  let meta_contents = read_block(file, metaindex_handle, false)?;
+  let meta_block = Block::new(
+    meta_contents.data,
+    Arc::new(crate::comparator::BytewiseComparator),
+  )?;
+  let key = GLOBAL_SEQNO_NAME.as_bytes();
+  let mut it = meta_block.iter();
+  it.seek(key);
+  if !it.valid() || it.key() != key {
+    return Ok(None);
+  }
+  match <[u8; 8]>::try_from(it.value()) {
+    Ok(bytes) => Ok(Some(u64::from_le_bytes(bytes))),
+    Err(_) => Err(Error::Corruption(format!(
+      "invalid {GLOBAL_SEQNO_NAME} entry in metaindex"
+    ))),
+  }
+}
+
 /// Read the filter block from a table's metaindex, if the metaindex contains an
 /// entry for the key `"filter.<policy.name()>"`.
 ///
@@ -347,6 +634,53 @@ fn read_filter_block(
   Ok(FilterBlockReader::new(policy, filter_contents.data))
 }
 
+/// Read the range tombstones from a table's `rocksdb.range_del` block.
+///
+/// Returns an empty list when the metaindex has no such entry (the table
+/// holds no range tombstones, or predates them).  See
+/// `table/block_based/block_based_table_reader.cc: ReadRangeDelBlock`.
+fn read_range_del_block(
+  file: &dyn RandomAccessFile,
+  metaindex_handle: &BlockHandle,
+) -> Result<Vec<RangeTombstone>, Error> {
+  use crate::table::format::VALUE_TYPE_RANGE_DELETION;
+
+  let meta_contents = read_block(file, metaindex_handle, false)?;
+  let bytewise: Arc<dyn Comparator> = Arc::new(crate::comparator::BytewiseComparator);
+  let meta_block = Block::new(meta_contents.data, Arc::clone(&bytewise))?;
+  let key = RANGE_DEL_BLOCK_NAME.as_bytes();
+  let mut it = meta_block.iter();
+  it.seek(key);
+  if !it.valid() || it.key() != key {
+    return Ok(Vec::new());
+  }
+  let (handle, _) = BlockHandle::decode_from(it.value())?;
+
+  // The block is only ever iterated front to back, so its comparator is
+  // never consulted.
+  let contents = read_block(file, &handle, true)?;
+  let block = Block::new(contents.data, bytewise)?;
+  let mut it = block.iter();
+  it.seek_to_first();
+  let mut tombstones = Vec::new();
+  while it.valid() {
+    match parse_internal_key(it.key()) {
+      Some((start, seq, VALUE_TYPE_RANGE_DELETION)) => tombstones.push(RangeTombstone {
+        start: start.to_vec(),
+        end: it.value().to_vec(),
+        seq,
+      }),
+      _ => {
+        return Err(Error::Corruption(
+          "invalid key in range-del block".to_owned(),
+        ))
+      }
+    }
+    it.next();
+  }
+  Ok(tombstones)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -385,7 +719,7 @@ mod tests {
     )
     .unwrap();
     assert!(
-      matches!(table.get(&make_internal_key(b"hello", u64::MAX, 1), false, true).unwrap(), LookupResult::Value(v) if v == b"world")
+      matches!(table.get(&make_internal_key(b"hello", u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), LookupResult::Value(v) if v == b"world")
     );
   }
 
@@ -402,12 +736,74 @@ mod tests {
     .unwrap();
     assert!(matches!(
       table
-        .get(&make_internal_key(b"z", u64::MAX, 1), false, true)
+        .get(
+          &make_internal_key(b"z", u64::MAX, 1),
+          false,
+          true,
+          &mut GetContext::default()
+        )
         .unwrap(),
       LookupResult::NotInTable
     ));
   }
 
+  /// Counts the reads issued against the wrapped file.
+  struct CountingFile {
+    inner: Arc<dyn RandomAccessFile>,
+    reads: std::sync::atomic::AtomicUsize,
+  }
+
+  impl RandomAccessFile for CountingFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, Error> {
+      self
+        .reads
+        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+      self.inner.read_at(buf, offset)
+    }
+  }
+
+  #[test]
+  fn multi_get_reads_each_data_block_once() {
+    // 100-byte values put ~35 keys in each 4 KiB data block.
+    let value = [b'v'; 100];
+    let keys: Vec<Vec<u8>> = (0..200u32)
+      .map(|i| format!("key{i:05}").into_bytes())
+      .collect();
+    let pairs: Vec<(&[u8], u64, u8, &[u8])> = keys
+      .iter()
+      .map(|k| (k.as_slice(), 1, 1, &value[..]))
+      .collect();
+    let (tmp, size) = write_table_internal(&pairs);
+    let file = Arc::new(CountingFile {
+      inner: crate::env::random_access_from_file(tmp.reopen().unwrap()),
+      reads: Default::default(),
+    });
+    let table = Table::open(
+      Arc::clone(&file) as Arc<dyn RandomAccessFile>,
+      size,
+      None,
+      None,
+      Arc::new(crate::comparator::BytewiseComparator),
+    )
+    .unwrap();
+    let opened = file.reads.load(std::sync::atomic::Ordering::Relaxed);
+
+    // Ten keys from the first data block, plus one key absent from the table.
+    let mut lookups: Vec<KeyContext> = (0..10)
+      .map(|i| KeyContext::new(&keys[i], u64::MAX >> 8))
+      .chain(std::iter::once(KeyContext::new(b"zzz", u64::MAX >> 8)))
+      .collect();
+    table.multi_get(lookups.iter_mut().collect(), false, false);
+    assert_eq!(
+      file.reads.load(std::sync::atomic::Ordering::Relaxed) - opened,
+      1
+    );
+    for key in &lookups[..10] {
+      assert!(matches!(&key.result, Some(Ok(LookupResult::Value(v))) if v == &value));
+    }
+    assert!(lookups[10].result.is_none());
+  }
+
   #[test]
   fn get_tombstone_returns_deleted() {
     // vtype=0 is a deletion tombstone — must return Deleted, not NotInTable.
@@ -422,7 +818,12 @@ mod tests {
     .unwrap();
     assert!(matches!(
       table
-        .get(&make_internal_key(b"gone", u64::MAX, 1), false, true)
+        .get(
+          &make_internal_key(b"gone", u64::MAX, 1),
+          false,
+          true,
+          &mut GetContext::default()
+        )
         .unwrap(),
       LookupResult::Deleted
     ));
@@ -440,7 +841,7 @@ mod tests {
     )
     .unwrap();
     assert!(
-      matches!(table.get(&make_internal_key(b"key", u64::MAX, 1), false, true).unwrap(), LookupResult::Value(v) if v == b"new")
+      matches!(table.get(&make_internal_key(b"key", u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), LookupResult::Value(v) if v == b"new")
     );
   }
 
@@ -458,16 +859,21 @@ mod tests {
     .unwrap();
     // With sequence=u64::MAX, we see the newest (seq=10).
     assert!(
-      matches!(table.get(&make_internal_key(b"key", u64::MAX, 1), false, true).unwrap(), LookupResult::Value(v) if v == b"new")
+      matches!(table.get(&make_internal_key(b"key", u64::MAX, 1), false, true, &mut GetContext::default()).unwrap(), LookupResult::Value(v) if v == b"new")
     );
     // With sequence=7, seq=10 is invisible — we see seq=5.
     assert!(
-      matches!(table.get(&make_internal_key(b"key", 7, 1), false, true).unwrap(), LookupResult::Value(v) if v == b"old")
+      matches!(table.get(&make_internal_key(b"key", 7, 1), false, true, &mut GetContext::default()).unwrap(), LookupResult::Value(v) if v == b"old")
     );
     // With sequence=3, both versions are invisible.
     assert!(matches!(
       table
-        .get(&make_internal_key(b"key", 3, 1), false, true)
+        .get(
+          &make_internal_key(b"key", 3, 1),
+          false,
+          true,
+          &mut GetContext::default()
+        )
         .unwrap(),
       LookupResult::NotInTable
     ));
@@ -486,12 +892,101 @@ mod tests {
     .unwrap();
     assert!(matches!(
       table
-        .get(&make_internal_key(b"k", u64::MAX, 1), true, true)
+        .get(
+          &make_internal_key(b"k", u64::MAX, 1),
+          true,
+          true,
+          &mut GetContext::default()
+        )
         .unwrap(),
       LookupResult::Value(_)
     ));
   }
 
+  #[test]
+  fn get_collects_merge_operands_across_blocks() {
+    // 1 KiB operands overflow the 4 KiB data blocks, so the run of merge
+    // entries spans several blocks before reaching the base value.
+    let big: Vec<Vec<u8>> = (0..10u8).map(|i| vec![i; 1024]).collect();
+    let mut pairs: Vec<(&[u8], u64, u8, &[u8])> = Vec::new();
+    for (i, v) in big.iter().enumerate() {
+      pairs.push((b"key", 20 - i as u64, 2, v));
+    }
+    pairs.push((b"key", 5, 1, b"base"));
+    let (tmp, size) = write_table_internal(&pairs);
+    let table = Table::open(
+      crate::env::random_access_from_file(tmp.reopen().unwrap()),
+      size,
+      None,
+      None,
+      Arc::new(crate::comparator::BytewiseComparator),
+    )
+    .unwrap();
+    let mut ctx = GetContext::default();
+    assert!(matches!(
+      table
+        .get(&make_internal_key(b"key", u64::MAX, 2), false, true, &mut ctx)
+        .unwrap(),
+      LookupResult::Value(v) if v == b"base"
+    ));
+    assert_eq!(ctx.operands, big);
+  }
+
+  #[test]
+  fn range_tombstones_round_trip_and_hide_older_entries() {
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let mut b = TableBuilder::new(
+      crate::env::writable_from_file(tmp.reopen().unwrap()),
+      4096,
+      16,
+      None,
+      crate::options::CompressionType::NoCompression,
+      Arc::new(crate::comparator::BytewiseComparator),
+    );
+    for (uk, seq) in [(&b"a"[..], 1), (b"b", 2), (b"c", 9), (b"d", 2)] {
+      b.add(&make_internal_key(uk, seq, 1), b"v").unwrap();
+    }
+    // Added out of order; the builder sorts them.
+    b.add_range_tombstone(RangeTombstone {
+      start: b"c".to_vec(),
+      end: b"e".to_vec(),
+      seq: 3,
+    });
+    b.add_range_tombstone(RangeTombstone {
+      start: b"b".to_vec(),
+      end: b"d".to_vec(),
+      seq: 5,
+    });
+    let size = b.finish().unwrap();
+    let table = Table::open(
+      crate::env::random_access_from_file(tmp.reopen().unwrap()),
+      size,
+      None,
+      None,
+      Arc::new(crate::comparator::BytewiseComparator),
+    )
+    .unwrap();
+    assert_eq!(table.range_tombstones().iter().count(), 4);
+
+    let get = |uk: &[u8], seq: u64| {
+      table
+        .get(
+          &make_internal_key(uk, seq, 2),
+          false,
+          true,
+          &mut GetContext::default(),
+        )
+        .unwrap()
+    };
+    assert!(matches!(get(b"a", 100), LookupResult::Value(_)));
+    assert!(matches!(get(b"b", 100), LookupResult::Deleted));
+    // `c@9` is newer than both tombstones.
+    assert!(matches!(get(b"c", 100), LookupResult::Value(_)));
+    assert!(matches!(get(b"d", 100), LookupResult::Deleted));
+    // At snapshot 4 the `[b, d) @ 5` tombstone is invisible.
+    assert!(matches!(get(b"b", 4), LookupResult::Value(_)));
+  }
+
   #[test]
   fn file_too_small_returns_error() {
     let tmp = tempfile::NamedTempFile::new().unwrap();
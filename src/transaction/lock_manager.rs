@@ -0,0 +1,203 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Per-key exclusive locks for [`TransactionDb`](super::TransactionDb).
+//!
+//! Keys hash to one of a fixed number of stripes, each with its own mutex and
+//! condition variable, so transactions touching different keys rarely
+//! contend on the same mutex.  A transaction that finds a key locked waits on
+//! the stripe's condition variable until the key is released or its timeout
+//! expires.
+//!
+//! Deadlocks are detected with a wait-for graph: before waiting, a
+//! transaction records an edge to the lock holder and follows the holders'
+//! own edges.  Reaching itself means the wait would never end.
+//!
+//! See `utilities/transactions/lock/point/point_lock_manager.cc`.
+
+use crate::error::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest wait-for chain followed before giving up on deadlock detection
+/// (RocksDB's default `deadlock_detect_depth`).
+const DEADLOCK_DETECT_DEPTH: usize = 50;
+
+struct LockStripe {
+  /// Locked keys and the id of the transaction holding each.
+  locks: Mutex<HashMap<Vec<u8>, u64>>,
+  /// Signalled whenever a key in this stripe is unlocked.
+  released: Condvar,
+}
+
+pub(crate) struct LockManager {
+  stripes: Vec<LockStripe>,
+  /// Wait-for graph: each waiting transaction and the transaction holding the
+  /// lock it waits for.  With exclusive locks a waiter has one holder.
+  wait_for: Mutex<HashMap<u64, u64>>,
+}
+
+impl LockManager {
+  pub(crate) fn new(num_stripes: usize) -> Self {
+    LockManager {
+      stripes: (0..num_stripes.max(1))
+        .map(|_| LockStripe {
+          locks: Mutex::new(HashMap::new()),
+          released: Condvar::new(),
+        })
+        .collect(),
+      wait_for: Mutex::new(HashMap::new()),
+    }
+  }
+
+  fn stripe(&self, key: &[u8]) -> &LockStripe {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    &self.stripes[(hasher.finish() % self.stripes.len() as u64) as usize]
+  }
+
+  /// Lock `key` for transaction `txn`, waiting up to `timeout` for another
+  /// holder to release it.  Re-locking a key `txn` already holds succeeds.
+  ///
+  /// Fails with [`Error::TimedOut`] when the timeout expires, or with
+  /// [`Error::Busy`] if `deadlock_detect` is set and waiting would deadlock.
+  pub(crate) fn lock(
+    &self,
+    txn: u64,
+    key: &[u8],
+    timeout: Duration,
+    deadlock_detect: bool,
+  ) -> Result<(), Error> {
+    let stripe = self.stripe(key);
+    let deadline = Instant::now() + timeout;
+    let mut locks = stripe.locks.lock().unwrap();
+    let result = loop {
+      let holder = match locks.get(key) {
+        None => {
+          locks.insert(key.to_vec(), txn);
+          break Ok(());
+        }
+        Some(&holder) if holder == txn => break Ok(()),
+        Some(&holder) => holder,
+      };
+      // The holder may differ from the previous round; refresh the edge.
+      if self.add_wait_edge(txn, holder, deadlock_detect) {
+        break Err(Error::Busy("deadlock".to_owned()));
+      }
+      let now = Instant::now();
+      if now >= deadline {
+        break Err(Error::TimedOut("lock timeout".to_owned()));
+      }
+      locks = stripe
+        .released
+        .wait_timeout(locks, deadline - now)
+        .unwrap()
+        .0;
+    };
+    self.wait_for.lock().unwrap().remove(&txn);
+    result
+  }
+
+  /// Release `key` if `txn` holds it.
+  pub(crate) fn unlock(&self, txn: u64, key: &[u8]) {
+    let stripe = self.stripe(key);
+    let mut locks = stripe.locks.lock().unwrap();
+    if locks.get(key) == Some(&txn) {
+      locks.remove(key);
+      stripe.released.notify_all();
+    }
+  }
+
+  /// Record that `txn` waits for `holder`.  With `detect`, returns `true`
+  /// (and records nothing) if `holder` already waits, directly or through
+  /// other transactions, for `txn`.
+  fn add_wait_edge(&self, txn: u64, holder: u64, detect: bool) -> bool {
+    let mut wait_for = self.wait_for.lock().unwrap();
+    if detect {
+      let mut next = holder;
+      for _ in 0..DEADLOCK_DETECT_DEPTH {
+        if next == txn {
+          return true;
+        }
+        match wait_for.get(&next) {
+          Some(&n) => next = n,
+          None => break,
+        }
+      }
+    }
+    wait_for.insert(txn, holder);
+    false
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Arc;
+
+  const NO_WAIT: Duration = Duration::ZERO;
+
+  #[test]
+  fn lock_is_exclusive_and_reentrant() {
+    let lm = LockManager::new(4);
+    lm.lock(1, b"k", NO_WAIT, true).unwrap();
+    lm.lock(1, b"k", NO_WAIT, true).unwrap();
+    assert!(matches!(
+      lm.lock(2, b"k", NO_WAIT, true),
+      Err(Error::TimedOut(_))
+    ));
+    lm.lock(2, b"other", NO_WAIT, true).unwrap();
+    // Only the holder can release a lock.
+    lm.unlock(2, b"k");
+    assert!(lm.lock(2, b"k", NO_WAIT, true).is_err());
+    lm.unlock(1, b"k");
+    lm.lock(2, b"k", NO_WAIT, true).unwrap();
+  }
+
+  #[test]
+  fn waiter_acquires_after_release() {
+    let lm = Arc::new(LockManager::new(1));
+    lm.lock(1, b"k", NO_WAIT, true).unwrap();
+    let waiter = {
+      let lm = Arc::clone(&lm);
+      std::thread::spawn(move || lm.lock(2, b"k", Duration::from_secs(10), true))
+    };
+    std::thread::sleep(Duration::from_millis(20));
+    lm.unlock(1, b"k");
+    waiter.join().unwrap().unwrap();
+  }
+
+  #[test]
+  fn cycle_in_wait_for_graph_is_a_deadlock() {
+    let lm = Arc::new(LockManager::new(16));
+    lm.lock(1, b"a", NO_WAIT, true).unwrap();
+    lm.lock(2, b"b", NO_WAIT, true).unwrap();
+    // Transaction 1 waits for `b`, held by 2 …
+    let waiter = {
+      let lm = Arc::clone(&lm);
+      std::thread::spawn(move || lm.lock(1, b"b", Duration::from_secs(10), true))
+    };
+    while !lm.wait_for.lock().unwrap().contains_key(&1) {
+      std::thread::yield_now();
+    }
+    // … so 2 waiting for `a`, held by 1, closes the cycle.
+    assert!(matches!(
+      lm.lock(2, b"a", Duration::from_secs(10), true),
+      Err(Error::Busy(_))
+    ));
+    lm.unlock(2, b"b");
+    waiter.join().unwrap().unwrap();
+  }
+}
@@ -0,0 +1,27 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Multi-key transactions layered on [`Db::write`](crate::Db::write).
+//!
+//! A transaction buffers its writes in a
+//! [`WriteBatchWithIndex`](crate::WriteBatchWithIndex) and applies them as one
+//! batch at commit, so a commit is atomic and goes through the normal
+//! group-commit path.  Reads see the transaction's own uncommitted writes
+//! first.
+//!
+//! See `include/rocksdb/utilities/transaction.h`.
+
+mod lock_manager;
+mod optimistic;
+mod transaction_db;
+pub use optimistic::OptimisticTransaction;
+pub use transaction_db::{Transaction, TransactionDb, TransactionDbOptions, TransactionOptions};
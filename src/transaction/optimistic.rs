@@ -0,0 +1,205 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::{ConflictCheck, Db, Error, ReadOptions, Snapshot, WriteBatchWithIndex, WriteOptions};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// A transaction that takes no locks and detects conflicts at commit.
+///
+/// Reads go through a snapshot taken when the transaction begins.  Every key
+/// the transaction reads or writes is tracked; [`commit`](Self::commit) fails
+/// with [`Error::Busy`] if any tracked key was written by someone else after
+/// that snapshot, in which case nothing is applied and the caller may retry
+/// with a new transaction.
+///
+/// Obtained via [`Db::begin_optimistic_transaction`].  Dropping the
+/// transaction without committing discards its writes.
+///
+/// ```
+/// # let db = roughdb::Db::default();
+/// use roughdb::WriteOptions;
+///
+/// db.put(b"balance", b"10")?;
+/// let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+/// let balance = txn.get(b"balance")?;
+/// txn.put(b"balance", [balance, b"0".to_vec()].concat());
+/// txn.commit()?;
+/// assert_eq!(db.get(b"balance")?, b"100");
+/// # Ok::<(), roughdb::Error>(())
+/// ```
+///
+/// See `utilities/transactions/optimistic_transaction.cc`.
+pub struct OptimisticTransaction<'db> {
+  db: &'db Db,
+  write_options: WriteOptions,
+  snapshot: Snapshot<'db>,
+  writes: WriteBatchWithIndex,
+  /// Keys read or written, validated at commit.
+  tracked: BTreeSet<Vec<u8>>,
+}
+
+impl<'db> OptimisticTransaction<'db> {
+  pub(crate) fn new(db: &'db Db, write_options: &WriteOptions) -> Self {
+    OptimisticTransaction {
+      db,
+      write_options: write_options.clone(),
+      snapshot: db.get_snapshot(),
+      writes: WriteBatchWithIndex::new(Arc::clone(&db.inner.options.comparator)),
+      tracked: BTreeSet::new(),
+    }
+  }
+
+  /// Read `key` as of the transaction's snapshot, overlaid with the
+  /// transaction's own writes.
+  ///
+  /// Returns [`Error::NotFound`] if the key does not exist or was deleted.
+  pub fn get<K: AsRef<[u8]>>(&mut self, key: K) -> Result<Vec<u8>, Error> {
+    let key = key.as_ref();
+    self.tracked.insert(key.to_vec());
+    let opts = ReadOptions {
+      snapshot: Some(&self.snapshot),
+      ..ReadOptions::default()
+    };
+    self.writes.get_from_batch_and_db(self.db, &opts, key)
+  }
+
+  /// Buffer a write of `key` → `value`.
+  pub fn put<K, V>(&mut self, key: K, value: V)
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let key = key.as_ref();
+    self.writes.put(key, value);
+    self.tracked.insert(key.to_vec());
+  }
+
+  /// Buffer a deletion of `key`.
+  pub fn delete<K: AsRef<[u8]>>(&mut self, key: K) {
+    let key = key.as_ref();
+    self.writes.delete(key);
+    self.tracked.insert(key.to_vec());
+  }
+
+  /// Apply the buffered writes atomically, or fail with [`Error::Busy`] if a
+  /// tracked key was written after the transaction's snapshot.
+  ///
+  /// The conflict check runs in the write-group leader immediately before
+  /// the batch is applied, so no other write can land in between.  It only
+  /// consults the memtables: if writes newer than the snapshot may already
+  /// have been flushed, the commit fails with [`Error::Busy`] as well.
+  pub fn commit(self) -> Result<(), Error> {
+    let check = ConflictCheck {
+      snapshot_seq: self.snapshot.seq,
+      keys: self.tracked.into_iter().collect(),
+    };
+    self.db.write_internal(
+      &self.write_options,
+      self.writes.into_write_batch(),
+      Some(check),
+      None,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{Db, WriteOptions};
+
+  #[test]
+  fn reads_see_own_writes() {
+    let db = Db::default();
+    db.put(b"a", b"1").unwrap();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    assert_eq!(txn.get(b"a").unwrap(), b"1");
+    txn.put(b"a", b"2");
+    txn.put(b"b", b"3");
+    txn.delete(b"a");
+    assert!(txn.get(b"a").unwrap_err().is_not_found());
+    assert_eq!(txn.get(b"b").unwrap(), b"3");
+    // Nothing is visible outside the transaction until commit.
+    assert_eq!(db.get(b"a").unwrap(), b"1");
+    assert!(db.get(b"b").unwrap_err().is_not_found());
+    txn.commit().unwrap();
+    assert!(db.get(b"a").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"b").unwrap(), b"3");
+  }
+
+  #[test]
+  fn reads_use_the_begin_snapshot() {
+    let db = Db::default();
+    db.put(b"k", b"old").unwrap();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    db.put(b"k", b"new").unwrap();
+    assert_eq!(txn.get(b"k").unwrap(), b"old");
+  }
+
+  #[test]
+  fn conflicting_read_fails_commit() {
+    let db = Db::default();
+    db.put(b"k", b"1").unwrap();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    txn.get(b"k").unwrap();
+    txn.put(b"other", b"x");
+    db.put(b"k", b"2").unwrap();
+    assert!(txn.commit().unwrap_err().is_busy());
+    // The failed commit applied nothing.
+    assert!(db.get(b"other").unwrap_err().is_not_found());
+  }
+
+  #[test]
+  fn conflicting_write_fails_commit() {
+    let db = Db::default();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    txn.put(b"k", b"mine");
+    db.delete(b"k").unwrap();
+    assert!(txn.commit().unwrap_err().is_busy());
+  }
+
+  #[test]
+  fn range_deletion_conflicts_with_read() {
+    let db = Db::default();
+    db.put(b"k", b"1").unwrap();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    txn.get(b"k").unwrap();
+    db.delete_range(b"a", b"z").unwrap();
+    assert!(txn.commit().unwrap_err().is_busy());
+  }
+
+  #[test]
+  fn unrelated_writes_do_not_conflict() {
+    let db = Db::default();
+    let mut txn = db.begin_optimistic_transaction(&WriteOptions::default());
+    assert!(txn.get(b"k").unwrap_err().is_not_found());
+    db.put(b"j", b"1").unwrap();
+    db.put(b"l", b"1").unwrap();
+    txn.put(b"k", b"v");
+    txn.commit().unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+  }
+
+  #[test]
+  fn only_one_of_two_racing_transactions_commits() {
+    let db = Db::default();
+    db.put(b"counter", b"0").unwrap();
+    let mut t1 = db.begin_optimistic_transaction(&WriteOptions::default());
+    let mut t2 = db.begin_optimistic_transaction(&WriteOptions::default());
+    t1.get(b"counter").unwrap();
+    t2.get(b"counter").unwrap();
+    t1.put(b"counter", b"1");
+    t2.put(b"counter", b"2");
+    t1.commit().unwrap();
+    assert!(t2.commit().unwrap_err().is_busy());
+    assert_eq!(db.get(b"counter").unwrap(), b"1");
+  }
+}
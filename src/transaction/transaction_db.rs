@@ -0,0 +1,457 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::transaction::lock_manager::LockManager;
+use crate::{
+  Db, Error, Handler, Options, ReadOptions, WriteBatch, WriteBatchWithIndex, WriteOptions,
+};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Options for a [`TransactionDb`].
+///
+/// See `include/rocksdb/utilities/transaction_db.h: TransactionDBOptions`.
+#[derive(Debug, Clone)]
+pub struct TransactionDbOptions {
+  /// Number of stripes the key-lock table is split into.  More stripes mean
+  /// less contention on the lock table's mutexes.
+  ///
+  /// Default: 16.
+  pub num_stripes: usize,
+
+  /// How long a transaction waits for a key lock before failing with
+  /// [`Error::TimedOut`], unless overridden by
+  /// [`TransactionOptions::lock_timeout`].
+  ///
+  /// Default: 1 second.
+  pub transaction_lock_timeout: Duration,
+
+  /// How long a non-transactional write ([`TransactionDb::put`],
+  /// [`TransactionDb::write`], …) waits for the locks on its keys.
+  ///
+  /// Default: 1 second.
+  pub default_lock_timeout: Duration,
+}
+
+impl Default for TransactionDbOptions {
+  fn default() -> Self {
+    TransactionDbOptions {
+      num_stripes: 16,
+      transaction_lock_timeout: Duration::from_secs(1),
+      default_lock_timeout: Duration::from_secs(1),
+    }
+  }
+}
+
+/// Options for a single [`Transaction`].
+///
+/// See `include/rocksdb/utilities/transaction_db.h: TransactionOptions`.
+#[derive(Debug, Clone)]
+pub struct TransactionOptions {
+  /// Lock wait timeout for this transaction.  `None` uses
+  /// [`TransactionDbOptions::transaction_lock_timeout`].
+  ///
+  /// Default: `None`.
+  pub lock_timeout: Option<Duration>,
+
+  /// Before waiting for a lock, check whether the wait would deadlock and
+  /// fail with [`Error::Busy`] instead of waiting for the timeout.
+  ///
+  /// Default: true.
+  pub deadlock_detect: bool,
+}
+
+impl Default for TransactionOptions {
+  fn default() -> Self {
+    TransactionOptions {
+      lock_timeout: None,
+      deadlock_detect: true,
+    }
+  }
+}
+
+/// A [`Db`] whose writes are coordinated by per-key locks.
+///
+/// A [`Transaction`] locks every key it writes or reads with
+/// [`get_for_update`](Transaction::get_for_update) and holds the locks until
+/// it commits or is dropped, so conflicting transactions wait for each other
+/// instead of failing at commit like an
+/// [`OptimisticTransaction`](crate::OptimisticTransaction).  Commits are
+/// applied with [`Db::write`] and are recovered from the WAL like any batch.
+///
+/// Writes made through [`TransactionDb::put`], [`TransactionDb::delete`] and
+/// [`TransactionDb::write`] also take the locks on their keys.  Writing
+/// through [`TransactionDb::db`] bypasses them.
+///
+/// ```
+/// use roughdb::{Options, TransactionDb, TransactionDbOptions, TransactionOptions, WriteOptions};
+///
+/// # let dir = tempfile::tempdir().unwrap();
+/// let mut opts = Options::default();
+/// opts.create_if_missing = true;
+/// let txn_db = TransactionDb::open(dir.path(), opts, TransactionDbOptions::default())?;
+///
+/// let mut txn = txn_db.begin_transaction(&WriteOptions::default(), &TransactionOptions::default());
+/// let seen = txn.get_for_update(b"k").is_ok();
+/// txn.put(b"k", if seen { b"again" } else { b"first" })?;
+/// txn.commit()?;
+/// assert_eq!(txn_db.get(b"k")?, b"first");
+/// # Ok::<(), roughdb::Error>(())
+/// ```
+///
+/// See `utilities/transactions/pessimistic_transaction_db.cc`.
+pub struct TransactionDb {
+  db: Db,
+  options: TransactionDbOptions,
+  lock_manager: LockManager,
+  /// Id handed to the next transaction (or non-transactional write).
+  next_txn_id: AtomicU64,
+}
+
+impl TransactionDb {
+  /// Open (or create) the database at `path` with [`Db::open`] and wrap it.
+  pub fn open<P: AsRef<std::path::Path>>(
+    path: P,
+    options: Options,
+    txn_db_options: TransactionDbOptions,
+  ) -> Result<Self, Error> {
+    Ok(TransactionDb::new(Db::open(path, options)?, txn_db_options))
+  }
+
+  /// Wrap an already open `db`.
+  pub fn new(db: Db, txn_db_options: TransactionDbOptions) -> Self {
+    TransactionDb {
+      db,
+      lock_manager: LockManager::new(txn_db_options.num_stripes),
+      options: txn_db_options,
+      next_txn_id: AtomicU64::new(1),
+    }
+  }
+
+  /// The underlying database, for reads and for writes that should not take
+  /// key locks.
+  pub fn db(&self) -> &Db {
+    &self.db
+  }
+
+  /// Begin a [`Transaction`] that commits with `write_options`.
+  pub fn begin_transaction(
+    &self,
+    write_options: &WriteOptions,
+    txn_options: &TransactionOptions,
+  ) -> Transaction<'_> {
+    Transaction {
+      txn_db: self,
+      id: self.next_txn_id.fetch_add(1, Ordering::Relaxed),
+      write_options: write_options.clone(),
+      lock_timeout: txn_options
+        .lock_timeout
+        .unwrap_or(self.options.transaction_lock_timeout),
+      deadlock_detect: txn_options.deadlock_detect,
+      writes: WriteBatchWithIndex::new(Arc::clone(&self.db.inner.options.comparator)),
+      locked: BTreeSet::new(),
+    }
+  }
+
+  /// Read the latest committed value of `key`.  Takes no lock.
+  pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Vec<u8>, Error> {
+    self.db.get(key)
+  }
+
+  /// Write `key` → `value` once no transaction holds its lock.
+  pub fn put<K, V>(&self, key: K, value: V) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let mut batch = WriteBatch::new();
+    batch.put(key, value);
+    self.write(&WriteOptions::default(), batch)
+  }
+
+  /// Delete `key` once no transaction holds its lock.
+  pub fn delete<K: AsRef<[u8]>>(&self, key: K) -> Result<(), Error> {
+    let mut batch = WriteBatch::new();
+    batch.delete(key);
+    self.write(&WriteOptions::default(), batch)
+  }
+
+  /// Apply `batch` once no transaction holds a lock on any of its keys.
+  ///
+  /// Waits up to [`TransactionDbOptions::default_lock_timeout`] for each
+  /// lock.  Range deletions cannot be locked and are rejected with
+  /// [`Error::NotSupported`].
+  pub fn write(&self, opts: &WriteOptions, batch: WriteBatch) -> Result<(), Error> {
+    struct KeyCollector(BTreeSet<Vec<u8>>);
+    impl Handler for KeyCollector {
+      fn put(&mut self, key: &[u8], _value: &[u8]) -> Result<(), Error> {
+        self.0.insert(key.to_vec());
+        Ok(())
+      }
+      fn delete(&mut self, key: &[u8]) -> Result<(), Error> {
+        self.0.insert(key.to_vec());
+        Ok(())
+      }
+      fn merge(&mut self, key: &[u8], _value: &[u8]) -> Result<(), Error> {
+        self.0.insert(key.to_vec());
+        Ok(())
+      }
+    }
+    let mut keys = KeyCollector(BTreeSet::new());
+    batch.iterate(&mut keys)?;
+
+    let id = self.next_txn_id.fetch_add(1, Ordering::Relaxed);
+    let mut locked = Vec::new();
+    let mut result = Ok(());
+    for key in keys.0 {
+      result = self
+        .lock_manager
+        .lock(id, &key, self.options.default_lock_timeout, false);
+      if result.is_err() {
+        break;
+      }
+      locked.push(key);
+    }
+    if result.is_ok() {
+      result = self.db.write(opts, batch);
+    }
+    for key in &locked {
+      self.lock_manager.unlock(id, key);
+    }
+    result
+  }
+}
+
+/// A transaction that locks the keys it writes, holding the locks until it
+/// commits or is dropped.
+///
+/// Obtained via [`TransactionDb::begin_transaction`].  Reads see the
+/// transaction's own uncommitted writes first.  Dropping the transaction
+/// without committing discards its writes and releases its locks.
+///
+/// See `utilities/transactions/pessimistic_transaction.cc`.
+pub struct Transaction<'db> {
+  txn_db: &'db TransactionDb,
+  id: u64,
+  write_options: WriteOptions,
+  lock_timeout: Duration,
+  deadlock_detect: bool,
+  writes: WriteBatchWithIndex,
+  /// Keys this transaction holds locks on.
+  locked: BTreeSet<Vec<u8>>,
+}
+
+impl Transaction<'_> {
+  /// Read `key`, overlaid with the transaction's own writes.  Takes no lock,
+  /// so the value may change before the transaction commits.
+  ///
+  /// Returns [`Error::NotFound`] if the key does not exist or was deleted.
+  pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Vec<u8>, Error> {
+    self
+      .writes
+      .get_from_batch_and_db(&self.txn_db.db, &ReadOptions::default(), key)
+  }
+
+  /// Lock `key`, then read it.  No other transaction can write the key until
+  /// this one commits or is dropped, so the value read stays current.
+  ///
+  /// The lock is kept even if the key does not exist ([`Error::NotFound`]).
+  pub fn get_for_update<K: AsRef<[u8]>>(&mut self, key: K) -> Result<Vec<u8>, Error> {
+    let key = key.as_ref();
+    self.lock(key)?;
+    self.get(key)
+  }
+
+  /// Lock `key` and buffer a write of `key` → `value`.
+  ///
+  /// Fails with [`Error::TimedOut`] if the lock is not granted within the
+  /// lock timeout, or with [`Error::Busy`] if waiting for it would deadlock.
+  /// The transaction remains usable either way.
+  pub fn put<K, V>(&mut self, key: K, value: V) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let key = key.as_ref();
+    self.lock(key)?;
+    self.writes.put(key, value);
+    Ok(())
+  }
+
+  /// Lock `key` and buffer its deletion.  Fails like [`Transaction::put`].
+  pub fn delete<K: AsRef<[u8]>>(&mut self, key: K) -> Result<(), Error> {
+    let key = key.as_ref();
+    self.lock(key)?;
+    self.writes.delete(key);
+    Ok(())
+  }
+
+  /// Apply the buffered writes atomically with [`Db::write`], then release
+  /// the transaction's locks.
+  pub fn commit(mut self) -> Result<(), Error> {
+    let batch = std::mem::take(&mut self.writes).into_write_batch();
+    self.txn_db.db.write(&self.write_options, batch)
+  }
+
+  /// Discard the buffered writes and release the transaction's locks.
+  pub fn rollback(self) {}
+
+  fn lock(&mut self, key: &[u8]) -> Result<(), Error> {
+    if self.locked.contains(key) {
+      return Ok(());
+    }
+    self
+      .txn_db
+      .lock_manager
+      .lock(self.id, key, self.lock_timeout, self.deadlock_detect)?;
+    self.locked.insert(key.to_vec());
+    Ok(())
+  }
+}
+
+impl Drop for Transaction<'_> {
+  fn drop(&mut self) {
+    for key in &self.locked {
+      self.txn_db.lock_manager.unlock(self.id, key);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Arc;
+
+  fn txn_db() -> TransactionDb {
+    TransactionDb::new(Db::default(), TransactionDbOptions::default())
+  }
+
+  fn no_wait() -> TransactionOptions {
+    TransactionOptions {
+      lock_timeout: Some(Duration::ZERO),
+      ..TransactionOptions::default()
+    }
+  }
+
+  #[test]
+  fn commit_applies_writes_atomically() {
+    let db = txn_db();
+    db.put(b"a", b"1").unwrap();
+    let mut txn = db.begin_transaction(&WriteOptions::default(), &no_wait());
+    assert_eq!(txn.get_for_update(b"a").unwrap(), b"1");
+    txn.delete(b"a").unwrap();
+    txn.put(b"b", b"2").unwrap();
+    assert!(txn.get(b"a").unwrap_err().is_not_found());
+    assert_eq!(txn.get(b"b").unwrap(), b"2");
+    assert_eq!(db.get(b"a").unwrap(), b"1");
+    txn.commit().unwrap();
+    assert!(db.get(b"a").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"b").unwrap(), b"2");
+  }
+
+  #[test]
+  fn rollback_discards_writes_and_releases_locks() {
+    let db = txn_db();
+    let mut txn = db.begin_transaction(&WriteOptions::default(), &no_wait());
+    txn.put(b"k", b"v").unwrap();
+    txn.rollback();
+    assert!(db.get(b"k").unwrap_err().is_not_found());
+    let mut txn = db.begin_transaction(&WriteOptions::default(), &no_wait());
+    txn.put(b"k", b"v").unwrap();
+  }
+
+  #[test]
+  fn locked_key_times_out() {
+    let db = TransactionDb::new(
+      Db::default(),
+      TransactionDbOptions {
+        default_lock_timeout: Duration::ZERO,
+        ..TransactionDbOptions::default()
+      },
+    );
+    let mut t1 = db.begin_transaction(&WriteOptions::default(), &no_wait());
+    let mut t2 = db.begin_transaction(&WriteOptions::default(), &no_wait());
+    t1.get_for_update(b"k").unwrap_err();
+    assert!(matches!(t2.put(b"k", b"v"), Err(Error::TimedOut(_))));
+    // Non-transactional writes respect the lock too.
+    assert!(matches!(db.put(b"k", b"v"), Err(Error::TimedOut(_))));
+    // Other keys are unaffected, and the lock goes away with t1.
+    t2.put(b"j", b"v").unwrap();
+    drop(t1);
+    t2.put(b"k", b"v").unwrap();
+    t2.commit().unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+  }
+
+  #[test]
+  fn waiting_transaction_proceeds_after_commit() {
+    let db = Arc::new(txn_db());
+    let mut t1 = db.begin_transaction(&WriteOptions::default(), &TransactionOptions::default());
+    t1.put(b"k", b"1").unwrap();
+    let waiter = {
+      let db = Arc::clone(&db);
+      std::thread::spawn(move || {
+        let opts = TransactionOptions {
+          lock_timeout: Some(Duration::from_secs(10)),
+          ..TransactionOptions::default()
+        };
+        let mut t2 = db.begin_transaction(&WriteOptions::default(), &opts);
+        let v = t2.get_for_update(b"k").unwrap();
+        t2.put(b"k", [v, b"2".to_vec()].concat()).unwrap();
+        t2.commit().unwrap();
+      })
+    };
+    std::thread::sleep(Duration::from_millis(20));
+    t1.commit().unwrap();
+    waiter.join().unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"12");
+  }
+
+  #[test]
+  fn deadlock_is_detected() {
+    let db = Arc::new(txn_db());
+    let long_wait = TransactionOptions {
+      lock_timeout: Some(Duration::from_secs(10)),
+      ..TransactionOptions::default()
+    };
+    let mut t1 = db.begin_transaction(&WriteOptions::default(), &long_wait);
+    t1.put(b"a", b"1").unwrap();
+    let (locked_b, wait_b) = std::sync::mpsc::channel();
+    let other = {
+      let db = Arc::clone(&db);
+      let long_wait = long_wait.clone();
+      std::thread::spawn(move || {
+        let mut t2 = db.begin_transaction(&WriteOptions::default(), &long_wait);
+        t2.put(b"b", b"2").unwrap();
+        locked_b.send(()).unwrap();
+        // Blocks until t1 gives up `a`, or fails if this wait closes the cycle.
+        t2.put(b"a", b"2").map(|()| t2.commit())
+      })
+    };
+    wait_b.recv().unwrap();
+    // Whichever of the two waits closes the cycle fails with Busy; the other
+    // transaction then gets its lock.
+    match t1.put(b"b", b"1") {
+      Err(Error::Busy(_)) => {
+        drop(t1);
+        other.join().unwrap().unwrap().unwrap();
+      }
+      Ok(()) => {
+        assert!(matches!(other.join().unwrap(), Err(Error::Busy(_))));
+        t1.commit().unwrap();
+      }
+      Err(e) => panic!("{e}"),
+    }
+  }
+}
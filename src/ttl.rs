@@ -0,0 +1,670 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Values that expire a fixed time after they are written.
+//!
+//! [`DbWithTtl`] stores every value with an 8-byte little-endian expiry time
+//! (seconds since the Unix epoch, read from a [`Clock`]) appended.  Reads hide
+//! entries whose expiry has passed and strip the suffix from the rest, and
+//! [`TtlCompactionFilterFactory`] drops expired entries when compaction
+//! rewrites them.
+//!
+//! RocksDB appends the write time and applies one TTL to the whole database;
+//! storing the expiry instead lets each write carry its own TTL.
+//!
+//! See `utilities/ttl/db_ttl_impl.h` in RocksDB.
+
+use crate::coding::{read_u64_le, write_u64_le};
+use crate::compaction_filter::{CompactionDecision, CompactionFilter, CompactionFilterFactory};
+use crate::env::{Clock, SystemClock};
+use crate::{
+  Db, DbIter, Error, FlushOptions, Handler, Options, ReadOptions, WriteBatch, WriteOptions,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Length of the expiry suffix appended to every value.
+const EXPIRY_LEN: usize = 8;
+
+/// Expiry written for values without a TTL.
+const NO_EXPIRY: u64 = u64::MAX;
+
+/// Append `expiry` to `value`.
+fn encode_value(value: &[u8], expiry: u64) -> Vec<u8> {
+  let mut buf = [0u8; EXPIRY_LEN];
+  write_u64_le(&mut buf, expiry);
+  let mut out = Vec::with_capacity(value.len() + EXPIRY_LEN);
+  out.extend_from_slice(value);
+  out.extend_from_slice(&buf);
+  out
+}
+
+/// Split a stored value into the user value and its expiry, or `None` if it
+/// is too short to carry one.
+fn decode_value(stored: &[u8]) -> Option<(&[u8], u64)> {
+  let split = stored.len().checked_sub(EXPIRY_LEN)?;
+  let (value, expiry) = stored.split_at(split);
+  Some((value, read_u64_le(expiry.try_into().unwrap())))
+}
+
+/// An entry stays visible up to and including its expiry second.
+fn is_expired(expiry: u64, now: u64) -> bool {
+  now > expiry
+}
+
+fn corrupt_value() -> Error {
+  Error::Corruption("value too short for a TTL expiry".to_string())
+}
+
+/// Options for a [`DbWithTtl`].
+///
+/// See `include/rocksdb/utilities/db_ttl.h: DBWithTTL::Open`.
+#[derive(Clone)]
+pub struct TtlOptions {
+  /// TTL of values written by [`DbWithTtl::put`] and [`DbWithTtl::write`].
+  /// `None` means they never expire.
+  ///
+  /// Default: `None`.
+  pub default_ttl: Option<Duration>,
+
+  /// Clock that write times and expiry checks are read from.
+  ///
+  /// Default: [`SystemClock`].
+  pub clock: Arc<dyn Clock>,
+}
+
+impl Default for TtlOptions {
+  fn default() -> Self {
+    TtlOptions {
+      default_ttl: None,
+      clock: Arc::new(SystemClock),
+    }
+  }
+}
+
+impl std::fmt::Debug for TtlOptions {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("TtlOptions")
+      .field("default_ttl", &self.default_ttl)
+      .finish_non_exhaustive()
+  }
+}
+
+/// A [`Db`] whose values expire.
+///
+/// Each write records an expiry time of now plus its TTL.  From then on,
+/// [`get`](DbWithTtl::get) returns [`Error::NotFound`] and
+/// [`TtlIter`] skips the entry, and the next compaction that rewrites it
+/// drops it from disk.  An entry still visible to a live snapshot is not
+/// dropped until the snapshot is released.
+///
+/// Values are stored with their expiry appended, so reading them through
+/// [`DbWithTtl::db`] returns the raw encoding.  Merge operators are not
+/// supported.
+///
+/// ```
+/// use roughdb::{DbWithTtl, Options, TtlOptions};
+/// use std::time::Duration;
+///
+/// # let dir = tempfile::tempdir().unwrap();
+/// let mut opts = Options::default();
+/// opts.create_if_missing = true;
+/// let ttl_opts = TtlOptions {
+///   default_ttl: Some(Duration::from_secs(3600)),
+///   ..TtlOptions::default()
+/// };
+/// let db = DbWithTtl::open(dir.path(), opts, ttl_opts)?;
+///
+/// db.put(b"session", b"token")?;
+/// db.put_with_ttl(b"nonce", b"42", Duration::from_secs(60))?;
+/// assert_eq!(db.get(b"session")?, b"token");
+/// # Ok::<(), roughdb::Error>(())
+/// ```
+///
+/// See `utilities/ttl/db_ttl_impl.cc`.
+pub struct DbWithTtl {
+  db: Db,
+  options: TtlOptions,
+}
+
+impl DbWithTtl {
+  /// Open (or create) the database at `path` with [`Db::open`].
+  ///
+  /// A [`TtlCompactionFilterFactory`] is installed as
+  /// `options.compaction_filter_factory`; a factory already set there is
+  /// chained behind it and sees values without their expiry.
+  ///
+  /// Fails with [`Error::NotSupported`] if `options.merge_operator` is set.
+  pub fn open<P: AsRef<std::path::Path>>(
+    path: P,
+    mut options: Options,
+    ttl_options: TtlOptions,
+  ) -> Result<Self, Error> {
+    if options.merge_operator.is_some() {
+      return Err(Error::NotSupported(
+        "DbWithTtl does not support merge operators".to_string(),
+      ));
+    }
+    options.compaction_filter_factory = Some(Arc::new(TtlCompactionFilterFactory::new(
+      Arc::clone(&ttl_options.clock),
+      options.compaction_filter_factory.take(),
+    )));
+    Ok(DbWithTtl {
+      db: Db::open(path, options)?,
+      options: ttl_options,
+    })
+  }
+
+  /// The wrapped database.  Values read through it carry their expiry suffix.
+  pub fn db(&self) -> &Db {
+    &self.db
+  }
+
+  /// Expiry time for a write made now with `ttl`.
+  fn expiry(&self, ttl: Option<Duration>) -> u64 {
+    ttl.map_or(NO_EXPIRY, |ttl| {
+      self
+        .options
+        .clock
+        .current_time()
+        .saturating_add(ttl.as_secs())
+    })
+  }
+
+  /// Look up `key`, returning [`Error::NotFound`] if it is absent, deleted or
+  /// expired.
+  pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Vec<u8>, Error> {
+    self.get_with_options(&ReadOptions::default(), key)
+  }
+
+  /// Look up `key` with explicit read options; see [`DbWithTtl::get`].
+  pub fn get_with_options<K: AsRef<[u8]>>(
+    &self,
+    opts: &ReadOptions,
+    key: K,
+  ) -> Result<Vec<u8>, Error> {
+    let stored = self.db.get_with_options(opts, key)?;
+    self.strip(stored, self.options.clock.current_time())
+  }
+
+  /// Look up several keys at once; see [`Db::multi_get`].  Expired keys
+  /// yield [`Error::NotFound`].
+  pub fn multi_get<K: AsRef<[u8]>>(
+    &self,
+    opts: &ReadOptions,
+    keys: &[K],
+  ) -> Vec<Result<Vec<u8>, Error>> {
+    let now = self.options.clock.current_time();
+    self
+      .db
+      .multi_get(opts, keys)
+      .into_iter()
+      .map(|r| r.and_then(|stored| self.strip(stored, now)))
+      .collect()
+  }
+
+  /// Remove the expiry from `stored`, or fail if it has passed.
+  fn strip(&self, mut stored: Vec<u8>, now: u64) -> Result<Vec<u8>, Error> {
+    let (value, expiry) = decode_value(&stored).ok_or_else(corrupt_value)?;
+    if is_expired(expiry, now) {
+      return Err(Error::NotFound);
+    }
+    stored.truncate(value.len());
+    Ok(stored)
+  }
+
+  /// Write `key` → `value` with [`TtlOptions::default_ttl`].
+  pub fn put<K, V>(&self, key: K, value: V) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    self.put_internal(key.as_ref(), value.as_ref(), self.options.default_ttl)
+  }
+
+  /// Write `key` → `value`, expiring `ttl` after now.
+  pub fn put_with_ttl<K, V>(&self, key: K, value: V, ttl: Duration) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    self.put_internal(key.as_ref(), value.as_ref(), Some(ttl))
+  }
+
+  fn put_internal(&self, key: &[u8], value: &[u8], ttl: Option<Duration>) -> Result<(), Error> {
+    let mut batch = WriteBatch::new();
+    batch.put(key, encode_value(value, self.expiry(ttl)));
+    self.db.write(&WriteOptions::default(), batch)
+  }
+
+  /// Delete `key`.
+  pub fn delete<K: AsRef<[u8]>>(&self, key: K) -> Result<(), Error> {
+    self.db.delete(key)
+  }
+
+  /// Delete every key in `[begin, end)`.
+  pub fn delete_range<K, E>(&self, begin: K, end: E) -> Result<(), Error>
+  where
+    K: AsRef<[u8]>,
+    E: AsRef<[u8]>,
+  {
+    self.db.delete_range(begin, end)
+  }
+
+  /// Apply `batch` atomically, giving each put [`TtlOptions::default_ttl`].
+  ///
+  /// Fails with [`Error::NotSupported`] if the batch holds merge operands
+  /// or writes to a column family other than the default.
+  ///
+  /// See `utilities/ttl/db_ttl_impl.cc: DBWithTTLImpl::Write`.
+  pub fn write(&self, opts: &WriteOptions, batch: WriteBatch) -> Result<(), Error> {
+    struct AppendExpiry {
+      batch: WriteBatch,
+      expiry: u64,
+    }
+    impl Handler for AppendExpiry {
+      fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.batch.put(key, encode_value(value, self.expiry));
+        Ok(())
+      }
+      fn delete(&mut self, key: &[u8]) -> Result<(), Error> {
+        self.batch.delete(key);
+        Ok(())
+      }
+      fn delete_range(&mut self, begin: &[u8], end: &[u8]) -> Result<(), Error> {
+        self.batch.delete_range(begin, end);
+        Ok(())
+      }
+    }
+
+    let mut rewritten = AppendExpiry {
+      batch: WriteBatch::new(),
+      expiry: self.expiry(self.options.default_ttl),
+    };
+    batch.iterate(&mut rewritten)?;
+    self.db.write(opts, rewritten.batch)
+  }
+
+  /// Create an iterator that skips expired entries; see [`Db::new_iterator`].
+  ///
+  /// Expiry is judged against the clock when the iterator is created.
+  pub fn new_iterator(&self, opts: &ReadOptions<'_>) -> Result<TtlIter, Error> {
+    Ok(TtlIter {
+      inner: self.db.new_iterator(opts)?,
+      now: self.options.clock.current_time(),
+      error: None,
+    })
+  }
+
+  /// Flush the memtable; see [`Db::flush`].
+  pub fn flush(&self, opts: &FlushOptions) -> Result<(), Error> {
+    self.db.flush(opts)
+  }
+
+  /// Compact `[begin, end]`, dropping expired entries; see
+  /// [`Db::compact_range`].
+  pub fn compact_range(&self, begin: Option<&[u8]>, end: Option<&[u8]>) -> Result<(), Error> {
+    self.db.compact_range(begin, end)
+  }
+}
+
+/// Iterator over a [`DbWithTtl`] that skips expired entries and yields
+/// values without their expiry.
+///
+/// A stored value too short to hold an expiry stops the iterator with
+/// [`Error::Corruption`], reported by [`status`](TtlIter::status).
+///
+/// See `utilities/ttl/db_ttl_impl.h: TtlIterator`.
+pub struct TtlIter {
+  inner: DbIter,
+  now: u64,
+  error: Option<Error>,
+}
+
+impl TtlIter {
+  /// Returns `true` if the iterator is positioned at a valid entry.
+  pub fn valid(&self) -> bool {
+    self.error.is_none() && self.inner.valid()
+  }
+
+  /// Position at the first live entry.
+  pub fn seek_to_first(&mut self) {
+    self.inner.seek_to_first();
+    self.skip_expired_forward();
+  }
+
+  /// Position at the last live entry.
+  pub fn seek_to_last(&mut self) {
+    self.inner.seek_to_last();
+    self.skip_expired_backward();
+  }
+
+  /// Position at the first live entry with `key >= target`.
+  pub fn seek(&mut self, key: &[u8]) {
+    self.inner.seek(key);
+    self.skip_expired_forward();
+  }
+
+  /// Advance to the next live entry.
+  pub fn next(&mut self) {
+    self.inner.next();
+    self.skip_expired_forward();
+  }
+
+  /// Move to the previous live entry.
+  pub fn prev(&mut self) {
+    self.inner.prev();
+    self.skip_expired_backward();
+  }
+
+  /// Current user key.  Only valid when `valid()` is true.
+  pub fn key(&self) -> &[u8] {
+    self.inner.key()
+  }
+
+  /// Current value, without its expiry.  Only valid when `valid()` is true.
+  pub fn value(&self) -> &[u8] {
+    let stored = self.inner.value();
+    &stored[..stored.len() - EXPIRY_LEN]
+  }
+
+  /// Returns a sticky error if the iterator encountered corruption.
+  pub fn status(&self) -> Option<&Error> {
+    self.error.as_ref().or_else(|| self.inner.status())
+  }
+
+  /// Whether the current entry should be skipped.  Records an error for a
+  /// value without an expiry.
+  fn current_expired(&mut self) -> bool {
+    match decode_value(self.inner.value()) {
+      Some((_, expiry)) => is_expired(expiry, self.now),
+      None => {
+        self.error = Some(corrupt_value());
+        false
+      }
+    }
+  }
+
+  fn skip_expired_forward(&mut self) {
+    while self.valid() && self.current_expired() {
+      self.inner.next();
+    }
+  }
+
+  fn skip_expired_backward(&mut self) {
+    while self.valid() && self.current_expired() {
+      self.inner.prev();
+    }
+  }
+}
+
+/// Creates [`CompactionFilter`]s that remove entries written by a
+/// [`DbWithTtl`] once their expiry has passed.
+///
+/// [`DbWithTtl::open`] installs one automatically.  Entries that have not
+/// expired are passed, without their expiry, to the filters of the wrapped
+/// `user_factory` if there is one; a changed value keeps the original
+/// expiry.
+///
+/// See `utilities/ttl/db_ttl_impl.h: TtlCompactionFilterFactory`.
+pub struct TtlCompactionFilterFactory {
+  clock: Arc<dyn Clock>,
+  user_factory: Option<Arc<dyn CompactionFilterFactory>>,
+}
+
+impl TtlCompactionFilterFactory {
+  /// Create a factory that reads the time from `clock` and chains
+  /// `user_factory`.
+  pub fn new(
+    clock: Arc<dyn Clock>,
+    user_factory: Option<Arc<dyn CompactionFilterFactory>>,
+  ) -> Self {
+    TtlCompactionFilterFactory {
+      clock,
+      user_factory,
+    }
+  }
+}
+
+impl CompactionFilterFactory for TtlCompactionFilterFactory {
+  fn create_compaction_filter(&self) -> Box<dyn CompactionFilter> {
+    Box::new(TtlCompactionFilter {
+      now: self.clock.current_time(),
+      user_filter: self
+        .user_factory
+        .as_ref()
+        .map(|f| f.create_compaction_filter()),
+    })
+  }
+
+  fn name(&self) -> &str {
+    "roughdb.TtlCompactionFilterFactory"
+  }
+}
+
+/// Removes expired entries, judged against the time its compaction started.
+///
+/// See `utilities/ttl/db_ttl_impl.h: TtlCompactionFilter`.
+struct TtlCompactionFilter {
+  now: u64,
+  user_filter: Option<Box<dyn CompactionFilter>>,
+}
+
+impl CompactionFilter for TtlCompactionFilter {
+  fn filter(
+    &mut self,
+    level: usize,
+    key: &[u8],
+    value: &[u8],
+    value_type: u8,
+  ) -> CompactionDecision {
+    if value_type != 1 {
+      return match self.user_filter {
+        Some(ref mut user) => user.filter(level, key, value, value_type),
+        None => CompactionDecision::Keep,
+      };
+    }
+    // Leave values that do not carry an expiry for reads to report.
+    let Some((user_value, expiry)) = decode_value(value) else {
+      return CompactionDecision::Keep;
+    };
+    if is_expired(expiry, self.now) {
+      return CompactionDecision::Remove;
+    }
+    match self.user_filter {
+      Some(ref mut user) => match user.filter(level, key, user_value, value_type) {
+        CompactionDecision::ChangeValue(new_value) => {
+          CompactionDecision::ChangeValue(encode_value(&new_value, expiry))
+        }
+        decision => decision,
+      },
+      None => CompactionDecision::Keep,
+    }
+  }
+
+  fn name(&self) -> &str {
+    "roughdb.TtlCompactionFilter"
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::env::ManualClock;
+  use serial_test::serial;
+
+  fn open(dir: &std::path::Path, options: Options) -> (DbWithTtl, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::new(1_000));
+    let ttl_options = TtlOptions {
+      default_ttl: Some(Duration::from_secs(100)),
+      clock: Arc::clone(&clock) as Arc<dyn Clock>,
+    };
+    let options = Options {
+      create_if_missing: true,
+      ..options
+    };
+    (DbWithTtl::open(dir, options, ttl_options).unwrap(), clock)
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn get_hides_expired_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let (db, clock) = open(dir.path(), Options::default());
+    db.put(b"default", b"a").unwrap();
+    db.put_with_ttl(b"short", b"b", Duration::from_secs(10))
+      .unwrap();
+    assert_eq!(db.get(b"short").unwrap(), b"b");
+    assert_eq!(db.db().get(b"short").unwrap().len(), 1 + EXPIRY_LEN);
+
+    // Visible through the expiry second, gone after it.
+    clock.advance(10);
+    assert_eq!(db.get(b"short").unwrap(), b"b");
+    clock.advance(1);
+    assert!(db.get(b"short").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"default").unwrap(), b"a");
+    let got = db.multi_get(&ReadOptions::default(), &["short", "default"]);
+    assert!(got[0].as_ref().unwrap_err().is_not_found());
+    assert_eq!(got[1].as_ref().unwrap(), b"a");
+
+    clock.advance(90);
+    assert!(db.get(b"default").unwrap_err().is_not_found());
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn iterator_skips_expired_entries_in_both_directions() {
+    let dir = tempfile::tempdir().unwrap();
+    let (db, clock) = open(dir.path(), Options::default());
+    for (i, key) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+      // "a", "c" and "e" expire first.
+      let ttl = if i % 2 == 0 { 5 } else { 50 };
+      db.put_with_ttl(key, key.to_uppercase(), Duration::from_secs(ttl))
+        .unwrap();
+    }
+    clock.advance(6);
+
+    let mut it = db.new_iterator(&ReadOptions::default()).unwrap();
+    let mut forward = Vec::new();
+    it.seek_to_first();
+    while it.valid() {
+      forward.push((it.key().to_vec(), it.value().to_vec()));
+      it.next();
+    }
+    assert_eq!(
+      forward,
+      vec![
+        (b"b".to_vec(), b"B".to_vec()),
+        (b"d".to_vec(), b"D".to_vec())
+      ]
+    );
+
+    let mut backward = Vec::new();
+    it.seek_to_last();
+    while it.valid() {
+      backward.push(it.key().to_vec());
+      it.prev();
+    }
+    assert_eq!(backward, vec![b"d".to_vec(), b"b".to_vec()]);
+
+    it.seek(b"c");
+    assert_eq!(it.key(), b"d");
+    assert!(it.status().is_none());
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn compaction_drops_expired_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let (db, clock) = open(dir.path(), Options::default());
+    let mut batch = WriteBatch::new();
+    batch.put(b"batched", b"x");
+    batch.delete(b"missing");
+    db.write(&WriteOptions::default(), batch).unwrap();
+    db.put_with_ttl(b"long", b"y", Duration::from_secs(1_000))
+      .unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+
+    clock.advance(101);
+    // Hidden from reads but still stored until compaction rewrites it.
+    assert!(db.get(b"batched").unwrap_err().is_not_found());
+    assert!(db.db().get(b"batched").is_ok());
+
+    db.compact_range(None, None).unwrap();
+    assert!(db.db().get(b"batched").unwrap_err().is_not_found());
+    assert_eq!(db.get(b"long").unwrap(), b"y");
+  }
+
+  struct UppercaseFactory;
+
+  struct Uppercase;
+
+  impl CompactionFilter for Uppercase {
+    fn filter(&mut self, _: usize, _: &[u8], value: &[u8], _: u8) -> CompactionDecision {
+      CompactionDecision::ChangeValue(value.to_ascii_uppercase())
+    }
+    fn name(&self) -> &str {
+      "test.Uppercase"
+    }
+  }
+
+  impl CompactionFilterFactory for UppercaseFactory {
+    fn create_compaction_filter(&self) -> Box<dyn CompactionFilter> {
+      Box::new(Uppercase)
+    }
+    fn name(&self) -> &str {
+      "test.UppercaseFactory"
+    }
+  }
+
+  #[serial(fd)]
+  #[test]
+  fn user_compaction_filter_sees_values_without_expiry() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = Options {
+      compaction_filter_factory: Some(Arc::new(UppercaseFactory)),
+      ..Options::default()
+    };
+    let (db, clock) = open(dir.path(), options);
+    db.put(b"k", b"hello").unwrap();
+    db.flush(&FlushOptions::default()).unwrap();
+    db.compact_range(None, None).unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"HELLO");
+
+    // The rewritten value kept its expiry.
+    clock.advance(101);
+    assert!(db.get(b"k").unwrap_err().is_not_found());
+  }
+
+  #[test]
+  fn open_rejects_merge_operator() {
+    struct Concat;
+    impl crate::MergeOperator for Concat {
+      fn full_merge(&self, _: &[u8], base: Option<&[u8]>, ops: &[&[u8]]) -> Option<Vec<u8>> {
+        let mut out = base.unwrap_or_default().to_vec();
+        ops.iter().for_each(|op| out.extend_from_slice(op));
+        Some(out)
+      }
+      fn name(&self) -> &str {
+        "test.Concat"
+      }
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let options = Options {
+      create_if_missing: true,
+      merge_operator: Some(Arc::new(Concat)),
+      ..Options::default()
+    };
+    let err = DbWithTtl::open(dir.path(), options, TtlOptions::default()).err();
+    assert!(matches!(err, Some(Error::NotSupported(_))));
+  }
+}
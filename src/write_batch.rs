@@ -13,26 +13,104 @@
 use crate::coding::{
   read_u32_le, read_u64_le, read_varu64, write_u32_le, write_u64_le, write_varu64,
 };
+use crate::column_family::{ColumnFamilyHandle, DEFAULT_COLUMN_FAMILY_ID};
 use crate::Error;
 
 const HEADER_SIZE: usize = 12;
 const TAG_VALUE: u8 = 0x01;
 const TAG_DELETE: u8 = 0x00;
+const TAG_MERGE: u8 = 0x02;
+const TAG_RANGE_DELETION: u8 = 0x0F;
+// Records for a non-default column family carry its id (a varint) after the
+// tag.  Default-family records keep the tags above.  See RocksDB's
+// `db/dbformat.h: ValueType`.
+const TAG_COLUMN_FAMILY_DELETE: u8 = 0x04;
+const TAG_COLUMN_FAMILY_VALUE: u8 = 0x05;
+const TAG_COLUMN_FAMILY_MERGE: u8 = 0x06;
+const TAG_COLUMN_FAMILY_RANGE_DELETION: u8 = 0x0E;
 
 pub trait Handler {
   fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error>;
   fn delete(&mut self, key: &[u8]) -> Result<(), Error>;
+
+  /// Called for each merge operand in the batch.  Handlers that predate merge
+  /// support reject the record rather than silently dropping it.
+  fn merge(&mut self, _key: &[u8], _value: &[u8]) -> Result<(), Error> {
+    Err(Error::NotSupported(
+      "WriteBatch handler does not support merge".to_string(),
+    ))
+  }
+
+  /// Called for each range tombstone `[begin, end)` in the batch.  Handlers
+  /// that predate range deletion reject the record, as for `merge`.
+  fn delete_range(&mut self, _begin: &[u8], _end: &[u8]) -> Result<(), Error> {
+    Err(Error::NotSupported(
+      "WriteBatch handler does not support delete_range".to_string(),
+    ))
+  }
+
+  /// Called for every put, with the id of the column family it targets.  The
+  /// default forwards default-family records to [`Handler::put`] and rejects
+  /// the rest, so handlers unaware of column families keep working on
+  /// batches that do not use them.
+  ///
+  /// See `include/rocksdb/write_batch.h: WriteBatch::Handler::PutCF`.
+  fn put_cf(&mut self, column_family: u32, key: &[u8], value: &[u8]) -> Result<(), Error> {
+    check_default_column_family(column_family)?;
+    self.put(key, value)
+  }
+
+  /// Column-family form of [`Handler::delete`]; see [`Handler::put_cf`].
+  fn delete_cf(&mut self, column_family: u32, key: &[u8]) -> Result<(), Error> {
+    check_default_column_family(column_family)?;
+    self.delete(key)
+  }
+
+  /// Column-family form of [`Handler::merge`]; see [`Handler::put_cf`].
+  fn merge_cf(&mut self, column_family: u32, key: &[u8], value: &[u8]) -> Result<(), Error> {
+    check_default_column_family(column_family)?;
+    self.merge(key, value)
+  }
+
+  /// Column-family form of [`Handler::delete_range`]; see [`Handler::put_cf`].
+  fn delete_range_cf(&mut self, column_family: u32, begin: &[u8], end: &[u8]) -> Result<(), Error> {
+    check_default_column_family(column_family)?;
+    self.delete_range(begin, end)
+  }
+}
+
+fn check_default_column_family(column_family: u32) -> Result<(), Error> {
+  if column_family == DEFAULT_COLUMN_FAMILY_ID {
+    Ok(())
+  } else {
+    Err(Error::InvalidArgument(format!(
+      "WriteBatch handler does not support column family {column_family}"
+    )))
+  }
+}
+
+/// One decoded record of a [`WriteBatch`].
+pub(crate) enum Record<'a> {
+  Put(&'a [u8], &'a [u8]),
+  Delete(&'a [u8]),
+  Merge(&'a [u8], &'a [u8]),
+  DeleteRange(&'a [u8], &'a [u8]),
 }
 
 #[derive(Clone)]
 pub struct WriteBatch {
   rep: Vec<u8>,
+  /// Non-default column families the records target, noted as they are
+  /// added; `None` for a batch rebuilt from bytes, whose records must be
+  /// decoded to find them.
+  column_families: Option<Vec<u32>>,
 }
 
 impl WriteBatch {
   pub fn new() -> Self {
     WriteBatch {
       rep: vec![0u8; HEADER_SIZE],
+      column_families: Some(Vec::new()),
     }
   }
 
@@ -41,42 +119,130 @@ impl WriteBatch {
     K: AsRef<[u8]>,
     V: AsRef<[u8]>,
   {
-    let (key, value) = (key.as_ref(), value.as_ref());
-    self.rep.reserve(key.len() + value.len() + 21);
-    self.rep.push(TAG_VALUE);
-    let mut tmp = [0u8; 10];
-    let n = write_varu64(&mut tmp, key.len() as u64);
-    self.rep.extend_from_slice(&tmp[..n]);
-    self.rep.extend_from_slice(key);
-    let n = write_varu64(&mut tmp, value.len() as u64);
-    self.rep.extend_from_slice(&tmp[..n]);
-    self.rep.extend_from_slice(value);
-    self.set_count(self.count() + 1);
+    self.push_key_value(
+      DEFAULT_COLUMN_FAMILY_ID,
+      TAG_VALUE,
+      key.as_ref(),
+      value.as_ref(),
+    );
+  }
+
+  /// Append a merge operand for `key`.  The operand is combined with the
+  /// existing value by the [`MergeOperator`](crate::MergeOperator) configured
+  /// on the database.  See `db/write_batch.cc: WriteBatch::Merge`.
+  pub fn merge<K, V>(&mut self, key: K, value: V)
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    self.push_key_value(
+      DEFAULT_COLUMN_FAMILY_ID,
+      TAG_MERGE,
+      key.as_ref(),
+      value.as_ref(),
+    );
+  }
+
+  /// Append a range tombstone deleting every key in `[begin, end)`.  The
+  /// range is a single record — it consumes one sequence number no matter
+  /// how many keys it covers.  See `db/write_batch.cc: WriteBatch::DeleteRange`.
+  pub fn delete_range<K, E>(&mut self, begin: K, end: E)
+  where
+    K: AsRef<[u8]>,
+    E: AsRef<[u8]>,
+  {
+    self.push_key_value(
+      DEFAULT_COLUMN_FAMILY_ID,
+      TAG_RANGE_DELETION,
+      begin.as_ref(),
+      end.as_ref(),
+    );
   }
 
   pub fn delete<K>(&mut self, key: K)
   where
     K: AsRef<[u8]>,
   {
-    let key = key.as_ref();
-    self.rep.reserve(key.len() + 11);
-    self.rep.push(TAG_DELETE);
-    let mut tmp = [0u8; 10];
-    let n = write_varu64(&mut tmp, key.len() as u64);
-    self.rep.extend_from_slice(&tmp[..n]);
-    self.rep.extend_from_slice(key);
-    self.set_count(self.count() + 1);
+    self.push_key(DEFAULT_COLUMN_FAMILY_ID, TAG_DELETE, key.as_ref());
+  }
+
+  /// Append a put of `key` into column family `cf`.  All records of a batch
+  /// are applied atomically, whichever families they target.
+  ///
+  /// See `db/write_batch.cc: WriteBatch::Put(ColumnFamilyHandle*, ...)`.
+  pub fn put_cf<K, V>(&mut self, cf: &ColumnFamilyHandle, key: K, value: V)
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    self.push_key_value(cf.id(), TAG_VALUE, key.as_ref(), value.as_ref());
+  }
+
+  /// Append a deletion of `key` from column family `cf`.
+  pub fn delete_cf<K>(&mut self, cf: &ColumnFamilyHandle, key: K)
+  where
+    K: AsRef<[u8]>,
+  {
+    self.push_key(cf.id(), TAG_DELETE, key.as_ref());
+  }
+
+  /// Append a merge operand for `key` in column family `cf`; the family's
+  /// own merge operator combines it.
+  pub fn merge_cf<K, V>(&mut self, cf: &ColumnFamilyHandle, key: K, value: V)
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    self.push_key_value(cf.id(), TAG_MERGE, key.as_ref(), value.as_ref());
+  }
+
+  /// Append a range tombstone deleting `[begin, end)` in column family `cf`.
+  pub fn delete_range_cf<K, E>(&mut self, cf: &ColumnFamilyHandle, begin: K, end: E)
+  where
+    K: AsRef<[u8]>,
+    E: AsRef<[u8]>,
+  {
+    self.push_key_value(cf.id(), TAG_RANGE_DELETION, begin.as_ref(), end.as_ref());
   }
 
   pub fn clear(&mut self) {
     self.rep.clear();
     self.rep.resize(HEADER_SIZE, 0);
+    self.column_families = Some(Vec::new());
   }
 
   pub fn approximate_size(&self) -> usize {
     self.rep.len()
   }
 
+  /// Rebuild a batch from its serialized form, as returned by
+  /// [`WriteBatch::data`], e.g. after shipping it to another process.
+  ///
+  /// Only the 12-byte header is checked here; the records are validated when
+  /// the batch is written or iterated.  Returns [`Error::Corruption`] if
+  /// `data` is shorter than the header.
+  ///
+  /// See `include/rocksdb/write_batch.h: WriteBatch(const std::string& rep)`.
+  pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+    Self::from_contents(data.to_vec())
+  }
+
+  /// The serialized batch: an 8-byte sequence number and a 4-byte record
+  /// count (both little-endian) followed by the records.
+  ///
+  /// See `include/rocksdb/write_batch.h: WriteBatch::Data`.
+  pub fn data(&self) -> &[u8] {
+    &self.rep
+  }
+
+  /// Sequence number of the batch's first record once written; 0 for a batch
+  /// that has not been.  Batches from
+  /// [`Db::get_updates_since`](crate::Db::get_updates_since) carry the
+  /// sequence they were committed at.
+  pub fn sequence(&self) -> u64 {
+    read_u64_le(self.rep[0..8].try_into().unwrap())
+  }
+
   pub fn iterate(&self, handler: &mut dyn Handler) -> Result<(), Error> {
     if self.rep.len() < HEADER_SIZE {
       return Err(Error::Corruption("WriteBatch too small".to_string()));
@@ -85,58 +251,14 @@ impl WriteBatch {
     let mut pos = HEADER_SIZE;
     let mut seen: u32 = 0;
     while pos < self.rep.len() {
-      let tag = self.rep[pos];
-      pos += 1;
-      match tag {
-        TAG_VALUE => {
-          let (klen, ksize) = read_varu64(&self.rep[pos..]);
-          if ksize == 0 {
-            return Err(Error::Corruption(
-              "bad varint in WriteBatch key length".to_string(),
-            ));
-          }
-          pos += ksize;
-          let kend = pos + klen as usize;
-          if kend > self.rep.len() {
-            return Err(Error::Corruption("WriteBatch key truncated".to_string()));
-          }
-          let key = &self.rep[pos..kend];
-          pos = kend;
-          let (vlen, vsize) = read_varu64(&self.rep[pos..]);
-          if vsize == 0 {
-            return Err(Error::Corruption(
-              "bad varint in WriteBatch value length".to_string(),
-            ));
-          }
-          pos += vsize;
-          let vend = pos + vlen as usize;
-          if vend > self.rep.len() {
-            return Err(Error::Corruption("WriteBatch value truncated".to_string()));
-          }
-          let value = &self.rep[pos..vend];
-          pos = vend;
-          handler.put(key, value)?;
-        }
-        TAG_DELETE => {
-          let (klen, ksize) = read_varu64(&self.rep[pos..]);
-          if ksize == 0 {
-            return Err(Error::Corruption(
-              "bad varint in WriteBatch key length".to_string(),
-            ));
-          }
-          pos += ksize;
-          let kend = pos + klen as usize;
-          if kend > self.rep.len() {
-            return Err(Error::Corruption("WriteBatch key truncated".to_string()));
-          }
-          let key = &self.rep[pos..kend];
-          pos = kend;
-          handler.delete(key)?;
-        }
-        _ => {
-          return Err(Error::Corruption(format!("unknown WriteBatch tag: {tag}")));
-        }
+      let (cf, record, next) = self.decode_record(pos)?;
+      match record {
+        Record::Put(key, value) => handler.put_cf(cf, key, value)?,
+        Record::Delete(key) => handler.delete_cf(cf, key)?,
+        Record::Merge(key, value) => handler.merge_cf(cf, key, value)?,
+        Record::DeleteRange(begin, end) => handler.delete_range_cf(cf, begin, end)?,
       }
+      pos = next;
       seen += 1;
     }
     if seen != expected {
@@ -151,24 +273,42 @@ impl WriteBatch {
     let combined = self.count() + src.count();
     self.rep.extend_from_slice(&src.rep[HEADER_SIZE..]);
     self.set_count(combined);
+    match (&mut self.column_families, &src.column_families) {
+      (Some(ids), Some(src_ids)) => {
+        for &cf in src_ids {
+          note_column_family(ids, cf);
+        }
+      }
+      (ids, _) => *ids = None,
+    }
   }
 
   // ── pub(crate) internals ────────────────────────────────────────────────────
 
-  pub(crate) fn sequence(&self) -> u64 {
-    read_u64_le(self.rep[0..8].try_into().unwrap())
-  }
-
   pub(crate) fn set_sequence(&mut self, seq: u64) {
     write_u64_le((&mut self.rep[0..8]).try_into().unwrap(), seq);
   }
 
-  pub(crate) fn count(&self) -> u32 {
-    read_u32_le(self.rep[8..12].try_into().unwrap())
+  /// The WAL record of an ingestion that gave its files sequence number
+  /// `sequence`: an empty batch stamped one past it, so that a reader
+  /// advancing by the batch's count lands after the ingestion.
+  ///
+  /// The write path never logs an empty batch, so empty batches in the WAL
+  /// are always these.
+  pub(crate) fn ingestion_marker(sequence: u64) -> WriteBatch {
+    let mut marker = WriteBatch::new();
+    marker.set_sequence(sequence + 1);
+    marker
   }
 
-  pub(crate) fn contents(&self) -> &[u8] {
-    &self.rep
+  /// The sequence number of the ingestion this WAL record marks, if it is
+  /// an [`ingestion_marker`](Self::ingestion_marker).
+  pub(crate) fn ingested_sequence(&self) -> Option<u64> {
+    (self.count() == 0).then(|| self.sequence().saturating_sub(1))
+  }
+
+  pub(crate) fn count(&self) -> u32 {
+    read_u32_le(self.rep[8..12].try_into().unwrap())
   }
 
   /// Wrap raw bytes (as read from the WAL) into a `WriteBatch` for replay.
@@ -176,7 +316,128 @@ impl WriteBatch {
     if data.len() < HEADER_SIZE {
       return Err(Error::Corruption("write batch record too small".to_owned()));
     }
-    Ok(Self { rep: data })
+    Ok(Self {
+      rep: data,
+      column_families: None,
+    })
+  }
+
+  /// The non-default column families the batch's records target, decoding
+  /// the records only if the batch was rebuilt from bytes.
+  pub(crate) fn column_family_ids(&self) -> Result<std::borrow::Cow<'_, [u32]>, Error> {
+    if let Some(ids) = &self.column_families {
+      return Ok(ids.into());
+    }
+    let mut ids = Vec::new();
+    let mut pos = HEADER_SIZE;
+    while pos < self.rep.len() {
+      let (cf, _, next) = self.decode_record(pos)?;
+      if cf != DEFAULT_COLUMN_FAMILY_ID {
+        note_column_family(&mut ids, cf);
+      }
+      pos = next;
+    }
+    Ok(ids.into())
+  }
+
+  /// Decode the record at `offset` (as returned by [`WriteBatch::end_offset`]
+  /// before the record was added).
+  pub(crate) fn record_at(&self, offset: usize) -> Result<Record<'_>, Error> {
+    self.decode_record(offset).map(|(_, record, _)| record)
+  }
+
+  /// Offset at which the next record will be written.
+  pub(crate) fn end_offset(&self) -> usize {
+    self.rep.len()
+  }
+
+  /// Decode the record starting at `pos`, returning the id of the column
+  /// family it targets, the record, and the offset of the record that follows.
+  fn decode_record(&self, mut pos: usize) -> Result<(u32, Record<'_>, usize), Error> {
+    let mut tag = self.rep[pos];
+    pos += 1;
+    let mut cf = DEFAULT_COLUMN_FAMILY_ID;
+    if let Some(base) = default_family_tag(tag) {
+      let (id, size) = read_varu64(&self.rep[pos..]);
+      if size == 0 {
+        return Err(Error::Corruption(
+          "bad varint in WriteBatch column family".to_string(),
+        ));
+      }
+      pos += size;
+      cf = id as u32;
+      tag = base;
+    }
+    let key = self.read_slice(&mut pos, "key")?;
+    let record = match tag {
+      TAG_DELETE => Record::Delete(key),
+      TAG_VALUE => Record::Put(key, self.read_slice(&mut pos, "value")?),
+      TAG_MERGE => Record::Merge(key, self.read_slice(&mut pos, "value")?),
+      TAG_RANGE_DELETION => Record::DeleteRange(key, self.read_slice(&mut pos, "value")?),
+      _ => return Err(Error::Corruption(format!("unknown WriteBatch tag: {tag}"))),
+    };
+    Ok((cf, record, pos))
+  }
+
+  /// Read a length-prefixed slice at `*pos` and advance past it.
+  fn read_slice(&self, pos: &mut usize, what: &str) -> Result<&[u8], Error> {
+    let (len, size) = read_varu64(&self.rep[*pos..]);
+    if size == 0 {
+      return Err(Error::Corruption(format!(
+        "bad varint in WriteBatch {what} length"
+      )));
+    }
+    *pos += size;
+    let end = *pos + len as usize;
+    if end > self.rep.len() {
+      return Err(Error::Corruption(format!("WriteBatch {what} truncated")));
+    }
+    let slice = &self.rep[*pos..end];
+    *pos = end;
+    Ok(slice)
+  }
+
+  fn push_key_value(&mut self, cf: u32, tag: u8, key: &[u8], value: &[u8]) {
+    self.rep.reserve(key.len() + value.len() + 26);
+    self.push_tag(cf, tag);
+    self.push_slice(key);
+    self.push_slice(value);
+    self.set_count(self.count() + 1);
+  }
+
+  fn push_key(&mut self, cf: u32, tag: u8, key: &[u8]) {
+    self.rep.reserve(key.len() + 16);
+    self.push_tag(cf, tag);
+    self.push_slice(key);
+    self.set_count(self.count() + 1);
+  }
+
+  /// Push `tag`, switched to its column-family form followed by `cf` when
+  /// `cf` is not the default family.
+  fn push_tag(&mut self, cf: u32, tag: u8) {
+    if cf == DEFAULT_COLUMN_FAMILY_ID {
+      self.rep.push(tag);
+      return;
+    }
+    if let Some(ids) = &mut self.column_families {
+      note_column_family(ids, cf);
+    }
+    self.rep.push(match tag {
+      TAG_DELETE => TAG_COLUMN_FAMILY_DELETE,
+      TAG_VALUE => TAG_COLUMN_FAMILY_VALUE,
+      TAG_MERGE => TAG_COLUMN_FAMILY_MERGE,
+      _ => TAG_COLUMN_FAMILY_RANGE_DELETION,
+    });
+    let mut tmp = [0u8; 10];
+    let n = write_varu64(&mut tmp, cf as u64);
+    self.rep.extend_from_slice(&tmp[..n]);
+  }
+
+  fn push_slice(&mut self, data: &[u8]) {
+    let mut tmp = [0u8; 10];
+    let n = write_varu64(&mut tmp, data.len() as u64);
+    self.rep.extend_from_slice(&tmp[..n]);
+    self.rep.extend_from_slice(data);
   }
 
   fn set_count(&mut self, n: u32) {
@@ -184,6 +445,24 @@ impl WriteBatch {
   }
 }
 
+fn note_column_family(ids: &mut Vec<u32>, cf: u32) {
+  if !ids.contains(&cf) {
+    ids.push(cf);
+  }
+}
+
+/// The default-family tag for a column-family record tag, or `None` if `tag`
+/// is not a column-family tag.
+fn default_family_tag(tag: u8) -> Option<u8> {
+  match tag {
+    TAG_COLUMN_FAMILY_DELETE => Some(TAG_DELETE),
+    TAG_COLUMN_FAMILY_VALUE => Some(TAG_VALUE),
+    TAG_COLUMN_FAMILY_MERGE => Some(TAG_MERGE),
+    TAG_COLUMN_FAMILY_RANGE_DELETION => Some(TAG_RANGE_DELETION),
+    _ => None,
+  }
+}
+
 impl Default for WriteBatch {
   fn default() -> Self {
     Self::new()
@@ -203,6 +482,8 @@ mod tests {
   enum Op {
     Put(Vec<u8>, Vec<u8>),
     Delete(Vec<u8>),
+    Merge(Vec<u8>, Vec<u8>),
+    DeleteRange(Vec<u8>, Vec<u8>),
   }
 
   impl Handler for Recording {
@@ -214,6 +495,14 @@ mod tests {
       self.ops.push(Op::Delete(key.to_vec()));
       Ok(())
     }
+    fn merge(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+      self.ops.push(Op::Merge(key.to_vec(), value.to_vec()));
+      Ok(())
+    }
+    fn delete_range(&mut self, begin: &[u8], end: &[u8]) -> Result<(), Error> {
+      self.ops.push(Op::DeleteRange(begin.to_vec(), end.to_vec()));
+      Ok(())
+    }
   }
 
   #[test]
@@ -262,6 +551,147 @@ mod tests {
     );
   }
 
+  #[test]
+  fn merge_and_iterate() {
+    let mut b = WriteBatch::new();
+    b.put(b"a", b"1");
+    b.merge(b"a", b"+2");
+    b.delete(b"b");
+    let mut r = Recording { ops: vec![] };
+    b.iterate(&mut r).unwrap();
+    assert_eq!(b.count(), 3);
+    assert_eq!(
+      r.ops,
+      vec![
+        Op::Put(b"a".to_vec(), b"1".to_vec()),
+        Op::Merge(b"a".to_vec(), b"+2".to_vec()),
+        Op::Delete(b"b".to_vec()),
+      ]
+    );
+  }
+
+  #[test]
+  fn merge_rejected_by_default_handler() {
+    struct PutOnly;
+    impl Handler for PutOnly {
+      fn put(&mut self, _key: &[u8], _value: &[u8]) -> Result<(), Error> {
+        Ok(())
+      }
+      fn delete(&mut self, _key: &[u8]) -> Result<(), Error> {
+        Ok(())
+      }
+    }
+    let mut b = WriteBatch::new();
+    b.merge(b"k", b"v");
+    assert!(matches!(
+      b.iterate(&mut PutOnly),
+      Err(Error::NotSupported(_))
+    ));
+  }
+
+  #[test]
+  fn delete_range_and_iterate() {
+    let mut b = WriteBatch::new();
+    b.put(b"a", b"1");
+    b.delete_range(b"b", b"d");
+    b.delete(b"e");
+    let mut r = Recording { ops: vec![] };
+    b.iterate(&mut r).unwrap();
+    assert_eq!(b.count(), 3);
+    assert_eq!(
+      r.ops,
+      vec![
+        Op::Put(b"a".to_vec(), b"1".to_vec()),
+        Op::DeleteRange(b"b".to_vec(), b"d".to_vec()),
+        Op::Delete(b"e".to_vec()),
+      ]
+    );
+  }
+
+  #[test]
+  fn column_family_records_round_trip() {
+    #[derive(Default)]
+    struct ByFamily {
+      ops: Vec<(u32, Op)>,
+    }
+    impl Handler for ByFamily {
+      fn put(&mut self, _key: &[u8], _value: &[u8]) -> Result<(), Error> {
+        unreachable!("put_cf is overridden")
+      }
+      fn delete(&mut self, _key: &[u8]) -> Result<(), Error> {
+        unreachable!("delete_cf is overridden")
+      }
+      fn put_cf(&mut self, cf: u32, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.ops.push((cf, Op::Put(key.to_vec(), value.to_vec())));
+        Ok(())
+      }
+      fn delete_cf(&mut self, cf: u32, key: &[u8]) -> Result<(), Error> {
+        self.ops.push((cf, Op::Delete(key.to_vec())));
+        Ok(())
+      }
+      fn merge_cf(&mut self, cf: u32, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.ops.push((cf, Op::Merge(key.to_vec(), value.to_vec())));
+        Ok(())
+      }
+      fn delete_range_cf(&mut self, cf: u32, begin: &[u8], end: &[u8]) -> Result<(), Error> {
+        self
+          .ops
+          .push((cf, Op::DeleteRange(begin.to_vec(), end.to_vec())));
+        Ok(())
+      }
+    }
+
+    let cf = ColumnFamilyHandle::new(300, "cf");
+    let mut b = WriteBatch::new();
+    b.put(b"a", b"1");
+    b.put_cf(&cf, b"b", b"2");
+    b.delete_cf(&cf, b"c");
+    b.merge_cf(&cf, b"d", b"4");
+    b.delete_range_cf(&cf, b"e", b"f");
+    assert_eq!(b.count(), 5);
+    let mut r = ByFamily::default();
+    b.iterate(&mut r).unwrap();
+    assert_eq!(
+      r.ops,
+      vec![
+        (0, Op::Put(b"a".to_vec(), b"1".to_vec())),
+        (300, Op::Put(b"b".to_vec(), b"2".to_vec())),
+        (300, Op::Delete(b"c".to_vec())),
+        (300, Op::Merge(b"d".to_vec(), b"4".to_vec())),
+        (300, Op::DeleteRange(b"e".to_vec(), b"f".to_vec())),
+      ]
+    );
+
+    // A handler unaware of column families still sees default-family
+    // records, and rejects the others.
+    let mut r = Recording { ops: vec![] };
+    assert!(matches!(b.iterate(&mut r), Err(Error::InvalidArgument(_))));
+    assert_eq!(r.ops, vec![Op::Put(b"a".to_vec(), b"1".to_vec())]);
+  }
+
+  #[test]
+  fn column_family_ids_match_decoded_records() {
+    let a = ColumnFamilyHandle::new(7, "a");
+    let b = ColumnFamilyHandle::new(9, "b");
+    let mut batch = WriteBatch::new();
+    batch.put(b"k", b"v");
+    batch.put_cf(&b, b"k", b"v");
+    batch.delete_cf(&a, b"k");
+    batch.merge_cf(&b, b"k", b"v");
+    assert_eq!(&*batch.column_family_ids().unwrap(), &[9, 7]);
+
+    // A batch rebuilt from bytes decodes its records to find them.
+    let rebuilt = WriteBatch::from_contents(batch.data().to_vec()).unwrap();
+    assert_eq!(&*rebuilt.column_family_ids().unwrap(), &[9, 7]);
+
+    let mut other = WriteBatch::new();
+    other.put_cf(&a, b"x", b"y");
+    batch.append(&other);
+    assert_eq!(&*batch.column_family_ids().unwrap(), &[9, 7]);
+    batch.clear();
+    assert!(batch.column_family_ids().unwrap().is_empty());
+  }
+
   #[test]
   fn approximate_size_grows() {
     let mut b = WriteBatch::new();
@@ -286,6 +716,31 @@ mod tests {
     assert!(r.ops.is_empty());
   }
 
+  #[test]
+  fn from_bytes_round_trips_data() {
+    let mut b = WriteBatch::new();
+    b.put(b"k", b"v");
+    b.delete(b"d");
+    b.set_sequence(42);
+    let copy = WriteBatch::from_bytes(b.data()).unwrap();
+    assert_eq!(copy.sequence(), 42);
+    assert_eq!(copy.data(), b.data());
+    let mut r = Recording { ops: vec![] };
+    copy.iterate(&mut r).unwrap();
+    assert_eq!(
+      r.ops,
+      vec![
+        Op::Put(b"k".to_vec(), b"v".to_vec()),
+        Op::Delete(b"d".to_vec())
+      ]
+    );
+
+    assert!(matches!(
+      WriteBatch::from_bytes(&b.data()[..11]),
+      Err(Error::Corruption(_))
+    ));
+  }
+
   #[test]
   fn append_merges() {
     let mut a = WriteBatch::new();
@@ -0,0 +1,687 @@
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! A [`WriteBatch`] that can be read back before it is written.
+//!
+//! [`WriteBatchWithIndex`] keeps an ordered index from each user key to the
+//! offsets of its records in the batch, so pending writes can be looked up
+//! ([`get_from_batch_and_db`](WriteBatchWithIndex::get_from_batch_and_db)) or
+//! iterated on top of the database
+//! ([`new_iterator_with_base`](WriteBatchWithIndex::new_iterator_with_base))
+//! without applying them.  The batch itself is an ordinary [`WriteBatch`] and
+//! is committed with [`Db::write`].
+//!
+//! See `include/rocksdb/utilities/write_batch_with_index.h`.
+
+use crate::comparator::{BytewiseComparator, Comparator};
+use crate::merge_operator::{self, MergeOperator};
+use crate::write_batch::Record;
+use crate::{Db, DbIter, Error, ReadOptions, WriteBatch};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Index key: a user key ordered by the batch's comparator.
+struct IndexKey {
+  key: Vec<u8>,
+  comparator: Arc<dyn Comparator>,
+}
+
+impl Ord for IndexKey {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.comparator.compare(&self.key, &other.key)
+  }
+}
+
+impl PartialOrd for IndexKey {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for IndexKey {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other).is_eq()
+  }
+}
+
+impl Eq for IndexKey {}
+
+/// A [`WriteBatch`] with an index over its keys, for reading pending writes
+/// back before committing them.
+///
+/// Range deletions are not supported: they cannot be indexed by key.
+///
+/// ```
+/// use roughdb::{Db, ReadOptions, WriteBatchWithIndex, WriteOptions};
+///
+/// let db = Db::default();
+/// db.put(b"a", b"old")?;
+/// db.put(b"b", b"kept")?;
+///
+/// let mut batch = WriteBatchWithIndex::default();
+/// batch.put(b"a", b"new");
+/// batch.delete(b"b");
+/// assert_eq!(batch.get_from_batch_and_db(&db, &ReadOptions::default(), b"a")?, b"new");
+///
+/// let mut it = batch.new_iterator_with_base(db.new_iterator(&ReadOptions::default())?);
+/// it.seek_to_first();
+/// assert_eq!((it.key(), it.value()), (&b"a"[..], &b"new"[..]));
+/// it.next();
+/// assert!(!it.valid());
+///
+/// db.write(&WriteOptions::default(), batch.into_write_batch())?;
+/// # Ok::<(), roughdb::Error>(())
+/// ```
+///
+/// See `utilities/write_batch_with_index/write_batch_with_index.cc`.
+pub struct WriteBatchWithIndex {
+  batch: WriteBatch,
+  /// Offsets in `batch` of every record for each key, oldest first.
+  index: BTreeMap<IndexKey, Vec<usize>>,
+  comparator: Arc<dyn Comparator>,
+}
+
+impl Default for WriteBatchWithIndex {
+  fn default() -> Self {
+    Self::new(Arc::new(BytewiseComparator))
+  }
+}
+
+impl WriteBatchWithIndex {
+  /// Create an empty batch whose keys are ordered by `comparator`, which
+  /// must match the comparator of the database it is read against.
+  pub fn new(comparator: Arc<dyn Comparator>) -> Self {
+    WriteBatchWithIndex {
+      batch: WriteBatch::new(),
+      index: BTreeMap::new(),
+      comparator,
+    }
+  }
+
+  pub fn put<K, V>(&mut self, key: K, value: V)
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let offset = self.batch.end_offset();
+    self.batch.put(key.as_ref(), value);
+    self.add_to_index(key.as_ref(), offset);
+  }
+
+  pub fn delete<K: AsRef<[u8]>>(&mut self, key: K) {
+    let offset = self.batch.end_offset();
+    self.batch.delete(key.as_ref());
+    self.add_to_index(key.as_ref(), offset);
+  }
+
+  pub fn merge<K, V>(&mut self, key: K, value: V)
+  where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+  {
+    let offset = self.batch.end_offset();
+    self.batch.merge(key.as_ref(), value);
+    self.add_to_index(key.as_ref(), offset);
+  }
+
+  /// Number of records in the batch.
+  pub fn count(&self) -> u32 {
+    self.batch.count()
+  }
+
+  pub fn clear(&mut self) {
+    self.batch.clear();
+    self.index.clear();
+  }
+
+  /// The underlying batch.
+  pub fn write_batch(&self) -> &WriteBatch {
+    &self.batch
+  }
+
+  /// Give up the index and return the batch, ready for [`Db::write`].
+  pub fn into_write_batch(self) -> WriteBatch {
+    self.batch
+  }
+
+  /// Read `key` as the database would see it once the batch is written:
+  /// the batch's newest write for the key wins, merge operands in the batch
+  /// are applied on top of the value in `db` (read with `opts`), and a key
+  /// the batch does not touch is read from `db`.
+  ///
+  /// Returns [`Error::NotFound`] if the key does not exist or was deleted.
+  ///
+  /// See `WriteBatchWithIndex::GetFromBatchAndDB`.
+  pub fn get_from_batch_and_db<K: AsRef<[u8]>>(
+    &self,
+    db: &Db,
+    opts: &ReadOptions,
+    key: K,
+  ) -> Result<Vec<u8>, Error> {
+    let key = key.as_ref();
+    let Some(offsets) = self.offsets(key) else {
+      return db.get_with_options(opts, key);
+    };
+    self
+      .resolve(
+        key,
+        offsets,
+        db.inner.options.merge_operator.as_ref(),
+        || match db.get_with_options(opts, key) {
+          Ok(value) => Ok(Some(value)),
+          Err(Error::NotFound) => Ok(None),
+          Err(e) => Err(e),
+        },
+      )?
+      .ok_or(Error::NotFound)
+  }
+
+  /// Return an iterator over `base` with the batch's writes applied on top:
+  /// keys written in the batch show their pending value, keys deleted in the
+  /// batch are skipped, and merge operands are applied to the base value.
+  ///
+  /// `base` must order keys with the batch's comparator.
+  ///
+  /// See `WriteBatchWithIndex::NewIteratorWithBase`.
+  pub fn new_iterator_with_base(&self, base: DbIter) -> BaseDeltaIterator<'_> {
+    BaseDeltaIterator {
+      batch: self,
+      merge_operator: base.inner.merge_operator().cloned(),
+      base,
+      delta: self
+        .index
+        .iter()
+        .map(|(k, offsets)| (k.key.as_slice(), offsets.as_slice()))
+        .collect(),
+      delta_pos: 0,
+      forward: true,
+      current: Current::None,
+      status: None,
+    }
+  }
+
+  fn add_to_index(&mut self, key: &[u8], offset: usize) {
+    self
+      .index
+      .entry(IndexKey {
+        key: key.to_vec(),
+        comparator: Arc::clone(&self.comparator),
+      })
+      .or_default()
+      .push(offset);
+  }
+
+  fn offsets(&self, key: &[u8]) -> Option<&[usize]> {
+    let probe = IndexKey {
+      key: key.to_vec(),
+      comparator: Arc::clone(&self.comparator),
+    };
+    self.index.get(&probe).map(Vec::as_slice)
+  }
+
+  /// Apply the records at `offsets` (oldest first) for `key`.  Returns the
+  /// resulting value, or `None` if the key ends up deleted.  `base` supplies
+  /// the value beneath the batch; it is only called when the newest records
+  /// are merge operands with no put or delete under them in the batch.
+  fn resolve(
+    &self,
+    key: &[u8],
+    offsets: &[usize],
+    merge_operator: Option<&Arc<dyn MergeOperator>>,
+    base: impl FnOnce() -> Result<Option<Vec<u8>>, Error>,
+  ) -> Result<Option<Vec<u8>>, Error> {
+    // Newest first, as `merge_operator::full_merge` expects.
+    let mut operands = Vec::new();
+    for &offset in offsets.iter().rev() {
+      let existing = match self.batch.record_at(offset)? {
+        Record::Put(_, value) => Some(value),
+        Record::Delete(_) => None,
+        Record::Merge(_, operand) => {
+          operands.push(operand.to_vec());
+          continue;
+        }
+        Record::DeleteRange(..) => unreachable!("range deletions are not indexed"),
+      };
+      if operands.is_empty() {
+        return Ok(existing.map(<[u8]>::to_vec));
+      }
+      return merge_operator::full_merge(merge_operator, key, existing, &operands).map(Some);
+    }
+    let existing = base()?;
+    merge_operator::full_merge(merge_operator, key, existing.as_deref(), &operands).map(Some)
+  }
+}
+
+/// Which side the [`BaseDeltaIterator`] is positioned on.
+enum Current {
+  None,
+  Base,
+  /// Index into `delta`, with the entry's resolved value.
+  Delta(usize, Vec<u8>),
+}
+
+/// Iterator over a [`DbIter`] with a [`WriteBatchWithIndex`] applied on top.
+///
+/// Obtained via [`WriteBatchWithIndex::new_iterator_with_base`].  Like
+/// [`DbIter`], it starts unpositioned and supports both directions.
+///
+/// See `utilities/write_batch_with_index/write_batch_with_index_internal.h:
+/// BaseDeltaIterator`.
+pub struct BaseDeltaIterator<'a> {
+  batch: &'a WriteBatchWithIndex,
+  merge_operator: Option<Arc<dyn MergeOperator>>,
+  base: DbIter,
+  /// The batch's keys in order, with the offsets of their records.
+  delta: Vec<(&'a [u8], &'a [usize])>,
+  /// Cursor into `delta`: the next candidate is `delta[delta_pos]` moving
+  /// forward, `delta[delta_pos - 1]` moving backward.
+  delta_pos: usize,
+  /// Direction of the last positioning call.  Moving forward, `base` rests
+  /// on the first base key ≥ the current key; backward, on the last ≤ it.
+  forward: bool,
+  current: Current,
+  /// Sticky error from a failed merge.
+  status: Option<Error>,
+}
+
+impl BaseDeltaIterator<'_> {
+  pub fn valid(&self) -> bool {
+    !matches!(self.current, Current::None)
+  }
+
+  pub fn seek_to_first(&mut self) {
+    self.base.seek_to_first();
+    self.delta_pos = 0;
+    self.forward = true;
+    self.find_next_valid();
+  }
+
+  pub fn seek_to_last(&mut self) {
+    self.base.seek_to_last();
+    self.delta_pos = self.delta.len();
+    self.forward = false;
+    self.find_prev_valid();
+  }
+
+  /// Position at the first entry with `key >= target`.
+  pub fn seek(&mut self, target: &[u8]) {
+    self.base.seek(target);
+    self.delta_pos = self.delta_partition(|k| k.is_lt(), target);
+    self.forward = true;
+    self.find_next_valid();
+  }
+
+  /// # Panics
+  /// Panics (debug) if `valid()` is false.
+  pub fn next(&mut self) {
+    debug_assert!(self.valid());
+    let key = self.key().to_vec();
+    let cmp = Arc::clone(&self.batch.comparator);
+    if !self.base.valid() && !self.forward {
+      self.base.seek_to_first();
+    }
+    while self.base.valid() && cmp.compare(self.base.key(), &key).is_le() {
+      self.base.next();
+    }
+    self.delta_pos = self.delta_partition(|k| k.is_le(), &key);
+    self.forward = true;
+    self.find_next_valid();
+  }
+
+  /// # Panics
+  /// Panics (debug) if `valid()` is false.
+  pub fn prev(&mut self) {
+    debug_assert!(self.valid());
+    let key = self.key().to_vec();
+    let cmp = Arc::clone(&self.batch.comparator);
+    if !self.base.valid() && self.forward {
+      self.base.seek_to_last();
+    }
+    while self.base.valid() && cmp.compare(self.base.key(), &key).is_ge() {
+      self.base.prev();
+    }
+    self.delta_pos = self.delta_partition(|k| k.is_lt(), &key);
+    self.forward = false;
+    self.find_prev_valid();
+  }
+
+  /// Current user key.  Only valid when `valid()` is true.
+  pub fn key(&self) -> &[u8] {
+    match &self.current {
+      Current::Base => self.base.key(),
+      Current::Delta(i, _) => self.delta[*i].0,
+      Current::None => panic!("BaseDeltaIterator::key on an invalid iterator"),
+    }
+  }
+
+  /// Current value.  Only valid when `valid()` is true.
+  pub fn value(&self) -> &[u8] {
+    match &self.current {
+      Current::Base => self.base.value(),
+      Current::Delta(_, value) => value,
+      Current::None => panic!("BaseDeltaIterator::value on an invalid iterator"),
+    }
+  }
+
+  /// Returns a sticky error from the base iterator or from a failed merge.
+  pub fn status(&self) -> Option<&Error> {
+    self.status.as_ref().or_else(|| self.base.status())
+  }
+
+  /// Number of delta keys `k` with `pred(compare(k, target))`, for a
+  /// predicate that holds on a prefix of `delta`.
+  fn delta_partition(&self, pred: impl Fn(Ordering) -> bool, target: &[u8]) -> usize {
+    let cmp = &*self.batch.comparator;
+    self
+      .delta
+      .partition_point(|(k, _)| pred(cmp.compare(k, target)))
+  }
+
+  /// Settle on the smaller of the two sides' candidates, skipping keys the
+  /// batch deletes.
+  fn find_next_valid(&mut self) {
+    loop {
+      let Some(&(key, offsets)) = self.delta.get(self.delta_pos) else {
+        self.current = if self.base.valid() {
+          Current::Base
+        } else {
+          Current::None
+        };
+        return;
+      };
+      let order = if self.base.valid() {
+        self.batch.comparator.compare(self.base.key(), key)
+      } else {
+        Ordering::Greater
+      };
+      if order.is_lt() {
+        self.current = Current::Base;
+        return;
+      }
+      let base_equal = order.is_eq();
+      match self.resolve_delta(key, offsets, base_equal) {
+        Some(value) => {
+          self.current = Current::Delta(self.delta_pos, value);
+          return;
+        }
+        None if self.status.is_some() => {
+          self.current = Current::None;
+          return;
+        }
+        None => {
+          self.delta_pos += 1;
+          if base_equal {
+            self.base.next();
+          }
+        }
+      }
+    }
+  }
+
+  /// Mirror image of [`find_next_valid`](Self::find_next_valid).
+  fn find_prev_valid(&mut self) {
+    loop {
+      let Some(&(key, offsets)) = self
+        .delta_pos
+        .checked_sub(1)
+        .and_then(|i| self.delta.get(i))
+      else {
+        self.current = if self.base.valid() {
+          Current::Base
+        } else {
+          Current::None
+        };
+        return;
+      };
+      let order = if self.base.valid() {
+        self.batch.comparator.compare(self.base.key(), key)
+      } else {
+        Ordering::Less
+      };
+      if order.is_gt() {
+        self.current = Current::Base;
+        return;
+      }
+      let base_equal = order.is_eq();
+      match self.resolve_delta(key, offsets, base_equal) {
+        Some(value) => {
+          self.current = Current::Delta(self.delta_pos - 1, value);
+          return;
+        }
+        None if self.status.is_some() => {
+          self.current = Current::None;
+          return;
+        }
+        None => {
+          self.delta_pos -= 1;
+          if base_equal {
+            self.base.prev();
+          }
+        }
+      }
+    }
+  }
+
+  /// Value of a delta entry, or `None` if the batch deletes it (or a merge
+  /// failed, which sets `status`).  `base_equal` says whether `base` is on
+  /// the same key, whose value is then the merge base.
+  fn resolve_delta(&mut self, key: &[u8], offsets: &[usize], base_equal: bool) -> Option<Vec<u8>> {
+    let base = &self.base;
+    let resolved = self
+      .batch
+      .resolve(key, offsets, self.merge_operator.as_ref(), || {
+        Ok(base_equal.then(|| base.value().to_vec()))
+      });
+    match resolved {
+      Ok(value) => value,
+      Err(e) => {
+        self.status = Some(e);
+        None
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Options, WriteOptions};
+
+  fn collect_forward(it: &mut BaseDeltaIterator) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut out = Vec::new();
+    it.seek_to_first();
+    while it.valid() {
+      out.push((it.key().to_vec(), it.value().to_vec()));
+      it.next();
+    }
+    out
+  }
+
+  fn collect_backward(it: &mut BaseDeltaIterator) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut out = Vec::new();
+    it.seek_to_last();
+    while it.valid() {
+      out.push((it.key().to_vec(), it.value().to_vec()));
+      it.prev();
+    }
+    out.reverse();
+    out
+  }
+
+  fn kv(k: &str, v: &str) -> (Vec<u8>, Vec<u8>) {
+    (k.as_bytes().to_vec(), v.as_bytes().to_vec())
+  }
+
+  struct Concat;
+
+  impl MergeOperator for Concat {
+    fn full_merge(
+      &self,
+      _key: &[u8],
+      existing: Option<&[u8]>,
+      operands: &[&[u8]],
+    ) -> Option<Vec<u8>> {
+      let mut out = existing.unwrap_or_default().to_vec();
+      for op in operands {
+        out.extend_from_slice(op);
+      }
+      Some(out)
+    }
+
+    fn name(&self) -> &str {
+      "Concat"
+    }
+  }
+
+  fn concat_db(dir: &std::path::Path) -> Db {
+    let opts = Options {
+      create_if_missing: true,
+      merge_operator: Some(Arc::new(Concat)),
+      ..Options::default()
+    };
+    Db::open(dir, opts).unwrap()
+  }
+
+  #[test]
+  fn get_from_batch_and_db_prefers_the_batch() {
+    let db = Db::default();
+    db.put(b"a", b"db").unwrap();
+    db.put(b"b", b"db").unwrap();
+    let mut batch = WriteBatchWithIndex::default();
+    batch.put(b"a", b"one");
+    batch.put(b"a", b"two");
+    batch.delete(b"b");
+    batch.put(b"c", b"new");
+    let opts = ReadOptions::default();
+    assert_eq!(
+      batch.get_from_batch_and_db(&db, &opts, b"a").unwrap(),
+      b"two"
+    );
+    assert!(batch
+      .get_from_batch_and_db(&db, &opts, b"b")
+      .unwrap_err()
+      .is_not_found());
+    assert_eq!(
+      batch.get_from_batch_and_db(&db, &opts, b"c").unwrap(),
+      b"new"
+    );
+    assert!(batch
+      .get_from_batch_and_db(&db, &opts, b"d")
+      .unwrap_err()
+      .is_not_found());
+    assert_eq!(batch.count(), 4);
+  }
+
+  #[test]
+  fn merges_apply_to_batch_or_db_base() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = concat_db(dir.path());
+    db.put(b"a", b"db").unwrap();
+    let mut batch = WriteBatchWithIndex::default();
+    batch.merge(b"a", b"+1");
+    batch.delete(b"b");
+    batch.merge(b"b", b"+2");
+    batch.merge(b"b", b"+3");
+    let opts = ReadOptions::default();
+    assert_eq!(
+      batch.get_from_batch_and_db(&db, &opts, b"a").unwrap(),
+      b"db+1"
+    );
+    assert_eq!(
+      batch.get_from_batch_and_db(&db, &opts, b"b").unwrap(),
+      b"+2+3"
+    );
+
+    let mut it = batch.new_iterator_with_base(db.new_iterator(&opts).unwrap());
+    assert_eq!(
+      collect_forward(&mut it),
+      vec![kv("a", "db+1"), kv("b", "+2+3")]
+    );
+  }
+
+  #[test]
+  fn iterator_overlays_batch_on_db() {
+    let db = Db::default();
+    for k in ["a", "c", "e", "g"] {
+      db.put(k, "db").unwrap();
+    }
+    let mut batch = WriteBatchWithIndex::default();
+    batch.put(b"b", b"batch");
+    batch.put(b"c", b"batch");
+    batch.delete(b"e");
+    batch.delete(b"f");
+    batch.put(b"h", b"batch");
+    let expected = vec![
+      kv("a", "db"),
+      kv("b", "batch"),
+      kv("c", "batch"),
+      kv("g", "db"),
+      kv("h", "batch"),
+    ];
+    let base = || db.new_iterator(&ReadOptions::default()).unwrap();
+    assert_eq!(
+      collect_forward(&mut batch.new_iterator_with_base(base())),
+      expected
+    );
+    assert_eq!(
+      collect_backward(&mut batch.new_iterator_with_base(base())),
+      expected
+    );
+
+    let mut it = batch.new_iterator_with_base(base());
+    it.seek(b"d");
+    assert_eq!(it.key(), b"g");
+    // Switch direction across a deleted key and back.
+    it.prev();
+    assert_eq!(it.key(), b"c");
+    it.prev();
+    assert_eq!(it.key(), b"b");
+    it.next();
+    assert_eq!(it.key(), b"c");
+    it.next();
+    assert_eq!(it.key(), b"g");
+    it.next();
+    assert_eq!(it.key(), b"h");
+    it.next();
+    assert!(!it.valid());
+    assert!(it.status().is_none());
+  }
+
+  #[test]
+  fn iterator_over_empty_sides() {
+    let db = Db::default();
+    let mut batch = WriteBatchWithIndex::default();
+    let base = || db.new_iterator(&ReadOptions::default()).unwrap();
+    assert!(collect_forward(&mut batch.new_iterator_with_base(base())).is_empty());
+    batch.delete(b"x");
+    assert!(collect_backward(&mut batch.new_iterator_with_base(base())).is_empty());
+    batch.put(b"y", b"1");
+    assert_eq!(
+      collect_backward(&mut batch.new_iterator_with_base(base())),
+      vec![kv("y", "1")]
+    );
+  }
+
+  #[test]
+  fn commits_through_db_write() {
+    let db = Db::default();
+    db.put(b"gone", b"x").unwrap();
+    let mut batch = WriteBatchWithIndex::default();
+    batch.put(b"k", b"v");
+    batch.delete(b"gone");
+    db.write(&WriteOptions::default(), batch.into_write_batch())
+      .unwrap();
+    assert_eq!(db.get(b"k").unwrap(), b"v");
+    assert!(db.get(b"gone").unwrap_err().is_not_found());
+  }
+}